@@ -0,0 +1,260 @@
+use std::time::{Duration, Instant};
+
+use leisql::{Connection, Database};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Number of orders at scale factor 1, each of which has 1 to 7 line items.
+const ORDERS_PER_SCALE: usize = 10_000;
+/// Number of rows inserted by each `INSERT` statement.
+const INSERT_BATCH_SIZE: usize = 1_000;
+/// Dates are days since the first day of the data.
+const MAX_ORDER_DATE: i64 = 2_400;
+
+const MARKET_SEGMENTS: &[&str] = &[
+    "AUTOMOBILE",
+    "BUILDING",
+    "FURNITURE",
+    "HOUSEHOLD",
+    "MACHINERY",
+];
+const ORDER_PRIORITIES: &[&str] = &["1-URGENT", "2-HIGH", "3-MEDIUM", "4-NOT SPECIFIED", "5-LOW"];
+
+const SCHEMA: &str = "
+CREATE TABLE customer (
+    c_custkey INT PRIMARY KEY,
+    c_name TEXT,
+    c_nationkey INT,
+    c_mktsegment TEXT
+);
+CREATE TABLE orders (
+    o_orderkey INT PRIMARY KEY,
+    o_custkey INT,
+    o_orderstatus TEXT,
+    o_totalprice INT,
+    o_orderdate INT,
+    o_orderpriority TEXT
+);
+CREATE TABLE lineitem (
+    l_orderkey INT,
+    l_linenumber INT,
+    l_partkey INT,
+    l_quantity INT,
+    l_extendedprice INT,
+    l_discount INT,
+    l_returnflag TEXT,
+    l_linestatus TEXT,
+    l_shipdate INT,
+    l_commitdate INT,
+    l_receiptdate INT
+);
+CREATE INDEX lineitem_orderkey ON lineitem (l_orderkey);
+";
+
+/// Queries modeled after TPC-H, prices are in cents and there is no
+/// arithmetic other than `+` and `-`.
+const QUERIES: &[(&str, &str)] = &[
+    (
+        "Q1 pricing summary",
+        "SELECT l_returnflag, l_linestatus, sum(l_quantity), sum(l_extendedprice),
+            sum(l_discount), count(*)
+        FROM lineitem
+        WHERE l_shipdate <= 2300
+        GROUP BY l_returnflag, l_linestatus
+        ORDER BY l_returnflag, l_linestatus",
+    ),
+    (
+        "Q3 shipping priority",
+        "SELECT l_orderkey, sum(l_extendedprice) AS revenue, o_orderdate
+        FROM customer, orders, lineitem
+        WHERE c_mktsegment = 'BUILDING' AND c_custkey = o_custkey
+            AND l_orderkey = o_orderkey AND o_orderdate < 1200 AND l_shipdate > 1200
+        GROUP BY l_orderkey, o_orderdate
+        ORDER BY revenue DESC, o_orderdate",
+    ),
+    (
+        "Q4 order priority",
+        "SELECT o_orderpriority, count(*)
+        FROM orders
+        WHERE o_orderdate >= 1000 AND o_orderdate < 1090
+            AND EXISTS (
+                SELECT 1 FROM lineitem
+                WHERE l_orderkey = o_orderkey AND l_commitdate < l_receiptdate
+            )
+        GROUP BY o_orderpriority
+        ORDER BY o_orderpriority",
+    ),
+    (
+        "Q6 forecasting revenue",
+        "SELECT sum(l_extendedprice)
+        FROM lineitem
+        WHERE l_shipdate >= 365 AND l_shipdate < 730
+            AND l_discount >= 5 AND l_discount <= 7 AND l_quantity < 24",
+    ),
+    (
+        "Q10 returned items",
+        "SELECT c_custkey, c_name, sum(l_extendedprice) AS revenue, c_nationkey
+        FROM customer, orders, lineitem
+        WHERE c_custkey = o_custkey AND l_orderkey = o_orderkey
+            AND o_orderdate >= 600 AND o_orderdate < 690 AND l_returnflag = 'R'
+        GROUP BY c_custkey, c_name, c_nationkey
+        ORDER BY revenue DESC",
+    ),
+    (
+        "Q18 large volume customer",
+        "SELECT c_name, o_orderkey, o_totalprice, sum(l_quantity)
+        FROM customer, orders, lineitem
+        WHERE o_orderkey IN (
+                SELECT l_orderkey FROM lineitem
+                GROUP BY l_orderkey HAVING sum(l_quantity) > 250
+            )
+            AND c_custkey = o_custkey AND o_orderkey = l_orderkey
+        GROUP BY c_name, o_orderkey, o_totalprice
+        ORDER BY o_totalprice DESC, o_orderkey",
+    ),
+    (
+        "order lookup",
+        "SELECT * FROM orders, lineitem
+        WHERE o_orderkey = 4242 AND l_orderkey = o_orderkey",
+    ),
+];
+
+/// Benchmark of the executor with TPC-H style data and queries, run with
+/// `cargo bench --bench tpch`. The data is scaled by `LEISQL_BENCH_SCALE`,
+/// and each query is run `LEISQL_BENCH_ITERATIONS` times.
+fn main() {
+    let scale = env_or("LEISQL_BENCH_SCALE", 1.0);
+    let iterations = env_or("LEISQL_BENCH_ITERATIONS", 5);
+    let orders = ((ORDERS_PER_SCALE as f64 * scale) as usize).max(1);
+
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(SCHEMA, &[]).unwrap();
+
+    let start = Instant::now();
+    let rows = generate(&mut conn, orders);
+    println!(
+        "loaded {} rows at scale {} in {:.2?}\n",
+        rows,
+        scale,
+        start.elapsed()
+    );
+
+    println!(
+        "{:<28} {:>8} {:>12} {:>12} {:>12}",
+        "query", "rows", "min", "mean", "max"
+    );
+    for (name, sql) in QUERIES {
+        let mut timings = Vec::with_capacity(iterations);
+        let mut rows = 0;
+        for _ in 0..iterations {
+            let start = Instant::now();
+            rows = conn.query(sql, &[]).unwrap().data.len();
+            timings.push(start.elapsed());
+        }
+
+        let min = timings.iter().min().unwrap();
+        let max = timings.iter().max().unwrap();
+        let mean = timings.iter().sum::<Duration>() / timings.len() as u32;
+        println!(
+            "{:<28} {:>8} {:>12.2?} {:>12.2?} {:>12.2?}",
+            name, rows, min, mean, max
+        );
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Generate the data with a fixed seed, so the results are comparable
+/// across runs. Returns the number of rows inserted.
+fn generate(conn: &mut Connection, orders: usize) -> usize {
+    let mut rng = StdRng::seed_from_u64(42);
+    let customers = (orders / 10).max(1);
+
+    let customer_rows = (1..=customers)
+        .map(|custkey| {
+            format!(
+                "({}, 'Customer#{:09}', {}, '{}')",
+                custkey,
+                custkey,
+                rng.gen_range(0..25),
+                MARKET_SEGMENTS[rng.gen_range(0..MARKET_SEGMENTS.len())]
+            )
+        })
+        .collect::<Vec<_>>();
+    insert(conn, "customer", &customer_rows);
+
+    let mut order_rows = Vec::with_capacity(orders);
+    let mut lineitem_rows = vec![];
+    for orderkey in 1..=orders {
+        let orderdate = rng.gen_range(0..MAX_ORDER_DATE);
+        let mut totalprice = 0;
+        let mut shipped = 0;
+        let lines = rng.gen_range(1..=7);
+        for linenumber in 1..=lines {
+            let quantity = rng.gen_range(1..=50);
+            let extendedprice = quantity * rng.gen_range(90_000..=110_000);
+            let shipdate = orderdate + rng.gen_range(1..=121);
+            let commitdate = orderdate + rng.gen_range(30..=90);
+            let receiptdate = shipdate + rng.gen_range(1..=30);
+            let returnflag = if receiptdate > MAX_ORDER_DATE - 500 {
+                "N"
+            } else if rng.gen_bool(0.5) {
+                "R"
+            } else {
+                "A"
+            };
+            let linestatus = if shipdate > MAX_ORDER_DATE - 500 {
+                "O"
+            } else {
+                shipped += 1;
+                "F"
+            };
+            totalprice += extendedprice;
+            lineitem_rows.push(format!(
+                "({}, {}, {}, {}, {}, {}, '{}', '{}', {}, {}, {})",
+                orderkey,
+                linenumber,
+                rng.gen_range(1..=orders * 20),
+                quantity,
+                extendedprice,
+                rng.gen_range(0..=10),
+                returnflag,
+                linestatus,
+                shipdate,
+                commitdate,
+                receiptdate
+            ));
+        }
+
+        let orderstatus = match shipped {
+            0 => "O",
+            shipped if shipped == lines => "F",
+            _ => "P",
+        };
+        order_rows.push(format!(
+            "({}, {}, '{}', {}, {}, '{}')",
+            orderkey,
+            rng.gen_range(1..=customers),
+            orderstatus,
+            totalprice,
+            orderdate,
+            ORDER_PRIORITIES[rng.gen_range(0..ORDER_PRIORITIES.len())]
+        ));
+    }
+    insert(conn, "orders", &order_rows);
+    insert(conn, "lineitem", &lineitem_rows);
+
+    customer_rows.len() + order_rows.len() + lineitem_rows.len()
+}
+
+fn insert(conn: &mut Connection, table: &str, rows: &[String]) {
+    for batch in rows.chunks(INSERT_BATCH_SIZE) {
+        let sql = format!("INSERT INTO {} VALUES {}", table, batch.join(", "));
+        conn.execute(&sql, &[]).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+use std::{fmt::Display, process::exit, str::FromStr, sync::Arc, time::Duration};
+
+use leisql::{
+    catalog::user::UserCatalog,
+    server::{
+        auth::{AuthMethod, MakeAuthenticator},
+        metrics::serve_metrics,
+        tls::load_tls_acceptor,
+        MakePostgresHandler,
+    },
+    storage::wal::SyncPolicy,
+    util::{metrics::METRICS, LogFilter, LogFormat, Logger},
+    Database,
+};
+use log::{error, info};
+use pgwire::{api::MakeHandler, tokio::process_socket};
+use tokio::net::TcpListener;
+
+#[tokio::main]
+pub async fn main() {
+    // Logging is configured with environment variables, e.g.
+    // `LEISQL_LOG=info,leisql::storage=debug LEISQL_LOG_FORMAT=json`, records
+    // are written to `LEISQL_LOG_FILE` instead of stdout if it's set, which
+    // is rotated once it exceeds `LEISQL_LOG_MAX_SIZE` bytes(100MB by
+    // default), keeping `LEISQL_LOG_MAX_FILES` rotated files(5 by default)
+    let filter = parse_env::<LogFilter>("LEISQL_LOG").unwrap_or_else(|| "info".parse().unwrap());
+    let format = parse_env("LEISQL_LOG_FORMAT").unwrap_or(LogFormat::Text);
+    let mut logger = Logger::new(filter).with_format(format);
+    if let Ok(path) = std::env::var("LEISQL_LOG_FILE") {
+        let max_size = parse_env("LEISQL_LOG_MAX_SIZE").unwrap_or(100 << 20);
+        let max_files = parse_env("LEISQL_LOG_MAX_FILES").unwrap_or(5);
+        logger = logger
+            .with_file(path.as_ref(), max_size, max_files)
+            .unwrap_or_else(|e| exit_with_error(format!("cannot open log file {}: {}", path, e)));
+    }
+    logger.init().unwrap();
+
+    // Data is persisted with WAL in the data directory, otherwise it's only kept in memory
+    let mut database = match std::env::var("LEISQL_DATA_DIR") {
+        Ok(data_dir) => {
+            let policy = parse_env("LEISQL_WAL_SYNC").unwrap_or(SyncPolicy::Always);
+            Database::open_with_sync_policy(data_dir, policy)
+                .unwrap_or_else(|e| exit_with_error(format!("cannot open database: {}", e.message)))
+        }
+        Err(_) => Database::in_memory(),
+    };
+
+    // Statements running longer than the threshold are logged, e.g.
+    // `LEISQL_LOG_MIN_DURATION_STATEMENT=100ms`, sessions can change it with `SET`
+    if let Ok(threshold) = std::env::var("LEISQL_LOG_MIN_DURATION_STATEMENT") {
+        database
+            .set_default("log_min_duration_statement", &threshold)
+            .unwrap_or_else(|e| {
+                exit_with_error(format!(
+                    "invalid LEISQL_LOG_MIN_DURATION_STATEMENT {:?}: {}",
+                    threshold, e.message
+                ))
+            });
+    }
+
+    // `COPY` can only read and write the files in the directory, e.g.
+    // `LEISQL_COPY_DIR=/var/lib/leisql/copy`, it's disallowed if it's not set
+    if let Ok(copy_dir) = std::env::var("LEISQL_COPY_DIR") {
+        database.set_copy_directory(copy_dir);
+    }
+
+    // Functions can only be loaded from the WebAssembly modules in the directory,
+    // e.g. `LEISQL_FUNCTION_DIR=/var/lib/leisql/functions`, it's disallowed if it's not set
+    if let Ok(function_dir) = std::env::var("LEISQL_FUNCTION_DIR") {
+        database.set_function_directory(function_dir);
+    }
+
+    // Tables with many dead tuples are vacuumed in background every interval if
+    // it's given, e.g. `LEISQL_AUTOVACUUM_NAPTIME=60` in seconds
+    let naptime = parse_env::<u64>("LEISQL_AUTOVACUUM_NAPTIME").map(|secs| {
+        if secs == 0 {
+            exit_with_error("invalid LEISQL_AUTOVACUUM_NAPTIME \"0\": must be positive");
+        }
+        Duration::from_secs(secs)
+    });
+    if let Some(naptime) = naptime {
+        let database = database.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(naptime);
+            loop {
+                interval.tick().await;
+                let database = database.clone();
+                if let Ok(Err(e)) = tokio::task::spawn_blocking(move || database.autovacuum()).await
+                {
+                    error!("Autovacuum failed: {}", e);
+                }
+            }
+        });
+    }
+
+    // Authentication is configured with environment variables, e.g.
+    // `LEISQL_AUTH_METHOD=md5 LEISQL_USERS=alice:secret,bob:secret`
+    let auth_method = parse_env("LEISQL_AUTH_METHOD").unwrap_or(AuthMethod::Trust);
+    let users = UserCatalog::parse(&std::env::var("LEISQL_USERS").unwrap_or_default())
+        .unwrap_or_else(|e| exit_with_error(format!("invalid LEISQL_USERS: {}", e)));
+    let mut authenticator = MakeAuthenticator::new(auth_method, users);
+
+    // TLS is enabled if both certificate and private key are provided
+    let tls_acceptor = match (
+        std::env::var("LEISQL_TLS_CERT"),
+        std::env::var("LEISQL_TLS_KEY"),
+    ) {
+        (Ok(cert_path), Ok(key_path)) => {
+            let tls_acceptor = load_tls_acceptor(&cert_path, &key_path)
+                .unwrap_or_else(|e| exit_with_error(format!("cannot load TLS config: {}", e)));
+            let certificate = std::fs::read(&cert_path).unwrap_or_else(|e| {
+                exit_with_error(format!("cannot read certificate {}: {}", cert_path, e))
+            });
+            authenticator = authenticator.with_certificate(certificate);
+            info!("TLS is enabled with certificate {}", cert_path);
+            Some(Arc::new(tls_acceptor))
+        }
+        _ => None,
+    };
+
+    // Each connection has its own session, sharing the catalog and storage
+    let processor = Arc::new(MakePostgresHandler::new(database, authenticator));
+
+    // Metrics are served over HTTP at `/metrics` if the address is given,
+    // e.g. `LEISQL_METRICS_ADDR=127.0.0.1:9187`
+    if let Ok(metrics_addr) = std::env::var("LEISQL_METRICS_ADDR") {
+        let metrics_listener = TcpListener::bind(&metrics_addr).await.unwrap_or_else(|e| {
+            exit_with_error(format!("cannot listen to {}: {}", metrics_addr, e))
+        });
+        info!("Serving metrics at http://{}/metrics", metrics_addr);
+        tokio::spawn(serve_metrics(metrics_listener));
+    }
+
+    let server_addr =
+        std::env::var("LEISQL_LISTEN_ADDR").unwrap_or_else(|_| "127.0.0.1:5432".to_string());
+    let listener = TcpListener::bind(&server_addr)
+        .await
+        .unwrap_or_else(|e| exit_with_error(format!("cannot listen to {}: {}", server_addr, e)));
+    info!("Listening to {}", server_addr);
+    loop {
+        let incoming_socket = listener.accept().await.unwrap();
+        let processor_ref = processor.make();
+        // The connection is closed once the session is terminated by
+        // `pg_terminate_backend`, even if it's idle
+        let interrupts = processor_ref
+            .session
+            .lock()
+            .unwrap()
+            .context()
+            .interrupts
+            .clone();
+        let process = process_socket(
+            incoming_socket.0,
+            tls_acceptor.clone(),
+            processor_ref.clone(),
+            processor_ref.clone(),
+            processor_ref,
+        );
+        tokio::spawn(async move {
+            METRICS.connection_opened();
+            let result = tokio::select! {
+                result = process => result,
+                _ = interrupts.terminated() => Ok(()),
+            };
+            METRICS.connection_closed();
+            result
+        });
+    }
+}
+
+/// Parse the configuration from the environment variable, `None` if it's not
+/// set. The server fails to start if it's invalid.
+fn parse_env<T>(name: &str) -> Option<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = std::env::var(name).ok()?;
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(e) => exit_with_error(format!("invalid {} {:?}: {}", name, value, e)),
+    }
+}
+
+/// Report the error preventing the server from starting, and exit with a
+/// non-zero status instead of panicking.
+fn exit_with_error(message: impl Display) -> ! {
+    eprintln!("leisql-server: {}", message);
+    exit(1);
+}
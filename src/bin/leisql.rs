@@ -0,0 +1,219 @@
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process::exit,
+    sync::atomic::Ordering,
+};
+
+use leisql::{
+    cli::{Backend, Client, OutputFormat, Repl},
+    Database,
+};
+
+const USAGE: &str = "\
+Usage: leisql [OPTION]... [DATA_DIR]
+
+The data is persisted in DATA_DIR, otherwise it's only kept in memory.
+With --connect, the commands are run by the server instead, the password is
+read from PGPASSWORD if it's required.
+
+Options:
+      --connect=HOST:PORT  connect to a running server
+  -U, --user=USER    user name to connect as, defaults to $USER
+  -d, --dbname=NAME  database to connect to
+  -c, --command=SQL  run the command and exit, can be repeated
+  -f, --file=FILE    run the commands in the file and exit, can be repeated
+  -q, --quiet        don't print the tags of commands returning no rows
+      --csv          print the rows in CSV format
+  -h, --help         show this help
+
+Commands are read from stdin if neither -c nor -f is given. Exit status is
+0 on success, 1 if a command failed (except in an interactive session), 2 on
+invalid usage, if the database, the server or a file can't be opened, or if
+the input can't be read or the output can't be written.";
+
+/// A script to run non-interactively.
+enum Script {
+    Command(String),
+    File(PathBuf),
+}
+
+struct Args {
+    data_dir: Option<String>,
+    connect: Option<String>,
+    user: Option<String>,
+    dbname: Option<String>,
+    scripts: Vec<Script>,
+    quiet: bool,
+    csv: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut result = Args {
+        data_dir: None,
+        connect: None,
+        user: None,
+        dbname: None,
+        scripts: vec![],
+        quiet: false,
+        csv: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let (option, value) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                (option.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |option: &str| {
+            value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("option {} requires an argument", option))
+        };
+        match option.as_str() {
+            "-c" | "--command" => result.scripts.push(Script::Command(value(&option)?)),
+            "-f" | "--file" => result
+                .scripts
+                .push(Script::File(PathBuf::from(value(&option)?))),
+            "--connect" => result.connect = Some(value(&option)?),
+            "-U" | "--user" => result.user = Some(value(&option)?),
+            "-d" | "--dbname" => result.dbname = Some(value(&option)?),
+            "-q" | "--quiet" => result.quiet = true,
+            "--csv" => result.csv = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            _ if option.starts_with('-') => return Err(format!("invalid option {}", option)),
+            _ if result.data_dir.is_none() => result.data_dir = Some(arg),
+            _ => return Err(format!("extra argument {}", arg)),
+        }
+    }
+    if result.connect.is_some() && result.data_dir.is_some() {
+        return Err("DATA_DIR can't be used with --connect".to_string());
+    }
+    Ok(result)
+}
+
+/// Shell of an embedded database or a server, it's interactive unless
+/// commands are given with `-c` or `-f`. Statements are saved to `LEISQL_HISTORY`,
+/// `~/.leisql_history` by default.
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("leisql: {}\n\n{}", e, USAGE);
+        exit(2);
+    });
+    let backend = match (&args.connect, &args.data_dir) {
+        (Some(addr), _) => {
+            let user = args
+                .user
+                .clone()
+                .or_else(|| std::env::var("USER").ok())
+                .unwrap_or_else(|| "postgres".to_string());
+            let password = std::env::var("PGPASSWORD").ok();
+            let client = Client::connect(addr, &user, args.dbname.as_deref(), password.as_deref())
+                .unwrap_or_else(|e| {
+                    eprintln!("leisql: {}", e.message);
+                    exit(2);
+                });
+            Backend::Remote(client)
+        }
+        (None, data_dir) => {
+            let database = match data_dir {
+                Some(data_dir) => Database::open(data_dir).unwrap_or_else(|e| {
+                    eprintln!("leisql: cannot open database: {}", e.message);
+                    exit(2);
+                }),
+                None => Database::in_memory(),
+            };
+            let conn = match &args.dbname {
+                Some(dbname) => database.connect_to(dbname).unwrap_or_else(|e| {
+                    eprintln!("leisql: {}", e.message);
+                    exit(2);
+                }),
+                None => database.connect(),
+            };
+            Backend::Embedded(conn)
+        }
+    };
+
+    let mut repl = Repl::new(backend);
+    repl.printer.quiet = args.quiet;
+    if args.csv {
+        repl.printer.format = OutputFormat::Csv;
+    }
+    let mut stdout = io::stdout();
+
+    if !args.scripts.is_empty() {
+        for script in args.scripts.iter() {
+            let text = match script {
+                Script::Command(command) => command.clone(),
+                Script::File(path) => std::fs::read_to_string(path).unwrap_or_else(|e| {
+                    eprintln!("leisql: {}: {}", path.display(), e);
+                    exit(2);
+                }),
+            };
+            match repl.run_script(&text, &mut stdout) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => exit_on_output_error(e, repl.failed()),
+            }
+        }
+        exit(repl.failed() as i32);
+    }
+
+    let interactive = io::stdin().is_terminal();
+    let history_path = std::env::var_os("LEISQL_HISTORY")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".leisql_history"))
+        });
+    if let (true, Some(path)) = (interactive, history_path) {
+        if let Err(e) = repl.set_history(path.clone()) {
+            eprintln!(
+                "leisql: history is disabled, cannot open {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    repl = repl.with_prompts(interactive);
+
+    // Ctrl-C cancels the current input instead of quitting, it's handled by
+    // the line editor while reading, and by the flag while executing
+    let interrupted = repl.interrupted();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+    std::thread::spawn(move || {
+        runtime.block_on(async {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                interrupted.store(true, Ordering::SeqCst);
+            }
+        })
+    });
+
+    let result = if interactive {
+        repl.run_interactive(&mut stdout)
+    } else {
+        repl.run(io::stdin().lock(), &mut stdout)
+    };
+    let failed = !interactive && repl.failed();
+    if let Err(e) = result {
+        exit_on_output_error(e, failed);
+    }
+    exit(failed as i32);
+}
+
+/// Exit on the I/O error of the shell. A closed output, e.g. piped to `head`,
+/// is not an error, so the exit status is the same as if it's finished.
+fn exit_on_output_error(e: io::Error, failed: bool) -> ! {
+    if e.kind() == io::ErrorKind::BrokenPipe {
+        exit(failed as i32);
+    }
+    eprintln!("leisql: {}", e);
+    exit(2);
+}
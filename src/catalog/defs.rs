@@ -1,20 +1,334 @@
-use crate::core::Type;
+use std::sync::Arc;
+
+use crate::core::{Datum, IntWidth, Tuple, Type};
 
 #[derive(Clone, Debug)]
 pub struct ColumnDefinition {
     pub name: String,
     pub data_type: Type,
     pub null: bool,
+    /// Expression of the default value in SQL text, evaluated on insertion.
+    pub default: Option<String>,
+    /// Declared width of integer column, `None` for the integer columns of
+    /// tables not created by DDL, which are 64-bit.
+    pub int_width: Option<IntWidth>,
 }
 
 #[derive(Clone, Debug)]
 pub struct TableDefinition {
     pub name: String,
     pub columns: Vec<ColumnDefinition>,
+    pub indexes: Vec<IndexDefinition>,
+    /// Name of the unique index backing the primary key.
+    pub primary_key: Option<String>,
+    pub foreign_keys: Vec<ForeignKeyDefinition>,
+    /// Statistics collected by `ANALYZE` and maintained by DML since then,
+    /// `None` if never analyzed.
+    pub statistics: Option<TableStatistics>,
+    /// Version of catalog when the table was created or altered, the queries
+    /// bound with an older catalog can't scan it.
+    pub version: u64,
+}
+
+impl TableDefinition {
+    /// The unique index with exactly the given key columns.
+    pub fn find_unique_index(&self, columns: &[usize]) -> Option<&IndexDefinition> {
+        self.indexes
+            .iter()
+            .find(|index| index.unique && index.columns == columns)
+    }
+}
+
+/// Statistics of a table, used to estimate the cardinality of plans.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableStatistics {
+    pub row_count: u64,
+    /// Statistics of each column, in the order of columns.
+    pub columns: Vec<ColumnStatistics>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnStatistics {
+    /// Fraction of the NULL values.
+    pub null_fraction: f64,
+    /// Number of distinct non-NULL values.
+    pub distinct_count: u64,
+    /// The smallest and largest non-NULL values, NULL if there isn't any.
+    pub min: Datum,
+    pub max: Datum,
+    /// Bounds of the equi-height histogram of the non-NULL values, each of
+    /// the buckets between adjacent bounds holds the same number of values.
+    /// Empty if there isn't any value.
+    pub histogram: Vec<Datum>,
+}
+
+impl TableStatistics {
+    /// Collect the statistics of tuples with `width` columns.
+    pub fn collect(width: usize, tuples: impl Iterator<Item = Tuple>) -> Self {
+        let mut row_count = 0;
+        let mut null_counts = vec![0u64; width];
+        let mut column_values = vec![vec![]; width];
+        for tuple in tuples {
+            row_count += 1;
+            let mut values = tuple.values.into_iter();
+            for (column, column_values) in column_values.iter_mut().enumerate() {
+                match values.next().unwrap_or(Datum::Null) {
+                    Datum::Null => null_counts[column] += 1,
+                    value => column_values.push(value),
+                }
+            }
+        }
+
+        let columns = null_counts
+            .into_iter()
+            .zip(column_values)
+            .map(|(null_count, mut values)| {
+                values.sort();
+                let histogram = histogram_bounds(&values);
+                values.dedup();
+                ColumnStatistics {
+                    null_fraction: if row_count == 0 {
+                        0.0
+                    } else {
+                        null_count as f64 / row_count as f64
+                    },
+                    distinct_count: values.len() as u64,
+                    min: values.first().cloned().unwrap_or(Datum::Null),
+                    max: values.last().cloned().unwrap_or(Datum::Null),
+                    histogram,
+                }
+            })
+            .collect();
+
+        Self { row_count, columns }
+    }
+
+    /// Account for the inserted tuples. The value beyond the range of column
+    /// is counted as a new distinct value, and stretches the outermost bucket
+    /// of histogram.
+    pub fn record_insert<'a>(&mut self, tuples: impl Iterator<Item = &'a Tuple>) {
+        let old_rows = self.row_count;
+        let mut null_counts = vec![0u64; self.columns.len()];
+        for tuple in tuples {
+            self.row_count += 1;
+            for (column, statistics) in self.columns.iter_mut().enumerate() {
+                match tuple.values.get(column).unwrap_or(&Datum::Null) {
+                    Datum::Null => null_counts[column] += 1,
+                    value => statistics.record_value(value),
+                }
+            }
+        }
+
+        for (statistics, null_count) in self.columns.iter_mut().zip(null_counts) {
+            if self.row_count > 0 {
+                let nulls = statistics.null_fraction * old_rows as f64 + null_count as f64;
+                statistics.null_fraction = nulls / self.row_count as f64;
+            }
+        }
+    }
+
+    /// Account for the updated tuples, the values they replace are unknown,
+    /// so only the new values are recorded.
+    pub fn record_update<'a>(&mut self, tuples: impl Iterator<Item = &'a Tuple>) {
+        for tuple in tuples {
+            for (column, statistics) in self.columns.iter_mut().enumerate() {
+                match tuple.values.get(column).unwrap_or(&Datum::Null) {
+                    Datum::Null => {}
+                    value => statistics.record_value(value),
+                }
+            }
+        }
+        self.bound_distinct_counts();
+    }
+
+    /// Account for the deleted tuples, whose values are unknown, so the
+    /// distinct values are only bounded by the remaining rows.
+    pub fn record_delete(&mut self, count: usize) {
+        self.row_count = self.row_count.saturating_sub(count as u64);
+        self.bound_distinct_counts();
+    }
+
+    fn bound_distinct_counts(&mut self) {
+        for statistics in self.columns.iter_mut() {
+            let values = ((1.0 - statistics.null_fraction) * self.row_count as f64).ceil() as u64;
+            statistics.distinct_count = statistics.distinct_count.min(values);
+        }
+    }
+}
+
+impl ColumnStatistics {
+    /// Widen the range with the non-NULL value.
+    fn record_value(&mut self, value: &Datum) {
+        if self.min.is_null() {
+            self.min = value.clone();
+            self.max = value.clone();
+        } else if value < &self.min {
+            self.min = value.clone();
+            if let Some(bound) = self.histogram.first_mut() {
+                *bound = value.clone();
+            }
+        } else if value > &self.max {
+            self.max = value.clone();
+            if let Some(bound) = self.histogram.last_mut() {
+                *bound = value.clone();
+            }
+        } else {
+            return;
+        }
+        self.distinct_count += 1;
+    }
+
+    /// Estimated fraction of the non-NULL values less than `value`, by the
+    /// position of it in the histogram. Within a bucket, the values are assumed
+    /// to be uniformly distributed if they are numbers.
+    pub fn fraction_below(&self, value: &Datum) -> Option<f64> {
+        let bounds = &self.histogram;
+        let buckets = bounds.len().checked_sub(1)?;
+        if value.typ() != bounds[0].typ() {
+            return None;
+        }
+        if value <= &bounds[0] {
+            return Some(0.0);
+        }
+        if value > &bounds[buckets] {
+            return Some(1.0);
+        }
+
+        // The bucket where `bounds[bucket] < value <= bounds[bucket + 1]`
+        let bucket = bounds.partition_point(|bound| bound < value) - 1;
+        let (low, high) = (&bounds[bucket], &bounds[bucket + 1]);
+        let within = match (low, high, value) {
+            (Datum::Int(low), Datum::Int(high), Datum::Int(value)) => {
+                (value - low) as f64 / (high - low) as f64
+            }
+            (Datum::Float(low), Datum::Float(high), Datum::Float(value)) if high > low => {
+                (value - low) / (high - low)
+            }
+            _ => 0.5,
+        };
+
+        Some((bucket as f64 + within) / buckets as f64)
+    }
+}
+
+/// Maximum number of the buckets in a histogram.
+const HISTOGRAM_BUCKETS: usize = 100;
+
+/// Bounds of the equi-height histogram of the sorted values.
+fn histogram_bounds(values: &[Datum]) -> Vec<Datum> {
+    if values.is_empty() {
+        return vec![];
+    }
+    let buckets = HISTOGRAM_BUCKETS.min(values.len().saturating_sub(1)).max(1);
+    (0..=buckets)
+        .map(|i| values[i * (values.len() - 1) / buckets].clone())
+        .collect()
+}
+
+/// A B-tree index on the columns of a table.
+#[derive(Clone, Debug)]
+pub struct IndexDefinition {
+    pub name: String,
+    /// Indices of the key columns in the table.
+    pub columns: Vec<usize>,
+    pub unique: bool,
+}
+
+impl IndexDefinition {
+    /// Key of the tuple in this index. While recovering, the tuples written before
+    /// the columns are changed may be shorter, the indexes are rebuilt after that.
+    pub fn key(&self, tuple: &Tuple) -> Vec<Datum> {
+        self.columns
+            .iter()
+            .map(|column| tuple.values.get(*column).cloned().unwrap_or(Datum::Null))
+            .collect()
+    }
+}
+
+/// A foreign key referencing the unique key of a table.
+#[derive(Clone, Debug)]
+pub struct ForeignKeyDefinition {
+    pub name: String,
+    /// Indices of the referencing columns in the table.
+    pub columns: Vec<usize>,
+    pub referred_schema: String,
+    pub referred_table: String,
+    /// Indices of the referenced columns in the referred table.
+    pub referred_columns: Vec<usize>,
+    pub on_delete: ReferentialAction,
+}
+
+impl ForeignKeyDefinition {
+    pub fn refers_to(&self, schema_name: &str, table_name: &str) -> bool {
+        self.referred_schema == schema_name && self.referred_table == table_name
+    }
+}
+
+/// Action on the referencing tuples when the referenced tuple is deleted.
+/// `RESTRICT` is the same as `NO ACTION`, since constraints are never deferred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferentialAction {
+    NoAction,
+    Cascade,
+}
+
+/// A sequence generating integers, e.g. for `SERIAL` columns.
+#[derive(Clone, Debug)]
+pub struct SequenceDefinition {
+    pub name: String,
+    pub start: i64,
+    pub increment: i64,
+    pub min_value: i64,
+    pub max_value: i64,
+    /// Wrap around when the limit is reached, instead of failing.
+    pub cycle: bool,
+    /// The column (table_name, column_name) owning it, the sequence is dropped
+    /// together with the table.
+    pub owned_by: Option<(String, String)>,
+}
+
+impl SequenceDefinition {
+    /// The value after `value`, `None` if the limit is reached without cycle.
+    pub fn next_value(&self, value: i64) -> Option<i64> {
+        match value.checked_add(self.increment) {
+            Some(next) if (self.min_value..=self.max_value).contains(&next) => Some(next),
+            _ if !self.cycle => None,
+            _ if self.increment > 0 => Some(self.min_value),
+            _ => Some(self.max_value),
+        }
+    }
+}
+
+/// A user-defined function, the SQL ones are inlined into the queries
+/// calling them.
+#[derive(Clone, Debug)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub arg_types: Vec<Type>,
+    pub return_type: Type,
+    pub body: FunctionBody,
+}
+
+#[derive(Clone, Debug)]
+pub enum FunctionBody {
+    /// The expression computing the result in SQL text, the arguments are
+    /// referenced by the parameters `$1`, `$2`, ...
+    Sql(String),
+    /// WebAssembly module exporting the function, which is kept in catalog
+    /// so the function doesn't depend on the file it's loaded from.
+    Wasm(Arc<[u8]>),
+}
+
+#[derive(Clone, Debug)]
+pub struct DatabaseDefinition {
+    pub name: String,
+    pub schemas: Vec<SchemaDefinition>,
 }
 
 #[derive(Clone, Debug)]
 pub struct SchemaDefinition {
     pub name: String,
     pub tables: Vec<TableDefinition>,
+    pub sequences: Vec<SequenceDefinition>,
+    pub functions: Vec<FunctionDefinition>,
 }
@@ -1,101 +1,268 @@
-use self::defs::{SchemaDefinition, TableDefinition};
-use crate::core::{ErrorKind, SQLError};
+use std::sync::Arc;
+
+use self::defs::{
+    DatabaseDefinition, FunctionBody, FunctionDefinition, IndexDefinition, SchemaDefinition,
+    SequenceDefinition, TableDefinition, TableStatistics,
+};
+use crate::{
+    core::{sqlstate, ErrorKind, SQLError},
+    sql::expression::{
+        aggregate::AggregateFunctionRegistry, function::ScalarFunctionRegistry, wasm::WasmFunction,
+    },
+};
 
 pub mod defs;
+pub mod pg_catalog;
+pub mod user;
+
+/// Database the connections are in if they don't choose one, which can't be
+/// dropped.
+pub const DEFAULT_DATABASE: &str = "leisql";
 
 #[derive(Debug, Clone, Default)]
 pub struct Catalog {
-    pub schemas: Vec<SchemaDefinition>,
+    /// Databases of the server, the objects in them are named by
+    /// (database, schema, name), so the databases never share any object.
+    pub databases: Vec<DatabaseDefinition>,
+    /// Scalar and aggregate functions, the new ones are registered into a copy
+    /// of the registry, so the running queries keep their snapshot.
+    pub scalar_functions: Arc<ScalarFunctionRegistry>,
+    pub aggregate_functions: Arc<AggregateFunctionRegistry>,
+    /// Bumped by every DDL, the statements prepared with an older version are
+    /// bound again.
+    pub version: u64,
 }
 
 impl Catalog {
     pub fn new() -> Self {
-        let mut empty = Self::default();
-        empty.create_schema("default").unwrap();
+        let mut empty = Self {
+            scalar_functions: ScalarFunctionRegistry::builtin().clone(),
+            aggregate_functions: Arc::new(AggregateFunctionRegistry::with_builtins()),
+            ..Self::default()
+        };
+        empty.create_database(DEFAULT_DATABASE).unwrap();
         empty
     }
 
-    pub fn create_schema(&mut self, schema_name: &str) -> Result<(), SQLError> {
-        if self.exists_schema(schema_name)? {
+    /// Create the database with an empty `default` schema.
+    pub fn create_database(&mut self, database_name: &str) -> Result<(), SQLError> {
+        if self.exists_database(database_name) {
             return Err(SQLError::new(
                 ErrorKind::CatalogError,
-                "schema already exists",
-            ));
+                format!("database \"{}\" already exists", database_name),
+            )
+            .with_code(sqlstate::DUPLICATE_DATABASE));
+        }
+
+        self.databases.push(DatabaseDefinition {
+            name: database_name.to_string(),
+            schemas: vec![],
+        });
+        self.create_schema(database_name, "default")
+    }
+
+    /// Drop the database with all the objects in it.
+    pub fn drop_database(&mut self, database_name: &str) -> Result<(), SQLError> {
+        if !self.exists_database(database_name) {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!("database \"{}\" does not exist", database_name),
+            )
+            .with_code(sqlstate::INVALID_CATALOG_NAME));
+        }
+
+        for schema_name in self.list_schemas(database_name) {
+            self.drop_schema(database_name, &schema_name)?;
         }
+        self.databases
+            .retain(|database| database.name != database_name);
+
+        Ok(())
+    }
+
+    pub fn exists_database(&self, database_name: &str) -> bool {
+        self.databases
+            .iter()
+            .any(|database| database.name == database_name)
+    }
 
-        self.schemas.push(SchemaDefinition {
+    /// The view of the database, which is used to plan the queries of a
+    /// session.
+    pub fn database<'a>(&'a self, database_name: &'a str) -> DatabaseCatalog<'a> {
+        DatabaseCatalog {
+            catalog: self,
+            database_name,
+        }
+    }
+
+    /// The schemas of database, none if it doesn't exist.
+    pub fn schemas(&self, database_name: &str) -> impl Iterator<Item = &SchemaDefinition> {
+        self.databases
+            .iter()
+            .find(|database| database.name == database_name)
+            .into_iter()
+            .flat_map(|database| database.schemas.iter())
+    }
+
+    fn schemas_mut(&mut self, database_name: &str) -> impl Iterator<Item = &mut SchemaDefinition> {
+        self.databases
+            .iter_mut()
+            .find(|database| database.name == database_name)
+            .into_iter()
+            .flat_map(|database| database.schemas.iter_mut())
+    }
+
+    pub fn create_schema(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+    ) -> Result<(), SQLError> {
+        if self.exists_schema(database_name, schema_name)? {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "schema already exists")
+                    .with_code(sqlstate::DUPLICATE_SCHEMA),
+            );
+        }
+
+        let database = self
+            .databases
+            .iter_mut()
+            .find(|database| database.name == database_name)
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("database \"{}\" does not exist", database_name),
+                )
+                .with_code(sqlstate::INVALID_CATALOG_NAME)
+            })?;
+        database.schemas.push(SchemaDefinition {
             name: schema_name.to_string(),
             tables: vec![],
+            sequences: vec![],
+            functions: vec![],
         });
 
         Ok(())
     }
 
-    pub fn drop_schema(&mut self, schema_name: &str) -> Result<(), SQLError> {
-        if !self.exists_schema(schema_name)? {
-            return Err(SQLError::new(
-                ErrorKind::CatalogError,
-                "schema does not exist",
-            ));
+    pub fn drop_schema(&mut self, database_name: &str, schema_name: &str) -> Result<(), SQLError> {
+        if !self.exists_schema(database_name, schema_name)? {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "schema does not exist")
+                    .with_code(sqlstate::INVALID_SCHEMA_NAME),
+            );
         }
 
-        self.schemas.retain(|schema| schema.name != schema_name);
+        let functions = self
+            .schemas(database_name)
+            .filter(|schema| schema.name == schema_name)
+            .flat_map(|schema| schema.functions.iter())
+            .map(|function| function.name.clone())
+            .collect::<Vec<_>>();
+        for function_name in functions.iter() {
+            self.unregister_wasm_function(database_name, schema_name, function_name);
+        }
+        if let Some(database) = self
+            .databases
+            .iter_mut()
+            .find(|database| database.name == database_name)
+        {
+            database.schemas.retain(|schema| schema.name != schema_name);
+        }
 
         Ok(())
     }
 
-    pub fn exists_schema(&self, schema_name: &str) -> Result<bool, SQLError> {
-        Ok(self.schemas.iter().any(|schema| schema.name == schema_name))
+    pub fn exists_schema(&self, database_name: &str, schema_name: &str) -> Result<bool, SQLError> {
+        Ok(self
+            .schemas(database_name)
+            .any(|schema| schema.name == schema_name))
     }
 
-    #[allow(dead_code)]
-    pub fn list_schemas(&self) -> Vec<String> {
-        self.schemas
-            .iter()
+    pub fn list_schemas(&self, database_name: &str) -> Vec<String> {
+        self.schemas(database_name)
             .map(|schema| schema.name.clone())
             .collect()
     }
 
     pub fn create_table(
         &mut self,
+        database_name: &str,
         schema_name: &str,
         table_def: &TableDefinition,
     ) -> Result<(), SQLError> {
-        if !self.exists_schema(schema_name)? {
-            return Err(SQLError::new(
-                ErrorKind::CatalogError,
-                "schema does not exist",
-            ));
+        if !self.exists_schema(database_name, schema_name)? {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "schema does not exist")
+                    .with_code(sqlstate::INVALID_SCHEMA_NAME),
+            );
         }
 
         if self
-            .find_table_by_name(schema_name, &table_def.name)?
+            .find_table_by_name(database_name, schema_name, &table_def.name)?
             .is_some()
+            || self
+                .find_sequence_by_name(database_name, schema_name, &table_def.name)?
+                .is_some()
         {
-            return Err(SQLError::new(
-                ErrorKind::CatalogError,
-                "table already exists",
-            ));
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "table already exists")
+                    .with_code(sqlstate::DUPLICATE_TABLE),
+            );
+        }
+
+        for index_def in table_def.indexes.iter() {
+            if self.exists_relation(database_name, schema_name, &index_def.name)? {
+                return Err(SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("relation \"{}\" already exists", index_def.name),
+                )
+                .with_code(sqlstate::DUPLICATE_TABLE));
+            }
+        }
+
+        for foreign_key in table_def.foreign_keys.iter() {
+            if !foreign_key.refers_to(schema_name, &table_def.name)
+                && self
+                    .find_table_by_name(
+                        database_name,
+                        &foreign_key.referred_schema,
+                        &foreign_key.referred_table,
+                    )?
+                    .is_none()
+            {
+                return Err(SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("relation \"{}\" does not exist", foreign_key.referred_table),
+                )
+                .with_code(sqlstate::UNDEFINED_TABLE));
+            }
         }
 
-        if let Some(schema) = self.schemas.iter_mut().find(|v| v.name == schema_name) {
+        if let Some(schema) = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+        {
             schema.tables.push(table_def.clone());
         }
 
         Ok(())
     }
 
-    pub fn list_tables(&self, schema_name: &str) -> Result<Vec<String>, SQLError> {
-        if !self.exists_schema(schema_name)? {
-            return Err(SQLError::new(
-                ErrorKind::CatalogError,
-                "schema does not exist",
-            ));
+    pub fn list_tables(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+    ) -> Result<Vec<String>, SQLError> {
+        if !self.exists_schema(database_name, schema_name)? {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "schema does not exist")
+                    .with_code(sqlstate::INVALID_SCHEMA_NAME),
+            );
         }
 
         Ok(self
-            .schemas
-            .iter()
+            .schemas(database_name)
             .find(|v| v.name == schema_name)
             .map(|schema| {
                 schema
@@ -110,12 +277,13 @@ impl Catalog {
     /// Find a table by qualified names
     pub fn find_table_by_name(
         &self,
+        database_name: &str,
         schema_name: &str,
         table_name: &str,
     ) -> Result<Option<TableDefinition>, SQLError> {
         let mut candidates = vec![];
         // Schema name is not specified
-        for schema in &self.schemas {
+        for schema in self.schemas(database_name) {
             if schema.name == schema_name {
                 if let Some(table) = schema.tables.iter().find(|table| table.name == table_name) {
                     candidates.push(table.clone());
@@ -140,29 +308,591 @@ impl Catalog {
         }
     }
 
-    pub fn drop_table(&mut self, schema_name: &str, table_name: &str) -> Result<(), SQLError> {
-        if !self.exists_schema(schema_name)? {
+    pub fn drop_table(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<(), SQLError> {
+        if !self.exists_schema(database_name, schema_name)? {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "schema does not exist")
+                    .with_code(sqlstate::INVALID_SCHEMA_NAME),
+            );
+        }
+
+        if let Some((referencing_table, foreign_key)) = self
+            .referencing_tables(database_name, schema_name, table_name)
+            .into_iter()
+            .filter(|(schema, table)| schema != schema_name || table.name != table_name)
+            .find_map(|(_, table)| {
+                let foreign_key = table
+                    .foreign_keys
+                    .iter()
+                    .find(|foreign_key| foreign_key.refers_to(schema_name, table_name))?
+                    .clone();
+                Some((table.name, foreign_key))
+            })
+        {
             return Err(SQLError::new(
                 ErrorKind::CatalogError,
-                "schema does not exist",
-            ));
+                format!(
+                    "cannot drop table {} because other objects depend on it",
+                    table_name
+                ),
+            )
+            .with_code(sqlstate::DEPENDENT_OBJECTS_STILL_EXIST)
+            .with_detail(format!(
+                "constraint {} on table {} depends on table {}",
+                foreign_key.name, referencing_table, table_name
+            )));
         }
 
         let schema = self
-            .schemas
-            .iter_mut()
+            .schemas_mut(database_name)
             .find(|v| v.name == schema_name)
             .unwrap();
 
         if !schema.tables.iter().any(|table| table.name == table_name) {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "table does not exist")
+                    .with_code(sqlstate::UNDEFINED_TABLE),
+            );
+        }
+
+        schema.tables.retain(|table| table.name != table_name);
+
+        Ok(())
+    }
+
+    /// Replace the definition of table, e.g. when its columns are changed.
+    pub fn alter_table(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        table_def: &TableDefinition,
+    ) -> Result<(), SQLError> {
+        let table = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+            .and_then(|schema| schema.tables.iter_mut().find(|v| v.name == table_def.name))
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "table does not exist")
+                    .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        // The statistics are stale since the columns are changed
+        *table = TableDefinition {
+            statistics: None,
+            ..table_def.clone()
+        };
+
+        Ok(())
+    }
+
+    /// Bump the version of catalog, and of the table whose columns are changed.
+    pub fn bump_version(&mut self, changed_table: Option<(&str, &str, &str)>) {
+        self.version += 1;
+        let Some((database_name, schema_name, table_name)) = changed_table else {
+            return;
+        };
+        let version = self.version;
+        let table = self
+            .schemas_mut(database_name)
+            .find(|schema| schema.name == schema_name)
+            .and_then(|schema| schema.tables.iter_mut().find(|v| v.name == table_name));
+        if let Some(table) = table {
+            table.version = version;
+        }
+    }
+
+    /// Check that the table scanned by the statement bound with catalog of
+    /// `version` hasn't been dropped or altered since then. The catalog must
+    /// be held while scanning the table, so it can't be changed meanwhile.
+    pub fn check_table_version(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        version: u64,
+    ) -> Result<(), SQLError> {
+        let table_version = self
+            .schemas(database_name)
+            .find(|schema| schema.name == schema_name)
+            .and_then(|schema| schema.tables.iter().find(|v| v.name == table_name))
+            .map(|table| table.version);
+        match table_version {
+            Some(table_version) if table_version <= version => Ok(()),
+            _ => Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!(
+                    "catalog changed: table \"{}\" was dropped or altered while the statement was running",
+                    table_name
+                ),
+            )
+            .with_code(sqlstate::OBJECT_NOT_IN_PREREQUISITE_STATE)),
+        }
+    }
+
+    /// Change the statistics of table if it has been analyzed, used to account
+    /// for DML. Nothing happens if the table has been dropped.
+    pub fn update_statistics(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        f: impl FnOnce(&mut TableStatistics),
+    ) {
+        let statistics = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+            .and_then(|schema| schema.tables.iter_mut().find(|v| v.name == table_name))
+            .and_then(|table| table.statistics.as_mut());
+        if let Some(statistics) = statistics {
+            f(statistics);
+        }
+    }
+
+    /// Replace the statistics of table, collected by `ANALYZE`.
+    pub fn set_statistics(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        statistics: &TableStatistics,
+    ) -> Result<(), SQLError> {
+        let table = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+            .and_then(|schema| schema.tables.iter_mut().find(|v| v.name == table_name))
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "table does not exist")
+                    .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        table.statistics = Some(statistics.clone());
+
+        Ok(())
+    }
+
+    pub fn create_index(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        index_def: &IndexDefinition,
+    ) -> Result<(), SQLError> {
+        if self.exists_relation(database_name, schema_name, &index_def.name)? {
             return Err(SQLError::new(
                 ErrorKind::CatalogError,
-                "table does not exist",
-            ));
+                format!("relation \"{}\" already exists", index_def.name),
+            )
+            .with_code(sqlstate::DUPLICATE_TABLE));
         }
 
-        schema.tables.retain(|table| table.name != table_name);
+        let table = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+            .and_then(|schema| schema.tables.iter_mut().find(|v| v.name == table_name))
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "table does not exist")
+                    .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        table.indexes.push(index_def.clone());
 
         Ok(())
     }
+
+    /// Find an index by qualified name, returns the name of its table and the definition.
+    pub fn find_index_by_name(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        index_name: &str,
+    ) -> Result<Option<(String, IndexDefinition)>, SQLError> {
+        if !self.exists_schema(database_name, schema_name)? {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "schema does not exist")
+                    .with_code(sqlstate::INVALID_SCHEMA_NAME),
+            );
+        }
+
+        Ok(self
+            .schemas(database_name)
+            .filter(|schema| schema.name == schema_name)
+            .flat_map(|schema| schema.tables.iter())
+            .find_map(|table| {
+                table
+                    .indexes
+                    .iter()
+                    .find(|index| index.name == index_name)
+                    .map(|index| (table.name.clone(), index.clone()))
+            }))
+    }
+
+    /// Tables with foreign keys referencing the table, including itself.
+    pub fn referencing_tables(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Vec<(String, TableDefinition)> {
+        self.schemas(database_name)
+            .flat_map(|schema| {
+                schema
+                    .tables
+                    .iter()
+                    .filter(|table| {
+                        table
+                            .foreign_keys
+                            .iter()
+                            .any(|foreign_key| foreign_key.refers_to(schema_name, table_name))
+                    })
+                    .map(|table| (schema.name.clone(), table.clone()))
+            })
+            .collect()
+    }
+
+    pub fn drop_index(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+        index_name: &str,
+    ) -> Result<(), SQLError> {
+        // Foreign keys are enforced with the unique index of the referenced columns
+        let referred = self.find_table_by_name(database_name, schema_name, table_name)?;
+        let dependent = referred.as_ref().and_then(|referred| {
+            self.referencing_tables(database_name, schema_name, table_name)
+                .into_iter()
+                .find_map(|(_, table)| {
+                    let foreign_key = table.foreign_keys.iter().find(|foreign_key| {
+                        foreign_key.refers_to(schema_name, table_name)
+                            && referred
+                                .find_unique_index(&foreign_key.referred_columns)
+                                .map_or(false, |index| index.name == index_name)
+                    })?;
+                    Some((foreign_key.name.clone(), table.name.clone()))
+                })
+        });
+        if let Some((constraint, table)) = dependent {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!(
+                    "cannot drop index {} because constraint {} on table {} requires it",
+                    index_name, constraint, table
+                ),
+            )
+            .with_code(sqlstate::DEPENDENT_OBJECTS_STILL_EXIST));
+        }
+
+        let table = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+            .and_then(|schema| schema.tables.iter_mut().find(|v| v.name == table_name))
+            .filter(|table| table.indexes.iter().any(|index| index.name == index_name))
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("index \"{}\" does not exist", index_name),
+                )
+                .with_code(sqlstate::UNDEFINED_OBJECT)
+            })?;
+        if table.primary_key.as_deref() == Some(index_name) {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!(
+                    "cannot drop index {} because it is the primary key of table {}",
+                    index_name, table_name
+                ),
+            )
+            .with_code(sqlstate::DEPENDENT_OBJECTS_STILL_EXIST));
+        }
+        table.indexes.retain(|index| index.name != index_name);
+
+        Ok(())
+    }
+
+    pub fn create_sequence(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        sequence_def: &SequenceDefinition,
+    ) -> Result<(), SQLError> {
+        if self.exists_relation(database_name, schema_name, &sequence_def.name)? {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!("relation \"{}\" already exists", sequence_def.name),
+            )
+            .with_code(sqlstate::DUPLICATE_TABLE));
+        }
+
+        if let Some(schema) = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+        {
+            schema.sequences.push(sequence_def.clone());
+        }
+
+        Ok(())
+    }
+
+    pub fn find_sequence_by_name(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        sequence_name: &str,
+    ) -> Result<Option<SequenceDefinition>, SQLError> {
+        if !self.exists_schema(database_name, schema_name)? {
+            return Err(
+                SQLError::new(ErrorKind::CatalogError, "schema does not exist")
+                    .with_code(sqlstate::INVALID_SCHEMA_NAME),
+            );
+        }
+
+        Ok(self
+            .schemas(database_name)
+            .filter(|schema| schema.name == schema_name)
+            .flat_map(|schema| schema.sequences.iter())
+            .find(|sequence| sequence.name == sequence_name)
+            .cloned())
+    }
+
+    /// The sequences owned by the columns of table.
+    pub fn owned_sequences(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Vec<SequenceDefinition> {
+        self.schemas(database_name)
+            .filter(|schema| schema.name == schema_name)
+            .flat_map(|schema| schema.sequences.iter())
+            .filter(
+                |sequence| matches!(&sequence.owned_by, Some((table, _)) if table == table_name),
+            )
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_sequence_owner(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        sequence_name: &str,
+        owned_by: &Option<(String, String)>,
+    ) -> Result<(), SQLError> {
+        let sequence = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+            .and_then(|schema| {
+                schema
+                    .sequences
+                    .iter_mut()
+                    .find(|sequence| sequence.name == sequence_name)
+            })
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("sequence \"{}\" does not exist", sequence_name),
+                )
+                .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        sequence.owned_by = owned_by.clone();
+
+        Ok(())
+    }
+
+    pub fn drop_sequence(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        sequence_name: &str,
+    ) -> Result<(), SQLError> {
+        let sequence = self
+            .find_sequence_by_name(database_name, schema_name, sequence_name)?
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("sequence \"{}\" does not exist", sequence_name),
+                )
+                .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+
+        // The owned sequence is only dropped with its column
+        if let Some((table_name, column_name)) = &sequence.owned_by {
+            let owner = self.find_table_by_name(database_name, schema_name, table_name)?;
+            if owner.map_or(false, |table| {
+                table
+                    .columns
+                    .iter()
+                    .any(|column| &column.name == column_name)
+            }) {
+                return Err(SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!(
+                        "cannot drop sequence {} because other objects depend on it",
+                        sequence_name
+                    ),
+                )
+                .with_code(sqlstate::DEPENDENT_OBJECTS_STILL_EXIST)
+                .with_detail(format!(
+                    "default value for column {} of table {} depends on sequence {}",
+                    column_name, table_name, sequence_name
+                )));
+            }
+        }
+
+        if let Some(schema) = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+        {
+            schema
+                .sequences
+                .retain(|sequence| sequence.name != sequence_name);
+        }
+
+        Ok(())
+    }
+
+    /// Create the function, or replace the existing one of the same name.
+    pub fn create_function(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        function_def: &FunctionDefinition,
+    ) -> Result<(), SQLError> {
+        let schema = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "schema does not exist")
+                    .with_code(sqlstate::INVALID_SCHEMA_NAME)
+            })?;
+        // WebAssembly functions are called through the registry
+        let wasm_function = match &function_def.body {
+            FunctionBody::Sql(_) => None,
+            FunctionBody::Wasm(module) => Some(WasmFunction::compile(
+                &function_def.name,
+                module,
+                &function_def.arg_types,
+                &function_def.return_type,
+            )?),
+        };
+        schema
+            .functions
+            .retain(|function| function.name != function_def.name);
+        schema.functions.push(function_def.clone());
+
+        self.unregister_wasm_function(database_name, schema_name, &function_def.name);
+        if let Some(wasm_function) = wasm_function {
+            wasm_function.register(
+                Arc::make_mut(&mut self.scalar_functions),
+                &wasm_function_name(database_name, schema_name, &function_def.name),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn unregister_wasm_function(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        function_name: &str,
+    ) {
+        let name = wasm_function_name(database_name, schema_name, function_name);
+        if self.scalar_functions.contains(&name) {
+            Arc::make_mut(&mut self.scalar_functions).unregister(&name);
+        }
+    }
+
+    pub fn find_function_by_name(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        function_name: &str,
+    ) -> Option<&FunctionDefinition> {
+        self.schemas(database_name)
+            .filter(|schema| schema.name == schema_name)
+            .flat_map(|schema| schema.functions.iter())
+            .find(|function| function.name == function_name)
+    }
+
+    pub fn has_functions(&self) -> bool {
+        self.databases
+            .iter()
+            .flat_map(|database| database.schemas.iter())
+            .any(|schema| !schema.functions.is_empty())
+    }
+
+    pub fn drop_function(
+        &mut self,
+        database_name: &str,
+        schema_name: &str,
+        function_name: &str,
+    ) -> Result<(), SQLError> {
+        if self
+            .find_function_by_name(database_name, schema_name, function_name)
+            .is_none()
+        {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!("function {} does not exist", function_name),
+            )
+            .with_code(sqlstate::UNDEFINED_FUNCTION));
+        }
+
+        if let Some(schema) = self
+            .schemas_mut(database_name)
+            .find(|v| v.name == schema_name)
+        {
+            schema
+                .functions
+                .retain(|function| function.name != function_name);
+        }
+        self.unregister_wasm_function(database_name, schema_name, function_name);
+
+        Ok(())
+    }
+
+    /// Tables, indexes and sequences share the namespace of relations in a schema.
+    pub fn exists_relation(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        name: &str,
+    ) -> Result<bool, SQLError> {
+        Ok(self
+            .find_table_by_name(database_name, schema_name, name)?
+            .is_some()
+            || self
+                .find_index_by_name(database_name, schema_name, name)?
+                .is_some()
+            || self
+                .find_sequence_by_name(database_name, schema_name, name)?
+                .is_some())
+    }
+}
+
+/// The objects of a database in the catalog.
+#[derive(Clone, Copy)]
+pub struct DatabaseCatalog<'a> {
+    catalog: &'a Catalog,
+    database_name: &'a str,
+}
+
+impl DatabaseCatalog<'_> {
+    pub fn find_table_by_name(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<Option<TableDefinition>, SQLError> {
+        self.catalog
+            .find_table_by_name(self.database_name, schema_name, table_name)
+    }
+}
+
+/// Name of the WebAssembly function in the registry of scalar functions,
+/// which is qualified by database and schema and called by the inlined calls.
+pub fn wasm_function_name(database_name: &str, schema_name: &str, function_name: &str) -> String {
+    format!("{}.{}.{}", database_name, schema_name, function_name).to_lowercase()
 }
@@ -0,0 +1,1230 @@
+use std::collections::HashMap;
+
+use super::{
+    defs::{
+        ColumnDefinition, FunctionBody, ReferentialAction, SchemaDefinition, TableDefinition,
+        TableStatistics,
+    },
+    Catalog, DatabaseCatalog, DEFAULT_DATABASE,
+};
+use crate::{
+    core::{Datum, IntWidth, Type},
+    sql::session::{activity::SessionActivity, to_pg_column_type, to_pg_type},
+    util::format_timestamp,
+};
+
+/// The schema of system relations, which are generated from the catalog so
+/// that clients like psql can inspect the database as in Postgres.
+pub const SYSTEM_SCHEMA: &str = "pg_catalog";
+
+/// The superuser owning all the objects.
+pub const OWNER_OID: i64 = 10;
+pub const OWNER_NAME: &str = "postgres";
+
+const SYSTEM_SCHEMA_OID: i64 = 11;
+/// OID of the default database, the others have normal OIDs.
+const DATABASE_OID: i64 = 1;
+const HEAP_AM_OID: i64 = 2;
+const BTREE_AM_OID: i64 = 403;
+/// OIDs of the system relations start from it, and OIDs of the other objects
+/// start from `FIRST_NORMAL_OID`, both are the same as Postgres.
+const FIRST_SYSTEM_OID: i64 = 12000;
+const FIRST_NORMAL_OID: i64 = 16384;
+const UTF8_ENCODING: i64 = 6;
+/// The built-in collations of Postgres, the columns are not collatable so
+/// they are only listed for the clients.
+const COLLATIONS: [(i64, &str, &str); 3] =
+    [(100, "default", "d"), (950, "C", "c"), (951, "POSIX", "c")];
+
+/// The data types listed in `pg_type` with the widths of integer types, their
+/// arrays are listed as well.
+const TYPES: [(Type, Option<IntWidth>); 9] = [
+    (Type::Int, Some(IntWidth::Int2)),
+    (Type::Int, Some(IntWidth::Int4)),
+    (Type::Int, None),
+    (Type::Float, None),
+    (Type::String, None),
+    (Type::Boolean, None),
+    (Type::Uuid, None),
+    (Type::Json, None),
+    (Type::Bytea, None),
+];
+
+lazy_static! {
+    static ref SYSTEM_TABLES: Vec<TableDefinition> = {
+        let oid = || Type::Int;
+        let int = || Type::Int;
+        let float = || Type::Float;
+        let text = || Type::String;
+        let bool = || Type::Boolean;
+        let array = |typ: Type| Type::Array(Box::new(typ));
+
+        vec![
+            system_table(
+                "pg_namespace",
+                vec![
+                    ("oid", oid()),
+                    ("nspname", text()),
+                    ("nspowner", oid()),
+                    ("nspacl", array(text())),
+                ],
+            ),
+            system_table(
+                "pg_class",
+                vec![
+                    ("oid", oid()),
+                    ("relname", text()),
+                    ("relnamespace", oid()),
+                    ("reltype", oid()),
+                    ("reloftype", oid()),
+                    ("relowner", oid()),
+                    ("relam", oid()),
+                    ("relfilenode", oid()),
+                    ("reltablespace", oid()),
+                    ("relpages", int()),
+                    ("reltuples", float()),
+                    ("reltoastrelid", oid()),
+                    ("relhasindex", bool()),
+                    ("relisshared", bool()),
+                    ("relpersistence", text()),
+                    ("relkind", text()),
+                    ("relnatts", int()),
+                    ("relchecks", int()),
+                    ("relhasrules", bool()),
+                    ("relhastriggers", bool()),
+                    ("relhassubclass", bool()),
+                    ("relrowsecurity", bool()),
+                    ("relforcerowsecurity", bool()),
+                    ("relispopulated", bool()),
+                    ("relreplident", text()),
+                    ("relispartition", bool()),
+                    ("relacl", array(text())),
+                    ("reloptions", array(text())),
+                    ("relpartbound", text()),
+                ],
+            ),
+            system_table(
+                "pg_attribute",
+                vec![
+                    ("attrelid", oid()),
+                    ("attname", text()),
+                    ("atttypid", oid()),
+                    ("attlen", int()),
+                    ("attnum", int()),
+                    ("atttypmod", int()),
+                    ("attndims", int()),
+                    ("attnotnull", bool()),
+                    ("atthasdef", bool()),
+                    ("attidentity", text()),
+                    ("attgenerated", text()),
+                    ("attisdropped", bool()),
+                    ("attislocal", bool()),
+                    ("attcollation", oid()),
+                ],
+            ),
+            system_table(
+                "pg_attrdef",
+                vec![
+                    ("oid", oid()),
+                    ("adrelid", oid()),
+                    ("adnum", int()),
+                    ("adbin", text()),
+                ],
+            ),
+            system_table(
+                "pg_index",
+                vec![
+                    ("indexrelid", oid()),
+                    ("indrelid", oid()),
+                    ("indnatts", int()),
+                    ("indnkeyatts", int()),
+                    ("indisunique", bool()),
+                    ("indisprimary", bool()),
+                    ("indisexclusion", bool()),
+                    ("indimmediate", bool()),
+                    ("indisclustered", bool()),
+                    ("indisvalid", bool()),
+                    ("indisready", bool()),
+                    ("indislive", bool()),
+                    ("indisreplident", bool()),
+                    ("indkey", array(int())),
+                    ("indexprs", text()),
+                    ("indpred", text()),
+                ],
+            ),
+            system_table(
+                "pg_constraint",
+                vec![
+                    ("oid", oid()),
+                    ("conname", text()),
+                    ("connamespace", oid()),
+                    ("contype", text()),
+                    ("condeferrable", bool()),
+                    ("condeferred", bool()),
+                    ("convalidated", bool()),
+                    ("conrelid", oid()),
+                    ("contypid", oid()),
+                    ("conindid", oid()),
+                    ("conparentid", oid()),
+                    ("confrelid", oid()),
+                    ("confupdtype", text()),
+                    ("confdeltype", text()),
+                    ("confmatchtype", text()),
+                    ("conislocal", bool()),
+                    ("conkey", array(int())),
+                    ("confkey", array(int())),
+                ],
+            ),
+            system_table(
+                "pg_sequence",
+                vec![
+                    ("seqrelid", oid()),
+                    ("seqtypid", oid()),
+                    ("seqstart", int()),
+                    ("seqincrement", int()),
+                    ("seqmax", int()),
+                    ("seqmin", int()),
+                    ("seqcache", int()),
+                    ("seqcycle", bool()),
+                ],
+            ),
+            system_table(
+                "pg_type",
+                vec![
+                    ("oid", oid()),
+                    ("typname", text()),
+                    ("typnamespace", oid()),
+                    ("typowner", oid()),
+                    ("typlen", int()),
+                    ("typbyval", bool()),
+                    ("typtype", text()),
+                    ("typcategory", text()),
+                    ("typisdefined", bool()),
+                    ("typrelid", oid()),
+                    ("typelem", oid()),
+                    ("typarray", oid()),
+                    ("typnotnull", bool()),
+                    ("typbasetype", oid()),
+                    ("typtypmod", int()),
+                    ("typndims", int()),
+                    ("typcollation", oid()),
+                ],
+            ),
+            system_table(
+                "pg_am",
+                vec![("oid", oid()), ("amname", text()), ("amtype", text())],
+            ),
+            system_table(
+                "pg_collation",
+                vec![
+                    ("oid", oid()),
+                    ("collname", text()),
+                    ("collnamespace", oid()),
+                    ("collowner", oid()),
+                    ("collprovider", text()),
+                    ("collencoding", int()),
+                ],
+            ),
+            system_table(
+                "pg_database",
+                vec![
+                    ("oid", oid()),
+                    ("datname", text()),
+                    ("datdba", oid()),
+                    ("encoding", int()),
+                    ("datlocprovider", text()),
+                    ("datistemplate", bool()),
+                    ("datallowconn", bool()),
+                    ("datconnlimit", int()),
+                    ("datcollate", text()),
+                    ("datctype", text()),
+                    ("daticulocale", text()),
+                    ("datacl", array(text())),
+                ],
+            ),
+            system_table(
+                "pg_roles",
+                vec![
+                    ("oid", oid()),
+                    ("rolname", text()),
+                    ("rolsuper", bool()),
+                    ("rolinherit", bool()),
+                    ("rolcreaterole", bool()),
+                    ("rolcreatedb", bool()),
+                    ("rolcanlogin", bool()),
+                    ("rolconnlimit", int()),
+                ],
+            ),
+            // Always empty, since row-level security is not supported
+            system_table(
+                "pg_policy",
+                vec![
+                    ("oid", oid()),
+                    ("polname", text()),
+                    ("polrelid", oid()),
+                    ("polcmd", text()),
+                    ("polpermissive", bool()),
+                    ("polroles", array(oid())),
+                    ("polqual", text()),
+                    ("polwithcheck", text()),
+                ],
+            ),
+            // Always empty, since extended statistics are not supported
+            system_table(
+                "pg_statistic_ext",
+                vec![
+                    ("oid", oid()),
+                    ("stxrelid", oid()),
+                    ("stxname", text()),
+                    ("stxnamespace", oid()),
+                    ("stxowner", oid()),
+                    ("stxstattarget", int()),
+                    ("stxkeys", array(int())),
+                    ("stxkind", array(text())),
+                ],
+            ),
+            // Always empty, since inheritance and partitioning are not supported
+            system_table(
+                "pg_inherits",
+                vec![
+                    ("inhrelid", oid()),
+                    ("inhparent", oid()),
+                    ("inhseqno", int()),
+                    ("inhdetachpending", bool()),
+                ],
+            ),
+            // Only the user-defined functions are listed
+            system_table(
+                "pg_proc",
+                vec![
+                    ("oid", oid()),
+                    ("proname", text()),
+                    ("pronamespace", oid()),
+                    ("proowner", oid()),
+                    ("prokind", text()),
+                    ("proretset", bool()),
+                    ("prorettype", oid()),
+                    ("pronargs", int()),
+                    ("proargtypes", array(oid())),
+                    ("prosrc", text()),
+                ],
+            ),
+            // Always empty, since logical replication is not supported
+            system_table(
+                "pg_publication",
+                vec![
+                    ("oid", oid()),
+                    ("pubname", text()),
+                    ("pubowner", oid()),
+                    ("puballtables", bool()),
+                    ("pubinsert", bool()),
+                    ("pubupdate", bool()),
+                    ("pubdelete", bool()),
+                    ("pubtruncate", bool()),
+                    ("pubviaroot", bool()),
+                ],
+            ),
+            system_table(
+                "pg_publication_rel",
+                vec![("oid", oid()), ("prpubid", oid()), ("prrelid", oid())],
+            ),
+            // The values are text, since they are of different types, and
+            // `min_value` and `max_value` are not in Postgres
+            system_table(
+                "pg_stats",
+                vec![
+                    ("schemaname", text()),
+                    ("tablename", text()),
+                    ("attname", text()),
+                    ("inherited", bool()),
+                    ("null_frac", float()),
+                    ("n_distinct", float()),
+                    ("histogram_bounds", array(text())),
+                    ("min_value", text()),
+                    ("max_value", text()),
+                ],
+            ),
+            // Timestamps are text, since there is no timestamp type
+            system_table(
+                "pg_stat_activity",
+                vec![
+                    ("datid", oid()),
+                    ("datname", text()),
+                    ("pid", int()),
+                    ("usesysid", oid()),
+                    ("usename", text()),
+                    ("application_name", text()),
+                    ("client_addr", text()),
+                    ("backend_start", text()),
+                    ("query_start", text()),
+                    ("state_change", text()),
+                    ("state", text()),
+                    ("query", text()),
+                    ("backend_type", text()),
+                ],
+            ),
+        ]
+    };
+}
+
+fn system_table(name: &str, columns: Vec<(&str, Type)>) -> TableDefinition {
+    TableDefinition {
+        name: name.to_string(),
+        columns: columns
+            .into_iter()
+            .map(|(name, data_type)| ColumnDefinition {
+                name: name.to_string(),
+                data_type,
+                null: true,
+                default: None,
+                int_width: None,
+            })
+            .collect(),
+        indexes: vec![],
+        primary_key: None,
+        foreign_keys: vec![],
+        statistics: None,
+        version: 0,
+    }
+}
+
+/// Find a system relation by qualified name.
+pub fn find_system_table(schema_name: &str, table_name: &str) -> Option<&'static TableDefinition> {
+    if schema_name != SYSTEM_SCHEMA {
+        return None;
+    }
+
+    SYSTEM_TABLES.iter().find(|table| table.name == table_name)
+}
+
+/// Name of the type in the form of Postgres `format_type`, e.g. `bigint`.
+pub fn format_type(oid: i64) -> Option<String> {
+    let typ = pgwire::api::Type::from_oid(u32::try_from(oid).ok()?)?;
+    if let postgres_types::Kind::Array(elem_type) = typ.kind() {
+        return Some(format!("{}[]", format_type(elem_type.oid() as i64)?));
+    }
+
+    let name = match &typ {
+        &pgwire::api::Type::INT2 => "smallint",
+        &pgwire::api::Type::INT4 => "integer",
+        &pgwire::api::Type::INT8 => "bigint",
+        &pgwire::api::Type::FLOAT4 => "real",
+        &pgwire::api::Type::FLOAT8 => "double precision",
+        &pgwire::api::Type::VARCHAR => "character varying",
+        &pgwire::api::Type::BPCHAR => "character",
+        &pgwire::api::Type::BOOL => "boolean",
+        typ => typ.name(),
+    };
+    Some(name.to_string())
+}
+
+/// A relation listed in `pg_class`.
+struct Relation {
+    schema_name: String,
+    name: String,
+    /// `relkind`, e.g. `r` for tables.
+    kind: String,
+    /// If it can be referenced without schema name in the session.
+    visible: bool,
+    /// Definition of the index, or the key columns of the index.
+    index: Option<(String, Vec<String>)>,
+}
+
+/// A function listed in `pg_proc`.
+struct Function {
+    /// If it can be called without schema name in the session.
+    visible: bool,
+    /// Names of the argument types, e.g. `bigint, text`.
+    arguments: String,
+    /// Name of the result type.
+    result: String,
+}
+
+/// Snapshot of the catalog in the form of Postgres system relations.
+///
+/// OIDs are assigned to the objects in the order of the catalog, so they are
+/// stable until the catalog is changed.
+pub struct SystemCatalog {
+    tables: HashMap<String, Vec<Vec<Datum>>>,
+    relations: HashMap<i64, Relation>,
+    functions: HashMap<i64, Function>,
+    /// Definitions of constraints by OID.
+    constraints: HashMap<i64, String>,
+    /// OIDs of the databases by name.
+    databases: HashMap<String, i64>,
+}
+
+impl SystemCatalog {
+    /// All the databases are listed, but only the schemas of
+    /// `current_database`. The relations of `current_schema` and `pg_catalog`
+    /// are visible.
+    pub fn new(catalog: &Catalog, current_database: &str, current_schema: &str) -> Self {
+        let mut system_catalog = Self {
+            tables: HashMap::new(),
+            relations: HashMap::new(),
+            functions: HashMap::new(),
+            constraints: HashMap::new(),
+            databases: HashMap::new(),
+        };
+        system_catalog.add_types();
+        system_catalog.add_row(
+            "pg_am",
+            vec![
+                Datum::Int(HEAP_AM_OID),
+                Datum::String("heap".into()),
+                Datum::String("t".into()),
+            ],
+        );
+        system_catalog.add_row(
+            "pg_am",
+            vec![
+                Datum::Int(BTREE_AM_OID),
+                Datum::String("btree".into()),
+                Datum::String("i".into()),
+            ],
+        );
+        for (oid, name, provider) in COLLATIONS {
+            system_catalog.add_row(
+                "pg_collation",
+                vec![
+                    Datum::Int(oid),
+                    Datum::String(name.into()),
+                    Datum::Int(SYSTEM_SCHEMA_OID),
+                    Datum::Int(OWNER_OID),
+                    Datum::String(provider.into()),
+                    Datum::Int(-1),
+                ],
+            );
+        }
+        system_catalog.add_row(
+            "pg_roles",
+            vec![
+                Datum::Int(OWNER_OID),
+                Datum::String(OWNER_NAME.into()),
+                Datum::Boolean(true),
+                Datum::Boolean(true),
+                Datum::Boolean(true),
+                Datum::Boolean(true),
+                Datum::Boolean(true),
+                Datum::Int(-1),
+            ],
+        );
+        let mut oids = FIRST_NORMAL_OID..;
+        for database in catalog.databases.iter() {
+            let database_oid = if database.name == DEFAULT_DATABASE {
+                DATABASE_OID
+            } else {
+                oids.next().unwrap()
+            };
+            system_catalog
+                .databases
+                .insert(database.name.clone(), database_oid);
+            system_catalog.add_row(
+                "pg_database",
+                vec![
+                    Datum::Int(database_oid),
+                    Datum::String(database.name.as_str().into()),
+                    Datum::Int(OWNER_OID),
+                    Datum::Int(UTF8_ENCODING),
+                    Datum::String("c".into()),
+                    Datum::Boolean(false),
+                    Datum::Boolean(true),
+                    Datum::Int(-1),
+                    Datum::String("C".into()),
+                    Datum::String("C".into()),
+                    Datum::Null,
+                    Datum::Null,
+                ],
+            );
+        }
+
+        let system_schema = SchemaDefinition {
+            name: SYSTEM_SCHEMA.to_string(),
+            tables: SYSTEM_TABLES.clone(),
+            sequences: vec![],
+            functions: vec![],
+        };
+        let mut system_oids = FIRST_SYSTEM_OID..;
+        let mut schemas = vec![(&system_schema, SYSTEM_SCHEMA_OID, true)];
+        for schema in catalog.schemas(current_database) {
+            let visible = schema.name == current_schema;
+            schemas.push((schema, oids.next().unwrap(), visible));
+        }
+
+        // OIDs of the tables and indexes are assigned first, since foreign
+        // keys can reference the tables after them.
+        let mut relation_oids = HashMap::new();
+        for (schema, schema_oid, _) in schemas.iter() {
+            let oids: &mut dyn Iterator<Item = i64> = if *schema_oid == SYSTEM_SCHEMA_OID {
+                &mut system_oids
+            } else {
+                &mut oids
+            };
+            for table in schema.tables.iter() {
+                relation_oids.insert(
+                    (schema.name.clone(), table.name.clone()),
+                    oids.next().unwrap(),
+                );
+                for index in table.indexes.iter() {
+                    relation_oids.insert(
+                        (schema.name.clone(), index.name.clone()),
+                        oids.next().unwrap(),
+                    );
+                }
+            }
+        }
+
+        for (schema, schema_oid, visible) in schemas {
+            system_catalog.add_schema(
+                &catalog.database(current_database),
+                schema,
+                schema_oid,
+                visible,
+                &relation_oids,
+                &mut oids,
+            );
+        }
+
+        system_catalog
+    }
+
+    /// Rows of the system relation.
+    pub fn rows(&self, table_name: &str) -> Vec<Vec<Datum>> {
+        self.tables.get(table_name).cloned().unwrap_or_default()
+    }
+
+    /// `pg_table_is_visible(oid)`
+    pub fn is_visible(&self, oid: i64) -> Option<bool> {
+        self.relations.get(&oid).map(|relation| relation.visible)
+    }
+
+    /// `pg_relation_is_publishable(oid)`, only the user tables can be
+    /// published.
+    pub fn is_publishable(&self, oid: i64) -> Option<bool> {
+        self.relations
+            .get(&oid)
+            .map(|relation| relation.kind == "r" && relation.schema_name != SYSTEM_SCHEMA)
+    }
+
+    /// `pg_function_is_visible(oid)`
+    pub fn is_function_visible(&self, oid: i64) -> Option<bool> {
+        self.functions.get(&oid).map(|function| function.visible)
+    }
+
+    /// `pg_get_function_arguments(oid)`
+    pub fn function_arguments(&self, oid: i64) -> Option<String> {
+        self.functions
+            .get(&oid)
+            .map(|function| function.arguments.clone())
+    }
+
+    /// `pg_get_function_result(oid)`
+    pub fn function_result(&self, oid: i64) -> Option<String> {
+        self.functions
+            .get(&oid)
+            .map(|function| function.result.clone())
+    }
+
+    /// Name of the relation as the output of `regclass`, which is qualified
+    /// if it's not visible.
+    pub fn relation_name(&self, oid: i64) -> Option<String> {
+        self.relations.get(&oid).map(|relation| {
+            if relation.visible {
+                relation.name.clone()
+            } else {
+                format!("{}.{}", relation.schema_name, relation.name)
+            }
+        })
+    }
+
+    /// Name of the schema as the output of `regnamespace`.
+    pub fn namespace_name(&self, oid: i64) -> Option<String> {
+        self.tables["pg_namespace"]
+            .iter()
+            .find(|row| row[0] == Datum::Int(oid))
+            .and_then(|row| row[1].as_string().map(|name| name.to_string()))
+    }
+
+    /// OID of the schema by name as the input of `regnamespace`, which may be
+    /// the OID itself.
+    pub fn namespace_oid(&self, name: &str) -> Option<i64> {
+        if let Ok(oid) = name.parse() {
+            return Some(oid);
+        }
+
+        self.tables["pg_namespace"]
+            .iter()
+            .find(|row| row[1].as_string().map(|s| s.as_ref()) == Some(name))
+            .and_then(|row| row[0].as_int().copied())
+    }
+
+    /// OID of the relation by name as the input of `regclass`, which may be
+    /// qualified or the OID itself.
+    pub fn relation_oid(&self, name: &str) -> Option<i64> {
+        if let Ok(oid) = name.parse() {
+            return Some(oid);
+        }
+
+        let (schema_name, name) = match name.split_once('.') {
+            Some((schema_name, name)) => (Some(schema_name), name),
+            None => (None, name),
+        };
+        self.relations
+            .iter()
+            .find(|(_, relation)| {
+                relation.name == name
+                    && schema_name.map_or(relation.visible, |schema_name| {
+                        relation.schema_name == schema_name
+                    })
+            })
+            .map(|(oid, _)| *oid)
+    }
+
+    /// `pg_get_indexdef(oid, column)`, returns the definition of index if
+    /// `column` is 0, or the name of the key column.
+    pub fn index_definition(&self, oid: i64, column: i64) -> Option<String> {
+        let (definition, columns) = self.relations.get(&oid)?.index.as_ref()?;
+        if column == 0 {
+            Some(definition.clone())
+        } else {
+            columns.get(usize::try_from(column).ok()? - 1).cloned()
+        }
+    }
+
+    /// `pg_get_constraintdef(oid)`
+    pub fn constraint_definition(&self, oid: i64) -> Option<String> {
+        self.constraints.get(&oid).cloned()
+    }
+
+    /// Add the sessions to `pg_stat_activity`.
+    pub fn add_activities(&mut self, activities: Vec<SessionActivity>) {
+        for activity in activities {
+            let user = activity.user.unwrap_or_else(|| OWNER_NAME.to_string());
+            let database_oid = self.databases.get(&activity.database).copied();
+            self.add_row(
+                "pg_stat_activity",
+                vec![
+                    database_oid.map_or(Datum::Null, Datum::Int),
+                    Datum::String(activity.database.into()),
+                    Datum::Int(activity.pid),
+                    Datum::Int(OWNER_OID),
+                    Datum::String(user.into()),
+                    Datum::String(activity.application_name.into()),
+                    Datum::Null,
+                    Datum::String(format_timestamp(activity.backend_start).into()),
+                    activity.query_start.map_or(Datum::Null, |time| {
+                        Datum::String(format_timestamp(time).into())
+                    }),
+                    Datum::String(format_timestamp(activity.state_change).into()),
+                    Datum::String(activity.state.to_string().into()),
+                    Datum::String(activity.query.into()),
+                    Datum::String("client backend".into()),
+                ],
+            );
+        }
+    }
+
+    fn add_row(&mut self, table_name: &str, row: Vec<Datum>) {
+        self.tables
+            .entry(table_name.to_string())
+            .or_default()
+            .push(row);
+    }
+
+    fn add_types(&mut self) {
+        for (typ, int_width) in TYPES.iter() {
+            let array_type = Type::Array(Box::new(typ.clone()));
+            for (typ, elem_type, array_type) in [
+                (typ, None, Some(&array_type)),
+                (&array_type, Some(typ), None),
+            ] {
+                let (len, by_value, category) = match (typ, int_width) {
+                    (Type::Int, Some(IntWidth::Int2)) => (2, true, "N"),
+                    (Type::Int, Some(IntWidth::Int4)) => (4, true, "N"),
+                    (Type::Int | Type::Float, _) => (8, true, "N"),
+                    (Type::Boolean, _) => (1, true, "B"),
+                    (Type::Uuid, _) => (16, false, "U"),
+                    (Type::String, _) => (-1, false, "S"),
+                    (Type::Json, _) => (-1, false, "U"),
+                    (Type::Bytea, _) => (-1, false, "U"),
+                    _ => (-1, false, "A"),
+                };
+                let type_oid = |typ: Option<&Type>| {
+                    Datum::Int(typ.map_or(0, |typ| to_pg_column_type(typ, *int_width).oid() as i64))
+                };
+                self.add_row(
+                    "pg_type",
+                    vec![
+                        type_oid(Some(typ)),
+                        Datum::String(to_pg_column_type(typ, *int_width).name().into()),
+                        Datum::Int(SYSTEM_SCHEMA_OID),
+                        Datum::Int(OWNER_OID),
+                        Datum::Int(len),
+                        Datum::Boolean(by_value),
+                        Datum::String("b".into()),
+                        Datum::String(category.into()),
+                        Datum::Boolean(true),
+                        Datum::Int(0),
+                        type_oid(elem_type),
+                        type_oid(array_type),
+                        Datum::Boolean(false),
+                        Datum::Int(0),
+                        Datum::Int(-1),
+                        Datum::Int(0),
+                        Datum::Int(0),
+                    ],
+                );
+            }
+        }
+    }
+
+    /// `relation_oids` are the OIDs of tables and indexes by qualified name,
+    /// and the other objects are assigned with `oids`.
+    fn add_schema(
+        &mut self,
+        catalog: &DatabaseCatalog,
+        schema: &SchemaDefinition,
+        schema_oid: i64,
+        visible: bool,
+        relation_oids: &HashMap<(String, String), i64>,
+        oids: &mut impl Iterator<Item = i64>,
+    ) {
+        self.add_row(
+            "pg_namespace",
+            vec![
+                Datum::Int(schema_oid),
+                Datum::String(schema.name.clone().into()),
+                Datum::Int(OWNER_OID),
+                Datum::Null,
+            ],
+        );
+
+        let relation_oid = |schema_name: &str, name: &str| {
+            relation_oids[&(schema_name.to_string(), name.to_string())]
+        };
+
+        for table in schema.tables.iter() {
+            let table_oid = relation_oid(&schema.name, &table.name);
+            self.add_relation(
+                schema,
+                schema_oid,
+                table_oid,
+                &table.name,
+                "r",
+                HEAP_AM_OID,
+                table.columns.len(),
+                !table.indexes.is_empty(),
+                table
+                    .statistics
+                    .as_ref()
+                    .map(|statistics| statistics.row_count),
+                visible,
+            );
+            if let Some(statistics) = &table.statistics {
+                self.add_statistics(&schema.name, table, statistics);
+            }
+
+            for (i, column) in table.columns.iter().enumerate() {
+                self.add_attribute(table_oid, column, i + 1);
+                if let Some(default) = &column.default {
+                    self.add_row(
+                        "pg_attrdef",
+                        vec![
+                            Datum::Int(oids.next().unwrap()),
+                            Datum::Int(table_oid),
+                            Datum::Int(i as i64 + 1),
+                            Datum::String(default.clone().into()),
+                        ],
+                    );
+                }
+            }
+
+            for index in table.indexes.iter() {
+                let index_oid = relation_oid(&schema.name, &index.name);
+                let primary = table.primary_key.as_ref() == Some(&index.name);
+                let key = index
+                    .columns
+                    .iter()
+                    .map(|column| Datum::Int(*column as i64 + 1))
+                    .collect::<Vec<_>>();
+                let column_names = index
+                    .columns
+                    .iter()
+                    .map(|column| table.columns[*column].name.clone())
+                    .collect::<Vec<_>>();
+
+                self.add_relation(
+                    schema,
+                    schema_oid,
+                    index_oid,
+                    &index.name,
+                    "i",
+                    BTREE_AM_OID,
+                    index.columns.len(),
+                    false,
+                    None,
+                    visible,
+                );
+                let definition = format!(
+                    "CREATE {}INDEX {} ON {}.{} USING btree ({})",
+                    if index.unique { "UNIQUE " } else { "" },
+                    index.name,
+                    schema.name,
+                    table.name,
+                    column_names.join(", ")
+                );
+                self.relations.get_mut(&index_oid).unwrap().index =
+                    Some((definition, column_names.clone()));
+                for (i, column) in index.columns.iter().enumerate() {
+                    self.add_attribute(index_oid, &table.columns[*column], i + 1);
+                }
+
+                self.add_row(
+                    "pg_index",
+                    vec![
+                        Datum::Int(index_oid),
+                        Datum::Int(table_oid),
+                        Datum::Int(key.len() as i64),
+                        Datum::Int(key.len() as i64),
+                        Datum::Boolean(index.unique),
+                        Datum::Boolean(primary),
+                        Datum::Boolean(false),
+                        Datum::Boolean(true),
+                        Datum::Boolean(false),
+                        Datum::Boolean(true),
+                        Datum::Boolean(true),
+                        Datum::Boolean(true),
+                        Datum::Boolean(false),
+                        Datum::Array(key.clone()),
+                        Datum::Null,
+                        Datum::Null,
+                    ],
+                );
+
+                if primary {
+                    let constraint_oid = oids.next().unwrap();
+                    self.add_constraint(
+                        constraint_oid,
+                        &index.name,
+                        schema_oid,
+                        "p",
+                        table_oid,
+                        index_oid,
+                        key,
+                        None,
+                    );
+                    self.constraints.insert(
+                        constraint_oid,
+                        format!("PRIMARY KEY ({})", column_names.join(", ")),
+                    );
+                }
+            }
+
+            for foreign_key in table.foreign_keys.iter() {
+                let Ok(Some(referred)) = catalog.find_table_by_name(
+                    &foreign_key.referred_schema,
+                    &foreign_key.referred_table,
+                ) else {
+                    continue;
+                };
+                let referred_index = referred
+                    .find_unique_index(&foreign_key.referred_columns)
+                    .map_or(0, |index| {
+                        relation_oid(&foreign_key.referred_schema, &index.name)
+                    });
+                let column_names = |table: &TableDefinition, columns: &[usize]| {
+                    columns
+                        .iter()
+                        .map(|column| table.columns[*column].name.clone())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let key = |columns: &[usize]| {
+                    columns
+                        .iter()
+                        .map(|column| Datum::Int(*column as i64 + 1))
+                        .collect()
+                };
+
+                let constraint_oid = oids.next().unwrap();
+                self.add_constraint(
+                    constraint_oid,
+                    &foreign_key.name,
+                    schema_oid,
+                    "f",
+                    table_oid,
+                    referred_index,
+                    key(&foreign_key.columns),
+                    Some((
+                        relation_oid(&foreign_key.referred_schema, &referred.name),
+                        key(&foreign_key.referred_columns),
+                        foreign_key.on_delete,
+                    )),
+                );
+                self.constraints.insert(
+                    constraint_oid,
+                    format!(
+                        "FOREIGN KEY ({}) REFERENCES {}({}){}",
+                        column_names(table, &foreign_key.columns),
+                        referred.name,
+                        column_names(&referred, &foreign_key.referred_columns),
+                        match foreign_key.on_delete {
+                            ReferentialAction::NoAction => "",
+                            ReferentialAction::Cascade => " ON DELETE CASCADE",
+                        }
+                    ),
+                );
+            }
+        }
+
+        for sequence in schema.sequences.iter() {
+            let sequence_oid = oids.next().unwrap();
+            self.add_relation(
+                schema,
+                schema_oid,
+                sequence_oid,
+                &sequence.name,
+                "S",
+                0,
+                0,
+                false,
+                None,
+                visible,
+            );
+            self.add_row(
+                "pg_sequence",
+                vec![
+                    Datum::Int(sequence_oid),
+                    Datum::Int(to_pg_type(&Type::Int).oid() as i64),
+                    Datum::Int(sequence.start),
+                    Datum::Int(sequence.increment),
+                    Datum::Int(sequence.max_value),
+                    Datum::Int(sequence.min_value),
+                    Datum::Int(1),
+                    Datum::Boolean(sequence.cycle),
+                ],
+            );
+        }
+
+        for function in schema.functions.iter() {
+            let function_oid = oids.next().unwrap();
+            let type_oid = |typ: &Type| to_pg_type(typ).oid() as i64;
+            let type_name = |typ: &Type| format_type(type_oid(typ)).unwrap_or_default();
+            self.functions.insert(
+                function_oid,
+                Function {
+                    visible,
+                    arguments: function
+                        .arg_types
+                        .iter()
+                        .map(type_name)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    result: type_name(&function.return_type),
+                },
+            );
+            self.add_row(
+                "pg_proc",
+                vec![
+                    Datum::Int(function_oid),
+                    Datum::String(function.name.clone().into()),
+                    Datum::Int(schema_oid),
+                    Datum::Int(OWNER_OID),
+                    Datum::String("f".into()),
+                    Datum::Boolean(false),
+                    Datum::Int(type_oid(&function.return_type)),
+                    Datum::Int(function.arg_types.len() as i64),
+                    Datum::Array(
+                        function
+                            .arg_types
+                            .iter()
+                            .map(|typ| Datum::Int(type_oid(typ)))
+                            .collect(),
+                    ),
+                    match &function.body {
+                        FunctionBody::Sql(body) => Datum::String(body.clone().into()),
+                        FunctionBody::Wasm(_) => Datum::Null,
+                    },
+                ],
+            );
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_relation(
+        &mut self,
+        schema: &SchemaDefinition,
+        schema_oid: i64,
+        oid: i64,
+        name: &str,
+        kind: &str,
+        am_oid: i64,
+        num_columns: usize,
+        has_index: bool,
+        row_count: Option<u64>,
+        visible: bool,
+    ) {
+        self.relations.insert(
+            oid,
+            Relation {
+                schema_name: schema.name.clone(),
+                name: name.to_string(),
+                kind: kind.to_string(),
+                visible,
+                index: None,
+            },
+        );
+        self.add_row(
+            "pg_class",
+            vec![
+                Datum::Int(oid),
+                Datum::String(name.into()),
+                Datum::Int(schema_oid),
+                Datum::Int(0),
+                Datum::Int(0),
+                Datum::Int(OWNER_OID),
+                Datum::Int(am_oid),
+                Datum::Int(oid),
+                Datum::Int(0),
+                Datum::Int(0),
+                // -1 if the table has never been analyzed, as Postgres
+                Datum::Float(row_count.map_or(-1.0, |row_count| row_count as f64)),
+                Datum::Int(0),
+                Datum::Boolean(has_index),
+                Datum::Boolean(false),
+                Datum::String("p".into()),
+                Datum::String(kind.into()),
+                Datum::Int(num_columns as i64),
+                Datum::Int(0),
+                Datum::Boolean(false),
+                Datum::Boolean(false),
+                Datum::Boolean(false),
+                Datum::Boolean(false),
+                Datum::Boolean(false),
+                Datum::Boolean(true),
+                Datum::String("d".into()),
+                Datum::Boolean(false),
+                Datum::Null,
+                Datum::Null,
+                Datum::Null,
+            ],
+        );
+    }
+
+    /// Add the statistics of columns to `pg_stats`.
+    fn add_statistics(
+        &mut self,
+        schema_name: &str,
+        table: &TableDefinition,
+        statistics: &TableStatistics,
+    ) {
+        let text = |value: &Datum| match value {
+            Datum::Null => Datum::Null,
+            value => Datum::String(value.to_string().into()),
+        };
+        for (column, statistics) in table.columns.iter().zip(statistics.columns.iter()) {
+            let histogram_bounds = match statistics.histogram.is_empty() {
+                true => Datum::Null,
+                false => Datum::Array(statistics.histogram.iter().map(text).collect()),
+            };
+            self.add_row(
+                "pg_stats",
+                vec![
+                    Datum::String(schema_name.into()),
+                    Datum::String(table.name.clone().into()),
+                    Datum::String(column.name.clone().into()),
+                    Datum::Boolean(false),
+                    Datum::Float(statistics.null_fraction),
+                    Datum::Float(statistics.distinct_count as f64),
+                    histogram_bounds,
+                    text(&statistics.min),
+                    text(&statistics.max),
+                ],
+            );
+        }
+    }
+
+    fn add_attribute(&mut self, relation_oid: i64, column: &ColumnDefinition, num: usize) {
+        let len = match (&column.data_type, column.int_width) {
+            (Type::Int, Some(IntWidth::Int2)) => 2,
+            (Type::Int, Some(IntWidth::Int4)) => 4,
+            (Type::Int | Type::Float, _) => 8,
+            (Type::Boolean, _) => 1,
+            (Type::Uuid, _) => 16,
+            _ => -1,
+        };
+        self.add_row(
+            "pg_attribute",
+            vec![
+                Datum::Int(relation_oid),
+                Datum::String(column.name.clone().into()),
+                Datum::Int(to_pg_column_type(&column.data_type, column.int_width).oid() as i64),
+                Datum::Int(len),
+                Datum::Int(num as i64),
+                Datum::Int(-1),
+                Datum::Int(matches!(column.data_type, Type::Array(_)) as i64),
+                Datum::Boolean(!column.null),
+                Datum::Boolean(column.default.is_some()),
+                Datum::String("".into()),
+                Datum::String("".into()),
+                Datum::Boolean(false),
+                Datum::Boolean(true),
+                Datum::Int(0),
+            ],
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_constraint(
+        &mut self,
+        oid: i64,
+        name: &str,
+        schema_oid: i64,
+        kind: &str,
+        relation_oid: i64,
+        index_oid: i64,
+        key: Vec<Datum>,
+        referred: Option<(i64, Vec<Datum>, ReferentialAction)>,
+    ) {
+        let (referred_oid, referred_key, delete_action) = match referred {
+            Some((referred_oid, referred_key, on_delete)) => (
+                Datum::Int(referred_oid),
+                Datum::Array(referred_key),
+                Datum::String(
+                    match on_delete {
+                        ReferentialAction::NoAction => "a",
+                        ReferentialAction::Cascade => "c",
+                    }
+                    .into(),
+                ),
+            ),
+            None => (Datum::Int(0), Datum::Null, Datum::String(" ".into())),
+        };
+        let update_action = if kind == "f" { "a" } else { " " };
+        let match_type = if kind == "f" { "s" } else { " " };
+
+        self.add_row(
+            "pg_constraint",
+            vec![
+                Datum::Int(oid),
+                Datum::String(name.into()),
+                Datum::Int(schema_oid),
+                Datum::String(kind.into()),
+                Datum::Boolean(false),
+                Datum::Boolean(false),
+                Datum::Boolean(true),
+                Datum::Int(relation_oid),
+                Datum::Int(0),
+                Datum::Int(index_oid),
+                Datum::Int(0),
+                referred_oid,
+                Datum::String(update_action.into()),
+                delete_action,
+                Datum::String(match_type.into()),
+                Datum::Boolean(true),
+                Datum::Array(key),
+                referred_key,
+            ],
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+/// Users allowed to connect to the server, with their passwords in cleartext.
+#[derive(Debug, Clone, Default)]
+pub struct UserCatalog {
+    users: HashMap<String, String>,
+}
+
+impl UserCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_user(&mut self, name: &str, password: &str) {
+        self.users.insert(name.to_string(), password.to_string());
+    }
+
+    pub fn find_password(&self, name: &str) -> Option<&String> {
+        self.users.get(name)
+    }
+
+    /// Parse users from a list in form of `user1:password1,user2:password2`.
+    pub fn parse(users: &str) -> Result<Self, String> {
+        let mut catalog = Self::new();
+        for user in users.split(',').filter(|user| !user.is_empty()) {
+            let (name, password) = user
+                .split_once(':')
+                .ok_or_else(|| format!("invalid user definition: {}", user))?;
+            catalog.add_user(name, password);
+        }
+
+        Ok(catalog)
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::{
+    authentication::{
+        md5_hash,
+        sasl::{ChannelBinding, ScramSha256, SCRAM_SHA_256},
+    },
+    message::{backend::Message, frontend},
+};
+
+use crate::{
+    core::{sqlstate, Datum, ErrorKind, IntWidth, SQLError, Tuple, Type},
+    sql::session::{
+        from_pg_type,
+        result::{QueryResult, ResultColumn},
+        SQLKind,
+    },
+};
+
+/// Client of a server speaking Postgres protocol, only the simple query
+/// protocol without TLS is supported.
+pub struct Client {
+    stream: TcpStream,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+}
+
+impl Client {
+    /// Connect to the server and authenticate with the password, if it's
+    /// required by the server.
+    pub fn connect(
+        addr: &str,
+        user: &str,
+        database: Option<&str>,
+        password: Option<&str>,
+    ) -> Result<Self, SQLError> {
+        let stream = TcpStream::connect(addr).map_err(|e| {
+            SQLError::new(
+                ErrorKind::UnknownError,
+                format!("could not connect to server {}: {}", addr, e),
+            )
+            .with_code(sqlstate::IO_ERROR)
+        })?;
+        let mut client = Self {
+            stream,
+            read_buf: BytesMut::new(),
+            write_buf: BytesMut::new(),
+        };
+
+        let mut parameters = vec![("user", user), ("application_name", "leisql")];
+        if let Some(database) = database {
+            parameters.push(("database", database));
+        }
+        frontend::startup_message(parameters, &mut client.write_buf).map_err(io_error)?;
+        client.flush()?;
+        client.authenticate(user, password)?;
+
+        // Wait until the server is ready for queries
+        loop {
+            match client.read_message()? {
+                Message::ReadyForQuery(_) => return Ok(client),
+                Message::ErrorResponse(body) => return Err(server_error(body.fields())),
+                _ => {}
+            }
+        }
+    }
+
+    fn authenticate(&mut self, user: &str, password: Option<&str>) -> Result<(), SQLError> {
+        let password = || {
+            password.ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::UnknownError,
+                    "password authentication failed, no password supplied",
+                )
+                .with_code(sqlstate::INVALID_PASSWORD)
+                .with_hint("Set the password with PGPASSWORD.")
+            })
+        };
+
+        match self.read_message()? {
+            Message::AuthenticationOk => return Ok(()),
+            Message::AuthenticationCleartextPassword => {
+                frontend::password_message(password()?.as_bytes(), &mut self.write_buf)
+                    .map_err(io_error)?;
+            }
+            Message::AuthenticationMd5Password(body) => {
+                let hash = md5_hash(user.as_bytes(), password()?.as_bytes(), body.salt());
+                frontend::password_message(hash.as_bytes(), &mut self.write_buf)
+                    .map_err(io_error)?;
+            }
+            Message::AuthenticationSasl(body) => {
+                let supported = body
+                    .mechanisms()
+                    .any(|mechanism| Ok(mechanism == SCRAM_SHA_256))
+                    .map_err(io_error)?;
+                if !supported {
+                    return Err(unsupported_authentication());
+                }
+                let mut scram =
+                    ScramSha256::new(password()?.as_bytes(), ChannelBinding::unsupported());
+                frontend::sasl_initial_response(
+                    SCRAM_SHA_256,
+                    scram.message(),
+                    &mut self.write_buf,
+                )
+                .map_err(io_error)?;
+                self.flush()?;
+
+                match self.read_message()? {
+                    Message::AuthenticationSaslContinue(body) => {
+                        scram.update(body.data()).map_err(io_error)?
+                    }
+                    Message::ErrorResponse(body) => return Err(server_error(body.fields())),
+                    _ => return Err(unexpected_message()),
+                }
+                frontend::sasl_response(scram.message(), &mut self.write_buf).map_err(io_error)?;
+                self.flush()?;
+
+                match self.read_message()? {
+                    // A wrong password is answered with the error of the
+                    // final message, e.g. `e=invalid-proof`
+                    Message::AuthenticationSaslFinal(body) => {
+                        scram.finish(body.data()).map_err(|_| {
+                            SQLError::new(
+                                ErrorKind::UnknownError,
+                                format!("password authentication failed for user \"{}\"", user),
+                            )
+                            .with_code(sqlstate::INVALID_PASSWORD)
+                        })?
+                    }
+                    Message::ErrorResponse(body) => return Err(server_error(body.fields())),
+                    _ => return Err(unexpected_message()),
+                }
+                return self.expect_authentication_ok();
+            }
+            Message::ErrorResponse(body) => return Err(server_error(body.fields())),
+            _ => return Err(unsupported_authentication()),
+        }
+        self.flush()?;
+        self.expect_authentication_ok()
+    }
+
+    fn expect_authentication_ok(&mut self) -> Result<(), SQLError> {
+        match self.read_message()? {
+            Message::AuthenticationOk => Ok(()),
+            Message::ErrorResponse(body) => Err(server_error(body.fields())),
+            _ => Err(unexpected_message()),
+        }
+    }
+
+    /// Execute the statements with simple query protocol, returns the result
+    /// of each statement, the rest statements are skipped by the server once
+    /// an error occurs.
+    pub fn simple_query(
+        &mut self,
+        sql: &str,
+    ) -> Result<Vec<Result<QueryResult, SQLError>>, SQLError> {
+        frontend::query(sql, &mut self.write_buf).map_err(io_error)?;
+        self.flush()?;
+
+        let mut results = vec![];
+        let mut columns = vec![];
+        let mut data = vec![];
+        loop {
+            match self.read_message()? {
+                Message::RowDescription(body) => {
+                    columns = body
+                        .fields()
+                        .map(|field| {
+                            let pg_type = postgres_types::Type::from_oid(field.type_oid());
+                            let typ = pg_type
+                                .as_ref()
+                                .and_then(|typ| from_pg_type(typ).ok())
+                                .unwrap_or(Type::String);
+                            let int_width = match pg_type {
+                                Some(postgres_types::Type::INT2) => Some(IntWidth::Int2),
+                                Some(postgres_types::Type::INT4) => Some(IntWidth::Int4),
+                                _ => None,
+                            };
+                            Ok(ResultColumn {
+                                name: field.name().to_string(),
+                                typ,
+                                int_width,
+                            })
+                        })
+                        .collect()
+                        .map_err(io_error)?;
+                }
+                Message::DataRow(body) => {
+                    let ranges = body.ranges().collect::<Vec<_>>().map_err(io_error)?;
+                    let values = ranges
+                        .into_iter()
+                        .zip(columns.iter())
+                        .map(|(range, column)| match range {
+                            Some(range) => parse_value(&body.buffer()[range], &column.typ),
+                            None => Datum::Null,
+                        })
+                        .collect();
+                    data.push(Tuple::new(values));
+                }
+                Message::CommandComplete(body) => {
+                    let tag = body.tag().map_err(io_error)?;
+                    results.push(Ok(command_result(
+                        tag,
+                        std::mem::take(&mut columns),
+                        std::mem::take(&mut data),
+                    )));
+                }
+                Message::EmptyQueryResponse => {}
+                Message::ErrorResponse(body) => results.push(Err(server_error(body.fields()))),
+                Message::ReadyForQuery(_) => return Ok(results),
+                _ => {}
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), SQLError> {
+        self.stream.write_all(&self.write_buf).map_err(io_error)?;
+        self.write_buf.clear();
+        Ok(())
+    }
+
+    fn read_message(&mut self) -> Result<Message, SQLError> {
+        loop {
+            if let Some(message) = Message::parse(&mut self.read_buf).map_err(io_error)? {
+                return Ok(message);
+            }
+            let mut chunk = [0; 8192];
+            let n = self.stream.read(&mut chunk).map_err(io_error)?;
+            if n == 0 {
+                return Err(SQLError::new(
+                    ErrorKind::UnknownError,
+                    "server closed the connection unexpectedly",
+                )
+                .with_code(sqlstate::IO_ERROR));
+            }
+            self.read_buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        frontend::terminate(&mut self.write_buf);
+        let _ = self.flush();
+    }
+}
+
+/// Parse the value in text format, it's kept as string if it's not valid
+/// for the type.
+fn parse_value(bytes: &[u8], typ: &Type) -> Datum {
+    let text = Datum::String(String::from_utf8_lossy(bytes).into());
+    match text.cast(typ) {
+        Datum::Null => text,
+        value => value,
+    }
+}
+
+/// Result of the statement completed with the command tag, e.g. `UPDATE 3`.
+fn command_result(tag: &str, columns: Vec<ResultColumn>, data: Vec<Tuple>) -> QueryResult {
+    let (command, rows) = match tag.rsplit_once(' ') {
+        Some((command, rows)) => (command, rows.parse::<i64>().ok()),
+        None => (tag, None),
+    };
+    let kind = match (command, rows) {
+        _ if !columns.is_empty() => SQLKind::Query,
+        ("UPDATE", Some(_)) => SQLKind::Update,
+        ("DELETE", Some(_)) => SQLKind::Delete,
+        ("COPY", Some(_)) => SQLKind::Copy,
+        _ => SQLKind::Execute,
+    };
+    let data = match (kind, rows) {
+        (SQLKind::Update | SQLKind::Delete | SQLKind::Copy, Some(rows)) => {
+            vec![Tuple::new(vec![Datum::Int(rows)])]
+        }
+        _ => data,
+    };
+
+    QueryResult {
+        columns,
+        data,
+        kind,
+    }
+}
+
+/// Error reported by the server in `ErrorResponse`.
+fn server_error(mut fields: postgres_protocol::message::backend::ErrorFields<'_>) -> SQLError {
+    let mut error = SQLError::new(ErrorKind::UnknownError, "");
+    while let Ok(Some(field)) = fields.next() {
+        match field.type_() {
+            b'M' => error.message = field.value().to_string(),
+            b'D' => error.detail = Some(field.value().to_string()),
+            b'H' => error.hint = Some(field.value().to_string()),
+            _ => {}
+        }
+    }
+    error
+}
+
+fn io_error(e: std::io::Error) -> SQLError {
+    SQLError::new(ErrorKind::UnknownError, e.to_string()).with_code(sqlstate::IO_ERROR)
+}
+
+fn unexpected_message() -> SQLError {
+    SQLError::new(
+        ErrorKind::UnknownError,
+        "unexpected message from the server",
+    )
+    .with_code(sqlstate::PROTOCOL_VIOLATION)
+}
+
+fn unsupported_authentication() -> SQLError {
+    SQLError::new(
+        ErrorKind::UnknownError,
+        "authentication method requested by the server is not supported",
+    )
+    .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+}
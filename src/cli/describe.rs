@@ -0,0 +1,150 @@
+use crate::{
+    catalog::defs::{ReferentialAction, TableDefinition},
+    core::{sqlstate, Datum, ErrorKind, SQLError, Tuple, Type},
+    sql::session::{
+        context::QueryContext,
+        result::{QueryResult, ResultColumn},
+        to_pg_column_type, SQLKind,
+    },
+};
+
+/// Tables of the current schema, for `\dt`.
+pub fn list_tables(ctx: &QueryContext) -> Result<QueryResult, SQLError> {
+    let tables = ctx
+        .catalog()
+        .list_tables(&ctx.current_database, &ctx.current_schema)?;
+    Ok(string_result(
+        &["Schema", "Name", "Type"],
+        tables
+            .into_iter()
+            .map(|table| vec![ctx.current_schema.clone(), table, "table".to_string()])
+            .collect(),
+    ))
+}
+
+/// All the schemas of the current database, for `\l`.
+pub fn list_schemas(ctx: &QueryContext) -> QueryResult {
+    string_result(
+        &["Name"],
+        ctx.catalog()
+            .list_schemas(&ctx.current_database)
+            .into_iter()
+            .map(|schema| vec![schema])
+            .collect(),
+    )
+}
+
+/// Columns of the table, and the lines describing its indexes and foreign
+/// keys, for `\d table`.
+pub fn describe_table(
+    ctx: &QueryContext,
+    name: &str,
+) -> Result<(QueryResult, Vec<String>), SQLError> {
+    let (schema_name, table_name) = match name.split_once('.') {
+        Some((schema_name, table_name)) => (schema_name, table_name),
+        None => (ctx.current_schema.as_str(), name),
+    };
+    let table = ctx
+        .catalog()
+        .find_table_by_name(&ctx.current_database, schema_name, table_name)?
+        .ok_or_else(|| {
+            SQLError::new(
+                ErrorKind::CatalogError,
+                format!("Did not find any relation named \"{}\".", name),
+            )
+            .with_code(sqlstate::UNDEFINED_TABLE)
+        })?;
+
+    let columns = string_result(
+        &["Column", "Type", "Nullable", "Default"],
+        table
+            .columns
+            .iter()
+            .map(|column| {
+                vec![
+                    column.name.clone(),
+                    to_pg_column_type(&column.data_type, column.int_width)
+                        .name()
+                        .to_string(),
+                    if column.null { "" } else { "not null" }.to_string(),
+                    column.default.clone().unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    );
+
+    let mut footer = vec![];
+    if !table.indexes.is_empty() {
+        footer.push("Indexes:".to_string());
+    }
+    for index in table.indexes.iter() {
+        let kind = if table.primary_key.as_ref() == Some(&index.name) {
+            "PRIMARY KEY"
+        } else if index.unique {
+            "UNIQUE"
+        } else {
+            "INDEX"
+        };
+        footer.push(format!(
+            "    \"{}\" {} ({})",
+            index.name,
+            kind,
+            column_names(&table, &index.columns)
+        ));
+    }
+    if !table.foreign_keys.is_empty() {
+        footer.push("Foreign-key constraints:".to_string());
+    }
+    for foreign_key in table.foreign_keys.iter() {
+        let referred_columns = ctx
+            .catalog()
+            .find_table_by_name(
+                &ctx.current_database,
+                &foreign_key.referred_schema,
+                &foreign_key.referred_table,
+            )?
+            .map(|referred| column_names(&referred, &foreign_key.referred_columns))
+            .unwrap_or_default();
+        footer.push(format!(
+            "    \"{}\" FOREIGN KEY ({}) REFERENCES {}.{}({}){}",
+            foreign_key.name,
+            column_names(&table, &foreign_key.columns),
+            foreign_key.referred_schema,
+            foreign_key.referred_table,
+            referred_columns,
+            match foreign_key.on_delete {
+                ReferentialAction::NoAction => "",
+                ReferentialAction::Cascade => " ON DELETE CASCADE",
+            }
+        ));
+    }
+
+    Ok((columns, footer))
+}
+
+fn column_names(table: &TableDefinition, columns: &[usize]) -> String {
+    columns
+        .iter()
+        .map(|column| table.columns[*column].name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Result of the rows of strings.
+fn string_result(names: &[&str], rows: Vec<Vec<String>>) -> QueryResult {
+    QueryResult {
+        columns: names
+            .iter()
+            .map(|name| ResultColumn {
+                name: name.to_string(),
+                typ: Type::String,
+                int_width: None,
+            })
+            .collect(),
+        data: rows
+            .into_iter()
+            .map(|row| Tuple::new(row.into_iter().map(|v| Datum::String(v.into())).collect()))
+            .collect(),
+        kind: SQLKind::Query,
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    core::{Datum, Json, SQLError, Type},
+    sql::session::{result::QueryResult, SQLKind},
+};
+
+/// Format of the rows printed by the shell, set with `\pset format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Table with aligned columns, the default one.
+    Aligned,
+    Csv,
+    /// Array of objects keyed by the names of columns.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "aligned" => Ok(OutputFormat::Aligned),
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!(
+                "\\pset: allowed formats are aligned, csv, json, not {}",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Aligned => write!(f, "aligned"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Printer of the results and errors of statements.
+#[derive(Clone, Copy, Debug)]
+pub struct Printer {
+    pub format: OutputFormat,
+    /// Print each column of a row in its own line, only for the aligned
+    /// format.
+    pub expanded: bool,
+    /// Don't print the command tags of the statements returning no rows.
+    pub quiet: bool,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Aligned,
+            expanded: false,
+            quiet: false,
+        }
+    }
+}
+
+impl Printer {
+    /// Print the rows of the result, or the command tag of the statements
+    /// returning no rows.
+    pub fn write_result(&self, result: &QueryResult, output: &mut impl Write) -> io::Result<()> {
+        let affected_rows = result.affected_rows();
+        match result.kind {
+            SQLKind::Query => {}
+            _ if self.quiet => return Ok(()),
+            SQLKind::Update => return writeln!(output, "UPDATE {}", affected_rows),
+            SQLKind::Delete => return writeln!(output, "DELETE {}", affected_rows),
+            SQLKind::Copy => return writeln!(output, "COPY {}", affected_rows),
+            SQLKind::Execute => return writeln!(output, "OK"),
+        }
+
+        match self.format {
+            OutputFormat::Aligned if self.expanded && !result.data.is_empty() => {
+                write_expanded(result, output)
+            }
+            OutputFormat::Aligned => {
+                write_table(result, output)?;
+                match result.data.len() {
+                    1 => writeln!(output, "(1 row)\n"),
+                    rows => writeln!(output, "({} rows)\n", rows),
+                }
+            }
+            OutputFormat::Csv => write_csv(result, output),
+            OutputFormat::Json => write_json(result, output),
+        }
+    }
+
+    pub fn write_error(&self, e: &SQLError, output: &mut impl Write) -> io::Result<()> {
+        writeln!(output, "ERROR:  {}", e.message)?;
+        if let Some(detail) = &e.detail {
+            writeln!(output, "DETAIL:  {}", detail)?;
+        }
+        if let Some(hint) = &e.hint {
+            writeln!(output, "HINT:  {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text of the value in a cell, NULL is empty.
+fn cell_text(datum: &Datum) -> String {
+    match datum {
+        Datum::Null => String::new(),
+        datum => datum.to_string(),
+    }
+}
+
+/// Print the rows as an aligned table with the names of columns as header,
+/// numbers are aligned to the right.
+pub fn write_table(result: &QueryResult, output: &mut impl Write) -> io::Result<()> {
+    let rows = result
+        .data
+        .iter()
+        .map(|tuple| tuple.values.iter().map(cell_text).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let widths = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .fold(column.name.chars().count(), usize::max)
+        })
+        .collect::<Vec<_>>();
+
+    let header = result
+        .columns
+        .iter()
+        .zip(widths.iter())
+        .map(|(column, width)| format!(" {:^width$} ", column.name, width = width))
+        .collect::<Vec<_>>();
+    writeln!(output, "{}", header.join("|"))?;
+    let separator = widths
+        .iter()
+        .map(|width| "-".repeat(width + 2))
+        .collect::<Vec<_>>();
+    writeln!(output, "{}", separator.join("+"))?;
+    for row in rows.iter() {
+        let cells = row
+            .iter()
+            .zip(result.columns.iter().zip(widths.iter()))
+            .map(|(cell, (column, width))| match column.typ {
+                Type::Int | Type::Float => format!(" {:>width$} ", cell, width = width),
+                _ => format!(" {:<width$} ", cell, width = width),
+            })
+            .collect::<Vec<_>>();
+        writeln!(output, "{}", cells.join("|").trim_end())?;
+    }
+    Ok(())
+}
+
+/// Print each row as a record, with a line for each column.
+fn write_expanded(result: &QueryResult, output: &mut impl Write) -> io::Result<()> {
+    let name_width = result
+        .columns
+        .iter()
+        .map(|column| column.name.chars().count())
+        .max()
+        .unwrap_or_default();
+    let value_width = result
+        .data
+        .iter()
+        .flat_map(|tuple| tuple.values.iter())
+        .map(|datum| cell_text(datum).chars().count())
+        .max()
+        .unwrap_or_default();
+
+    for (i, tuple) in result.data.iter().enumerate() {
+        let title = format!("-[ RECORD {} ]", i + 1);
+        let title_width = name_width + value_width + 3;
+        writeln!(
+            output,
+            "{}{}",
+            title,
+            "-".repeat(title_width.saturating_sub(title.chars().count()))
+        )?;
+        for (column, datum) in result.columns.iter().zip(tuple.values.iter()) {
+            let line = format!(
+                "{:<width$} | {}",
+                column.name,
+                cell_text(datum),
+                width = name_width
+            );
+            writeln!(output, "{}", line.trim_end())?;
+        }
+    }
+    writeln!(output)
+}
+
+/// Print the rows as CSV with a header, fields are quoted if needed, and
+/// NULL is an empty unquoted field.
+fn write_csv(result: &QueryResult, output: &mut impl Write) -> io::Result<()> {
+    let quote = |field: &str| {
+        if field.is_empty() || field.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+
+    let header = result
+        .columns
+        .iter()
+        .map(|column| quote(&column.name))
+        .collect::<Vec<_>>();
+    writeln!(output, "{}", header.join(","))?;
+    for tuple in result.data.iter() {
+        let fields = tuple
+            .values
+            .iter()
+            .map(|datum| match datum {
+                Datum::Null => String::new(),
+                datum => quote(&datum.to_string()),
+            })
+            .collect::<Vec<_>>();
+        writeln!(output, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// Print the rows as a JSON array of objects, the values keep their types.
+fn write_json(result: &QueryResult, output: &mut impl Write) -> io::Result<()> {
+    let rows = result
+        .data
+        .iter()
+        .map(|tuple| {
+            Json::Object(
+                result
+                    .columns
+                    .iter()
+                    .zip(tuple.values.iter())
+                    .map(|(column, datum)| (column.name.clone(), Json::from(datum)))
+                    .collect(),
+            )
+        })
+        .collect();
+    writeln!(output, "{}", Json::Array(rows))
+}
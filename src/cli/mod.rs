@@ -0,0 +1,505 @@
+mod client;
+mod describe;
+mod format;
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+pub use self::{
+    client::Client,
+    format::{OutputFormat, Printer},
+};
+use crate::{
+    core::{sqlstate, ErrorKind, SQLError},
+    Connection, QueryResult,
+};
+
+pub const PROMPT: &str = "leisql=> ";
+/// Prompt of the lines continuing an incomplete statement.
+const CONTINUATION_PROMPT: &str = "leisql-> ";
+
+/// Where the statements of the shell are executed.
+pub enum Backend {
+    /// Connection to an embedded database.
+    Embedded(Connection),
+    /// Connection to a server over Postgres protocol.
+    Remote(Client),
+}
+
+impl Backend {
+    /// Execute the statements, returns the results of the ones executed
+    /// and the time taken by each of them. The rest statements are skipped
+    /// once an error occurs.
+    fn execute(&mut self, sql: &str) -> Vec<(Result<QueryResult, SQLError>, Duration)> {
+        match self {
+            Backend::Embedded(conn) => {
+                let statements = match conn.session().parse(sql) {
+                    Ok(statements) => statements,
+                    Err(e) => return vec![(Err(e), Duration::ZERO)],
+                };
+                let mut results = vec![];
+                for statement in statements.iter() {
+                    let start = Instant::now();
+                    let result = conn.session().execute(statement);
+                    let failed = result.is_err();
+                    results.push((result, start.elapsed()));
+                    if failed {
+                        break;
+                    }
+                }
+                results
+            }
+            // The statements are sent at once, so the time is only known
+            // for all of them
+            Backend::Remote(client) => {
+                let start = Instant::now();
+                let results = match client.simple_query(sql) {
+                    Ok(results) => results,
+                    Err(e) => return vec![(Err(e), start.elapsed())],
+                };
+                let elapsed = start.elapsed();
+                let count = results.len();
+                results
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, result)| {
+                        let time = if i + 1 == count {
+                            elapsed
+                        } else {
+                            Duration::ZERO
+                        };
+                        (result, time)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Run the query for a meta-command on the server.
+    fn remote_query(client: &mut Client, sql: &str) -> Result<QueryResult, SQLError> {
+        client
+            .simple_query(sql)?
+            .pop()
+            .unwrap_or_else(|| Ok(QueryResult::empty()))
+    }
+
+    fn list_tables(&mut self) -> Result<QueryResult, SQLError> {
+        match self {
+            Backend::Embedded(conn) => describe::list_tables(conn.session().context()),
+            Backend::Remote(client) => Self::remote_query(
+                client,
+                "SELECT n.nspname AS \"Schema\", c.relname AS \"Name\", 'table' AS \"Type\" \
+                 FROM pg_catalog.pg_class AS c \
+                 JOIN pg_catalog.pg_namespace AS n ON n.oid = c.relnamespace \
+                 WHERE c.relkind = 'r' AND n.nspname <> 'pg_catalog' ORDER BY 1, 2",
+            ),
+        }
+    }
+
+    fn list_schemas(&mut self) -> Result<QueryResult, SQLError> {
+        match self {
+            Backend::Embedded(conn) => Ok(describe::list_schemas(conn.session().context())),
+            Backend::Remote(client) => Self::remote_query(
+                client,
+                "SELECT nspname AS \"Name\" FROM pg_catalog.pg_namespace ORDER BY 1",
+            ),
+        }
+    }
+
+    /// Columns of the table and the footer, indexes and foreign keys are
+    /// not shown for the tables on a server.
+    fn describe_table(&mut self, name: &str) -> Result<(QueryResult, Vec<String>), SQLError> {
+        let client = match self {
+            Backend::Embedded(conn) => {
+                return describe::describe_table(conn.session().context(), name)
+            }
+            Backend::Remote(client) => client,
+        };
+        let quote = |name: &str| format!("'{}'", name.replace('\'', "''"));
+        let filter = match name.split_once('.') {
+            Some((schema_name, table_name)) => format!(
+                "n.nspname = {} AND c.relname = {}",
+                quote(schema_name),
+                quote(table_name)
+            ),
+            None => format!("c.relname = {}", quote(name)),
+        };
+        let columns = Self::remote_query(
+            client,
+            &format!(
+                "SELECT a.attname AS \"Column\", \
+                 pg_catalog.format_type(a.atttypid, a.atttypmod) AS \"Type\", \
+                 CASE WHEN a.attnotnull THEN 'not null' ELSE '' END AS \"Nullable\" \
+                 FROM pg_catalog.pg_attribute AS a \
+                 JOIN pg_catalog.pg_class AS c ON c.oid = a.attrelid \
+                 JOIN pg_catalog.pg_namespace AS n ON n.oid = c.relnamespace \
+                 WHERE {} AND a.attnum > 0 ORDER BY a.attnum",
+                filter
+            ),
+        )?;
+        if columns.data.is_empty() {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!("Did not find any relation named \"{}\".", name),
+            )
+            .with_code(sqlstate::UNDEFINED_TABLE));
+        }
+        Ok((columns, vec![]))
+    }
+}
+
+/// Interactive shell running the statements in a connection. Lines are
+/// accumulated until a statement is terminated by `;`, and the statements
+/// are saved to the history file.
+pub struct Repl {
+    backend: Backend,
+    /// Lines of the incomplete statement.
+    buffer: String,
+    history_path: Option<PathBuf>,
+    /// Line editor reading from the terminal, which keeps the history.
+    editor: Option<DefaultEditor>,
+    /// Set by Ctrl-C, which cancels the current input.
+    interrupted: Arc<AtomicBool>,
+    /// Print the time taken by each statement, toggled by `\timing`.
+    timing: bool,
+    pub printer: Printer,
+    /// Prompt for the input, only if it's a terminal.
+    prompts: bool,
+    /// Whether any statement has failed.
+    failed: bool,
+}
+
+impl Repl {
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            buffer: String::new(),
+            history_path: None,
+            editor: None,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            timing: false,
+            printer: Printer::default(),
+            prompts: true,
+            failed: false,
+        }
+    }
+
+    pub fn with_prompts(mut self, prompts: bool) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Load the history of the line editor from the file, and append the
+    /// executed statements to it. The file is created if it doesn't exist.
+    pub fn set_history(&mut self, path: PathBuf) -> io::Result<()> {
+        OpenOptions::new().create(true).append(true).open(&path)?;
+        self.history_path = Some(path);
+        Ok(())
+    }
+
+    /// Flag to be set on Ctrl-C, the input read so far is discarded before
+    /// the next line.
+    pub fn interrupted(&self) -> Arc<AtomicBool> {
+        self.interrupted.clone()
+    }
+
+    /// Whether any statement has failed.
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Read and execute the statements until EOF or `\q`.
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        self.prompt(output)?;
+        for line in input.lines() {
+            let line = line?;
+            if self.interrupted.swap(false, Ordering::SeqCst) {
+                self.buffer.clear();
+            }
+            if !self.handle_line(&line, output)? {
+                return Ok(());
+            }
+            self.prompt(output)?;
+        }
+        if self.prompts {
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+
+    /// Read and execute the statements from the terminal until EOF or `\q`,
+    /// the lines can be edited and the statements in the history are
+    /// recalled with the arrow keys. Ctrl-C cancels the current input.
+    pub fn run_interactive(&mut self, output: &mut impl Write) -> io::Result<()> {
+        let mut editor = DefaultEditor::new().map_err(readline_error)?;
+        if let Some(path) = &self.history_path {
+            if let Err(e) = editor.load_history(path) {
+                eprintln!(
+                    "leisql: could not load history from {}: {}",
+                    path.display(),
+                    e
+                );
+                self.history_path = None;
+            }
+        }
+        self.editor = Some(editor);
+
+        loop {
+            let prompt = if self.buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            let line = self.editor.as_mut().unwrap().readline(prompt);
+            match line {
+                Ok(line) => {
+                    if self.interrupted.swap(false, Ordering::SeqCst) {
+                        self.buffer.clear();
+                    }
+                    if !self.handle_line(&line, output)? {
+                        return Ok(());
+                    }
+                }
+                Err(ReadlineError::Interrupted) => self.buffer.clear(),
+                Err(ReadlineError::Eof) => return Ok(()),
+                Err(e) => return Err(readline_error(e)),
+            }
+        }
+    }
+
+    /// Execute the statements and meta-commands in the script, the last
+    /// statement doesn't need to end with `;`. Returns false if it's stopped
+    /// by an error or `\q`.
+    pub fn run_script(&mut self, script: &str, output: &mut impl Write) -> io::Result<bool> {
+        for line in script.lines() {
+            if !self.handle_line(line, output)? || self.failed {
+                return Ok(false);
+            }
+        }
+        if !self.buffer.trim().is_empty() {
+            self.execute_buffer(output)?;
+        }
+        self.buffer.clear();
+        Ok(!self.failed)
+    }
+
+    fn prompt(&self, output: &mut impl Write) -> io::Result<()> {
+        if !self.prompts {
+            return Ok(());
+        }
+        let prompt = if self.buffer.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        write!(output, "{}", prompt)?;
+        output.flush()
+    }
+
+    /// Handle a line of input, returns false if the shell should quit.
+    fn handle_line(&mut self, line: &str, output: &mut impl Write) -> io::Result<bool> {
+        if self.buffer.is_empty() && line.trim_start().starts_with('\\') {
+            return self.meta_command(line.trim(), output);
+        }
+        self.push_line(line, output)?;
+        Ok(true)
+    }
+
+    /// Execute the meta-command, returns false if the shell should quit.
+    fn meta_command(&mut self, line: &str, output: &mut impl Write) -> io::Result<bool> {
+        let (command, argument) = match line.split_once(char::is_whitespace) {
+            Some((command, argument)) => (command, Some(argument.trim())),
+            None => (line, None),
+        };
+        match (command, argument) {
+            ("\\q", None) => return Ok(false),
+            ("\\dt", None) | ("\\d", None) => match self.backend.list_tables() {
+                Ok(tables) if tables.data.is_empty() => {
+                    writeln!(output, "Did not find any relations.")?
+                }
+                Ok(tables) => self.printer.write_result(&tables, output)?,
+                Err(e) => self.report_error(&e, output)?,
+            },
+            ("\\d", Some(table)) => match self.backend.describe_table(table) {
+                Ok((columns, footer)) => {
+                    writeln!(output, "Table \"{}\"", table)?;
+                    format::write_table(&columns, output)?;
+                    for line in footer.iter() {
+                        writeln!(output, "{}", line)?;
+                    }
+                    writeln!(output)?;
+                }
+                Err(e) => self.report_error(&e, output)?,
+            },
+            ("\\l", None) => match self.backend.list_schemas() {
+                Ok(schemas) => self.printer.write_result(&schemas, output)?,
+                Err(e) => self.report_error(&e, output)?,
+            },
+            ("\\i", Some(path)) => match File::open(path) {
+                Ok(file) => {
+                    for line in BufReader::new(file).lines() {
+                        if !self.handle_line(&line?, output)? {
+                            return Ok(false);
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.failed = true;
+                    eprintln!("{}: {}", path, e)
+                }
+            },
+            ("\\timing", None) => {
+                self.timing = !self.timing;
+                writeln!(output, "Timing is {}.", on_off(self.timing))?;
+            }
+            ("\\pset", None) => {
+                writeln!(output, "format      {}", self.printer.format)?;
+                writeln!(output, "expanded    {}", on_off(self.printer.expanded))?;
+            }
+            ("\\pset", Some(argument)) => {
+                let (option, value) = match argument.split_once(char::is_whitespace) {
+                    Some((option, value)) => (option, Some(value.trim())),
+                    None => (argument, None),
+                };
+                match (option, value) {
+                    ("format", Some(format)) => match format.parse() {
+                        Ok(format) => {
+                            self.printer.format = format;
+                            writeln!(output, "Output format is {}.", format)?;
+                        }
+                        Err(e) => writeln!(output, "{}", e)?,
+                    },
+                    ("format", None) => {
+                        writeln!(output, "Output format is {}.", self.printer.format)?
+                    }
+                    ("expanded", value) => self.set_expanded(value, output)?,
+                    _ => writeln!(output, "\\pset: unknown option: {}", option)?,
+                }
+            }
+            ("\\x", value) => self.set_expanded(value, output)?,
+            ("\\s", None) => match &self.editor {
+                Some(editor) => {
+                    for entry in editor.history().iter() {
+                        writeln!(output, "{}", entry)?;
+                    }
+                }
+                None => writeln!(output, "history is disabled")?,
+            },
+            ("\\?", None) => {
+                writeln!(output, "\\d [TABLE]   list tables, or describe the table")?;
+                writeln!(output, "\\dt          list tables")?;
+                writeln!(output, "\\l           list schemas")?;
+                writeln!(output, "\\i FILE      execute the statements in the file")?;
+                writeln!(output, "\\timing      toggle timing of statements")?;
+                writeln!(output, "\\pset [format aligned|csv|json] [expanded on|off]")?;
+                writeln!(output, "             show or set the output format")?;
+                writeln!(output, "\\x [on|off]  toggle expanded output")?;
+                writeln!(output, "\\s           show the history of statements")?;
+                writeln!(output, "\\q           quit")?;
+                writeln!(output, "\\?           show this help")?;
+            }
+            _ => writeln!(output, "invalid command {}, try \\? for help", command)?,
+        }
+        Ok(true)
+    }
+
+    /// Set expanded output, it's toggled if the value is not specified.
+    fn set_expanded(&mut self, value: Option<&str>, output: &mut impl Write) -> io::Result<()> {
+        self.printer.expanded = match value {
+            None => !self.printer.expanded,
+            Some("on") => true,
+            Some("off") => false,
+            Some(value) => {
+                return writeln!(
+                    output,
+                    "unrecognized value \"{}\" for \"expanded\": Boolean expected",
+                    value
+                )
+            }
+        };
+        writeln!(
+            output,
+            "Expanded display is {}.",
+            on_off(self.printer.expanded)
+        )
+    }
+
+    /// Add the line to the current input, which is executed once it ends
+    /// with `;`.
+    fn push_line(&mut self, line: &str, output: &mut impl Write) -> io::Result<()> {
+        if line.trim().is_empty() && self.buffer.is_empty() {
+            return Ok(());
+        }
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(line);
+        if !self.buffer.trim_end().ends_with(';') {
+            return Ok(());
+        }
+        self.execute_buffer(output)
+    }
+
+    /// Execute the statements of the current input one by one, errors are
+    /// printed to stderr, and the rest statements are skipped once an error
+    /// occurs.
+    fn execute_buffer(&mut self, output: &mut impl Write) -> io::Result<()> {
+        let sql = std::mem::take(&mut self.buffer);
+        if let Some(editor) = &mut self.editor {
+            editor
+                .add_history_entry(sql.as_str())
+                .map_err(readline_error)?;
+            if let Some(path) = &self.history_path {
+                if let Err(e) = editor.append_history(path) {
+                    eprintln!(
+                        "leisql: could not save history to {}: {}",
+                        path.display(),
+                        e
+                    );
+                    self.history_path = None;
+                }
+            }
+        }
+        for (result, elapsed) in self.backend.execute(&sql) {
+            match result {
+                Ok(result) => self.printer.write_result(&result, output)?,
+                Err(e) => return self.report_error(&e, output),
+            }
+            if self.timing {
+                writeln!(output, "Time: {:.3} ms", elapsed.as_secs_f64() * 1000.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn report_error(&mut self, e: &SQLError, output: &mut impl Write) -> io::Result<()> {
+        self.failed = true;
+        output.flush()?;
+        self.printer.write_error(e, &mut io::stderr())
+    }
+}
+
+fn readline_error(e: ReadlineError) -> io::Error {
+    match e {
+        ReadlineError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::Other, e),
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
@@ -0,0 +1,126 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+use super::Datum;
+
+/// Parse the text representation of a one-dimensional array, e.g.
+/// `{1,"a b",NULL}`. Returns `None` if it's invalid, and the unquoted
+/// `NULL` elements are `None`.
+pub fn parse_array(text: &str) -> Option<Vec<Option<String>>> {
+    let mut chars = text.trim().chars().peekable();
+    if chars.next()? != '{' {
+        return None;
+    }
+
+    let mut elements = vec![];
+    skip_whitespace(&mut chars);
+    if chars.next_if_eq(&'}').is_none() {
+        loop {
+            skip_whitespace(&mut chars);
+            elements.push(parse_element(&mut chars)?);
+
+            skip_whitespace(&mut chars);
+            match chars.next()? {
+                ',' => continue,
+                '}' => break,
+                _ => return None,
+            }
+        }
+    }
+
+    chars.next().is_none().then_some(elements)
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_element(chars: &mut Peekable<Chars>) -> Option<Option<String>> {
+    let mut element = String::new();
+    if chars.next_if_eq(&'"').is_some() {
+        loop {
+            match chars.next()? {
+                '"' => return Some(Some(element)),
+                '\\' => element.push(chars.next()?),
+                c => element.push(c),
+            }
+        }
+    }
+
+    // Unquoted element ends with the delimiter, the trailing whitespaces are
+    // ignored unless they are escaped.
+    let mut escaped = false;
+    let mut len = 0;
+    while let Some(c) = chars.next_if(|c| !matches!(c, ',' | '}')) {
+        match c {
+            '{' | '"' => return None,
+            '\\' => {
+                element.push(chars.next()?);
+                escaped = true;
+                len = element.len();
+            }
+            c => {
+                element.push(c);
+                if !c.is_whitespace() {
+                    len = element.len();
+                }
+            }
+        }
+    }
+    element.truncate(len);
+
+    if element.is_empty() {
+        None
+    } else if !escaped && element.eq_ignore_ascii_case("NULL") {
+        Some(None)
+    } else {
+        Some(Some(element))
+    }
+}
+
+/// Text of a non-null element or value sent to clients, booleans are written
+/// as `t` and `f` like in Postgres.
+pub fn array_element_text(value: &Datum) -> String {
+    match value {
+        Datum::Boolean(v) => (if *v { "t" } else { "f" }).to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Text representation of the array, the elements are quoted if they
+/// contain special characters.
+pub struct DisplayArray<'a>(pub &'a [Datum]);
+
+impl Display for DisplayArray<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+        for (i, value) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if value.is_null() {
+                write!(f, "NULL")?;
+                continue;
+            }
+
+            let text = array_element_text(value);
+            let need_quote = text.is_empty()
+                || text.eq_ignore_ascii_case("NULL")
+                || text
+                    .chars()
+                    .any(|c| matches!(c, '{' | '}' | ',' | '"' | '\\') || c.is_whitespace());
+            if need_quote {
+                write!(f, "\"")?;
+                for c in text.chars() {
+                    if matches!(c, '"' | '\\') {
+                        write!(f, "\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "\"")?;
+            } else {
+                write!(f, "{}", text)?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
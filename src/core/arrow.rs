@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type,
+        UInt32Type, UInt64Type, UInt8Type,
+    },
+    Array, ArrayRef, BinaryArray, BooleanArray, FixedSizeBinaryArray, Float64Array, Int16Array,
+    Int32Array, Int64Array, ListArray, NullArray, RecordBatch, RecordBatchOptions, StringArray,
+};
+use arrow_buffer::{BooleanBuffer, NullBuffer, OffsetBuffer};
+use arrow_schema::{DataType, Field, Schema};
+use uuid::Uuid;
+
+use super::{sqlstate, Datum, ErrorKind, IntWidth, SQLError, Tuple, Type};
+
+/// Arrow type of the values, integers are as wide as the declared width, and
+/// UUID is stored as 16 bytes.
+pub fn arrow_type(typ: &Type, int_width: Option<IntWidth>) -> DataType {
+    match typ {
+        Type::Int => match int_width {
+            Some(IntWidth::Int2) => DataType::Int16,
+            Some(IntWidth::Int4) => DataType::Int32,
+            Some(IntWidth::Int8) | None => DataType::Int64,
+        },
+        Type::Float => DataType::Float64,
+        Type::String | Type::Json => DataType::Utf8,
+        Type::Boolean => DataType::Boolean,
+        Type::Uuid => DataType::FixedSizeBinary(16),
+        Type::Bytea => DataType::Binary,
+        Type::Array(elem_type) => DataType::List(Arc::new(Field::new(
+            "item",
+            arrow_type(elem_type, None),
+            true,
+        ))),
+        Type::Null | Type::Any | Type::Never => DataType::Null,
+    }
+}
+
+/// Convert the rows to a record batch, the columns are named and typed by
+/// `columns`.
+pub fn to_record_batch(
+    columns: &[(String, Type, Option<IntWidth>)],
+    rows: &[Tuple],
+) -> Result<RecordBatch, SQLError> {
+    let fields = columns
+        .iter()
+        .map(|(name, typ, int_width)| Field::new(name, arrow_type(typ, *int_width), true))
+        .collect::<Vec<_>>();
+    let arrays = columns
+        .iter()
+        .enumerate()
+        .map(|(index, (_, typ, int_width))| {
+            let values = rows
+                .iter()
+                .map(|row| &row.values[index])
+                .collect::<Vec<_>>();
+            to_array(typ, *int_width, &values)
+        })
+        .collect::<Vec<_>>();
+
+    // The row count is needed by the results without any column
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+    RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), arrays, &options)
+        .map_err(arrow_error)
+}
+
+fn to_array(typ: &Type, int_width: Option<IntWidth>, values: &[&Datum]) -> ArrayRef {
+    let values = values.iter().copied();
+    match typ {
+        Type::Int => {
+            let values = values.map(|datum| datum.as_int().copied());
+            match int_width {
+                // The values are checked against the width on insert
+                Some(IntWidth::Int2) => {
+                    Arc::new(values.map(|v| v.map(|v| v as i16)).collect::<Int16Array>())
+                }
+                Some(IntWidth::Int4) => {
+                    Arc::new(values.map(|v| v.map(|v| v as i32)).collect::<Int32Array>())
+                }
+                Some(IntWidth::Int8) | None => Arc::new(values.collect::<Int64Array>()),
+            }
+        }
+        Type::Float => Arc::new(
+            values
+                .map(|datum| datum.as_float().copied())
+                .collect::<Float64Array>(),
+        ),
+        Type::String => Arc::new(
+            values
+                .map(|datum| datum.as_string().map(|v| v.as_ref()))
+                .collect::<StringArray>(),
+        ),
+        Type::Json => Arc::new(
+            values
+                .map(|datum| datum.as_json().map(|v| v.to_string()))
+                .collect::<StringArray>(),
+        ),
+        Type::Boolean => Arc::new(
+            values
+                .map(|datum| datum.as_boolean().copied())
+                .collect::<BooleanArray>(),
+        ),
+        Type::Uuid => Arc::new(
+            FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                values.map(|datum| datum.as_uuid().map(|v| *v.as_bytes())),
+                16,
+            )
+            .unwrap(),
+        ),
+        Type::Bytea => Arc::new(
+            values
+                .map(|datum| datum.as_bytea().map(|v| v.as_ref()))
+                .collect::<BinaryArray>(),
+        ),
+        Type::Array(elem_type) => {
+            let mut offsets = vec![0i32];
+            let mut elements = vec![];
+            let mut valid = vec![];
+            for datum in values {
+                if let Datum::Array(values) = datum {
+                    elements.extend(values.iter());
+                }
+                offsets.push(elements.len() as i32);
+                valid.push(matches!(datum, Datum::Array(_)));
+            }
+            let field = Arc::new(Field::new("item", arrow_type(elem_type, None), true));
+            let nulls = NullBuffer::new(BooleanBuffer::collect_bool(valid.len(), |i| valid[i]));
+            Arc::new(ListArray::new(
+                field,
+                OffsetBuffer::new(offsets.into()),
+                to_array(elem_type, None, &elements),
+                Some(nulls),
+            ))
+        }
+        Type::Null | Type::Any | Type::Never => Arc::new(NullArray::new(values.len())),
+    }
+}
+
+/// The value at the index of array, which is converted to the closest type.
+pub fn datum_from_array(array: &dyn Array, index: usize) -> Result<Datum, SQLError> {
+    if array.is_null(index) {
+        return Ok(Datum::Null);
+    }
+
+    Ok(match array.data_type() {
+        DataType::Null => Datum::Null,
+        DataType::Int8 => Datum::Int(array.as_primitive::<Int8Type>().value(index) as i64),
+        DataType::Int16 => Datum::Int(array.as_primitive::<Int16Type>().value(index) as i64),
+        DataType::Int32 => Datum::Int(array.as_primitive::<Int32Type>().value(index) as i64),
+        DataType::Int64 => Datum::Int(array.as_primitive::<Int64Type>().value(index)),
+        DataType::UInt8 => Datum::Int(array.as_primitive::<UInt8Type>().value(index) as i64),
+        DataType::UInt16 => Datum::Int(array.as_primitive::<UInt16Type>().value(index) as i64),
+        DataType::UInt32 => Datum::Int(array.as_primitive::<UInt32Type>().value(index) as i64),
+        DataType::UInt64 => {
+            let v = array.as_primitive::<UInt64Type>().value(index);
+            Datum::Int(i64::try_from(v).map_err(|_| {
+                SQLError::new(ErrorKind::RuntimeError, "bigint out of range")
+                    .with_code(sqlstate::NUMERIC_VALUE_OUT_OF_RANGE)
+            })?)
+        }
+        DataType::Float32 => Datum::Float(array.as_primitive::<Float32Type>().value(index) as f64),
+        DataType::Float64 => Datum::Float(array.as_primitive::<Float64Type>().value(index)),
+        DataType::Boolean => Datum::Boolean(array.as_boolean().value(index)),
+        DataType::Utf8 => Datum::String(array.as_string::<i32>().value(index).into()),
+        DataType::LargeUtf8 => Datum::String(array.as_string::<i64>().value(index).into()),
+        DataType::Binary => Datum::Bytea(array.as_binary::<i32>().value(index).into()),
+        DataType::LargeBinary => Datum::Bytea(array.as_binary::<i64>().value(index).into()),
+        DataType::FixedSizeBinary(16) => Datum::Uuid(Uuid::from_bytes(
+            fixed_size_binary(array).value(index).try_into().unwrap(),
+        )),
+        DataType::FixedSizeBinary(_) => Datum::Bytea(fixed_size_binary(array).value(index).into()),
+        DataType::List(_) => {
+            let values = array.as_list::<i32>().value(index);
+            Datum::Array(
+                (0..values.len())
+                    .map(|i| datum_from_array(values.as_ref(), i))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        DataType::LargeList(_) => {
+            let values = array.as_list::<i64>().value(index);
+            Datum::Array(
+                (0..values.len())
+                    .map(|i| datum_from_array(values.as_ref(), i))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        data_type => {
+            return Err(SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("Arrow type {} is not supported", data_type),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+        }
+    })
+}
+
+fn fixed_size_binary(array: &dyn Array) -> &FixedSizeBinaryArray {
+    array
+        .as_any()
+        .downcast_ref::<FixedSizeBinaryArray>()
+        .unwrap()
+}
+
+pub fn arrow_error(e: impl ToString) -> SQLError {
+    SQLError::new(ErrorKind::RuntimeError, e.to_string()).with_code(sqlstate::DATA_EXCEPTION)
+}
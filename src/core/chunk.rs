@@ -0,0 +1,49 @@
+use super::Tuple;
+
+/// Maximum number of tuples in a chunk.
+pub const CHUNK_SIZE: usize = 1024;
+
+/// A batch of tuples, the unit of data moved between the vectorized
+/// executors, so the per-tuple overhead of pulling is amortized.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub tuples: Vec<Tuple>,
+}
+
+impl Chunk {
+    pub fn new(tuples: Vec<Tuple>) -> Self {
+        Self { tuples }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            tuples: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tuples.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.tuples.len() >= CHUNK_SIZE
+    }
+
+    pub fn push(&mut self, tuple: Tuple) {
+        self.tuples.push(tuple);
+    }
+
+    /// `None` for an empty chunk, since the executors never emit them.
+    pub fn non_empty(self) -> Option<Self> {
+        (!self.is_empty()).then_some(self)
+    }
+}
+
+impl IntoIterator for Chunk {
+    type Item = Tuple;
+    type IntoIter = std::vec::IntoIter<Tuple>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tuples.into_iter()
+    }
+}
@@ -1,17 +1,26 @@
-use std::{fmt::Display, hash::Hash};
+use std::{cmp::Ordering, fmt::Display, hash::Hash, sync::Arc};
 
 use enum_as_inner::EnumAsInner;
 use sqlparser::ast;
+use uuid::Uuid;
 
-use super::{ErrorKind, SQLError, Type};
+use super::{
+    parse_array, parse_bytea, parse_hex, sqlstate, DisplayArray, DisplayBytea, ErrorKind, Json,
+    SQLError, Type,
+};
 
 /// A single datum value.
 #[derive(Debug, Clone, EnumAsInner)]
 pub enum Datum {
     Int(i64),
     Float(f64),
-    String(String),
+    String(Arc<str>),
     Boolean(bool),
+    Uuid(Uuid),
+    Json(Json),
+    Bytea(Arc<[u8]>),
+    /// Elements of an one-dimensional array, which are of the same type.
+    Array(Vec<Datum>),
 
     Null,
 }
@@ -23,6 +32,10 @@ impl Display for Datum {
             Datum::Float(v) => write!(f, "{}", v),
             Datum::String(v) => write!(f, "{}", v),
             Datum::Boolean(v) => write!(f, "{}", if *v { "TRUE" } else { "FALSE" }),
+            Datum::Uuid(v) => write!(f, "{}", v),
+            Datum::Json(v) => write!(f, "{}", v),
+            Datum::Bytea(v) => write!(f, "{}", DisplayBytea(v)),
+            Datum::Array(v) => write!(f, "{}", DisplayArray(v)),
             Datum::Null => write!(f, "NULL"),
         }
     }
@@ -34,13 +47,39 @@ impl TryFrom<&ast::Value> for Datum {
     fn try_from(value: &ast::Value) -> Result<Self, Self::Error> {
         match value {
             ast::Value::Number(v, _) => {
-                Ok(Datum::Int(v.parse().map_err(|e| {
+                // Numbers that cannot be represented as integer are parsed as float
+                if let Ok(v) = v.parse() {
+                    return Ok(Datum::Int(v));
+                }
+
+                Ok(Datum::Float(v.parse().map_err(|e| {
                     SQLError::new(ErrorKind::ParseError, format!("{}", e))
+                        .with_code(sqlstate::INVALID_TEXT_REPRESENTATION)
                 })?))
             }
-            ast::Value::SingleQuotedString(v) => Ok(Datum::String(v.to_string())),
+            // Escapes are already processed by the tokenizer
+            ast::Value::SingleQuotedString(v) | ast::Value::EscapedStringLiteral(v) => {
+                Ok(Datum::String(v.as_str().into()))
+            }
+            ast::Value::DollarQuotedString(v) => Ok(Datum::String(v.value.as_str().into())),
+            ast::Value::HexStringLiteral(v) => match parse_hex(v) {
+                Some(bytes) => Ok(Datum::Bytea(bytes.into())),
+                None => {
+                    let message = match v.chars().find(|c| !c.is_ascii_hexdigit()) {
+                        Some(c) => format!("\"{}\" is not a valid hexadecimal digit", c),
+                        None => "invalid hexadecimal data: odd number of digits".to_string(),
+                    };
+                    Err(SQLError::new(ErrorKind::ParseError, message)
+                        .with_code(sqlstate::INVALID_TEXT_REPRESENTATION))
+                }
+            },
+            ast::Value::Boolean(v) => Ok(Datum::Boolean(*v)),
             ast::Value::Null => Ok(Datum::Null),
-            _ => unimplemented!(),
+            _ => Err(SQLError::new(
+                ErrorKind::ParseError,
+                format!("literal is not supported: {}", value),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
         }
     }
 }
@@ -52,6 +91,16 @@ impl Datum {
             Datum::Float(_) => Type::Float,
             Datum::String(_) => Type::String,
             Datum::Boolean(_) => Type::Boolean,
+            Datum::Uuid(_) => Type::Uuid,
+            Datum::Json(_) => Type::Json,
+            Datum::Bytea(_) => Type::Bytea,
+            // The element type of an array without non-NULL elements is unknown
+            Datum::Array(v) => Type::Array(Box::new(
+                v.iter()
+                    .map(Datum::typ)
+                    .find(|typ| typ != &Type::Null)
+                    .unwrap_or(Type::Null),
+            )),
             Datum::Null => Type::Null,
         }
     }
@@ -59,13 +108,15 @@ impl Datum {
     pub fn cast(&self, dest_typ: &Type) -> Self {
         match (self, dest_typ) {
             (Datum::Int(v), Type::Int) => Datum::Int(*v),
-            (Datum::Int(v), Type::String) => Datum::String(v.to_string()),
+            (Datum::Int(v), Type::String) => Datum::String(v.to_string().into()),
             (Datum::Int(v), Type::Boolean) => Datum::Boolean(*v != 0),
             (Datum::Int(v), Type::Float) => Datum::Float(*v as f64),
 
             (Datum::String(v), Type::Int) => v.parse().map_or(Datum::Null, Datum::Int),
             (Datum::String(v), Type::Float) => v.parse().map_or(Datum::Null, Datum::Float),
-            (Datum::String(v), Type::String) => Datum::String(v.to_string()),
+            (Datum::String(v), Type::String) => Datum::String(v.clone()),
+            (Datum::String(v), Type::Uuid) => Uuid::parse_str(v).map_or(Datum::Null, Datum::Uuid),
+            (Datum::String(v), Type::Json) => Json::parse(v).map_or(Datum::Null, Datum::Json),
             (Datum::String(v), Type::Boolean) => {
                 let v = v.to_lowercase();
                 if matches!(v.as_str(), "true" | "t") {
@@ -77,18 +128,73 @@ impl Datum {
                 }
             }
 
-            (Datum::Null, _) => self.clone(),
+            (Datum::Null, _) | (_, Type::Any) => self.clone(),
 
             (Datum::Boolean(v), Type::Int) => Datum::Int(if *v { 1 } else { 0 }),
             (Datum::Boolean(_v), Type::Float) => Datum::Null,
-            (Datum::Boolean(_v), Type::String) => Datum::String(self.to_string()),
+            (Datum::Boolean(_v), Type::String) => Datum::String(self.to_string().into()),
             (Datum::Boolean(_), Type::Boolean) => self.clone(),
 
-            (Datum::Float(v), Type::Int) => Datum::Int(*v as i64),
+            // Rounded half to even like PostgreSQL, NULL if out of range
+            (Datum::Float(v), Type::Int) => {
+                let rounded = v.round();
+                let rounded = if (rounded - v).abs() == 0.5 {
+                    (v / 2.0).round() * 2.0
+                } else {
+                    rounded
+                };
+                if rounded >= -(i64::MAX as f64) - 1.0 && rounded < i64::MAX as f64 {
+                    Datum::Int(rounded as i64)
+                } else {
+                    Datum::Null
+                }
+            }
             (Datum::Float(_), Type::Float) => self.clone(),
-            (Datum::Float(v), Type::String) => Datum::String(v.to_string()),
+            (Datum::Float(v), Type::String) => Datum::String(v.to_string().into()),
             (Datum::Float(v), Type::Boolean) => Datum::Boolean(*v != 0.0),
 
+            (Datum::Uuid(_), Type::Uuid) => self.clone(),
+            (Datum::Uuid(v), Type::String) => Datum::String(v.to_string().into()),
+            // UUID cannot be cast from or to other types
+            (Datum::Uuid(_), _) | (_, Type::Uuid) => Datum::Null,
+
+            (Datum::Json(_), Type::Json) => self.clone(),
+            (Datum::Json(v), Type::String) => Datum::String(v.to_string().into()),
+            // JSON can only be cast from or to string
+            (Datum::Json(_), _) | (_, Type::Json) => Datum::Null,
+
+            (Datum::Bytea(_), Type::Bytea) => self.clone(),
+            (Datum::Bytea(_), Type::String) => Datum::String(self.to_string().into()),
+            (Datum::String(v), Type::Bytea) => {
+                parse_bytea(v).map_or(Datum::Null, |bytes| Datum::Bytea(bytes.into()))
+            }
+            // Binary strings can only be cast from or to string
+            (Datum::Bytea(_), _) | (_, Type::Bytea) => Datum::Null,
+
+            (Datum::Array(v), Type::Array(elem_type)) => match elem_type.as_ref() {
+                Type::Null | Type::Never => self.clone(),
+                elem_type => Datum::Array(v.iter().map(|value| value.cast(elem_type)).collect()),
+            },
+            (Datum::Array(_), Type::String) => Datum::String(self.to_string().into()),
+            (Datum::String(v), Type::Array(elem_type)) => {
+                // NULL if any element is invalid
+                let values = parse_array(v).and_then(|elements| {
+                    elements
+                        .into_iter()
+                        .map(|element| match element {
+                            Some(element) => match Datum::String(element.into()).cast(elem_type) {
+                                Datum::Null => None,
+                                value => Some(value),
+                            },
+                            None => Some(Datum::Null),
+                        })
+                        .collect::<Option<Vec<_>>>()
+                });
+                values.map_or(Datum::Null, Datum::Array)
+            }
+            // Arrays can only be cast from or to string and arrays
+            (Datum::Array(_), _) | (_, Type::Array(_)) => Datum::Null,
+
             _ => unreachable!(),
         }
     }
@@ -101,6 +207,10 @@ impl Hash for Datum {
             Datum::Float(v) => v.to_bits().hash(state),
             Datum::String(v) => v.hash(state),
             Datum::Boolean(v) => v.hash(state),
+            Datum::Uuid(v) => v.hash(state),
+            Datum::Json(v) => v.hash(state),
+            Datum::Bytea(v) => v.hash(state),
+            Datum::Array(v) => v.hash(state),
             // TODO: maybe we should use a different hash for null so
             // that it doesn't collide with other values
             Datum::Null => 0.hash(state),
@@ -115,6 +225,10 @@ impl PartialEq for Datum {
             (Self::Float(l0), Self::Float(r0)) => l0.to_bits() == r0.to_bits(),
             (Self::String(l0), Self::String(r0)) => l0 == r0,
             (Self::Boolean(l0), Self::Boolean(r0)) => l0 == r0,
+            (Self::Uuid(l0), Self::Uuid(r0)) => l0 == r0,
+            (Self::Json(l0), Self::Json(r0)) => l0 == r0,
+            (Self::Bytea(l0), Self::Bytea(r0)) => l0 == r0,
+            (Self::Array(l0), Self::Array(r0)) => l0 == r0,
             (Self::Null, Self::Null) => true,
             _ => false,
         }
@@ -124,3 +238,78 @@ impl PartialEq for Datum {
 impl Eq for Datum {
     fn assert_receiver_is_total_eq(&self) {}
 }
+
+impl PartialOrd for Datum {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Total order of datums, used for sorting.
+///
+/// NULL is greater than any other value, which follows the default
+/// `NULLS LAST` behavior of ascending order in Postgres.
+impl Ord for Datum {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Int(l), Self::Int(r)) => l.cmp(r),
+            (Self::Float(l), Self::Float(r)) => l.total_cmp(r),
+            (Self::String(l), Self::String(r)) => l.cmp(r),
+            (Self::Boolean(l), Self::Boolean(r)) => l.cmp(r),
+            (Self::Uuid(l), Self::Uuid(r)) => l.cmp(r),
+            (Self::Json(l), Self::Json(r)) => l.cmp(r),
+            (Self::Bytea(l), Self::Bytea(r)) => l.cmp(r),
+            (Self::Array(l), Self::Array(r)) => l.cmp(r),
+            (Self::Null, Self::Null) => Ordering::Equal,
+            (Self::Null, _) => Ordering::Greater,
+            (_, Self::Null) => Ordering::Less,
+
+            // Numbers of different types are compared by value, the type is
+            // used to break the tie so that the order is consistent with `Eq`.
+            (Self::Int(l), Self::Float(r)) => (*l as f64).total_cmp(r).then(Ordering::Less),
+            (Self::Float(l), Self::Int(r)) => l.total_cmp(&(*r as f64)).then(Ordering::Greater),
+
+            // Values of other different types are compared by the type.
+            _ => self.type_order().cmp(&other.type_order()),
+        }
+    }
+}
+
+/// Direction of a sort key, and the placement of NULLs in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortOrder {
+    pub asc: bool,
+    pub nulls_first: bool,
+}
+
+impl SortOrder {
+    /// Compare the datums in the total order of `Datum`, with NULLs placed
+    /// regardless of the direction.
+    pub fn compare(&self, left: &Datum, right: &Datum) -> Ordering {
+        match (left.is_null(), right.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if self.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if self.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if self.asc => left.cmp(right),
+            (false, false) => right.cmp(left),
+        }
+    }
+}
+
+impl Datum {
+    fn type_order(&self) -> u8 {
+        match self {
+            Datum::Boolean(_) => 0,
+            Datum::Int(_) => 1,
+            Datum::Float(_) => 2,
+            Datum::String(_) => 3,
+            Datum::Uuid(_) => 4,
+            Datum::Json(_) => 5,
+            Datum::Bytea(_) => 6,
+            Datum::Array(_) => 7,
+            Datum::Null => 8,
+        }
+    }
+}
@@ -4,6 +4,78 @@ use std::{error::Error, fmt::Display};
 pub struct SQLError {
     pub kind: ErrorKind,
     pub message: String,
+    /// SQLSTATE code of the error, the default code of `kind` is used if not set.
+    pub code: Option<&'static str>,
+    /// Secondary message with more details about the error.
+    pub detail: Option<String>,
+    /// Suggestion about how to fix the error.
+    pub hint: Option<String>,
+}
+
+/// SQLSTATE codes of Postgres, see <https://www.postgresql.org/docs/current/errcodes-appendix.html>.
+#[allow(dead_code)]
+pub mod sqlstate {
+    pub const FEATURE_NOT_SUPPORTED: &str = "0A000";
+    pub const PROTOCOL_VIOLATION: &str = "08P01";
+    pub const CARDINALITY_VIOLATION: &str = "21000";
+    pub const DATA_EXCEPTION: &str = "22000";
+    pub const NUMERIC_VALUE_OUT_OF_RANGE: &str = "22003";
+    pub const NULL_VALUE_NOT_ALLOWED: &str = "22004";
+    pub const SEQUENCE_GENERATOR_LIMIT_EXCEEDED: &str = "2200H";
+    pub const DIVISION_BY_ZERO: &str = "22012";
+    pub const INVALID_PRECEDING_OR_FOLLOWING_SIZE: &str = "22013";
+    pub const INVALID_REGULAR_EXPRESSION: &str = "2201B";
+    pub const INVALID_ROW_COUNT_IN_LIMIT_CLAUSE: &str = "2201W";
+    pub const INVALID_ROW_COUNT_IN_RESULT_OFFSET_CLAUSE: &str = "2201X";
+    pub const INVALID_PARAMETER_VALUE: &str = "22023";
+    pub const INVALID_ESCAPE_SEQUENCE: &str = "22025";
+    pub const INVALID_TEXT_REPRESENTATION: &str = "22P02";
+    pub const INVALID_BINARY_REPRESENTATION: &str = "22P03";
+    pub const BAD_COPY_FILE_FORMAT: &str = "22P04";
+    pub const NOT_NULL_VIOLATION: &str = "23502";
+    pub const FOREIGN_KEY_VIOLATION: &str = "23503";
+    pub const UNIQUE_VIOLATION: &str = "23505";
+    pub const INVALID_SQL_STATEMENT_NAME: &str = "26000";
+    pub const INVALID_PASSWORD: &str = "28P01";
+    pub const DEPENDENT_OBJECTS_STILL_EXIST: &str = "2BP01";
+    pub const INVALID_CURSOR_NAME: &str = "34000";
+    pub const EXTERNAL_ROUTINE_EXCEPTION: &str = "38000";
+    pub const INVALID_CATALOG_NAME: &str = "3D000";
+    pub const INVALID_SCHEMA_NAME: &str = "3F000";
+    pub const SYNTAX_ERROR_OR_ACCESS_RULE_VIOLATION: &str = "42000";
+    pub const INSUFFICIENT_PRIVILEGE: &str = "42501";
+    pub const SYNTAX_ERROR: &str = "42601";
+    pub const DUPLICATE_COLUMN: &str = "42701";
+    pub const AMBIGUOUS_COLUMN: &str = "42702";
+    pub const UNDEFINED_COLUMN: &str = "42703";
+    pub const UNDEFINED_OBJECT: &str = "42704";
+    pub const DUPLICATE_FUNCTION: &str = "42723";
+    pub const GROUPING_ERROR: &str = "42803";
+    pub const DATATYPE_MISMATCH: &str = "42804";
+    pub const WRONG_OBJECT_TYPE: &str = "42809";
+    pub const INVALID_FOREIGN_KEY: &str = "42830";
+    pub const CANNOT_COERCE: &str = "42846";
+    pub const UNDEFINED_FUNCTION: &str = "42883";
+    pub const UNDEFINED_PARAMETER: &str = "42P02";
+    pub const DUPLICATE_DATABASE: &str = "42P04";
+    pub const DUPLICATE_SCHEMA: &str = "42P06";
+    pub const DUPLICATE_TABLE: &str = "42P07";
+    pub const INVALID_COLUMN_REFERENCE: &str = "42P10";
+    pub const INVALID_FUNCTION_DEFINITION: &str = "42P13";
+    pub const UNDEFINED_TABLE: &str = "42P01";
+    pub const INVALID_TABLE_DEFINITION: &str = "42P16";
+    pub const WINDOWING_ERROR: &str = "42P20";
+    pub const PROGRAM_LIMIT_EXCEEDED: &str = "54000";
+    pub const OBJECT_NOT_IN_PREREQUISITE_STATE: &str = "55000";
+    pub const OBJECT_IN_USE: &str = "55006";
+    pub const CANT_CHANGE_RUNTIME_PARAM: &str = "55P02";
+    pub const QUERY_CANCELED: &str = "57014";
+    pub const ADMIN_SHUTDOWN: &str = "57P01";
+    pub const IO_ERROR: &str = "58030";
+    pub const UNDEFINED_FILE: &str = "58P01";
+    pub const LOCK_FILE_EXISTS: &str = "F0001";
+    pub const INTERNAL_ERROR: &str = "XX000";
+    pub const DATA_CORRUPTED: &str = "XX001";
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -14,11 +86,28 @@ pub enum ErrorKind {
     CatalogError,
     TypeError,
     RuntimeError,
+    StorageError,
     UnknownError,
 }
 
 impl Error for SQLError {}
 
+impl ErrorKind {
+    /// Default SQLSTATE code of the error kind.
+    pub fn sqlstate(&self) -> &'static str {
+        match self {
+            ErrorKind::ParseError => sqlstate::SYNTAX_ERROR,
+            ErrorKind::PlannerError | ErrorKind::CatalogError => {
+                sqlstate::SYNTAX_ERROR_OR_ACCESS_RULE_VIOLATION
+            }
+            ErrorKind::TypeError => sqlstate::DATATYPE_MISMATCH,
+            ErrorKind::RuntimeError => sqlstate::DATA_EXCEPTION,
+            ErrorKind::StorageError => sqlstate::IO_ERROR,
+            ErrorKind::UnknownError => sqlstate::INTERNAL_ERROR,
+        }
+    }
+}
+
 impl Display for ErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -27,6 +116,7 @@ impl Display for ErrorKind {
             ErrorKind::CatalogError => write!(f, "Catalog Error"),
             ErrorKind::TypeError => write!(f, "Type Error"),
             ErrorKind::RuntimeError => write!(f, "Runtime Error"),
+            ErrorKind::StorageError => write!(f, "Storage Error"),
             ErrorKind::UnknownError => write!(f, "Unknown Error"),
         }
     }
@@ -43,6 +133,29 @@ impl SQLError {
         Self {
             kind,
             message: message.as_ref().to_string(),
+            code: None,
+            detail: None,
+            hint: None,
         }
     }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl AsRef<str>) -> Self {
+        self.detail = Some(detail.as_ref().to_string());
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl AsRef<str>) -> Self {
+        self.hint = Some(hint.as_ref().to_string());
+        self
+    }
+
+    /// SQLSTATE code of the error.
+    pub fn sqlstate(&self) -> &'static str {
+        self.code.unwrap_or_else(|| self.kind.sqlstate())
+    }
 }
@@ -0,0 +1,305 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+use super::Datum;
+
+/// Nesting depth limit of JSON values, to avoid stack overflow while parsing.
+const MAX_DEPTH: usize = 512;
+
+/// A parsed JSON value.
+///
+/// Numbers keep their original text so that they are never rounded, and
+/// objects keep the order of keys, with the last value of duplicated keys.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Json {
+    Null,
+    Boolean(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parse JSON text, returns `None` if it's invalid.
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+
+        chars.peek().is_none().then_some(value)
+    }
+
+    /// Value of the key if it's an object.
+    pub fn field(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Element at the index if it's an array, negative index counts from the end.
+    pub fn element(&self, index: i64) -> Option<&Json> {
+        match self {
+            Json::Array(elements) => {
+                let index = if index < 0 {
+                    elements.len().checked_sub(index.unsigned_abs() as usize)?
+                } else {
+                    index as usize
+                };
+                elements.get(index)
+            }
+            _ => None,
+        }
+    }
+
+    /// Follow the path of object keys or array indexes.
+    pub fn path(&self, path: &[String]) -> Option<&Json> {
+        path.iter().try_fold(self, |value, step| match value {
+            Json::Array(_) => value.element(step.parse().ok()?),
+            _ => value.field(step),
+        })
+    }
+
+    /// Text of the value, strings are unquoted and JSON null is `None`.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            Json::Null => None,
+            Json::String(v) => Some(v.clone()),
+            _ => Some(self.to_string()),
+        }
+    }
+}
+
+impl From<&Datum> for Json {
+    fn from(value: &Datum) -> Self {
+        match value {
+            Datum::Int(v) => Json::Number(v.to_string()),
+            // JSON has no representation of infinity and NaN
+            Datum::Float(v) if v.is_finite() => Json::Number(v.to_string()),
+            Datum::Float(v) => Json::String(v.to_string()),
+            Datum::String(v) => Json::String(v.to_string()),
+            Datum::Boolean(v) => Json::Boolean(*v),
+            Datum::Uuid(v) => Json::String(v.to_string()),
+            Datum::Json(v) => v.clone(),
+            Datum::Bytea(_) => Json::String(value.to_string()),
+            Datum::Array(v) => Json::Array(v.iter().map(Json::from).collect()),
+            Datum::Null => Json::Null,
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Boolean(v) => write!(f, "{}", v),
+            Json::Number(v) => write!(f, "{}", v),
+            Json::String(v) => write_string(f, v),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ": {}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, value: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            '\u{8}' => write!(f, "\\b")?,
+            '\u{c}' => write!(f, "\\f")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars
+        .next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        .is_some()
+    {}
+}
+
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Option<Json> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
+    skip_whitespace(chars);
+    match *chars.peek()? {
+        '{' => {
+            chars.next();
+            let mut fields: Vec<(String, Json)> = vec![];
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Some(Json::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                if chars.next()? != '"' {
+                    return None;
+                }
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next()? != ':' {
+                    return None;
+                }
+                let value = parse_value(chars, depth + 1)?;
+                match fields.iter_mut().find(|(k, _)| *k == key) {
+                    Some(field) => field.1 = value,
+                    None => fields.push((key, value)),
+                }
+
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => continue,
+                    '}' => return Some(Json::Object(fields)),
+                    _ => return None,
+                }
+            }
+        }
+        '[' => {
+            chars.next();
+            let mut elements = vec![];
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Some(Json::Array(elements));
+            }
+            loop {
+                elements.push(parse_value(chars, depth + 1)?);
+
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => continue,
+                    ']' => return Some(Json::Array(elements)),
+                    _ => return None,
+                }
+            }
+        }
+        '"' => {
+            chars.next();
+            parse_string(chars).map(Json::String)
+        }
+        '-' | '0'..='9' => parse_number(chars).map(Json::Number),
+        _ => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+                word.push(c);
+            }
+            match word.as_str() {
+                "null" => Some(Json::Null),
+                "true" => Some(Json::Boolean(true)),
+                "false" => Some(Json::Boolean(false)),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Parse the rest of a string after the opening quote.
+fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                '"' => value.push('"'),
+                '\\' => value.push('\\'),
+                '/' => value.push('/'),
+                'b' => value.push('\u{8}'),
+                'f' => value.push('\u{c}'),
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                'u' => {
+                    let high = parse_hex4(chars)?;
+                    let code = if (0xD800..0xDC00).contains(&high) {
+                        // Surrogate pair
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = parse_hex4(chars)?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return None;
+                        }
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    } else {
+                        high
+                    };
+                    value.push(char::from_u32(code)?);
+                }
+                _ => return None,
+            },
+            c if c.is_control() => return None,
+            c => value.push(c),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Option<u32> {
+    (0..4).try_fold(0, |code, _| Some(code * 16 + chars.next()?.to_digit(16)?))
+}
+
+/// Number in form of `-?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?`.
+fn parse_number(chars: &mut Peekable<Chars>) -> Option<String> {
+    fn take_digits(chars: &mut Peekable<Chars>, number: &mut String) -> bool {
+        let len = number.len();
+        while let Some(c) = chars.next_if(char::is_ascii_digit) {
+            number.push(c);
+        }
+        number.len() > len
+    }
+
+    let mut number = String::new();
+
+    if let Some(c) = chars.next_if_eq(&'-') {
+        number.push(c);
+    }
+    if let Some(c) = chars.next_if_eq(&'0') {
+        number.push(c);
+    } else if !take_digits(chars, &mut number) {
+        return None;
+    }
+    if let Some(c) = chars.next_if_eq(&'.') {
+        number.push(c);
+        if !take_digits(chars, &mut number) {
+            return None;
+        }
+    }
+    if let Some(c) = chars.next_if(|c| matches!(c, 'e' | 'E')) {
+        number.push(c);
+        if let Some(c) = chars.next_if(|c| matches!(c, '+' | '-')) {
+            number.push(c);
+        }
+        if !take_digits(chars, &mut number) {
+            return None;
+        }
+    }
+
+    Some(number)
+}
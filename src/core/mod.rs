@@ -1,9 +1,19 @@
+pub mod array;
+pub mod arrow;
+pub mod bytea;
+pub mod chunk;
 pub mod datum;
 pub mod error;
+pub mod json;
 pub mod tuple;
 pub mod types;
 
+pub use array::*;
+pub use arrow::*;
+pub use bytea::*;
+pub use chunk::*;
 pub use datum::*;
 pub use error::*;
+pub use json::*;
 pub use tuple::*;
 pub use types::*;
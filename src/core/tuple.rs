@@ -25,10 +25,19 @@ impl Tuple {
         self.values.get(index).cloned()
     }
 
-    pub fn project(&self, indices: &[usize]) -> Tuple {
+    /// Keep the values at the indices, which are moved out of the tuple, only
+    /// the ones projected more than once are cloned.
+    pub fn project(mut self, indices: &[usize]) -> Tuple {
         let values = indices
             .iter()
-            .map(|i| self.values[*i].clone())
+            .enumerate()
+            .map(|(position, i)| {
+                if indices[position + 1..].contains(i) {
+                    self.values[*i].clone()
+                } else {
+                    std::mem::replace(&mut self.values[*i], Datum::Null)
+                }
+            })
             .collect::<Vec<_>>();
 
         Tuple::new(values)
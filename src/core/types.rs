@@ -1,6 +1,6 @@
-use sqlparser::ast::DataType;
+use sqlparser::ast::{ArrayElemTypeDef, DataType, ObjectName};
 
-use super::{ErrorKind, SQLError};
+use super::{sqlstate, Datum, ErrorKind, SQLError};
 
 #[allow(dead_code)]
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -9,6 +9,11 @@ pub enum Type {
     Float,
     String,
     Boolean,
+    Uuid,
+    Json,
+    Bytea,
+    /// One-dimensional array of the element type.
+    Array(Box<Type>),
 
     Null,
 
@@ -31,14 +36,141 @@ impl TryFrom<&DataType> for Type {
             | DataType::SmallInt(_)
             | DataType::TinyInt(_) => Ok(Type::Int),
 
-            DataType::Varchar(_) | DataType::Char(_) | DataType::String => Ok(Type::String),
+            DataType::Varchar(_) | DataType::Char(_) | DataType::String(_) | DataType::Text => {
+                Ok(Type::String)
+            }
 
             DataType::Boolean => Ok(Type::Boolean),
 
-            _ => Err(SQLError::new(
-                ErrorKind::TypeError,
-                format!("Unknown data type: {:?}", value),
-            )),
+            DataType::Uuid => Ok(Type::Uuid),
+
+            DataType::JSON => Ok(Type::Json),
+
+            DataType::Bytea => Ok(Type::Bytea),
+            // Names of Postgres types, which can be qualified by `pg_catalog`
+            DataType::Custom(name, modifiers) if modifiers.is_empty() => {
+                let type_name = pg_type_name(name).ok_or_else(|| unknown_data_type(value))?;
+                match type_name.as_str() {
+                    "oid" | "int2" | "int4" | "int8" => Ok(Type::Int),
+                    "text" | "name" | "char" | "bpchar" | "varchar" => Ok(Type::String),
+                    "bool" => Ok(Type::Boolean),
+                    "jsonb" => Ok(Type::Json),
+                    "bytea" => Ok(Type::Bytea),
+                    _ => Err(unknown_data_type(value)),
+                }
+            }
+
+            // Arrays of multiple dimensions are the same as one dimension, as
+            // the declared dimensions are not enforced by Postgres either.
+            DataType::Array(
+                ArrayElemTypeDef::SquareBracket(elem_type)
+                | ArrayElemTypeDef::AngleBracket(elem_type),
+            ) => match Type::try_from(elem_type.as_ref())? {
+                Type::Array(elem_type) => Ok(Type::Array(elem_type)),
+                elem_type => Ok(Type::Array(Box::new(elem_type))),
+            },
+
+            _ => Err(unknown_data_type(value)),
+        }
+    }
+}
+
+/// Name of Postgres type in lower case, which can be qualified by
+/// `pg_catalog`.
+fn pg_type_name(name: &ObjectName) -> Option<String> {
+    let type_name = match name.0.as_slice() {
+        [schema, type_name] if schema.value.eq_ignore_ascii_case("pg_catalog") => type_name,
+        [type_name] => type_name,
+        _ => return None,
+    };
+    Some(type_name.value.to_lowercase())
+}
+
+fn unknown_data_type(data_type: &DataType) -> SQLError {
+    SQLError::new(
+        ErrorKind::TypeError,
+        format!("Unknown data type: {:?}", data_type),
+    )
+    .with_code(sqlstate::UNDEFINED_OBJECT)
+}
+
+impl Type {
+    /// If the type contains `Any`, which is only used by the argument
+    /// types of polymorphic functions.
+    pub fn is_polymorphic(&self) -> bool {
+        match self {
+            Type::Any => true,
+            Type::Array(elem_type) => elem_type.is_polymorphic(),
+            _ => false,
+        }
+    }
+
+    /// If the type is an instance of the given type, where `Any` matches
+    /// any type, e.g. `Array(Int)` is an instance of `Array(Any)`.
+    pub fn is_instance_of(&self, typ: &Type) -> bool {
+        match (self, typ) {
+            (_, Type::Any) => true,
+            (Type::Array(elem_type), Type::Array(typ)) => elem_type.is_instance_of(typ),
+            _ => self == typ,
+        }
+    }
+}
+
+/// Declared width of integer column. The values are always 64-bit integers,
+/// and the ones of narrower widths are range checked when they are written
+/// to the column, cast to the type, or computed by arithmetic of the type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IntWidth {
+    /// `smallint`
+    Int2,
+    /// `integer`
+    Int4,
+    /// `bigint`
+    Int8,
+}
+
+impl IntWidth {
+    /// Width of the integer data type, `None` if it's not an integer type.
+    pub fn from_data_type(data_type: &DataType) -> Option<Self> {
+        match data_type {
+            DataType::SmallInt(_) | DataType::TinyInt(_) => Some(IntWidth::Int2),
+            DataType::Int(_) | DataType::Integer(_) => Some(IntWidth::Int4),
+            DataType::BigInt(_) => Some(IntWidth::Int8),
+            DataType::Custom(name, modifiers) if modifiers.is_empty() => {
+                match pg_type_name(name)?.as_str() {
+                    "int2" => Some(IntWidth::Int2),
+                    "int4" => Some(IntWidth::Int4),
+                    "int8" => Some(IntWidth::Int8),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// SQL name of the type, e.g. `smallint`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntWidth::Int2 => "smallint",
+            IntWidth::Int4 => "integer",
+            IntWidth::Int8 => "bigint",
+        }
+    }
+
+    /// Check that the value fits in the width.
+    pub fn check(&self, value: &Datum) -> Result<(), SQLError> {
+        let fits = match (self, value) {
+            (IntWidth::Int2, Datum::Int(v)) => i16::try_from(*v).is_ok(),
+            (IntWidth::Int4, Datum::Int(v)) => i32::try_from(*v).is_ok(),
+            _ => true,
+        };
+        if !fits {
+            return Err(SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("{} out of range", self.name()),
+            )
+            .with_code(sqlstate::NUMERIC_VALUE_OUT_OF_RANGE));
         }
+        Ok(())
     }
 }
@@ -0,0 +1,238 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    catalog::Catalog,
+    core::{Datum, SQLError, Type},
+    sql::{
+        expression::function::IntoEvalResult,
+        runtime::write_parquet,
+        session::{
+            activity::ActivityRegistry, context::QueryContext, result::QueryResult,
+            settings::Settings, SQLKind,
+        },
+        Session,
+    },
+    storage::{wal::SyncPolicy, StorageManager},
+};
+
+/// An embedded database, all the sessions created from it share the same
+/// catalog and storage.
+#[derive(Clone)]
+pub struct Database {
+    catalog: Arc<RwLock<Catalog>>,
+    storage_mgr: Arc<StorageManager>,
+    /// Initial run-time parameters of the sessions.
+    settings: Settings,
+    activity: Arc<ActivityRegistry>,
+    /// Directory of the files `COPY` can read and write.
+    copy_directory: Option<PathBuf>,
+    /// Directory of the WebAssembly modules of functions.
+    function_directory: Option<PathBuf>,
+}
+
+impl Database {
+    /// Open the database persisted in the data directory, the WAL is synced
+    /// on every change.
+    pub fn open(data_dir: impl AsRef<Path>) -> Result<Self, SQLError> {
+        Self::open_with_sync_policy(data_dir, SyncPolicy::Always)
+    }
+
+    /// Open the database persisted in the data directory, recovering it from
+    /// the WAL if it exists.
+    pub fn open_with_sync_policy(
+        data_dir: impl AsRef<Path>,
+        policy: SyncPolicy,
+    ) -> Result<Self, SQLError> {
+        let mut catalog = Catalog::new();
+        let storage_mgr = StorageManager::recover(data_dir.as_ref(), policy, &mut catalog)?;
+        Ok(Self::new(catalog, storage_mgr))
+    }
+
+    /// Open a database whose data is only kept in memory.
+    pub fn in_memory() -> Self {
+        Self::new(Catalog::new(), StorageManager::default())
+    }
+
+    fn new(catalog: Catalog, storage_mgr: StorageManager) -> Self {
+        Self {
+            catalog: Arc::new(RwLock::new(catalog)),
+            storage_mgr: Arc::new(storage_mgr),
+            settings: Settings::default(),
+            activity: Arc::default(),
+            copy_directory: None,
+            function_directory: None,
+        }
+    }
+
+    /// Change the initial value of the run-time parameter for the sessions
+    /// created afterwards, which can still be changed by `SET`.
+    pub fn set_default(&mut self, name: &str, value: &str) -> Result<(), SQLError> {
+        self.settings.set(name, Some(value.to_string()))
+    }
+
+    /// Allow `COPY` to read and write the files in the directory, whose
+    /// paths are relative to it. `COPY` with files is rejected otherwise.
+    pub fn set_copy_directory(&mut self, dir: impl Into<PathBuf>) {
+        self.copy_directory = Some(dir.into());
+    }
+
+    /// Allow `CREATE FUNCTION ... LANGUAGE wasm` to load the modules in the
+    /// directory, whose paths are relative to it. It's rejected otherwise.
+    pub fn set_function_directory(&mut self, dir: impl Into<PathBuf>) {
+        self.function_directory = Some(dir.into());
+    }
+
+    /// Register a scalar function for all the sessions, which is called by
+    /// its lowercase name like the builtin ones. It's immutable, so the calls
+    /// with constant arguments are evaluated while planning. The functions
+    /// aren't persisted, they are registered again after opening.
+    pub fn register_scalar_function<F, R>(
+        &self,
+        name: &str,
+        arg_types: &[Type],
+        ret_type: Type,
+        func: F,
+    ) where
+        F: Fn(&[Datum]) -> R + Send + Sync + 'static,
+        R: IntoEvalResult,
+    {
+        let mut catalog = self.catalog.write().unwrap();
+        // The running queries keep the registry they are bound with
+        Arc::make_mut(&mut catalog.scalar_functions).register(
+            &name.to_lowercase(),
+            arg_types,
+            ret_type,
+            func,
+        );
+        catalog.bump_version(None);
+    }
+
+    /// Register an aggregate function for all the sessions, the state starts
+    /// from `init`, is updated in place by `accumulate` with the arguments of
+    /// every row, NULL included, and the result is computed by `finalize`.
+    pub fn register_aggregate_function<A, F>(
+        &self,
+        name: &str,
+        arg_types: &[Type],
+        ret_type: Type,
+        init: Datum,
+        accumulate: A,
+        finalize: F,
+    ) where
+        A: Fn(&mut Datum, &[Datum]) + Send + Sync + 'static,
+        F: Fn(&Datum) -> Datum + Send + Sync + 'static,
+    {
+        let mut catalog = self.catalog.write().unwrap();
+        Arc::make_mut(&mut catalog.aggregate_functions).register_user_defined(
+            &name.to_lowercase(),
+            arg_types,
+            ret_type,
+            init,
+            accumulate,
+            finalize,
+        );
+        catalog.bump_version(None);
+    }
+
+    /// Vacuum the tables with too many dead tuples, which is run periodically
+    /// by the server if autovacuum is enabled.
+    pub fn autovacuum(&self) -> Result<(), SQLError> {
+        let mut catalog = self.catalog.write().unwrap();
+        self.storage_mgr.autovacuum(&mut catalog)
+    }
+
+    /// Create a new session with the default settings.
+    pub fn session(&self) -> Session {
+        let mut ctx = QueryContext::new(self.catalog.clone(), self.storage_mgr.clone());
+        ctx.settings = self.settings.clone();
+        ctx.activity = self.activity.clone();
+        ctx.copy_directory = self.copy_directory.clone();
+        ctx.function_directory = self.function_directory.clone();
+        Session::new(ctx)
+    }
+
+    /// Create a new connection, which has its own session.
+    pub fn connect(&self) -> Connection {
+        Connection {
+            session: self.session(),
+        }
+    }
+
+    /// Create a new connection to the database of the name, instead of the
+    /// default one.
+    pub fn connect_to(&self, database_name: &str) -> Result<Connection, SQLError> {
+        let mut session = self.session();
+        session.apply_startup_parameters(&HashMap::from([(
+            "database".to_string(),
+            database_name.to_string(),
+        )]))?;
+        Ok(Connection { session })
+    }
+}
+
+/// A connection to the embedded database, statements are executed in its
+/// session, so prepared statements and settings are kept between them.
+pub struct Connection {
+    session: Session,
+}
+
+impl Connection {
+    /// Execute the statements, returns the number of rows affected or
+    /// returned by the last one.
+    pub fn execute(&mut self, sql: &str, parameters: &[Datum]) -> Result<usize, SQLError> {
+        let result = self.run(sql, parameters)?;
+        Ok(match result.kind {
+            SQLKind::Query => result.data.len(),
+            SQLKind::Update | SQLKind::Delete | SQLKind::Copy => result.affected_rows(),
+            SQLKind::Execute => 0,
+        })
+    }
+
+    /// Execute the statements, returns the result of the last one.
+    pub fn query(&mut self, sql: &str, parameters: &[Datum]) -> Result<QueryResult, SQLError> {
+        self.run(sql, parameters)
+    }
+
+    /// Execute the statements, and write the result of the last one to the
+    /// Parquet file, returns the number of rows written.
+    pub fn export_parquet(
+        &mut self,
+        sql: &str,
+        parameters: &[Datum],
+        path: impl AsRef<Path>,
+    ) -> Result<usize, SQLError> {
+        let result = self.run(sql, parameters)?;
+        let columns = result
+            .columns
+            .iter()
+            .map(|column| (column.name.clone(), column.typ.clone(), column.int_width))
+            .collect::<Vec<_>>();
+        write_parquet(&path.as_ref().to_string_lossy(), &columns, &result.data)?;
+
+        Ok(result.data.len())
+    }
+
+    /// The session of connection.
+    pub fn session(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Execute the statements one by one, `$n` in them are bound to the
+    /// parameters. The rest statements are skipped once an error occurs.
+    fn run(&mut self, sql: &str, parameters: &[Datum]) -> Result<QueryResult, SQLError> {
+        let mut result = QueryResult::empty();
+        for statement in self.session.parse(sql)?.iter() {
+            result = if parameters.is_empty() {
+                self.session.execute(statement)?
+            } else {
+                self.session.execute_statement(statement, parameters)?
+            };
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,14 @@
+#[macro_use]
+extern crate lazy_static;
+
+pub mod catalog;
+pub mod cli;
+pub mod core;
+pub mod database;
+pub mod server;
+pub mod sql;
+pub mod storage;
+pub mod util;
+
+pub use database::{Connection, Database};
+pub use sql::session::result::{FromDatum, QueryResult, ResultColumn, Row};
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, fmt::Debug, str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+use futures::Sink;
+use pgwire::{
+    api::{
+        auth::{
+            cleartext::CleartextPasswordAuthStartupHandler,
+            finish_authentication,
+            md5pass::{
+                hash_md5_password, MakeMd5PasswordAuthStartupHandler, Md5PasswordAuthStartupHandler,
+            },
+            save_startup_parameters_to_metadata,
+            scram::{
+                gen_salted_password, MakeSASLScramAuthStartupHandler, SASLScramAuthStartupHandler,
+            },
+            AuthSource, LoginInfo, Password, ServerParameterProvider, StartupHandler,
+        },
+        ClientInfo, MakeHandler,
+    },
+    error::{PgWireError, PgWireResult},
+    messages::{PgWireBackendMessage, PgWireFrontendMessage},
+};
+
+use crate::catalog::user::UserCatalog;
+
+/// Iteration count of SCRAM password hashing, the minimal one required by RFC 7677.
+const SCRAM_ITERATIONS: usize = 4096;
+
+/// Authentication methods, named after the ones in `pg_hba.conf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Allow connections without password.
+    Trust,
+    /// Password sent in cleartext.
+    Password,
+    Md5,
+    ScramSha256,
+}
+
+impl FromStr for AuthMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "trust" => Ok(AuthMethod::Trust),
+            "password" => Ok(AuthMethod::Password),
+            "md5" => Ok(AuthMethod::Md5),
+            "scram-sha-256" => Ok(AuthMethod::ScramSha256),
+            _ => Err(format!("unknown authentication method: {}", s)),
+        }
+    }
+}
+
+/// Password source backed by the user catalog, the password is hashed as
+/// required by the authentication method.
+#[derive(Clone)]
+pub struct UserAuthSource {
+    users: Arc<UserCatalog>,
+    method: AuthMethod,
+}
+
+#[async_trait]
+impl AuthSource for UserAuthSource {
+    async fn get_password(&self, login: &LoginInfo) -> PgWireResult<Password> {
+        let user = login.user().map(String::as_str).unwrap_or_default();
+        // Unknown users are challenged with a random password, so they fail
+        // the same way as a wrong password, and can't be told apart from the
+        // existing users
+        let dummy;
+        let password = match self.users.find_password(user) {
+            Some(password) => password,
+            None => {
+                dummy = format!("{:032x}", rand::random::<u128>());
+                &dummy
+            }
+        };
+
+        match self.method {
+            AuthMethod::Md5 => {
+                let salt = rand::random::<[u8; 4]>().to_vec();
+                let hashed = hash_md5_password(user, password, &salt);
+                Ok(Password::new(Some(salt), hashed.into_bytes()))
+            }
+            AuthMethod::ScramSha256 => {
+                let salt = rand::random::<[u8; 16]>().to_vec();
+                let salted = gen_salted_password(password, &salt, SCRAM_ITERATIONS);
+                Ok(Password::new(Some(salt), salted))
+            }
+            AuthMethod::Trust | AuthMethod::Password => {
+                Ok(Password::new(None, password.as_bytes().to_vec()))
+            }
+        }
+    }
+}
+
+/// Parameters reported to client with `ParameterStatus` after authentication.
+pub struct ParameterProvider;
+
+impl ServerParameterProvider for ParameterProvider {
+    fn server_parameters<C>(&self, client: &C) -> Option<HashMap<String, String>>
+    where
+        C: ClientInfo,
+    {
+        let mut parameters = HashMap::new();
+        // Report a Postgres version, since clients decide the supported features with it
+        parameters.insert("server_version".to_string(), "14.0".to_string());
+        parameters.insert("server_encoding".to_string(), "UTF8".to_string());
+        parameters.insert("client_encoding".to_string(), "UTF8".to_string());
+        parameters.insert("DateStyle".to_string(), "ISO, MDY".to_string());
+        parameters.insert("integer_datetimes".to_string(), "on".to_string());
+        parameters.insert("standard_conforming_strings".to_string(), "on".to_string());
+        parameters.insert(
+            "application_name".to_string(),
+            client
+                .metadata()
+                .get("application_name")
+                .cloned()
+                .unwrap_or_default(),
+        );
+
+        Some(parameters)
+    }
+}
+
+/// Accepts all the connections without password.
+pub struct TrustStartupHandler;
+
+#[async_trait]
+impl StartupHandler for TrustStartupHandler {
+    async fn on_startup<C>(
+        &self,
+        client: &mut C,
+        message: PgWireFrontendMessage,
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if let PgWireFrontendMessage::Startup(ref startup) = message {
+            save_startup_parameters_to_metadata(client, startup);
+            finish_authentication(client, &ParameterProvider).await;
+        }
+        Ok(())
+    }
+}
+type ScramHandler = Arc<SASLScramAuthStartupHandler<UserAuthSource, ParameterProvider>>;
+
+/// Startup handler of a connection, dispatched by the authentication method.
+pub enum Authenticator {
+    Trust(TrustStartupHandler),
+    Password(CleartextPasswordAuthStartupHandler<UserAuthSource, ParameterProvider>),
+    Md5(Arc<Md5PasswordAuthStartupHandler<UserAuthSource, ParameterProvider>>),
+    /// SCRAM-SHA-256-PLUS is only offered to TLS connections, since channel
+    /// binding requires the server certificate.
+    ScramSha256 {
+        handler: ScramHandler,
+        tls_handler: Option<ScramHandler>,
+    },
+}
+
+#[async_trait]
+impl StartupHandler for Authenticator {
+    async fn on_startup<C>(
+        &self,
+        client: &mut C,
+        message: PgWireFrontendMessage,
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        match self {
+            Authenticator::Trust(handler) => handler.on_startup(client, message).await,
+            Authenticator::Password(handler) => handler.on_startup(client, message).await,
+            Authenticator::Md5(handler) => handler.on_startup(client, message).await,
+            Authenticator::ScramSha256 {
+                tls_handler: Some(handler),
+                ..
+            } if client.is_secure() => handler.on_startup(client, message).await,
+            Authenticator::ScramSha256 { handler, .. } => handler.on_startup(client, message).await,
+        }
+    }
+}
+
+/// Makes an authenticator for each connection, since the handshake of some
+/// authentication methods is stateful.
+pub struct MakeAuthenticator {
+    method: AuthMethod,
+    auth_source: UserAuthSource,
+    /// Server certificate in PEM format, for SCRAM channel binding.
+    certificate: Option<Vec<u8>>,
+}
+
+impl MakeAuthenticator {
+    pub fn new(method: AuthMethod, users: UserCatalog) -> Self {
+        Self {
+            method,
+            auth_source: UserAuthSource {
+                users: Arc::new(users),
+                method,
+            },
+            certificate: None,
+        }
+    }
+
+    /// Enable SCRAM-SHA-256-PLUS with the server certificate of TLS.
+    pub fn with_certificate(mut self, certs_pem: Vec<u8>) -> Self {
+        self.certificate = Some(certs_pem);
+        self
+    }
+}
+
+impl MakeHandler for MakeAuthenticator {
+    type Handler = Arc<Authenticator>;
+
+    fn make(&self) -> Self::Handler {
+        let auth_source = Arc::new(self.auth_source.clone());
+        let parameter_provider = Arc::new(ParameterProvider);
+        let authenticator = match self.method {
+            AuthMethod::Trust => Authenticator::Trust(TrustStartupHandler),
+            AuthMethod::Password => {
+                Authenticator::Password(CleartextPasswordAuthStartupHandler::new(
+                    self.auth_source.clone(),
+                    ParameterProvider,
+                ))
+            }
+            AuthMethod::Md5 => Authenticator::Md5(
+                MakeMd5PasswordAuthStartupHandler::new(auth_source, parameter_provider).make(),
+            ),
+            AuthMethod::ScramSha256 => {
+                let mut make_handler =
+                    MakeSASLScramAuthStartupHandler::new(auth_source, parameter_provider);
+                make_handler.set_iterations(SCRAM_ITERATIONS);
+                let handler = make_handler.make();
+                let tls_handler = self.certificate.as_ref().map(|certificate| {
+                    // The certificate has been validated while loading TLS config
+                    make_handler.configure_certificate(certificate).unwrap();
+                    make_handler.make()
+                });
+                Authenticator::ScramSha256 {
+                    handler,
+                    tls_handler,
+                }
+            }
+        };
+
+        Arc::new(authenticator)
+    }
+}
@@ -0,0 +1,259 @@
+use std::{error::Error, sync::Arc};
+
+use bytes::{BufMut, BytesMut};
+use pgwire::{
+    api::{
+        portal::Format,
+        results::{DataRowEncoder, FieldFormat, FieldInfo},
+        Type,
+    },
+    error::{PgWireError, PgWireResult},
+    messages::data::DataRow,
+    types::ToSqlText,
+};
+use postgres_types::{to_sql_checked, FromSql, IsNull, Kind, ToSql};
+use uuid::Uuid;
+
+use super::into_pg_error;
+use crate::{
+    core::{array_element_text, sqlstate, Datum, ErrorKind, SQLError, Tuple},
+    sql::session::{cast_parameter, result::ResultColumn, to_pg_column_type},
+};
+
+/// Binary encoding of datums, the Postgres type of a datum is decided
+/// by the variant of datum, see `to_pg_type`, and the integers are of the
+/// declared widths of columns, see `to_pg_column_type`.
+impl ToSql for Datum {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>>
+    where
+        Self: Sized,
+    {
+        match self {
+            // The values of narrower integer columns are in range
+            Datum::Int(v) => match *ty {
+                Type::INT2 => (*v as i16).to_sql(ty, out),
+                Type::INT4 => (*v as i32).to_sql(ty, out),
+                _ => v.to_sql(ty, out),
+            },
+            Datum::Float(v) => v.to_sql(ty, out),
+            Datum::String(v) => v.as_ref().to_sql(ty, out),
+            Datum::Boolean(v) => v.to_sql(ty, out),
+            Datum::Uuid(v) => {
+                out.put_slice(v.as_bytes());
+                Ok(IsNull::No)
+            }
+            // The binary format of JSON is the same as text
+            Datum::Json(v) => v.to_string().to_sql(ty, out),
+            Datum::Bytea(v) => {
+                out.put_slice(v);
+                Ok(IsNull::No)
+            }
+            Datum::Array(values) => {
+                let elem_type = match ty.kind() {
+                    Kind::Array(elem_type) => elem_type,
+                    _ => return Err(format!("cannot encode array as type {}", ty).into()),
+                };
+
+                // Header of the array: number of dimensions, if it contains NULL,
+                // element type, then the length and lower bound of each dimension.
+                out.put_i32(if values.is_empty() { 0 } else { 1 });
+                out.put_i32(values.iter().any(Datum::is_null) as i32);
+                out.put_u32(elem_type.oid());
+                if !values.is_empty() {
+                    out.put_i32(values.len() as i32);
+                    out.put_i32(1);
+                }
+
+                // Each element is prefixed with its length, which is -1 for NULL
+                for value in values {
+                    let start = out.len();
+                    out.put_i32(0);
+                    let len = match value.to_sql(elem_type, out)? {
+                        IsNull::Yes => -1,
+                        IsNull::No => (out.len() - start - 4) as i32,
+                    };
+                    out[start..start + 4].copy_from_slice(&len.to_be_bytes());
+                }
+                Ok(IsNull::No)
+            }
+            Datum::Null => Ok(IsNull::Yes),
+        }
+    }
+
+    fn accepts(_ty: &Type) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+/// Text encoding of datums, NULL is sent as a missing value and booleans as
+/// `t` and `f`.
+impl ToSqlText for Datum {
+    fn to_sql_text(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>>
+    where
+        Self: Sized,
+    {
+        if self.is_null() {
+            return Ok(IsNull::Yes);
+        }
+        out.put_slice(array_element_text(self).as_bytes());
+        Ok(IsNull::No)
+    }
+}
+
+/// Fields of the result columns, in the formats requested by client.
+pub fn result_fields(columns: &[ResultColumn], format: &Format) -> Vec<FieldInfo> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let field_format = if format.is_binary(i) {
+                FieldFormat::Binary
+            } else {
+                FieldFormat::Text
+            };
+
+            FieldInfo::new(
+                column.name.clone(),
+                None,
+                None,
+                to_pg_column_type(&column.typ, column.int_width),
+                field_format,
+            )
+        })
+        .collect()
+}
+
+/// Encode a tuple into data row, with the format of each field.
+pub fn encode_tuple(tuple: &Tuple, fields: &Arc<Vec<FieldInfo>>) -> PgWireResult<DataRow> {
+    let mut encoder = DataRowEncoder::new(fields.clone());
+    for datum in tuple.values.iter() {
+        encoder.encode_field(datum)?;
+    }
+
+    encoder.finish()
+}
+
+/// Decode the value of parameter sent by client, the value will be
+/// cast to the type of parameter, see `cast_parameter`.
+pub fn decode_parameter(
+    value: Option<&[u8]>,
+    binary: bool,
+    pg_type: &Type,
+    typ: &crate::core::Type,
+) -> PgWireResult<Datum> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(Datum::Null),
+    };
+
+    let datum = if binary {
+        decode_binary(value, pg_type)?
+    } else {
+        Datum::String(
+            std::str::from_utf8(value)
+                .map_err(|e| {
+                    into_pg_error(
+                        SQLError::new(ErrorKind::RuntimeError, e.to_string())
+                            .with_code(sqlstate::INVALID_TEXT_REPRESENTATION),
+                    )
+                })?
+                .into(),
+        )
+    };
+
+    cast_parameter(&datum, typ).map_err(into_pg_error)
+}
+
+fn decode_binary(value: &[u8], pg_type: &Type) -> PgWireResult<Datum> {
+    let datum = match *pg_type {
+        Type::INT2 => Datum::Int(i16::from_sql(pg_type, value).map_err(into_parse_error)? as i64),
+        Type::INT4 => Datum::Int(i32::from_sql(pg_type, value).map_err(into_parse_error)? as i64),
+        Type::INT8 => Datum::Int(i64::from_sql(pg_type, value).map_err(into_parse_error)?),
+        Type::FLOAT4 => {
+            Datum::Float(f32::from_sql(pg_type, value).map_err(into_parse_error)? as f64)
+        }
+        Type::FLOAT8 => Datum::Float(f64::from_sql(pg_type, value).map_err(into_parse_error)?),
+        Type::BOOL => Datum::Boolean(bool::from_sql(pg_type, value).map_err(into_parse_error)?),
+        Type::UUID => Datum::Uuid(Uuid::from_slice(value).map_err(into_parse_error)?),
+        Type::BYTEA => Datum::Bytea(value.into()),
+        // The binary format of JSONB is the text with a version number
+        Type::JSONB => match value.split_first() {
+            Some((1, text)) => Datum::String(
+                <&str>::from_sql(&Type::JSON, text)
+                    .map_err(into_parse_error)?
+                    .into(),
+            ),
+            _ => return Err(into_parse_error("unsupported jsonb version number")),
+        },
+        _ => match pg_type.kind() {
+            Kind::Array(elem_type) => decode_binary_array(value, elem_type)?,
+            _ => Datum::String(
+                <&str>::from_sql(pg_type, value)
+                    .map_err(into_parse_error)?
+                    .into(),
+            ),
+        },
+    };
+
+    Ok(datum)
+}
+
+/// Decode an array of one dimension in binary format, see `ToSql` of `Datum`.
+fn decode_binary_array(mut value: &[u8], elem_type: &Type) -> PgWireResult<Datum> {
+    fn take<'a>(value: &mut &'a [u8], len: usize) -> PgWireResult<&'a [u8]> {
+        if value.len() < len {
+            return Err(into_parse_error("invalid array binary format"));
+        }
+        let (head, tail) = value.split_at(len);
+        *value = tail;
+        Ok(head)
+    }
+    fn take_i32(value: &mut &[u8]) -> PgWireResult<i32> {
+        Ok(i32::from_be_bytes(take(value, 4)?.try_into().unwrap()))
+    }
+
+    let dimensions = take_i32(&mut value)?;
+    // Skip the flag of NULL and the element type
+    take(&mut value, 8)?;
+    let len = match dimensions {
+        0 => 0,
+        1 => {
+            let len = take_i32(&mut value)?;
+            // Skip the lower bound
+            take(&mut value, 4)?;
+            len
+        }
+        _ => {
+            return Err(into_parse_error(
+                "multidimensional arrays are not supported",
+            ))
+        }
+    };
+
+    let mut values = vec![];
+    for _ in 0..len {
+        let datum = match take_i32(&mut value)? {
+            -1 => Datum::Null,
+            len => decode_binary(take(&mut value, len.max(0) as usize)?, elem_type)?,
+        };
+        values.push(datum);
+    }
+
+    Ok(Datum::Array(values))
+}
+
+fn into_parse_error<E: Into<Box<dyn Error + Sync + Send>>>(e: E) -> PgWireError {
+    into_pg_error(
+        SQLError::new(ErrorKind::RuntimeError, e.into().to_string())
+            .with_code(sqlstate::INVALID_BINARY_REPRESENTATION),
+    )
+}
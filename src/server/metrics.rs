@@ -0,0 +1,64 @@
+use std::io::Error as IOError;
+
+use log::warn;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::util::metrics::{enable_operator_timing, METRICS};
+
+/// Maximum size of the request head, the rest is not read.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Serve the metrics over HTTP at `/metrics`, in Prometheus text format.
+/// Operators are only timed once it's started.
+pub async fn serve_metrics(listener: TcpListener) -> Result<(), IOError> {
+    enable_operator_timing();
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = handle_request(stream).await {
+                warn!("Failed to serve metrics: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_request(mut stream: TcpStream) -> Result<(), IOError> {
+    // Only the request line matters, read until the end of the head
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n")
+        && request.len() < MAX_REQUEST_SIZE
+    {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            ("200 OK", "text/plain; version=0.0.4", METRICS.render())
+        }
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method Not Allowed\n".to_string(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -1,64 +1,728 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
 
 use async_trait::async_trait;
-use futures::{stream, StreamExt};
+use futures::{Sink, SinkExt};
 
 use pgwire::{
     api::{
-        query::SimpleQueryHandler,
-        results::{query_response, DataRowEncoder, Response, Tag},
-        ClientInfo,
+        auth::{save_startup_parameters_to_metadata, StartupHandler},
+        portal::{Format, Portal},
+        query::{ExtendedQueryHandler, SimpleQueryHandler, StatementOrPortal},
+        results::{DescribeResponse, Response, Tag},
+        stmt::{QueryParser, StoredStatement},
+        store::{MemPortalStore, PortalStore},
+        ClientInfo, MakeHandler, PgWireConnectionState, Type, DEFAULT_NAME,
+    },
+    error::{ErrorInfo, PgWireError, PgWireResult},
+    messages::{
+        data::{NoData, ParameterDescription, RowDescription},
+        extendedquery::{
+            Bind, BindComplete, Close, CloseComplete, Describe, Execute, Parse, ParseComplete,
+            PortalSuspended, Sync as PgSync, TARGET_TYPE_BYTE_PORTAL, TARGET_TYPE_BYTE_STATEMENT,
+        },
+        response::{EmptyQueryResponse, ErrorResponse, ReadyForQuery, READY_STATUS_IDLE},
+        simplequery::Query,
+        PgWireBackendMessage, PgWireFrontendMessage,
     },
-    error::PgWireResult,
 };
+use tokio::task::block_in_place;
 
-use crate::sql::{
-    session::{QueryResult, SQLKind},
-    Session,
+use self::{
+    auth::{Authenticator, MakeAuthenticator},
+    encoding::{decode_parameter, encode_tuple, result_fields},
+};
+use crate::{
+    core::{sqlstate, Datum, ErrorKind, SQLError},
+    database::Database,
+    sql::{
+        parser::{parse_sql, SQLStatement},
+        session::{
+            from_pg_type, result::QueryResult, to_pg_type, PreparedStatement, QueryCursor, SQLKind,
+        },
+        Session,
+    },
+    util::metrics::METRICS,
 };
 
+pub mod auth;
+mod encoding;
+pub mod metrics;
+pub mod tls;
+
+/// Handler of a connection. The statements are executed on the worker thread
+/// with `block_in_place`, so the other connections are served meanwhile,
+/// e.g. to cancel a long running statement.
 pub struct PostgresHandler {
     pub session: Arc<Mutex<Session>>,
+    portal_store: Arc<MemPortalStore<SQLStatement>>,
+    query_parser: Arc<SQLParser>,
+    /// Cursors of the executed portals, indexed by portal name. The cursor
+    /// of an exhausted portal is kept until `Sync`, so that it's not executed
+    /// again.
+    cursors: Mutex<HashMap<String, ExecutedPortal>>,
+    /// Set once a message of extended query fails, the following messages
+    /// are discarded until `Sync`.
+    error_until_sync: AtomicBool,
+    authenticator: Arc<Authenticator>,
+}
+
+/// An executed portal, with the cursor opened for it.
+type ExecutedPortal = (Arc<Portal<SQLStatement>>, QueryCursor);
+
+impl PostgresHandler {
+    pub fn new(session: Arc<Mutex<Session>>, authenticator: Arc<Authenticator>) -> Self {
+        Self {
+            session,
+            portal_store: Arc::new(MemPortalStore::new()),
+            query_parser: Arc::new(SQLParser),
+            cursors: Mutex::new(HashMap::new()),
+            error_until_sync: AtomicBool::new(false),
+            authenticator,
+        }
+    }
+
+    fn session(&self) -> MutexGuard<'_, Session> {
+        self.session.lock().unwrap()
+    }
+
+    /// Check if the message of extended query should be discarded, because
+    /// an earlier one has failed.
+    fn skip_until_sync(&self) -> bool {
+        self.error_until_sync.load(Ordering::Relaxed)
+    }
+
+    /// Send the error of an extended query message. Unlike simple query,
+    /// `ReadyForQuery` isn't sent until `Sync`, the messages before it are
+    /// discarded.
+    async fn report_error<C>(&self, client: &mut C, result: PgWireResult<()>) -> PgWireResult<()>
+    where
+        C: Sink<PgWireBackendMessage> + Unpin + Send,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let info = match result {
+            Ok(()) => return Ok(()),
+            Err(PgWireError::UserError(info)) => *info,
+            Err(PgWireError::IoError(e)) => return Err(PgWireError::IoError(e)),
+            Err(e) => into_error_info(protocol_error(e)),
+        };
+        self.error_until_sync.store(true, Ordering::Relaxed);
+        client
+            .send(PgWireBackendMessage::ErrorResponse(info.into()))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn execute_portal<C>(&self, client: &mut C, message: &Execute) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let portal_name = message.name().as_deref().unwrap_or(DEFAULT_NAME);
+        let portal = self
+            .portal_store
+            .get_portal(portal_name)
+            .ok_or_else(|| PgWireError::PortalNotFound(portal_name.to_owned()))?;
+
+        let (command, result, suspended) = block_in_place(|| {
+            let mut session = self.session();
+            let mut cursors = self.cursors.lock().unwrap();
+            let statement = portal.statement().statement();
+
+            // The cursor is discarded if the portal has been bound again
+            let mut cursor = match cursors.remove(portal_name) {
+                Some((opened_portal, cursor)) if Arc::ptr_eq(&opened_portal, &portal) => cursor,
+                _ => {
+                    let (_, parameters) = bind_portal(&mut session, &portal)?;
+                    session
+                        .open_cursor(statement, &parameters)
+                        .map_err(into_pg_error)?
+                }
+            };
+
+            let result = session
+                .fetch(&mut cursor, *message.max_rows() as usize)
+                .map_err(into_pg_error)?;
+            let suspended = !cursor.is_finished();
+            cursors.insert(portal_name.to_owned(), (portal.clone(), cursor));
+
+            PgWireResult::Ok((session.command(statement), result, suspended))
+        })?;
+
+        send_result(
+            client,
+            command,
+            &result,
+            portal.result_column_format(),
+            false,
+            suspended,
+        )
+        .await
+    }
+
+    async fn describe<C>(&self, client: &mut C, message: &Describe) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let name = message.name().as_deref().unwrap_or(DEFAULT_NAME);
+        let describe_response = match message.target_type() {
+            TARGET_TYPE_BYTE_STATEMENT => {
+                let statement = self
+                    .portal_store
+                    .get_statement(name)
+                    .ok_or_else(|| PgWireError::StatementNotFound(name.to_owned()))?;
+                self.do_describe(client, StatementOrPortal::Statement(&statement))
+                    .await?
+            }
+            TARGET_TYPE_BYTE_PORTAL => {
+                let portal = self
+                    .portal_store
+                    .get_portal(name)
+                    .ok_or_else(|| PgWireError::PortalNotFound(name.to_owned()))?;
+                self.do_describe(client, StatementOrPortal::Portal(&portal))
+                    .await?
+            }
+            target_type => return Err(PgWireError::InvalidTargetType(target_type)),
+        };
+
+        if let Some(parameter_types) = describe_response.parameters() {
+            client
+                .send(PgWireBackendMessage::ParameterDescription(
+                    ParameterDescription::new(parameter_types.iter().map(|t| t.oid()).collect()),
+                ))
+                .await?;
+        }
+        // Statements without result columns, e.g. `INSERT`, have no data
+        let fields = describe_response.fields();
+        if fields.is_empty() {
+            client.send(PgWireBackendMessage::NoData(NoData)).await?;
+        } else {
+            client
+                .send(PgWireBackendMessage::RowDescription(RowDescription::new(
+                    fields.iter().map(Into::into).collect(),
+                )))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The session is initialized with the startup parameters before
+/// authentication, so a connection to the database or schema that doesn't
+/// exist is rejected with a `FATAL` error.
+#[async_trait]
+impl StartupHandler for PostgresHandler {
+    async fn on_startup<C>(
+        &self,
+        client: &mut C,
+        message: PgWireFrontendMessage,
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if let PgWireFrontendMessage::Startup(ref startup) = message {
+            save_startup_parameters_to_metadata(client, startup);
+            let result = self.session().apply_startup_parameters(client.metadata());
+            if let Err(e) = result {
+                let mut info = into_error_info(e);
+                info.set_severity("FATAL".to_string());
+                client
+                    .send(PgWireBackendMessage::ErrorResponse(ErrorResponse::from(
+                        info,
+                    )))
+                    .await?;
+                client.close().await?;
+                return Ok(());
+            }
+        }
+        self.authenticator.on_startup(client, message).await
+    }
+}
+
+/// Makes a handler with its own session for each connection, all the sessions
+/// share the same catalog and storage of the database.
+pub struct MakePostgresHandler {
+    database: Database,
+    authenticator: MakeAuthenticator,
+}
+
+impl MakePostgresHandler {
+    pub fn new(database: Database, authenticator: MakeAuthenticator) -> Self {
+        Self {
+            database,
+            authenticator,
+        }
+    }
+}
+
+impl MakeHandler for MakePostgresHandler {
+    type Handler = Arc<PostgresHandler>;
+
+    fn make(&self) -> Self::Handler {
+        Arc::new(PostgresHandler::new(
+            Arc::new(Mutex::new(self.database.session())),
+            self.authenticator.make(),
+        ))
+    }
+}
+
+/// Parser of extended query protocol
+pub struct SQLParser;
+
+impl QueryParser for SQLParser {
+    type Statement = SQLStatement;
+
+    fn parse_sql(&self, sql: &str, _types: &[pgwire::api::Type]) -> PgWireResult<SQLStatement> {
+        let mut statements = parse_sql(sql).map_err(into_pg_error)?;
+        if statements.len() != 1 {
+            return Err(into_pg_error(SQLError::new(
+                ErrorKind::ParseError,
+                "cannot insert multiple commands into a prepared statement",
+            )));
+        }
+
+        Ok(statements.remove(0))
+    }
+}
+
+fn into_pg_error(e: SQLError) -> PgWireError {
+    PgWireError::UserError(Box::new(into_error_info(e)))
+}
+
+/// Convert the error of pgwire on extended query messages, e.g. an unknown
+/// statement or portal.
+fn protocol_error(e: PgWireError) -> SQLError {
+    let (code, message) = match e {
+        PgWireError::StatementNotFound(name) => (
+            sqlstate::INVALID_SQL_STATEMENT_NAME,
+            format!("prepared statement \"{}\" does not exist", name),
+        ),
+        PgWireError::PortalNotFound(name) => (
+            sqlstate::INVALID_CURSOR_NAME,
+            format!("portal \"{}\" does not exist", name),
+        ),
+        e => (sqlstate::PROTOCOL_VIOLATION, e.to_string()),
+    };
+    SQLError::new(ErrorKind::PlannerError, message).with_code(code)
+}
+
+/// Convert the error into an `ErrorResponse` with SQLSTATE code.
+fn into_error_info(e: SQLError) -> ErrorInfo {
+    METRICS.record_error(&e.kind);
+    let mut info = ErrorInfo::new("ERROR".to_string(), e.sqlstate().to_string(), e.message);
+    info.set_detail(e.detail);
+    info.set_hint(e.hint);
+    info
+}
+
+/// Prepare the statement of portal, and decode the parameters bound to it.
+fn bind_portal(
+    session: &mut Session,
+    portal: &Portal<SQLStatement>,
+) -> PgWireResult<(PreparedStatement, Vec<Datum>)> {
+    let statement = portal.statement();
+    let declared_types = statement
+        .parameter_types()
+        .iter()
+        .map(from_pg_type)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(into_pg_error)?;
+    let prepared = session
+        .prepare(statement.statement(), &declared_types)
+        .map_err(into_pg_error)?;
+
+    if portal.parameter_len() != prepared.parameter_types.len() {
+        return Err(into_pg_error(
+            SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "bind message supplies {} parameters, but prepared statement requires {}",
+                    portal.parameter_len(),
+                    prepared.parameter_types.len()
+                ),
+            )
+            .with_code(sqlstate::PROTOCOL_VIOLATION),
+        ));
+    }
+
+    let parameters = portal
+        .parameters()
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            // Binary values are encoded in the type declared by client
+            let pg_type = statement
+                .parameter_types()
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| to_pg_type(&prepared.parameter_types[i]));
+            decode_parameter(
+                value.as_deref(),
+                portal.parameter_format().is_binary(i),
+                &pg_type,
+                &prepared.parameter_types[i],
+            )
+        })
+        .collect::<PgWireResult<Vec<_>>>()?;
+
+    Ok((prepared, parameters))
+}
+
+/// Command tag of the statement, with the number of rows for the commands
+/// reporting it, e.g. `INSERT 0 1` and `CREATE TABLE`.
+fn command_tag(command: &str, result: &QueryResult) -> Tag {
+    // Rows returned, or rows affected by DML statements without `RETURNING`
+    let rows = match result.kind {
+        SQLKind::Query => result.data.len(),
+        _ => result.affected_rows(),
+    };
+
+    match command {
+        // OID of the inserted row, which is always 0
+        "INSERT" => Tag::new_for_execution("INSERT 0", Some(rows)),
+        "SELECT" | "UPDATE" | "DELETE" | "COPY" => Tag::new_for_execution(command, Some(rows)),
+        _ => Tag::new_for_execution(command, None),
+    }
+}
+
+/// Send the rows of the result in the given format, then the command tag, or
+/// `PortalSuspended` if there are rows left. Row description is only sent for
+/// simple query, extended query sends it on `Describe` instead.
+async fn send_result<C>(
+    client: &mut C,
+    command: &str,
+    result: &QueryResult,
+    format: &Format,
+    with_row_description: bool,
+    suspended: bool,
+) -> PgWireResult<()>
+where
+    C: Sink<PgWireBackendMessage> + Unpin + Send,
+    PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+{
+    if let SQLKind::Query = result.kind {
+        let fields = Arc::new(result_fields(&result.columns, format));
+        if with_row_description {
+            client
+                .feed(PgWireBackendMessage::RowDescription(RowDescription::new(
+                    fields.iter().map(Into::into).collect(),
+                )))
+                .await?;
+        }
+        for tuple in result.data.iter() {
+            client
+                .feed(PgWireBackendMessage::DataRow(encode_tuple(tuple, &fields)?))
+                .await?;
+        }
+    }
+
+    if suspended {
+        client
+            .send(PgWireBackendMessage::PortalSuspended(PortalSuspended))
+            .await?;
+    } else {
+        client
+            .send(PgWireBackendMessage::CommandComplete(
+                command_tag(command, result).into(),
+            ))
+            .await?;
+    }
+
+    Ok(())
 }
 
 #[async_trait]
 impl SimpleQueryHandler for PostgresHandler {
-    async fn do_query<'b, C>(&self, _client: &C, query: &'b str) -> PgWireResult<Vec<Response<'b>>>
+    /// Execute the statements one by one, and the rest statements are skipped
+    /// once an error occurs. Unlike the default one, the command tags follow
+    /// the statements, e.g. `INSERT 0 1` for `INSERT ... RETURNING`.
+    async fn on_query<C>(&self, client: &mut C, query: Query) -> PgWireResult<()>
     where
-        C: ClientInfo + Unpin + Send + Sync,
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
     {
-        let mut session = self.session.lock().unwrap();
-
-        let QueryResult {
-            fields,
-            data: tuples,
-            kind,
-        } = session
-            .execute(query)
-            .map_err(|e| pgwire::error::PgWireError::ApiError(Box::new(e)))?;
-
-        match kind {
-            SQLKind::Query => {
-                let data_row_stream = stream::iter(tuples.into_iter()).map(|tuple| {
-                    let mut encoder = DataRowEncoder::new(2);
-                    for datum in tuple.values.iter() {
-                        encoder.encode_text_format_field(Some(datum))?;
+        client.set_state(PgWireConnectionState::QueryInProgress);
+
+        let statements = self.session().parse(query.query());
+        match statements {
+            Ok(statements) if statements.is_empty() => {
+                client
+                    .feed(PgWireBackendMessage::EmptyQueryResponse(EmptyQueryResponse))
+                    .await?;
+            }
+            Ok(statements) => {
+                for statement in statements.iter() {
+                    let result = block_in_place(|| {
+                        let mut session = self.session();
+                        let command = session.command(statement);
+                        session.execute(statement).map(|result| (command, result))
+                    });
+                    match result {
+                        // Simple query protocol always returns results in text format
+                        Ok((command, result)) => {
+                            send_result(client, command, &result, &Format::UnifiedText, true, false)
+                                .await?
+                        }
+                        Err(e) => {
+                            client
+                                .feed(PgWireBackendMessage::ErrorResponse(
+                                    into_error_info(e).into(),
+                                ))
+                                .await?;
+                            break;
+                        }
                     }
+                }
+            }
+            Err(e) => {
+                client
+                    .feed(PgWireBackendMessage::ErrorResponse(
+                        into_error_info(e).into(),
+                    ))
+                    .await?;
+            }
+        }
 
-                    encoder.finish()
-                });
+        client
+            .feed(PgWireBackendMessage::ReadyForQuery(ReadyForQuery::new(
+                READY_STATUS_IDLE,
+            )))
+            .await?;
+        client.flush().await?;
+        client.set_state(PgWireConnectionState::ReadyForQuery);
 
-                Ok(vec![Response::Query(query_response(
-                    Some(fields),
-                    data_row_stream,
-                ))])
-            }
-            SQLKind::Execute => {
-                return Ok(vec![Response::Execution(Tag::new_for_execution(
-                    "Something good happened",
-                    None,
-                ))])
+        Ok(())
+    }
+
+    /// Queries are executed by `on_query`.
+    async fn do_query<'b, C>(&self, _client: &C, _query: &'b str) -> PgWireResult<Vec<Response<'b>>>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        unreachable!("queries are executed by on_query")
+    }
+}
+
+#[async_trait]
+impl ExtendedQueryHandler for PostgresHandler {
+    type Statement = SQLStatement;
+    type QueryParser = SQLParser;
+    type PortalStore = MemPortalStore<SQLStatement>;
+
+    fn portal_store(&self) -> Arc<Self::PortalStore> {
+        self.portal_store.clone()
+    }
+
+    fn query_parser(&self) -> Arc<Self::QueryParser> {
+        self.query_parser.clone()
+    }
+
+    async fn do_describe<C>(
+        &self,
+        _client: &mut C,
+        target: StatementOrPortal<'_, SQLStatement>,
+    ) -> PgWireResult<DescribeResponse>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        let mut session = self.session();
+
+        // Parameter types are only described for statements
+        let (statement, inference_parameters) = match target {
+            StatementOrPortal::Statement(statement) => (statement, true),
+            StatementOrPortal::Portal(portal) => (portal.statement().as_ref(), false),
+        };
+
+        let declared_types = statement
+            .parameter_types()
+            .iter()
+            .map(from_pg_type)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(into_pg_error)?;
+        let prepared = session
+            .prepare(statement.statement(), &declared_types)
+            .map_err(into_pg_error)?;
+
+        let parameter_types = inference_parameters.then(|| {
+            prepared
+                .parameter_types
+                .iter()
+                .map(to_pg_type)
+                .collect::<Vec<_>>()
+        });
+
+        Ok(DescribeResponse::new(
+            parameter_types,
+            result_fields(&prepared.columns, &Format::UnifiedText),
+        ))
+    }
+
+    /// Same as the default one, except that the error is reported by
+    /// `report_error`.
+    async fn on_parse<C>(&self, client: &mut C, message: Parse) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if self.skip_until_sync() {
+            return Ok(());
+        }
+
+        let result = async {
+            let types = message
+                .type_oids()
+                .iter()
+                .map(|oid| Type::from_oid(*oid).ok_or(PgWireError::UnknownTypeId(*oid)))
+                .collect::<PgWireResult<Vec<_>>>()?;
+            let statement = self.query_parser.parse_sql(message.query(), &types)?;
+            let name = message.name().as_deref().unwrap_or(DEFAULT_NAME);
+            let statement = StoredStatement::new(name.to_owned(), statement, types);
+            self.portal_store.put_statement(Arc::new(statement));
+            client
+                .send(PgWireBackendMessage::ParseComplete(ParseComplete::new()))
+                .await?;
+            Ok(())
+        }
+        .await;
+        self.report_error(client, result).await
+    }
+
+    /// Same as the default one, except that the error is reported by
+    /// `report_error`.
+    async fn on_bind<C>(&self, client: &mut C, message: Bind) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if self.skip_until_sync() {
+            return Ok(());
+        }
+
+        let result = async {
+            let name = message.statement_name().as_deref().unwrap_or(DEFAULT_NAME);
+            let statement = self
+                .portal_store
+                .get_statement(name)
+                .ok_or_else(|| PgWireError::StatementNotFound(name.to_owned()))?;
+            let portal = Portal::try_new(&message, statement)?;
+            self.portal_store.put_portal(Arc::new(portal));
+            client
+                .send(PgWireBackendMessage::BindComplete(BindComplete::new()))
+                .await?;
+            Ok(())
+        }
+        .await;
+        self.report_error(client, result).await
+    }
+
+    /// Execute the portal, the results are fetched from a cursor kept across
+    /// `Execute` messages, so the portal can be suspended when the row limit
+    /// is reached. An exhausted portal completes with no rows if it's
+    /// executed again. The cursors are closed on `Sync`.
+    async fn on_execute<C>(&self, client: &mut C, message: Execute) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if self.skip_until_sync() {
+            return Ok(());
+        }
+
+        let result = self.execute_portal(client, &message).await;
+        self.report_error(client, result).await
+    }
+
+    /// Send `ReadyForQuery` for the messages since the last `Sync`, the
+    /// cursors of the portals are closed.
+    async fn on_sync<C>(&self, client: &mut C, _message: PgSync) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        self.cursors.lock().unwrap().clear();
+        self.error_until_sync.store(false, Ordering::Relaxed);
+
+        client
+            .send(PgWireBackendMessage::ReadyForQuery(ReadyForQuery::new(
+                READY_STATUS_IDLE,
+            )))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Same as the default one, except that `ReadyForQuery` is only sent on
+    /// `Sync`.
+    async fn on_describe<C>(&self, client: &mut C, message: Describe) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if self.skip_until_sync() {
+            return Ok(());
+        }
+
+        let result = self.describe(client, &message).await;
+        self.report_error(client, result).await
+    }
+
+    /// Close the statement or portal, with the cursor of portal. Unlike the
+    /// default one, `ReadyForQuery` is only sent on `Sync`.
+    async fn on_close<C>(&self, client: &mut C, message: Close) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if self.skip_until_sync() {
+            return Ok(());
+        }
+
+        let name = message.name().as_deref().unwrap_or(DEFAULT_NAME);
+        match message.target_type() {
+            TARGET_TYPE_BYTE_STATEMENT => self.portal_store.rm_statement(name),
+            TARGET_TYPE_BYTE_PORTAL => {
+                self.portal_store.rm_portal(name);
+                self.cursors.lock().unwrap().remove(name);
             }
+            _ => {}
         }
+        client
+            .send(PgWireBackendMessage::CloseComplete(CloseComplete))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Portals are executed by `on_execute`.
+    async fn do_query<'a, 'b: 'a, C>(
+        &'b self,
+        _client: &mut C,
+        _portal: &'a Portal<SQLStatement>,
+        _max_rows: usize,
+    ) -> PgWireResult<Response<'a>>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        unreachable!("portals are executed by on_execute")
     }
 }
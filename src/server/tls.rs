@@ -0,0 +1,47 @@
+use std::{
+    fs::File,
+    io::{BufReader, Error as IOError, ErrorKind},
+    sync::Arc,
+};
+
+use rustls_pemfile::Item;
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
+
+/// Build a TLS acceptor with the certificate chain and private key in PEM format.
+pub fn load_tls_acceptor(cert_path: &str, key_path: &str) -> Result<TlsAcceptor, IOError> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert_path)?))?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+    if certs.is_empty() {
+        return Err(IOError::new(
+            ErrorKind::InvalidInput,
+            format!("no certificate found in {}", cert_path),
+        ));
+    }
+
+    // The first private key is used, either in PKCS#8, PKCS#1 or SEC1 format
+    let key = rustls_pemfile::read_all(&mut BufReader::new(File::open(key_path)?))?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            IOError::new(
+                ErrorKind::InvalidInput,
+                format!("no private key found in {}", key_path),
+            )
+        })?;
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| IOError::new(ErrorKind::InvalidInput, e))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
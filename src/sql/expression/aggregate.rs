@@ -1,19 +1,8 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
 use enum_as_inner::EnumAsInner;
 
-use crate::core::{Datum, Type};
-
-lazy_static! {
-    static ref BUILTIN_AGGREGATE_FUNCTIONS: AggregateFunctionRegistry = {
-        let mut registry = AggregateFunctionRegistry::default();
-        register_count(&mut registry);
-        register_sum(&mut registry);
-        register_avg(&mut registry);
-        register_min_max(&mut registry);
-        registry
-    };
-}
+use crate::core::{sqlstate, Datum, ErrorKind, Json, SQLError, Type};
 
 #[derive(Debug, Clone, EnumAsInner)]
 pub enum AggregateState {
@@ -21,6 +10,29 @@ pub enum AggregateState {
     Sum(Datum),
     Avg(Datum, usize),
     MinMax(Datum),
+    /// Count, mean and sum of squared differences from the mean of the
+    /// values, which are updated with Welford's algorithm.
+    Moments(Statistic, u64, f64, f64),
+    Bool(Datum),
+    JsonAgg(Vec<Json>),
+    /// Concatenated values of `string_agg`, which is `None` before the first
+    /// non-null value.
+    StringAgg(Option<String>),
+    /// State of the aggregate functions defined by an init/accumulate/finalize
+    /// triple, which is finalized by the function.
+    UserDefined(Datum),
+    /// Sort keys and arguments of the tuples of an aggregate with `ORDER BY`,
+    /// which are accumulated in the order of keys when finalized.
+    Buffered(Vec<(Vec<Datum>, Vec<Datum>)>),
+}
+
+/// Statistic computed from the moments of values.
+#[derive(Debug, Clone, Copy)]
+pub enum Statistic {
+    VarSamp,
+    VarPop,
+    StddevSamp,
+    StddevPop,
 }
 
 impl AggregateState {
@@ -28,42 +40,141 @@ impl AggregateState {
         match self {
             AggregateState::Count(count) => Datum::Int(*count as i64),
             AggregateState::Sum(value) => value.clone(),
-            AggregateState::Avg(value, count) => {
-                if *count == 0 {
+            AggregateState::Avg(value, count) => match value {
+                Datum::Float(sum) if *count > 0 => Datum::Float(sum / *count as f64),
+                _ => Datum::Null,
+            },
+            AggregateState::MinMax(value) => value.clone(),
+            AggregateState::Moments(statistic, count, _, m2) => {
+                let variance = match statistic {
+                    Statistic::VarSamp | Statistic::StddevSamp if *count > 1 => {
+                        m2 / (*count - 1) as f64
+                    }
+                    Statistic::VarPop | Statistic::StddevPop if *count > 0 => m2 / *count as f64,
+                    _ => return Datum::Null,
+                };
+                match statistic {
+                    Statistic::VarSamp | Statistic::VarPop => Datum::Float(variance),
+                    Statistic::StddevSamp | Statistic::StddevPop => Datum::Float(variance.sqrt()),
+                }
+            }
+            AggregateState::Bool(value) => value.clone(),
+            AggregateState::JsonAgg(values) => {
+                if values.is_empty() {
                     Datum::Null
                 } else {
-                    value.clone()
+                    Datum::Json(Json::Array(values.clone()))
                 }
             }
-            AggregateState::MinMax(value) => value.clone(),
+            AggregateState::StringAgg(value) => value
+                .as_deref()
+                .map_or(Datum::Null, |value| Datum::String(value.into())),
+            AggregateState::UserDefined(value) => value.clone(),
+            AggregateState::Buffered(_) => unreachable!("buffered tuples are not accumulated"),
         }
     }
 }
 
+/// Result of accumulating a row, the functions that cannot fail can return
+/// nothing.
+pub trait IntoAccumulateResult {
+    fn into_accumulate_result(self) -> Result<(), SQLError>;
+}
+
+impl IntoAccumulateResult for () {
+    fn into_accumulate_result(self) -> Result<(), SQLError> {
+        Ok(())
+    }
+}
+
+impl IntoAccumulateResult for Result<(), SQLError> {
+    fn into_accumulate_result(self) -> Result<(), SQLError> {
+        self
+    }
+}
+
 #[allow(clippy::type_complexity)]
 pub struct AggregateFunction {
     pub name: String,
     pub arg_types: Vec<Type>,
     pub ret_type: Type,
     pub default_state: AggregateState,
-    pub accumulate: Box<dyn Fn(&[Datum], &AggregateState) -> AggregateState + Send + Sync>,
+    /// Accumulate the arguments of a row into the state in place.
+    pub accumulate:
+        Box<dyn Fn(&[Datum], &mut AggregateState) -> Result<(), SQLError> + Send + Sync>,
+    /// Compute the result from the state of a user-defined aggregate.
+    pub finalize: Option<Box<dyn Fn(&Datum) -> Datum + Send + Sync>>,
 }
 
-#[derive(Default)]
+impl AggregateFunction {
+    pub fn finalize(&self, state: &AggregateState) -> Datum {
+        match (state, &self.finalize) {
+            (AggregateState::UserDefined(value), Some(finalize)) => finalize(value),
+            (state, _) => state.finalize(),
+        }
+    }
+}
+
+/// Aggregate functions of a catalog, which are seeded from the builtin ones.
+#[derive(Default, Clone)]
 pub struct AggregateFunctionRegistry {
     pub functions: HashMap<String, Vec<Arc<AggregateFunction>>>,
 }
 
+impl Debug for AggregateFunctionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.functions.keys()).finish()
+    }
+}
+
 impl AggregateFunctionRegistry {
-    pub fn builtin() -> &'static AggregateFunctionRegistry {
-        &BUILTIN_AGGREGATE_FUNCTIONS
+    pub fn with_builtins() -> Self {
+        let mut registry = AggregateFunctionRegistry::default();
+        register_count(&mut registry);
+        register_sum(&mut registry);
+        register_avg(&mut registry);
+        register_min_max(&mut registry);
+        register_statistics(&mut registry);
+        register_bool_and_or(&mut registry);
+        register_json_agg(&mut registry);
+        register_array_string_agg(&mut registry);
+        registry
     }
 
     pub fn contains(&self, name: &str) -> bool {
         self.functions.contains_key(name)
     }
 
-    pub fn register_skip_null<F>(
+    pub fn register<F, R>(
+        &mut self,
+        name: &str,
+        arg_types: &[Type],
+        ret_type: Type,
+        default_state: AggregateState,
+        accumulate: F,
+    ) where
+        F: Fn(&[Datum], &mut AggregateState) -> R + Send + Sync + 'static,
+        R: IntoAccumulateResult,
+    {
+        let accumulate = move |args: &[Datum], state: &mut AggregateState| {
+            accumulate(args, state).into_accumulate_result()
+        };
+
+        let func = Arc::new(AggregateFunction {
+            name: name.to_string(),
+            arg_types: arg_types.to_vec(),
+            ret_type,
+            default_state,
+            accumulate: Box::new(accumulate),
+            finalize: None,
+        });
+        self.functions
+            .entry(name.to_string())
+            .or_insert_with(Vec::new)
+            .push(func);
+    }
+
+    pub fn register_skip_null<F, R>(
         &mut self,
         name: &str,
         arg_types: &[Type],
@@ -71,14 +182,15 @@ impl AggregateFunctionRegistry {
         default_state: AggregateState,
         accumulate: F,
     ) where
-        F: Fn(&[Datum], &AggregateState) -> AggregateState + Send + Sync + 'static,
+        F: Fn(&[Datum], &mut AggregateState) -> R + Send + Sync + 'static,
+        R: IntoAccumulateResult,
     {
-        let null_skipper = move |args: &[Datum], state: &AggregateState| {
+        let null_skipper = move |args: &[Datum], state: &mut AggregateState| {
             if args.iter().any(|arg| arg.is_null()) {
-                state.clone()
-            } else {
-                accumulate(args, state)
+                return Ok(());
             }
+
+            accumulate(args, state).into_accumulate_result()
         };
 
         let func = Arc::new(AggregateFunction {
@@ -87,6 +199,41 @@ impl AggregateFunctionRegistry {
             ret_type,
             default_state,
             accumulate: Box::new(null_skipper),
+            finalize: None,
+        });
+        self.functions
+            .entry(name.to_string())
+            .or_insert_with(Vec::new)
+            .push(func);
+    }
+
+    /// Register an aggregate function defined by the initial state, the
+    /// function accumulating the arguments of each row into the state, and
+    /// the function computing the result from the final state.
+    pub fn register_user_defined<A, R, F>(
+        &mut self,
+        name: &str,
+        arg_types: &[Type],
+        ret_type: Type,
+        init: Datum,
+        accumulate: A,
+        finalize: F,
+    ) where
+        A: Fn(&mut Datum, &[Datum]) -> R + Send + Sync + 'static,
+        R: IntoAccumulateResult,
+        F: Fn(&Datum) -> Datum + Send + Sync + 'static,
+    {
+        let accumulate = move |args: &[Datum], state: &mut AggregateState| {
+            accumulate(state.as_user_defined_mut().unwrap(), args).into_accumulate_result()
+        };
+
+        let func = Arc::new(AggregateFunction {
+            name: name.to_string(),
+            arg_types: arg_types.to_vec(),
+            ret_type,
+            default_state: AggregateState::UserDefined(init),
+            accumulate: Box::new(accumulate),
+            finalize: Some(Box::new(finalize)),
         });
         self.functions
             .entry(name.to_string())
@@ -105,10 +252,8 @@ fn register_count(registry: &mut AggregateFunctionRegistry) {
         &[],
         Type::Int,
         AggregateState::Count(0),
-        |_: &[Datum], state: &AggregateState| {
-            let state = state.as_count().unwrap();
-
-            AggregateState::Count(*state + 1)
+        |_: &[Datum], state: &mut AggregateState| {
+            *state.as_count_mut().unwrap() += 1;
         },
     );
     registry.register_skip_null(
@@ -116,10 +261,8 @@ fn register_count(registry: &mut AggregateFunctionRegistry) {
         &[Type::Any],
         Type::Int,
         AggregateState::Count(0),
-        |_: &[Datum], state: &AggregateState| {
-            let state = state.as_count().unwrap();
-
-            AggregateState::Count(*state + 1)
+        |_: &[Datum], state: &mut AggregateState| {
+            *state.as_count_mut().unwrap() += 1;
         },
     );
 }
@@ -130,16 +273,18 @@ fn register_sum(registry: &mut AggregateFunctionRegistry) {
         &[Type::Int],
         Type::Int,
         AggregateState::Sum(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let mut state = state.as_sum().unwrap().clone();
-
-            if matches!(state, Datum::Null) {
-                state = Datum::Int(0);
-            }
+        |args: &[Datum], state: &mut AggregateState| {
+            let state = state.as_sum_mut().unwrap();
+            let sum = state.as_int().copied().unwrap_or(0);
 
             let arg = args[0].as_int().unwrap();
 
-            AggregateState::Sum(Datum::Int(*state.as_int().unwrap() + arg))
+            let sum = sum.checked_add(*arg).ok_or_else(|| {
+                SQLError::new(ErrorKind::RuntimeError, "bigint out of range")
+                    .with_code(sqlstate::NUMERIC_VALUE_OUT_OF_RANGE)
+            })?;
+            *state = Datum::Int(sum);
+            Ok(())
         },
     );
     registry.register_skip_null(
@@ -147,16 +292,13 @@ fn register_sum(registry: &mut AggregateFunctionRegistry) {
         &[Type::Float],
         Type::Float,
         AggregateState::Sum(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let mut state = state.as_sum().unwrap().clone();
-
-            if matches!(state, Datum::Null) {
-                state = Datum::Float(0f64);
-            }
+        |args: &[Datum], state: &mut AggregateState| {
+            let state = state.as_sum_mut().unwrap();
+            let sum = state.as_float().copied().unwrap_or(0f64);
 
             let arg = args[0].as_float().unwrap();
 
-            AggregateState::Sum(Datum::Float(*state.as_float().unwrap() + arg))
+            *state = Datum::Float(sum + arg);
         },
     );
 }
@@ -167,19 +309,13 @@ fn register_avg(registry: &mut AggregateFunctionRegistry) {
         &[Type::Int],
         Type::Float,
         AggregateState::Avg(Datum::Null, 0),
-        |args: &[Datum], state: &AggregateState| {
-            let mut state = state.clone().into_avg().unwrap();
-
-            if matches!(state.0, Datum::Null) {
-                state.0 = Datum::Float(0f64);
-            }
+        |args: &[Datum], state: &mut AggregateState| {
+            let (sum, count) = state.as_avg_mut().unwrap();
 
             let arg = args[0].as_int().unwrap();
 
-            AggregateState::Avg(
-                Datum::Float(*state.0.as_float().unwrap() + *arg as f64),
-                state.1 + 1,
-            )
+            *sum = Datum::Float(sum.as_float().copied().unwrap_or(0f64) + *arg as f64);
+            *count += 1;
         },
     );
     registry.register_skip_null(
@@ -187,150 +323,162 @@ fn register_avg(registry: &mut AggregateFunctionRegistry) {
         &[Type::Float],
         Type::Float,
         AggregateState::Avg(Datum::Null, 0),
-        |args: &[Datum], state: &AggregateState| {
-            let mut state = state.clone().into_avg().unwrap();
-
-            if matches!(state.0, Datum::Null) {
-                state.0 = Datum::Float(0f64);
-            }
+        |args: &[Datum], state: &mut AggregateState| {
+            let (sum, count) = state.as_avg_mut().unwrap();
 
             let arg = args[0].as_float().unwrap();
 
-            AggregateState::Avg(
-                Datum::Float(*state.0.as_float().unwrap() + arg),
-                state.1 + 1,
-            )
+            *sum = Datum::Float(sum.as_float().copied().unwrap_or(0f64) + arg);
+            *count += 1;
         },
     );
 }
 
+/// The values are compared in the total order of `Datum`, which is also the
+/// order of sorting.
 fn register_min_max(registry: &mut AggregateFunctionRegistry) {
-    // Min
-    registry.register_skip_null(
-        "min",
-        &[Type::Int],
-        Type::Int,
-        AggregateState::MinMax(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let s = state.as_min_max().unwrap();
-
-            let arg = args[0].as_int().unwrap();
-
-            if matches!(state, AggregateState::MinMax(Datum::Null)) {
-                return AggregateState::MinMax(Datum::Int(*arg));
-            };
-
-            if arg < s.as_int().unwrap() {
-                AggregateState::MinMax(Datum::Int(*arg))
-            } else {
-                AggregateState::MinMax(s.clone())
-            }
-        },
-    );
-    registry.register_skip_null(
-        "min",
-        &[Type::Float],
-        Type::Float,
-        AggregateState::MinMax(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let s = state.as_min_max().unwrap();
-
-            let arg = args[0].as_float().unwrap();
+    for typ in [Type::Int, Type::Float, Type::String] {
+        registry.register_skip_null(
+            "min",
+            &[typ.clone()],
+            typ.clone(),
+            AggregateState::MinMax(Datum::Null),
+            |args: &[Datum], state: &mut AggregateState| {
+                let s = state.as_min_max_mut().unwrap();
+                if s.is_null() || args[0] < *s {
+                    *s = args[0].clone();
+                }
+            },
+        );
+        registry.register_skip_null(
+            "max",
+            &[typ.clone()],
+            typ,
+            AggregateState::MinMax(Datum::Null),
+            |args: &[Datum], state: &mut AggregateState| {
+                let s = state.as_min_max_mut().unwrap();
+                if s.is_null() || args[0] > *s {
+                    *s = args[0].clone();
+                }
+            },
+        );
+    }
+}
 
-            if matches!(state, AggregateState::MinMax(Datum::Null)) {
-                return AggregateState::MinMax(Datum::Float(*arg));
-            };
+fn register_statistics(registry: &mut AggregateFunctionRegistry) {
+    for (name, statistic) in [
+        ("stddev", Statistic::StddevSamp),
+        ("stddev_samp", Statistic::StddevSamp),
+        ("stddev_pop", Statistic::StddevPop),
+        ("variance", Statistic::VarSamp),
+        ("var_samp", Statistic::VarSamp),
+        ("var_pop", Statistic::VarPop),
+    ] {
+        for arg_type in [Type::Int, Type::Float] {
+            registry.register_skip_null(
+                name,
+                &[arg_type],
+                Type::Float,
+                AggregateState::Moments(statistic, 0, 0f64, 0f64),
+                |args: &[Datum], state: &mut AggregateState| {
+                    let (_, count, mean, m2) = state.as_moments_mut().unwrap();
+
+                    let arg = match &args[0] {
+                        Datum::Int(v) => *v as f64,
+                        arg => *arg.as_float().unwrap(),
+                    };
+
+                    *count += 1;
+                    let delta = arg - *mean;
+                    *mean += delta / *count as f64;
+                    *m2 += delta * (arg - *mean);
+                },
+            );
+        }
+    }
+}
 
-            if arg < s.as_float().unwrap() {
-                AggregateState::MinMax(Datum::Float(*arg))
-            } else {
-                AggregateState::MinMax(s.clone())
-            }
-        },
-    );
+/// `every` is the standard name of `bool_and`.
+fn register_bool_and_or(registry: &mut AggregateFunctionRegistry) {
+    for name in ["bool_and", "every"] {
+        registry.register_skip_null(
+            name,
+            &[Type::Boolean],
+            Type::Boolean,
+            AggregateState::Bool(Datum::Null),
+            |args: &[Datum], state: &mut AggregateState| {
+                let s = state.as_bool_mut().unwrap();
+
+                let arg = args[0].as_boolean().unwrap();
+
+                *s = Datum::Boolean(*arg && s.as_boolean().copied().unwrap_or(true));
+            },
+        );
+    }
     registry.register_skip_null(
-        "min",
-        &[Type::String],
-        Type::String,
-        AggregateState::MinMax(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let s = state.as_min_max().unwrap();
+        "bool_or",
+        &[Type::Boolean],
+        Type::Boolean,
+        AggregateState::Bool(Datum::Null),
+        |args: &[Datum], state: &mut AggregateState| {
+            let s = state.as_bool_mut().unwrap();
 
-            let arg = args[0].as_string().unwrap();
+            let arg = args[0].as_boolean().unwrap();
 
-            if matches!(state, AggregateState::MinMax(Datum::Null)) {
-                return AggregateState::MinMax(Datum::String(arg.clone()));
-            };
-
-            if arg < s.as_string().unwrap() {
-                AggregateState::MinMax(Datum::String(arg.clone()))
-            } else {
-                AggregateState::MinMax(s.clone())
-            }
+            *s = Datum::Boolean(*arg || s.as_boolean().copied().unwrap_or(false));
         },
     );
+}
 
-    // Max
-    registry.register_skip_null(
-        "max",
-        &[Type::Int],
-        Type::Int,
-        AggregateState::MinMax(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let s = state.as_min_max().unwrap();
-
-            let arg = args[0].as_int().unwrap();
-
-            if matches!(state, AggregateState::MinMax(Datum::Null)) {
-                return AggregateState::MinMax(Datum::Int(*arg));
-            };
-
-            if arg > s.as_int().unwrap() {
-                AggregateState::MinMax(Datum::Int(*arg))
-            } else {
-                AggregateState::MinMax(s.clone())
-            }
+/// NULL values are aggregated as JSON null, rather than skipped.
+fn register_json_agg(registry: &mut AggregateFunctionRegistry) {
+    registry.register(
+        "json_agg",
+        &[Type::Any],
+        Type::Json,
+        AggregateState::JsonAgg(vec![]),
+        |args: &[Datum], state: &mut AggregateState| {
+            state.as_json_agg_mut().unwrap().push(Json::from(&args[0]));
         },
     );
-    registry.register_skip_null(
-        "max",
-        &[Type::Float],
-        Type::Float,
-        AggregateState::MinMax(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let s = state.as_min_max().unwrap();
-
-            let arg = args[0].as_float().unwrap();
-
-            if matches!(state, AggregateState::MinMax(Datum::Null)) {
-                return AggregateState::MinMax(Datum::Float(*arg));
-            };
+}
 
-            if arg > s.as_float().unwrap() {
-                AggregateState::MinMax(Datum::Float(*arg))
-            } else {
-                AggregateState::MinMax(s.clone())
-            }
-        },
-    );
-    registry.register_skip_null(
-        "max",
-        &[Type::String],
+/// `array_agg` keeps NULL values as elements while `string_agg` skips them,
+/// the result of no rows is NULL rather than an empty array or string.
+fn register_array_string_agg(registry: &mut AggregateFunctionRegistry) {
+    for typ in [Type::Int, Type::Float, Type::String, Type::Boolean] {
+        registry.register_user_defined(
+            "array_agg",
+            &[typ.clone()],
+            Type::Array(Box::new(typ)),
+            Datum::Array(vec![]),
+            |state: &mut Datum, args: &[Datum]| {
+                state.as_array_mut().unwrap().push(args[0].clone());
+            },
+            |state: &Datum| match state {
+                Datum::Array(values) if values.is_empty() => Datum::Null,
+                state => state.clone(),
+            },
+        );
+    }
+    registry.register(
+        "string_agg",
+        &[Type::String, Type::String],
         Type::String,
-        AggregateState::MinMax(Datum::Null),
-        |args: &[Datum], state: &AggregateState| {
-            let s = state.as_min_max().unwrap();
-
-            let arg = args[0].as_string().unwrap();
-
-            if matches!(state, AggregateState::MinMax(Datum::Null)) {
-                return AggregateState::MinMax(Datum::String(arg.clone()));
+        AggregateState::StringAgg(None),
+        |args: &[Datum], state: &mut AggregateState| {
+            let Datum::String(value) = &args[0] else {
+                return;
             };
-
-            if arg > s.as_string().unwrap() {
-                AggregateState::MinMax(Datum::String(arg.clone()))
-            } else {
-                AggregateState::MinMax(s.clone())
+            match state.as_string_agg_mut().unwrap() {
+                Some(prefix) => {
+                    // NULL delimiter is the same as empty string
+                    if let Datum::String(delimiter) = &args[1] {
+                        prefix.push_str(delimiter);
+                    }
+                    prefix.push_str(value);
+                }
+                state => *state = Some(value.to_string()),
             }
         },
     );
@@ -1,16 +1,45 @@
-use std::{collections::HashMap, sync::Arc};
-
-use crate::core::{Datum, Type};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::{
+    catalog::{
+        pg_catalog::{format_type, SystemCatalog, OWNER_NAME, OWNER_OID},
+        Catalog,
+    },
+    core::{
+        array_element_text, parse_array, sqlstate, Datum, ErrorKind, IntWidth, Json, SQLError, Type,
+    },
+    sql::session::{
+        activity::{ActivityRegistry, Interrupts},
+        to_pg_type,
+    },
+};
 
 lazy_static! {
-    static ref BUILTIN_SCALAR_FUNCTIONS: ScalarFunctionRegistry = {
+    static ref BUILTIN_SCALAR_FUNCTIONS: Arc<ScalarFunctionRegistry> = {
         let mut registry = ScalarFunctionRegistry::default();
 
         register_arithmetic_functions(&mut registry);
         register_comparison_functions(&mut registry);
+        register_logical_functions(&mut registry);
+        register_null_functions(&mut registry);
+        register_pattern_matching_functions(&mut registry);
+        register_uuid_functions(&mut registry);
+        register_bytea_functions(&mut registry);
+        register_json_functions(&mut registry);
+        register_array_functions(&mut registry);
+        register_system_functions(&mut registry);
+        register_random_functions(&mut registry);
         register_cast_functions(&mut registry);
 
-        registry
+        Arc::new(registry)
     };
 }
 
@@ -19,35 +48,103 @@ pub struct ScalarFunction {
     pub name: String,
     pub arg_types: Vec<Type>,
     pub ret_type: Type,
-    pub eval: Box<dyn Fn(&[Datum]) -> Datum + Send + Sync>,
+    pub volatility: Volatility,
+    pub eval: Box<dyn Fn(&[Datum]) -> Result<Datum, SQLError> + Send + Sync>,
+    /// Create an instance of the function with fresh state, for each call of
+    /// the function in a query.
+    pub instantiate: Option<Box<dyn Fn() -> ScalarFunction + Send + Sync>>,
 }
 
-#[derive(Default)]
+impl ScalarFunction {
+    /// The function to evaluate a call in a query, which is a new instance if
+    /// the function holds state across rows.
+    pub fn instance(self: &Arc<Self>) -> Arc<Self> {
+        match &self.instantiate {
+            Some(instantiate) => Arc::new(instantiate()),
+            None => self.clone(),
+        }
+    }
+}
+
+/// Whether the result of function may change with the same arguments, like
+/// the volatility categories of Postgres.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Volatility {
+    /// Always returns the same result, so the calls with constant arguments
+    /// are evaluated while planning.
+    Immutable,
+    /// Returns the same result within a statement, e.g. reading the catalog.
+    Stable,
+    /// The result may change on every call, e.g. `gen_random_uuid()`.
+    Volatile,
+}
+
+/// Result of evaluating a function, the functions that cannot fail can
+/// return the datum directly.
+pub trait IntoEvalResult {
+    fn into_eval_result(self) -> Result<Datum, SQLError>;
+}
+
+impl IntoEvalResult for Datum {
+    fn into_eval_result(self) -> Result<Datum, SQLError> {
+        Ok(self)
+    }
+}
+
+impl IntoEvalResult for Result<Datum, SQLError> {
+    fn into_eval_result(self) -> Result<Datum, SQLError> {
+        self
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct ScalarFunctionRegistry {
     /// function name -> overloads
     pub functions: HashMap<String, Vec<Arc<ScalarFunction>>>,
 }
 
+impl Debug for ScalarFunctionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.functions.keys()).finish()
+    }
+}
+
 impl ScalarFunctionRegistry {
-    pub fn builtin() -> &'static ScalarFunctionRegistry {
+    /// The builtin functions, which seed the registry of each catalog.
+    pub fn builtin() -> &'static Arc<ScalarFunctionRegistry> {
         &BUILTIN_SCALAR_FUNCTIONS
     }
 
-    #[allow(dead_code)]
+    /// Functions reading the catalog, which is snapshotted on the first call,
+    /// and the functions signaling the other sessions.
+    pub fn catalog(
+        catalog: Arc<RwLock<Catalog>>,
+        current_database: String,
+        current_schema: String,
+        activity: Arc<ActivityRegistry>,
+    ) -> Self {
+        let mut registry = ScalarFunctionRegistry::default();
+        register_catalog_functions(&mut registry, catalog, current_database, current_schema);
+        register_signal_functions(&mut registry, activity);
+        registry
+    }
+
     pub fn contains(&self, name: &str) -> bool {
         self.functions.contains_key(name)
     }
 
-    #[allow(dead_code)]
-    pub fn register<F>(&mut self, name: &str, arg_types: &[Type], ret_type: Type, func: F)
+    pub fn register<F, R>(&mut self, name: &str, arg_types: &[Type], ret_type: Type, func: F)
     where
-        F: Fn(&[Datum]) -> Datum + Send + Sync + 'static,
+        F: Fn(&[Datum]) -> R + Send + Sync + 'static,
+        R: IntoEvalResult,
     {
         let scalar_func = ScalarFunction {
             name: name.to_string(),
             arg_types: arg_types.to_vec(),
             ret_type,
-            eval: Box::new(func),
+            volatility: Volatility::Immutable,
+            eval: Box::new(move |args| func(args).into_eval_result()),
+            instantiate: None,
         };
 
         self.functions
@@ -56,48 +153,114 @@ impl ScalarFunctionRegistry {
             .push(Arc::new(scalar_func));
     }
 
-    pub fn register_null_passthrough<F>(
+    pub fn register_null_passthrough<F, R>(
         &mut self,
         name: &str,
         arg_types: &[Type],
         ret_type: Type,
         func: F,
     ) where
-        F: Fn(&[Datum]) -> Datum + Send + Sync + 'static,
+        F: Fn(&[Datum]) -> R + Send + Sync + 'static,
+        R: IntoEvalResult,
     {
         let null_passthrough_func = move |args: &[Datum]| {
             if args.iter().any(|arg| arg.is_null()) {
-                return Datum::Null;
+                return Ok(Datum::Null);
             }
 
-            func(args)
+            func(args).into_eval_result()
         };
 
         let scalar_func = ScalarFunction {
             name: name.to_string(),
             arg_types: arg_types.to_vec(),
             ret_type,
+            volatility: Volatility::Immutable,
             eval: Box::new(null_passthrough_func),
+            instantiate: None,
+        };
+
+        self.functions
+            .entry(name.to_string())
+            .or_insert_with(Vec::new)
+            .push(Arc::new(scalar_func));
+    }
+
+    /// Register a volatile function with state, which is initialized for each
+    /// call of the function in a query, and updated by every evaluation.
+    pub fn register_stateful<S, F, R>(
+        &mut self,
+        name: &str,
+        arg_types: &[Type],
+        ret_type: Type,
+        init: fn() -> S,
+        func: F,
+    ) where
+        S: Send + 'static,
+        F: Fn(&mut S, &[Datum]) -> R + Send + Sync + 'static,
+        R: IntoEvalResult,
+    {
+        let func = Arc::new(func);
+        let (func_name, func_arg_types) = (name.to_string(), arg_types.to_vec());
+        let instantiate = move || {
+            let state = Mutex::new(init());
+            let func = func.clone();
+            ScalarFunction {
+                name: func_name.clone(),
+                arg_types: func_arg_types.clone(),
+                ret_type: ret_type.clone(),
+                volatility: Volatility::Volatile,
+                eval: Box::new(move |args| {
+                    func(&mut state.lock().unwrap(), args).into_eval_result()
+                }),
+                instantiate: None,
+            }
         };
 
+        let scalar_func = ScalarFunction {
+            instantiate: Some(Box::new(instantiate.clone())),
+            ..instantiate()
+        };
         self.functions
             .entry(name.to_string())
             .or_insert_with(Vec::new)
             .push(Arc::new(scalar_func));
     }
 
+    /// Set the volatility of all the overloads of function, the functions are
+    /// registered as immutable.
+    pub fn set_volatility(&mut self, name: &str, volatility: Volatility) {
+        for func in self.functions.get_mut(name).into_iter().flatten() {
+            Arc::get_mut(func)
+                .expect("function is set up before being shared")
+                .volatility = volatility;
+        }
+    }
+
+    /// Remove all the overloads of function.
+    pub fn unregister(&mut self, name: &str) {
+        self.functions.remove(name);
+    }
+
     pub fn search_candidates(&self, name: &str) -> Vec<Arc<ScalarFunction>> {
         self.functions.get(name).cloned().unwrap_or_default()
     }
 }
 
+fn integer_out_of_range() -> SQLError {
+    SQLError::new(ErrorKind::RuntimeError, "integer out of range")
+        .with_code(sqlstate::NUMERIC_VALUE_OUT_OF_RANGE)
+}
+
 pub fn register_arithmetic_functions(registry: &mut ScalarFunctionRegistry) {
     // Plus
     registry.register_null_passthrough("+", &[Type::Int, Type::Int], Type::Int, |args| {
         let left = args[0].as_int().unwrap();
         let right = args[1].as_int().unwrap();
 
-        Datum::Int(left + right)
+        left.checked_add(*right)
+            .map(Datum::Int)
+            .ok_or_else(integer_out_of_range)
     });
     registry.register_null_passthrough("+", &[Type::Float, Type::Float], Type::Float, |args| {
         let left = args[0].as_float().unwrap();
@@ -111,7 +274,9 @@ pub fn register_arithmetic_functions(registry: &mut ScalarFunctionRegistry) {
         let left = args[0].as_int().unwrap();
         let right = args[1].as_int().unwrap();
 
-        Datum::Int(left + right)
+        left.checked_sub(*right)
+            .map(Datum::Int)
+            .ok_or_else(integer_out_of_range)
     });
     registry.register_null_passthrough("-", &[Type::Float, Type::Float], Type::Float, |args| {
         let left = args[0].as_float().unwrap();
@@ -119,6 +284,25 @@ pub fn register_arithmetic_functions(registry: &mut ScalarFunctionRegistry) {
 
         Datum::Float(left - right)
     });
+
+    // Negation
+    registry.register_null_passthrough("neg", &[Type::Int], Type::Int, |args| {
+        let value = args[0].as_int().unwrap();
+
+        value
+            .checked_neg()
+            .map(Datum::Int)
+            .ok_or_else(integer_out_of_range)
+    });
+    registry.register_null_passthrough("neg", &[Type::Float], Type::Float, |args| {
+        let value = args[0].as_float().unwrap();
+
+        Datum::Float(-value)
+    });
+
+    // Identity
+    registry.register_null_passthrough("pos", &[Type::Int], Type::Int, |args| args[0].clone());
+    registry.register_null_passthrough("pos", &[Type::Float], Type::Float, |args| args[0].clone());
 }
 
 pub fn register_comparison_functions(registry: &mut ScalarFunctionRegistry) {
@@ -280,6 +464,899 @@ pub fn register_comparison_functions(registry: &mut ScalarFunctionRegistry) {
     );
 }
 
+/// Logical functions follow the three-valued logic of SQL, so they
+/// are not registered with null passthrough.
+pub fn register_logical_functions(registry: &mut ScalarFunctionRegistry) {
+    registry.register(
+        "and",
+        &[Type::Boolean, Type::Boolean],
+        Type::Boolean,
+        |args| match (&args[0], &args[1]) {
+            (Datum::Boolean(false), _) | (_, Datum::Boolean(false)) => Datum::Boolean(false),
+            (Datum::Boolean(true), Datum::Boolean(true)) => Datum::Boolean(true),
+            _ => Datum::Null,
+        },
+    );
+    registry.register(
+        "or",
+        &[Type::Boolean, Type::Boolean],
+        Type::Boolean,
+        |args| match (&args[0], &args[1]) {
+            (Datum::Boolean(true), _) | (_, Datum::Boolean(true)) => Datum::Boolean(true),
+            (Datum::Boolean(false), Datum::Boolean(false)) => Datum::Boolean(false),
+            _ => Datum::Null,
+        },
+    );
+    registry.register_null_passthrough("not", &[Type::Boolean], Type::Boolean, |args| {
+        let value = args[0].as_boolean().unwrap();
+
+        Datum::Boolean(!value)
+    });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn register_null_functions(registry: &mut ScalarFunctionRegistry) {
+    registry.register("is_null", &[Type::Any], Type::Boolean, |args| {
+        Datum::Boolean(args[0].is_null())
+    });
+    registry.register("is_not_null", &[Type::Any], Type::Boolean, |args| {
+        Datum::Boolean(!args[0].is_null())
+    });
+
+    // Boolean tests never return NULL, the unknown value is NULL
+    let tests: [(&str, fn(&Datum) -> bool); 6] = [
+        ("is_true", |value| matches!(value, Datum::Boolean(true))),
+        ("is_not_true", |value| {
+            !matches!(value, Datum::Boolean(true))
+        }),
+        ("is_false", |value| matches!(value, Datum::Boolean(false))),
+        ("is_not_false", |value| {
+            !matches!(value, Datum::Boolean(false))
+        }),
+        ("is_unknown", Datum::is_null),
+        ("is_not_unknown", |value| !value.is_null()),
+    ];
+    for (name, test) in tests {
+        registry.register(name, &[Type::Boolean], Type::Boolean, move |args| {
+            Datum::Boolean(test(&args[0]))
+        });
+    }
+
+    // Null-safe comparisons, two NULLs are not distinct from each other,
+    // and NULL is distinct from any other value.
+    for typ in [
+        Type::Int,
+        Type::Float,
+        Type::String,
+        Type::Boolean,
+        Type::Uuid,
+        Type::Json,
+        Type::Bytea,
+    ] {
+        let arg_types = [typ.clone(), typ];
+        registry.register("is_distinct_from", &arg_types, Type::Boolean, |args| {
+            Datum::Boolean(is_distinct(&args[0], &args[1]))
+        });
+        registry.register("is_not_distinct_from", &arg_types, Type::Boolean, |args| {
+            Datum::Boolean(!is_distinct(&args[0], &args[1]))
+        });
+    }
+}
+
+/// Values are compared in the same way as `=` except for NULLs, e.g. floats
+/// are compared by value rather than by bits.
+fn is_distinct(left: &Datum, right: &Datum) -> bool {
+    match (left, right) {
+        (Datum::Float(left), Datum::Float(right)) => left != right,
+        _ => left != right,
+    }
+}
+
+/// Cache of the last compiled pattern, since the pattern is usually a constant.
+#[derive(Default)]
+struct PatternCache {
+    /// (pattern key, compiled regex or the error of invalid pattern)
+    last: Mutex<Option<(String, Result<Regex, SQLError>)>>,
+}
+
+impl PatternCache {
+    /// Match the text with the pattern identified by `key`, the pattern will be
+    /// compiled with `compile` if it's not cached.
+    fn is_match<F>(&self, key: &str, compile: F, text: &str) -> Result<bool, SQLError>
+    where
+        F: FnOnce() -> Result<Regex, SQLError>,
+    {
+        let mut last = self.last.lock().unwrap();
+        if !matches!(last.as_ref(), Some((last_key, _)) if last_key == key) {
+            *last = Some((key.to_string(), compile()));
+        }
+
+        match last.as_ref().map(|(_, regex)| regex) {
+            Some(Ok(regex)) => Ok(regex.is_match(text)),
+            Some(Err(e)) => Err(e.clone()),
+            None => unreachable!("the pattern is compiled above"),
+        }
+    }
+}
+
+fn compile_regex(regex: &str) -> Result<Regex, SQLError> {
+    Regex::new(regex).map_err(|e| {
+        SQLError::new(
+            ErrorKind::RuntimeError,
+            format!("invalid regular expression: {}", e),
+        )
+        .with_code(sqlstate::INVALID_REGULAR_EXPRESSION)
+    })
+}
+
+/// Translate a `LIKE` pattern into an anchored regex, `%` matches any sequence
+/// of characters and `_` matches any single character.
+fn like_to_regex(
+    pattern: &str,
+    escape: Option<char>,
+    case_insensitive: bool,
+) -> Result<Regex, SQLError> {
+    let mut regex = String::from(if case_insensitive { "(?is)^" } else { "(?s)^" });
+
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if Some(c) == escape => {
+                let c = chars.next().ok_or_else(|| {
+                    SQLError::new(
+                        ErrorKind::RuntimeError,
+                        "LIKE pattern must not end with escape character",
+                    )
+                    .with_code(sqlstate::INVALID_ESCAPE_SEQUENCE)
+                })?;
+                regex.push_str(&regex::escape(&c.to_string()))
+            }
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+
+    compile_regex(&regex)
+}
+
+/// Pattern matching functions are named after the operators of Postgres.
+/// Invalid patterns fail rather than not matching.
+pub fn register_pattern_matching_functions(registry: &mut ScalarFunctionRegistry) {
+    // (name, case insensitive, negated)
+    let like_functions = [
+        // LIKE
+        ("~~", false, false),
+        // ILIKE
+        ("~~*", true, false),
+        // NOT LIKE
+        ("!~~", false, true),
+        // NOT ILIKE
+        ("!~~*", true, true),
+    ];
+    for (name, case_insensitive, negated) in like_functions {
+        // Backslash is the default escape character
+        let cache = PatternCache::default();
+        registry.register_null_passthrough(
+            name,
+            &[Type::String, Type::String],
+            Type::Boolean,
+            move |args| {
+                let text = args[0].as_string().unwrap();
+                let pattern = args[1].as_string().unwrap();
+
+                let matched = cache.is_match(
+                    pattern,
+                    || like_to_regex(pattern, Some('\\'), case_insensitive),
+                    text,
+                )?;
+                Ok(Datum::Boolean(matched != negated))
+            },
+        );
+
+        // With `ESCAPE` clause, an empty escape string disables the escape mechanism
+        let cache = PatternCache::default();
+        registry.register_null_passthrough(
+            name,
+            &[Type::String, Type::String, Type::String],
+            Type::Boolean,
+            move |args| {
+                let text = args[0].as_string().unwrap();
+                let pattern = args[1].as_string().unwrap();
+                let escape = args[2].as_string().unwrap();
+                if escape.chars().count() > 1 {
+                    return Err(
+                        SQLError::new(ErrorKind::RuntimeError, "invalid escape string")
+                            .with_code(sqlstate::INVALID_ESCAPE_SEQUENCE),
+                    );
+                }
+
+                let matched = cache.is_match(
+                    &format!("{}{}", escape, pattern),
+                    || like_to_regex(pattern, escape.chars().next(), case_insensitive),
+                    text,
+                )?;
+                Ok(Datum::Boolean(matched != negated))
+            },
+        );
+    }
+
+    // (name, case insensitive, negated)
+    let regex_functions = [
+        ("~", false, false),
+        ("~*", true, false),
+        ("!~", false, true),
+        ("!~*", true, true),
+    ];
+    for (name, case_insensitive, negated) in regex_functions {
+        let cache = PatternCache::default();
+        registry.register_null_passthrough(
+            name,
+            &[Type::String, Type::String],
+            Type::Boolean,
+            move |args| {
+                let text = args[0].as_string().unwrap();
+                let pattern = args[1].as_string().unwrap();
+
+                let matched = cache.is_match(
+                    pattern,
+                    || {
+                        if case_insensitive {
+                            compile_regex(&format!("(?i){}", pattern))
+                        } else {
+                            compile_regex(pattern)
+                        }
+                    },
+                    text,
+                )?;
+                Ok(Datum::Boolean(matched != negated))
+            },
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn register_uuid_functions(registry: &mut ScalarFunctionRegistry) {
+    let comparisons: [(&str, fn(&Uuid, &Uuid) -> bool); 6] = [
+        ("=", Uuid::eq),
+        ("<>", Uuid::ne),
+        ("<", Uuid::lt),
+        ("<=", Uuid::le),
+        (">", Uuid::gt),
+        (">=", Uuid::ge),
+    ];
+    for (name, cmp) in comparisons {
+        registry.register_null_passthrough(
+            name,
+            &[Type::Uuid, Type::Uuid],
+            Type::Boolean,
+            move |args| {
+                let left = args[0].as_uuid().unwrap();
+                let right = args[1].as_uuid().unwrap();
+
+                Datum::Boolean(cmp(left, right))
+            },
+        );
+    }
+
+    registry.register("gen_random_uuid", &[], Type::Uuid, |_| {
+        Datum::Uuid(Uuid::new_v4())
+    });
+    registry.set_volatility("gen_random_uuid", Volatility::Volatile);
+}
+
+/// Binary strings are compared byte by byte.
+#[allow(clippy::type_complexity)]
+pub fn register_bytea_functions(registry: &mut ScalarFunctionRegistry) {
+    let comparisons: [(&str, fn(&[u8], &[u8]) -> bool); 6] = [
+        ("=", <[u8]>::eq),
+        ("<>", <[u8]>::ne),
+        ("<", <[u8]>::lt),
+        ("<=", <[u8]>::le),
+        (">", <[u8]>::gt),
+        (">=", <[u8]>::ge),
+    ];
+    for (name, cmp) in comparisons {
+        registry.register_null_passthrough(
+            name,
+            &[Type::Bytea, Type::Bytea],
+            Type::Boolean,
+            move |args| {
+                let left = args[0].as_bytea().unwrap();
+                let right = args[1].as_bytea().unwrap();
+
+                Datum::Boolean(cmp(left, right))
+            },
+        );
+    }
+}
+
+/// Parse the path of `#>` and `#>>` in form of text array, e.g. `{a,0}`.
+fn parse_json_path(path: &str) -> Option<Vec<String>> {
+    parse_array(path)?.into_iter().collect()
+}
+
+/// JSON operators are named after the operators of Postgres. The result
+/// is NULL if the key, index or path doesn't exist.
+pub fn register_json_functions(registry: &mut ScalarFunctionRegistry) {
+    // Get object field by key, or array element by index
+    registry.register_null_passthrough("->", &[Type::Json, Type::String], Type::Json, |args| {
+        let value = args[0].as_json().unwrap();
+        let key = args[1].as_string().unwrap();
+
+        value.field(key).cloned().map_or(Datum::Null, Datum::Json)
+    });
+    registry.register_null_passthrough("->", &[Type::Json, Type::Int], Type::Json, |args| {
+        let value = args[0].as_json().unwrap();
+        let index = args[1].as_int().unwrap();
+
+        value
+            .element(*index)
+            .cloned()
+            .map_or(Datum::Null, Datum::Json)
+    });
+
+    // Same as `->` but returns text
+    registry.register_null_passthrough("->>", &[Type::Json, Type::String], Type::String, |args| {
+        let value = args[0].as_json().unwrap();
+        let key = args[1].as_string().unwrap();
+
+        value
+            .field(key)
+            .and_then(Json::to_text)
+            .map_or(Datum::Null, |text| Datum::String(text.into()))
+    });
+    registry.register_null_passthrough("->>", &[Type::Json, Type::Int], Type::String, |args| {
+        let value = args[0].as_json().unwrap();
+        let index = args[1].as_int().unwrap();
+
+        value
+            .element(*index)
+            .and_then(Json::to_text)
+            .map_or(Datum::Null, |text| Datum::String(text.into()))
+    });
+
+    // Get value at the path
+    registry.register_null_passthrough("#>", &[Type::Json, Type::String], Type::Json, |args| {
+        let value = args[0].as_json().unwrap();
+        let path = args[1].as_string().unwrap();
+
+        parse_json_path(path)
+            .and_then(|path| value.path(&path).cloned())
+            .map_or(Datum::Null, Datum::Json)
+    });
+
+    // Same as `#>` but returns text
+    registry.register_null_passthrough("#>>", &[Type::Json, Type::String], Type::String, |args| {
+        let value = args[0].as_json().unwrap();
+        let path = args[1].as_string().unwrap();
+
+        parse_json_path(path)
+            .and_then(|path| value.path(&path).and_then(Json::to_text))
+            .map_or(Datum::Null, |text| Datum::String(text.into()))
+    });
+}
+
+pub fn register_array_functions(registry: &mut ScalarFunctionRegistry) {
+    let any_array = Type::Array(Box::new(Type::Any));
+
+    // Only one-dimensional arrays are supported, the length of other
+    // dimensions is NULL, so is the length of empty arrays.
+    registry.register_null_passthrough(
+        "array_length",
+        &[any_array.clone(), Type::Int],
+        Type::Int,
+        |args| {
+            let values = args[0].as_array().unwrap();
+            let dimension = args[1].as_int().unwrap();
+
+            if *dimension == 1 && !values.is_empty() {
+                Datum::Int(values.len() as i64)
+            } else {
+                Datum::Null
+            }
+        },
+    );
+
+    // Arrays are equal if all the elements are equal, NULL elements are equal
+    // to each other. Elements of array literals are text, which are cast to
+    // the types of the other side.
+    let array_equal = |left: &[Datum], right: &[Datum]| {
+        left.len() == right.len()
+            && left
+                .iter()
+                .zip(right)
+                .all(|(l, r)| l == r || r.cast(&l.typ()) == *l || l.cast(&r.typ()) == *r)
+    };
+    registry.register_null_passthrough(
+        "=",
+        &[any_array.clone(), any_array.clone()],
+        Type::Boolean,
+        move |args| {
+            Datum::Boolean(array_equal(
+                args[0].as_array().unwrap(),
+                args[1].as_array().unwrap(),
+            ))
+        },
+    );
+    registry.register_null_passthrough(
+        "<>",
+        &[any_array.clone(), any_array.clone()],
+        Type::Boolean,
+        move |args| {
+            Datum::Boolean(!array_equal(
+                args[0].as_array().unwrap(),
+                args[1].as_array().unwrap(),
+            ))
+        },
+    );
+
+    // `value = ANY(array)`, which is NULL if no element is equal but some
+    // element is NULL, like `IN`
+    registry.register(
+        "= any",
+        &[Type::Any, any_array.clone()],
+        Type::Boolean,
+        |args| {
+            let (value, Datum::Array(values)) = (&args[0], &args[1]) else {
+                return Datum::Null;
+            };
+            if value.is_null() {
+                Datum::Null
+            } else if values.contains(value) {
+                Datum::Boolean(true)
+            } else if values.iter().any(Datum::is_null) {
+                Datum::Null
+            } else {
+                Datum::Boolean(false)
+            }
+        },
+    );
+
+    // Containment, NULL elements are never contained
+    let contains = |values: &[Datum], elements: &[Datum]| {
+        elements
+            .iter()
+            .all(|element| !element.is_null() && values.contains(element))
+    };
+    registry.register_null_passthrough(
+        "@>",
+        &[any_array.clone(), any_array.clone()],
+        Type::Boolean,
+        move |args| {
+            let left = args[0].as_array().unwrap();
+            let right = args[1].as_array().unwrap();
+
+            Datum::Boolean(contains(left, right))
+        },
+    );
+    registry.register_null_passthrough(
+        "<@",
+        &[any_array.clone(), any_array],
+        Type::Boolean,
+        move |args| {
+            let left = args[0].as_array().unwrap();
+            let right = args[1].as_array().unwrap();
+
+            Datum::Boolean(contains(right, left))
+        },
+    );
+}
+
+/// Functions of Postgres system catalogs used by clients, which don't read
+/// the catalog.
+/// `random()` returns a value in `[0, 1)`, the values of `random(seed)` are
+/// generated from the seed of its first call, so they are reproducible in a
+/// query.
+fn register_random_functions(registry: &mut ScalarFunctionRegistry) {
+    registry.register("random", &[], Type::Float, |_| {
+        Datum::Float(rand::random::<f64>())
+    });
+    registry.set_volatility("random", Volatility::Volatile);
+
+    registry.register_stateful(
+        "random",
+        &[Type::Int],
+        Type::Float,
+        || None,
+        |rng: &mut Option<StdRng>, args| match &args[0] {
+            Datum::Int(seed) => {
+                let rng = rng.get_or_insert_with(|| StdRng::seed_from_u64(*seed as u64));
+                Datum::Float(rng.gen::<f64>())
+            }
+            _ => Datum::Null,
+        },
+    );
+}
+
+pub fn register_system_functions(registry: &mut ScalarFunctionRegistry) {
+    // All the objects are owned by the superuser
+    registry.register_null_passthrough("pg_get_userbyid", &[Type::Int], Type::String, |args| {
+        let oid = args[0].as_int().unwrap();
+
+        if *oid == OWNER_OID {
+            Datum::String(OWNER_NAME.into())
+        } else {
+            Datum::String(format!("unknown (OID={})", oid).into())
+        }
+    });
+
+    // Only UTF8 encoding is supported
+    registry.register_null_passthrough("pg_encoding_to_char", &[Type::Int], Type::String, |args| {
+        match args[0].as_int().unwrap() {
+            6 => Datum::String("UTF8".into()),
+            _ => Datum::String("".into()),
+        }
+    });
+
+    // Type modifiers are not supported, so it can be NULL
+    registry.register(
+        "format_type",
+        &[Type::Int, Type::Int],
+        Type::String,
+        |args| match &args[0] {
+            Datum::Int(oid) => Datum::String(
+                format_type(*oid)
+                    .unwrap_or_else(|| "???".to_string())
+                    .into(),
+            ),
+            _ => Datum::Null,
+        },
+    );
+
+    // Expressions are stored as SQL text, which is returned as is
+    registry.register_null_passthrough(
+        "pg_get_expr",
+        &[Type::String, Type::Int],
+        Type::String,
+        |args| args[0].clone(),
+    );
+    registry.register_null_passthrough(
+        "pg_get_expr",
+        &[Type::String, Type::Int, Type::Boolean],
+        Type::String,
+        |args| args[0].clone(),
+    );
+
+    // NULL elements are skipped
+    registry.register_null_passthrough(
+        "array_to_string",
+        &[Type::Array(Box::new(Type::Any)), Type::String],
+        Type::String,
+        |args| {
+            let values = args[0].as_array().unwrap();
+            let delimiter = args[1].as_string().unwrap();
+
+            Datum::String(
+                values
+                    .iter()
+                    .filter(|value| !value.is_null())
+                    .map(array_element_text)
+                    .collect::<Vec<_>>()
+                    .join(delimiter)
+                    .into(),
+            )
+        },
+    );
+
+    // Comments are not supported
+    registry.register(
+        "obj_description",
+        &[Type::Int, Type::String],
+        Type::String,
+        |_| Datum::Null,
+    );
+    registry.register(
+        "col_description",
+        &[Type::Int, Type::Int],
+        Type::String,
+        |_| Datum::Null,
+    );
+
+    // Extended statistics are not supported
+    registry.register(
+        "pg_get_statisticsobjdef_columns",
+        &[Type::Int],
+        Type::String,
+        |_| Datum::Null,
+    );
+}
+
+/// System catalog built on first use, so the catalog is not read by queries
+/// which don't call the functions.
+struct CatalogSnapshot {
+    catalog: Arc<RwLock<Catalog>>,
+    current_database: String,
+    current_schema: String,
+    snapshot: OnceLock<SystemCatalog>,
+}
+
+impl CatalogSnapshot {
+    fn get(&self) -> &SystemCatalog {
+        self.snapshot.get_or_init(|| {
+            SystemCatalog::new(
+                &self.catalog.read().unwrap(),
+                &self.current_database,
+                &self.current_schema,
+            )
+        })
+    }
+}
+
+/// Functions of Postgres system catalogs reading the snapshot of catalog,
+/// which returns NULL for unknown OIDs.
+fn register_catalog_functions(
+    registry: &mut ScalarFunctionRegistry,
+    catalog: Arc<RwLock<Catalog>>,
+    current_database: String,
+    current_schema: String,
+) {
+    let system_catalog = Arc::new(CatalogSnapshot {
+        catalog,
+        current_database,
+        current_schema,
+        snapshot: OnceLock::new(),
+    });
+
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough(
+        "pg_table_is_visible",
+        &[Type::Int],
+        Type::Boolean,
+        move |args| {
+            let oid = args[0].as_int().unwrap();
+
+            catalog
+                .get()
+                .is_visible(*oid)
+                .map_or(Datum::Null, Datum::Boolean)
+        },
+    );
+
+    // Casts of `regclass` between the name and OID of relation
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough("regclass", &[Type::Int], Type::String, move |args| {
+        let oid = args[0].as_int().unwrap();
+
+        catalog.get().relation_name(*oid).map_or_else(
+            || Datum::String(oid.to_string().into()),
+            |name| Datum::String(name.into()),
+        )
+    });
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough("regclass", &[Type::String], Type::Int, move |args| {
+        let name = args[0].as_string().unwrap();
+
+        catalog
+            .get()
+            .relation_oid(name)
+            .map_or(Datum::Null, Datum::Int)
+    });
+
+    // Casts of `regnamespace` between the name and OID of schema
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough("regnamespace", &[Type::Int], Type::String, move |args| {
+        let oid = args[0].as_int().unwrap();
+
+        catalog.get().namespace_name(*oid).map_or_else(
+            || Datum::String(oid.to_string().into()),
+            |name| Datum::String(name.into()),
+        )
+    });
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough("regnamespace", &[Type::String], Type::Int, move |args| {
+        let name = args[0].as_string().unwrap();
+
+        catalog
+            .get()
+            .namespace_oid(name)
+            .map_or(Datum::Null, Datum::Int)
+    });
+
+    for arg_types in [vec![Type::Int], vec![Type::Int, Type::Int, Type::Boolean]] {
+        let catalog = system_catalog.clone();
+        registry.register_null_passthrough(
+            "pg_get_indexdef",
+            &arg_types,
+            Type::String,
+            move |args| {
+                let oid = args[0].as_int().unwrap();
+                let column = args.get(1).and_then(Datum::as_int).unwrap_or(&0);
+
+                catalog
+                    .get()
+                    .index_definition(*oid, *column)
+                    .map_or(Datum::Null, |text| Datum::String(text.into()))
+            },
+        );
+    }
+
+    for arg_types in [vec![Type::Int], vec![Type::Int, Type::Boolean]] {
+        let catalog = system_catalog.clone();
+        registry.register_null_passthrough(
+            "pg_get_constraintdef",
+            &arg_types,
+            Type::String,
+            move |args| {
+                let oid = args[0].as_int().unwrap();
+
+                catalog
+                    .get()
+                    .constraint_definition(*oid)
+                    .map_or(Datum::Null, |text| Datum::String(text.into()))
+            },
+        );
+    }
+
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough(
+        "pg_relation_is_publishable",
+        &[Type::Int],
+        Type::Boolean,
+        move |args| {
+            let oid = args[0].as_int().unwrap();
+
+            catalog
+                .get()
+                .is_publishable(*oid)
+                .map_or(Datum::Null, Datum::Boolean)
+        },
+    );
+
+    // The argument is `regclass`, which may be the name of relation
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough(
+        "pg_relation_is_publishable",
+        &[Type::String],
+        Type::Boolean,
+        move |args| {
+            let name = args[0].as_string().unwrap();
+            let catalog = catalog.get();
+
+            catalog
+                .relation_oid(name)
+                .and_then(|oid| catalog.is_publishable(oid))
+                .map_or(Datum::Null, Datum::Boolean)
+        },
+    );
+
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough(
+        "pg_function_is_visible",
+        &[Type::Int],
+        Type::Boolean,
+        move |args| {
+            let oid = args[0].as_int().unwrap();
+
+            catalog
+                .get()
+                .is_function_visible(*oid)
+                .map_or(Datum::Null, Datum::Boolean)
+        },
+    );
+
+    let catalog = system_catalog.clone();
+    registry.register_null_passthrough(
+        "pg_get_function_arguments",
+        &[Type::Int],
+        Type::String,
+        move |args| {
+            let oid = args[0].as_int().unwrap();
+
+            catalog
+                .get()
+                .function_arguments(*oid)
+                .map_or(Datum::Null, |text| Datum::String(text.into()))
+        },
+    );
+
+    let catalog = system_catalog;
+    registry.register_null_passthrough(
+        "pg_get_function_result",
+        &[Type::Int],
+        Type::String,
+        move |args| {
+            let oid = args[0].as_int().unwrap();
+
+            catalog
+                .get()
+                .function_result(*oid)
+                .map_or(Datum::Null, |text| Datum::String(text.into()))
+        },
+    );
+
+    // The catalog may change between statements
+    let names = registry.functions.keys().cloned().collect::<Vec<_>>();
+    for name in names {
+        registry.set_volatility(&name, Volatility::Stable);
+    }
+}
+
+/// Functions interrupting the sessions by pid, which return false if there
+/// is no such session.
+fn register_signal_functions(
+    registry: &mut ScalarFunctionRegistry,
+    activity: Arc<ActivityRegistry>,
+) {
+    let sessions = activity.clone();
+    registry.register_null_passthrough(
+        "pg_cancel_backend",
+        &[Type::Int],
+        Type::Boolean,
+        move |args| {
+            let pid = args[0].as_int().unwrap();
+
+            Datum::Boolean(sessions.interrupt(*pid, Interrupts::cancel))
+        },
+    );
+    registry.register_null_passthrough(
+        "pg_terminate_backend",
+        &[Type::Int],
+        Type::Boolean,
+        move |args| {
+            let pid = args[0].as_int().unwrap();
+
+            Datum::Boolean(activity.interrupt(*pid, Interrupts::terminate))
+        },
+    );
+
+    registry.set_volatility("pg_cancel_backend", Volatility::Volatile);
+    registry.set_volatility("pg_terminate_backend", Volatility::Volatile);
+}
+
+/// Cast the value by the cast functions, which fails instead of returning
+/// NULL if the value can't be converted.
+pub fn cast_value(value: &Datum, typ: &Type) -> Result<Datum, SQLError> {
+    if let (Datum::Array(elements), Type::Array(elem_type)) = (value, typ) {
+        if !matches!(elem_type.as_ref(), Type::Null | Type::Never) {
+            return elements
+                .iter()
+                .map(|element| cast_value(element, elem_type))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Datum::Array);
+        }
+    }
+
+    let datum = value.cast(typ);
+    if datum.is_null() && !value.is_null() {
+        let type_name = to_pg_type(typ).name().to_string();
+        return Err(match value {
+            Datum::String(v) => SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("invalid input syntax for type {}: \"{}\"", type_name, v),
+            )
+            .with_code(sqlstate::INVALID_TEXT_REPRESENTATION),
+            value => SQLError::new(
+                ErrorKind::RuntimeError,
+                format!(
+                    "cannot cast type {} to {}",
+                    to_pg_type(&value.typ()).name(),
+                    type_name
+                ),
+            )
+            .with_code(sqlstate::CANNOT_COERCE),
+        });
+    }
+
+    Ok(datum)
+}
+
+/// Floats are rounded to the nearest integer, which must be in the range of
+/// the width.
+fn cast_to_int(value: &Datum, int_width: IntWidth) -> Result<Datum, SQLError> {
+    let value = cast_value(value, &Type::Int).map_err(|e| match value {
+        Datum::Float(_) => SQLError::new(
+            ErrorKind::RuntimeError,
+            format!("{} out of range", int_width.name()),
+        )
+        .with_code(sqlstate::NUMERIC_VALUE_OUT_OF_RANGE),
+        _ => e,
+    })?;
+    int_width.check(&value)?;
+    Ok(value)
+}
+
 fn register_cast_functions(registry: &mut ScalarFunctionRegistry) {
     // Cast as int
     // registry.register_null_passthrough("to_int", &[Type::String], Type::Int, |args| {
@@ -302,17 +1379,19 @@ fn register_cast_functions(registry: &mut ScalarFunctionRegistry) {
 
     //     value.cast(&Type::Int)
     // });
-    registry.register_null_passthrough("to_int", &[Type::Any], Type::Int, |args| {
-        let value = &args[0];
-
-        value.cast(&Type::Int)
-    });
+    for (name, int_width) in [
+        ("to_int", IntWidth::Int8),
+        ("to_int2", IntWidth::Int2),
+        ("to_int4", IntWidth::Int4),
+    ] {
+        registry.register_null_passthrough(name, &[Type::Any], Type::Int, move |args| {
+            cast_to_int(&args[0], int_width)
+        });
+    }
 
     // Cast as float
     registry.register_null_passthrough("to_float", &[Type::Any], Type::Float, |args| {
-        let value = &args[0];
-
-        value.cast(&Type::Float)
+        cast_value(&args[0], &Type::Float)
     });
 
     // Cast as string
@@ -337,9 +1416,7 @@ fn register_cast_functions(registry: &mut ScalarFunctionRegistry) {
     //     value.cast(&Type::String)
     // });
     registry.register_null_passthrough("to_string", &[Type::Any], Type::String, |args| {
-        let value = &args[0];
-
-        value.cast(&Type::String)
+        cast_value(&args[0], &Type::String)
     });
 
     // Cast as boolean
@@ -364,8 +1441,21 @@ fn register_cast_functions(registry: &mut ScalarFunctionRegistry) {
     //     value.cast(&Type::String)
     // });
     registry.register_null_passthrough("to_boolean", &[Type::Any], Type::Boolean, |args| {
-        let value = &args[0];
+        cast_value(&args[0], &Type::Boolean)
+    });
+
+    // Cast as uuid
+    registry.register_null_passthrough("to_uuid", &[Type::Any], Type::Uuid, |args| {
+        cast_value(&args[0], &Type::Uuid)
+    });
+
+    // Cast as bytea
+    registry.register_null_passthrough("to_bytea", &[Type::Any], Type::Bytea, |args| {
+        cast_value(&args[0], &Type::Bytea)
+    });
 
-        value.cast(&Type::Boolean)
+    // Cast as json
+    registry.register_null_passthrough("to_json", &[Type::Any], Type::Json, |args| {
+        cast_value(&args[0], &Type::Json)
     });
 }
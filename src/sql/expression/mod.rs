@@ -1,18 +1,192 @@
 pub mod aggregate;
 pub mod function;
 pub mod type_check;
+pub mod wasm;
+pub mod window;
 
 use std::sync::Arc;
 
 use function::ScalarFunction;
 
-use crate::core::{Datum, ErrorKind, SQLError, Tuple, Type};
+use crate::{
+    core::{sqlstate, Datum, ErrorKind, Json, SQLError, Tuple, Type},
+    sql::session::context::SessionSequence,
+};
 
 #[derive(Clone)]
 pub enum Expression {
     Column(usize, Type),
     Literal(Datum, Type),
     Function(Arc<ScalarFunction>, Vec<Expression>),
+    /// Special forms take variadic arguments, which are usually of the
+    /// same type, and evaluate them lazily.
+    SpecialForm(SpecialForm, Vec<Expression>, Type),
+    /// `nextval` or `currval` of a sequence resolved in the session.
+    Sequence(SequenceFunction, SessionSequence),
+}
+
+/// Functions that cannot be implemented as scalar functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecialForm {
+    /// `COALESCE(a, b, ...)`, returns the first non-NULL argument.
+    Coalesce,
+    /// `NULLIF(a, b)`, returns NULL if `a` equals to `b`, otherwise returns `a`.
+    NullIf,
+    /// `GREATEST(a, b, ...)`, returns the largest non-NULL argument.
+    Greatest,
+    /// `LEAST(a, b, ...)`, returns the smallest non-NULL argument.
+    Least,
+    /// `JSON_BUILD_OBJECT(k1, v1, k2, v2, ...)`, builds a JSON object from
+    /// the pairs of keys and values of any types.
+    JsonBuildObject,
+    /// `ARRAY[a, b, ...]`, builds an array of the arguments.
+    Array,
+    /// `a[i]`, returns the i-th element of array `a`, the index starts from 1.
+    ArraySubscript,
+    /// `CASE WHEN c1 THEN r1 ... ELSE e END`, the arguments are the pairs of
+    /// conditions and results followed by the `ELSE` result.
+    Case,
+}
+
+impl SpecialForm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "coalesce" => Some(SpecialForm::Coalesce),
+            "nullif" => Some(SpecialForm::NullIf),
+            "greatest" => Some(SpecialForm::Greatest),
+            "least" => Some(SpecialForm::Least),
+            "json_build_object" => Some(SpecialForm::JsonBuildObject),
+            "array" => Some(SpecialForm::Array),
+            "[]" => Some(SpecialForm::ArraySubscript),
+            "case" => Some(SpecialForm::Case),
+            _ => None,
+        }
+    }
+
+    /// Check if the number of arguments is valid.
+    pub fn check_arity(&self, num_args: usize) -> bool {
+        match self {
+            SpecialForm::NullIf | SpecialForm::ArraySubscript => num_args == 2,
+            SpecialForm::Array => true,
+            SpecialForm::Coalesce | SpecialForm::Greatest | SpecialForm::Least => num_args > 0,
+            SpecialForm::JsonBuildObject => num_args % 2 == 0,
+            SpecialForm::Case => num_args >= 3 && num_args % 2 == 1,
+        }
+    }
+
+    fn eval(&self, args: &[Expression], tuple: &Tuple) -> Result<Datum, SQLError> {
+        match self {
+            SpecialForm::Coalesce => {
+                for arg in args.iter() {
+                    let value = arg.eval(tuple)?;
+                    if !value.is_null() {
+                        return Ok(value);
+                    }
+                }
+
+                Ok(Datum::Null)
+            }
+            SpecialForm::NullIf => {
+                let left = args[0].eval(tuple)?;
+                let right = args[1].eval(tuple)?;
+
+                if !left.is_null() && left == right {
+                    Ok(Datum::Null)
+                } else {
+                    Ok(left)
+                }
+            }
+            SpecialForm::Greatest | SpecialForm::Least => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.eval(tuple))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter()
+                    .filter(|value| !value.is_null());
+
+                let result = if *self == SpecialForm::Greatest {
+                    values.max()
+                } else {
+                    values.min()
+                };
+
+                Ok(result.unwrap_or(Datum::Null))
+            }
+            SpecialForm::JsonBuildObject => {
+                let mut fields: Vec<(String, Json)> = vec![];
+                for (i, pair) in args.chunks(2).enumerate() {
+                    let key = match pair[0].eval(tuple)? {
+                        Datum::String(key) => key.to_string(),
+                        Datum::Null => {
+                            return Err(SQLError::new(
+                                ErrorKind::RuntimeError,
+                                format!("argument {}: key must not be null", i * 2 + 1),
+                            )
+                            .with_code(sqlstate::NULL_VALUE_NOT_ALLOWED))
+                        }
+                        key => key.to_string(),
+                    };
+                    let value = Json::from(&pair[1].eval(tuple)?);
+
+                    match fields.iter_mut().find(|(k, _)| *k == key) {
+                        Some(field) => field.1 = value,
+                        None => fields.push((key, value)),
+                    }
+                }
+
+                Ok(Datum::Json(Json::Object(fields)))
+            }
+            SpecialForm::Array => Ok(Datum::Array(
+                args.iter()
+                    .map(|arg| arg.eval(tuple))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )),
+            SpecialForm::ArraySubscript => {
+                let array = args[0].eval(tuple)?;
+                let index = args[1].eval(tuple)?;
+
+                // NULL if the index is out of bounds
+                let value = match (array, index) {
+                    (Datum::Array(mut values), Datum::Int(index))
+                        if index >= 1 && index as usize <= values.len() =>
+                    {
+                        values.swap_remove(index as usize - 1)
+                    }
+                    _ => Datum::Null,
+                };
+                Ok(value)
+            }
+            SpecialForm::Case => {
+                // Only the results of the first matched condition is evaluated
+                let (else_result, branches) = args.split_last().unwrap();
+                for branch in branches.chunks(2) {
+                    if let Datum::Boolean(true) = branch[0].eval(tuple)? {
+                        return branch[1].eval(tuple);
+                    }
+                }
+                else_result.eval(tuple)
+            }
+        }
+    }
+}
+
+/// Functions on sequences, which are evaluated with side effects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SequenceFunction {
+    /// `NEXTVAL(name)`, advances the sequence and returns the new value.
+    NextVal,
+    /// `CURRVAL(name)`, returns the value of the last `nextval` in the session.
+    CurrVal,
+}
+
+impl SequenceFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nextval" => Some(SequenceFunction::NextVal),
+            "currval" => Some(SequenceFunction::CurrVal),
+            _ => None,
+        }
+    }
 }
 
 impl Expression {
@@ -21,6 +195,8 @@ impl Expression {
             Expression::Column(_, ty) => ty,
             Expression::Literal(_, ty) => ty,
             Expression::Function(func, _) => &func.ret_type,
+            Expression::SpecialForm(_, _, ty) => ty,
+            Expression::Sequence(..) => &Type::Int,
         }
     }
 
@@ -38,8 +214,21 @@ impl Expression {
                     .iter()
                     .map(|arg| arg.eval(tuple))
                     .collect::<Result<Vec<_>, _>>()?;
-                Ok((func.eval)(args.as_slice()))
+                (func.eval)(args.as_slice())
+            }
+            Expression::SpecialForm(form, args, _) => form.eval(args, tuple),
+            Expression::Sequence(SequenceFunction::NextVal, sequence) => {
+                Ok(Datum::Int(sequence.next_value()?))
+            }
+            Expression::Sequence(SequenceFunction::CurrVal, sequence) => {
+                Ok(Datum::Int(sequence.current_value()?))
             }
         }
     }
+
+    /// Evaluate the expression, which is coerced to boolean, as a predicate.
+    /// Only `true` satisfies it, the unknown result, i.e. NULL, doesn't.
+    pub fn eval_predicate(&self, tuple: &Tuple) -> Result<bool, SQLError> {
+        Ok(matches!(self.eval(tuple)?, Datum::Boolean(true)))
+    }
 }
@@ -2,12 +2,16 @@ use std::sync::Arc;
 
 use super::{
     aggregate::{AggregateFunction, AggregateFunctionRegistry},
-    function::ScalarFunctionRegistry,
-    Expression,
+    function::{cast_value, ScalarFunction, ScalarFunctionRegistry, Volatility},
+    window::WindowFunctionKind,
+    Expression, SequenceFunction, SpecialForm,
 };
 use crate::{
-    core::{ErrorKind, SQLError, Type},
-    sql::planner::{Column, ScalarExpr},
+    core::{sqlstate, Datum, ErrorKind, SQLError, Tuple, Type},
+    sql::{
+        planner::{Column, ScalarExpr},
+        session::{context::SessionSequence, to_pg_type},
+    },
 };
 
 lazy_static! {
@@ -16,32 +20,77 @@ lazy_static! {
         (Type::Int, Type::String),
         (Type::Int, Type::Boolean),
 
-        (Type::Float, Type::Int),
         (Type::Float, Type::String),
 
         (Type::Boolean, Type::Int),
         (Type::Boolean, Type::String),
 
+        (Type::String, Type::Uuid),
+        (Type::String, Type::Json),
+        (Type::String, Type::Bytea),
+
         // Null can be cast to any type
         (Type::Null, Type::Int),
         (Type::Null, Type::Float),
         (Type::Null, Type::Boolean),
         (Type::Null, Type::String),
+        (Type::Null, Type::Uuid),
+        (Type::Null, Type::Json),
+        (Type::Null, Type::Bytea),
 
         // Any type can be cast to Any
         (Type::Int, Type::Any),
         (Type::Float, Type::Any),
         (Type::Boolean, Type::Any),
         (Type::String, Type::Any),
+        (Type::Uuid, Type::Any),
+        (Type::Json, Type::Any),
+        (Type::Bytea, Type::Any),
     ];
 }
 
 pub fn can_auto_cast_to(from: &Type, to: &Type) -> bool {
-    AUTO_CAST.contains(&(from.clone(), to.clone()))
+    match (from, to) {
+        // Arrays can be cast if their elements can be
+        (Type::Array(from), Type::Array(to)) => can_auto_cast_to(from, to),
+        (Type::Null | Type::String, Type::Array(_)) | (Type::Array(_), Type::Any) => true,
+        _ => AUTO_CAST.contains(&(from.clone(), to.clone())),
+    }
 }
 
 pub trait ColumnTypeResolver {
     fn resolve_column_type(&self, column: &Column) -> Result<Type, SQLError>;
+
+    /// Resolve the column of outer query to its value, which is bound while
+    /// executing the subquery of `Apply`.
+    fn resolve_outer_column(&self, _column: &Column) -> Result<Expression, SQLError> {
+        Err(
+            SQLError::new(ErrorKind::PlannerError, "unsupported correlated subquery")
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED),
+        )
+    }
+
+    /// Resolve the sequence of `nextval` and `currval`, which is only available
+    /// with a session.
+    fn resolve_sequence(&self, name: &str) -> Result<SessionSequence, SQLError> {
+        Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!("cannot use sequence \"{}\" here", name),
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+    }
+
+    /// Functions reading the system catalog, which is only available with a
+    /// session.
+    fn catalog_functions(&self) -> Option<ScalarFunctionRegistry> {
+        None
+    }
+
+    /// Functions registered in the catalog, only the builtin ones are
+    /// available without a session.
+    fn scalar_functions(&self) -> Arc<ScalarFunctionRegistry> {
+        ScalarFunctionRegistry::builtin().clone()
+    }
 }
 
 pub fn type_check<Ctxt: ColumnTypeResolver>(
@@ -53,79 +102,490 @@ pub fn type_check<Ctxt: ColumnTypeResolver>(
             column.index,
             ctx.resolve_column_type(column)?,
         )),
+        ScalarExpr::OuterColumn(column) => ctx.resolve_outer_column(column),
         ScalarExpr::Literal(value) => Ok(Expression::Literal(value.clone(), value.typ())),
+        // Parameters are replaced with values before execution, an unbound
+        // parameter is only type checked while describing a prepared statement.
+        ScalarExpr::Parameter(_) => Ok(Expression::Literal(Datum::Null, Type::Null)),
         ScalarExpr::FunctionCall(func, args) => {
+            if let Some(function) = SequenceFunction::from_name(func) {
+                return type_check_sequence_function(ctx, func, function, args);
+            }
+
             let args = args
                 .iter()
                 .map(|arg| type_check(ctx, arg))
                 .collect::<Result<Vec<_>, _>>()?;
 
-            let func = type_check_function(func, &args, ScalarFunctionRegistry::builtin())?;
+            if let Some(form) = SpecialForm::from_name(func) {
+                return type_check_special_form(func, form, args);
+            }
+
+            // Casts to arrays are named after the casts of elements
+            if let Some(elem_cast) = func.strip_suffix("[]") {
+                let elem_type = ScalarFunctionRegistry::builtin().search_candidates(elem_cast)[0]
+                    .ret_type
+                    .clone();
+                let arg = args.into_iter().next().unwrap();
+                return Ok(wrap_cast(arg, Type::Array(Box::new(elem_type))));
+            }
+
+            if let Some(registry) = ctx
+                .catalog_functions()
+                .filter(|registry| registry.contains(func))
+            {
+                return type_check_function(func, &args, &registry);
+            }
+
+            let func = type_check_function(func, &args, &ctx.scalar_functions())?;
 
             Ok(func)
         }
     }
 }
 
-fn type_check_function(
-    name: &str,
-    args: &[Expression],
-    registry: &ScalarFunctionRegistry,
-) -> Result<Expression, SQLError> {
-    let candidates = registry.search_candidates(name);
-
-    for candidate in candidates.iter() {
-        if candidate.arg_types.len() != args.len() {
-            continue;
+/// Infer the types of parameters from the context they are used in, e.g. the
+/// type of `$1` in `a = $1` is the same as column `a`. Parameters whose types
+/// are already known will not be changed.
+pub fn infer_parameter_types<Ctxt: ColumnTypeResolver>(
+    ctx: &Ctxt,
+    scalar: &ScalarExpr,
+    parameter_types: &mut Vec<Option<Type>>,
+) -> Result<(), SQLError> {
+    let args = match scalar {
+        ScalarExpr::FunctionCall(_, args) => args,
+        ScalarExpr::Parameter(index) => {
+            if parameter_types.len() <= *index {
+                parameter_types.resize(*index + 1, None);
+            }
+            return Ok(());
         }
+        _ => return Ok(()),
+    };
+
+    for arg in args.iter() {
+        infer_parameter_types(ctx, arg, parameter_types)?;
+    }
 
-        // We may add some cast for arguments if auto cast is available
-        let mut arguments = args.to_vec();
+    let func = match scalar {
+        ScalarExpr::FunctionCall(func, _) => func,
+        _ => unreachable!(),
+    };
 
-        let mut matched = true;
-        for (i, arg) in args.iter().enumerate() {
-            if candidate.arg_types[i] == Type::Any {
-                continue;
+    let unknown_parameters = args
+        .iter()
+        .enumerate()
+        .filter_map(|(i, arg)| match arg {
+            ScalarExpr::Parameter(index) if parameter_types[*index].is_none() => Some((i, *index)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if unknown_parameters.is_empty() {
+        return Ok(());
+    }
+
+    // Types of arguments, `None` for the unknown parameters
+    let arg_types = args
+        .iter()
+        .map(|arg| match arg {
+            ScalarExpr::Parameter(index) => Ok(parameter_types[*index].clone()),
+            _ => Ok(Some(type_check(ctx, arg)?.typ().clone())),
+        })
+        .collect::<Result<Vec<_>, SQLError>>()?;
+
+    if let Some(form) = SpecialForm::from_name(func) {
+        match form {
+            SpecialForm::JsonBuildObject => return Ok(()),
+            SpecialForm::ArraySubscript => {
+                for (i, index) in unknown_parameters {
+                    if i == 1 {
+                        parameter_types[index] = Some(Type::Int);
+                    }
+                }
+                return Ok(());
+            }
+            SpecialForm::Case => {
+                let is_result = |i: usize| i % 2 == 1 || i == args.len() - 1;
+                let result_types = arg_types
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| is_result(*i))
+                    .filter_map(|(_, typ)| typ.clone())
+                    .collect::<Vec<_>>();
+                let result_type = common_super_type(&result_types).filter(|typ| typ != &Type::Null);
+                for (i, index) in unknown_parameters {
+                    if !is_result(i) {
+                        parameter_types[index] = Some(Type::Boolean);
+                    } else if let Some(typ) = &result_type {
+                        parameter_types[index] = Some(typ.clone());
+                    }
+                }
+                return Ok(());
             }
+            _ => {}
+        }
 
-            if arg.typ() != &candidate.arg_types[i]
-                && !can_auto_cast_to(arg.typ(), &candidate.arg_types[i])
-            {
-                matched = false;
-                break;
+        let known_types = arg_types.iter().flatten().cloned().collect::<Vec<_>>();
+        if let Some(typ) = common_super_type(&known_types).filter(|typ| typ != &Type::Null) {
+            for (_, index) in unknown_parameters {
+                parameter_types[index] = Some(typ.clone());
             }
-            // Wrap cast since there is auto cast rule
-            arguments[i] = wrap_cast(arguments[i].clone(), candidate.arg_types[i].clone());
         }
+        return Ok(());
+    }
 
-        if matched {
-            return Ok(Expression::Function(candidate.clone(), arguments));
+    // Prefer the overload that exactly matches the known argument types,
+    // then the one that matches with auto cast.
+    let candidates = ctx.scalar_functions().search_candidates(func);
+    let matches = |candidate: &Arc<ScalarFunction>, auto_cast: bool| {
+        candidate.arg_types.len() == arg_types.len()
+            && arg_types
+                .iter()
+                .zip(candidate.arg_types.iter())
+                .all(|(arg_type, expected)| match arg_type {
+                    Some(arg_type) => {
+                        arg_type.is_instance_of(expected)
+                            || (auto_cast && can_auto_cast_to(arg_type, expected))
+                    }
+                    None => !expected.is_polymorphic(),
+                })
+    };
+    let candidate = candidates
+        .iter()
+        .find(|candidate| matches(candidate, false))
+        .or_else(|| candidates.iter().find(|candidate| matches(candidate, true)));
+    if let Some(candidate) = candidate {
+        for (i, index) in unknown_parameters {
+            parameter_types[index] = Some(candidate.arg_types[i].clone());
         }
     }
 
+    Ok(())
+}
+
+fn type_check_function(
+    name: &str,
+    args: &[Expression],
+    registry: &ScalarFunctionRegistry,
+) -> Result<Expression, SQLError> {
+    let candidates = registry.search_candidates(name);
+
+    // Prefer the overload that exactly matches the argument types, then the
+    // one that matches with auto cast.
+    let matches = |candidate: &Arc<ScalarFunction>, auto_cast: bool| {
+        candidate.arg_types.len() == args.len()
+            && args
+                .iter()
+                .zip(candidate.arg_types.iter())
+                .all(|(arg, expected)| {
+                    arg.typ().is_instance_of(expected)
+                        || (auto_cast && can_auto_cast_to(arg.typ(), expected))
+                })
+    };
+    let candidate = candidates
+        .iter()
+        .find(|candidate| matches(candidate, false))
+        .or_else(|| candidates.iter().find(|candidate| matches(candidate, true)));
+
+    if let Some(candidate) = candidate {
+        // Wrap cast since there is auto cast rule
+        let arguments = args
+            .iter()
+            .zip(candidate.arg_types.iter())
+            .map(|(arg, expected)| {
+                if arg.typ().is_instance_of(expected) {
+                    arg.clone()
+                } else {
+                    wrap_cast(arg.clone(), expected.clone())
+                }
+            })
+            .collect();
+
+        return Ok(fold_constant(Expression::Function(
+            candidate.instance(),
+            arguments,
+        )));
+    }
+
     Err(SQLError::new(
         ErrorKind::CatalogError,
         format!(
             "cannot find overload of function with given types: {}",
             name
         ),
+    )
+.with_code(sqlstate::UNDEFINED_FUNCTION)
+.with_hint("No function matches the given name and argument types. You might need to add explicit type casts."))
+}
+
+/// The sequence is resolved while type checking, so its name must be a constant.
+fn type_check_sequence_function<Ctxt: ColumnTypeResolver>(
+    ctx: &Ctxt,
+    name: &str,
+    function: SequenceFunction,
+    args: &[ScalarExpr],
+) -> Result<Expression, SQLError> {
+    match args {
+        [ScalarExpr::Literal(Datum::String(sequence_name))] => Ok(Expression::Sequence(
+            function,
+            ctx.resolve_sequence(sequence_name)?,
+        )),
+        [_] => Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!("argument of function {} must be a sequence name", name),
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+        _ => Err(SQLError::new(
+            ErrorKind::CatalogError,
+            format!("invalid number of arguments for function: {}", name),
+        )
+        .with_code(sqlstate::UNDEFINED_FUNCTION)),
+    }
+}
+
+/// Find the common type that all the given types can be cast to.
+/// NULL is compatible with any type.
+pub fn common_super_type(types: &[Type]) -> Option<Type> {
+    let mut result = Type::Null;
+    for typ in types.iter() {
+        if typ == &result || typ == &Type::Null {
+            continue;
+        }
+
+        result = match (&result, typ) {
+            (Type::Null, _) => typ.clone(),
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Type::Float,
+            _ if can_auto_cast_to(typ, &result) => result,
+            _ if can_auto_cast_to(&result, typ) => typ.clone(),
+            _ => return None,
+        };
+    }
+
+    Some(result)
+}
+
+/// The arguments of special forms are cast to their common type, except
+/// `json_build_object` and array subscript.
+fn type_check_special_form(
+    name: &str,
+    form: SpecialForm,
+    args: Vec<Expression>,
+) -> Result<Expression, SQLError> {
+    if !form.check_arity(args.len()) {
+        return Err(SQLError::new(
+            ErrorKind::CatalogError,
+            format!("invalid number of arguments for function: {}", name),
+        )
+        .with_code(sqlstate::UNDEFINED_FUNCTION));
+    }
+
+    match form {
+        // Arguments of any types are converted to JSON values
+        SpecialForm::JsonBuildObject => return Ok(Expression::SpecialForm(form, args, Type::Json)),
+        SpecialForm::ArraySubscript => return type_check_array_subscript(form, args),
+        SpecialForm::Case => return type_check_case(name, form, args),
+        _ => {}
+    }
+
+    let common_type =
+        common_super_type(&args.iter().map(|arg| arg.typ().clone()).collect::<Vec<_>>())
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::TypeError,
+                    format!("arguments of function {} cannot be matched", name),
+                )
+                .with_code(sqlstate::DATATYPE_MISMATCH)
+            })?;
+
+    let args = if common_type == Type::Null {
+        args
+    } else {
+        args.into_iter()
+            .map(|arg| wrap_cast(arg, common_type.clone()))
+            .collect()
+    };
+
+    let ret_type = match (form, common_type) {
+        (SpecialForm::Array, Type::Array(_)) => {
+            return Err(SQLError::new(
+                ErrorKind::TypeError,
+                "multidimensional arrays are not supported",
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+        }
+        (SpecialForm::Array, elem_type) => Type::Array(Box::new(elem_type)),
+        (_, common_type) => common_type,
+    };
+
+    Ok(Expression::SpecialForm(form, args, ret_type))
+}
+
+/// The conditions of `CASE` are cast to boolean, and the results are cast to
+/// their common type.
+fn type_check_case(
+    name: &str,
+    form: SpecialForm,
+    args: Vec<Expression>,
+) -> Result<Expression, SQLError> {
+    let num_args = args.len();
+    let is_result = |i: usize| i % 2 == 1 || i == num_args - 1;
+
+    let result_types = args
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| is_result(*i))
+        .map(|(_, arg)| arg.typ().clone())
+        .collect::<Vec<_>>();
+    let common_type = common_super_type(&result_types).ok_or_else(|| {
+        SQLError::new(
+            ErrorKind::TypeError,
+            format!("results of {} cannot be matched", name.to_uppercase()),
+        )
+        .with_code(sqlstate::DATATYPE_MISMATCH)
+    })?;
+
+    let args = args
+        .into_iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            if is_result(i) {
+                if common_type == Type::Null {
+                    Ok(arg)
+                } else {
+                    Ok(wrap_cast(arg, common_type.clone()))
+                }
+            } else if matches!(arg.typ(), Type::Boolean | Type::Null) {
+                Ok(arg)
+            } else {
+                Err(SQLError::new(
+                    ErrorKind::TypeError,
+                    format!(
+                        "argument of {} must be type boolean, not type {}",
+                        name.to_uppercase(),
+                        to_pg_type(arg.typ()).name()
+                    ),
+                )
+                .with_code(sqlstate::DATATYPE_MISMATCH))
+            }
+        })
+        .collect::<Result<Vec<_>, SQLError>>()?;
+
+    Ok(Expression::SpecialForm(form, args, common_type))
+}
+
+/// The subscript of array returns the element type, and the index is cast
+/// to integer.
+fn type_check_array_subscript(
+    form: SpecialForm,
+    mut args: Vec<Expression>,
+) -> Result<Expression, SQLError> {
+    let index = args.pop().unwrap();
+    let array = args.pop().unwrap();
+
+    let elem_type = match array.typ() {
+        Type::Array(elem_type) => elem_type.as_ref().clone(),
+        Type::Null => Type::Null,
+        typ => {
+            return Err(SQLError::new(
+                ErrorKind::TypeError,
+                format!(
+                    "cannot subscript type {} because it is not an array",
+                    to_pg_type(typ).name()
+                ),
+            )
+            .with_code(sqlstate::DATATYPE_MISMATCH))
+        }
+    };
+    if index.typ() != &Type::Int && !can_auto_cast_to(index.typ(), &Type::Int) {
+        return Err(SQLError::new(
+            ErrorKind::TypeError,
+            "array subscript must have type integer",
+        )
+        .with_code(sqlstate::DATATYPE_MISMATCH));
+    }
+
+    Ok(Expression::SpecialForm(
+        form,
+        vec![array, wrap_cast(index, Type::Int)],
+        elem_type,
     ))
 }
 
-fn wrap_cast(expr: Expression, target_type: Type) -> Expression {
+/// Name of the function casting values to the type, casts of arrays are not
+/// registered since the element type varies.
+pub fn cast_function_name(typ: &Type) -> Option<&'static str> {
+    match typ {
+        Type::Int => Some("to_int"),
+        Type::Float => Some("to_float"),
+        Type::String => Some("to_string"),
+        Type::Boolean => Some("to_boolean"),
+        Type::Uuid => Some("to_uuid"),
+        Type::Json => Some("to_json"),
+        Type::Bytea => Some("to_bytea"),
+        _ => None,
+    }
+}
+
+/// Cast the expression to the target type if it has a different type.
+pub fn wrap_cast(expr: Expression, target_type: Type) -> Expression {
     let original_type = expr.typ();
     if original_type == &target_type {
         expr
-    } else {
-        let cast_func_name = match target_type {
-            Type::Int => "to_int",
-            Type::Float => "to_float",
-            Type::String => "to_string",
-            Type::Boolean => "to_boolean",
-            _ => unreachable!(),
-        };
+    } else if let Some(cast_func_name) = cast_function_name(&target_type) {
+        // Casts are always the builtin ones
         let func = ScalarFunctionRegistry::builtin().search_candidates(cast_func_name)[0].clone();
-        Expression::Function(func, vec![expr])
+        fold_constant(Expression::Function(func, vec![expr]))
+    } else if let Type::Array(_) = target_type {
+        // Casts of arrays are created on demand
+        let typ = target_type.clone();
+        let func = ScalarFunction {
+            name: "to_array".to_string(),
+            arg_types: vec![Type::Any],
+            ret_type: target_type,
+            volatility: Volatility::Immutable,
+            eval: Box::new(move |args| cast_value(&args[0], &typ)),
+            instantiate: None,
+        };
+        fold_constant(Expression::Function(Arc::new(func), vec![expr]))
+    } else {
+        unreachable!()
+    }
+}
+
+/// Evaluate the call of immutable function with constant arguments, the
+/// errors are left to the execution, since the call may never be evaluated,
+/// e.g. in a branch of `CASE`.
+fn fold_constant(expr: Expression) -> Expression {
+    match &expr {
+        Expression::Function(func, args)
+            if func.volatility == Volatility::Immutable
+                && args
+                    .iter()
+                    .all(|arg| matches!(arg, Expression::Literal(..))) =>
+        {
+            match expr.eval(&Tuple::default()) {
+                Ok(value) => Expression::Literal(value, func.ret_type.clone()),
+                Err(_) => expr,
+            }
+        }
+        _ => expr,
+    }
+}
+
+/// Check the condition of a clause, e.g. `WHERE`, is boolean or NULL, other
+/// types are not cast to boolean implicitly like PostgreSQL.
+pub fn coerce_to_boolean(expr: Expression, construct: &str) -> Result<Expression, SQLError> {
+    match expr.typ() {
+        Type::Boolean | Type::Null => Ok(expr),
+        typ => Err(SQLError::new(
+            ErrorKind::TypeError,
+            format!(
+                "argument of {} must be type boolean, not type {}",
+                construct,
+                to_pg_type(typ).name()
+            ),
+        )
+        .with_code(sqlstate::DATATYPE_MISMATCH)),
     }
 }
 
@@ -143,11 +603,7 @@ pub fn type_check_aggregate_function(
 
         let mut matched = true;
         for (i, arg) in args.iter().enumerate() {
-            if candidate.arg_types[i] == Type::Any {
-                continue;
-            }
-
-            if arg.typ() != &candidate.arg_types[i] {
+            if !arg.typ().is_instance_of(&candidate.arg_types[i]) {
                 matched = false;
                 break;
             }
@@ -164,5 +620,33 @@ pub fn type_check_aggregate_function(
             "cannot find overload of function with given types: {}",
             name
         ),
-    ))
+    )
+.with_code(sqlstate::UNDEFINED_FUNCTION)
+.with_hint("No function matches the given name and argument types. You might need to add explicit type casts."))
+}
+
+pub fn type_check_window_function(
+    name: &str,
+    args: &[Expression],
+    aggregate_registry: &AggregateFunctionRegistry,
+) -> Result<(WindowFunctionKind, Vec<Expression>), SQLError> {
+    if let Some(kind) = WindowFunctionKind::ranking(name) {
+        if !args.is_empty() {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!(
+                    "cannot find overload of function with given types: {}",
+                    name
+                ),
+            )
+.with_code(sqlstate::UNDEFINED_FUNCTION)
+.with_hint("No function matches the given name and argument types. You might need to add explicit type casts."));
+        }
+
+        return Ok((kind, vec![]));
+    }
+
+    let (agg, args) = type_check_aggregate_function(name, args, aggregate_registry)?;
+
+    Ok((WindowFunctionKind::Aggregate(agg), args))
 }
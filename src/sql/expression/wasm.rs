@@ -0,0 +1,257 @@
+use wasmi::{
+    core::{TrapCode, ValueType},
+    Engine, Extern, ExternType, FuncType, Instance, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, Value,
+};
+
+use super::function::ScalarFunctionRegistry;
+use crate::{
+    core::{sqlstate, Datum, ErrorKind, SQLError, Type},
+    sql::session::to_pg_type,
+};
+
+/// Fuel of a call, which is consumed by the executed instructions, so the
+/// functions looping forever are stopped.
+const CALL_FUEL: u64 = 100_000_000;
+
+/// Maximum size of the linear memory of a call.
+const MEMORY_LIMIT: usize = 16 << 20;
+
+/// A scalar function exported by a WebAssembly module. The module is
+/// instantiated in a new sandbox for every call, which can't import any host
+/// function and has bounded fuel and memory, so the calls can't affect the
+/// server nor each other.
+///
+/// Integers are passed as `i64` and booleans as `i32`. Text
+/// and binary strings are copied into the memory allocated by the exported
+/// `alloc(len: i32) -> i32`, and passed as the pointer and length. The
+/// strings returned are packed into `i64`, the pointer in the high 32 bits
+/// and the length in the low ones.
+pub struct WasmFunction {
+    name: String,
+    module: Module,
+    arg_types: Vec<Type>,
+    ret_type: Type,
+}
+
+impl WasmFunction {
+    /// Compile the module and check it exports the function with the
+    /// signature of argument and return types.
+    pub fn compile(
+        name: &str,
+        bytes: &[u8],
+        arg_types: &[Type],
+        ret_type: &Type,
+    ) -> Result<Self, SQLError> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| {
+            invalid_definition(format!(
+                "invalid WebAssembly module of function {}: {}",
+                name, e
+            ))
+        })?;
+
+        if let Some(import) = module.imports().next() {
+            return Err(invalid_definition(format!(
+                "WebAssembly module of function {} cannot import \"{}.{}\"",
+                name,
+                import.module(),
+                import.name()
+            ))
+            .with_hint("Functions run without access to the host."));
+        }
+
+        let mut params = vec![];
+        for typ in arg_types.iter() {
+            params.extend_from_slice(value_types(typ)?);
+        }
+        let results = match value_types(ret_type)? {
+            [ValueType::I32, ValueType::I32] => &[ValueType::I64][..],
+            results => results,
+        };
+        let expected = FuncType::new(params, results.iter().copied());
+        match module.get_export(name) {
+            Some(ExternType::Func(func_type)) if func_type == expected => {}
+            _ => {
+                return Err(invalid_definition(format!(
+                    "WebAssembly module must export function \"{}\" of type {:?} -> {:?}",
+                    name,
+                    expected.params(),
+                    expected.results()
+                )))
+            }
+        }
+
+        let uses_memory = arg_types
+            .iter()
+            .chain(std::iter::once(ret_type))
+            .any(|typ| matches!(typ, Type::String | Type::Bytea));
+        let alloc_type = FuncType::new([ValueType::I32], [ValueType::I32]);
+        if uses_memory
+            && (!matches!(module.get_export("memory"), Some(ExternType::Memory(_)))
+                || !matches!(module.get_export("alloc"), Some(ExternType::Func(func_type)) if func_type == alloc_type))
+        {
+            return Err(invalid_definition(format!(
+                "WebAssembly module of function {} must export \"memory\" and \"alloc\" to pass strings",
+                name
+            )));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            module,
+            arg_types: arg_types.to_vec(),
+            ret_type: ret_type.clone(),
+        })
+    }
+
+    /// Register the function with the name, NULL is returned if any argument
+    /// is NULL.
+    pub fn register(self, registry: &mut ScalarFunctionRegistry, name: &str) {
+        let (arg_types, ret_type) = (self.arg_types.clone(), self.ret_type.clone());
+        registry.register_null_passthrough(name, &arg_types, ret_type, move |args| self.call(args));
+    }
+
+    pub fn call(&self, args: &[Datum]) -> Result<Datum, SQLError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MEMORY_LIMIT)
+            .instances(1)
+            .build();
+        let mut store = Store::new(self.module.engine(), limits);
+        store.limiter(|limits| limits);
+        store.add_fuel(CALL_FUEL).unwrap();
+        let instance = Linker::<StoreLimits>::new(self.module.engine())
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| self.call_error(e))?;
+
+        let mut params = vec![];
+        for arg in args.iter() {
+            match arg {
+                Datum::Int(v) => params.push(Value::I64(*v)),
+                Datum::Boolean(v) => params.push(Value::I32(*v as i32)),
+                Datum::String(v) => {
+                    params.extend(self.pass_bytes(&mut store, instance, v.as_bytes())?)
+                }
+                Datum::Bytea(v) => params.extend(self.pass_bytes(&mut store, instance, v)?),
+                arg => unreachable!("argument {} is checked on creation", arg),
+            }
+        }
+
+        let func = instance.get_func(&store, &self.name).unwrap();
+        let mut results = [Value::default(func.ty(&store).results()[0])];
+        func.call(&mut store, &params, &mut results)
+            .map_err(|e| self.call_error(e))?;
+
+        Ok(match (&self.ret_type, &results[0]) {
+            (Type::Int, Value::I64(v)) => Datum::Int(*v),
+            (Type::Boolean, Value::I32(v)) => Datum::Boolean(*v != 0),
+            (Type::String, Value::I64(v)) => {
+                let bytes = self.read_bytes(&store, instance, *v)?;
+                let text = String::from_utf8(bytes).map_err(|_| {
+                    SQLError::new(
+                        ErrorKind::RuntimeError,
+                        format!("function {} returned invalid UTF-8 text", self.name),
+                    )
+                    .with_code(sqlstate::DATA_EXCEPTION)
+                })?;
+                Datum::String(text.into())
+            }
+            (Type::Bytea, Value::I64(v)) => {
+                Datum::Bytea(self.read_bytes(&store, instance, *v)?.into())
+            }
+            (_, result) => unreachable!("result {:?} is checked on creation", result),
+        })
+    }
+
+    /// Copy the bytes into the memory allocated by the module, returns the
+    /// pointer and length.
+    fn pass_bytes(
+        &self,
+        store: &mut Store<StoreLimits>,
+        instance: Instance,
+        bytes: &[u8],
+    ) -> Result<[Value; 2], SQLError> {
+        let len = i32::try_from(bytes.len()).map_err(|_| self.memory_error())?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&*store, "alloc")
+            .unwrap();
+        let ptr = alloc
+            .call(&mut *store, len)
+            .map_err(|e| self.call_error(e.into()))?;
+        memory(&*store, instance)
+            .write(&mut *store, ptr as u32 as usize, bytes)
+            .map_err(|_| self.memory_error())?;
+
+        Ok([Value::I32(ptr), Value::I32(len)])
+    }
+
+    fn read_bytes(
+        &self,
+        store: &Store<StoreLimits>,
+        instance: Instance,
+        packed: i64,
+    ) -> Result<Vec<u8>, SQLError> {
+        let (ptr, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+        memory(store, instance)
+            .data(store)
+            .get(ptr..ptr + len)
+            .map(|bytes| bytes.to_vec())
+            .ok_or_else(|| self.memory_error())
+    }
+
+    fn call_error(&self, e: wasmi::Error) -> SQLError {
+        let out_of_fuel = matches!(&e, wasmi::Error::Trap(trap) if matches!(trap.trap_code(), Some(TrapCode::OutOfFuel)));
+        if out_of_fuel {
+            SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("function {} exceeded the execution limit", self.name),
+            )
+            .with_code(sqlstate::PROGRAM_LIMIT_EXCEEDED)
+        } else {
+            SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("function {} failed: {}", self.name, e),
+            )
+            .with_code(sqlstate::EXTERNAL_ROUTINE_EXCEPTION)
+        }
+    }
+
+    fn memory_error(&self) -> SQLError {
+        SQLError::new(
+            ErrorKind::RuntimeError,
+            format!("function {} accessed memory out of bounds", self.name),
+        )
+        .with_code(sqlstate::EXTERNAL_ROUTINE_EXCEPTION)
+    }
+}
+
+fn memory(store: &Store<StoreLimits>, instance: Instance) -> Memory {
+    match instance.get_export(store, "memory") {
+        Some(Extern::Memory(memory)) => memory,
+        _ => unreachable!("memory is checked on creation"),
+    }
+}
+
+/// WebAssembly values the datum of type is passed as.
+fn value_types(typ: &Type) -> Result<&'static [ValueType], SQLError> {
+    match typ {
+        Type::Int => Ok(&[ValueType::I64]),
+        Type::Boolean => Ok(&[ValueType::I32]),
+        Type::String | Type::Bytea => Ok(&[ValueType::I32, ValueType::I32]),
+        typ => Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!(
+                "type {} is not supported by WebAssembly functions",
+                to_pg_type(typ).name()
+            ),
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+    }
+}
+
+fn invalid_definition(message: String) -> SQLError {
+    SQLError::new(ErrorKind::PlannerError, message).with_code(sqlstate::INVALID_FUNCTION_DEFINITION)
+}
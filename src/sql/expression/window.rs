@@ -0,0 +1,121 @@
+use std::{fmt::Display, sync::Arc};
+
+use super::{aggregate::AggregateFunction, Expression};
+use crate::core::{Datum, SortOrder, Type};
+
+#[derive(Clone)]
+pub enum WindowFunctionKind {
+    /// `row_number()`, number of the current row within its partition.
+    RowNumber,
+    /// `rank()`, rank of the current row with gaps.
+    Rank,
+    /// `dense_rank()`, rank of the current row without gaps.
+    DenseRank,
+    /// Aggregate function used as window function, e.g. `sum(a) OVER (...)`.
+    Aggregate(Arc<AggregateFunction>),
+}
+
+impl WindowFunctionKind {
+    /// Find the ranking function with the given name.
+    pub fn ranking(name: &str) -> Option<Self> {
+        match name {
+            "row_number" => Some(WindowFunctionKind::RowNumber),
+            "rank" => Some(WindowFunctionKind::Rank),
+            "dense_rank" => Some(WindowFunctionKind::DenseRank),
+            _ => None,
+        }
+    }
+}
+
+/// Frame of a window function, the tuples of the partition aggregated for
+/// the current tuple, e.g. `ROWS BETWEEN 1 PRECEDING AND CURRENT ROW`.
+/// Ranking functions ignore it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowFrame {
+    pub units: WindowFrameUnits,
+    pub start: WindowFrameBound,
+    pub end: WindowFrameBound,
+}
+
+impl Default for WindowFrame {
+    /// `RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW`, from the start of
+    /// the partition to the last peer of the current tuple.
+    fn default() -> Self {
+        Self {
+            units: WindowFrameUnits::Range,
+            start: WindowFrameBound::UnboundedPreceding,
+            end: WindowFrameBound::CurrentRow,
+        }
+    }
+}
+
+impl Display for WindowFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let units = match self.units {
+            WindowFrameUnits::Rows => "ROWS",
+            WindowFrameUnits::Range => "RANGE",
+        };
+        write!(f, "{} BETWEEN {} AND {}", units, self.start, self.end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowFrameUnits {
+    /// The offsets are numbers of tuples.
+    Rows,
+    /// The offsets are distances from the order key of the current tuple,
+    /// and the current row includes its peers.
+    Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowFrameBound {
+    UnboundedPreceding,
+    Preceding(Datum),
+    CurrentRow,
+    Following(Datum),
+    UnboundedFollowing,
+}
+
+impl WindowFrameBound {
+    pub fn offset(&self) -> Option<&Datum> {
+        match self {
+            WindowFrameBound::Preceding(offset) | WindowFrameBound::Following(offset) => {
+                Some(offset)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for WindowFrameBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowFrameBound::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            WindowFrameBound::Preceding(offset) => write!(f, "{} PRECEDING", offset),
+            WindowFrameBound::CurrentRow => write!(f, "CURRENT ROW"),
+            WindowFrameBound::Following(offset) => write!(f, "{} FOLLOWING", offset),
+            WindowFrameBound::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WindowFunction {
+    pub kind: WindowFunctionKind,
+    pub args: Vec<Expression>,
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<(Expression, SortOrder)>,
+    pub frame: WindowFrame,
+}
+
+impl WindowFunction {
+    pub fn ret_type(&self) -> Type {
+        match &self.kind {
+            WindowFunctionKind::RowNumber
+            | WindowFunctionKind::Rank
+            | WindowFunctionKind::DenseRank => Type::Int,
+            WindowFunctionKind::Aggregate(agg) => agg.ret_type.clone(),
+        }
+    }
+}
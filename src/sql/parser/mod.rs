@@ -1,15 +1,424 @@
-use sqlparser::{ast::Statement, dialect::PostgreSqlDialect, parser::Parser};
+use std::fmt;
+
+use sqlparser::{
+    ast::{
+        helpers::stmt_create_table::CreateTableBuilder, AnalyzeFormat, Expr, ObjectName, SetExpr,
+        Statement,
+    },
+    dialect::PostgreSqlDialect,
+    keywords::Keyword,
+    parser::{Parser, ParserError},
+    tokenizer::{Token, Tokenizer, Word},
+};
 
 use crate::core::{ErrorKind, SQLError};
 
-/// Parse SQL string into AST
-pub fn parse_sql(sql_text: &str) -> Result<Statement, SQLError> {
-    let parser = Parser::new(&PostgreSqlDialect {});
+/// A statement of SQL text, either parsed by sqlparser, or one of the
+/// Postgres statements it doesn't support.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SQLStatement {
+    Sql(Statement),
+    /// `EXPLAIN [(option [value], ...)] statement`, including the options
+    /// the parser has no fields for.
+    Explain {
+        statement: Box<Statement>,
+        analyze: bool,
+        verbose: bool,
+        costs: bool,
+        format: Option<AnalyzeFormat>,
+    },
+    /// `VACUUM table`, the table name is empty if omitted, which means all
+    /// the tables.
+    Vacuum {
+        table_name: ObjectName,
+    },
+    /// `DROP DATABASE [IF EXISTS] name`
+    DropDatabase {
+        name: ObjectName,
+        if_exists: bool,
+    },
+}
+
+/// `EXPLAIN` and `DESCRIBE` parsed by the parser are the same as `EXPLAIN`
+/// with options.
+impl From<Statement> for SQLStatement {
+    fn from(statement: Statement) -> Self {
+        match statement {
+            Statement::Explain {
+                analyze,
+                verbose,
+                statement,
+                format,
+                ..
+            } => SQLStatement::Explain {
+                statement,
+                analyze,
+                verbose,
+                costs: true,
+                format,
+            },
+            statement => SQLStatement::Sql(statement),
+        }
+    }
+}
+
+impl fmt::Display for SQLStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SQLStatement::Sql(statement) => write!(f, "{}", statement),
+            SQLStatement::Explain {
+                statement,
+                analyze,
+                verbose,
+                costs,
+                format,
+            } => {
+                let mut options = vec![];
+                if *analyze {
+                    options.push("ANALYZE".to_string());
+                }
+                if *verbose {
+                    options.push("VERBOSE".to_string());
+                }
+                if !costs {
+                    options.push("COSTS OFF".to_string());
+                }
+                if let Some(format) = format {
+                    options.push(format!("FORMAT {}", format));
+                }
+                if options.is_empty() {
+                    write!(f, "EXPLAIN {}", statement)
+                } else {
+                    write!(f, "EXPLAIN ({}) {}", options.join(", "), statement)
+                }
+            }
+            SQLStatement::Vacuum { table_name } if table_name.0.is_empty() => write!(f, "VACUUM"),
+            SQLStatement::Vacuum { table_name } => write!(f, "VACUUM {}", table_name),
+            SQLStatement::DropDatabase { name, if_exists } => write!(
+                f,
+                "DROP DATABASE {}{}",
+                if *if_exists { "IF EXISTS " } else { "" },
+                name
+            ),
+        }
+    }
+}
+
+/// Parse SQL string into AST, the string may contain multiple statements
+/// separated by semicolons.
+pub fn parse_sql(sql_text: &str) -> Result<Vec<SQLStatement>, SQLError> {
+    let dialect = PostgreSqlDialect {};
 
-    let statement = parser
-        .try_with_sql(sql_text)
-        .and_then(|mut parser| parser.parse_statement())
+    let mut statements = tokenize(&dialect, sql_text)
+        .and_then(|tokens| parse_statements(&mut Parser::new(&dialect).with_tokens(tokens)))
         .map_err(|e| SQLError::new(ErrorKind::ParseError, e.to_string()))?;
+    for statement in statements.iter_mut() {
+        let statement = match statement {
+            SQLStatement::Sql(statement) => statement,
+            SQLStatement::Explain { statement, .. } => statement.as_mut(),
+            _ => continue,
+        };
+        rewrite_select_into(statement);
+    }
+
+    Ok(statements)
+}
+
+/// `SELECT ... INTO new_table FROM ...` is rewritten to
+/// `CREATE TABLE new_table AS SELECT ... FROM ...`.
+fn rewrite_select_into(statement: &mut Statement) {
+    let Statement::Query(query) = statement else {
+        return;
+    };
+    let SetExpr::Select(select) = query.body.as_mut() else {
+        return;
+    };
+    let Some(into) = select.into.take() else {
+        return;
+    };
+    *statement = CreateTableBuilder::new(into.name)
+        .temporary(into.temporary)
+        .query(Some(query.clone()))
+        .build();
+}
+
+/// Parse a single expression, e.g. the default value of a column.
+pub fn parse_expr(sql_text: &str) -> Result<Expr, SQLError> {
+    let dialect = PostgreSqlDialect {};
+
+    tokenize(&dialect, sql_text)
+        .and_then(|tokens| Parser::new(&dialect).with_tokens(tokens).parse_expr())
+        .map_err(|e| SQLError::new(ErrorKind::ParseError, e.to_string()))
+}
+
+fn tokenize(dialect: &PostgreSqlDialect, sql_text: &str) -> Result<Vec<Token>, ParserError> {
+    let tokens = Tokenizer::new(dialect, sql_text).tokenize()?;
+    Ok(rewrite_table_queries(rewrite_array_aggs(tokens)))
+}
+
+/// `array_agg` is a keyword, which the parser parses into a special
+/// expression supporting a single sort key of `ORDER BY` and no `FILTER`. It's
+/// rewritten to a plain word to be parsed as a call of aggregate function.
+fn rewrite_array_aggs(tokens: Vec<Token>) -> Vec<Token> {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            Token::Word(word) if word.keyword == Keyword::ARRAY_AGG => Token::Word(Word {
+                keyword: Keyword::NoKeyword,
+                ..word
+            }),
+            token => token,
+        })
+        .collect()
+}
+
+/// Rewrite `TABLE name` starting a query to `SELECT * FROM name`, since the
+/// parser only supports it in a few places and consumes the tokens following
+/// the name. Queries start a statement or follow `(`, `)` of `EXPLAIN`
+/// options, a set operator, `AS`, or the keywords of `EXPLAIN`.
+fn rewrite_table_queries(tokens: Vec<Token>) -> Vec<Token> {
+    let mut result: Vec<Token> = Vec::with_capacity(tokens.len());
+    let mut is_explain = false;
+    for token in tokens {
+        let previous = result
+            .iter()
+            .rev()
+            .find(|token| !matches!(token, Token::Whitespace(_)));
+        let starts_query = match previous {
+            None | Some(Token::SemiColon | Token::LParen | Token::RParen) => true,
+            Some(Token::Word(word)) => match word.keyword {
+                Keyword::UNION
+                | Keyword::EXCEPT
+                | Keyword::INTERSECT
+                | Keyword::ALL
+                | Keyword::DISTINCT
+                | Keyword::AS
+                | Keyword::EXPLAIN => true,
+                Keyword::ANALYZE | Keyword::VERBOSE => is_explain,
+                _ => false,
+            },
+            _ => false,
+        };
+        match &token {
+            Token::SemiColon => is_explain = false,
+            Token::Word(word) if matches!(previous, None | Some(Token::SemiColon)) => {
+                is_explain = word.keyword == Keyword::EXPLAIN
+            }
+            _ => {}
+        }
+
+        if starts_query && matches!(&token, Token::Word(word) if word.keyword == Keyword::TABLE) {
+            result.push(Token::make_keyword("SELECT"));
+            result.push(Token::Mul);
+            result.push(Token::make_keyword("FROM"));
+        } else {
+            result.push(token);
+        }
+    }
+
+    result
+}
+
+/// Same as `Parser::parse_statements`, with the Postgres syntax which is not
+/// supported by the parser.
+fn parse_statements(parser: &mut Parser) -> Result<Vec<SQLStatement>, ParserError> {
+    let mut statements = vec![];
+    let mut expecting_statement_delimiter = false;
+    loop {
+        while parser.consume_token(&Token::SemiColon) {
+            expecting_statement_delimiter = false;
+        }
+
+        if parser.peek_token() == Token::EOF {
+            break;
+        }
+        if expecting_statement_delimiter {
+            return parser.expected("end of statement", parser.peek_token());
+        }
+
+        let is_explain_with_options = matches!(
+            parser.peek_token().token,
+            Token::Word(word) if word.keyword == Keyword::EXPLAIN
+        ) && parser.peek_nth_token(1) == Token::LParen;
+        let is_analyze = matches!(
+            parser.peek_token().token,
+            Token::Word(word) if word.keyword == Keyword::ANALYZE
+        );
+        let is_vacuum = matches!(
+            parser.peek_token().token,
+            Token::Word(word) if word.value.eq_ignore_ascii_case("VACUUM")
+        );
+        let is_drop_database = matches!(
+            parser.peek_token().token,
+            Token::Word(word) if word.keyword == Keyword::DROP
+        ) && matches!(
+            parser.peek_nth_token(1).token,
+            Token::Word(word) if word.keyword == Keyword::DATABASE
+        );
+        if is_explain_with_options {
+            statements.push(parse_explain_with_options(parser)?);
+        } else if is_analyze {
+            statements.push(parse_analyze(parser)?.into());
+        } else if is_vacuum {
+            statements.extend(parse_vacuum(parser)?);
+        } else if is_drop_database {
+            statements.push(parse_drop_database(parser)?);
+        } else {
+            statements.push(parser.parse_statement()?.into());
+        }
+        expecting_statement_delimiter = true;
+    }
+
+    Ok(statements)
+}
+
+/// `EXPLAIN (option [value], ...) statement`, the options without effects
+/// like `BUFFERS` are accepted and ignored.
+fn parse_explain_with_options(parser: &mut Parser) -> Result<SQLStatement, ParserError> {
+    parser.next_token();
+    parser.expect_token(&Token::LParen)?;
+
+    let mut analyze = false;
+    let mut verbose = false;
+    let mut costs = true;
+    let mut format = None;
+    loop {
+        let option = parser.parse_identifier()?;
+        match option.value.to_uppercase().as_str() {
+            "ANALYZE" => analyze = parse_explain_boolean(parser)?,
+            "VERBOSE" => verbose = parse_explain_boolean(parser)?,
+            "FORMAT" => {
+                let name = parser.parse_identifier()?;
+                format = match name.value.to_uppercase().as_str() {
+                    "TEXT" => Some(AnalyzeFormat::TEXT),
+                    "JSON" => Some(AnalyzeFormat::JSON),
+                    _ => {
+                        return Err(ParserError::ParserError(format!(
+                            "unrecognized value for EXPLAIN option \"format\": \"{}\"",
+                            name.value
+                        )))
+                    }
+                };
+            }
+            "COSTS" => costs = parse_explain_boolean(parser)?,
+            "SETTINGS" | "BUFFERS" | "WAL" | "TIMING" | "SUMMARY" => {
+                parse_explain_boolean(parser)?;
+            }
+            _ => {
+                return Err(ParserError::ParserError(format!(
+                    "unrecognized EXPLAIN option \"{}\"",
+                    option.value
+                )))
+            }
+        }
+
+        if !parser.consume_token(&Token::Comma) {
+            break;
+        }
+    }
+    parser.expect_token(&Token::RParen)?;
+
+    let statement = parser.parse_statement()?;
+    if let Statement::Explain { .. } = statement {
+        return Err(ParserError::ParserError(
+            "Explain must be root of the plan".to_string(),
+        ));
+    }
+
+    Ok(SQLStatement::Explain {
+        statement: Box::new(statement),
+        analyze,
+        verbose,
+        costs,
+        format,
+    })
+}
+
+/// `ANALYZE [VERBOSE] [table]`, the table name is empty if omitted, which
+/// means all the tables. The parser only supports the Hive syntax
+/// `ANALYZE TABLE table`, which is also accepted.
+fn parse_analyze(parser: &mut Parser) -> Result<Statement, ParserError> {
+    parser.next_token();
+    let _ = parser.parse_keyword(Keyword::VERBOSE);
+    let _ = parser.parse_keyword(Keyword::TABLE);
+
+    Ok(analyze_statement(parse_optional_table_name(parser)?))
+}
+
+/// `VACUUM [FULL] [FREEZE] [VERBOSE] [ANALYZE] [table]`, the table name is
+/// empty if omitted. The tables are always compacted, and `ANALYZE` is
+/// followed by an `ANALYZE` statement of the same tables.
+fn parse_vacuum(parser: &mut Parser) -> Result<Vec<SQLStatement>, ParserError> {
+    parser.next_token();
+    let mut analyze = false;
+    while let Some(keyword) = parser.parse_one_of_keywords(&[
+        Keyword::FULL,
+        Keyword::FREEZE,
+        Keyword::VERBOSE,
+        Keyword::ANALYZE,
+    ]) {
+        analyze |= keyword == Keyword::ANALYZE;
+    }
+
+    let table_name = parse_optional_table_name(parser)?;
+    let mut statements = vec![SQLStatement::Vacuum {
+        table_name: table_name.clone(),
+    }];
+    if analyze {
+        statements.push(analyze_statement(table_name).into());
+    }
+
+    Ok(statements)
+}
+
+/// `DROP DATABASE [IF EXISTS] name`, which is not supported by the parser.
+fn parse_drop_database(parser: &mut Parser) -> Result<SQLStatement, ParserError> {
+    parser.next_token();
+    parser.next_token();
+    let if_exists = parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+
+    Ok(SQLStatement::DropDatabase {
+        name: parser.parse_object_name()?,
+        if_exists,
+    })
+}
+
+/// Name of the table at the end of statement, empty if omitted.
+fn parse_optional_table_name(parser: &mut Parser) -> Result<ObjectName, ParserError> {
+    if matches!(parser.peek_token().token, Token::EOF | Token::SemiColon) {
+        Ok(ObjectName(vec![]))
+    } else {
+        parser.parse_object_name()
+    }
+}
+
+fn analyze_statement(table_name: ObjectName) -> Statement {
+    Statement::Analyze {
+        table_name,
+        partitions: None,
+        for_columns: false,
+        columns: vec![],
+        cache_metadata: false,
+        noscan: false,
+        compute_statistics: false,
+    }
+}
+
+/// Optional boolean value of `EXPLAIN` option, which is true if omitted.
+fn parse_explain_boolean(parser: &mut Parser) -> Result<bool, ParserError> {
+    if matches!(parser.peek_token().token, Token::Comma | Token::RParen) {
+        return Ok(true);
+    }
 
-    Ok(statement)
+    let token = parser.next_token();
+    let value = match &token.token {
+        Token::Word(word) => word.value.to_lowercase(),
+        Token::Number(number, _) => number.clone(),
+        _ => return parser.expected("a boolean value", token),
+    };
+    match value.as_str() {
+        "true" | "on" | "1" => Ok(true),
+        "false" | "off" | "0" => Ok(false),
+        _ => parser.expected("a boolean value", token),
+    }
 }
@@ -1,19 +1,29 @@
-use std::ops::ControlFlow;
+use std::{ops::ControlFlow, sync::Arc};
 
 use sqlparser::ast::{Expr, Function, Visitor};
 
+use super::subquery::is_subquery;
 use crate::{core::SQLError, sql::expression::aggregate::AggregateFunctionRegistry};
 
 pub struct AggregateFunctionVisitor {
+    registry: Arc<AggregateFunctionRegistry>,
     pub aggregates: Vec<Function>,
+    /// Calls of `grouping()`, which are computed with the aggregates.
+    pub groupings: Vec<Function>,
     pub error: Option<SQLError>,
+    /// Number of the subqueries being visited, whose aggregate functions
+    /// belong to themselves.
+    depth: usize,
 }
 
 impl AggregateFunctionVisitor {
-    pub fn new() -> Self {
+    pub fn new(registry: Arc<AggregateFunctionRegistry>) -> Self {
         Self {
+            registry,
             aggregates: vec![],
+            groupings: vec![],
             error: None,
+            depth: 0,
         }
     }
 }
@@ -22,16 +32,35 @@ impl Visitor for AggregateFunctionVisitor {
     type Break = ();
 
     fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if is_subquery(expr) {
+            self.depth += 1;
+        }
+        if self.depth > 0 {
+            return ControlFlow::Continue(());
+        }
         match expr {
             Expr::Function(func)
-                if func.name.0.len() == 1
-                    && AggregateFunctionRegistry::builtin()
-                        .contains(&func.name.0[0].to_string()) =>
+                if func.over.is_none()
+                    && func.name.0.len() == 1
+                    && self.registry.contains(&func.name.0[0].value.to_lowercase()) =>
             {
                 self.aggregates.push(func.clone());
             }
+            Expr::Function(func)
+                if func.name.0.len() == 1
+                    && func.name.0[0].value.eq_ignore_ascii_case("grouping") =>
+            {
+                self.groupings.push(func.clone());
+            }
             _ => {}
         }
         ControlFlow::Continue(())
     }
+
+    fn post_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if is_subquery(expr) {
+            self.depth -= 1;
+        }
+        ControlFlow::Continue(())
+    }
 }
@@ -1,16 +1,23 @@
 use super::scope::Scope;
 
+/// BindContext keeps track of the scopes of the outer queries
+/// while binding subqueries.
 pub struct BindContext {
     pub scopes: Vec<Scope>,
 }
 
-#[allow(dead_code)]
 impl BindContext {
     pub fn push(&mut self, scope: Scope) {
         self.scopes.push(scope);
     }
 
-    pub fn current_scope(&self) -> &Scope {
-        self.scopes.last().unwrap()
+    pub fn pop(&mut self) -> Option<Scope> {
+        self.scopes.pop()
+    }
+
+    /// Scope of the nearest outer query, only the columns of this
+    /// scope can be referenced by a correlated subquery.
+    pub fn outer_scope(&self) -> Option<&Scope> {
+        self.scopes.last()
     }
 }
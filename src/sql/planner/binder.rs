@@ -1,23 +1,57 @@
+use std::{
+    ops::ControlFlow,
+    path::{Component, Path},
+    sync::Arc,
+};
+
 use sqlparser::ast::{
-    Expr, Ident, JoinConstraint, JoinOperator, ObjectName, Query, Select, SelectItem, SetExpr,
-    Statement, TableAlias, TableFactor, TableWithJoins, Visit,
+    visit_expressions_mut, AlterColumnOperation, AlterTableOperation, AnalyzeFormat, ArgMode,
+    Assignment, BinaryOperator, ColumnDef, ColumnOption, CopyLegacyCsvOption, CopyLegacyOption,
+    CopyOption, CopySource, CopyTarget, CreateFunctionBody, DataType, Distinct, Expr, FunctionArg,
+    FunctionArgExpr, FunctionDefinition as FunctionAs, GroupByExpr, Ident, JoinConstraint,
+    JoinOperator, MinMaxValue, ObjectName, OperateFunctionArg, OrderByExpr, Query,
+    ReferentialAction, Select, SelectItem, SequenceOptions, SetExpr, SetOperator, SetQuantifier,
+    Statement, TableAlias, TableConstraint, TableFactor, TableWithJoins, Value, Values, Visit,
 };
 
 use super::{
     aggregate::AggregateFunctionVisitor,
     bind_context::BindContext,
-    scalar::bind_aggregate_function,
+    decorrelate::decorrelate_subquery,
+    function::{build_function_body, inline_functions, is_builtin_function, qualify_function_name},
+    index::collect_columns,
+    optimizer::Optimizer,
+    scalar::{
+        bind_aggregate_function, bind_grouping_function, bind_order_by, bind_window_function,
+    },
     scope::{QualifiedNamePrefix, Variable},
-    Column, Plan, ScalarExpr,
+    subquery::ScalarSubqueryVisitor,
+    window::WindowFunctionVisitor,
+    AggregateExpr, Column, ExplainFormat, ExplainOptions, OrderBy, Plan, ScalarExpr,
 };
 use crate::{
-    catalog::defs::{ColumnDefinition, TableDefinition},
-    core::{ErrorKind, SQLError, Tuple, Type},
+    catalog::{
+        defs::{
+            ColumnDefinition, ForeignKeyDefinition, FunctionBody, FunctionDefinition,
+            IndexDefinition, ReferentialAction as ForeignKeyAction, SequenceDefinition,
+            TableDefinition,
+        },
+        pg_catalog::{find_system_table, OWNER_NAME, SYSTEM_SCHEMA},
+    },
+    core::{sqlstate, Datum, ErrorKind, IntWidth, SQLError, Type},
     sql::{
+        expression::{aggregate::AggregateFunctionRegistry, wasm::WasmFunction},
+        parser::{parse_expr, parse_sql, SQLStatement},
         planner::{scalar::bind_scalar, scope::Scope},
-        runtime::{DDLJob, DMLJob},
-        session::context::QueryContext,
+        runtime::{
+            copy_file_error, AlterTableJob, CopyFormat, CopyJob, CsvOptions, DDLJob, DMLJob,
+        },
+        session::{
+            context::QueryContext,
+            settings::{check_settable, unrecognized_parameter},
+        },
     },
+    util::format_timestamp,
 };
 
 struct FlattenedSelectItem {
@@ -34,20 +68,104 @@ impl<'a> Binder<'a> {
         Self { ctx }
     }
 
-    pub fn bind_statement(&mut self, stmt: &Statement) -> Result<(Plan, Scope), SQLError> {
+    pub fn bind_statement(&mut self, stmt: &SQLStatement) -> Result<(Plan, Scope), SQLError> {
+        let catalog_version = self.ctx.catalog().version;
+        self.ctx.catalog_version = catalog_version;
+
+        match stmt {
+            SQLStatement::Sql(stmt) => self.bind_sql_statement(stmt),
+            SQLStatement::Explain {
+                statement,
+                analyze,
+                verbose,
+                costs,
+                format,
+            } => {
+                let format = match format {
+                    None | Some(AnalyzeFormat::TEXT) => ExplainFormat::Text,
+                    Some(AnalyzeFormat::JSON) => ExplainFormat::Json,
+                    Some(format) => {
+                        return Err(SQLError::new(
+                            ErrorKind::PlannerError,
+                            format!("unsupported EXPLAIN format: {}", format),
+                        )
+                        .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+                    }
+                };
+                let (plan, _) = self.bind_sql_statement(statement)?;
+                let plan = Plan::Explain {
+                    plan: Box::new(plan),
+                    options: ExplainOptions {
+                        format,
+                        verbose: *verbose,
+                        costs: *costs,
+                        analyze: *analyze,
+                    },
+                };
+                let scope = Scope {
+                    variables: vec![Variable {
+                        prefix: None,
+                        name: "QUERY PLAN".to_string(),
+                        expr: None,
+                    }],
+                    ..Default::default()
+                };
+
+                Ok((plan, scope))
+            }
+            SQLStatement::Vacuum { table_name } => {
+                let names = self.bind_maintained_tables(table_name)?;
+                Ok((Plan::DDL(DDLJob::Vacuum(names)), Scope::default()))
+            }
+            SQLStatement::DropDatabase { name, if_exists } => {
+                let database_name = Self::bind_database_name(name)?;
+                let plan = Plan::DDL(DDLJob::DropDatabase(database_name, *if_exists));
+
+                Ok((plan, Scope::default()))
+            }
+        }
+    }
+
+    fn bind_sql_statement(&mut self, stmt: &Statement) -> Result<(Plan, Scope), SQLError> {
         let mut bind_context = BindContext { scopes: vec![] };
 
+        // The calls of SQL functions are replaced by their bodies, while the
+        // bodies of functions being created call the functions by name.
+        let inlined;
+        let stmt = if self.ctx.catalog().has_functions()
+            && !matches!(stmt, Statement::CreateFunction { .. })
+        {
+            let mut stmt = stmt.clone();
+            inline_functions(
+                &mut stmt,
+                &self.ctx.catalog(),
+                &self.ctx.current_database,
+                &self.ctx.current_schema,
+            )?;
+            inlined = stmt;
+            &inlined
+        } else {
+            stmt
+        };
+
         match stmt {
+            Statement::CreateDatabase {
+                db_name,
+                if_not_exists,
+                ..
+            } => {
+                let database_name = Self::bind_database_name(db_name)?;
+                let plan = Plan::DDL(DDLJob::CreateDatabase(database_name, *if_not_exists));
+
+                Ok((plan, Scope::default()))
+            }
+
             Statement::CreateSchema {
                 schema_name,
                 if_not_exists,
             } => {
-                if *if_not_exists {
-                    unimplemented!()
-                }
-
                 let schema_name = schema_name.to_string();
-                let plan = Plan::DDL(DDLJob::CreateSchema(schema_name));
+                let plan = Plan::DDL(DDLJob::CreateSchema(schema_name, *if_not_exists));
 
                 Ok((plan, Scope::default()))
             }
@@ -55,13 +173,11 @@ impl<'a> Binder<'a> {
             Statement::CreateTable {
                 if_not_exists,
                 name,
-                columns,
+                columns: column_defs,
+                constraints,
+                query,
                 ..
             } => {
-                if *if_not_exists {
-                    unimplemented!()
-                }
-
                 let (schema_name, table_name) = match name {
                     ObjectName(v) if v.len() == 1 => {
                         (self.ctx.current_schema.clone(), v[0].to_string())
@@ -70,29 +186,438 @@ impl<'a> Binder<'a> {
                     _ => return Err(SQLError::new(ErrorKind::PlannerError, "invalid table name")),
                 };
 
-                let columns = columns
+                // The columns are derived from the output of query
+                if let Some(query) = query {
+                    if !column_defs.is_empty() || !constraints.is_empty() {
+                        return Err(SQLError::new(
+                            ErrorKind::PlannerError,
+                            "CREATE TABLE AS with column definitions or constraints is not supported",
+                        )
+                        .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+                    }
+
+                    let (input, scope) = self.bind_query(&mut bind_context, query)?;
+                    let input = Optimizer::default().optimize(
+                        input,
+                        &self.ctx.catalog().database(&self.ctx.current_database),
+                    )?;
+                    let mut column_names: Vec<String> = vec![];
+                    for variable in scope.variables.iter() {
+                        if column_names.contains(&variable.name) {
+                            return Err(SQLError::new(
+                                ErrorKind::PlannerError,
+                                format!("column \"{}\" specified more than once", variable.name),
+                            )
+                            .with_code(sqlstate::DUPLICATE_COLUMN));
+                        }
+                        column_names.push(variable.name.clone());
+                    }
+                    let plan = Plan::CreateTableAs {
+                        schema_name,
+                        table_name,
+                        column_names,
+                        if_not_exists: *if_not_exists,
+                        input: Box::new(input),
+                    };
+
+                    return Ok((plan, Scope::default()));
+                }
+
+                // Sequences generating the serial columns
+                let mut sequences = vec![];
+                let mut columns = column_defs
                     .iter()
                     .map(|col| {
-                        let name = col.name.to_string();
-                        let data_type = Type::try_from(&col.data_type)?;
-                        let null = col
-                            .options
-                            .iter()
-                            .any(|opt| matches!(opt.option, sqlparser::ast::ColumnOption::Null));
-
-                        Ok(ColumnDefinition {
-                            name,
-                            data_type,
-                            null,
-                        })
+                        Self::bind_column_def(
+                            &mut bind_context,
+                            &schema_name,
+                            &table_name,
+                            col,
+                            &mut sequences,
+                        )
                     })
                     .collect::<Result<Vec<_>, _>>()?;
 
-                let table_def = TableDefinition {
+                let primary_key =
+                    Self::bind_primary_key(&table_name, &columns, column_defs, constraints)?;
+                // Columns of primary key are implicitly NOT NULL
+                for index in primary_key
+                    .iter()
+                    .flat_map(|index_def| index_def.columns.iter())
+                {
+                    columns[*index].null = false;
+                }
+                let mut table_def = TableDefinition {
                     name: table_name,
                     columns,
+                    primary_key: primary_key.as_ref().map(|index_def| index_def.name.clone()),
+                    indexes: primary_key.into_iter().collect(),
+                    foreign_keys: vec![],
+                    statistics: None,
+                    version: 0,
+                };
+                table_def.foreign_keys =
+                    self.bind_foreign_keys(&schema_name, &table_def, column_defs, constraints)?;
+                let plan = Plan::DDL(DDLJob::CreateTable(
+                    schema_name,
+                    table_def,
+                    sequences,
+                    *if_not_exists,
+                ));
+
+                Ok((plan, Scope::default()))
+            }
+
+            Statement::CreateIndex {
+                name,
+                table_name,
+                using,
+                columns,
+                unique,
+                if_not_exists,
+                include,
+                nulls_distinct,
+                predicate,
+                ..
+            } => {
+                let not_supported = |feature: String| {
+                    SQLError::new(ErrorKind::PlannerError, feature)
+                        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+                };
+                let Some(name) = name else {
+                    return Err(not_supported("index without name is not supported".into()));
+                };
+                if !include.is_empty() {
+                    return Err(not_supported("INCLUDE of index is not supported".into()));
+                }
+                if *nulls_distinct == Some(false) {
+                    return Err(not_supported("NULLS NOT DISTINCT is not supported".into()));
+                }
+                if predicate.is_some() {
+                    return Err(not_supported("partial index is not supported".into()));
+                }
+                if let Some(using) = using {
+                    if !using.value.eq_ignore_ascii_case("btree") {
+                        return Err(not_supported(format!(
+                            "index access method \"{}\" is not supported",
+                            using.value
+                        )));
+                    }
+                }
+
+                let (schema_name, table_name) = Self::qualify_table_name(self.ctx, &table_name.0);
+                let table_def = self
+                    .ctx
+                    .catalog()
+                    .find_table_by_name(&self.ctx.current_database, &schema_name, &table_name)?
+                    .ok_or_else(|| {
+                        SQLError::new(ErrorKind::CatalogError, "table not found")
+                            .with_code(sqlstate::UNDEFINED_TABLE)
+                    })?;
+
+                let columns = columns
+                    .iter()
+                    .map(|column| {
+                        // Only ascending order with the default NULLS LAST is supported
+                        if column.asc == Some(false) || column.nulls_first == Some(true) {
+                            return Err(not_supported(format!(
+                                "index column ordering \"{}\" is not supported",
+                                column
+                            )));
+                        }
+                        let Expr::Identifier(ident) = &column.expr else {
+                            return Err(not_supported(format!(
+                                "index on expression \"{}\" is not supported",
+                                column.expr
+                            )));
+                        };
+                        table_def
+                            .columns
+                            .iter()
+                            .position(|column| column.name == ident.value)
+                            .ok_or_else(|| {
+                                SQLError::new(
+                                    ErrorKind::PlannerError,
+                                    format!("column \"{}\" does not exist", ident.value),
+                                )
+                                .with_code(sqlstate::UNDEFINED_COLUMN)
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // The index is always created in the schema of its table
+                let index_def = IndexDefinition {
+                    name: name.0.last().unwrap().to_string(),
+                    columns,
+                    unique: *unique,
+                };
+                let plan = Plan::DDL(DDLJob::CreateIndex(
+                    schema_name,
+                    table_name,
+                    index_def,
+                    *if_not_exists,
+                ));
+
+                Ok((plan, Scope::default()))
+            }
+
+            Statement::CreateSequence {
+                temporary,
+                if_not_exists,
+                name,
+                data_type,
+                sequence_options,
+                owned_by,
+            } => {
+                if *temporary {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "CREATE TEMPORARY SEQUENCE is not supported",
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+                }
+
+                let (schema_name, sequence_name) = Self::qualify_table_name(self.ctx, &name.0);
+                let sequence_def = self.bind_sequence(
+                    &schema_name,
+                    sequence_name,
+                    data_type.as_ref(),
+                    sequence_options,
+                    owned_by.as_ref(),
+                )?;
+                let plan = Plan::DDL(DDLJob::CreateSequence(
+                    schema_name,
+                    sequence_def,
+                    *if_not_exists,
+                ));
+
+                Ok((plan, Scope::default()))
+            }
+
+            Statement::CreateFunction {
+                or_replace,
+                temporary,
+                name,
+                args,
+                return_type,
+                params,
+            } => {
+                if *temporary {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "CREATE TEMPORARY FUNCTION is not supported",
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+                }
+
+                let plan = self.bind_create_function(
+                    name,
+                    args.as_deref().unwrap_or_default(),
+                    return_type.as_ref(),
+                    params,
+                    *or_replace,
+                )?;
+
+                Ok((plan, Scope::default()))
+            }
+
+            Statement::DropFunction {
+                if_exists,
+                func_desc,
+                ..
+            } => {
+                let mut names = vec![];
+                for desc in func_desc.iter() {
+                    let (schema_name, function_name) =
+                        qualify_function_name(&self.ctx.current_schema, &desc.name.0).ok_or_else(
+                            || SQLError::new(ErrorKind::PlannerError, "invalid function name"),
+                        )?;
+                    if self
+                        .ctx
+                        .catalog()
+                        .find_function_by_name(
+                            &self.ctx.current_database,
+                            &schema_name,
+                            &function_name,
+                        )
+                        .is_none()
+                    {
+                        if *if_exists {
+                            continue;
+                        }
+                        return Err(SQLError::new(
+                            ErrorKind::CatalogError,
+                            format!("function {} does not exist", desc.name),
+                        )
+                        .with_code(sqlstate::UNDEFINED_FUNCTION));
+                    }
+                    names.push((schema_name, function_name));
+                }
+
+                Ok((Plan::DDL(DDLJob::DropFunctions(names)), Scope::default()))
+            }
+
+            Statement::AlterTable {
+                name,
+                if_exists,
+                operations,
+                ..
+            } => {
+                let not_supported = |feature: &str| {
+                    SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!("{} is not supported", feature),
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+                };
+                // Skipped changes plan nothing, like DROP INDEX IF EXISTS
+                let skipped = || Ok((Plan::DDL(DDLJob::DropIndexes(vec![])), Scope::default()));
+                let [operation] = operations.as_slice() else {
+                    return Err(not_supported("ALTER TABLE with multiple actions"));
+                };
+
+                let (schema_name, table_name) = Self::qualify_table_name(self.ctx, &name.0);
+                let Some(table_def) = self.ctx.catalog().find_table_by_name(
+                    &self.ctx.current_database,
+                    &schema_name,
+                    &table_name,
+                )?
+                else {
+                    if *if_exists {
+                        return skipped();
+                    }
+                    return Err(SQLError::new(ErrorKind::CatalogError, "table not found")
+                        .with_code(sqlstate::UNDEFINED_TABLE));
+                };
+                let find_column = |name: &str| {
+                    table_def
+                        .columns
+                        .iter()
+                        .position(|column| column.name == name)
+                };
+                let undefined_column = |name: &str| {
+                    SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!(
+                            "column \"{}\" of relation \"{}\" does not exist",
+                            name, table_name
+                        ),
+                    )
+                    .with_code(sqlstate::UNDEFINED_COLUMN)
+                };
+                let duplicate_column = |name: &str| {
+                    SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!(
+                            "column \"{}\" of relation \"{}\" already exists",
+                            name, table_name
+                        ),
+                    )
+                    .with_code(sqlstate::DUPLICATE_COLUMN)
+                };
+
+                let job = match operation {
+                    AlterTableOperation::AddColumn {
+                        if_not_exists,
+                        column_def,
+                        ..
+                    } => {
+                        // Constraints can only be declared with the table
+                        if column_def.options.iter().any(|opt| {
+                            matches!(
+                                opt.option,
+                                ColumnOption::Unique { .. }
+                                    | ColumnOption::ForeignKey { .. }
+                                    | ColumnOption::Check(_)
+                            )
+                        }) {
+                            return Err(not_supported("ADD COLUMN with constraints"));
+                        }
+                        if *if_not_exists && find_column(&column_def.name.value).is_some() {
+                            return skipped();
+                        }
+
+                        let mut sequences = vec![];
+                        let column = Self::bind_column_def(
+                            &mut bind_context,
+                            &schema_name,
+                            &table_name,
+                            column_def,
+                            &mut sequences,
+                        )?;
+                        if find_column(&column.name).is_some() {
+                            return Err(duplicate_column(&column.name));
+                        }
+                        // The serial column is filled by its new sequence instead
+                        let default = match &column.default {
+                            Some(default) if sequences.is_empty() => Some(bind_scalar(
+                                &mut bind_context,
+                                &Scope::default(),
+                                &parse_expr(default)?,
+                            )?),
+                            _ => None,
+                        };
+                        AlterTableJob::AddColumn(column, default, sequences.pop())
+                    }
+                    AlterTableOperation::DropColumn {
+                        column_name,
+                        if_exists,
+                        cascade,
+                    } => {
+                        if *cascade {
+                            return Err(not_supported("DROP COLUMN CASCADE"));
+                        }
+
+                        let index = match find_column(&column_name.value) {
+                            Some(index) => index,
+                            None if *if_exists => return skipped(),
+                            None => return Err(undefined_column(&column_name.value)),
+                        };
+                        AlterTableJob::DropColumn(index)
+                    }
+                    AlterTableOperation::RenameColumn {
+                        old_column_name,
+                        new_column_name,
+                    } => {
+                        let index = find_column(&old_column_name.value)
+                            .ok_or_else(|| undefined_column(&old_column_name.value))?;
+                        if find_column(&new_column_name.value).is_some() {
+                            return Err(duplicate_column(&new_column_name.value));
+                        }
+                        AlterTableJob::RenameColumn(index, new_column_name.value.clone())
+                    }
+                    AlterTableOperation::AlterColumn {
+                        column_name,
+                        op: AlterColumnOperation::SetDataType { data_type, using },
+                    } => {
+                        let index = find_column(&column_name.value)
+                            .ok_or_else(|| undefined_column(&column_name.value))?;
+                        // The expression is bound over the columns of table, like `SET` of `UPDATE`
+                        let using = match using {
+                            Some(expr) => {
+                                let table = TableFactor::Table {
+                                    name: name.clone(),
+                                    alias: None,
+                                    args: None,
+                                    with_hints: vec![],
+                                    version: None,
+                                    partitions: vec![],
+                                };
+                                let (_, scope) = self.bind_table_ref(&mut bind_context, &table)?;
+                                Some(bind_scalar(&mut bind_context, &scope, expr)?)
+                            }
+                            None => None,
+                        };
+                        AlterTableJob::AlterColumnType(
+                            index,
+                            Type::try_from(data_type)?,
+                            IntWidth::from_data_type(data_type),
+                            using,
+                        )
+                    }
+                    operation => return Err(not_supported(&format!("ALTER TABLE {}", operation))),
                 };
-                let plan = Plan::DDL(DDLJob::CreateTable(schema_name, table_def));
+                let plan = Plan::DDL(DDLJob::AlterTable(schema_name, table_name, job));
 
                 Ok((plan, Scope::default()))
             }
@@ -109,16 +634,17 @@ impl<'a> Binder<'a> {
                 Ok((plan, Scope::default()))
             }
 
+            Statement::Analyze { table_name, .. } => {
+                let names = self.bind_maintained_tables(table_name)?;
+                Ok((Plan::DDL(DDLJob::Analyze(names)), Scope::default()))
+            }
+
             Statement::Drop {
                 object_type,
                 if_exists,
                 names,
                 ..
             } => {
-                if *if_exists {
-                    unimplemented!();
-                }
-
                 if names.is_empty() || names.len() > 2 {
                     return Err(SQLError::new(ErrorKind::PlannerError, "invalid table name"));
                 }
@@ -130,7 +656,7 @@ impl<'a> Binder<'a> {
                             .map(|idents| Self::qualify_table_name(self.ctx, &idents.0))
                             .collect::<Vec<_>>();
 
-                        Plan::DDL(DDLJob::DropTables(names))
+                        Plan::DDL(DDLJob::DropTables(names, *if_exists))
                     }
                     sqlparser::ast::ObjectType::Schema => {
                         let names = names
@@ -138,28 +664,166 @@ impl<'a> Binder<'a> {
                             .map(|idents| idents.0[0].to_string())
                             .collect::<Vec<_>>();
 
-                        Plan::DDL(DDLJob::DropSchemas(names))
+                        Plan::DDL(DDLJob::DropSchemas(names, *if_exists))
+                    }
+                    sqlparser::ast::ObjectType::Index => {
+                        let mut indexes = vec![];
+                        for idents in names.iter() {
+                            let (schema_name, index_name) =
+                                Self::qualify_table_name(self.ctx, &idents.0);
+                            let index = match self.ctx.catalog().find_index_by_name(
+                                &self.ctx.current_database,
+                                &schema_name,
+                                &index_name,
+                            ) {
+                                Ok(None) | Err(_) if *if_exists => continue,
+                                index => index?,
+                            };
+                            let (table_name, _) = index.ok_or_else(|| {
+                                SQLError::new(
+                                    ErrorKind::CatalogError,
+                                    format!("index \"{}\" does not exist", index_name),
+                                )
+                                .with_code(sqlstate::UNDEFINED_OBJECT)
+                            })?;
+                            indexes.push((schema_name, table_name, index_name));
+                        }
+
+                        Plan::DDL(DDLJob::DropIndexes(indexes))
+                    }
+                    sqlparser::ast::ObjectType::Sequence => {
+                        let names = names
+                            .iter()
+                            .map(|idents| Self::qualify_table_name(self.ctx, &idents.0))
+                            .collect::<Vec<_>>();
+
+                        Plan::DDL(DDLJob::DropSequences(names, *if_exists))
+                    }
+                    object_type => {
+                        return Err(SQLError::new(
+                            ErrorKind::PlannerError,
+                            format!("DROP {} is not supported", object_type),
+                        )
+                        .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
                     }
-                    _ => unimplemented!(),
                 };
 
                 Ok((plan, Scope::default()))
             }
 
-            Statement::Query(query) => self.bind_query(&mut bind_context, query),
+            Statement::Query(query) => {
+                let (plan, scope) = self.bind_query(&mut bind_context, query)?;
+                let plan = Optimizer::default().optimize(
+                    plan,
+                    &self.ctx.catalog().database(&self.ctx.current_database),
+                )?;
+                Ok((plan, scope))
+            }
 
-            Statement::Insert {
-                table_name, source, ..
+            Statement::Copy {
+                source: CopySource::Query(_),
+                ..
+            } => Err(
+                SQLError::new(ErrorKind::PlannerError, "COPY of query is not supported")
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED),
+            ),
+
+            Statement::Copy {
+                source:
+                    CopySource::Table {
+                        table_name,
+                        columns,
+                    },
+                to,
+                target,
+                options,
+                legacy_options,
+                ..
             } => Ok((
-                self.bind_insert(&mut bind_context, &table_name.0, source.as_ref())?,
+                self.bind_copy(
+                    &mut bind_context,
+                    &table_name.0,
+                    columns,
+                    *to,
+                    target,
+                    bind_copy_format(options, legacy_options)?,
+                )?,
                 Scope::default(),
             )),
 
-            Statement::Explain { statement, .. } => {
-                let (plan, _) = self.bind_statement(statement)?;
-                let plan = Plan::Explain(plan.to_string());
+            Statement::Insert {
+                table_name,
+                columns,
+                source,
+                returning,
+                ..
+            } => self.bind_insert(
+                &mut bind_context,
+                &table_name.0,
+                columns,
+                source.as_ref(),
+                returning.as_deref(),
+            ),
+
+            Statement::Update {
+                table,
+                assignments,
+                from,
+                selection,
+                returning,
+            } => {
+                if from.is_some() {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "UPDATE ... FROM is not supported",
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+                }
 
-                Ok((plan, Scope::default()))
+                self.bind_update(
+                    &mut bind_context,
+                    table,
+                    assignments,
+                    selection.as_ref(),
+                    returning.as_deref(),
+                )
+            }
+
+            Statement::Delete {
+                from,
+                using,
+                selection,
+                returning,
+                ..
+            } => {
+                if using.is_some() {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "DELETE ... USING is not supported",
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+                }
+                let [TableWithJoins { relation, joins }] = from.as_slice() else {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "DELETE of multiple tables is not supported",
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+                };
+                if !joins.is_empty() {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "DELETE of joined tables is not supported",
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+                }
+
+                self.bind_delete(
+                    &mut bind_context,
+                    relation,
+                    selection.as_ref(),
+                    returning.as_deref(),
+                )
             }
 
             Statement::Use { db_name } => {
@@ -169,8 +833,150 @@ impl<'a> Binder<'a> {
                 Ok((plan, Scope::default()))
             }
 
-            _ => unimplemented!(),
+            Statement::SetVariable {
+                variable, value, ..
+            } => self.bind_set_variable(&variable.to_string(), value),
+            Statement::SetTimeZone { value, .. } => {
+                self.bind_set_variable("TimeZone", std::slice::from_ref(value))
+            }
+            Statement::ShowVariable { variable } => self.bind_show_variable(variable),
+
+            stmt => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("statement is not supported: {}", stmt),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+        }
+    }
+
+    /// Bind `SET name = value`, where `search_path` changes the current
+    /// schema to the first schema in the list.
+    pub fn bind_set_variable(
+        &mut self,
+        name: &str,
+        values: &[Expr],
+    ) -> Result<(Plan, Scope), SQLError> {
+        let value = match values {
+            [value] if is_default_keyword(value) => None,
+            values => Some(
+                values
+                    .iter()
+                    .map(setting_value)
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+        };
+
+        let plan = if name.eq_ignore_ascii_case("search_path") {
+            let schema_name = value
+                .and_then(|schemas| schemas.into_iter().next())
+                .unwrap_or_else(|| "default".to_string());
+            Plan::Use(schema_name)
+        } else {
+            check_settable(&name.to_lowercase())?;
+            Plan::SetVariable(name.to_string(), value.map(|values| values.join(", ")))
+        };
+
+        Ok((plan, Scope::default()))
+    }
+
+    /// Bind `SHOW name` to the values of parameters, `SHOW ALL` shows all the
+    /// parameters with their names.
+    pub fn bind_show_variable(&mut self, variable: &[Ident]) -> Result<(Plan, Scope), SQLError> {
+        // The parser splits the name by whitespaces and dots, where only the
+        // custom parameters have dots.
+        let words = variable
+            .iter()
+            .map(|ident| ident.value.to_lowercase())
+            .collect::<Vec<_>>();
+        let name = match words.join(" ").as_str() {
+            "transaction isolation level" => "transaction_isolation".to_string(),
+            "time zone" => "timezone".to_string(),
+            _ => words.join("."),
+        };
+        let name = name.as_str();
+
+        let column = |name: &str| Variable {
+            prefix: None,
+            name: name.to_string(),
+            expr: None,
+        };
+        let literal = |value: String| ScalarExpr::Literal(Datum::String(value.into()));
+
+        if name == "all" {
+            let mut settings = self.ctx.settings.all();
+            settings.push(("search_path".to_string(), self.ctx.current_schema.clone()));
+            settings.sort_by_key(|(name, _)| name.to_lowercase());
+            let rows = settings
+                .into_iter()
+                .map(|(name, value)| vec![literal(name), literal(value)])
+                .collect();
+            let scope = Scope {
+                variables: vec![column("name"), column("setting")],
+                ..Default::default()
+            };
+
+            return Ok((Plan::Values { rows }, scope));
         }
+
+        // The sessions as in `pg_stat_activity`
+        if name == "processlist" {
+            let rows = self
+                .ctx
+                .activity
+                .list()
+                .into_iter()
+                .map(|activity| {
+                    vec![
+                        ScalarExpr::Literal(Datum::Int(activity.pid)),
+                        literal(activity.user.unwrap_or_else(|| OWNER_NAME.to_string())),
+                        literal(activity.application_name),
+                        literal(activity.state.to_string()),
+                        activity
+                            .query_start
+                            .map_or(ScalarExpr::Literal(Datum::Null), |time| {
+                                literal(format_timestamp(time))
+                            }),
+                        literal(activity.query),
+                    ]
+                })
+                .collect();
+            let scope = Scope {
+                variables: [
+                    "pid",
+                    "usename",
+                    "application_name",
+                    "state",
+                    "query_start",
+                    "query",
+                ]
+                .into_iter()
+                .map(column)
+                .collect(),
+                ..Default::default()
+            };
+
+            return Ok((Plan::Values { rows }, scope));
+        }
+
+        let value = if name == "search_path" {
+            self.ctx.current_schema.clone()
+        } else {
+            self.ctx
+                .settings
+                .get(name)
+                .ok_or_else(|| unrecognized_parameter(name))?
+        };
+        let scope = Scope {
+            variables: vec![column(name)],
+            ..Default::default()
+        };
+
+        Ok((
+            Plan::Values {
+                rows: vec![vec![literal(value)]],
+            },
+            scope,
+        ))
     }
 
     pub fn bind_query(
@@ -178,36 +984,324 @@ impl<'a> Binder<'a> {
         ctx: &mut BindContext,
         query: &Query,
     ) -> Result<(Plan, Scope), SQLError> {
-        match query.body.as_ref() {
+        let (plan, scope) = match query.body.as_ref() {
             SetExpr::Select(select_stmt) => {
-                let plan = self.bind_select_statement(ctx, select_stmt)?;
-                Ok(plan)
+                self.bind_select_statement(ctx, select_stmt, &query.order_by)?
             }
-            _ => unimplemented!(),
-        }
+            body => {
+                let (plan, scope) = self.bind_set_expr(ctx, body)?;
+                let select_list = scope
+                    .variables
+                    .iter()
+                    .map(|variable| FlattenedSelectItem {
+                        expr: Expr::Identifier(Ident::new(variable.name.clone())),
+                        alias: variable.name.clone(),
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut order_by = vec![];
+                for order in query.order_by.iter() {
+                    let expr =
+                        match resolve_output_reference("ORDER BY", &order.expr, &select_list)? {
+                            Some(position) => ScalarExpr::Column(Column { index: position }),
+                            None => bind_scalar(ctx, &scope, &order.expr)?,
+                        };
+                    order_by.push(bind_order_by(order, expr));
+                }
+
+                (sort(plan, order_by), scope)
+            }
+        };
+
+        let plan = self.bind_limit(ctx, plan, &scope, query)?;
+        Ok((plan, scope))
     }
 
-    pub fn bind_select_statement(
+    /// Bind the body of query without `ORDER BY`, which is applied to the
+    /// output columns.
+    fn bind_set_expr(
         &mut self,
         ctx: &mut BindContext,
-        select_stmt: &Select,
+        body: &SetExpr,
     ) -> Result<(Plan, Scope), SQLError> {
-        if select_stmt.from.is_empty() {
-            // Dual table scan if no `FROM` clause is specified.
-            return Ok((
-                Plan::Get {
-                    schema_name: "system".to_string(),
-                    table_name: "dual".to_string(),
-                },
-                Scope::default(),
-            ));
-        }
+        match body {
+            SetExpr::Select(select_stmt) => self.bind_select_statement(ctx, select_stmt, &[]),
+            SetExpr::Values(values) => self.bind_values(ctx, values),
+            SetExpr::Query(query) => self.bind_query(ctx, query),
+            SetExpr::SetOperation {
+                op: SetOperator::Union,
+                set_quantifier,
+                left,
+                right,
+            } => {
+                let (left, scope) = self.bind_set_expr(ctx, left)?;
+                let (right, right_scope) = self.bind_set_expr(ctx, right)?;
+                if scope.variables.len() != right_scope.variables.len() {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "each UNION query must have the same number of columns",
+                    )
+                    .with_code(sqlstate::SYNTAX_ERROR));
+                }
 
-        let table_factors = select_stmt
-            .from
+                // The output columns are named by the left side
+                let scope = Scope {
+                    variables: scope
+                        .variables
+                        .into_iter()
+                        .map(|variable| Variable {
+                            prefix: None,
+                            name: variable.name,
+                            expr: None,
+                        })
+                        .collect(),
+                    ..Default::default()
+                };
+                let plan = Plan::UnionAll {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                };
+                let plan = match set_quantifier {
+                    SetQuantifier::All => plan,
+                    _ => Plan::Distinct {
+                        input: Box::new(plan),
+                    },
+                };
+                Ok((plan, scope))
+            }
+            SetExpr::SetOperation { op, .. } => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("{} is not supported", op),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+            body => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("query is not supported: {}", body),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+        }
+    }
+
+    /// Bind `LIMIT` and `OFFSET`, or their standard spelling `OFFSET n ROWS
+    /// FETCH FIRST m ROWS ONLY`, on top of the plan. `FETCH FIRST ROW ONLY`
+    /// without a count fetches one row.
+    fn bind_limit(
+        &mut self,
+        ctx: &mut BindContext,
+        plan: Plan,
+        scope: &Scope,
+        query: &Query,
+    ) -> Result<Plan, SQLError> {
+        let fetch = match &query.fetch {
+            Some(_) if query.limit.is_some() => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "multiple LIMIT clauses not allowed",
+                )
+                .with_code(sqlstate::SYNTAX_ERROR))
+            }
+            Some(fetch) if fetch.percent => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "FETCH FIRST ... PERCENT is not supported",
+                )
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+            }
+            Some(fetch) if fetch.with_ties => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "FETCH FIRST ... WITH TIES is not supported",
+                )
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+            }
+            Some(fetch) => Some(
+                fetch
+                    .quantity
+                    .clone()
+                    .unwrap_or_else(|| Expr::Value(Value::Number("1".to_string(), false))),
+            ),
+            None => None,
+        };
+
+        // The counts are evaluated once, so they cannot reference the columns
+        let mut bind_count = |clause: &str, expr: &Expr| {
+            let scalar = bind_scalar(ctx, scope, expr)?;
+            let mut columns = Default::default();
+            collect_columns(&scalar, &mut columns);
+            if !columns.is_empty() || scalar.has_outer_column() {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!("argument of {} must not contain variables", clause),
+                )
+                .with_code(sqlstate::INVALID_COLUMN_REFERENCE));
+            }
+            Ok(scalar)
+        };
+        let limit = query
+            .limit
+            .as_ref()
+            .or(fetch.as_ref())
+            .map(|expr| bind_count("LIMIT", expr))
+            .transpose()?;
+        let offset = query
+            .offset
+            .as_ref()
+            .map(|offset| bind_count("OFFSET", &offset.value))
+            .transpose()?;
+
+        if limit.is_none() && offset.is_none() {
+            return Ok(plan);
+        }
+        Ok(Plan::Limit {
+            limit,
+            offset,
+            input: Box::new(plan),
+        })
+    }
+
+    /// Bind the `VALUES` list, the columns are named `column1`, `column2`, etc.
+    pub fn bind_values(
+        &mut self,
+        ctx: &mut BindContext,
+        values: &Values,
+    ) -> Result<(Plan, Scope), SQLError> {
+        let width = values.rows.first().map_or(0, Vec::len);
+        if values.rows.iter().any(|row| row.len() != width) {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "VALUES lists must all be the same length",
+            )
+            .with_code(sqlstate::SYNTAX_ERROR));
+        }
+
+        let scope = Scope::default();
+        let rows = values
+            .rows
             .iter()
-            .map(|table| self.bind_table_with_joins(ctx, table))
-            .collect::<Result<Vec<_>, _>>()?;
+            .map(|row| {
+                row.iter()
+                    .map(|expr| bind_scalar(ctx, &scope, expr))
+                    .collect::<Result<Vec<_>, SQLError>>()
+            })
+            .collect::<Result<Vec<_>, SQLError>>()?;
+
+        let scope = Scope {
+            variables: (1..=width)
+                .map(|i| Variable {
+                    prefix: None,
+                    name: format!("column{}", i),
+                    expr: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        Ok((Plan::Values { rows }, scope))
+    }
+
+    /// Bind the function call in `FROM` clause, only `unnest` is supported.
+    /// The arguments cannot reference the columns of other tables.
+    pub fn bind_table_function(
+        &mut self,
+        ctx: &mut BindContext,
+        name: &ObjectName,
+        args: &[FunctionArg],
+        alias: Option<&TableAlias>,
+    ) -> Result<(Plan, Scope), SQLError> {
+        let func_name = name.to_string().to_lowercase();
+        if func_name != "unnest" || args.is_empty() {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!(
+                    "cannot find overload of function with given types: {}",
+                    func_name
+                ),
+            )
+            .with_code(sqlstate::UNDEFINED_FUNCTION));
+        }
+
+        let scope = Scope::default();
+        let arrays = args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => bind_scalar(ctx, &scope, expr),
+                _ => Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!(
+                        "argument {} of function {} is not supported",
+                        arg, func_name
+                    ),
+                )
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+            })
+            .collect::<Result<Vec<_>, SQLError>>()?;
+
+        // The columns are named after the function, or the alias if there is
+        // only one column.
+        let mut scope = Scope {
+            variables: arrays
+                .iter()
+                .map(|_| Variable {
+                    prefix: Some(QualifiedNamePrefix {
+                        schema_name: None,
+                        table_name: func_name.clone(),
+                    }),
+                    name: func_name.clone(),
+                    expr: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        if let Some(alias) = alias {
+            if arrays.len() == 1 {
+                scope.variables[0].name = alias.name.value.clone();
+            }
+            Self::apply_table_alias(&mut scope, alias)?;
+        }
+
+        Ok((Plan::Unnest { arrays }, scope))
+    }
+
+    pub fn bind_select_statement(
+        &mut self,
+        ctx: &mut BindContext,
+        select_stmt: &Select,
+        order_by: &[OrderByExpr],
+    ) -> Result<(Plan, Scope), SQLError> {
+        // `SELECT INTO` of statements is rewritten to `CREATE TABLE AS`
+        if select_stmt.into.is_some() {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "SELECT ... INTO is not allowed here",
+            )
+            .with_code(sqlstate::SYNTAX_ERROR));
+        }
+        let not_supported = |feature: &str| {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                format!("{} is not supported", feature),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+        };
+        let distinct = match &select_stmt.distinct {
+            None => false,
+            Some(Distinct::Distinct) => true,
+            Some(Distinct::On(_)) => return Err(not_supported("SELECT DISTINCT ON")),
+        };
+        let GroupByExpr::Expressions(group_by) = &select_stmt.group_by else {
+            return Err(not_supported("GROUP BY ALL"));
+        };
+
+        let table_factors = if select_stmt.from.is_empty() {
+            // A single row without any column if no `FROM` clause is specified
+            vec![(Plan::Values { rows: vec![vec![]] }, Scope::default())]
+        } else {
+            select_stmt
+                .from
+                .iter()
+                .map(|table| self.bind_table_with_joins(ctx, table))
+                .collect::<Result<Vec<_>, _>>()?
+        };
 
         // Combine the joins in left-deep fashion.
         let (mut plan, from_scope) = table_factors
@@ -215,6 +1309,7 @@ impl<'a> Binder<'a> {
             .reduce(|prev, next| {
                 (
                     Plan::Join {
+                        keys: vec![],
                         left: Box::new(prev.0),
                         right: Box::new(next.0),
                     },
@@ -225,19 +1320,24 @@ impl<'a> Binder<'a> {
 
         // Handle `WHERE` clause.
         if let Some(selection) = &select_stmt.selection {
-            let scalar = bind_scalar(ctx, &from_scope, selection)?;
-            plan = Plan::Filter {
-                input: Box::new(plan),
-                predicate: scalar,
-            };
+            plan = self.bind_where(ctx, plan, &from_scope, selection)?;
         }
 
         // Expand the select list, the wildcard is expanded to columns.
-        let flattened_select_list = self.expand_select_list(&from_scope, &select_stmt.projection);
+        let flattened_select_list =
+            self.expand_select_list(&from_scope, &select_stmt.projection)?;
+
+        // The aliases of select list can be referenced by `HAVING`
+        let having = select_stmt
+            .having
+            .as_ref()
+            .map(|having| replace_select_aliases(having, &from_scope, &flattened_select_list))
+            .transpose()?;
 
         // Collect aggregate functions
+        let aggregate_functions = self.ctx.catalog().aggregate_functions.clone();
         let aggregate_exprs = {
-            let mut aggregate_visitor = AggregateFunctionVisitor::new();
+            let mut aggregate_visitor = AggregateFunctionVisitor::new(aggregate_functions.clone());
             // Collect aggregate functions from `SELECT` clause.
             for item in select_stmt.projection.iter() {
                 match item {
@@ -250,25 +1350,46 @@ impl<'a> Binder<'a> {
                     _ => {}
                 }
             }
-            if let Some(having) = &select_stmt.having {
+            if let Some(having) = &having {
                 // Collect aggregate functions from `HAVING` clause.
                 having.visit(&mut aggregate_visitor);
             }
+            // Collect aggregate functions from `ORDER BY` clause.
+            for order in order_by.iter() {
+                order.expr.visit(&mut aggregate_visitor);
+            }
 
             if let Some(err) = aggregate_visitor.error {
                 return Err(err);
             }
 
-            aggregate_visitor.aggregates
+            aggregate_visitor
         };
+        let (aggregate_exprs, grouping_exprs) =
+            (aggregate_exprs.aggregates, aggregate_exprs.groupings);
 
         // Handle `GROUP BY` clause and `HAVING` clause.
-        let group_scope = if !select_stmt.group_by.is_empty() {
+        let mut group_scope = if !group_by.is_empty() {
             // First, we will add the group by keys to the scope.
             // And from now on, the from scope will no longer be valid.
-            let mut group_scope = Scope::default();
+            let mut group_scope = Scope {
+                ungrouped: Some(Box::new(from_scope.clone())),
+                ..Default::default()
+            };
             let mut group_keys = vec![];
-            for expr in &select_stmt.group_by {
+            let group_by = group_by
+                .iter()
+                .map(|expr| {
+                    resolve_group_by_expr(
+                        expr,
+                        &from_scope,
+                        &flattened_select_list,
+                        &aggregate_functions,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let (group_exprs, grouping_sets) = expand_grouping_sets(&group_by);
+            for expr in &group_exprs {
                 let scalar = bind_scalar(ctx, &from_scope, expr)?;
 
                 if let ScalarExpr::Column(Column { index }) = &scalar {
@@ -286,6 +1407,14 @@ impl<'a> Binder<'a> {
 
                 group_keys.push(scalar);
             }
+            if !grouping_sets.is_empty() {
+                // The index of grouping set
+                group_scope.variables.push(Variable {
+                    prefix: None,
+                    name: "?column?".to_string(),
+                    expr: None,
+                });
+            }
 
             // Bind the aggregate functions. The original aggregate expression will be
             // bound with variable, so the aggregate function can be replaced by the
@@ -302,14 +1431,42 @@ impl<'a> Binder<'a> {
                 })
                 .collect::<Result<Vec<_>, _>>()?;
 
-            plan = self.bind_aggregate(plan, group_keys, aggregates)?;
+            let groupings = grouping_exprs
+                .iter()
+                .map(|func| {
+                    bind_grouping_function(ctx, &from_scope, func, &group_keys, &grouping_sets)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            plan = self.bind_aggregate(plan, group_keys, grouping_sets, aggregates)?;
+
+            // The results of `grouping()` are appended to the aggregate
+            if !groupings.is_empty() {
+                group_scope
+                    .variables
+                    .extend(grouping_exprs.into_iter().map(|func| Variable {
+                        prefix: None,
+                        name: "?column?".to_string(),
+                        expr: Some(Expr::Function(func)),
+                    }));
+                plan = Plan::Map {
+                    scalars: groupings,
+                    input: Box::new(plan),
+                };
+            }
 
             group_scope
         } else {
+            // None of the arguments can be a group key
+            if let Some(func) = grouping_exprs.first() {
+                bind_grouping_function(ctx, &from_scope, func, &[], &[])?;
+            }
+
             let mut group_scope = from_scope.clone();
             if !aggregate_exprs.is_empty() {
                 // This is a scalar aggregate
                 group_scope.variables = vec![];
+                group_scope.ungrouped = Some(Box::new(from_scope.clone()));
                 let aggregates = aggregate_exprs
                     .iter()
                     .map(|expr| {
@@ -322,14 +1479,16 @@ impl<'a> Binder<'a> {
                     })
                     .collect::<Result<Vec<_>, _>>()?;
 
-                plan = self.bind_aggregate(plan, vec![], aggregates)?;
+                plan = self.bind_aggregate(plan, vec![], vec![], aggregates)?;
             }
 
             group_scope
         };
 
         // Handle `HAVING` clause.
-        if let Some(having) = &select_stmt.having {
+        if let Some(having) = &having {
+            plan =
+                self.bind_scalar_subqueries(ctx, plan, &mut group_scope, std::iter::once(having))?;
             let scalar = bind_scalar(ctx, &group_scope, having)?;
             plan = Plan::Filter {
                 input: Box::new(plan),
@@ -337,6 +1496,53 @@ impl<'a> Binder<'a> {
             };
         }
 
+        // Handle window functions, the results will be appended to the tuples.
+        let window_functions = {
+            let mut window_visitor = WindowFunctionVisitor::new();
+            for item in select_stmt.projection.iter() {
+                match item {
+                    SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                        expr.visit(&mut window_visitor);
+                    }
+                    _ => {}
+                }
+            }
+
+            window_visitor.window_functions
+        };
+        if !window_functions.is_empty() {
+            let window_exprs = window_functions
+                .iter()
+                .map(|func| bind_window_function(ctx, &group_scope, func))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // The window function calls will be replaced by the variables later.
+            group_scope
+                .variables
+                .extend(window_functions.into_iter().map(|func| Variable {
+                    prefix: None,
+                    name: "?column?".to_string(),
+                    expr: Some(Expr::Function(func)),
+                }));
+
+            plan = Plan::Window {
+                window_exprs,
+                input: Box::new(plan),
+            };
+        }
+
+        // Handle the scalar subqueries of `SELECT` and `ORDER BY` clauses, their
+        // values will be appended to the tuples.
+        plan = self.bind_scalar_subqueries(
+            ctx,
+            plan,
+            &mut group_scope,
+            flattened_select_list
+                .iter()
+                .map(|item| &item.expr)
+                .chain(order_by.iter().map(|order| &order.expr)),
+        )?;
+
         // Handle `SELECT` clause.
         let mut output_projections = vec![];
         let mut scalar_maps = vec![];
@@ -346,10 +1552,49 @@ impl<'a> Binder<'a> {
                 // If the select item is a column, we don't need to evaluate it
                 output_projections.push((index, select_item.alias.clone()));
             } else {
+                // The evaluated scalars are appended after the variables in scope.
+                output_projections.push((
+                    group_scope.variables.len() + scalar_maps.len(),
+                    select_item.alias.clone(),
+                ));
                 scalar_maps.push(scalar);
-                output_projections.push((group_scope.variables.len(), select_item.alias.clone()));
             }
         }
+
+        // Handle `ORDER BY` clause, the keys are evaluated before projection,
+        // except for `DISTINCT`, which can only be sorted on the output columns.
+        let mut sort_keys = vec![];
+        for order in order_by {
+            let position =
+                resolve_output_reference("ORDER BY", &order.expr, &flattened_select_list)?;
+            let mut expr = match position {
+                Some(position) => ScalarExpr::Column(Column {
+                    index: output_projections[position].0,
+                }),
+                None => bind_scalar(ctx, &group_scope, &order.expr)?,
+            };
+            if distinct {
+                let base = group_scope.variables.len();
+                let position = position.or_else(|| {
+                    output_projections.iter().position(|(index, _)| {
+                        expr == ScalarExpr::Column(Column { index: *index })
+                            || index
+                                .checked_sub(base)
+                                .map_or(false, |i| scalar_maps[i] == expr)
+                    })
+                });
+                let Some(position) = position else {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "for SELECT DISTINCT, ORDER BY expressions must appear in select list",
+                    )
+                    .with_code(sqlstate::INVALID_COLUMN_REFERENCE));
+                };
+                expr = ScalarExpr::Column(Column { index: position });
+            }
+            sort_keys.push(bind_order_by(order, expr));
+        }
+
         if !scalar_maps.is_empty() {
             plan = Plan::Map {
                 scalars: scalar_maps,
@@ -357,12 +1602,30 @@ impl<'a> Binder<'a> {
             };
         }
 
+        // `DISTINCT` is sorted after the duplicates are eliminated
+        let (sort_keys, distinct_sort_keys) = if distinct {
+            (vec![], sort_keys)
+        } else {
+            (sort_keys, vec![])
+        };
+        plan = sort(plan, sort_keys);
+
         // Project the result
-        let plan = Plan::Project {
+        let mut plan = Plan::Project {
             input: Box::new(plan),
             projections: output_projections.iter().map(|(index, _)| *index).collect(),
         };
 
+        // Handle `DISTINCT` keyword, duplicated tuples are eliminated after projection.
+        if distinct {
+            plan = sort(
+                Plan::Distinct {
+                    input: Box::new(plan),
+                },
+                distinct_sort_keys,
+            );
+        }
+
         let output_scope = Scope {
             variables: output_projections
                 .iter()
@@ -372,27 +1635,225 @@ impl<'a> Binder<'a> {
                     expr: None,
                 })
                 .collect(),
+            ..Default::default()
         };
 
         Ok((plan, output_scope))
     }
 
+    /// Bind the `WHERE` clause on top of the given plan. The conjunctions are
+    /// split, subquery predicates are planned as semi joins, other predicates
+    /// are planned as filters.
+    pub fn bind_where(
+        &mut self,
+        ctx: &mut BindContext,
+        plan: Plan,
+        scope: &Scope,
+        selection: &Expr,
+    ) -> Result<Plan, SQLError> {
+        match selection {
+            Expr::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => {
+                let left_key = bind_scalar(ctx, scope, expr)?;
+                let (subquery_plan, subquery_scope, conditions) =
+                    self.bind_subquery(ctx, scope, subquery)?;
+                if subquery_scope.variables.len() != 1 {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        "subquery has too many columns",
+                    )
+                    .with_code(sqlstate::SYNTAX_ERROR));
+                }
+
+                Ok(Plan::SemiJoin {
+                    left_key: Some(left_key),
+                    conditions,
+                    anti: *negated,
+                    left: Box::new(plan),
+                    right: Box::new(subquery_plan),
+                })
+            }
+            Expr::Exists { subquery, negated } => {
+                // A positive limit without offset doesn't change whether the
+                // subquery has any row, so it's dropped, and the correlated
+                // subqueries like `EXISTS (SELECT 1 ... LIMIT 1)` can be
+                // decorrelated.
+                let mut subquery = subquery.as_ref().clone();
+                let positive = match (&subquery.limit, &subquery.fetch) {
+                    (Some(limit), None) => is_positive_count(limit),
+                    (None, Some(fetch)) => {
+                        !fetch.percent
+                            && !fetch.with_ties
+                            && fetch.quantity.as_ref().map_or(true, is_positive_count)
+                    }
+                    _ => false,
+                };
+                if positive && subquery.offset.is_none() {
+                    subquery.limit = None;
+                    subquery.fetch = None;
+                }
+                let (subquery_plan, _, conditions) = self.bind_subquery(ctx, scope, &subquery)?;
+
+                Ok(Plan::SemiJoin {
+                    left_key: None,
+                    conditions,
+                    anti: *negated,
+                    left: Box::new(plan),
+                    right: Box::new(subquery_plan),
+                })
+            }
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::And,
+                right,
+            } => {
+                let plan = self.bind_where(ctx, plan, scope, left)?;
+                self.bind_where(ctx, plan, scope, right)
+            }
+            Expr::Nested(expr) => self.bind_where(ctx, plan, scope, expr),
+            _ => {
+                // The values of scalar subqueries are only used by the filter
+                let mut filter_scope = scope.clone();
+                let plan = self.bind_scalar_subqueries(
+                    ctx,
+                    plan,
+                    &mut filter_scope,
+                    std::iter::once(selection),
+                )?;
+                let scalar = bind_scalar(ctx, &filter_scope, selection)?;
+                let plan = Plan::Filter {
+                    input: Box::new(plan),
+                    predicate: scalar,
+                };
+                if filter_scope.variables.len() == scope.variables.len() {
+                    return Ok(plan);
+                }
+                Ok(Plan::Project {
+                    projections: (0..scope.variables.len()).collect(),
+                    input: Box::new(plan),
+                })
+            }
+        }
+    }
+
+    /// Bind a subquery used by a semi join, the columns of `outer_scope` are
+    /// visible to the subquery.
+    ///
+    /// The subquery will be decorrelated, and the correlated predicates are
+    /// returned as the join conditions, which are evaluated with the combined
+    /// tuple of the outer query and the subquery.
+    fn bind_subquery(
+        &mut self,
+        ctx: &mut BindContext,
+        outer_scope: &Scope,
+        subquery: &Query,
+    ) -> Result<(Plan, Scope, Vec<ScalarExpr>), SQLError> {
+        ctx.push(outer_scope.clone());
+        let result = self.bind_query(ctx, subquery);
+        ctx.pop();
+        let (plan, scope) = result?;
+
+        let (plan, predicates) = decorrelate_subquery(plan)?;
+
+        let outer_width = outer_scope.variables.len();
+        let conditions = predicates
+            .iter()
+            .map(|predicate| {
+                predicate.rewrite_columns(
+                    &mut |column| {
+                        ScalarExpr::Column(Column {
+                            index: outer_width + column.index,
+                        })
+                    },
+                    &mut |column| ScalarExpr::Column(column.clone()),
+                )
+            })
+            .collect();
+
+        Ok((plan, scope, conditions))
+    }
+
+    /// Bind the scalar subqueries in the expressions with `Apply`, and add the
+    /// variables of their values to the scope, which replace the subqueries
+    /// while binding the expressions later.
+    fn bind_scalar_subqueries<'e>(
+        &mut self,
+        ctx: &mut BindContext,
+        mut plan: Plan,
+        scope: &mut Scope,
+        exprs: impl IntoIterator<Item = &'e Expr>,
+    ) -> Result<Plan, SQLError> {
+        let mut visitor = ScalarSubqueryVisitor::new();
+        for expr in exprs {
+            expr.visit(&mut visitor);
+        }
+
+        for expr in visitor.subqueries {
+            let (Expr::Subquery(subquery) | Expr::ArraySubquery(subquery)) = &expr else {
+                unreachable!()
+            };
+            ctx.push(scope.clone());
+            let result = self.bind_query(ctx, subquery);
+            ctx.pop();
+            let (mut subquery_plan, subquery_scope) = result?;
+            if subquery_scope.variables.len() != 1 {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "subquery must return only one column",
+                )
+                .with_code(sqlstate::SYNTAX_ERROR));
+            }
+
+            // The referenced columns of the outer query are passed to the
+            // subquery as the parameters of `Apply`
+            let mut parameters = vec![];
+            subquery_plan.rewrite_outer_columns(&mut |column| {
+                let parameter = ScalarExpr::Column(column.clone());
+                let index = parameters
+                    .iter()
+                    .position(|p| *p == parameter)
+                    .unwrap_or_else(|| {
+                        parameters.push(parameter);
+                        parameters.len() - 1
+                    });
+                ScalarExpr::OuterColumn(Column { index })
+            });
+
+            scope.variables.push(Variable {
+                prefix: None,
+                name: "?column?".to_string(),
+                expr: Some(expr.clone()),
+            });
+            plan = Plan::Apply {
+                parameters,
+                array: matches!(expr, Expr::ArraySubquery(_)),
+                subquery: Box::new(subquery_plan),
+                input: Box::new(plan),
+            };
+        }
+
+        Ok(plan)
+    }
+
     fn expand_select_list(
         &mut self,
         from_scope: &Scope,
         select_list: &[SelectItem],
-    ) -> Vec<FlattenedSelectItem> {
-        select_list
-            .iter()
-            .flat_map(|item| match &item {
+    ) -> Result<Vec<FlattenedSelectItem>, SQLError> {
+        let mut items = vec![];
+        for item in select_list.iter() {
+            items.extend(match &item {
                 SelectItem::UnnamedExpr(expr) => vec![FlattenedSelectItem {
                     expr: expr.clone(),
-                    alias: "?column?".to_string(),
+                    alias: Self::output_name(expr),
                 }],
                 SelectItem::ExprWithAlias { expr, alias } => {
                     vec![FlattenedSelectItem {
                         expr: expr.clone(),
-                        alias: alias.to_string(),
+                        alias: alias.value.clone(),
                     }]
                 }
                 SelectItem::Wildcard(_) => from_scope
@@ -423,9 +1884,17 @@ impl<'a> Binder<'a> {
                     })
                     .collect::<Vec<_>>(),
 
-                _ => unimplemented!(),
-            })
-            .collect()
+                SelectItem::QualifiedWildcard(name, _) => {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!("{}.* is not supported", name),
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+                }
+            });
+        }
+
+        Ok(items)
     }
 
     pub fn bind_table_with_joins(
@@ -461,24 +1930,42 @@ impl<'a> Binder<'a> {
         table: &TableFactor,
     ) -> Result<(Plan, Scope), SQLError> {
         match table {
+            TableFactor::Table {
+                name,
+                alias,
+                args: Some(args),
+                ..
+            } => self.bind_table_function(ctx, name, args, alias.as_ref()),
+
             TableFactor::Table { name, alias, .. } => {
                 let ObjectName(names) = name;
                 if names.len() > 2 {
                     return Err(SQLError::new(ErrorKind::PlannerError, "invalid table name"));
                 }
 
-                let schema_name = if names.len() == 2 {
+                let mut schema_name = if names.len() == 2 {
                     names[0].to_string()
                 } else {
                     self.ctx.current_schema.clone()
                 };
                 let table_name = names.last().unwrap().to_string();
 
-                if let Some(table_def) = self
-                    .ctx
-                    .catalog
-                    .find_table_by_name(&schema_name, &table_name)?
-                {
+                // System relations are found first, since `pg_catalog` is
+                // implicitly the first schema to search as in Postgres.
+                let system_table = find_system_table(SYSTEM_SCHEMA, &table_name)
+                    .filter(|_| names.len() == 1 || schema_name == SYSTEM_SCHEMA);
+                let table_def = if let Some(table_def) = system_table {
+                    schema_name = SYSTEM_SCHEMA.to_string();
+                    Some(table_def.clone())
+                } else {
+                    self.ctx.catalog().find_table_by_name(
+                        &self.ctx.current_database,
+                        &schema_name,
+                        &table_name,
+                    )?
+                };
+
+                if let Some(table_def) = table_def {
                     let mut scope = Scope::default();
                     scope
                         .variables
@@ -486,9 +1973,6 @@ impl<'a> Binder<'a> {
                             prefix: Some(QualifiedNamePrefix {
                                 schema_name: Some(schema_name.clone()),
                                 table_name: if let Some(alias) = alias {
-                                    if !alias.columns.is_empty() {
-                                        unimplemented!()
-                                    }
                                     alias.name.to_string()
                                 } else {
                                     table_name.clone()
@@ -498,9 +1982,14 @@ impl<'a> Binder<'a> {
                             expr: None,
                         }));
 
+                    if let Some(alias) = alias {
+                        Self::apply_column_aliases(&mut scope, alias)?;
+                    }
+
                     let plan = Plan::Get {
                         schema_name,
                         table_name,
+                        projections: None,
                     };
 
                     Ok((plan, scope))
@@ -508,7 +1997,8 @@ impl<'a> Binder<'a> {
                     Err(SQLError::new(
                         ErrorKind::PlannerError,
                         format!("table {} not found", table_name),
-                    ))
+                    )
+                    .with_code(sqlstate::UNDEFINED_TABLE))
                 }
             }
 
@@ -519,7 +2009,7 @@ impl<'a> Binder<'a> {
                 let (plan, mut scope) = self.bind_table_with_joins(ctx, table_with_joins)?;
 
                 if let Some(alias) = alias {
-                    Self::apply_table_alias(&mut scope, alias);
+                    Self::apply_table_alias(&mut scope, alias)?;
                 }
 
                 Ok((plan, scope))
@@ -531,12 +2021,16 @@ impl<'a> Binder<'a> {
                 let (plan, scope) = self.bind_query(ctx, subquery)?;
                 let mut scope = scope;
                 if let Some(alias) = alias {
-                    Self::apply_table_alias(&mut scope, alias);
+                    Self::apply_table_alias(&mut scope, alias)?;
                 }
                 Ok((plan, scope))
             }
 
-            _ => unimplemented!(),
+            table_factor => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("table reference is not supported: {}", table_factor),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
         }
     }
 
@@ -549,10 +2043,32 @@ impl<'a> Binder<'a> {
         right_plan: Plan,
         right_scope: Scope,
     ) -> Result<(Plan, Scope), SQLError> {
+        let not_supported = |feature: &str| {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                format!("{} is not supported", feature),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+        };
         let join_scope = left_scope.extend(&right_scope);
+        let (left, right) = (Box::new(left_plan), Box::new(right_plan));
+
+        if let JoinOperator::LeftOuter(JoinConstraint::On(expr)) = join_op {
+            let condition = bind_scalar(ctx, &join_scope, expr)?;
+            return Ok((
+                Plan::LeftOuterJoin {
+                    condition,
+                    left,
+                    right,
+                },
+                join_scope,
+            ));
+        }
+
         let join_plan = Plan::Join {
-            left: Box::new(left_plan),
-            right: Box::new(right_plan),
+            keys: vec![],
+            left,
+            right,
         };
 
         match join_op {
@@ -568,10 +2084,23 @@ impl<'a> Binder<'a> {
                     ))
                 }
                 JoinConstraint::None => Ok((join_plan, join_scope)),
-                _ => unimplemented!(),
+                JoinConstraint::Using(_) => Err(not_supported("JOIN ... USING")),
+                JoinConstraint::Natural => Err(not_supported("NATURAL JOIN")),
             },
             JoinOperator::CrossJoin => Ok((join_plan, join_scope)),
-            _ => unimplemented!(),
+            JoinOperator::LeftOuter(JoinConstraint::Using(_)) => {
+                Err(not_supported("JOIN ... USING"))
+            }
+            JoinOperator::LeftOuter(_) => Err(not_supported("NATURAL JOIN")),
+            JoinOperator::RightOuter(_) => Err(not_supported("RIGHT JOIN")),
+            JoinOperator::FullOuter(_) => Err(not_supported("FULL JOIN")),
+            JoinOperator::LeftSemi(_) | JoinOperator::RightSemi(_) => {
+                Err(not_supported("SEMI JOIN"))
+            }
+            JoinOperator::LeftAnti(_) | JoinOperator::RightAnti(_) => {
+                Err(not_supported("ANTI JOIN"))
+            }
+            JoinOperator::CrossApply | JoinOperator::OuterApply => Err(not_supported("APPLY")),
         }
     }
 
@@ -579,10 +2108,12 @@ impl<'a> Binder<'a> {
         &mut self,
         plan: Plan,
         group_by: Vec<ScalarExpr>,
-        aggregates: Vec<(String, Vec<ScalarExpr>)>,
+        grouping_sets: Vec<Vec<usize>>,
+        aggregates: Vec<AggregateExpr>,
     ) -> Result<Plan, SQLError> {
         Ok(Plan::Aggregate {
             group_by,
+            grouping_sets,
             aggregates,
             input: Box::new(plan),
         })
@@ -592,15 +2123,23 @@ impl<'a> Binder<'a> {
         &mut self,
         ctx: &mut BindContext,
         table_idents: &[Ident],
+        column_idents: &[Ident],
         source: &Query,
-    ) -> Result<Plan, SQLError> {
+        returning: Option<&[SelectItem]>,
+    ) -> Result<(Plan, Scope), SQLError> {
         let (schema_name, table_name) = Self::qualify_table_name(self.ctx, table_idents);
 
         let table_def = self
             .ctx
-            .catalog
-            .find_table_by_name(&schema_name, &table_name)?
-            .ok_or_else(|| SQLError::new(ErrorKind::CatalogError, "table not found"))?;
+            .catalog()
+            .find_table_by_name(&self.ctx.current_database, &schema_name, &table_name)?
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "table not found")
+                    .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+
+        // Target columns of the values
+        let target_columns = Self::bind_target_columns(&table_def, column_idents)?;
 
         let mut insert_data = vec![];
 
@@ -608,44 +2147,379 @@ impl<'a> Binder<'a> {
         match source.body.as_ref() {
             SetExpr::Values(values) => {
                 for row in values.rows.iter() {
-                    if row.len() != table_def.columns.len() {
+                    if row.len() > target_columns.len() {
                         return Err(SQLError::new(
                             ErrorKind::PlannerError,
-                            "invalid insert values",
-                        ));
+                            "INSERT has more expressions than target columns",
+                        )
+                        .with_code(sqlstate::SYNTAX_ERROR));
                     }
-                    let mut tuple = Tuple::default();
-                    for (expr, col_def) in row.iter().zip(table_def.columns.iter()) {
-                        let scalar = bind_scalar(ctx, &scope, expr)?;
-                        if let ScalarExpr::Literal(value) = scalar {
-                            let value = value.cast(&col_def.data_type);
-                            tuple.append(value);
-                        } else {
-                            return Err(SQLError::new(
-                                ErrorKind::PlannerError,
-                                "invalid insert values",
-                            ));
+                    if !column_idents.is_empty() && row.len() < target_columns.len() {
+                        return Err(SQLError::new(
+                            ErrorKind::PlannerError,
+                            "INSERT has more target columns than expressions",
+                        )
+                        .with_code(sqlstate::SYNTAX_ERROR));
+                    }
+
+                    // Columns without values are filled with the default values
+                    let mut values = vec![None; table_def.columns.len()];
+                    for (expr, column) in row.iter().zip(target_columns.iter()) {
+                        if is_default_keyword(expr) {
+                            continue;
                         }
+                        // Values can be any expressions without column references,
+                        // they are evaluated on execution
+                        values[*column] = Some(bind_scalar(ctx, &scope, expr)?);
                     }
-                    insert_data.push(tuple);
+                    let values = values
+                        .into_iter()
+                        .zip(table_def.columns.iter())
+                        .map(|(value, column)| match (value, &column.default) {
+                            (Some(value), _) => Ok(value),
+                            (None, Some(default)) => {
+                                bind_scalar(ctx, &scope, &parse_expr(default)?)
+                            }
+                            (None, None) => Ok(ScalarExpr::Literal(Datum::Null)),
+                        })
+                        .collect::<Result<Vec<_>, SQLError>>()?;
+                    insert_data.push(values);
                 }
             }
-            _ => unimplemented!(),
+            _ => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "INSERT ... SELECT is not supported",
+                )
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+            }
         }
 
-        let plan = Plan::DML(DMLJob::Insert((schema_name, table_name), insert_data));
+        let table_scope = Scope {
+            variables: table_def
+                .columns
+                .iter()
+                .map(|column| Variable {
+                    prefix: Some(QualifiedNamePrefix {
+                        schema_name: Some(schema_name.clone()),
+                        table_name: table_name.clone(),
+                    }),
+                    name: column.name.clone(),
+                    expr: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let (returning, scope) = self.bind_returning(ctx, &table_scope, returning)?;
 
-        Ok(plan)
+        let plan = Plan::DML(
+            DMLJob::Insert((schema_name, table_name), insert_data),
+            returning,
+        );
+
+        Ok((plan, scope))
     }
 
-    fn apply_table_alias(scope: &mut Scope, alias: &TableAlias) {
-        for variable in scope.variables.iter_mut() {
-            match &mut variable.prefix {
-                Some(QualifiedNamePrefix {
-                    schema_name,
-                    table_name,
-                }) => {
-                    *schema_name = None;
+    pub fn bind_update(
+        &mut self,
+        ctx: &mut BindContext,
+        table: &TableWithJoins,
+        assignments: &[Assignment],
+        selection: Option<&Expr>,
+        returning: Option<&[SelectItem]>,
+    ) -> Result<(Plan, Scope), SQLError> {
+        if !table.joins.is_empty() {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "UPDATE with joined tables is not supported",
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+        }
+
+        let (plan, scope) = self.bind_table_ref(ctx, &table.relation)?;
+        let (schema_name, table_name) = match plan {
+            Plan::Get {
+                schema_name,
+                table_name,
+                ..
+            } => (schema_name, table_name),
+            _ => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "invalid update target",
+                ))
+            }
+        };
+
+        let assignments = assignments
+            .iter()
+            .map(|assignment| {
+                let column = scope.resolve_column(&assignment.id)?.ok_or_else(|| {
+                    SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!(
+                            "column not found: {}",
+                            assignment
+                                .id
+                                .iter()
+                                .map(Ident::to_string)
+                                .collect::<Vec<_>>()
+                                .join(".")
+                        ),
+                    )
+                    .with_code(sqlstate::UNDEFINED_COLUMN)
+                })?;
+                let value = bind_scalar(ctx, &scope, &assignment.value)?;
+
+                Ok((column.index, value))
+            })
+            .collect::<Result<Vec<_>, SQLError>>()?;
+
+        let predicate = selection
+            .map(|expr| bind_scalar(ctx, &scope, expr))
+            .transpose()?;
+
+        let (returning, scope) = self.bind_returning(ctx, &scope, returning)?;
+
+        let plan = Plan::DML(
+            DMLJob::Update((schema_name, table_name), assignments, predicate),
+            returning,
+        );
+
+        Ok((plan, scope))
+    }
+
+    pub fn bind_delete(
+        &mut self,
+        ctx: &mut BindContext,
+        table: &TableFactor,
+        selection: Option<&Expr>,
+        returning: Option<&[SelectItem]>,
+    ) -> Result<(Plan, Scope), SQLError> {
+        let (plan, scope) = self.bind_table_ref(ctx, table)?;
+        let (schema_name, table_name) = match plan {
+            Plan::Get {
+                schema_name,
+                table_name,
+                ..
+            } => (schema_name, table_name),
+            _ => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "invalid delete target",
+                ))
+            }
+        };
+
+        let predicate = selection
+            .map(|expr| bind_scalar(ctx, &scope, expr))
+            .transpose()?;
+
+        let (returning, scope) = self.bind_returning(ctx, &scope, returning)?;
+
+        let plan = Plan::DML(
+            DMLJob::Delete((schema_name, table_name), predicate),
+            returning,
+        );
+
+        Ok((plan, scope))
+    }
+
+    /// Indices of the named columns of table, all the columns in order if
+    /// no column is named.
+    fn bind_target_columns(
+        table_def: &TableDefinition,
+        column_idents: &[Ident],
+    ) -> Result<Vec<usize>, SQLError> {
+        if column_idents.is_empty() {
+            return Ok((0..table_def.columns.len()).collect());
+        }
+
+        let mut target_columns = vec![];
+        for ident in column_idents.iter() {
+            let index = table_def
+                .columns
+                .iter()
+                .position(|column| column.name == ident.value)
+                .ok_or_else(|| {
+                    SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!(
+                            "column \"{}\" of relation \"{}\" does not exist",
+                            ident.value, table_def.name
+                        ),
+                    )
+                    .with_code(sqlstate::UNDEFINED_COLUMN)
+                })?;
+            if target_columns.contains(&index) {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!("column \"{}\" specified more than once", ident.value),
+                )
+                .with_code(sqlstate::DUPLICATE_COLUMN));
+            }
+            target_columns.push(index);
+        }
+
+        Ok(target_columns)
+    }
+
+    /// Bind `COPY` between a table and a CSV or Parquet file on the server.
+    pub fn bind_copy(
+        &mut self,
+        ctx: &mut BindContext,
+        table_idents: &[Ident],
+        column_idents: &[Ident],
+        to: bool,
+        target: &CopyTarget,
+        format: CopyFormat,
+    ) -> Result<Plan, SQLError> {
+        let (schema_name, table_name) = Self::qualify_table_name(self.ctx, table_idents);
+        let table_def = self
+            .ctx
+            .catalog()
+            .find_table_by_name(&self.ctx.current_database, &schema_name, &table_name)?
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "table not found")
+                    .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        let columns = Self::bind_target_columns(&table_def, column_idents)?;
+
+        let file = match target {
+            CopyTarget::File { filename } => self.resolve_copy_file(filename)?,
+            _ => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!(
+                        "COPY {} {} is not supported",
+                        if to { "TO" } else { "FROM" },
+                        target
+                    ),
+                )
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+            }
+        };
+
+        let job = CopyJob {
+            table: (schema_name, table_name),
+            columns,
+            file,
+            format,
+        };
+        if to {
+            return Ok(Plan::DML(DMLJob::CopyTo(job), None));
+        }
+
+        // Columns not in the file are filled with the default values
+        let scope = Scope::default();
+        let defaults = table_def
+            .columns
+            .iter()
+            .map(|column| match &column.default {
+                Some(default) => bind_scalar(ctx, &scope, &parse_expr(default)?),
+                None => Ok(ScalarExpr::Literal(Datum::Null)),
+            })
+            .collect::<Result<Vec<_>, SQLError>>()?;
+
+        Ok(Plan::DML(DMLJob::CopyFrom(job, defaults), None))
+    }
+
+    /// Resolve the file of `COPY` in the configured directory. Paths are
+    /// relative to it and cannot escape from it, so that clients can't read
+    /// or write arbitrary files of the server.
+    fn resolve_copy_file(&self, filename: &str) -> Result<String, SQLError> {
+        let Some(dir) = &self.ctx.copy_directory else {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "COPY to or from a file is not allowed",
+            )
+            .with_code(sqlstate::INSUFFICIENT_PRIVILEGE)
+            .with_hint("The directory of files must be configured by the server."));
+        };
+
+        Self::resolve_file(dir, filename)
+    }
+
+    /// Path of the file in the directory, which must be relative to it
+    /// without leaving it.
+    fn resolve_file(dir: &Path, filename: &str) -> Result<String, SQLError> {
+        let path = Path::new(filename);
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "path \"{}\" must be relative to the directory of files and cannot contain \"..\"",
+                    filename
+                ),
+            )
+            .with_code(sqlstate::INSUFFICIENT_PRIVILEGE));
+        }
+
+        Ok(dir.join(path).to_string_lossy().into_owned())
+    }
+
+    /// Bind the `RETURNING` list against the scope of target table, the
+    /// returned scope names the result columns.
+    fn bind_returning(
+        &mut self,
+        ctx: &mut BindContext,
+        table_scope: &Scope,
+        returning: Option<&[SelectItem]>,
+    ) -> Result<(Option<Vec<ScalarExpr>>, Scope), SQLError> {
+        let Some(returning) = returning else {
+            return Ok((None, Scope::default()));
+        };
+
+        let mut scope = Scope::default();
+        let scalars = self
+            .expand_select_list(table_scope, returning)?
+            .into_iter()
+            .map(|item| {
+                scope.variables.push(Variable {
+                    prefix: None,
+                    name: item.alias,
+                    expr: None,
+                });
+                bind_scalar(ctx, table_scope, &item.expr)
+            })
+            .collect::<Result<Vec<_>, SQLError>>()?;
+
+        Ok((Some(scalars), scope))
+    }
+
+    /// Name of the unnamed select item like Postgres, i.e. the name of column
+    /// or function, otherwise `?column?`.
+    fn output_name(expr: &Expr) -> String {
+        match expr {
+            Expr::Identifier(ident) => ident.value.clone(),
+            Expr::CompoundIdentifier(idents) => idents.last().unwrap().value.clone(),
+            Expr::Function(func) => func.name.0.last().unwrap().value.to_lowercase(),
+            Expr::Nested(expr) | Expr::Cast { expr, .. } => Self::output_name(expr),
+            // Scalar subqueries are named after their only column
+            Expr::Subquery(query) => match query.body.as_ref() {
+                SetExpr::Select(select) => match select.projection.first() {
+                    Some(SelectItem::UnnamedExpr(expr)) => Self::output_name(expr),
+                    Some(SelectItem::ExprWithAlias { alias, .. }) => alias.value.clone(),
+                    _ => "?column?".to_string(),
+                },
+                _ => "?column?".to_string(),
+            },
+            Expr::ArraySubquery(_) => "array".to_string(),
+            _ => "?column?".to_string(),
+        }
+    }
+
+    fn apply_table_alias(scope: &mut Scope, alias: &TableAlias) -> Result<(), SQLError> {
+        for variable in scope.variables.iter_mut() {
+            match &mut variable.prefix {
+                Some(QualifiedNamePrefix {
+                    schema_name,
+                    table_name,
+                }) => {
+                    *schema_name = None;
                     *table_name = alias.name.to_string();
                 }
                 None => {
@@ -656,6 +2530,798 @@ impl<'a> Binder<'a> {
                 }
             }
         }
+
+        Self::apply_column_aliases(scope, alias)
+    }
+
+    /// Rename the columns in scope with the column aliases, e.g. `AS t(a, b)`.
+    /// The columns without aliases keep their names.
+    fn apply_column_aliases(scope: &mut Scope, alias: &TableAlias) -> Result<(), SQLError> {
+        if alias.columns.len() > scope.variables.len() {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "table \"{}\" has {} columns available but {} columns specified",
+                    alias.name,
+                    scope.variables.len(),
+                    alias.columns.len()
+                ),
+            )
+            .with_code(sqlstate::INVALID_COLUMN_REFERENCE));
+        }
+        for (variable, column) in scope.variables.iter_mut().zip(alias.columns.iter()) {
+            variable.name = column.value.clone();
+        }
+
+        Ok(())
+    }
+
+    /// Bind the definition of column, the sequence of serial column is added to
+    /// `sequences`.
+    fn bind_column_def(
+        bind_context: &mut BindContext,
+        schema_name: &str,
+        table_name: &str,
+        col: &ColumnDef,
+        sequences: &mut Vec<SequenceDefinition>,
+    ) -> Result<ColumnDefinition, SQLError> {
+        let name = col.name.to_string();
+        let serial_type = Self::serial_type(&col.data_type);
+        let data_type = Type::try_from(serial_type.as_ref().unwrap_or(&col.data_type))?;
+        let int_width = IntWidth::from_data_type(serial_type.as_ref().unwrap_or(&col.data_type));
+        let has_option = |option| col.options.iter().any(|opt| opt.option == option);
+        if has_option(ColumnOption::Null) && has_option(ColumnOption::NotNull) {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "conflicting NULL/NOT NULL declarations for column \"{}\" of table \"{}\"",
+                    name, table_name
+                ),
+            )
+            .with_code(sqlstate::SYNTAX_ERROR));
+        }
+        let mut null = !has_option(ColumnOption::NotNull);
+        let mut default = col
+            .options
+            .iter()
+            .find_map(|opt| match &opt.option {
+                ColumnOption::Default(expr) => Some(expr),
+                _ => None,
+            })
+            .map(|expr| {
+                // Default value can only be a constant expression
+                bind_scalar(bind_context, &Scope::default(), expr)?;
+                Ok::<_, SQLError>(expr.to_string())
+            })
+            .transpose()?;
+
+        // Serial column is a NOT NULL integer column, with the default
+        // value generated by a sequence owned by it.
+        if let Some(serial_type) = serial_type {
+            if default.is_some() {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!(
+                        "multiple default values specified for column \"{}\" of table \"{}\"",
+                        name, table_name
+                    ),
+                )
+                .with_code(sqlstate::SYNTAX_ERROR));
+            }
+
+            let sequence_name = format!("{}_{}_seq", table_name, name);
+            default = Some(format!(
+                "nextval('{}.{}')",
+                schema_name.replace('\'', "''"),
+                sequence_name.replace('\'', "''")
+            ));
+            null = false;
+            sequences.push(SequenceDefinition {
+                name: sequence_name,
+                start: 1,
+                increment: 1,
+                min_value: 1,
+                max_value: Self::integer_max_value(&serial_type).unwrap(),
+                cycle: false,
+                owned_by: Some((table_name.to_string(), name.clone())),
+            });
+        }
+
+        Ok(ColumnDefinition {
+            name,
+            data_type,
+            null,
+            default,
+            int_width,
+        })
+    }
+
+    /// Bind the `PRIMARY KEY` of a new table, either as a column option or a table
+    /// constraint. The returned unique index is used to enforce it.
+    fn bind_primary_key(
+        table_name: &str,
+        columns: &[ColumnDefinition],
+        column_defs: &[ColumnDef],
+        constraints: &[TableConstraint],
+    ) -> Result<Option<IndexDefinition>, SQLError> {
+        let mut primary_keys = vec![];
+        for (index, column_def) in column_defs.iter().enumerate() {
+            for option in column_def.options.iter() {
+                if let ColumnOption::Unique { is_primary: true } = option.option {
+                    primary_keys.push((option.name.as_ref(), vec![index]));
+                }
+            }
+        }
+        for constraint in constraints.iter() {
+            if let TableConstraint::Unique {
+                name,
+                columns: names,
+                is_primary: true,
+            } = constraint
+            {
+                let key = names
+                    .iter()
+                    .map(|name| {
+                        columns
+                            .iter()
+                            .position(|column| column.name == name.value)
+                            .ok_or_else(|| {
+                                SQLError::new(
+                                    ErrorKind::PlannerError,
+                                    format!("column \"{}\" named in key does not exist", name),
+                                )
+                                .with_code(sqlstate::UNDEFINED_COLUMN)
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                primary_keys.push((name.as_ref(), key));
+            }
+        }
+
+        if primary_keys.len() > 1 {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "multiple primary keys for table \"{}\" are not allowed",
+                    table_name
+                ),
+            )
+            .with_code(sqlstate::INVALID_TABLE_DEFINITION));
+        }
+
+        Ok(primary_keys.pop().map(|(name, columns)| IndexDefinition {
+            name: name.map_or_else(|| format!("{}_pkey", table_name), |name| name.value.clone()),
+            columns,
+            unique: true,
+        }))
+    }
+
+    /// Bind the `FOREIGN KEY` constraints of the new table `table_def`, which can
+    /// reference the table itself.
+    fn bind_foreign_keys(
+        &self,
+        schema_name: &str,
+        table_def: &TableDefinition,
+        column_defs: &[ColumnDef],
+        constraints: &[TableConstraint],
+    ) -> Result<Vec<ForeignKeyDefinition>, SQLError> {
+        let find_column = |columns: &[ColumnDefinition], ident: &Ident| {
+            columns
+                .iter()
+                .position(|column| column.name == ident.value)
+                .ok_or_else(|| {
+                    SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!(
+                            "column \"{}\" referenced in foreign key constraint does not exist",
+                            ident.value
+                        ),
+                    )
+                    .with_code(sqlstate::UNDEFINED_COLUMN)
+                })
+        };
+
+        // (name, columns, foreign_table, referred_columns, on_delete, on_update)
+        let mut clauses = vec![];
+        for (index, column_def) in column_defs.iter().enumerate() {
+            for option in column_def.options.iter() {
+                if let ColumnOption::ForeignKey {
+                    foreign_table,
+                    referred_columns,
+                    on_delete,
+                    on_update,
+                } = &option.option
+                {
+                    clauses.push((
+                        option.name.as_ref(),
+                        vec![index],
+                        foreign_table,
+                        referred_columns,
+                        *on_delete,
+                        *on_update,
+                    ));
+                }
+            }
+        }
+        for constraint in constraints.iter() {
+            if let TableConstraint::ForeignKey {
+                name,
+                columns,
+                foreign_table,
+                referred_columns,
+                on_delete,
+                on_update,
+            } = constraint
+            {
+                let columns = columns
+                    .iter()
+                    .map(|ident| find_column(&table_def.columns, ident))
+                    .collect::<Result<Vec<_>, _>>()?;
+                clauses.push((
+                    name.as_ref(),
+                    columns,
+                    foreign_table,
+                    referred_columns,
+                    *on_delete,
+                    *on_update,
+                ));
+            }
+        }
+
+        let mut foreign_keys = vec![];
+        let not_supported = |clause: &str, action: ReferentialAction| {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                format!("foreign key action {} {} is not supported", clause, action),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+        };
+        for (name, columns, foreign_table, referred_names, on_delete, on_update) in clauses {
+            match on_update {
+                None | Some(ReferentialAction::NoAction | ReferentialAction::Restrict) => {}
+                Some(action) => return Err(not_supported("ON UPDATE", action)),
+            }
+            let on_delete = match on_delete {
+                None | Some(ReferentialAction::NoAction | ReferentialAction::Restrict) => {
+                    ForeignKeyAction::NoAction
+                }
+                Some(ReferentialAction::Cascade) => ForeignKeyAction::Cascade,
+                Some(action) => return Err(not_supported("ON DELETE", action)),
+            };
+
+            let (referred_schema, referred_table) =
+                Self::qualify_table_name(self.ctx, &foreign_table.0);
+            let referred_def = if referred_schema == schema_name && referred_table == table_def.name
+            {
+                table_def.clone()
+            } else {
+                self.ctx
+                    .catalog()
+                    .find_table_by_name(
+                        &self.ctx.current_database,
+                        &referred_schema,
+                        &referred_table,
+                    )?
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::PlannerError,
+                            format!("relation \"{}\" does not exist", referred_table),
+                        )
+                        .with_code(sqlstate::UNDEFINED_TABLE)
+                    })?
+            };
+
+            // The primary key is referenced if the columns are not specified
+            let referred_columns = if referred_names.is_empty() {
+                referred_def
+                    .primary_key
+                    .as_ref()
+                    .and_then(|name| {
+                        referred_def
+                            .indexes
+                            .iter()
+                            .find(|index| index.name == *name)
+                    })
+                    .map(|index| index.columns.clone())
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::PlannerError,
+                            format!(
+                                "there is no primary key for referenced table \"{}\"",
+                                referred_table
+                            ),
+                        )
+                        .with_code(sqlstate::INVALID_FOREIGN_KEY)
+                    })?
+            } else {
+                referred_names
+                    .iter()
+                    .map(|ident| find_column(&referred_def.columns, ident))
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+
+            if columns.len() != referred_columns.len() {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "number of referencing and referenced columns for foreign key disagree",
+                )
+                .with_code(sqlstate::INVALID_FOREIGN_KEY));
+            }
+            if referred_def.find_unique_index(&referred_columns).is_none() {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!(
+                        "there is no unique constraint matching given keys for referenced table \"{}\"",
+                        referred_table
+                    ),
+                )
+                .with_code(sqlstate::INVALID_FOREIGN_KEY));
+            }
+
+            let name = name.map_or_else(
+                || {
+                    let names = columns
+                        .iter()
+                        .map(|column| table_def.columns[*column].name.clone())
+                        .collect::<Vec<_>>();
+                    format!("{}_{}_fkey", table_def.name, names.join("_"))
+                },
+                |name| name.value.clone(),
+            );
+            for (column, referred_column) in columns.iter().zip(referred_columns.iter()) {
+                let column = &table_def.columns[*column];
+                let referred_column = &referred_def.columns[*referred_column];
+                if column.data_type != referred_column.data_type {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!("foreign key constraint \"{}\" cannot be implemented", name),
+                    )
+                    .with_code(sqlstate::DATATYPE_MISMATCH)
+                    .with_detail(format!(
+                        "Key columns \"{}\" and \"{}\" are of incompatible types.",
+                        column.name, referred_column.name
+                    )));
+                }
+            }
+
+            foreign_keys.push(ForeignKeyDefinition {
+                name,
+                columns,
+                referred_schema,
+                referred_table,
+                referred_columns,
+                on_delete,
+            });
+        }
+
+        Ok(foreign_keys)
+    }
+
+    /// Bind the options of `CREATE SEQUENCE`, the defaults depend on the direction
+    /// of sequence, like Postgres.
+    fn bind_sequence(
+        &self,
+        schema_name: &str,
+        sequence_name: String,
+        data_type: Option<&DataType>,
+        options: &[SequenceOptions],
+        owned_by: Option<&ObjectName>,
+    ) -> Result<SequenceDefinition, SQLError> {
+        let invalid = |message: String| {
+            SQLError::new(ErrorKind::PlannerError, message)
+                .with_code(sqlstate::INVALID_PARAMETER_VALUE)
+        };
+
+        let data_type = data_type.cloned().unwrap_or(DataType::BigInt(None));
+        let type_max = Self::integer_max_value(&data_type).ok_or_else(|| {
+            invalid("sequence type must be smallint, integer, or bigint".to_string())
+        })?;
+        let type_min = -type_max - 1;
+
+        let (mut increment, mut min_value, mut max_value, mut start, mut cycle) =
+            (1, None, None, None, false);
+        for option in options.iter() {
+            match option {
+                SequenceOptions::IncrementBy(expr, _) => {
+                    increment = Self::bind_sequence_value(expr)?
+                }
+                SequenceOptions::MinValue(MinMaxValue::Some(expr)) => {
+                    min_value = Some(Self::bind_sequence_value(expr)?)
+                }
+                SequenceOptions::MaxValue(MinMaxValue::Some(expr)) => {
+                    max_value = Some(Self::bind_sequence_value(expr)?)
+                }
+                SequenceOptions::MinValue(_) => min_value = None,
+                SequenceOptions::MaxValue(_) => max_value = None,
+                SequenceOptions::StartWith(expr, _) => {
+                    start = Some(Self::bind_sequence_value(expr)?)
+                }
+                // Values are never cached, since they are logged ahead
+                SequenceOptions::Cache(_) => {}
+                SequenceOptions::Cycle(no_cycle) => cycle = !no_cycle,
+            }
+        }
+
+        if increment == 0 {
+            return Err(invalid("INCREMENT must not be zero".to_string()));
+        }
+        let min_value = min_value.unwrap_or(if increment > 0 { 1 } else { type_min });
+        let max_value = max_value.unwrap_or(if increment > 0 { type_max } else { -1 });
+        for (option, value) in [("MINVALUE", min_value), ("MAXVALUE", max_value)] {
+            if value < type_min || value > type_max {
+                return Err(invalid(format!(
+                    "{} ({}) is out of range for sequence data type {}",
+                    option,
+                    value,
+                    data_type.to_string().to_lowercase()
+                )));
+            }
+        }
+        if min_value >= max_value {
+            return Err(invalid(format!(
+                "MINVALUE ({}) must be less than MAXVALUE ({})",
+                min_value, max_value
+            )));
+        }
+        let start = start.unwrap_or(if increment > 0 { min_value } else { max_value });
+        if start < min_value {
+            return Err(invalid(format!(
+                "START value ({}) cannot be less than MINVALUE ({})",
+                start, min_value
+            )));
+        }
+        if start > max_value {
+            return Err(invalid(format!(
+                "START value ({}) cannot be greater than MAXVALUE ({})",
+                start, max_value
+            )));
+        }
+
+        let owned_by = match owned_by.map(|name| name.0.as_slice()) {
+            None => None,
+            Some([ident]) if ident.quote_style.is_none() && ident.value == "NONE" => None,
+            Some([schema, _, _]) if schema.to_string() != schema_name => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "sequence must be in same schema as table it is linked to",
+                )
+                .with_code(sqlstate::OBJECT_NOT_IN_PREREQUISITE_STATE));
+            }
+            Some([table, column]) | Some([_, table, column]) => {
+                let (table_name, column_name) = (table.to_string(), column.to_string());
+                let table_def = self
+                    .ctx
+                    .catalog()
+                    .find_table_by_name(&self.ctx.current_database, schema_name, &table_name)?
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::CatalogError,
+                            format!("relation \"{}\" does not exist", table_name),
+                        )
+                        .with_code(sqlstate::UNDEFINED_TABLE)
+                    })?;
+                if !table_def
+                    .columns
+                    .iter()
+                    .any(|column| column.name == column_name)
+                {
+                    return Err(SQLError::new(
+                        ErrorKind::CatalogError,
+                        format!(
+                            "column \"{}\" of relation \"{}\" does not exist",
+                            column_name, table_name
+                        ),
+                    )
+                    .with_code(sqlstate::UNDEFINED_COLUMN));
+                }
+                Some((table_name, column_name))
+            }
+            Some(_) => {
+                return Err(
+                    SQLError::new(ErrorKind::PlannerError, "invalid OWNED BY option")
+                        .with_code(sqlstate::SYNTAX_ERROR)
+                        .with_hint("Specify OWNED BY table.column or OWNED BY NONE."),
+                )
+            }
+        };
+
+        Ok(SequenceDefinition {
+            name: sequence_name,
+            start,
+            increment,
+            min_value,
+            max_value,
+            cycle,
+            owned_by,
+        })
+    }
+
+    /// Options of sequence are integer literals.
+    fn bind_sequence_value(expr: &Expr) -> Result<i64, SQLError> {
+        match expr {
+            Expr::Value(Value::Number(value, _)) => value.parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                format!("invalid value for sequence option: {}", expr),
+            )
+            .with_code(sqlstate::SYNTAX_ERROR)
+        })
+    }
+
+    /// The integer type generated by a serial type, e.g. `SERIAL` is `INTEGER`.
+    fn serial_type(data_type: &DataType) -> Option<DataType> {
+        let DataType::Custom(name, modifiers) = data_type else {
+            return None;
+        };
+        if !modifiers.is_empty() {
+            return None;
+        }
+        match name.to_string().to_lowercase().as_str() {
+            "smallserial" | "serial2" => Some(DataType::SmallInt(None)),
+            "serial" | "serial4" => Some(DataType::Integer(None)),
+            "bigserial" | "serial8" => Some(DataType::BigInt(None)),
+            _ => None,
+        }
+    }
+
+    /// The maximum value of integer type, which limits the sequences generating it.
+    fn integer_max_value(data_type: &DataType) -> Option<i64> {
+        match data_type {
+            DataType::SmallInt(_) => Some(i16::MAX as i64),
+            DataType::Int(_) | DataType::Integer(_) => Some(i32::MAX as i64),
+            DataType::BigInt(_) => Some(i64::MAX),
+            _ => None,
+        }
+    }
+
+    /// `CREATE FUNCTION` of a SQL function computing a single expression,
+    /// the body is either `RETURN expr` or `AS 'SELECT expr'`. Functions in
+    /// `LANGUAGE wasm` are loaded from `AS 'module.wasm'` instead.
+    fn bind_create_function(
+        &self,
+        name: &ObjectName,
+        args: &[OperateFunctionArg],
+        return_type: Option<&DataType>,
+        params: &CreateFunctionBody,
+        or_replace: bool,
+    ) -> Result<Plan, SQLError> {
+        let mut wasm = false;
+        if let Some(language) = &params.language {
+            match language.value.to_lowercase().as_str() {
+                "sql" => {}
+                "wasm" => wasm = true,
+                _ => {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!("language \"{}\" is not supported", language.value),
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+                }
+            }
+        }
+        let return_type = return_type.ok_or_else(|| {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                "function result type must be specified",
+            )
+            .with_code(sqlstate::INVALID_FUNCTION_DEFINITION)
+        })?;
+        if args
+            .iter()
+            .any(|arg| !matches!(arg.mode, None | Some(ArgMode::In)) || arg.default_expr.is_some())
+        {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "only IN arguments without defaults are supported in functions",
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+        }
+
+        let (schema_name, function_name) = qualify_function_name(&self.ctx.current_schema, &name.0)
+            .ok_or_else(|| SQLError::new(ErrorKind::PlannerError, "invalid function name"))?;
+        if is_builtin_function(self.ctx, &function_name) {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("function \"{}\" already exists", function_name),
+            )
+            .with_code(sqlstate::DUPLICATE_FUNCTION));
+        }
+
+        let arg_types = args
+            .iter()
+            .map(|arg| Type::try_from(&arg.data_type))
+            .collect::<Result<Vec<_>, _>>()?;
+        if wasm {
+            let module = self.load_function_module(&function_name, params)?;
+            let return_type = Type::try_from(return_type)?;
+            // Check the module before it's persisted in the catalog
+            WasmFunction::compile(&function_name, &module, &arg_types, &return_type)?;
+            let function_def = FunctionDefinition {
+                name: function_name,
+                arg_types,
+                return_type,
+                body: FunctionBody::Wasm(module.into()),
+            };
+            return Ok(Plan::DDL(DDLJob::CreateFunction(
+                schema_name,
+                function_def,
+                or_replace,
+            )));
+        }
+
+        let body = match (&params.return_, &params.as_) {
+            (Some(expr), None) => expr.clone(),
+            (None, Some(FunctionAs::SingleQuotedDef(sql_text)))
+            | (None, Some(FunctionAs::DoubleDollarDef(sql_text))) => {
+                Self::function_body_expr(sql_text)?
+            }
+            (None, None) => {
+                return Err(
+                    SQLError::new(ErrorKind::PlannerError, "no function body specified")
+                        .with_code(sqlstate::INVALID_FUNCTION_DEFINITION),
+                )
+            }
+            (Some(_), Some(_)) => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    "duplicate function body specified",
+                )
+                .with_code(sqlstate::INVALID_FUNCTION_DEFINITION))
+            }
+        };
+
+        let function_def = FunctionDefinition {
+            name: function_name,
+            arg_types,
+            return_type: Type::try_from(return_type)?,
+            body: FunctionBody::Sql(build_function_body(args, return_type, &body)?),
+        };
+
+        Ok(Plan::DDL(DDLJob::CreateFunction(
+            schema_name,
+            function_def,
+            or_replace,
+        )))
+    }
+
+    /// Read the WebAssembly module of function from `AS 'file'`, which is
+    /// relative to the directory of functions.
+    fn load_function_module(
+        &self,
+        function_name: &str,
+        params: &CreateFunctionBody,
+    ) -> Result<Vec<u8>, SQLError> {
+        let filename = match (&params.return_, &params.as_) {
+            (None, Some(FunctionAs::SingleQuotedDef(filename)))
+            | (None, Some(FunctionAs::DoubleDollarDef(filename))) => filename,
+            _ => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!(
+                        "function {} in language \"wasm\" must be defined by AS 'module.wasm'",
+                        function_name
+                    ),
+                )
+                .with_code(sqlstate::INVALID_FUNCTION_DEFINITION))
+            }
+        };
+        let Some(dir) = &self.ctx.function_directory else {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "loading functions from files is not allowed",
+            )
+            .with_code(sqlstate::INSUFFICIENT_PRIVILEGE)
+            .with_hint("The directory of functions must be configured by the server."));
+        };
+
+        let file = Self::resolve_file(dir, filename)?;
+        std::fs::read(&file).map_err(|e| copy_file_error(&file, "reading", e))
+    }
+
+    /// The expression of `SELECT expr`, the only form of function body in SQL
+    /// text supported.
+    fn function_body_expr(sql_text: &str) -> Result<Expr, SQLError> {
+        let not_supported = || {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                "only SQL functions returning a single expression are supported",
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+        };
+
+        let mut stmts = parse_sql(sql_text)?;
+        let (1, Some(SQLStatement::Sql(Statement::Query(query)))) = (stmts.len(), stmts.pop())
+        else {
+            return Err(not_supported());
+        };
+        let Query {
+            with: None,
+            body,
+            order_by,
+            limit: None,
+            offset: None,
+            fetch: None,
+            ..
+        } = *query
+        else {
+            return Err(not_supported());
+        };
+        let SetExpr::Select(select) = *body else {
+            return Err(not_supported());
+        };
+        let Select {
+            distinct: None,
+            top: None,
+            projection,
+            into: None,
+            from,
+            selection: None,
+            group_by: GroupByExpr::Expressions(group_by),
+            having: None,
+            ..
+        } = *select
+        else {
+            return Err(not_supported());
+        };
+        match projection.as_slice() {
+            [SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. }]
+                if from.is_empty() && group_by.is_empty() && order_by.is_empty() =>
+            {
+                Ok(expr.clone())
+            }
+            _ => Err(not_supported()),
+        }
+    }
+
+    /// Tables (schema_name, table_name) maintained by `ANALYZE` or `VACUUM`,
+    /// all the tables if the name is empty.
+    fn bind_maintained_tables(
+        &self,
+        table_name: &ObjectName,
+    ) -> Result<Vec<(String, String)>, SQLError> {
+        let names = if table_name.0.is_empty() {
+            let catalog = self.ctx.catalog();
+            catalog
+                .schemas(&self.ctx.current_database)
+                .flat_map(|schema| {
+                    schema
+                        .tables
+                        .iter()
+                        .map(|table| (schema.name.clone(), table.name.clone()))
+                })
+                .collect()
+        } else {
+            let (schema_name, table_name) = Self::qualify_table_name(self.ctx, &table_name.0);
+            if self
+                .ctx
+                .catalog()
+                .find_table_by_name(&self.ctx.current_database, &schema_name, &table_name)?
+                .is_none()
+            {
+                return Err(SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("relation \"{}\" does not exist", table_name),
+                )
+                .with_code(sqlstate::UNDEFINED_TABLE));
+            }
+            vec![(schema_name, table_name)]
+        };
+        Ok(names)
+    }
+
+    /// Databases are named by a single identifier.
+    fn bind_database_name(name: &ObjectName) -> Result<String, SQLError> {
+        match name.0.as_slice() {
+            [ident] => Ok(ident.to_string()),
+            _ => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("invalid database name: {}", name),
+            )
+            .with_code(sqlstate::SYNTAX_ERROR)),
+        }
     }
 
     fn qualify_table_name(ctx: &QueryContext, idents: &[Ident]) -> (String, String) {
@@ -666,3 +3332,320 @@ impl<'a> Binder<'a> {
         }
     }
 }
+
+/// Output column referenced by `ORDER BY` or `GROUP BY`, either by the
+/// position in select list or by the output name. `None` if the expression is
+/// not such a reference.
+fn resolve_output_reference(
+    clause: &str,
+    expr: &Expr,
+    select_list: &[FlattenedSelectItem],
+) -> Result<Option<usize>, SQLError> {
+    match expr {
+        Expr::Value(Value::Number(number, _)) => match number.parse::<usize>() {
+            Ok(position) if (1..=select_list.len()).contains(&position) => Ok(Some(position - 1)),
+            _ => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("{} position {} is not in select list", clause, number),
+            )
+            .with_code(sqlstate::INVALID_COLUMN_REFERENCE)),
+        },
+        Expr::Identifier(ident) => {
+            let mut items = select_list
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.alias == ident.value);
+            let Some((position, item)) = items.next() else {
+                return Ok(None);
+            };
+            // Output columns of the same name are fine if they are the same
+            if items.any(|(_, other)| other.expr != item.expr) {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!("{} \"{}\" is ambiguous", clause, ident.value),
+                )
+                .with_code(sqlstate::AMBIGUOUS_COLUMN));
+            }
+            Ok(Some(position))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Replace the positions and the aliases of select list referenced by the
+/// items of `GROUP BY` with the select expressions. The columns of `FROM` take
+/// precedence over the aliases.
+fn resolve_group_by_expr(
+    expr: &Expr,
+    from_scope: &Scope,
+    select_list: &[FlattenedSelectItem],
+    aggregate_functions: &Arc<AggregateFunctionRegistry>,
+) -> Result<Expr, SQLError> {
+    let resolve_all = |exprs: &[Vec<Expr>]| {
+        exprs
+            .iter()
+            .map(|set| {
+                set.iter()
+                    .map(|expr| {
+                        resolve_group_by_expr(expr, from_scope, select_list, aggregate_functions)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let expr = match expr {
+        Expr::GroupingSets(sets) => return Ok(Expr::GroupingSets(resolve_all(sets)?)),
+        Expr::Rollup(elements) => return Ok(Expr::Rollup(resolve_all(elements)?)),
+        Expr::Cube(elements) => return Ok(Expr::Cube(resolve_all(elements)?)),
+        Expr::Identifier(ident) if from_scope.resolve_column(&[ident.clone()])?.is_some() => {
+            expr.clone()
+        }
+        expr => match resolve_output_reference("GROUP BY", expr, select_list)? {
+            Some(position) => select_list[position].expr.clone(),
+            None => expr.clone(),
+        },
+    };
+
+    let mut aggregate_visitor = AggregateFunctionVisitor::new(aggregate_functions.clone());
+    expr.visit(&mut aggregate_visitor);
+    if !aggregate_visitor.aggregates.is_empty() {
+        return Err(SQLError::new(
+            ErrorKind::PlannerError,
+            "aggregate functions are not allowed in GROUP BY",
+        )
+        .with_code(sqlstate::GROUPING_ERROR));
+    }
+
+    Ok(expr)
+}
+
+/// Replace the aliases of select list referenced by the expression with the
+/// aliased expressions, unless they are columns of `FROM`.
+fn replace_select_aliases(
+    expr: &Expr,
+    from_scope: &Scope,
+    select_list: &[FlattenedSelectItem],
+) -> Result<Expr, SQLError> {
+    let mut expr = expr.clone();
+    let result = visit_expressions_mut(&mut expr, |expr| {
+        let Expr::Identifier(ident) = expr else {
+            return ControlFlow::Continue(());
+        };
+        match from_scope.resolve_column(&[ident.clone()]) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                if let Some(item) = select_list.iter().find(|item| item.alias == ident.value) {
+                    *expr = item.expr.clone();
+                }
+            }
+            Err(err) => return ControlFlow::Break(err),
+        }
+        ControlFlow::Continue(())
+    });
+
+    match result {
+        ControlFlow::Break(err) => Err(err),
+        ControlFlow::Continue(()) => Ok(expr),
+    }
+}
+
+/// Whether the count of `LIMIT` or `FETCH FIRST` is a positive integer literal.
+fn is_positive_count(expr: &Expr) -> bool {
+    match expr {
+        Expr::Value(Value::Number(count, _)) => count.parse::<i64>().map_or(false, |n| n > 0),
+        _ => false,
+    }
+}
+
+/// Sort the plan on the keys, if there are any.
+fn sort(plan: Plan, order_by: Vec<OrderBy>) -> Plan {
+    if order_by.is_empty() {
+        return plan;
+    }
+
+    Plan::Sort {
+        order_by,
+        input: Box::new(plan),
+    }
+}
+
+/// Group keys of `GROUP BY`, and the grouping sets of `GROUPING SETS`,
+/// `ROLLUP` and `CUBE` as the indices of the keys. The grouping sets of the
+/// items are crossed, and a plain `GROUP BY` has no grouping sets.
+fn expand_grouping_sets(group_by: &[Expr]) -> (Vec<Expr>, Vec<Vec<usize>>) {
+    fn key_index(keys: &mut Vec<Expr>, expr: &Expr) -> usize {
+        keys.iter().position(|key| key == expr).unwrap_or_else(|| {
+            keys.push(expr.clone());
+            keys.len() - 1
+        })
+    }
+
+    let mut keys = vec![];
+    let mut grouping_sets: Vec<Vec<usize>> = vec![vec![]];
+    for expr in group_by {
+        let sets: Vec<Vec<&Expr>> = match expr {
+            Expr::GroupingSets(sets) => sets.iter().map(|set| set.iter().collect()).collect(),
+            // The prefixes of the elements, from the longest one
+            Expr::Rollup(elements) => (0..=elements.len())
+                .rev()
+                .map(|len| elements[..len].iter().flatten().collect())
+                .collect(),
+            // The subsets of the elements, in the order of PostgreSQL
+            Expr::Cube(elements) => (0..1usize << elements.len())
+                .rev()
+                .map(|mask| {
+                    elements
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| mask & (1 << (elements.len() - 1 - i)) != 0)
+                        .flat_map(|(_, element)| element.iter())
+                        .collect()
+                })
+                .collect(),
+            expr => vec![vec![expr]],
+        };
+        let sets = sets
+            .into_iter()
+            .map(|set| {
+                set.into_iter()
+                    .map(|expr| key_index(&mut keys, expr))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        grouping_sets = grouping_sets
+            .iter()
+            .flat_map(|prefix| {
+                sets.iter()
+                    .map(move |set| prefix.iter().chain(set).copied().collect())
+            })
+            .collect();
+    }
+
+    let plain = group_by.iter().all(|expr| {
+        !matches!(
+            expr,
+            Expr::GroupingSets(_) | Expr::Rollup(_) | Expr::Cube(_)
+        )
+    });
+    if plain {
+        grouping_sets.clear();
+    }
+
+    (keys, grouping_sets)
+}
+
+/// `DEFAULT` keyword in the values of `INSERT` or `SET`, which is parsed as an
+/// identifier.
+fn is_default_keyword(expr: &Expr) -> bool {
+    matches!(expr, Expr::Identifier(ident) if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("default"))
+}
+
+/// Bind the options of `COPY`, the CSV options are only allowed in the CSV
+/// format.
+fn bind_copy_format(
+    options: &[CopyOption],
+    legacy_options: &[CopyLegacyOption],
+) -> Result<CopyFormat, SQLError> {
+    let mut format = None;
+    let mut csv_options = CsvOptions::default();
+    // The first CSV option, which is rejected by the other formats
+    let mut csv_option = None;
+    // The escape is the same as quote unless specified
+    let mut escape = None;
+    let not_supported = |option: &dyn std::fmt::Display| {
+        SQLError::new(
+            ErrorKind::PlannerError,
+            format!("COPY option {} is not supported", option),
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+    };
+    for option in options.iter() {
+        match option {
+            CopyOption::Format(name) => {
+                format = Some(name.value.to_lowercase());
+                continue;
+            }
+            CopyOption::Delimiter(c) => csv_options.delimiter = *c,
+            CopyOption::Null(null) => csv_options.null = null.clone(),
+            CopyOption::Header(header) => csv_options.header = *header,
+            CopyOption::Quote(c) => csv_options.quote = *c,
+            CopyOption::Escape(c) => escape = Some(*c),
+            CopyOption::Freeze(_) => continue,
+            option => return Err(not_supported(option)),
+        }
+        csv_option.get_or_insert_with(|| option.to_string());
+    }
+    for option in legacy_options.iter() {
+        match option {
+            CopyLegacyOption::Csv(csv_legacy_options) => {
+                format = Some("csv".to_string());
+                for option in csv_legacy_options.iter() {
+                    match option {
+                        CopyLegacyCsvOption::Header => csv_options.header = true,
+                        CopyLegacyCsvOption::Quote(c) => csv_options.quote = *c,
+                        CopyLegacyCsvOption::Escape(c) => escape = Some(*c),
+                        option => return Err(not_supported(option)),
+                    }
+                }
+            }
+            CopyLegacyOption::Delimiter(c) => {
+                csv_options.delimiter = *c;
+                csv_option.get_or_insert_with(|| option.to_string());
+            }
+            CopyLegacyOption::Null(null) => {
+                csv_options.null = null.clone();
+                csv_option.get_or_insert_with(|| option.to_string());
+            }
+            CopyLegacyOption::Binary => format = Some("binary".to_string()),
+        }
+    }
+
+    match format.as_deref() {
+        Some("csv") => {
+            csv_options.escape = escape.unwrap_or(csv_options.quote);
+            csv_options.validate()?;
+            Ok(CopyFormat::Csv(csv_options))
+        }
+        Some("parquet") => match csv_option {
+            Some(option) => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("COPY option {} is only available in CSV mode", option),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+            None => Ok(CopyFormat::Parquet),
+        },
+        _ => Err(SQLError::new(
+            ErrorKind::PlannerError,
+            "COPY only supports the CSV and Parquet formats",
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+    }
+}
+
+/// Text of the value in `SET`, which can be a literal or an identifier.
+fn setting_value(expr: &Expr) -> Result<String, SQLError> {
+    match expr {
+        Expr::Value(Value::Number(v, _)) | Expr::Value(Value::SingleQuotedString(v)) => {
+            Ok(v.clone())
+        }
+        Expr::Value(Value::Boolean(v)) => Ok((if *v { "on" } else { "off" }).to_string()),
+        Expr::Identifier(ident) => Ok(ident.value.clone()),
+        Expr::UnaryOp {
+            op: sqlparser::ast::UnaryOperator::Minus,
+            expr,
+        } => match expr.as_ref() {
+            Expr::Value(Value::Number(v, _)) => Ok(format!("-{}", v)),
+            _ => Err(invalid_setting_value(expr)),
+        },
+        _ => Err(invalid_setting_value(expr)),
+    }
+}
+
+fn invalid_setting_value(expr: &Expr) -> SQLError {
+    SQLError::new(
+        ErrorKind::PlannerError,
+        format!("invalid value for parameter: {}", expr),
+    )
+    .with_code(sqlstate::SYNTAX_ERROR)
+}
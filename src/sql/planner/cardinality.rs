@@ -0,0 +1,292 @@
+use super::{index::source_column, Column, Plan, ScalarExpr};
+use crate::{
+    catalog::{defs::ColumnStatistics, DatabaseCatalog},
+    core::Datum,
+};
+
+/// Selectivity of an equality without statistics.
+const DEFAULT_EQ_SELECTIVITY: f64 = 0.005;
+/// Selectivity of a range comparison without statistics.
+const DEFAULT_RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+/// Selectivity of the other predicates.
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+
+/// Estimated number of the output rows of plan, based on the statistics
+/// collected by `ANALYZE`. `None` if any of the scanned tables has not been
+/// analyzed, or the plan doesn't produce rows.
+pub fn estimate_rows(plan: &Plan, catalog: &DatabaseCatalog) -> Option<f64> {
+    match plan {
+        Plan::Get {
+            schema_name,
+            table_name,
+            ..
+        } => {
+            let table_def = catalog.find_table_by_name(schema_name, table_name).ok()??;
+            Some(table_def.statistics?.row_count as f64)
+        }
+        Plan::IndexScan {
+            schema_name,
+            table_name,
+            columns,
+            range,
+            ..
+        } => {
+            // Estimated as the filters of the key range on the table
+            let table = Plan::Get {
+                schema_name: schema_name.clone(),
+                table_name: table_name.clone(),
+                projections: None,
+            };
+            let key = |i: usize| ScalarExpr::Column(Column { index: columns[i] });
+            let mut predicates = range
+                .prefix
+                .iter()
+                .enumerate()
+                .map(|(i, value)| comparison("=", key(i), value.clone()))
+                .collect::<Vec<_>>();
+            let column = range.prefix.len();
+            if let Some((value, inclusive)) = &range.lower {
+                let op = if *inclusive { ">=" } else { ">" };
+                predicates.push(comparison(op, key(column), value.clone()));
+            }
+            if let Some((value, inclusive)) = &range.upper {
+                let op = if *inclusive { "<=" } else { "<" };
+                predicates.push(comparison(op, key(column), value.clone()));
+            }
+
+            let rows = estimate_rows(&table, catalog)?;
+            Some(predicates.iter().fold(rows, |rows, predicate| {
+                rows * selectivity(predicate, &table, catalog)
+            }))
+        }
+        Plan::Filter { predicate, input } => {
+            Some(estimate_rows(input, catalog)? * selectivity(predicate, input, catalog))
+        }
+        Plan::Map { input, .. }
+        | Plan::Apply { input, .. }
+        | Plan::Project { input, .. }
+        | Plan::Window { input, .. }
+        | Plan::Distinct { input }
+        | Plan::Sort { input, .. } => estimate_rows(input, catalog),
+        Plan::Join { keys, left, right } => {
+            let rows = estimate_rows(left, catalog)? * estimate_rows(right, catalog)?;
+            Some(keys.iter().fold(rows, |rows, (left, right)| {
+                rows * equality_selectivity(left, right, plan, catalog)
+            }))
+        }
+        Plan::LeftOuterJoin {
+            condition,
+            left,
+            right,
+        } => {
+            // Every left row is emitted at least once
+            let left_rows = estimate_rows(left, catalog)?;
+            let rows =
+                left_rows * estimate_rows(right, catalog)? * selectivity(condition, plan, catalog);
+            Some(rows.max(left_rows))
+        }
+        Plan::SemiJoin { left, .. } => Some(estimate_rows(left, catalog)? * DEFAULT_SELECTIVITY),
+        Plan::UnionAll { left, right } => {
+            Some(estimate_rows(left, catalog)? + estimate_rows(right, catalog)?)
+        }
+        Plan::Limit {
+            limit,
+            offset,
+            input,
+        } => {
+            // Counts that aren't known until execution are ignored
+            let count = |scalar: &Option<ScalarExpr>| match scalar {
+                Some(ScalarExpr::Literal(Datum::Int(count))) => Some(*count.max(&0) as f64),
+                _ => None,
+            };
+            let rows = estimate_rows(input, catalog)?;
+            let rows = (rows - count(offset).unwrap_or(0.0)).max(0.0);
+            Some(count(limit).map_or(rows, |limit| rows.min(limit)))
+        }
+        Plan::Aggregate {
+            group_by,
+            grouping_sets,
+            input,
+            ..
+        } => {
+            let rows = estimate_rows(input, catalog)?;
+            if grouping_sets.is_empty() {
+                let keys = group_by.iter().collect::<Vec<_>>();
+                return Some(estimate_groups(&keys, input, rows, catalog));
+            }
+            // The groups of each grouping set are emitted separately
+            Some(
+                grouping_sets
+                    .iter()
+                    .map(|set| {
+                        let keys = set.iter().map(|key| &group_by[*key]).collect::<Vec<_>>();
+                        estimate_groups(&keys, input, rows, catalog)
+                    })
+                    .sum(),
+            )
+        }
+        Plan::Values { rows } => Some(rows.len() as f64),
+        Plan::Unnest { .. }
+        | Plan::DDL(_)
+        | Plan::DML(..)
+        | Plan::CreateTableAs { .. }
+        | Plan::Explain { .. }
+        | Plan::Use(_)
+        | Plan::SetVariable(..) => None,
+    }
+}
+
+/// Fraction of the rows of `input` satisfying the predicate.
+fn selectivity(predicate: &ScalarExpr, input: &Plan, catalog: &DatabaseCatalog) -> f64 {
+    let ScalarExpr::FunctionCall(name, args) = predicate else {
+        return match predicate {
+            ScalarExpr::Literal(Datum::Boolean(true)) => 1.0,
+            ScalarExpr::Literal(_) => 0.0,
+            _ => DEFAULT_SELECTIVITY,
+        };
+    };
+
+    match (name.as_str(), args.as_slice()) {
+        ("and", [left, right]) => {
+            selectivity(left, input, catalog) * selectivity(right, input, catalog)
+        }
+        ("or", [left, right]) => {
+            let (left, right) = (
+                selectivity(left, input, catalog),
+                selectivity(right, input, catalog),
+            );
+            left + right - left * right
+        }
+        ("not", [arg]) => 1.0 - selectivity(arg, input, catalog),
+        ("is_null", [arg]) => scalar_statistics(arg, input, catalog)
+            .map_or(DEFAULT_EQ_SELECTIVITY, |statistics| {
+                statistics.null_fraction
+            }),
+        ("is_not_null", [arg]) => scalar_statistics(arg, input, catalog)
+            .map_or(1.0 - DEFAULT_EQ_SELECTIVITY, |statistics| {
+                1.0 - statistics.null_fraction
+            }),
+        ("=" | "<>", [left, right]) => {
+            let selectivity = equality_selectivity(left, right, input, catalog);
+            if name == "=" {
+                selectivity
+            } else {
+                1.0 - selectivity
+            }
+        }
+        ("<" | "<=" | ">" | ">=", [left, right]) => {
+            match (
+                scalar_statistics(left, input, catalog),
+                scalar_statistics(right, input, catalog),
+            ) {
+                (Some(statistics), None) => range_selectivity(name, &statistics, right),
+                (None, Some(statistics)) => {
+                    let op = match name.as_str() {
+                        "<" => ">",
+                        "<=" => ">=",
+                        ">" => "<",
+                        _ => "<=",
+                    };
+                    range_selectivity(op, &statistics, left)
+                }
+                _ => DEFAULT_RANGE_SELECTIVITY,
+            }
+        }
+        _ => DEFAULT_SELECTIVITY,
+    }
+}
+
+/// Selectivity of `left = right`, assuming the values are uniformly distributed
+/// among the distinct values.
+fn equality_selectivity(
+    left: &ScalarExpr,
+    right: &ScalarExpr,
+    input: &Plan,
+    catalog: &DatabaseCatalog,
+) -> f64 {
+    let distinct = |statistics: &ColumnStatistics| statistics.distinct_count.max(1) as f64;
+    match (
+        scalar_statistics(left, input, catalog),
+        scalar_statistics(right, input, catalog),
+    ) {
+        (Some(left), Some(right)) => {
+            (1.0 - left.null_fraction) * (1.0 - right.null_fraction)
+                / distinct(&left).max(distinct(&right))
+        }
+        (Some(statistics), None) | (None, Some(statistics)) => {
+            if statistics.distinct_count == 0 {
+                return 0.0;
+            }
+            (1.0 - statistics.null_fraction) / distinct(&statistics)
+        }
+        (None, None) => DEFAULT_EQ_SELECTIVITY,
+    }
+}
+
+/// Selectivity of `column op value`, estimated with the histogram of column.
+fn range_selectivity(op: &str, statistics: &ColumnStatistics, value: &ScalarExpr) -> f64 {
+    let below = match value {
+        ScalarExpr::Literal(value) => statistics.fraction_below(value),
+        _ => None,
+    };
+    let fraction = match below {
+        Some(below) => {
+            // Fraction of the values equal to `value`
+            let equal = 1.0 / statistics.distinct_count.max(1) as f64;
+            let fraction = match op {
+                "<" => below,
+                "<=" => below + equal,
+                ">" => 1.0 - below - equal,
+                _ => 1.0 - below,
+            };
+            fraction.clamp(0.0, 1.0)
+        }
+        None => DEFAULT_RANGE_SELECTIVITY,
+    };
+
+    fraction * (1.0 - statistics.null_fraction)
+}
+
+/// Statistics of the column referenced by the scalar, traced down to the
+/// scanned table.
+fn scalar_statistics(
+    scalar: &ScalarExpr,
+    plan: &Plan,
+    catalog: &DatabaseCatalog,
+) -> Option<ColumnStatistics> {
+    match scalar {
+        ScalarExpr::Column(Column { index }) => column_statistics(*index, plan, catalog),
+        _ => None,
+    }
+}
+
+fn column_statistics(
+    column: usize,
+    plan: &Plan,
+    catalog: &DatabaseCatalog,
+) -> Option<ColumnStatistics> {
+    let (table_def, column) = source_column(column, plan, catalog)?;
+    table_def.statistics?.columns.get(column).cloned()
+}
+
+/// Number of groups of the input rows grouped by the keys.
+fn estimate_groups(
+    keys: &[&ScalarExpr],
+    input: &Plan,
+    rows: f64,
+    catalog: &DatabaseCatalog,
+) -> f64 {
+    if keys.is_empty() {
+        return 1.0;
+    }
+    // Number of groups is bounded by the distinct values of the keys
+    let groups = keys.iter().try_fold(1.0, |groups, scalar| {
+        let statistics = scalar_statistics(scalar, input, catalog)?;
+        Some(groups * statistics.distinct_count.max(1) as f64)
+    });
+    groups.map_or(rows, |groups| groups.min(rows))
+}
+
+fn comparison(op: &str, column: ScalarExpr, value: ScalarExpr) -> ScalarExpr {
+    ScalarExpr::FunctionCall(op.to_string(), vec![column, value])
+}
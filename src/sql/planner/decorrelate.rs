@@ -0,0 +1,201 @@
+use super::{AggregateExpr, Column, Plan, ScalarExpr};
+use crate::core::{sqlstate, ErrorKind, SQLError};
+
+/// Decorrelate the plan of a subquery used by a semi join.
+///
+/// Correlated subqueries reference the columns of the outer query with
+/// `ScalarExpr::OuterColumn`. The filters containing outer columns are
+/// pulled up to the top of the subquery plan, so the subquery itself is
+/// uncorrelated and the pulled predicates can be evaluated as the join
+/// conditions.
+///
+/// Returns the uncorrelated plan and the pulled predicates, the `Column`s
+/// in the predicates reference the output of the returned plan.
+///
+/// Since the predicates are pulled through `Distinct`, the result is only
+/// valid for semi joins, where duplicates don't matter.
+pub fn decorrelate_subquery(plan: Plan) -> Result<(Plan, Vec<ScalarExpr>), SQLError> {
+    match plan {
+        Plan::Filter { predicate, input } => {
+            let (input, mut predicates) = decorrelate_subquery(*input)?;
+            if predicate.has_outer_column() {
+                predicates.push(predicate);
+                Ok((input, predicates))
+            } else {
+                Ok((
+                    Plan::Filter {
+                        predicate,
+                        input: Box::new(input),
+                    },
+                    predicates,
+                ))
+            }
+        }
+
+        Plan::Project {
+            mut projections,
+            input,
+        } => {
+            let (input, predicates) = decorrelate_subquery(*input)?;
+
+            // The columns referenced by the pulled predicates should be kept
+            // in the output of the projection.
+            let predicates = predicates
+                .iter()
+                .map(|predicate| {
+                    predicate.rewrite_columns(
+                        &mut |column| {
+                            let index = projections
+                                .iter()
+                                .position(|index| *index == column.index)
+                                .unwrap_or_else(|| {
+                                    projections.push(column.index);
+                                    projections.len() - 1
+                                });
+                            ScalarExpr::Column(Column { index })
+                        },
+                        &mut |column| ScalarExpr::OuterColumn(column.clone()),
+                    )
+                })
+                .collect();
+
+            Ok((
+                Plan::Project {
+                    projections,
+                    input: Box::new(input),
+                },
+                predicates,
+            ))
+        }
+
+        // The new columns are appended by `Map`, so the column references
+        // of the pulled predicates are still valid.
+        Plan::Map { scalars, input } if !scalars.iter().any(ScalarExpr::has_outer_column) => {
+            let (input, predicates) = decorrelate_subquery(*input)?;
+            Ok((
+                Plan::Map {
+                    scalars,
+                    input: Box::new(input),
+                },
+                predicates,
+            ))
+        }
+
+        Plan::Distinct { input } => {
+            let (input, predicates) = decorrelate_subquery(*input)?;
+            Ok((
+                Plan::Distinct {
+                    input: Box::new(input),
+                },
+                predicates,
+            ))
+        }
+
+        // The order doesn't matter to the semi join
+        Plan::Sort { input, .. } => decorrelate_subquery(*input),
+
+        plan => {
+            if has_outer_column(&plan) {
+                Err(
+                    SQLError::new(ErrorKind::PlannerError, "unsupported correlated subquery")
+                        .with_code(sqlstate::FEATURE_NOT_SUPPORTED),
+                )
+            } else {
+                Ok((plan, vec![]))
+            }
+        }
+    }
+}
+
+/// Check if the plan references any column of the outer query.
+fn has_outer_column(plan: &Plan) -> bool {
+    match plan {
+        Plan::Get { .. }
+        | Plan::IndexScan { .. }
+        | Plan::DDL(_)
+        | Plan::DML(..)
+        | Plan::CreateTableAs { .. }
+        | Plan::Explain { .. }
+        | Plan::Use(_)
+        | Plan::SetVariable(..) => false,
+        Plan::Map { scalars, input } => {
+            scalars.iter().any(ScalarExpr::has_outer_column) || has_outer_column(input)
+        }
+        Plan::Project { input, .. } | Plan::Distinct { input } => has_outer_column(input),
+        Plan::Sort { order_by, input } => {
+            order_by.iter().any(|order| order.expr.has_outer_column()) || has_outer_column(input)
+        }
+        Plan::Limit {
+            limit,
+            offset,
+            input,
+        } => {
+            limit
+                .iter()
+                .chain(offset.iter())
+                .any(ScalarExpr::has_outer_column)
+                || has_outer_column(input)
+        }
+        Plan::Values { rows } => rows.iter().flatten().any(ScalarExpr::has_outer_column),
+        Plan::Unnest { arrays } => arrays.iter().any(ScalarExpr::has_outer_column),
+        Plan::Filter { predicate, input } => {
+            predicate.has_outer_column() || has_outer_column(input)
+        }
+        Plan::Join { keys, left, right } => {
+            keys.iter()
+                .any(|(left, right)| left.has_outer_column() || right.has_outer_column())
+                || has_outer_column(left)
+                || has_outer_column(right)
+        }
+        Plan::LeftOuterJoin {
+            condition,
+            left,
+            right,
+        } => condition.has_outer_column() || has_outer_column(left) || has_outer_column(right),
+        Plan::UnionAll { left, right } => has_outer_column(left) || has_outer_column(right),
+        Plan::SemiJoin {
+            left_key,
+            conditions,
+            left,
+            right,
+            ..
+        } => {
+            left_key
+                .as_ref()
+                .map_or(false, ScalarExpr::has_outer_column)
+                || conditions.iter().any(ScalarExpr::has_outer_column)
+                || has_outer_column(left)
+                || has_outer_column(right)
+        }
+        // The outer columns of subquery reference the parameters
+        Plan::Apply {
+            parameters, input, ..
+        } => parameters.iter().any(ScalarExpr::has_outer_column) || has_outer_column(input),
+        Plan::Window {
+            window_exprs,
+            input,
+        } => {
+            window_exprs.iter().any(|window_expr| {
+                window_expr
+                    .args
+                    .iter()
+                    .chain(window_expr.partition_by.iter())
+                    .chain(window_expr.order_by.iter().map(|order_by| &order_by.expr))
+                    .any(ScalarExpr::has_outer_column)
+            }) || has_outer_column(input)
+        }
+        Plan::Aggregate {
+            group_by,
+            aggregates,
+            input,
+            ..
+        } => {
+            group_by.iter().any(ScalarExpr::has_outer_column)
+                || aggregates
+                    .iter()
+                    .flat_map(AggregateExpr::scalars)
+                    .any(ScalarExpr::has_outer_column)
+                || has_outer_column(input)
+        }
+    }
+}
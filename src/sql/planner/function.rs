@@ -0,0 +1,392 @@
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    DataType, Expr, FunctionArg, FunctionArgExpr, Ident, ObjectName, OperateFunctionArg, Query,
+    SelectItem, SetExpr, Statement, TableFactor, Value, VisitMut, VisitorMut,
+};
+
+use crate::{
+    catalog::{
+        defs::{FunctionBody, FunctionDefinition},
+        wasm_function_name, Catalog,
+    },
+    core::{sqlstate, ErrorKind, SQLError},
+    sql::{
+        expression::{
+            function::ScalarFunctionRegistry, window::WindowFunctionKind, SequenceFunction,
+            SpecialForm,
+        },
+        parser::parse_expr,
+        session::context::QueryContext,
+    },
+};
+
+/// Maximum depth of the functions called by the bodies of functions, which
+/// stops the inlining of recursive functions.
+const MAX_INLINE_DEPTH: usize = 32;
+
+/// Whether the name is taken by a builtin function, which can't be redefined.
+pub fn is_builtin_function(ctx: &QueryContext, name: &str) -> bool {
+    ctx.catalog().scalar_functions.contains(name)
+        || ScalarFunctionRegistry::catalog(
+            ctx.catalog.clone(),
+            ctx.current_database.clone(),
+            ctx.current_schema.clone(),
+            ctx.activity.clone(),
+        )
+        .contains(name)
+        || ctx.catalog().aggregate_functions.contains(name)
+        || WindowFunctionKind::ranking(name).is_some()
+        || SpecialForm::from_name(name).is_some()
+        || SequenceFunction::from_name(name).is_some()
+        || name == "grouping"
+}
+
+/// Build the body of function stored in catalog from the expression, the
+/// arguments are replaced by the parameters cast to their types, and the
+/// result is cast to the return type.
+pub fn build_function_body(
+    args: &[OperateFunctionArg],
+    return_type: &DataType,
+    body: &Expr,
+) -> Result<String, SQLError> {
+    let mut body = body.clone();
+    let mut builder = BodyBuilder { args, error: None };
+    if let ControlFlow::Break(()) = body.visit(&mut builder) {
+        return Err(builder.error.unwrap());
+    }
+
+    Ok(format!("CAST(({}) AS {})", body, return_type))
+}
+
+struct BodyBuilder<'a> {
+    args: &'a [OperateFunctionArg],
+    error: Option<SQLError>,
+}
+
+impl BodyBuilder<'_> {
+    fn parameter(&self, index: usize) -> Expr {
+        Expr::Cast {
+            expr: Box::new(Expr::Value(Value::Placeholder(format!("${}", index + 1)))),
+            data_type: self.args[index].data_type.clone(),
+            format: None,
+        }
+    }
+
+    fn resolve(&self, expr: &Expr) -> Result<Option<Expr>, SQLError> {
+        match expr {
+            Expr::Identifier(ident) => {
+                let index = self.args.iter().position(|arg| {
+                    arg.name
+                        .as_ref()
+                        .map_or(false, |name| ident_name(name) == ident_name(ident))
+                });
+                match index {
+                    Some(index) => Ok(Some(self.parameter(index))),
+                    None => Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!("column \"{}\" does not exist", ident.value),
+                    )
+                    .with_code(sqlstate::UNDEFINED_COLUMN)),
+                }
+            }
+            Expr::CompoundIdentifier(idents) => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "missing FROM-clause entry for table \"{}\"",
+                    idents[0].value
+                ),
+            )
+            .with_code(sqlstate::UNDEFINED_TABLE)),
+            Expr::Value(Value::Placeholder(placeholder)) => {
+                let index = placeholder
+                    .strip_prefix('$')
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .filter(|index| (1..=self.args.len()).contains(index))
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::PlannerError,
+                            format!("there is no parameter {}", placeholder),
+                        )
+                        .with_code(sqlstate::UNDEFINED_PARAMETER)
+                    })?;
+                Ok(Some(self.parameter(index - 1)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl VisitorMut for BodyBuilder<'_> {
+    type Break = ();
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        if let Expr::InSubquery { .. } | Expr::Exists { .. } | Expr::Subquery(_) = expr {
+            self.error = Some(
+                SQLError::new(
+                    ErrorKind::PlannerError,
+                    "subquery is not supported in function body",
+                )
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED),
+            );
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match self.resolve(expr) {
+            Ok(Some(parameter)) => *expr = parameter,
+            Ok(None) => {}
+            Err(err) => {
+                self.error = Some(err);
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Replace the calls of the functions in catalog by their bodies, with the
+/// parameters replaced by the arguments.
+pub fn inline_functions<V: VisitMut>(
+    node: &mut V,
+    catalog: &Catalog,
+    current_database: &str,
+    current_schema: &str,
+) -> Result<(), SQLError> {
+    inline(node, catalog, current_database, current_schema, 0)
+}
+
+fn inline<V: VisitMut>(
+    node: &mut V,
+    catalog: &Catalog,
+    current_database: &str,
+    current_schema: &str,
+    depth: usize,
+) -> Result<(), SQLError> {
+    let mut inliner = FunctionInliner {
+        catalog,
+        current_database,
+        current_schema,
+        depth,
+        error: None,
+    };
+    match node.visit(&mut inliner) {
+        ControlFlow::Break(()) => Err(inliner.error.unwrap()),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+struct FunctionInliner<'a> {
+    catalog: &'a Catalog,
+    current_database: &'a str,
+    current_schema: &'a str,
+    depth: usize,
+    error: Option<SQLError>,
+}
+
+impl FunctionInliner<'_> {
+    fn find_function(&self, name: &[Ident]) -> Option<&FunctionDefinition> {
+        let (schema_name, function_name) = qualify_function_name(self.current_schema, name)?;
+        self.catalog
+            .find_function_by_name(self.current_database, &schema_name, &function_name)
+    }
+
+    fn inline_call(&self, expr: &Expr) -> Result<Option<Expr>, SQLError> {
+        let Expr::Function(func) = expr else {
+            return Ok(None);
+        };
+        let Some(function_def) = self.find_function(&func.name.0) else {
+            return Ok(None);
+        };
+
+        let not_aggregate = |clause: &str| {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "{} specified, but {} is not an aggregate function",
+                    clause, func.name
+                ),
+            )
+            .with_code(sqlstate::WRONG_OBJECT_TYPE)
+        };
+        if func.distinct {
+            return Err(not_aggregate("DISTINCT"));
+        }
+        if func.filter.is_some() {
+            return Err(not_aggregate("FILTER"));
+        }
+        if !func.order_by.is_empty() {
+            return Err(not_aggregate("ORDER BY"));
+        }
+        if func.over.is_some() {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "OVER specified, but {} is not a window function nor an aggregate function",
+                    func.name
+                ),
+            )
+            .with_code(sqlstate::WRONG_OBJECT_TYPE));
+        }
+
+        let args = func
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) => Ok(arg.clone()),
+                _ => Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!(
+                        "argument {} of function {} is not supported",
+                        arg, func.name
+                    ),
+                )
+                .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if args.len() != function_def.arg_types.len() {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "function {}({}) does not exist",
+                    func.name,
+                    vec!["unknown"; args.len()].join(", ")
+                ),
+            )
+            .with_code(sqlstate::UNDEFINED_FUNCTION)
+            .with_hint(
+                "No function matches the given name and argument types. You might need to add explicit type casts.",
+            ));
+        }
+        if self.depth >= MAX_INLINE_DEPTH {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("function {} is nested too deeply", func.name),
+            )
+            .with_code(sqlstate::PROGRAM_LIMIT_EXCEEDED));
+        }
+
+        let body = match &function_def.body {
+            FunctionBody::Sql(body) => body,
+            FunctionBody::Wasm(_) => {
+                // Call the function registered by the catalog instead
+                let (schema_name, _) = qualify_function_name(self.current_schema, &func.name.0)
+                    .expect("function is found by name");
+                let mut call = func.clone();
+                call.name = ObjectName(vec![Ident::new(wasm_function_name(
+                    self.current_database,
+                    &schema_name,
+                    &function_def.name,
+                ))]);
+                call.args = args
+                    .into_iter()
+                    .map(|arg| FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)))
+                    .collect();
+                return Ok(Some(Expr::Function(call)));
+            }
+        };
+        let mut body = parse_expr(body)?;
+        inline(
+            &mut body,
+            self.catalog,
+            self.current_database,
+            self.current_schema,
+            self.depth + 1,
+        )?;
+        let _ = sqlparser::ast::visit_expressions_mut(&mut body, |expr| {
+            if let Expr::Value(Value::Placeholder(placeholder)) = expr {
+                // The placeholders are validated while creating the function
+                let index = placeholder[1..].parse::<usize>().unwrap() - 1;
+                *expr = Expr::Nested(Box::new(args[index].clone()));
+            }
+            ControlFlow::<()>::Continue(())
+        });
+
+        Ok(Some(Expr::Nested(Box::new(body))))
+    }
+
+    /// Name the unnamed select items calling functions after the functions,
+    /// which would be lost after inlining, including the ones of CTEs and
+    /// derived tables.
+    fn name_select_items(&self, query: &mut Query) {
+        if let Some(with) = &mut query.with {
+            for cte in with.cte_tables.iter_mut() {
+                self.name_select_items(&mut cte.query);
+            }
+        }
+        self.name_set_expr_items(&mut query.body);
+    }
+
+    fn name_set_expr_items(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in select.projection.iter_mut() {
+                    let SelectItem::UnnamedExpr(Expr::Function(func)) = item else {
+                        continue;
+                    };
+                    if self.find_function(&func.name.0).is_none() {
+                        continue;
+                    }
+                    let alias = Ident::new(func.name.0.last().unwrap().value.to_lowercase());
+                    let expr = Expr::Function(func.clone());
+                    *item = SelectItem::ExprWithAlias { expr, alias };
+                }
+                for table in select.from.iter_mut() {
+                    for factor in std::iter::once(&mut table.relation)
+                        .chain(table.joins.iter_mut().map(|join| &mut join.relation))
+                    {
+                        if let TableFactor::Derived { subquery, .. } = factor {
+                            self.name_select_items(subquery);
+                        }
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, .. } => self.name_set_expr_items(left),
+            SetExpr::Query(query) => self.name_select_items(query),
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for FunctionInliner<'_> {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, statement: &mut Statement) -> ControlFlow<Self::Break> {
+        if let Statement::Query(query) = statement {
+            self.name_select_items(query);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match self.inline_call(expr) {
+            Ok(Some(body)) => *expr = body,
+            Ok(None) => {}
+            Err(err) => {
+                self.error = Some(err);
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Resolve the function name to (schema_name, function_name), `None` if the
+/// name is invalid.
+pub fn qualify_function_name(current_schema: &str, name: &[Ident]) -> Option<(String, String)> {
+    match name {
+        [function_name] => Some((current_schema.to_string(), ident_name(function_name))),
+        [schema_name, function_name] => Some((ident_name(schema_name), ident_name(function_name))),
+        _ => None,
+    }
+}
+
+/// Names are case-insensitive unless quoted.
+fn ident_name(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
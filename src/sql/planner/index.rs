@@ -0,0 +1,565 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use super::{cardinality::estimate_rows, AggregateExpr, Column, Plan, ScalarExpr};
+use crate::{
+    catalog::{defs::TableDefinition, pg_catalog::find_system_table, DatabaseCatalog},
+    core::SQLError,
+};
+
+/// Key range of an index scan. The leading key columns are equal to `prefix`,
+/// and the next key column is bounded by `lower` and `upper`, with a flag
+/// indicating if the bound is inclusive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyRange {
+    pub prefix: Vec<ScalarExpr>,
+    pub lower: Option<(ScalarExpr, bool)>,
+    pub upper: Option<(ScalarExpr, bool)>,
+}
+
+impl KeyRange {
+    pub fn scalars_mut(&mut self) -> impl Iterator<Item = &mut ScalarExpr> {
+        self.prefix
+            .iter_mut()
+            .chain(self.lower.iter_mut().map(|(scalar, _)| scalar))
+            .chain(self.upper.iter_mut().map(|(scalar, _)| scalar))
+    }
+
+    /// Number of the leading key columns restricted by the range.
+    pub fn key_len(&self) -> usize {
+        self.prefix.len() + (self.lower.is_some() || self.upper.is_some()) as usize
+    }
+}
+
+impl Display for KeyRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut conditions = self
+            .prefix
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("key{} = {}", i, value))
+            .collect::<Vec<_>>();
+        let column = self.prefix.len();
+        if let Some((value, inclusive)) = &self.lower {
+            let op = if *inclusive { ">=" } else { ">" };
+            conditions.push(format!("key{} {} {}", column, op, value));
+        }
+        if let Some((value, inclusive)) = &self.upper {
+            let op = if *inclusive { "<=" } else { "<" };
+            conditions.push(format!("key{} {} {}", column, op, value));
+        }
+        write!(f, "{}", conditions.join(", "))
+    }
+}
+
+/// A predicate in the form of `column op value`.
+struct Comparison {
+    column: usize,
+    op: &'static str,
+    value: ScalarExpr,
+}
+
+/// Replace the filters on top of a table scan with an index scan, if any of the
+/// predicates can be answered by an index of the table. The remaining predicates
+/// are still evaluated by filters on top of it.
+pub fn plan_index_scan(plan: Plan, catalog: &DatabaseCatalog) -> Result<Plan, SQLError> {
+    let mut predicates = vec![];
+    let mut input = plan;
+    while let Plan::Filter {
+        predicate,
+        input: child,
+    } = input
+    {
+        split_conjunctions(predicate, &mut predicates);
+        input = *child;
+    }
+    // Predicates are collected from top to bottom
+    predicates.reverse();
+
+    // The predicates reference the columns of table only if they are not pruned
+    let Plan::Get { schema_name, table_name, projections: None } = &input else {
+        return Ok(build_filters(input, predicates));
+    };
+    let Some(table_def) = catalog.find_table_by_name(schema_name, table_name)? else {
+        return Ok(build_filters(input, predicates));
+    };
+
+    let comparisons = predicates
+        .iter()
+        .map(|predicate| as_comparison(predicate, &table_def))
+        .collect::<Vec<_>>();
+
+    let mut candidates = vec![];
+    for index in table_def.indexes.iter() {
+        let mut used = vec![];
+        let mut range = KeyRange {
+            prefix: vec![],
+            lower: None,
+            upper: None,
+        };
+        for column in index.columns.iter() {
+            let Some(position) = find_comparison(&comparisons, &used, *column, &["="]) else {
+                break;
+            };
+            used.push(position);
+            range
+                .prefix
+                .push(comparisons[position].as_ref().unwrap().value.clone());
+        }
+        if let Some(column) = index.columns.get(range.prefix.len()) {
+            if let Some(position) = find_comparison(&comparisons, &used, *column, &[">", ">="]) {
+                used.push(position);
+                let comparison = comparisons[position].as_ref().unwrap();
+                range.lower = Some((comparison.value.clone(), comparison.op == ">="));
+            }
+            if let Some(position) = find_comparison(&comparisons, &used, *column, &["<", "<="]) {
+                used.push(position);
+                let comparison = comparisons[position].as_ref().unwrap();
+                range.upper = Some((comparison.value.clone(), comparison.op == "<="));
+            }
+        }
+
+        if !used.is_empty() {
+            let score = (range.prefix.len(), used.len());
+            let plan = Plan::IndexScan {
+                schema_name: schema_name.clone(),
+                table_name: table_name.clone(),
+                index_name: index.name.clone(),
+                columns: index.columns.clone(),
+                range,
+                index_only: false,
+                projections: None,
+            };
+            candidates.push((plan, used, score));
+        }
+    }
+
+    // Choose the index with the fewest estimated rows if the table has been analyzed,
+    // otherwise the one restricting the most key columns, with equality conditions preferred
+    let best = if table_def.statistics.is_some() {
+        candidates.into_iter().min_by(|(a, _, _), (b, _, _)| {
+            let rows = |plan| estimate_rows(plan, catalog).unwrap_or(f64::INFINITY);
+            rows(a).total_cmp(&rows(b))
+        })
+    } else {
+        // The first one is chosen among the same scores
+        candidates
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, _, score)| *score)
+    };
+    let Some((plan, used, _)) = best else {
+        return Ok(build_filters(input, predicates));
+    };
+
+    let residual = predicates
+        .into_iter()
+        .enumerate()
+        .filter(|(position, _)| !used.contains(position))
+        .map(|(_, predicate)| predicate)
+        .collect();
+
+    Ok(build_filters(plan, residual))
+}
+
+pub fn split_conjunctions(predicate: ScalarExpr, predicates: &mut Vec<ScalarExpr>) {
+    match predicate {
+        ScalarExpr::FunctionCall(name, args) if name == "and" && args.len() == 2 => {
+            // Keep the original order after reversed
+            for arg in args.into_iter().rev() {
+                split_conjunctions(arg, predicates);
+            }
+        }
+        predicate => predicates.push(predicate),
+    }
+}
+
+pub fn build_filters(input: Plan, predicates: Vec<ScalarExpr>) -> Plan {
+    predicates
+        .into_iter()
+        .fold(input, |input, predicate| Plan::Filter {
+            predicate,
+            input: Box::new(input),
+        })
+}
+
+/// Match the predicate with `column op value`, where the value is a constant of
+/// the same type as the column, or a parameter.
+fn as_comparison(predicate: &ScalarExpr, table_def: &TableDefinition) -> Option<Comparison> {
+    let ScalarExpr::FunctionCall(name, args) = predicate else {
+        return None;
+    };
+    let (column, value, flipped) = match args.as_slice() {
+        [ScalarExpr::Column(Column { index }), value] => (*index, value, false),
+        [value, ScalarExpr::Column(Column { index })] => (*index, value, true),
+        _ => return None,
+    };
+
+    let op = match (name.as_str(), flipped) {
+        ("=", _) => "=",
+        ("<", false) | (">", true) => "<",
+        ("<=", false) | (">=", true) => "<=",
+        (">", false) | ("<", true) => ">",
+        (">=", false) | ("<=", true) => ">=",
+        _ => return None,
+    };
+
+    let column_type = &table_def.columns.get(column)?.data_type;
+    match value {
+        ScalarExpr::Literal(datum) if datum.typ() == *column_type => {}
+        ScalarExpr::Parameter(_) => {}
+        _ => return None,
+    }
+
+    Some(Comparison {
+        column,
+        op,
+        value: value.clone(),
+    })
+}
+
+fn find_comparison(
+    comparisons: &[Option<Comparison>],
+    used: &[usize],
+    column: usize,
+    ops: &[&str],
+) -> Option<usize> {
+    (0..comparisons.len()).find(|position| {
+        !used.contains(position)
+            && comparisons[*position]
+                .as_ref()
+                .map_or(false, |c| c.column == column && ops.contains(&c.op))
+    })
+}
+
+/// Turn the index scans into index-only scans, if all the columns of the table
+/// referenced by the operators above are contained in the index.
+pub fn plan_index_only_scan(plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<(), SQLError> {
+    let required = (0..output_width(plan, catalog)?).collect();
+    mark_index_only(plan, required, catalog)
+}
+
+/// `required` is the set of output columns used by the parent operators.
+fn mark_index_only(
+    plan: &mut Plan,
+    mut required: BTreeSet<usize>,
+    catalog: &DatabaseCatalog,
+) -> Result<(), SQLError> {
+    match plan {
+        Plan::IndexScan {
+            schema_name,
+            table_name,
+            index_name,
+            columns,
+            range,
+            index_only,
+            projections,
+        } => {
+            if let Some(projections) = projections {
+                required = required.iter().map(|column| projections[*column]).collect();
+            }
+            let covers = |columns: &[usize]| required.iter().all(|column| columns.contains(column));
+            if covers(columns) {
+                *index_only = true;
+                return Ok(());
+            }
+
+            // Switch to a covering index with the same leading key columns, the
+            // range can be used as is.
+            let leading = &columns[..range.key_len()];
+            let table_def = catalog.find_table_by_name(schema_name, table_name)?;
+            if let Some(index) = table_def
+                .iter()
+                .flat_map(|table| table.indexes.iter())
+                .find(|index| index.columns.starts_with(leading) && covers(&index.columns))
+            {
+                *index_name = index.name.clone();
+                *columns = index.columns.clone();
+                *index_only = true;
+            }
+            Ok(())
+        }
+        Plan::Project { projections, input } => {
+            let required = required.iter().map(|i| projections[*i]).collect();
+            mark_index_only(input, required, catalog)
+        }
+        Plan::Filter { predicate, input } => {
+            collect_columns(predicate, &mut required);
+            mark_index_only(input, required, catalog)
+        }
+        Plan::Map { scalars, input } => {
+            let width = output_width(input, catalog)?;
+            let mut input_required = BTreeSet::new();
+            for column in required {
+                if column < width {
+                    input_required.insert(column);
+                } else {
+                    collect_columns(&scalars[column - width], &mut input_required);
+                }
+            }
+            mark_index_only(input, input_required, catalog)
+        }
+        Plan::Window {
+            window_exprs,
+            input,
+        } => {
+            let width = output_width(input, catalog)?;
+            required.retain(|column| *column < width);
+            for window_expr in window_exprs.iter() {
+                for scalar in window_expr
+                    .args
+                    .iter()
+                    .chain(window_expr.partition_by.iter())
+                    .chain(window_expr.order_by.iter().map(|order| &order.expr))
+                {
+                    collect_columns(scalar, &mut required);
+                }
+            }
+            mark_index_only(input, required, catalog)
+        }
+        Plan::Apply {
+            parameters,
+            subquery,
+            input,
+            ..
+        } => {
+            let width = output_width(input, catalog)?;
+            required.retain(|column| *column < width);
+            for scalar in parameters.iter() {
+                collect_columns(scalar, &mut required);
+            }
+            plan_index_only_scan(subquery, catalog)?;
+            mark_index_only(input, required, catalog)
+        }
+        Plan::Aggregate {
+            group_by,
+            aggregates,
+            input,
+            ..
+        } => {
+            let mut required = BTreeSet::new();
+            for scalar in group_by
+                .iter()
+                .chain(aggregates.iter().flat_map(AggregateExpr::scalars))
+            {
+                collect_columns(scalar, &mut required);
+            }
+            mark_index_only(input, required, catalog)
+        }
+        Plan::Distinct { input } => {
+            let required = (0..output_width(input, catalog)?).collect();
+            mark_index_only(input, required, catalog)
+        }
+        Plan::Sort { order_by, input } => {
+            for order in order_by.iter() {
+                collect_columns(&order.expr, &mut required);
+            }
+            mark_index_only(input, required, catalog)
+        }
+        Plan::Limit { input, .. } => mark_index_only(input, required, catalog),
+        Plan::Join { keys, left, right } => {
+            // Keys are evaluated with the combined tuple of both sides
+            let width = output_width(left, catalog)?;
+            for (left_key, right_key) in keys.iter() {
+                collect_columns(left_key, &mut required);
+                collect_columns(right_key, &mut required);
+            }
+            let right_required = required
+                .iter()
+                .filter(|column| **column >= width)
+                .map(|column| column - width)
+                .collect();
+            required.retain(|column| *column < width);
+            mark_index_only(left, required, catalog)?;
+            mark_index_only(right, right_required, catalog)
+        }
+        Plan::LeftOuterJoin {
+            condition,
+            left,
+            right,
+        } => {
+            // Condition is evaluated with the combined tuple of both sides
+            let width = output_width(left, catalog)?;
+            collect_columns(condition, &mut required);
+            let right_required = required
+                .iter()
+                .filter(|column| **column >= width)
+                .map(|column| column - width)
+                .collect();
+            required.retain(|column| *column < width);
+            mark_index_only(left, required, catalog)?;
+            mark_index_only(right, right_required, catalog)
+        }
+        Plan::SemiJoin {
+            left_key,
+            conditions,
+            left,
+            right,
+            ..
+        } => {
+            // Conditions are evaluated with the combined tuple of both sides
+            let width = output_width(left, catalog)?;
+            let mut combined = BTreeSet::new();
+            for scalar in left_key.iter().chain(conditions.iter()) {
+                collect_columns(scalar, &mut combined);
+            }
+            let mut right_required = combined
+                .iter()
+                .filter(|column| **column >= width)
+                .map(|column| column - width)
+                .collect::<BTreeSet<_>>();
+            if left_key.is_some() {
+                right_required.insert(0);
+            }
+            required.extend(combined.into_iter().filter(|column| *column < width));
+            mark_index_only(left, required, catalog)?;
+            mark_index_only(right, right_required, catalog)
+        }
+        // Both sides emit the same columns
+        Plan::UnionAll { left, right } => {
+            mark_index_only(left, required.clone(), catalog)?;
+            mark_index_only(right, required, catalog)
+        }
+        // The query of `CREATE TABLE ... AS` is planned before being wrapped
+        Plan::Get { .. }
+        | Plan::Values { .. }
+        | Plan::Unnest { .. }
+        | Plan::DDL(_)
+        | Plan::DML(..)
+        | Plan::CreateTableAs { .. }
+        | Plan::Explain { .. }
+        | Plan::Use(_)
+        | Plan::SetVariable(..) => Ok(()),
+    }
+}
+
+/// Number of columns in the output tuples of the plan.
+pub fn output_width(plan: &Plan, catalog: &DatabaseCatalog) -> Result<usize, SQLError> {
+    Ok(match plan {
+        Plan::Get {
+            projections: Some(projections),
+            ..
+        }
+        | Plan::IndexScan {
+            projections: Some(projections),
+            ..
+        } => projections.len(),
+        Plan::Get {
+            schema_name,
+            table_name,
+            ..
+        }
+        | Plan::IndexScan {
+            schema_name,
+            table_name,
+            ..
+        } => match find_system_table(schema_name, table_name) {
+            Some(table_def) => table_def.columns.len(),
+            None => catalog
+                .find_table_by_name(schema_name, table_name)?
+                .map_or(0, |table_def| table_def.columns.len()),
+        },
+        Plan::Map { scalars, input } => output_width(input, catalog)? + scalars.len(),
+        Plan::Project { projections, .. } => projections.len(),
+        Plan::Filter { input, .. }
+        | Plan::Distinct { input }
+        | Plan::Sort { input, .. }
+        | Plan::Limit { input, .. } => output_width(input, catalog)?,
+        Plan::Join { left, right, .. } | Plan::LeftOuterJoin { left, right, .. } => {
+            output_width(left, catalog)? + output_width(right, catalog)?
+        }
+        Plan::Aggregate {
+            group_by,
+            grouping_sets,
+            aggregates,
+            ..
+        } => group_by.len() + usize::from(!grouping_sets.is_empty()) + aggregates.len(),
+        Plan::SemiJoin { left, .. } | Plan::UnionAll { left, .. } => output_width(left, catalog)?,
+        Plan::Apply { input, .. } => output_width(input, catalog)? + 1,
+        Plan::Window {
+            window_exprs,
+            input,
+        } => output_width(input, catalog)? + window_exprs.len(),
+        Plan::Values { rows } => rows.first().map_or(0, Vec::len),
+        Plan::Unnest { arrays } => arrays.len(),
+        Plan::DDL(_)
+        | Plan::DML(..)
+        | Plan::CreateTableAs { .. }
+        | Plan::Explain { .. }
+        | Plan::Use(_)
+        | Plan::SetVariable(..) => 0,
+    })
+}
+
+/// The table and the index of column, from which the output column of plan is
+/// passed through without being computed. `None` if it's computed.
+pub fn source_column(
+    column: usize,
+    plan: &Plan,
+    catalog: &DatabaseCatalog,
+) -> Option<(TableDefinition, usize)> {
+    match plan {
+        Plan::Get {
+            schema_name,
+            table_name,
+            projections,
+        }
+        | Plan::IndexScan {
+            schema_name,
+            table_name,
+            projections,
+            ..
+        } => {
+            let column = match projections {
+                Some(projections) => *projections.get(column)?,
+                None => column,
+            };
+            let table_def = catalog.find_table_by_name(schema_name, table_name).ok()??;
+            Some((table_def, column))
+        }
+        Plan::Filter { input, .. }
+        | Plan::Distinct { input }
+        | Plan::Sort { input, .. }
+        | Plan::Limit { input, .. }
+        | Plan::SemiJoin { left: input, .. } => source_column(column, input, catalog),
+        Plan::Map { input, .. } | Plan::Apply { input, .. } | Plan::Window { input, .. } => {
+            // The appended columns are computed
+            if column < output_width(input, catalog).ok()? {
+                source_column(column, input, catalog)
+            } else {
+                None
+            }
+        }
+        Plan::Project { projections, input } => {
+            source_column(*projections.get(column)?, input, catalog)
+        }
+        Plan::Join { left, right, .. } | Plan::LeftOuterJoin { left, right, .. } => {
+            let width = output_width(left, catalog).ok()?;
+            if column < width {
+                source_column(column, left, catalog)
+            } else {
+                source_column(column - width, right, catalog)
+            }
+        }
+        Plan::Aggregate {
+            group_by, input, ..
+        } => match group_by.get(column)? {
+            ScalarExpr::Column(Column { index }) => source_column(*index, input, catalog),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn collect_columns(scalar: &ScalarExpr, columns: &mut BTreeSet<usize>) {
+    match scalar {
+        ScalarExpr::FunctionCall(_, args) => {
+            for arg in args.iter() {
+                collect_columns(arg, columns);
+            }
+        }
+        ScalarExpr::Column(Column { index }) => {
+            columns.insert(*index);
+        }
+        ScalarExpr::OuterColumn(_) | ScalarExpr::Literal(_) | ScalarExpr::Parameter(_) => {}
+    }
+}
@@ -1,13 +1,25 @@
 use std::fmt::Display;
 
-use super::runtime::{DDLJob, DMLJob};
-use crate::core::Datum;
+use self::index::KeyRange;
+use super::{
+    expression::window::WindowFrame,
+    runtime::{DDLJob, DMLJob},
+};
+use crate::core::{sqlstate, Datum, ErrorKind, SQLError, SortOrder};
 
 pub mod aggregate;
 pub mod bind_context;
 pub mod binder;
+pub mod cardinality;
+pub mod decorrelate;
+pub mod function;
+pub mod index;
+pub mod optimizer;
+pub mod physical;
 pub mod scalar;
 pub mod scope;
+pub mod subquery;
+pub mod window;
 
 #[derive(Debug)]
 pub struct QualifiedObjectName {
@@ -15,11 +27,27 @@ pub struct QualifiedObjectName {
 }
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Plan {
     Get {
         schema_name: String,
         table_name: String,
+        /// Columns of the table to emit, all of them if `None`.
+        projections: Option<Vec<usize>>,
+    },
+    /// Scan the tuples of a table in the key range of an index.
+    IndexScan {
+        schema_name: String,
+        table_name: String,
+        index_name: String,
+        /// Key columns of the index.
+        columns: Vec<usize>,
+        range: KeyRange,
+        /// Tuples are built from the index entries without reading the heap,
+        /// only the key columns are filled, and the others are NULL.
+        index_only: bool,
+        /// Columns of the table to emit, all of them if `None`.
+        projections: Option<Vec<usize>>,
     },
     Map {
         scalars: Vec<ScalarExpr>,
@@ -33,23 +61,268 @@ pub enum Plan {
         predicate: ScalarExpr,
         input: Box<Plan>,
     },
+    /// Inner join, a cross join if there are no keys.
     Join {
-        // Cross join
+        /// Pairs of expressions on the `left` and `right` side that must be
+        /// equal, evaluated with the combined tuple.
+        keys: Vec<(ScalarExpr, ScalarExpr)>,
         left: Box<Plan>,
         right: Box<Plan>,
     },
+    /// Left outer join, emits the combined tuples satisfying `condition`, and
+    /// the `left` tuples without any match padded with NULLs.
+    LeftOuterJoin {
+        /// Evaluated with the combined tuple of `left` and `right`.
+        condition: ScalarExpr,
+        left: Box<Plan>,
+        right: Box<Plan>,
+    },
+    /// The output is the group keys followed by the aggregates. With grouping
+    /// sets, the keys missing from the grouping set of a row are NULL, and the
+    /// index of its grouping set is emitted after the keys.
     Aggregate {
         group_by: Vec<ScalarExpr>,
-        /// (agg_func_name, arguments)
-        aggregates: Vec<(String, Vec<ScalarExpr>)>,
+        /// Indices of the group keys in each grouping set, empty for plain
+        /// `GROUP BY`.
+        grouping_sets: Vec<Vec<usize>>,
+        aggregates: Vec<AggregateExpr>,
+        input: Box<Plan>,
+    },
+    /// Semi join, emits the tuples of `left` that have a match in `right`.
+    /// It will be an anti join if `anti` is true, i.e. emits the tuples
+    /// that have no match in `right`.
+    ///
+    /// Used by `IN (subquery)` and `EXISTS (subquery)`.
+    SemiJoin {
+        /// Expression evaluated with the `left` tuple, and compared with the
+        /// first column of `right`. `None` for `EXISTS`.
+        left_key: Option<ScalarExpr>,
+        /// Conditions evaluated with the combined tuple of `left` and `right`,
+        /// only the `right` tuples satisfying all of them can be matched.
+        /// These come from the correlated predicates of subqueries.
+        conditions: Vec<ScalarExpr>,
+        anti: bool,
+        left: Box<Plan>,
+        right: Box<Plan>,
+    },
+    /// Evaluate the scalar subquery for each tuple of `input`, and append its
+    /// value to the tuple, which is NULL if the subquery returns no rows.
+    ///
+    /// Used by the subqueries in expressions, e.g. `SELECT (SELECT max(b) FROM
+    /// t2 WHERE t2.a = t1.a) FROM t1`.
+    Apply {
+        /// Expressions evaluated with the `input` tuple, the `OuterColumn`s of
+        /// subquery reference them by position.
+        parameters: Vec<ScalarExpr>,
+        /// If the values of all rows are collected into an array instead, for
+        /// `ARRAY(SELECT ...)`.
+        array: bool,
+        subquery: Box<Plan>,
+        input: Box<Plan>,
+    },
+    /// Evaluate window functions, the results are appended to the input tuples.
+    Window {
+        window_exprs: Vec<WindowExpr>,
+        input: Box<Plan>,
+    },
+    /// Eliminate duplicated tuples, used by `SELECT DISTINCT`.
+    Distinct {
+        input: Box<Plan>,
+    },
+    /// Emit the tuples of `left` followed by the ones of `right`, which have
+    /// the same number of columns. Used by `UNION ALL`, and by `UNION` with
+    /// `Distinct` on top.
+    UnionAll {
+        left: Box<Plan>,
+        right: Box<Plan>,
+    },
+    /// Sort the input tuples on the keys, used by `ORDER BY`.
+    Sort {
+        order_by: Vec<OrderBy>,
         input: Box<Plan>,
     },
+    /// Skip the first `offset` tuples of the input, and emit at most `limit`
+    /// tuples after them, used by `LIMIT`, `OFFSET` and `FETCH FIRST`. The
+    /// counts are constant expressions, no limit or offset if `None`.
+    Limit {
+        limit: Option<ScalarExpr>,
+        offset: Option<ScalarExpr>,
+        input: Box<Plan>,
+    },
+    /// Rows of constant expressions, used by `VALUES` lists.
+    Values {
+        rows: Vec<Vec<ScalarExpr>>,
+    },
+    /// Rows of the elements of arrays, used by `unnest` in `FROM` clause. The
+    /// arrays are zipped, and the shorter ones are padded with NULLs.
+    Unnest {
+        arrays: Vec<ScalarExpr>,
+    },
 
     /// Data definition language (DDL)
     DDL(DDLJob),
-    DML(DMLJob),
-    Explain(String),
+    /// Create table with the columns named by `column_names`, and insert the
+    /// results of `input` into it.
+    CreateTableAs {
+        schema_name: String,
+        table_name: String,
+        column_names: Vec<String>,
+        /// Skip the query if the table already exists.
+        if_not_exists: bool,
+        input: Box<Plan>,
+    },
+    /// Data manipulation language (DML), with the optional `RETURNING` list
+    /// evaluated on the affected tuples.
+    DML(DMLJob, Option<Vec<ScalarExpr>>),
+    /// Show the plan with the options of `EXPLAIN`.
+    Explain {
+        plan: Box<Plan>,
+        options: ExplainOptions,
+    },
     Use(String),
+    /// Set the run-time parameter of session, or reset it to the default
+    /// value if the value is `None`.
+    SetVariable(String, Option<String>),
+}
+
+/// Output format of `EXPLAIN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplainFormat {
+    /// Indented lines of the plan nodes, one row per line.
+    Text,
+    /// A JSON document of the plan tree in a single row.
+    Json,
+}
+
+/// Options of `EXPLAIN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExplainOptions {
+    pub format: ExplainFormat,
+    /// Show the output types of each node.
+    pub verbose: bool,
+    /// Show the estimated rows of each node.
+    pub costs: bool,
+    /// Execute the statement, and show the rows it returned and the time of
+    /// execution.
+    pub analyze: bool,
+}
+
+/// Sort key of `ORDER BY`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expr: ScalarExpr,
+    pub asc: bool,
+    /// NULLs come first by default only in the descending order.
+    pub nulls_first: bool,
+}
+
+impl OrderBy {
+    pub fn sort_order(&self) -> SortOrder {
+        SortOrder {
+            asc: self.asc,
+            nulls_first: self.nulls_first,
+        }
+    }
+}
+
+impl Display for OrderBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.expr, if self.asc { "ASC" } else { "DESC" })?;
+        if self.nulls_first == self.asc {
+            write!(
+                f,
+                " {}",
+                if self.nulls_first {
+                    "NULLS FIRST"
+                } else {
+                    "NULLS LAST"
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Aggregate function call, e.g. `count(*) FILTER (WHERE a > 0)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateExpr {
+    pub func_name: String,
+    pub args: Vec<ScalarExpr>,
+    /// Only the tuples satisfying the filter are aggregated.
+    pub filter: Option<ScalarExpr>,
+    /// The tuples are aggregated in the order of the keys, e.g.
+    /// `string_agg(a, ',' ORDER BY a)`.
+    pub order_by: Vec<OrderBy>,
+}
+
+impl AggregateExpr {
+    /// The arguments followed by the filter and the sort keys.
+    pub fn scalars(&self) -> impl Iterator<Item = &ScalarExpr> {
+        self.args
+            .iter()
+            .chain(self.filter.iter())
+            .chain(self.order_by.iter().map(|order_by| &order_by.expr))
+    }
+
+    pub fn scalars_mut(&mut self) -> impl Iterator<Item = &mut ScalarExpr> {
+        self.args
+            .iter_mut()
+            .chain(self.filter.iter_mut())
+            .chain(self.order_by.iter_mut().map(|order_by| &mut order_by.expr))
+    }
+}
+
+impl Display for AggregateExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}({}", self.func_name, join_display(&self.args))?;
+        if !self.order_by.is_empty() {
+            write!(f, " ORDER BY {}", join_display(&self.order_by))?;
+        }
+        write!(f, ")")?;
+        if let Some(filter) = &self.filter {
+            write!(f, " FILTER (WHERE {})", filter)?;
+        }
+        Ok(())
+    }
+}
+
+/// Window function call, e.g. `rank() OVER (PARTITION BY a ORDER BY b)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowExpr {
+    pub func_name: String,
+    pub args: Vec<ScalarExpr>,
+    pub partition_by: Vec<ScalarExpr>,
+    pub order_by: Vec<OrderBy>,
+    pub frame: WindowFrame,
+}
+
+impl Display for WindowExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}({}) OVER (PARTITION BY {} ORDER BY {}",
+            self.func_name,
+            self.args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.partition_by
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.order_by
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        if self.frame != WindowFrame::default() {
+            write!(f, " {}", self.frame)?;
+        }
+        write!(f, ")")
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -61,7 +334,62 @@ pub struct Column {
 pub enum ScalarExpr {
     FunctionCall(String, Vec<ScalarExpr>),
     Column(Column),
+    /// Column of the outer query, only used in correlated subqueries.
+    OuterColumn(Column),
     Literal(Datum),
+    /// Parameter placeholder of prepared statement, `$1` has index 0.
+    Parameter(usize),
+}
+
+impl ScalarExpr {
+    /// Check if the expression references any column of the outer query.
+    pub fn has_outer_column(&self) -> bool {
+        match self {
+            ScalarExpr::FunctionCall(_, args) => args.iter().any(ScalarExpr::has_outer_column),
+            ScalarExpr::OuterColumn(_) => true,
+            ScalarExpr::Column(_) | ScalarExpr::Literal(_) | ScalarExpr::Parameter(_) => false,
+        }
+    }
+
+    /// Rewrite the column references of the expression with the given functions.
+    pub fn rewrite_columns(
+        &self,
+        column_fn: &mut impl FnMut(&Column) -> ScalarExpr,
+        outer_column_fn: &mut impl FnMut(&Column) -> ScalarExpr,
+    ) -> ScalarExpr {
+        match self {
+            ScalarExpr::FunctionCall(name, args) => ScalarExpr::FunctionCall(
+                name.clone(),
+                args.iter()
+                    .map(|arg| arg.rewrite_columns(column_fn, outer_column_fn))
+                    .collect(),
+            ),
+            ScalarExpr::Column(column) => column_fn(column),
+            ScalarExpr::OuterColumn(column) => outer_column_fn(column),
+            ScalarExpr::Literal(_) | ScalarExpr::Parameter(_) => self.clone(),
+        }
+    }
+
+    /// Replace the parameter placeholders with the given values.
+    pub fn bind_parameters(&mut self, values: &[Datum]) -> Result<(), SQLError> {
+        match self {
+            ScalarExpr::FunctionCall(_, args) => args
+                .iter_mut()
+                .try_for_each(|arg| arg.bind_parameters(values)),
+            ScalarExpr::Parameter(index) => {
+                let value = values.get(*index).ok_or_else(|| {
+                    SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!("there is no parameter ${}", *index + 1),
+                    )
+                    .with_code(sqlstate::UNDEFINED_PARAMETER)
+                })?;
+                *self = ScalarExpr::Literal(value.clone());
+                Ok(())
+            }
+            ScalarExpr::Column(_) | ScalarExpr::OuterColumn(_) | ScalarExpr::Literal(_) => Ok(()),
+        }
+    }
 }
 
 impl Display for ScalarExpr {
@@ -77,7 +405,219 @@ impl Display for ScalarExpr {
                     .join(", ")
             ),
             ScalarExpr::Column(col) => write!(f, "#{}", col.index),
+            ScalarExpr::OuterColumn(col) => write!(f, "outer#{}", col.index),
             ScalarExpr::Literal(v) => write!(f, "{}", v),
+            ScalarExpr::Parameter(index) => write!(f, "${}", index + 1),
+        }
+    }
+}
+
+impl Plan {
+    /// Replace the parameter placeholders in the plan with the given values.
+    pub fn bind_parameters(&mut self, values: &[Datum]) -> Result<(), SQLError> {
+        match self {
+            Plan::Map { scalars, input } => {
+                for scalar in scalars.iter_mut() {
+                    scalar.bind_parameters(values)?;
+                }
+                input.bind_parameters(values)
+            }
+            Plan::Filter { predicate, input } => {
+                predicate.bind_parameters(values)?;
+                input.bind_parameters(values)
+            }
+            Plan::Aggregate {
+                group_by,
+                aggregates,
+                input,
+                ..
+            } => {
+                for scalar in group_by
+                    .iter_mut()
+                    .chain(aggregates.iter_mut().flat_map(AggregateExpr::scalars_mut))
+                {
+                    scalar.bind_parameters(values)?;
+                }
+                input.bind_parameters(values)
+            }
+            Plan::SemiJoin {
+                left_key,
+                conditions,
+                left,
+                right,
+                ..
+            } => {
+                for scalar in left_key.iter_mut().chain(conditions.iter_mut()) {
+                    scalar.bind_parameters(values)?;
+                }
+                left.bind_parameters(values)?;
+                right.bind_parameters(values)
+            }
+            Plan::Window {
+                window_exprs,
+                input,
+            } => {
+                for window_expr in window_exprs.iter_mut() {
+                    for scalar in window_expr
+                        .args
+                        .iter_mut()
+                        .chain(window_expr.partition_by.iter_mut())
+                        .chain(window_expr.order_by.iter_mut().map(|order| &mut order.expr))
+                    {
+                        scalar.bind_parameters(values)?;
+                    }
+                }
+                input.bind_parameters(values)
+            }
+            Plan::Apply {
+                parameters,
+                subquery,
+                input,
+                ..
+            } => {
+                for scalar in parameters.iter_mut() {
+                    scalar.bind_parameters(values)?;
+                }
+                subquery.bind_parameters(values)?;
+                input.bind_parameters(values)
+            }
+            Plan::Join { keys, left, right } => {
+                for (left_key, right_key) in keys.iter_mut() {
+                    left_key.bind_parameters(values)?;
+                    right_key.bind_parameters(values)?;
+                }
+                left.bind_parameters(values)?;
+                right.bind_parameters(values)
+            }
+            Plan::LeftOuterJoin {
+                condition,
+                left,
+                right,
+            } => {
+                condition.bind_parameters(values)?;
+                left.bind_parameters(values)?;
+                right.bind_parameters(values)
+            }
+            Plan::Sort { order_by, input } => {
+                for order in order_by.iter_mut() {
+                    order.expr.bind_parameters(values)?;
+                }
+                input.bind_parameters(values)
+            }
+            Plan::Limit {
+                limit,
+                offset,
+                input,
+            } => {
+                for scalar in limit.iter_mut().chain(offset.iter_mut()) {
+                    scalar.bind_parameters(values)?;
+                }
+                input.bind_parameters(values)
+            }
+            Plan::Explain { plan, .. } => plan.bind_parameters(values),
+            Plan::UnionAll { left, right } => {
+                left.bind_parameters(values)?;
+                right.bind_parameters(values)
+            }
+            Plan::Project { input, .. }
+            | Plan::Distinct { input }
+            | Plan::CreateTableAs { input, .. } => input.bind_parameters(values),
+            Plan::IndexScan { range, .. } => range
+                .scalars_mut()
+                .try_for_each(|scalar| scalar.bind_parameters(values)),
+            Plan::DML(job, returning) => {
+                job.bind_parameters(values)?;
+                returning
+                    .iter_mut()
+                    .flatten()
+                    .try_for_each(|scalar| scalar.bind_parameters(values))
+            }
+            Plan::Values { rows } => rows
+                .iter_mut()
+                .flatten()
+                .try_for_each(|scalar| scalar.bind_parameters(values)),
+            Plan::Unnest { arrays } => arrays
+                .iter_mut()
+                .try_for_each(|scalar| scalar.bind_parameters(values)),
+            Plan::Get { .. } | Plan::DDL(_) | Plan::Use(_) | Plan::SetVariable(..) => Ok(()),
+        }
+    }
+
+    /// Rewrite the references to the outer query in the plan. The subqueries
+    /// of `Apply` are skipped, since their outer columns reference this plan.
+    pub fn rewrite_outer_columns(
+        &mut self,
+        outer_column_fn: &mut impl FnMut(&Column) -> ScalarExpr,
+    ) {
+        for scalar in self.scalars_mut() {
+            *scalar = scalar.rewrite_columns(
+                &mut |column| ScalarExpr::Column(column.clone()),
+                outer_column_fn,
+            );
+        }
+
+        let children = match self {
+            Plan::Apply { input, .. } => vec![input.as_mut()],
+            plan => plan.children_mut(),
+        };
+        for child in children {
+            child.rewrite_outer_columns(outer_column_fn);
+        }
+    }
+
+    /// Expressions evaluated by the plan node, excluding the ones of its inputs.
+    fn scalars_mut(&mut self) -> Vec<&mut ScalarExpr> {
+        match self {
+            Plan::IndexScan { range, .. } => range.scalars_mut().collect(),
+            Plan::Map { scalars, .. } => scalars.iter_mut().collect(),
+            Plan::Filter { predicate, .. } => vec![predicate],
+            Plan::Join { keys, .. } => keys
+                .iter_mut()
+                .flat_map(|(left, right)| [left, right])
+                .collect(),
+            Plan::LeftOuterJoin { condition, .. } => vec![condition],
+            Plan::Aggregate {
+                group_by,
+                aggregates,
+                ..
+            } => group_by
+                .iter_mut()
+                .chain(aggregates.iter_mut().flat_map(AggregateExpr::scalars_mut))
+                .collect(),
+            Plan::SemiJoin {
+                left_key,
+                conditions,
+                ..
+            } => left_key.iter_mut().chain(conditions.iter_mut()).collect(),
+            Plan::Apply { parameters, .. } => parameters.iter_mut().collect(),
+            Plan::Window { window_exprs, .. } => window_exprs
+                .iter_mut()
+                .flat_map(|window_expr| {
+                    window_expr
+                        .args
+                        .iter_mut()
+                        .chain(window_expr.partition_by.iter_mut())
+                        .chain(window_expr.order_by.iter_mut().map(|order| &mut order.expr))
+                })
+                .collect(),
+            Plan::Sort { order_by, .. } => {
+                order_by.iter_mut().map(|order| &mut order.expr).collect()
+            }
+            Plan::Limit { limit, offset, .. } => {
+                limit.iter_mut().chain(offset.iter_mut()).collect()
+            }
+            Plan::Values { rows } => rows.iter_mut().flatten().collect(),
+            Plan::Unnest { arrays } => arrays.iter_mut().collect(),
+            Plan::DML(_, returning) => returning.iter_mut().flatten().collect(),
+            Plan::Get { .. }
+            | Plan::Project { .. }
+            | Plan::Distinct { .. }
+            | Plan::UnionAll { .. }
+            | Plan::DDL(_)
+            | Plan::CreateTableAs { .. }
+            | Plan::Explain { .. }
+            | Plan::Use(_)
+            | Plan::SetVariable(..) => vec![],
         }
     }
 }
@@ -91,118 +631,267 @@ impl Display for Plan {
 const DEFAULT_FORMAT_INDENT_SIZE: usize = 4;
 
 fn indent_format_plan(f: &mut std::fmt::Formatter, plan: &Plan, indent: usize) -> std::fmt::Result {
-    let indent_str = " ".repeat(indent);
-    match plan {
-        Plan::Get {
-            schema_name,
-            table_name,
-        } => write!(f, "{}Get: {}.{}", indent_str, schema_name, table_name),
-
-        Plan::Map { scalars, input } => {
-            write!(
-                f,
-                "{}Map: {}",
-                indent_str,
-                scalars
-                    .iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )?;
+    let (name, detail) = plan.describe();
+    write!(f, "{}{}", " ".repeat(indent), name)?;
+    if let Some(detail) = detail {
+        write!(f, ": {}", detail)?;
+    }
 
-            writeln!(f)?;
+    for child in plan.children() {
+        writeln!(f)?;
+        indent_format_plan(f, child, indent + DEFAULT_FORMAT_INDENT_SIZE)?;
+    }
 
-            indent_format_plan(f, input, indent + DEFAULT_FORMAT_INDENT_SIZE)
-        }
-        Plan::Project { projections, input } => {
-            write!(
-                f,
-                "{}Project: {}",
-                indent_str,
-                projections
-                    .iter()
-                    .map(|v| format!("#{}", v))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )?;
-            writeln!(f)?;
+    Ok(())
+}
 
-            indent_format_plan(f, input, indent + DEFAULT_FORMAT_INDENT_SIZE)
-        }
-        Plan::Filter { predicate, input } => {
-            write!(f, "{}Filter: {}", indent_str, predicate)?;
-            writeln!(f)?;
+/// Join the displayed items with commas.
+/// Columns emitted by a scan, empty if all of them are emitted.
+fn ddl_name(job: &DDLJob) -> &'static str {
+    match job {
+        DDLJob::CreateDatabase(_, _) => "CreateDatabase",
+        DDLJob::DropDatabase(_, _) => "DropDatabase",
+        DDLJob::CreateSchema(_, _) => "CreateSchema",
+        DDLJob::DropSchemas(_, _) => "DropSchema",
+        DDLJob::CreateTable(_, _, _, _) => "CreateTable",
+        DDLJob::DropTables(_, _) => "DropTable",
+        DDLJob::AlterTable(_, _, _) => "AlterTable",
+        DDLJob::CreateIndex(_, _, _, _) => "CreateIndex",
+        DDLJob::DropIndexes(_) => "DropIndex",
+        DDLJob::CreateSequence(_, _, _) => "CreateSequence",
+        DDLJob::DropSequences(_, _) => "DropSequence",
+        DDLJob::CreateFunction(_, _, _) => "CreateFunction",
+        DDLJob::DropFunctions(_) => "DropFunction",
+        DDLJob::Analyze(_) => "Analyze",
+        DDLJob::Vacuum(_) => "Vacuum",
+        DDLJob::ShowTables(_) => "ShowTables",
+    }
+}
 
-            indent_format_plan(f, input, indent + DEFAULT_FORMAT_INDENT_SIZE)
-        }
-        Plan::Join { left, right } => {
-            write!(f, "{}Join: ", indent_str)?;
-            writeln!(f)?;
+fn dml_name(job: &DMLJob) -> &'static str {
+    match job {
+        DMLJob::Insert(_, _) => "Insert",
+        DMLJob::Update(_, _, _) => "Update",
+        DMLJob::Delete(_, _) => "Delete",
+        DMLJob::CopyFrom(_, _) | DMLJob::CopyTo(_) => "Copy",
+    }
+}
 
-            indent_format_plan(f, left, indent + DEFAULT_FORMAT_INDENT_SIZE)?;
-            writeln!(f)?;
+fn projections_display(projections: &Option<Vec<usize>>) -> String {
+    match projections {
+        Some(projections) => format!(
+            ", columns: [{}]",
+            join_display(projections.iter().map(|v| format!("#{}", v)))
+        ),
+        None => String::new(),
+    }
+}
 
-            indent_format_plan(f, right, indent + DEFAULT_FORMAT_INDENT_SIZE)
-        }
-        Plan::DDL(job) => {
-            write!(
-                f,
-                "{}{}",
-                indent_str,
-                match job {
-                    DDLJob::CreateSchema(_) => "CreateSchema",
-                    DDLJob::DropSchemas(_) => "DropSchema",
-                    DDLJob::CreateTable(_, _) => "CreateTable",
-                    DDLJob::DropTables(_) => "DropTable",
-                    DDLJob::ShowTables(_) => "ShowTables",
-                }
-            )
+fn join_display<T: Display>(items: impl IntoIterator<Item = T>) -> String {
+    items
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Plan {
+    /// Inputs of the plan node.
+    pub fn children(&self) -> Vec<&Plan> {
+        match self {
+            Plan::Map { input, .. }
+            | Plan::Project { input, .. }
+            | Plan::Filter { input, .. }
+            | Plan::Aggregate { input, .. }
+            | Plan::Window { input, .. }
+            | Plan::Distinct { input }
+            | Plan::Sort { input, .. }
+            | Plan::Limit { input, .. }
+            | Plan::CreateTableAs { input, .. } => vec![input],
+            Plan::Join { left, right, .. }
+            | Plan::LeftOuterJoin { left, right, .. }
+            | Plan::SemiJoin { left, right, .. }
+            | Plan::UnionAll { left, right } => vec![left, right],
+            Plan::Apply {
+                subquery, input, ..
+            } => vec![input, subquery],
+            Plan::Explain { plan, .. } => vec![plan],
+            Plan::Get { .. }
+            | Plan::IndexScan { .. }
+            | Plan::DDL(_)
+            | Plan::DML(..)
+            | Plan::Values { .. }
+            | Plan::Unnest { .. }
+            | Plan::Use(_)
+            | Plan::SetVariable(..) => vec![],
         }
-        Plan::DML(job) => {
-            write!(
-                f,
-                "{}{}",
-                indent_str,
-                match job {
-                    DMLJob::Insert(_, _) => "Insert",
-                }
-            )
+    }
+
+    /// Mutable inputs of the plan node, in the same order as `children`.
+    pub fn children_mut(&mut self) -> Vec<&mut Plan> {
+        match self {
+            Plan::Map { input, .. }
+            | Plan::Project { input, .. }
+            | Plan::Filter { input, .. }
+            | Plan::Aggregate { input, .. }
+            | Plan::Window { input, .. }
+            | Plan::Distinct { input }
+            | Plan::Sort { input, .. }
+            | Plan::Limit { input, .. }
+            | Plan::CreateTableAs { input, .. } => vec![input],
+            Plan::Join { left, right, .. }
+            | Plan::LeftOuterJoin { left, right, .. }
+            | Plan::SemiJoin { left, right, .. }
+            | Plan::UnionAll { left, right } => vec![left, right],
+            Plan::Apply {
+                subquery, input, ..
+            } => vec![input, subquery],
+            Plan::Explain { plan, .. } => vec![plan],
+            Plan::Get { .. }
+            | Plan::IndexScan { .. }
+            | Plan::DDL(_)
+            | Plan::DML(..)
+            | Plan::Values { .. }
+            | Plan::Unnest { .. }
+            | Plan::Use(_)
+            | Plan::SetVariable(..) => vec![],
         }
-        Plan::Explain(_) => write!(f, "{}Explain", indent_str),
+    }
 
-        Plan::Aggregate {
-            group_by,
-            aggregates,
-            input,
-        } => {
-            write!(
-                f,
-                "{}Aggregate: group_by: {}, aggregates: {}",
-                indent_str,
-                group_by
-                    .iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                aggregates
+    /// Name of the plan node, and the details like the expressions it
+    /// evaluates, excluding the inputs.
+    pub fn describe(&self) -> (&'static str, Option<String>) {
+        match self {
+            Plan::Get {
+                schema_name,
+                table_name,
+                projections,
+            } => (
+                "Get",
+                Some(format!(
+                    "{}.{}{}",
+                    schema_name,
+                    table_name,
+                    projections_display(projections)
+                )),
+            ),
+            Plan::IndexScan {
+                schema_name,
+                table_name,
+                index_name,
+                columns,
+                range,
+                index_only,
+                projections,
+            } => (
+                if *index_only {
+                    "IndexOnlyScan"
+                } else {
+                    "IndexScan"
+                },
+                Some(format!(
+                    "{}.{} using {}({}), range: {}{}",
+                    schema_name,
+                    table_name,
+                    index_name,
+                    join_display(columns.iter().map(|v| format!("#{}", v))),
+                    range,
+                    projections_display(projections)
+                )),
+            ),
+            Plan::Map { scalars, .. } => ("Map", Some(join_display(scalars))),
+            Plan::Project { projections, .. } => (
+                "Project",
+                Some(join_display(projections.iter().map(|v| format!("#{}", v)))),
+            ),
+            Plan::Filter { predicate, .. } => ("Filter", Some(predicate.to_string())),
+            Plan::Join { keys, .. } => (
+                "Join",
+                (!keys.is_empty()).then(|| {
+                    join_display(
+                        keys.iter()
+                            .map(|(left, right)| format!("{} = {}", left, right)),
+                    )
+                }),
+            ),
+            Plan::LeftOuterJoin { condition, .. } => ("LeftOuterJoin", Some(condition.to_string())),
+            Plan::DDL(job) => (ddl_name(job), None),
+            Plan::DML(job, _) => (dml_name(job), None),
+            Plan::CreateTableAs {
+                schema_name,
+                table_name,
+                ..
+            } => (
+                "CreateTableAs",
+                Some(format!("{}.{}", schema_name, table_name)),
+            ),
+            Plan::Explain { .. } => ("Explain", None),
+            Plan::Aggregate {
+                group_by,
+                grouping_sets,
+                aggregates,
+                ..
+            } => {
+                let mut detail = format!("group_by: {}", join_display(group_by));
+                if !grouping_sets.is_empty() {
+                    detail += &format!(
+                        ", grouping_sets: {}",
+                        join_display(grouping_sets.iter().map(|set| format!(
+                            "({})",
+                            join_display(set.iter().map(|key| &group_by[*key]))
+                        )))
+                    );
+                }
+                detail += &format!(", aggregates: {}", join_display(aggregates));
+                ("Aggregate", Some(detail))
+            }
+            Plan::SemiJoin {
+                left_key,
+                conditions,
+                anti,
+                ..
+            } => {
+                let mut detail = left_key
+                    .as_ref()
+                    .map_or_else(|| "exists".to_string(), |key| key.to_string());
+                if !conditions.is_empty() {
+                    detail += &format!(", conditions: {}", join_display(conditions));
+                }
+                (if *anti { "AntiJoin" } else { "SemiJoin" }, Some(detail))
+            }
+            Plan::Apply {
+                parameters, array, ..
+            } => {
+                let mut details = vec![];
+                if *array {
+                    details.push("array".to_string());
+                }
+                if !parameters.is_empty() {
+                    details.push(format!("parameters: {}", join_display(parameters)));
+                }
+                ("Apply", (!details.is_empty()).then(|| details.join(", ")))
+            }
+            Plan::Window { window_exprs, .. } => ("Window", Some(join_display(window_exprs))),
+            Plan::Distinct { .. } => ("Distinct", None),
+            Plan::UnionAll { .. } => ("UnionAll", None),
+            Plan::Sort { order_by, .. } => ("Sort", Some(join_display(order_by))),
+            Plan::Limit { limit, offset, .. } => {
+                let detail = limit
                     .iter()
-                    .map(|(name, args)| {
-                        format!(
-                            "{}({})",
-                            name,
-                            args.iter()
-                                .map(|arg| arg.to_string())
-                                .collect::<Vec<_>>()
-                                .join(", ")
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )?;
-            writeln!(f)?;
-
-            indent_format_plan(f, input, indent + DEFAULT_FORMAT_INDENT_SIZE)
+                    .map(|limit| format!("limit: {}", limit))
+                    .chain(offset.iter().map(|offset| format!("offset: {}", offset)))
+                    .collect::<Vec<_>>();
+                ("Limit", Some(detail.join(", ")))
+            }
+            Plan::Values { rows } => (
+                "Values",
+                Some(join_display(
+                    rows.iter().map(|row| format!("({})", join_display(row))),
+                )),
+            ),
+            Plan::Unnest { arrays } => ("Unnest", Some(join_display(arrays))),
+            Plan::Use(_) => ("Use", None),
+            Plan::SetVariable(name, _) => ("SetVariable", Some(name.clone())),
         }
-        Plan::Use(_) => write!(f, "{}Use", indent_str),
     }
 }
@@ -0,0 +1,34 @@
+use super::{take_plan, Rule};
+use crate::{
+    catalog::DatabaseCatalog,
+    core::{Datum, SQLError},
+    sql::planner::{Plan, ScalarExpr},
+};
+
+/// Remove the filters which are always true, e.g. `WHERE true`.
+pub struct EliminateFilter;
+
+impl Rule for EliminateFilter {
+    fn name(&self) -> &'static str {
+        "EliminateFilter"
+    }
+
+    fn apply(&self, plan: &mut Plan, _catalog: &DatabaseCatalog) -> Result<bool, SQLError> {
+        if !matches!(
+            plan,
+            Plan::Filter {
+                predicate: ScalarExpr::Literal(Datum::Boolean(true)),
+                ..
+            }
+        ) {
+            return Ok(false);
+        }
+
+        let Plan::Filter { input, .. } = take_plan(plan) else {
+            unreachable!()
+        };
+        *plan = *input;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,96 @@
+use super::{push_down_filter::column_bounds, take_plan, Rule};
+use crate::{
+    catalog::DatabaseCatalog,
+    core::SQLError,
+    sql::planner::{
+        index::{build_filters, output_width, split_conjunctions},
+        Plan, ScalarExpr,
+    },
+};
+
+/// Turn the equality predicates of the filters on top of a join, which compare
+/// the columns of the left side with the ones of the right side, into the keys
+/// of the join, so it can be executed with a hash table instead of producing
+/// the whole cross product.
+pub struct ExtractJoinKeys;
+
+impl Rule for ExtractJoinKeys {
+    fn name(&self) -> &'static str {
+        "ExtractJoinKeys"
+    }
+
+    fn apply(&self, plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<bool, SQLError> {
+        let mut predicates = vec![];
+        let mut input = &*plan;
+        while let Plan::Filter {
+            predicate,
+            input: child,
+        } = input
+        {
+            split_conjunctions(predicate.clone(), &mut predicates);
+            input = child;
+        }
+        // Predicates are collected from top to bottom
+        predicates.reverse();
+
+        let Plan::Join { left, .. } = input else {
+            return Ok(false);
+        };
+        let width = output_width(left, catalog)?;
+        if !predicates
+            .iter()
+            .any(|predicate| as_join_key(predicate, width).is_some())
+        {
+            return Ok(false);
+        }
+
+        let mut input = take_plan(plan);
+        while let Plan::Filter { input: child, .. } = input {
+            input = *child;
+        }
+        let Plan::Join {
+            mut keys,
+            left,
+            right,
+        } = input
+        else {
+            unreachable!()
+        };
+
+        let mut remaining = vec![];
+        for predicate in predicates {
+            match as_join_key(&predicate, width) {
+                Some(key) => keys.push(key),
+                None => remaining.push(predicate),
+            }
+        }
+        *plan = build_filters(Plan::Join { keys, left, right }, remaining);
+
+        Ok(true)
+    }
+}
+
+/// Match the predicate with `left = right`, where `left` only references the
+/// columns of the left side, and `right` only references the right side. The
+/// operands are returned in the order of the sides.
+fn as_join_key(predicate: &ScalarExpr, width: usize) -> Option<(ScalarExpr, ScalarExpr)> {
+    let ScalarExpr::FunctionCall(name, args) = predicate else {
+        return None;
+    };
+    let [first, second] = args.as_slice() else {
+        return None;
+    };
+    if name != "=" || first.has_outer_column() || second.has_outer_column() {
+        return None;
+    }
+
+    match (column_bounds(first)?, column_bounds(second)?) {
+        ((_, last), (first_right, _)) if last < width && first_right >= width => {
+            Some((first.clone(), second.clone()))
+        }
+        ((first_right, _), (_, last)) if last < width && first_right >= width => {
+            Some((second.clone(), first.clone()))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,34 @@
+use super::{take_plan, Rule};
+use crate::{
+    catalog::DatabaseCatalog,
+    core::SQLError,
+    sql::planner::{index::plan_index_scan, Plan},
+};
+
+/// Replace the filters on top of a table scan with an index scan, see
+/// `plan_index_scan`.
+pub struct IndexScan;
+
+impl Rule for IndexScan {
+    fn name(&self) -> &'static str {
+        "IndexScan"
+    }
+
+    fn apply(&self, plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<bool, SQLError> {
+        if !matches!(plan, Plan::Filter { .. }) || !matches!(scan(plan), Plan::Get { .. }) {
+            return Ok(false);
+        }
+
+        *plan = plan_index_scan(take_plan(plan), catalog)?;
+
+        Ok(matches!(scan(plan), Plan::IndexScan { .. }))
+    }
+}
+
+/// The input below the chain of filters.
+fn scan(mut plan: &Plan) -> &Plan {
+    while let Plan::Filter { input, .. } = plan {
+        plan = input;
+    }
+    plan
+}
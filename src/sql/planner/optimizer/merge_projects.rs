@@ -0,0 +1,251 @@
+use super::{take_plan, Rule};
+use crate::{
+    catalog::DatabaseCatalog,
+    core::SQLError,
+    sql::planner::{
+        index::output_width, AggregateExpr, Column, OrderBy, Plan, ScalarExpr, WindowExpr,
+    },
+};
+
+/// Flatten the projections of subqueries, e.g. derived tables in `FROM`, into
+/// the parent plan. Adjacent projections are merged, the ones emitting their
+/// input as is are removed, and the others are pulled above the operators
+/// that append columns or combine inputs, so they can be merged further.
+pub struct MergeProjects;
+
+impl Rule for MergeProjects {
+    fn name(&self) -> &'static str {
+        "MergeProjects"
+    }
+
+    fn apply(&self, plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<bool, SQLError> {
+        match plan {
+            Plan::Project { projections, input } => {
+                if let Plan::Project {
+                    projections: inner_projections,
+                    input: inner_input,
+                } = input.as_mut()
+                {
+                    *projections = projections
+                        .iter()
+                        .map(|column| inner_projections[*column])
+                        .collect();
+                    *input = Box::new(take_plan(inner_input));
+                    return Ok(true);
+                }
+
+                if !projections
+                    .iter()
+                    .copied()
+                    .eq(0..output_width(input, catalog)?)
+                {
+                    return Ok(false);
+                }
+                *plan = take_plan(input);
+                Ok(true)
+            }
+            Plan::Map { scalars, input } => {
+                let Plan::Project {
+                    projections,
+                    input: project_input,
+                } = input.as_mut()
+                else {
+                    return Ok(false);
+                };
+
+                let scalars = scalars
+                    .iter()
+                    .map(|scalar| map_columns(scalar, projections))
+                    .collect::<Vec<_>>();
+                let projections = appended(projections, project_input, scalars.len(), catalog)?;
+                let input = Box::new(take_plan(project_input));
+                *plan = Plan::Project {
+                    projections,
+                    input: Box::new(Plan::Map { scalars, input }),
+                };
+                Ok(true)
+            }
+            Plan::Window {
+                window_exprs,
+                input,
+            } => {
+                let Plan::Project {
+                    projections,
+                    input: project_input,
+                } = input.as_mut()
+                else {
+                    return Ok(false);
+                };
+
+                let window_exprs = window_exprs
+                    .iter()
+                    .map(|window_expr| {
+                        let map_all = |scalars: &[ScalarExpr]| {
+                            scalars
+                                .iter()
+                                .map(|scalar| map_columns(scalar, projections))
+                                .collect()
+                        };
+                        WindowExpr {
+                            func_name: window_expr.func_name.clone(),
+                            args: map_all(&window_expr.args),
+                            partition_by: map_all(&window_expr.partition_by),
+                            order_by: window_expr
+                                .order_by
+                                .iter()
+                                .map(|order| OrderBy {
+                                    expr: map_columns(&order.expr, projections),
+                                    ..order.clone()
+                                })
+                                .collect(),
+                            frame: window_expr.frame.clone(),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let projections =
+                    appended(projections, project_input, window_exprs.len(), catalog)?;
+                let input = Box::new(take_plan(project_input));
+                *plan = Plan::Project {
+                    projections,
+                    input: Box::new(Plan::Window {
+                        window_exprs,
+                        input,
+                    }),
+                };
+                Ok(true)
+            }
+            // The output columns are computed by the aggregate, so the
+            // projection can be removed
+            Plan::Aggregate {
+                group_by,
+                aggregates,
+                input,
+                ..
+            } => {
+                let Plan::Project {
+                    projections,
+                    input: project_input,
+                } = input.as_mut()
+                else {
+                    return Ok(false);
+                };
+
+                for scalar in group_by
+                    .iter_mut()
+                    .chain(aggregates.iter_mut().flat_map(AggregateExpr::scalars_mut))
+                {
+                    *scalar = map_columns(scalar, projections);
+                }
+                *input = Box::new(take_plan(project_input));
+                Ok(true)
+            }
+            // Projections commute with sorting
+            Plan::Sort { order_by, input } => {
+                let Plan::Project {
+                    projections,
+                    input: project_input,
+                } = input.as_mut()
+                else {
+                    return Ok(false);
+                };
+
+                let order_by = order_by
+                    .iter()
+                    .map(|order| OrderBy {
+                        expr: map_columns(&order.expr, projections),
+                        ..order.clone()
+                    })
+                    .collect();
+                let projections = std::mem::take(projections);
+                let input = Box::new(take_plan(project_input));
+                *plan = Plan::Project {
+                    projections,
+                    input: Box::new(Plan::Sort { order_by, input }),
+                };
+                Ok(true)
+            }
+            Plan::Join { keys, left, right } => {
+                let Some(projections) = pull_up_from_join(left, right, catalog)? else {
+                    return Ok(false);
+                };
+
+                for (left_key, right_key) in keys.iter_mut() {
+                    *left_key = map_columns(left_key, &projections);
+                    *right_key = map_columns(right_key, &projections);
+                }
+                let input = Box::new(take_plan(plan));
+                *plan = Plan::Project { projections, input };
+                Ok(true)
+            }
+            // Projections commute with padding NULLs
+            Plan::LeftOuterJoin {
+                condition,
+                left,
+                right,
+            } => {
+                let Some(projections) = pull_up_from_join(left, right, catalog)? else {
+                    return Ok(false);
+                };
+
+                *condition = map_columns(condition, &projections);
+                let input = Box::new(take_plan(plan));
+                *plan = Plan::Project { projections, input };
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Remove the projection on either side of a join, returns the projection of
+/// the combined tuple replacing it.
+fn pull_up_from_join(
+    left: &mut Box<Plan>,
+    right: &mut Box<Plan>,
+    catalog: &DatabaseCatalog,
+) -> Result<Option<Vec<usize>>, SQLError> {
+    if let Plan::Project { projections, input } = left.as_mut() {
+        let right_width = output_width(right, catalog)?;
+        let projections = appended(projections, input, right_width, catalog)?;
+        *left = Box::new(take_plan(input));
+        return Ok(Some(projections));
+    }
+
+    if let Plan::Project { projections, input } = right.as_mut() {
+        let left_width = output_width(left, catalog)?;
+        let projections = (0..left_width)
+            .chain(projections.iter().map(|column| left_width + column))
+            .collect();
+        *right = Box::new(take_plan(input));
+        return Ok(Some(projections));
+    }
+
+    Ok(None)
+}
+
+/// Projection of `input` followed by `count` columns appended to it.
+fn appended(
+    projections: &[usize],
+    input: &Plan,
+    count: usize,
+    catalog: &DatabaseCatalog,
+) -> Result<Vec<usize>, SQLError> {
+    let width = output_width(input, catalog)?;
+    Ok(projections
+        .iter()
+        .copied()
+        .chain(width..width + count)
+        .collect())
+}
+
+/// Reference the input columns of the projection instead of its outputs.
+fn map_columns(scalar: &ScalarExpr, projections: &[usize]) -> ScalarExpr {
+    scalar.rewrite_columns(
+        &mut |column| {
+            ScalarExpr::Column(Column {
+                index: projections[column.index],
+            })
+        },
+        &mut |column| ScalarExpr::OuterColumn(column.clone()),
+    )
+}
@@ -0,0 +1,97 @@
+use super::{index::plan_index_only_scan, Plan};
+use crate::{catalog::DatabaseCatalog, core::SQLError};
+
+mod eliminate_filter;
+mod extract_join_keys;
+mod index_scan;
+mod merge_projects;
+mod prune_columns;
+mod push_down_filter;
+
+pub use eliminate_filter::EliminateFilter;
+pub use extract_join_keys::ExtractJoinKeys;
+pub use index_scan::IndexScan;
+pub use merge_projects::MergeProjects;
+pub use prune_columns::prune_columns;
+pub use push_down_filter::PushDownFilter;
+
+/// A transformation of the plan nodes matching some pattern, e.g. pushing
+/// a filter below a join.
+pub trait Rule {
+    fn name(&self) -> &'static str;
+
+    /// Rewrite the plan node in place, returns false if it doesn't match.
+    fn apply(&self, plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<bool, SQLError>;
+}
+
+/// Maximum times of rewriting a node or the whole plan, in case the rules keep
+/// undoing each other.
+const MAX_ITERATIONS: usize = 32;
+
+/// Rule-based optimizer. The plan is visited from top to bottom, and each node
+/// is rewritten by the rules in order until none of them applies, before its
+/// inputs are visited. So the nodes pushed down are rewritten again below, and
+/// the plan is visited again if any node is rewritten, for the ones pulled up.
+pub struct Optimizer {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Self::new(vec![
+            Box::new(EliminateFilter),
+            Box::new(MergeProjects),
+            Box::new(PushDownFilter),
+            Box::new(ExtractJoinKeys),
+            Box::new(IndexScan),
+        ])
+    }
+}
+
+impl Optimizer {
+    pub fn new(rules: Vec<Box<dyn Rule>>) -> Self {
+        Self { rules }
+    }
+
+    pub fn optimize(&self, mut plan: Plan, catalog: &DatabaseCatalog) -> Result<Plan, SQLError> {
+        for _ in 0..MAX_ITERATIONS {
+            if !self.rewrite(&mut plan, catalog)? {
+                break;
+            }
+        }
+        // These depend on the columns used by the whole plan, so they're done at last
+        plan_index_only_scan(&mut plan, catalog)?;
+        prune_columns(&mut plan, catalog)?;
+
+        Ok(plan)
+    }
+
+    /// Rewrite the plan and its inputs, returns true if any node is rewritten.
+    fn rewrite(&self, plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<bool, SQLError> {
+        let mut rewritten = false;
+        for _ in 0..MAX_ITERATIONS {
+            let mut applied = false;
+            for rule in self.rules.iter() {
+                if rule.apply(plan, catalog)? {
+                    applied = true;
+                    break;
+                }
+            }
+            if !applied {
+                break;
+            }
+            rewritten = true;
+        }
+
+        for child in plan.children_mut() {
+            rewritten |= self.rewrite(child, catalog)?;
+        }
+
+        Ok(rewritten)
+    }
+}
+
+/// Move the plan out of the node to rebuild it, leaving an empty plan.
+fn take_plan(plan: &mut Plan) -> Plan {
+    std::mem::replace(plan, Plan::Values { rows: vec![] })
+}
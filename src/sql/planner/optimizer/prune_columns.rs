@@ -0,0 +1,379 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    catalog::DatabaseCatalog,
+    core::SQLError,
+    sql::{
+        expression::SequenceFunction,
+        planner::{
+            index::{collect_columns, output_width},
+            AggregateExpr, Column, OrderBy, Plan, ScalarExpr, WindowExpr,
+        },
+    },
+};
+
+/// Remove the columns which are not referenced by the operators above, so the
+/// scans only emit the used columns of tables, and the unused expressions are
+/// not evaluated.
+pub fn prune_columns(plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<(), SQLError> {
+    let required = (0..output_width(plan, catalog)?).collect();
+    prune(plan, &required, catalog)?;
+
+    Ok(())
+}
+
+/// Prune the output columns of plan, `required` is the set of columns used by
+/// the parent. Returns the original indices of the remaining output columns,
+/// in order, which contain all the required ones.
+fn prune(
+    plan: &mut Plan,
+    required: &BTreeSet<usize>,
+    catalog: &DatabaseCatalog,
+) -> Result<Vec<usize>, SQLError> {
+    let width = output_width(plan, catalog)?;
+    match plan {
+        Plan::Get { projections, .. } | Plan::IndexScan { projections, .. } => {
+            // Unknown tables are left for the executor to report
+            if required.len() == width || required.iter().any(|column| *column >= width) {
+                return Ok(all_columns(width, required));
+            }
+
+            let kept = required.iter().copied().collect::<Vec<_>>();
+            *projections = Some(match projections {
+                Some(projections) => kept.iter().map(|column| projections[*column]).collect(),
+                None => kept.clone(),
+            });
+            Ok(kept)
+        }
+        Plan::Filter { predicate, input } => {
+            let mut input_required = required.clone();
+            collect_columns(predicate, &mut input_required);
+            let kept = prune(input, &input_required, catalog)?;
+            *predicate = remap(predicate, &kept);
+            Ok(kept)
+        }
+        Plan::Sort { order_by, input } => {
+            let mut input_required = required.clone();
+            for order in order_by.iter() {
+                collect_columns(&order.expr, &mut input_required);
+            }
+            let kept = prune(input, &input_required, catalog)?;
+            for order in order_by.iter_mut() {
+                order.expr = remap(&order.expr, &kept);
+            }
+            Ok(kept)
+        }
+        Plan::Project { projections, input } => {
+            let kept = required.iter().copied().collect::<Vec<_>>();
+            let selected = kept
+                .iter()
+                .map(|column| projections[*column])
+                .collect::<Vec<_>>();
+            let input_kept = prune(input, &selected.iter().copied().collect(), catalog)?;
+            *projections = selected
+                .iter()
+                .map(|column| position(&input_kept, *column))
+                .collect();
+            Ok(kept)
+        }
+        Plan::Map { scalars, input } => {
+            let input_width = width - scalars.len();
+            let mut input_required = input_columns(required, input_width);
+            // The expressions with side effects are always evaluated
+            let kept_scalars = (0..scalars.len())
+                .filter(|i| required.contains(&(input_width + i)) || has_side_effects(&scalars[*i]))
+                .collect::<Vec<_>>();
+            for i in kept_scalars.iter() {
+                collect_columns(&scalars[*i], &mut input_required);
+            }
+
+            let input_kept = prune(input, &input_required, catalog)?;
+            *scalars = kept_scalars
+                .iter()
+                .map(|i| remap(&scalars[*i], &input_kept))
+                .collect();
+            Ok(input_kept
+                .into_iter()
+                .chain(kept_scalars.into_iter().map(|i| input_width + i))
+                .collect())
+        }
+        Plan::Apply {
+            parameters,
+            subquery,
+            input,
+            ..
+        } => {
+            let input_width = width - 1;
+            let mut input_required = input_columns(required, input_width);
+            for scalar in parameters.iter() {
+                collect_columns(scalar, &mut input_required);
+            }
+
+            // The subquery is pruned on its own, since it's evaluated separately
+            prune_columns(subquery, catalog)?;
+            let input_kept = prune(input, &input_required, catalog)?;
+            *parameters = parameters
+                .iter()
+                .map(|scalar| remap(scalar, &input_kept))
+                .collect();
+            Ok(input_kept
+                .into_iter()
+                .chain(std::iter::once(input_width))
+                .collect())
+        }
+        Plan::Window {
+            window_exprs,
+            input,
+        } => {
+            let input_width = width - window_exprs.len();
+            let mut input_required = input_columns(required, input_width);
+            let kept_exprs = (0..window_exprs.len())
+                .filter(|i| required.contains(&(input_width + i)))
+                .collect::<Vec<_>>();
+            for i in kept_exprs.iter() {
+                let window_expr = &window_exprs[*i];
+                for scalar in window_expr
+                    .args
+                    .iter()
+                    .chain(window_expr.partition_by.iter())
+                    .chain(window_expr.order_by.iter().map(|order| &order.expr))
+                {
+                    collect_columns(scalar, &mut input_required);
+                }
+            }
+
+            let input_kept = prune(input, &input_required, catalog)?;
+            *window_exprs = kept_exprs
+                .iter()
+                .map(|i| {
+                    let window_expr = &window_exprs[*i];
+                    let remap_all = |scalars: &[ScalarExpr]| {
+                        scalars
+                            .iter()
+                            .map(|scalar| remap(scalar, &input_kept))
+                            .collect()
+                    };
+                    WindowExpr {
+                        func_name: window_expr.func_name.clone(),
+                        args: remap_all(&window_expr.args),
+                        partition_by: remap_all(&window_expr.partition_by),
+                        order_by: window_expr
+                            .order_by
+                            .iter()
+                            .map(|order| OrderBy {
+                                expr: remap(&order.expr, &input_kept),
+                                ..order.clone()
+                            })
+                            .collect(),
+                        frame: window_expr.frame.clone(),
+                    }
+                })
+                .collect();
+            Ok(input_kept
+                .into_iter()
+                .chain(kept_exprs.into_iter().map(|i| input_width + i))
+                .collect())
+        }
+        Plan::Aggregate {
+            group_by,
+            grouping_sets,
+            aggregates,
+            input,
+        } => {
+            // Group keys are never pruned, since they decide the groups, nor
+            // is the index of grouping set
+            let keys = group_by.len() + usize::from(!grouping_sets.is_empty());
+            let mut kept_aggregates = (0..aggregates.len())
+                .filter(|i| required.contains(&(keys + i)))
+                .collect::<Vec<_>>();
+            // Scalar aggregate keeps an aggregate, so it still produces a row
+            if keys == 0 && kept_aggregates.is_empty() && !aggregates.is_empty() {
+                kept_aggregates.push(0);
+            }
+            let mut input_required = BTreeSet::new();
+            for scalar in group_by.iter().chain(
+                kept_aggregates
+                    .iter()
+                    .flat_map(|i| aggregates[*i].scalars()),
+            ) {
+                collect_columns(scalar, &mut input_required);
+            }
+
+            let input_kept = prune(input, &input_required, catalog)?;
+            *group_by = group_by
+                .iter()
+                .map(|scalar| remap(scalar, &input_kept))
+                .collect();
+            *aggregates = kept_aggregates
+                .iter()
+                .map(|i| {
+                    let aggregate = &aggregates[*i];
+                    AggregateExpr {
+                        func_name: aggregate.func_name.clone(),
+                        args: aggregate
+                            .args
+                            .iter()
+                            .map(|arg| remap(arg, &input_kept))
+                            .collect(),
+                        filter: aggregate
+                            .filter
+                            .as_ref()
+                            .map(|filter| remap(filter, &input_kept)),
+                        order_by: aggregate
+                            .order_by
+                            .iter()
+                            .map(|order_by| OrderBy {
+                                expr: remap(&order_by.expr, &input_kept),
+                                ..order_by.clone()
+                            })
+                            .collect(),
+                    }
+                })
+                .collect();
+            Ok((0..keys)
+                .chain(kept_aggregates.into_iter().map(|i| keys + i))
+                .collect())
+        }
+        // The counts are constants
+        Plan::Limit { input, .. } => prune(input, required, catalog),
+        // Duplicates are decided by all the columns
+        Plan::Distinct { input } => prune(input, &(0..width).collect(), catalog),
+        // Both sides emit the same columns, which are kept
+        Plan::UnionAll { left, right } => {
+            let all = (0..width).collect();
+            prune(left, &all, catalog)?;
+            prune(right, &all, catalog)?;
+            Ok(all_columns(width, required))
+        }
+        Plan::Join { keys, left, right } => {
+            let left_width = output_width(left, catalog)?;
+            let mut required = required.clone();
+            for (left_key, right_key) in keys.iter() {
+                collect_columns(left_key, &mut required);
+                collect_columns(right_key, &mut required);
+            }
+            let (left_kept, right_kept) = prune_join(left, right, &required, left_width, catalog)?;
+            let kept = combine(left_kept, right_kept, left_width);
+            *keys = keys
+                .iter()
+                .map(|(left_key, right_key)| (remap(left_key, &kept), remap(right_key, &kept)))
+                .collect();
+            Ok(kept)
+        }
+        Plan::LeftOuterJoin {
+            condition,
+            left,
+            right,
+        } => {
+            let left_width = output_width(left, catalog)?;
+            let mut required = required.clone();
+            collect_columns(condition, &mut required);
+            let (left_kept, right_kept) = prune_join(left, right, &required, left_width, catalog)?;
+            let kept = combine(left_kept, right_kept, left_width);
+            *condition = remap(condition, &kept);
+            Ok(kept)
+        }
+        Plan::SemiJoin {
+            left_key,
+            conditions,
+            left,
+            right,
+            ..
+        } => {
+            // Conditions are evaluated with the combined tuple of both sides
+            let left_width = output_width(left, catalog)?;
+            let mut required = required.clone();
+            for scalar in left_key.iter().chain(conditions.iter()) {
+                collect_columns(scalar, &mut required);
+            }
+            // The first column of right side is compared with the left key
+            if left_key.is_some() {
+                required.insert(left_width);
+            }
+            let (left_kept, right_kept) = prune_join(left, right, &required, left_width, catalog)?;
+            *left_key = left_key.as_ref().map(|scalar| remap(scalar, &left_kept));
+            let kept = combine(left_kept.clone(), right_kept, left_width);
+            *conditions = conditions
+                .iter()
+                .map(|scalar| remap(scalar, &kept))
+                .collect();
+            Ok(left_kept)
+        }
+        Plan::Values { .. }
+        | Plan::Unnest { .. }
+        | Plan::DDL(_)
+        | Plan::DML(..)
+        | Plan::CreateTableAs { .. }
+        | Plan::Explain { .. }
+        | Plan::Use(_)
+        | Plan::SetVariable(..) => Ok(all_columns(width, required)),
+    }
+}
+
+/// Prune both sides of a join with the required columns of the combined tuple.
+fn prune_join(
+    left: &mut Plan,
+    right: &mut Plan,
+    required: &BTreeSet<usize>,
+    left_width: usize,
+    catalog: &DatabaseCatalog,
+) -> Result<(Vec<usize>, Vec<usize>), SQLError> {
+    let left_required = input_columns(required, left_width);
+    let right_required = required
+        .iter()
+        .filter(|column| **column >= left_width)
+        .map(|column| column - left_width)
+        .collect();
+
+    Ok((
+        prune(left, &left_required, catalog)?,
+        prune(right, &right_required, catalog)?,
+    ))
+}
+
+fn combine(left_kept: Vec<usize>, right_kept: Vec<usize>, left_width: usize) -> Vec<usize> {
+    left_kept
+        .into_iter()
+        .chain(right_kept.into_iter().map(|column| column + left_width))
+        .collect()
+}
+
+fn input_columns(required: &BTreeSet<usize>, input_width: usize) -> BTreeSet<usize> {
+    required
+        .iter()
+        .filter(|column| **column < input_width)
+        .copied()
+        .collect()
+}
+
+/// All the columns of a plan that is not pruned.
+fn all_columns(width: usize, required: &BTreeSet<usize>) -> Vec<usize> {
+    let width = required.last().map_or(width, |last| width.max(last + 1));
+    (0..width).collect()
+}
+
+/// Reference the columns by their positions in the remaining columns.
+fn remap(scalar: &ScalarExpr, kept: &[usize]) -> ScalarExpr {
+    scalar.rewrite_columns(
+        &mut |column| {
+            ScalarExpr::Column(Column {
+                index: position(kept, column.index),
+            })
+        },
+        &mut |column| ScalarExpr::OuterColumn(column.clone()),
+    )
+}
+
+fn position(kept: &[usize], column: usize) -> usize {
+    kept.iter().position(|kept| *kept == column).unwrap()
+}
+
+fn has_side_effects(scalar: &ScalarExpr) -> bool {
+    match scalar {
+        ScalarExpr::FunctionCall(name, args) => {
+            SequenceFunction::from_name(name) == Some(SequenceFunction::NextVal)
+                || args.iter().any(has_side_effects)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeSet;
+
+use super::{take_plan, Rule};
+use crate::{
+    catalog::DatabaseCatalog,
+    core::SQLError,
+    sql::planner::{
+        index::{build_filters, collect_columns, output_width, split_conjunctions},
+        Column, Plan, ScalarExpr,
+    },
+};
+
+/// Push the predicates of filters below joins, projections and `DISTINCT`, so
+/// they are evaluated earlier, and may be answered by indexes.
+pub struct PushDownFilter;
+
+impl Rule for PushDownFilter {
+    fn name(&self) -> &'static str {
+        "PushDownFilter"
+    }
+
+    fn apply(&self, plan: &mut Plan, catalog: &DatabaseCatalog) -> Result<bool, SQLError> {
+        let mut predicates = vec![];
+        let mut input = &*plan;
+        while let Plan::Filter {
+            predicate,
+            input: child,
+        } = input
+        {
+            split_conjunctions(predicate.clone(), &mut predicates);
+            input = child;
+        }
+        // Predicates are collected from top to bottom
+        predicates.reverse();
+
+        let pushable = match input {
+            Plan::Project { .. }
+            | Plan::Distinct { .. }
+            | Plan::Sort { .. }
+            | Plan::SemiJoin { .. } => true,
+            Plan::Join { left, .. } => {
+                let width = output_width(left, catalog)?;
+                predicates.iter().any(|predicate| {
+                    matches!(column_bounds(predicate), Some((first, last)) if last < width || first >= width)
+                })
+            }
+            Plan::LeftOuterJoin { left, .. } => {
+                let width = output_width(left, catalog)?;
+                predicates.iter().any(
+                    |predicate| matches!(column_bounds(predicate), Some((_, last)) if last < width),
+                )
+            }
+            _ => false,
+        };
+        if predicates.is_empty() || !pushable {
+            return Ok(false);
+        }
+
+        let mut input = take_plan(plan);
+        while let Plan::Filter { input: child, .. } = input {
+            input = *child;
+        }
+        let (input, remaining) = push_down(input, predicates, catalog)?;
+        *plan = build_filters(input, remaining);
+
+        Ok(true)
+    }
+}
+
+/// Push the predicates into the inputs of plan, returns the new plan and the
+/// predicates that cannot be pushed.
+fn push_down(
+    plan: Plan,
+    predicates: Vec<ScalarExpr>,
+    catalog: &DatabaseCatalog,
+) -> Result<(Plan, Vec<ScalarExpr>), SQLError> {
+    Ok(match plan {
+        Plan::Project { projections, input } => {
+            let predicates = predicates
+                .iter()
+                .map(|predicate| {
+                    predicate.rewrite_columns(
+                        &mut |column| {
+                            ScalarExpr::Column(Column {
+                                index: projections[column.index],
+                            })
+                        },
+                        &mut |column| ScalarExpr::OuterColumn(column.clone()),
+                    )
+                })
+                .collect();
+            let input = Box::new(build_filters(*input, predicates));
+            (Plan::Project { projections, input }, vec![])
+        }
+        Plan::Distinct { input } => {
+            let input = Box::new(build_filters(*input, predicates));
+            (Plan::Distinct { input }, vec![])
+        }
+        Plan::Sort { order_by, input } => {
+            let input = Box::new(build_filters(*input, predicates));
+            (Plan::Sort { order_by, input }, vec![])
+        }
+        // Only the columns of left side are emitted
+        Plan::SemiJoin {
+            left_key,
+            conditions,
+            anti,
+            left,
+            right,
+        } => {
+            let left = Box::new(build_filters(*left, predicates));
+            let plan = Plan::SemiJoin {
+                left_key,
+                conditions,
+                anti,
+                left,
+                right,
+            };
+            (plan, vec![])
+        }
+        Plan::Join { keys, left, right } => {
+            let width = output_width(&left, catalog)?;
+            let (left_predicates, right_predicates, remaining) = split_by_side(predicates, width);
+            let plan = Plan::Join {
+                keys,
+                left: Box::new(build_filters(*left, left_predicates)),
+                right: Box::new(build_filters(*right, right_predicates)),
+            };
+            (plan, remaining)
+        }
+        // The right side is padded with NULLs, so only the left side is filtered
+        Plan::LeftOuterJoin {
+            condition,
+            left,
+            right,
+        } => {
+            let width = output_width(&left, catalog)?;
+            let (left_predicates, remaining) = predicates.into_iter().partition(
+                |predicate| matches!(column_bounds(predicate), Some((_, last)) if last < width),
+            );
+            let plan = Plan::LeftOuterJoin {
+                condition,
+                left: Box::new(build_filters(*left, left_predicates)),
+                right,
+            };
+            (plan, remaining)
+        }
+        plan => (plan, predicates),
+    })
+}
+
+/// Split the predicates on the combined tuple of a join into the ones only
+/// referencing the left side, the ones only referencing the right side, with
+/// the columns shifted to the right input, and the others.
+fn split_by_side(
+    predicates: Vec<ScalarExpr>,
+    width: usize,
+) -> (Vec<ScalarExpr>, Vec<ScalarExpr>, Vec<ScalarExpr>) {
+    let mut left = vec![];
+    let mut right = vec![];
+    let mut remaining = vec![];
+    for predicate in predicates {
+        match column_bounds(&predicate) {
+            Some((_, last)) if last < width => left.push(predicate),
+            Some((first, _)) if first >= width => right.push(predicate.rewrite_columns(
+                &mut |column| {
+                    ScalarExpr::Column(Column {
+                        index: column.index - width,
+                    })
+                },
+                &mut |column| ScalarExpr::OuterColumn(column.clone()),
+            )),
+            _ => remaining.push(predicate),
+        }
+    }
+
+    (left, right, remaining)
+}
+
+/// The smallest and largest columns referenced by the predicate.
+pub(super) fn column_bounds(predicate: &ScalarExpr) -> Option<(usize, usize)> {
+    let mut columns = BTreeSet::new();
+    collect_columns(predicate, &mut columns);
+    Some((*columns.first()?, *columns.last()?))
+}
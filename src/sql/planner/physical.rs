@@ -0,0 +1,678 @@
+use super::{
+    index::KeyRange, AggregateExpr, Column, ExplainOptions, OrderBy, Plan, ScalarExpr, WindowExpr,
+};
+use crate::{
+    catalog::{pg_catalog::find_system_table, DatabaseCatalog},
+    sql::runtime::{DDLJob, DMLJob, RuntimeFilter},
+};
+
+/// Physical plan, the logical plan with the operators chosen to execute each
+/// node, e.g. hash join or nested-loop join. Each node corresponds to the node
+/// of logical plan at the same position, which has the same inputs, except
+/// for the operators answering a whole subtree, e.g. `RowCount`.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone)]
+pub enum PhysicalPlan {
+    Scan {
+        schema_name: String,
+        table_name: String,
+        projections: Option<Vec<usize>>,
+        /// Filters pushed down by the hash joins probing the scanned tuples.
+        runtime_filters: Vec<RuntimeFilter>,
+    },
+    IndexScan {
+        schema_name: String,
+        table_name: String,
+        index_name: String,
+        columns: Vec<usize>,
+        range: KeyRange,
+        index_only: bool,
+        projections: Option<Vec<usize>>,
+    },
+    Map {
+        scalars: Vec<ScalarExpr>,
+        input: Box<PhysicalPlan>,
+    },
+    Project {
+        projections: Vec<usize>,
+        input: Box<PhysicalPlan>,
+    },
+    Filter {
+        predicate: ScalarExpr,
+        input: Box<PhysicalPlan>,
+    },
+    /// Cross join, the right side is rescanned for each left tuple.
+    NestedLoopJoin {
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+    },
+    /// Equi-join with a hash table built on the keys of the right side.
+    HashJoin {
+        keys: Vec<(ScalarExpr, ScalarExpr)>,
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+        /// Filter built from the right side, pushed into the scan of the left
+        /// side.
+        runtime_filter: Option<RuntimeFilter>,
+    },
+    NestedLoopLeftOuterJoin {
+        condition: ScalarExpr,
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+    },
+    HashAggregate {
+        group_by: Vec<ScalarExpr>,
+        grouping_sets: Vec<Vec<usize>>,
+        aggregates: Vec<AggregateExpr>,
+        input: Box<PhysicalPlan>,
+    },
+    /// Aggregate of the input sorted on the group keys, the groups are
+    /// emitted one by one without a hash table.
+    StreamAggregate {
+        group_by: Vec<ScalarExpr>,
+        aggregates: Vec<AggregateExpr>,
+        input: Box<PhysicalPlan>,
+    },
+    /// Semi join with a hash set of the first column of the right side.
+    HashSemiJoin {
+        left_key: Option<ScalarExpr>,
+        anti: bool,
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+    },
+    /// Semi join with conditions on the combined tuple.
+    NestedLoopSemiJoin {
+        left_key: Option<ScalarExpr>,
+        conditions: Vec<ScalarExpr>,
+        anti: bool,
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+    },
+    /// Subquery executed for each distinct values of the parameters, the
+    /// results are cached.
+    Apply {
+        parameters: Vec<ScalarExpr>,
+        array: bool,
+        subquery: Box<PhysicalPlan>,
+        input: Box<PhysicalPlan>,
+    },
+    /// `count(*)` of a table without any filter or grouping, answered by the
+    /// number of tuples maintained in the heap instead of scanning it.
+    RowCount {
+        schema_name: String,
+        table_name: String,
+    },
+    Window {
+        window_exprs: Vec<WindowExpr>,
+        input: Box<PhysicalPlan>,
+    },
+    /// Sort of the whole input, which is buffered.
+    Sort {
+        order_by: Vec<OrderBy>,
+        input: Box<PhysicalPlan>,
+    },
+    /// Stops pulling the input once the limit is reached.
+    Limit {
+        limit: Option<ScalarExpr>,
+        offset: Option<ScalarExpr>,
+        input: Box<PhysicalPlan>,
+    },
+    HashDistinct {
+        input: Box<PhysicalPlan>,
+    },
+    UnionAll {
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+    },
+    Values {
+        rows: Vec<Vec<ScalarExpr>>,
+    },
+    Unnest {
+        arrays: Vec<ScalarExpr>,
+    },
+
+    DDL(DDLJob),
+    CreateTableAs {
+        schema_name: String,
+        table_name: String,
+        column_names: Vec<String>,
+        if_not_exists: bool,
+        input: Box<PhysicalPlan>,
+    },
+    DML(DMLJob, Option<Vec<ScalarExpr>>),
+    /// The logical plan is kept for the estimated rows of nodes.
+    Explain {
+        logical: Box<Plan>,
+        plan: Box<PhysicalPlan>,
+        options: ExplainOptions,
+    },
+    Use(String),
+    SetVariable(String, Option<String>),
+}
+
+impl PhysicalPlan {
+    /// Inputs of the plan node, in the same order as the logical plan.
+    pub fn children(&self) -> Vec<&PhysicalPlan> {
+        match self {
+            PhysicalPlan::Map { input, .. }
+            | PhysicalPlan::Project { input, .. }
+            | PhysicalPlan::Filter { input, .. }
+            | PhysicalPlan::HashAggregate { input, .. }
+            | PhysicalPlan::StreamAggregate { input, .. }
+            | PhysicalPlan::Window { input, .. }
+            | PhysicalPlan::HashDistinct { input }
+            | PhysicalPlan::Sort { input, .. }
+            | PhysicalPlan::Limit { input, .. }
+            | PhysicalPlan::CreateTableAs { input, .. } => vec![input],
+            PhysicalPlan::NestedLoopJoin { left, right }
+            | PhysicalPlan::HashJoin { left, right, .. }
+            | PhysicalPlan::NestedLoopLeftOuterJoin { left, right, .. }
+            | PhysicalPlan::HashSemiJoin { left, right, .. }
+            | PhysicalPlan::NestedLoopSemiJoin { left, right, .. }
+            | PhysicalPlan::UnionAll { left, right } => vec![left, right],
+            PhysicalPlan::Apply {
+                subquery, input, ..
+            } => vec![input, subquery],
+            PhysicalPlan::Explain { plan, .. } => vec![plan],
+            PhysicalPlan::Scan { .. }
+            | PhysicalPlan::IndexScan { .. }
+            | PhysicalPlan::RowCount { .. }
+            | PhysicalPlan::DDL(_)
+            | PhysicalPlan::DML(..)
+            | PhysicalPlan::Values { .. }
+            | PhysicalPlan::Unnest { .. }
+            | PhysicalPlan::Use(_)
+            | PhysicalPlan::SetVariable(..) => vec![],
+        }
+    }
+
+    /// Operators of the plan on one line, e.g. `HashJoin(Scan, Filter(Scan))`.
+    pub fn summary(&self) -> String {
+        let children = self.children();
+        if children.is_empty() {
+            return self.name().to_string();
+        }
+        let children = children
+            .iter()
+            .map(|child| child.summary())
+            .collect::<Vec<_>>();
+        format!("{}({})", self.name(), children.join(", "))
+    }
+
+    /// Name of the operator, shown by `EXPLAIN` with the details of the
+    /// logical plan node.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PhysicalPlan::Scan { .. } => "Scan",
+            PhysicalPlan::IndexScan { index_only, .. } => {
+                if *index_only {
+                    "IndexOnlyScan"
+                } else {
+                    "IndexScan"
+                }
+            }
+            PhysicalPlan::Map { .. } => "Map",
+            PhysicalPlan::Project { .. } => "Project",
+            PhysicalPlan::Filter { .. } => "Filter",
+            PhysicalPlan::NestedLoopJoin { .. } => "NestedLoopJoin",
+            PhysicalPlan::HashJoin { .. } => "HashJoin",
+            PhysicalPlan::NestedLoopLeftOuterJoin { .. } => "NestedLoopLeftOuterJoin",
+            PhysicalPlan::HashAggregate { .. } => "HashAggregate",
+            PhysicalPlan::StreamAggregate { .. } => "StreamAggregate",
+            PhysicalPlan::HashSemiJoin { anti, .. } => {
+                if *anti {
+                    "HashAntiJoin"
+                } else {
+                    "HashSemiJoin"
+                }
+            }
+            PhysicalPlan::NestedLoopSemiJoin { anti, .. } => {
+                if *anti {
+                    "NestedLoopAntiJoin"
+                } else {
+                    "NestedLoopSemiJoin"
+                }
+            }
+            PhysicalPlan::Apply { .. } => "Apply",
+            PhysicalPlan::RowCount { .. } => "RowCount",
+            PhysicalPlan::Window { .. } => "Window",
+            PhysicalPlan::HashDistinct { .. } => "HashDistinct",
+            PhysicalPlan::UnionAll { .. } => "UnionAll",
+            PhysicalPlan::Sort { .. } => "Sort",
+            PhysicalPlan::Limit { .. } => "Limit",
+            PhysicalPlan::Values { .. } => "Values",
+            PhysicalPlan::Unnest { .. } => "Unnest",
+            PhysicalPlan::DDL(job) => super::ddl_name(job),
+            PhysicalPlan::CreateTableAs { .. } => "CreateTableAs",
+            PhysicalPlan::DML(job, _) => super::dml_name(job),
+            PhysicalPlan::Explain { .. } => "Explain",
+            PhysicalPlan::Use(_) => "Use",
+            PhysicalPlan::SetVariable(..) => "SetVariable",
+        }
+    }
+}
+
+/// Choose the operators to execute the logical plan.
+pub fn plan_physical(plan: &Plan, catalog: &DatabaseCatalog) -> PhysicalPlan {
+    if let Some(row_count) = plan_row_count(plan) {
+        return row_count;
+    }
+
+    let input = |plan: &Plan| Box::new(plan_physical(plan, catalog));
+    match plan {
+        Plan::Get {
+            schema_name,
+            table_name,
+            projections,
+        } => PhysicalPlan::Scan {
+            schema_name: schema_name.clone(),
+            table_name: table_name.clone(),
+            projections: projections.clone(),
+            runtime_filters: vec![],
+        },
+        Plan::IndexScan {
+            schema_name,
+            table_name,
+            index_name,
+            columns,
+            range,
+            index_only,
+            projections,
+        } => PhysicalPlan::IndexScan {
+            schema_name: schema_name.clone(),
+            table_name: table_name.clone(),
+            index_name: index_name.clone(),
+            columns: columns.clone(),
+            range: range.clone(),
+            index_only: *index_only,
+            projections: projections.clone(),
+        },
+        Plan::Map { scalars, input: i } => PhysicalPlan::Map {
+            scalars: scalars.clone(),
+            input: input(i),
+        },
+        Plan::Project {
+            projections,
+            input: i,
+        } => PhysicalPlan::Project {
+            projections: projections.clone(),
+            input: input(i),
+        },
+        Plan::Filter {
+            predicate,
+            input: i,
+        } => PhysicalPlan::Filter {
+            predicate: predicate.clone(),
+            input: input(i),
+        },
+        // Equi-joins are executed with hash tables, the others are cross
+        // joins, which need the whole cross product anyway
+        Plan::Join { keys, left, right } if keys.is_empty() => PhysicalPlan::NestedLoopJoin {
+            left: input(left),
+            right: input(right),
+        },
+        Plan::Join { keys, left, right } => {
+            let mut left = input(left);
+            let runtime_filter = push_runtime_filter(keys, &mut left, catalog);
+            PhysicalPlan::HashJoin {
+                keys: keys.clone(),
+                left,
+                right: input(right),
+                runtime_filter,
+            }
+        }
+        Plan::LeftOuterJoin {
+            condition,
+            left,
+            right,
+        } => PhysicalPlan::NestedLoopLeftOuterJoin {
+            condition: condition.clone(),
+            left: input(left),
+            right: input(right),
+        },
+        Plan::Aggregate {
+            group_by,
+            grouping_sets,
+            aggregates,
+            input: i,
+        } if !group_by.is_empty()
+            && grouping_sets.is_empty()
+            && is_grouped(group_by, &sort_order(i)) =>
+        {
+            PhysicalPlan::StreamAggregate {
+                group_by: group_by.clone(),
+                aggregates: aggregates.clone(),
+                input: input(i),
+            }
+        }
+        Plan::Aggregate {
+            group_by,
+            grouping_sets,
+            aggregates,
+            input: i,
+        } => PhysicalPlan::HashAggregate {
+            group_by: group_by.clone(),
+            grouping_sets: grouping_sets.clone(),
+            aggregates: aggregates.clone(),
+            input: input(i),
+        },
+        // The conditions depend on both sides, so they cannot be answered by
+        // a hash set of the right side
+        Plan::SemiJoin {
+            left_key,
+            conditions,
+            anti,
+            left,
+            right,
+        } if conditions.is_empty() => PhysicalPlan::HashSemiJoin {
+            left_key: left_key.clone(),
+            anti: *anti,
+            left: input(left),
+            right: input(right),
+        },
+        Plan::SemiJoin {
+            left_key,
+            conditions,
+            anti,
+            left,
+            right,
+        } => PhysicalPlan::NestedLoopSemiJoin {
+            left_key: left_key.clone(),
+            conditions: conditions.clone(),
+            anti: *anti,
+            left: input(left),
+            right: input(right),
+        },
+        Plan::Apply {
+            parameters,
+            array,
+            subquery,
+            input: i,
+        } => PhysicalPlan::Apply {
+            parameters: parameters.clone(),
+            array: *array,
+            subquery: input(subquery),
+            input: input(i),
+        },
+        Plan::Window {
+            window_exprs,
+            input: i,
+        } => PhysicalPlan::Window {
+            window_exprs: window_exprs.clone(),
+            input: input(i),
+        },
+        Plan::Distinct { input: i } => PhysicalPlan::HashDistinct { input: input(i) },
+        Plan::UnionAll { left, right } => PhysicalPlan::UnionAll {
+            left: input(left),
+            right: input(right),
+        },
+        Plan::Sort { order_by, input: i } => PhysicalPlan::Sort {
+            order_by: order_by.clone(),
+            input: input(i),
+        },
+        Plan::Limit {
+            limit,
+            offset,
+            input: i,
+        } => PhysicalPlan::Limit {
+            limit: limit.clone(),
+            offset: offset.clone(),
+            input: input(i),
+        },
+        Plan::Values { rows } => PhysicalPlan::Values { rows: rows.clone() },
+        Plan::Unnest { arrays } => PhysicalPlan::Unnest {
+            arrays: arrays.clone(),
+        },
+        Plan::DDL(job) => PhysicalPlan::DDL(job.clone()),
+        Plan::CreateTableAs {
+            schema_name,
+            table_name,
+            column_names,
+            if_not_exists,
+            input: i,
+        } => PhysicalPlan::CreateTableAs {
+            schema_name: schema_name.clone(),
+            table_name: table_name.clone(),
+            column_names: column_names.clone(),
+            if_not_exists: *if_not_exists,
+            input: input(i),
+        },
+        Plan::DML(job, returning) => PhysicalPlan::DML(job.clone(), returning.clone()),
+        Plan::Explain { plan, options } => PhysicalPlan::Explain {
+            logical: Box::new(plan.as_ref().clone()),
+            plan: input(plan),
+            options: *options,
+        },
+        Plan::Use(schema_name) => PhysicalPlan::Use(schema_name.clone()),
+        Plan::SetVariable(name, value) => PhysicalPlan::SetVariable(name.clone(), value.clone()),
+    }
+}
+
+/// Answer `count(*)` of a table by the number of tuples in heap, the tables
+/// of system catalog are not stored in heap.
+fn plan_row_count(plan: &Plan) -> Option<PhysicalPlan> {
+    let Plan::Aggregate {
+        group_by,
+        grouping_sets,
+        aggregates,
+        input,
+    } = plan
+    else {
+        return None;
+    };
+    let Plan::Get {
+        schema_name,
+        table_name,
+        ..
+    } = input.as_ref()
+    else {
+        return None;
+    };
+
+    let is_count = matches!(aggregates.as_slice(), [AggregateExpr { func_name, args, filter: None, .. }] if func_name == "count" && args.is_empty());
+    if !group_by.is_empty()
+        || !grouping_sets.is_empty()
+        || !is_count
+        || find_system_table(schema_name, table_name).is_some()
+    {
+        return None;
+    }
+
+    Some(PhysicalPlan::RowCount {
+        schema_name: schema_name.clone(),
+        table_name: table_name.clone(),
+    })
+}
+
+/// Columns the output of the plan is sorted on, from the most significant one.
+fn sort_order(plan: &Plan) -> Vec<usize> {
+    // Only the leading sort columns that are kept by the projection
+    let project = |order: Vec<usize>, projections: &[usize]| {
+        order
+            .iter()
+            .map_while(|column| projections.iter().position(|p| p == column))
+            .collect()
+    };
+
+    match plan {
+        // Index entries are scanned in the order of keys
+        Plan::IndexScan {
+            columns,
+            projections: Some(projections),
+            ..
+        } => project(columns.clone(), projections),
+        Plan::IndexScan { columns, .. } => columns.clone(),
+        Plan::Project { projections, input } => project(sort_order(input), projections),
+        Plan::Filter { input, .. } | Plan::Map { input, .. } | Plan::Limit { input, .. } => {
+            sort_order(input)
+        }
+        Plan::Sort { order_by, .. } => order_by
+            .iter()
+            .map_while(|order| match &order.expr {
+                ScalarExpr::Column(column) => Some(column.index),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Whether the tuples of the same group keys are adjacent in the sort order,
+/// i.e. the group keys are columns making up a prefix of the sort order.
+fn is_grouped(group_by: &[ScalarExpr], sort_order: &[usize]) -> bool {
+    let mut columns = Vec::with_capacity(group_by.len());
+    for scalar in group_by {
+        let ScalarExpr::Column(column) = scalar else {
+            return false;
+        };
+        if !columns.contains(&column.index) {
+            columns.push(column.index);
+        }
+    }
+
+    columns.len() <= sort_order.len()
+        && sort_order[..columns.len()]
+            .iter()
+            .all(|column| columns.contains(column))
+}
+
+/// Push a runtime filter on the left keys of hash join into the scan of the
+/// table they come from, all the keys if they come from the same table,
+/// otherwise the first key which can be pushed down.
+fn push_runtime_filter(
+    keys: &[(ScalarExpr, ScalarExpr)],
+    left: &mut PhysicalPlan,
+    catalog: &DatabaseCatalog,
+) -> Option<RuntimeFilter> {
+    let columns = keys
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (key, _))| match key {
+            ScalarExpr::Column(Column { index }) => Some((i, *index)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let candidates = std::iter::once(columns.clone()).chain(columns.iter().map(|c| vec![*c]));
+    for candidate in candidates.filter(|candidate| !candidate.is_empty()) {
+        let (keys, columns): (Vec<_>, Vec<_>) = candidate.into_iter().unzip();
+        if let Some((runtime_filters, columns)) = probe_scan(left, columns, catalog) {
+            let runtime_filter = RuntimeFilter::new(keys, columns);
+            runtime_filters.push(runtime_filter.clone());
+            return Some(runtime_filter);
+        }
+    }
+
+    None
+}
+
+/// Find the scan producing the columns, through the operators which don't
+/// produce any tuple whose columns aren't from a scanned tuple, returns the
+/// runtime filters of the scan and the columns of the scanned table.
+fn probe_scan<'a>(
+    plan: &'a mut PhysicalPlan,
+    columns: Vec<usize>,
+    catalog: &DatabaseCatalog,
+) -> Option<(&'a mut Vec<RuntimeFilter>, Vec<usize>)> {
+    let project = |projections: &[usize], columns: Vec<usize>| {
+        columns
+            .into_iter()
+            .map(|column| projections.get(column).copied())
+            .collect::<Option<Vec<_>>>()
+    };
+    match plan {
+        PhysicalPlan::Scan {
+            schema_name,
+            table_name,
+            projections,
+            runtime_filters,
+        } => {
+            if find_system_table(schema_name, table_name).is_some() {
+                return None;
+            }
+            let columns = match projections {
+                Some(projections) => project(projections, columns)?,
+                None => columns,
+            };
+            Some((runtime_filters, columns))
+        }
+        PhysicalPlan::Filter { input, .. }
+        | PhysicalPlan::Sort { input, .. }
+        | PhysicalPlan::HashDistinct { input }
+        | PhysicalPlan::HashSemiJoin { left: input, .. }
+        | PhysicalPlan::NestedLoopSemiJoin { left: input, .. } => {
+            probe_scan(input, columns, catalog)
+        }
+        PhysicalPlan::Project { projections, input } => {
+            let columns = project(projections, columns)?;
+            probe_scan(input, columns, catalog)
+        }
+        // The appended columns are computed
+        PhysicalPlan::Map { input, .. }
+        | PhysicalPlan::Apply { input, .. }
+        | PhysicalPlan::NestedLoopLeftOuterJoin { left: input, .. } => {
+            let width = output_width(input, catalog)?;
+            if columns.iter().all(|column| *column < width) {
+                probe_scan(input, columns, catalog)
+            } else {
+                None
+            }
+        }
+        PhysicalPlan::HashJoin { left, right, .. }
+        | PhysicalPlan::NestedLoopJoin { left, right } => {
+            let width = output_width(left, catalog)?;
+            if columns.iter().all(|column| *column < width) {
+                probe_scan(left, columns, catalog)
+            } else if columns.iter().all(|column| *column >= width) {
+                let columns = columns.into_iter().map(|column| column - width).collect();
+                probe_scan(right, columns, catalog)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Number of the output columns of the plan, `None` if it's not known.
+fn output_width(plan: &PhysicalPlan, catalog: &DatabaseCatalog) -> Option<usize> {
+    match plan {
+        PhysicalPlan::Scan {
+            projections: Some(projections),
+            ..
+        }
+        | PhysicalPlan::IndexScan {
+            projections: Some(projections),
+            ..
+        }
+        | PhysicalPlan::Project { projections, .. } => Some(projections.len()),
+        PhysicalPlan::Scan {
+            schema_name,
+            table_name,
+            ..
+        } => match find_system_table(schema_name, table_name) {
+            Some(table_def) => Some(table_def.columns.len()),
+            None => Some(
+                catalog
+                    .find_table_by_name(schema_name, table_name)
+                    .ok()??
+                    .columns
+                    .len(),
+            ),
+        },
+        PhysicalPlan::Map { scalars, input } => Some(output_width(input, catalog)? + scalars.len()),
+        PhysicalPlan::Apply { input, .. } => Some(output_width(input, catalog)? + 1),
+        PhysicalPlan::Filter { input, .. }
+        | PhysicalPlan::Sort { input, .. }
+        | PhysicalPlan::Limit { input, .. }
+        | PhysicalPlan::HashDistinct { input }
+        | PhysicalPlan::UnionAll { left: input, .. }
+        | PhysicalPlan::HashSemiJoin { left: input, .. }
+        | PhysicalPlan::NestedLoopSemiJoin { left: input, .. } => output_width(input, catalog),
+        PhysicalPlan::HashJoin { left, right, .. }
+        | PhysicalPlan::NestedLoopJoin { left, right }
+        | PhysicalPlan::NestedLoopLeftOuterJoin { left, right, .. } => {
+            Some(output_width(left, catalog)? + output_width(right, catalog)?)
+        }
+        _ => None,
+    }
+}
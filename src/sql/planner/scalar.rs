@@ -1,7 +1,18 @@
-use sqlparser::ast::{self, Expr, Function, FunctionArgExpr, Ident};
+use sqlparser::ast::{self, Expr, Function, FunctionArgExpr, Ident, OrderByExpr, WindowType};
 
-use super::{bind_context::BindContext, scope::Scope, ScalarExpr};
-use crate::core::{Datum, ErrorKind, SQLError};
+use super::{
+    bind_context::BindContext, scope::Scope, AggregateExpr, Column, OrderBy, ScalarExpr, WindowExpr,
+};
+use crate::{
+    core::{sqlstate, Datum, ErrorKind, IntWidth, SQLError, Type},
+    sql::{
+        expression::{
+            type_check::cast_function_name,
+            window::{WindowFrame, WindowFrameBound, WindowFrameUnits},
+        },
+        session::to_pg_type,
+    },
+};
 
 pub fn bind_scalar(
     ctx: &mut BindContext,
@@ -16,22 +27,171 @@ pub fn bind_scalar(
         Expr::Identifier(ident) => bind_ident(ctx, scope, &[ident.clone()]),
         Expr::CompoundIdentifier(idents) => bind_ident(ctx, scope, idents),
         Expr::Value(literal) => bind_literal(literal),
+        Expr::TypedString { data_type, value } => bind_typed_string(data_type, value),
         Expr::Function(func) => bind_function(ctx, scope, func),
 
         Expr::BinaryOp { left, op, right } => bind_binary_op(ctx, scope, left, op, right),
+        // Only `= ANY(array)` is supported among the array comparisons
+        Expr::AnyOp {
+            left,
+            compare_op: ast::BinaryOperator::Eq,
+            right,
+        } => Ok(ScalarExpr::FunctionCall(
+            "= any".to_string(),
+            vec![
+                bind_scalar(ctx, scope, left)?,
+                bind_scalar(ctx, scope, right)?,
+            ],
+        )),
+        Expr::JsonAccess {
+            left,
+            operator,
+            right,
+        } => bind_json_access(ctx, scope, left, operator, right),
+        Expr::UnaryOp { op, expr } => bind_unary_op(ctx, scope, op, expr),
 
-        _ => unimplemented!(),
+        Expr::InSubquery { .. } | Expr::Exists { .. } => Err(SQLError::new(
+            ErrorKind::PlannerError,
+            "subquery predicate is only supported as a conjunct of WHERE clause",
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+        // Scalar subqueries are bound before the expressions containing them,
+        // except for the clauses they are not supported
+        Expr::Subquery(_) | Expr::ArraySubquery(_) => Err(SQLError::new(
+            ErrorKind::PlannerError,
+            "subquery is only supported in WHERE, HAVING, SELECT and ORDER BY clauses",
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
+
+        Expr::Nested(expr) => bind_scalar(ctx, scope, expr),
+        Expr::Cast {
+            expr, data_type, ..
+        } => bind_cast(ctx, scope, expr, data_type),
+        // There is only the default collation
+        Expr::Collate { expr, .. } => bind_scalar(ctx, scope, expr),
+        Expr::Case {
+            operand,
+            conditions,
+            results,
+            else_result,
+        } => bind_case(ctx, scope, operand, conditions, results, else_result),
+        Expr::Array(array) => Ok(ScalarExpr::FunctionCall(
+            "array".to_string(),
+            array
+                .elem
+                .iter()
+                .map(|elem| bind_scalar(ctx, scope, elem))
+                .collect::<Result<Vec<_>, _>>()?,
+        )),
+        // `a[i][j]` is bound as `(a[i])[j]`
+        Expr::ArrayIndex { obj, indexes } => {
+            indexes
+                .iter()
+                .try_fold(bind_scalar(ctx, scope, obj)?, |array, index| {
+                    Ok(ScalarExpr::FunctionCall(
+                        "[]".to_string(),
+                        vec![array, bind_scalar(ctx, scope, index)?],
+                    ))
+                })
+        }
+        Expr::IsNull(expr) => Ok(ScalarExpr::FunctionCall(
+            "is_null".to_string(),
+            vec![bind_scalar(ctx, scope, expr)?],
+        )),
+        Expr::IsNotNull(expr) => Ok(ScalarExpr::FunctionCall(
+            "is_not_null".to_string(),
+            vec![bind_scalar(ctx, scope, expr)?],
+        )),
+        Expr::IsTrue(expr) => bind_boolean_test(ctx, scope, "is_true", expr),
+        Expr::IsNotTrue(expr) => bind_boolean_test(ctx, scope, "is_not_true", expr),
+        Expr::IsFalse(expr) => bind_boolean_test(ctx, scope, "is_false", expr),
+        Expr::IsNotFalse(expr) => bind_boolean_test(ctx, scope, "is_not_false", expr),
+        Expr::IsUnknown(expr) => bind_boolean_test(ctx, scope, "is_unknown", expr),
+        Expr::IsNotUnknown(expr) => bind_boolean_test(ctx, scope, "is_not_unknown", expr),
+        Expr::IsDistinctFrom(left, right) => Ok(ScalarExpr::FunctionCall(
+            "is_distinct_from".to_string(),
+            vec![
+                bind_scalar(ctx, scope, left)?,
+                bind_scalar(ctx, scope, right)?,
+            ],
+        )),
+        Expr::IsNotDistinctFrom(left, right) => Ok(ScalarExpr::FunctionCall(
+            "is_not_distinct_from".to_string(),
+            vec![
+                bind_scalar(ctx, scope, left)?,
+                bind_scalar(ctx, scope, right)?,
+            ],
+        )),
+        Expr::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => bind_between(ctx, scope, expr, *negated, low, high),
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => bind_in_list(ctx, scope, expr, list, *negated),
+        Expr::Like {
+            negated,
+            expr,
+            pattern,
+            escape_char,
+        } => bind_like(ctx, scope, expr, pattern, *escape_char, false, *negated),
+        Expr::ILike {
+            negated,
+            expr,
+            pattern,
+            escape_char,
+        } => bind_like(ctx, scope, expr, pattern, *escape_char, true, *negated),
+
+        _ => Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!("expression is not supported: {}", expr),
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
     }
 }
 
 pub fn bind_ident(
-    _ctx: &mut BindContext,
+    ctx: &mut BindContext,
     scope: &Scope,
     qualified_ident: &[Ident],
 ) -> Result<ScalarExpr, SQLError> {
     if let Some(column) = scope.resolve_column(qualified_ident)? {
         let expr = ScalarExpr::Column(column);
         Ok(expr)
+    } else if let Some(column) = scope
+        .ungrouped
+        .as_ref()
+        .map(|from_scope| from_scope.resolve_column(qualified_ident))
+        .transpose()?
+        .flatten()
+    {
+        let from_scope = scope.ungrouped.as_ref().unwrap();
+        let variable = &from_scope.variables[column.index];
+        let name = match &variable.prefix {
+            Some(prefix) => format!("{}.{}", prefix.table_name, variable.name),
+            None => variable.name.clone(),
+        };
+        Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!(
+                "column \"{}\" must appear in the GROUP BY clause or be used in an aggregate function",
+                name
+            ),
+        )
+        .with_code(sqlstate::GROUPING_ERROR))
+    } else if let Some(column) = ctx
+        .outer_scope()
+        .map(|outer_scope| outer_scope.resolve_column(qualified_ident))
+        .transpose()?
+        .flatten()
+    {
+        // Reference to the column of the outer query.
+        let expr = ScalarExpr::OuterColumn(column);
+        Ok(expr)
     } else {
         Err(SQLError::new(
             ErrorKind::PlannerError,
@@ -43,80 +203,673 @@ pub fn bind_ident(
                     .collect::<Vec<_>>()
                     .join(".")
             ),
-        ))
+        )
+        .with_code(sqlstate::UNDEFINED_COLUMN))
     }
 }
 
 pub fn bind_literal(literal: &ast::Value) -> Result<ScalarExpr, SQLError> {
+    if let ast::Value::Placeholder(placeholder) = literal {
+        return bind_parameter(placeholder);
+    }
+
     Ok(ScalarExpr::Literal(Datum::try_from(literal)?))
 }
 
+/// Typed string literal like `JSON '{"a": 1}'`, the value is cast to the type
+/// while binding.
+pub fn bind_typed_string(data_type: &ast::DataType, value: &str) -> Result<ScalarExpr, SQLError> {
+    let typ = Type::try_from(data_type)?;
+    match Datum::String(value.into()).cast(&typ) {
+        Datum::Null => Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!(
+                "invalid input syntax for type {}: \"{}\"",
+                to_pg_type(&typ).name(),
+                value
+            ),
+        )
+        .with_code(sqlstate::INVALID_TEXT_REPRESENTATION)),
+        datum => {
+            if let Some(int_width) = IntWidth::from_data_type(data_type) {
+                int_width.check(&datum)?;
+            }
+            Ok(ScalarExpr::Literal(datum))
+        }
+    }
+}
+
+/// Bind parameter placeholder in form of `$n`, the index starts from 1.
+pub fn bind_parameter(placeholder: &str) -> Result<ScalarExpr, SQLError> {
+    placeholder
+        .strip_prefix('$')
+        .and_then(|index| index.parse::<usize>().ok())
+        .filter(|index| *index > 0)
+        .map(|index| ScalarExpr::Parameter(index - 1))
+        .ok_or_else(|| {
+            SQLError::new(
+                ErrorKind::PlannerError,
+                format!("invalid parameter placeholder: {}", placeholder),
+            )
+            .with_code(sqlstate::SYNTAX_ERROR)
+        })
+}
+
 pub fn bind_function(
     ctx: &mut BindContext,
     scope: &Scope,
     func: &Function,
 ) -> Result<ScalarExpr, SQLError> {
+    if func.over.is_some() {
+        // Window functions should have been resolved by the scope
+        return Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!("window function is not allowed here: {}", func),
+        )
+        .with_code(sqlstate::WINDOWING_ERROR));
+    }
+
+    if func.filter.is_some() {
+        return Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!(
+                "FILTER specified, but {} is not an aggregate function",
+                func.name
+            ),
+        )
+        .with_code(sqlstate::WRONG_OBJECT_TYPE));
+    }
+    if !func.order_by.is_empty() {
+        return Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!(
+                "ORDER BY specified, but {} is not an aggregate function",
+                func.name
+            ),
+        )
+        .with_code(sqlstate::WRONG_OBJECT_TYPE));
+    }
+
     let args = func
         .args
         .iter()
         .map(|arg| match arg {
-            ast::FunctionArg::Unnamed(arg) => match arg {
-                FunctionArgExpr::Expr(arg) => bind_scalar(ctx, scope, arg),
-                _ => unimplemented!(),
-            },
-            ast::FunctionArg::Named { .. } => unimplemented!(),
+            ast::FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) => bind_scalar(ctx, scope, arg),
+            ast::FunctionArg::Unnamed(_) => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "{}(*) specified, but {} is not an aggregate function",
+                    func.name, func.name
+                ),
+            )
+            .with_code(sqlstate::WRONG_OBJECT_TYPE)),
+            ast::FunctionArg::Named { .. } => Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!(
+                    "named arguments of function {} are not supported",
+                    func.name
+                ),
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED)),
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    let func = ScalarExpr::FunctionCall(func.name.to_string(), args);
+    // Builtin functions are in the `pg_catalog` schema
+    let name = match func.name.0.as_slice() {
+        [schema, name] if schema.value.eq_ignore_ascii_case("pg_catalog") => name.to_string(),
+        _ => func.name.to_string(),
+    };
+    let func = ScalarExpr::FunctionCall(name.to_lowercase(), args);
 
     Ok(func)
 }
 
+/// Casts are bound to the cast functions, and the casts to the object
+/// identifier types `regclass`, `regnamespace` and `regtype` are bound to the
+/// functions looking up the names.
+fn bind_cast(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    expr: &Expr,
+    data_type: &ast::DataType,
+) -> Result<ScalarExpr, SQLError> {
+    let type_name = match data_type {
+        ast::DataType::Regclass => Some("regclass".to_string()),
+        ast::DataType::Custom(name, _) => name.0.last().map(|name| name.value.to_lowercase()),
+        _ => None,
+    };
+    match type_name.as_deref() {
+        Some(name @ ("regclass" | "regnamespace")) => {
+            return Ok(ScalarExpr::FunctionCall(
+                name.to_string(),
+                vec![bind_scalar(ctx, scope, expr)?],
+            ))
+        }
+        Some("regtype") => {
+            return Ok(ScalarExpr::FunctionCall(
+                "format_type".to_string(),
+                vec![
+                    bind_scalar(ctx, scope, expr)?,
+                    ScalarExpr::Literal(Datum::Null),
+                ],
+            ))
+        }
+        _ => {}
+    }
+
+    // Literals are cast while binding, so the invalid ones are reported early
+    match expr {
+        Expr::Value(
+            ast::Value::SingleQuotedString(value) | ast::Value::EscapedStringLiteral(value),
+        )
+        | Expr::Value(ast::Value::DollarQuotedString(ast::DollarQuotedString { value, .. })) => {
+            return bind_typed_string(data_type, value);
+        }
+        _ => {}
+    }
+
+    let typ = Type::try_from(data_type)?;
+    // Casts to the narrower integer types are range checked, and the casts to
+    // arrays are named after the casts of elements, e.g. `to_int[]`
+    let func_name = match (IntWidth::from_data_type(data_type), &typ) {
+        (Some(IntWidth::Int2), _) => Some("to_int2".to_string()),
+        (Some(IntWidth::Int4), _) => Some("to_int4".to_string()),
+        (_, Type::Array(elem_type)) => {
+            cast_function_name(elem_type).map(|name| format!("{}[]", name))
+        }
+        _ => cast_function_name(&typ).map(str::to_string),
+    };
+    let func_name = func_name.ok_or_else(|| {
+        SQLError::new(
+            ErrorKind::PlannerError,
+            format!("cannot cast to type {}", to_pg_type(&typ).name()),
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+    })?;
+
+    Ok(ScalarExpr::FunctionCall(
+        func_name,
+        vec![bind_scalar(ctx, scope, expr)?],
+    ))
+}
+
+/// `CASE` is bound to the special form, where the operand of simple `CASE`
+/// is compared with each value, and the missing `ELSE` result is NULL.
+fn bind_case(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    operand: &Option<Box<Expr>>,
+    conditions: &[Expr],
+    results: &[Expr],
+    else_result: &Option<Box<Expr>>,
+) -> Result<ScalarExpr, SQLError> {
+    let operand = operand
+        .as_ref()
+        .map(|operand| bind_scalar(ctx, scope, operand))
+        .transpose()?;
+
+    let mut args = vec![];
+    for (condition, result) in conditions.iter().zip(results.iter()) {
+        let condition = bind_scalar(ctx, scope, condition)?;
+        let condition = match &operand {
+            Some(operand) => {
+                ScalarExpr::FunctionCall("=".to_string(), vec![operand.clone(), condition])
+            }
+            None => condition,
+        };
+        args.push(condition);
+        args.push(bind_scalar(ctx, scope, result)?);
+    }
+    args.push(match else_result {
+        Some(else_result) => bind_scalar(ctx, scope, else_result)?,
+        None => ScalarExpr::Literal(Datum::Null),
+    });
+
+    Ok(ScalarExpr::FunctionCall("case".to_string(), args))
+}
+
 pub fn bind_aggregate_function(
     ctx: &mut BindContext,
     scope: &Scope,
     func: &Function,
-) -> Result<(String, Vec<ScalarExpr>), SQLError> {
+) -> Result<AggregateExpr, SQLError> {
+    let not_supported = |feature: String| {
+        SQLError::new(ErrorKind::PlannerError, feature).with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+    };
     if func.distinct {
-        unimplemented!();
+        return Err(not_supported(format!(
+            "DISTINCT is not supported in aggregate function {}",
+            func.name
+        )));
     }
 
+    let filter = func
+        .filter
+        .as_ref()
+        .map(|filter| bind_scalar(ctx, scope, filter))
+        .transpose()?;
+    let order_by = func
+        .order_by
+        .iter()
+        .map(|order_by| {
+            Ok(bind_order_by(
+                order_by,
+                bind_scalar(ctx, scope, &order_by.expr)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, SQLError>>()?;
+    let func_args = func.args.iter().collect::<Vec<_>>();
+
     if func.name.to_string().to_lowercase() == "count" {
-        if func.args.len() > 1 {
+        if func_args.len() > 1 {
             return Err(SQLError::new(
                 ErrorKind::CatalogError,
                 "cannot find function count with given arguments",
-            ));
+            )
+            .with_code(sqlstate::UNDEFINED_FUNCTION));
         }
 
-        if let Some(arg) = func.args.get(0) {
-            match arg {
-                ast::FunctionArg::Unnamed(arg) => match arg {
-                    // Rewrite count(*) to count()
-                    FunctionArgExpr::Wildcard => return Ok(("count".to_string(), vec![])),
-                    _ => unimplemented!(),
-                },
-                _ => unimplemented!(),
-            }
+        // Rewrite count(*) to count(), `count(expr)` counts the non-null
+        // values of expr
+        if matches!(
+            func_args.first(),
+            None | Some(ast::FunctionArg::Unnamed(FunctionArgExpr::Wildcard))
+        ) {
+            return Ok(AggregateExpr {
+                func_name: "count".to_string(),
+                args: vec![],
+                filter,
+                order_by,
+            });
         }
-
-        return Ok(("count".to_string(), vec![]));
     }
 
-    let args = func
+    let args = func_args
+        .into_iter()
+        .map(|arg| match arg {
+            ast::FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) => bind_scalar(ctx, scope, arg),
+            _ => Err(not_supported(format!(
+                "argument {} of aggregate function {} is not supported",
+                arg, func.name
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(AggregateExpr {
+        func_name: func.name.to_string().to_lowercase(),
+        args,
+        filter,
+        order_by,
+    })
+}
+
+/// `grouping(a, b)` is a bit mask of the arguments, where a bit is set if the
+/// argument is not in the grouping set of the row. It is bound to a `CASE` on
+/// the index of grouping set, which follows the group keys.
+pub fn bind_grouping_function(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    func: &Function,
+    group_by: &[ScalarExpr],
+    grouping_sets: &[Vec<usize>],
+) -> Result<ScalarExpr, SQLError> {
+    let keys = func
         .args
         .iter()
-        .map(|arg| match arg {
-            ast::FunctionArg::Unnamed(arg) => match arg {
-                FunctionArgExpr::Expr(arg) => bind_scalar(ctx, scope, arg),
-                _ => unimplemented!(),
-            },
-            ast::FunctionArg::Named { .. } => unimplemented!(),
+        .map(|arg| {
+            let arg = match arg {
+                ast::FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) => arg,
+                _ => {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!("argument {} of GROUPING is not supported", arg),
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+                }
+            };
+            let arg = bind_scalar(ctx, scope, arg)?;
+            group_by.iter().position(|key| *key == arg).ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::PlannerError,
+                    "arguments to GROUPING must be grouping expressions of the associated query level",
+                )
+                .with_code(sqlstate::GROUPING_ERROR)
+            })
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    Ok((func.name.to_string(), args))
+    // All the group keys are in the single grouping set of plain `GROUP BY`
+    if grouping_sets.is_empty() {
+        return Ok(ScalarExpr::Literal(Datum::Int(0)));
+    }
+
+    let grouping_set = ScalarExpr::Column(Column {
+        index: group_by.len(),
+    });
+    let mut args = vec![];
+    for (i, set) in grouping_sets.iter().enumerate() {
+        let mask = keys
+            .iter()
+            .fold(0, |mask, key| mask << 1 | i64::from(!set.contains(key)));
+        args.push(ScalarExpr::FunctionCall(
+            "=".to_string(),
+            vec![
+                grouping_set.clone(),
+                ScalarExpr::Literal(Datum::Int(i as i64)),
+            ],
+        ));
+        args.push(ScalarExpr::Literal(Datum::Int(mask)));
+    }
+    args.push(ScalarExpr::Literal(Datum::Null));
+
+    Ok(ScalarExpr::FunctionCall("case".to_string(), args))
+}
+
+pub fn bind_window_function(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    func: &Function,
+) -> Result<WindowExpr, SQLError> {
+    let window_spec = match &func.over {
+        Some(WindowType::WindowSpec(window_spec)) => window_spec,
+        Some(WindowType::NamedWindow(_)) => {
+            return Err(
+                SQLError::new(ErrorKind::PlannerError, "named window is not supported")
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED),
+            )
+        }
+        None => {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("not a window function: {}", func),
+            ))
+        }
+    };
+
+    // The arguments are bound in the same way as aggregate functions
+    let AggregateExpr {
+        func_name,
+        args,
+        filter,
+        order_by,
+    } = bind_aggregate_function(ctx, scope, func)?;
+    if filter.is_some() {
+        return Err(SQLError::new(
+            ErrorKind::PlannerError,
+            "FILTER is not implemented for window functions",
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+    }
+    if !order_by.is_empty() {
+        return Err(SQLError::new(
+            ErrorKind::PlannerError,
+            "aggregate ORDER BY is not implemented for window functions",
+        )
+        .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+    }
+
+    let partition_by = window_spec
+        .partition_by
+        .iter()
+        .map(|expr| bind_scalar(ctx, scope, expr))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let order_by = window_spec
+        .order_by
+        .iter()
+        .map(|order_by| {
+            Ok(bind_order_by(
+                order_by,
+                bind_scalar(ctx, scope, &order_by.expr)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, SQLError>>()?;
+
+    let frame = window_spec
+        .window_frame
+        .as_ref()
+        .map(|frame| bind_window_frame(ctx, scope, frame, order_by.len()))
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(WindowExpr {
+        func_name: func_name.to_lowercase(),
+        args,
+        partition_by,
+        order_by,
+        frame,
+    })
+}
+
+/// `{ ROWS | RANGE } [BETWEEN] start [AND end]`, the end is the current row
+/// if omitted.
+fn bind_window_frame(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    frame: &ast::WindowFrame,
+    num_order_keys: usize,
+) -> Result<WindowFrame, SQLError> {
+    let windowing_error = |message: &str| {
+        SQLError::new(ErrorKind::PlannerError, message).with_code(sqlstate::WINDOWING_ERROR)
+    };
+
+    let units = match frame.units {
+        ast::WindowFrameUnits::Rows => WindowFrameUnits::Rows,
+        ast::WindowFrameUnits::Range => WindowFrameUnits::Range,
+        ast::WindowFrameUnits::Groups => {
+            return Err(
+                SQLError::new(ErrorKind::PlannerError, "GROUPS frame is not supported")
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED),
+            )
+        }
+    };
+    let start = bind_window_frame_bound(ctx, scope, units, &frame.start_bound, "starting")?;
+    let end = frame
+        .end_bound
+        .as_ref()
+        .map(|bound| bind_window_frame_bound(ctx, scope, units, bound, "ending"))
+        .transpose()?
+        .unwrap_or(WindowFrameBound::CurrentRow);
+
+    match (&start, &end) {
+        (WindowFrameBound::UnboundedFollowing, _) => {
+            Err(windowing_error("frame start cannot be UNBOUNDED FOLLOWING"))
+        }
+        (_, WindowFrameBound::UnboundedPreceding) => {
+            Err(windowing_error("frame end cannot be UNBOUNDED PRECEDING"))
+        }
+        (WindowFrameBound::CurrentRow, WindowFrameBound::Preceding(_)) => Err(windowing_error(
+            "frame starting from current row cannot have preceding rows",
+        )),
+        (
+            WindowFrameBound::Following(_),
+            WindowFrameBound::Preceding(_) | WindowFrameBound::CurrentRow,
+        ) => Err(windowing_error(
+            "frame starting from following row cannot have preceding rows",
+        )),
+        _ if units == WindowFrameUnits::Range
+            && (start.offset().is_some() || end.offset().is_some())
+            && num_order_keys != 1 =>
+        {
+            Err(windowing_error(
+                "RANGE with offset PRECEDING/FOLLOWING requires exactly one ORDER BY column",
+            ))
+        }
+        _ => Ok(WindowFrame { units, start, end }),
+    }
+}
+
+/// Bound of window frame, the offset must be a non-negative constant, which
+/// is an integer for `ROWS`.
+fn bind_window_frame_bound(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    units: WindowFrameUnits,
+    bound: &ast::WindowFrameBound,
+    side: &str,
+) -> Result<WindowFrameBound, SQLError> {
+    let units_name = match units {
+        WindowFrameUnits::Rows => "ROWS",
+        WindowFrameUnits::Range => "RANGE",
+    };
+    let mut offset = |expr: &Expr| {
+        let offset = match bind_scalar(ctx, scope, expr)? {
+            ScalarExpr::Literal(offset) => offset,
+            _ => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!("argument of {} must not contain variables", units_name),
+                )
+                .with_code(sqlstate::INVALID_COLUMN_REFERENCE))
+            }
+        };
+        let negative = match &offset {
+            Datum::Int(offset) => *offset < 0,
+            Datum::Float(offset) if units == WindowFrameUnits::Range => *offset < 0.0,
+            Datum::Null => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!("frame {} offset must not be null", side),
+                )
+                .with_code(sqlstate::NULL_VALUE_NOT_ALLOWED))
+            }
+            _ => {
+                return Err(SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!("invalid argument of {}: {}", units_name, offset),
+                )
+                .with_code(sqlstate::DATATYPE_MISMATCH))
+            }
+        };
+        if negative {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                format!("frame {} offset must not be negative", side),
+            )
+            .with_code(sqlstate::INVALID_PRECEDING_OR_FOLLOWING_SIZE));
+        }
+        Ok(offset)
+    };
+
+    Ok(match bound {
+        ast::WindowFrameBound::Preceding(None) => WindowFrameBound::UnboundedPreceding,
+        ast::WindowFrameBound::Preceding(Some(expr)) => WindowFrameBound::Preceding(offset(expr)?),
+        ast::WindowFrameBound::CurrentRow => WindowFrameBound::CurrentRow,
+        ast::WindowFrameBound::Following(Some(expr)) => WindowFrameBound::Following(offset(expr)?),
+        ast::WindowFrameBound::Following(None) => WindowFrameBound::UnboundedFollowing,
+    })
+}
+
+/// `expr IS [NOT] TRUE | FALSE | UNKNOWN`
+fn bind_boolean_test(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    func_name: &str,
+    expr: &Expr,
+) -> Result<ScalarExpr, SQLError> {
+    Ok(ScalarExpr::FunctionCall(
+        func_name.to_string(),
+        vec![bind_scalar(ctx, scope, expr)?],
+    ))
+}
+
+/// Sort key of the `ORDER BY` item, ascending by default.
+pub fn bind_order_by(order: &OrderByExpr, expr: ScalarExpr) -> OrderBy {
+    let asc = order.asc.unwrap_or(true);
+    OrderBy {
+        expr,
+        asc,
+        nulls_first: order.nulls_first.unwrap_or(!asc),
+    }
+}
+
+/// `x BETWEEN low AND high` is lowered to `x >= low AND x <= high`, and
+/// `x NOT BETWEEN low AND high` is lowered to `x < low OR x > high`.
+fn bind_between(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    expr: &Expr,
+    negated: bool,
+    low: &Expr,
+    high: &Expr,
+) -> Result<ScalarExpr, SQLError> {
+    let expr = bind_scalar(ctx, scope, expr)?;
+    let low = bind_scalar(ctx, scope, low)?;
+    let high = bind_scalar(ctx, scope, high)?;
+
+    let (low_op, high_op, logical_op) = if negated {
+        ("<", ">", "or")
+    } else {
+        (">=", "<=", "and")
+    };
+
+    Ok(ScalarExpr::FunctionCall(
+        logical_op.to_string(),
+        vec![
+            ScalarExpr::FunctionCall(low_op.to_string(), vec![expr.clone(), low]),
+            ScalarExpr::FunctionCall(high_op.to_string(), vec![expr, high]),
+        ],
+    ))
+}
+
+/// `x IN (a, b)` is lowered to `x = a OR x = b`, and `x NOT IN (a, b)` is
+/// lowered to `x <> a AND x <> b`, which keeps the semantics of NULL values.
+fn bind_in_list(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    expr: &Expr,
+    list: &[Expr],
+    negated: bool,
+) -> Result<ScalarExpr, SQLError> {
+    let expr = bind_scalar(ctx, scope, expr)?;
+
+    let (compare_op, logical_op) = if negated { ("<>", "and") } else { ("=", "or") };
+
+    list.iter()
+        .map(|item| {
+            Ok(ScalarExpr::FunctionCall(
+                compare_op.to_string(),
+                vec![expr.clone(), bind_scalar(ctx, scope, item)?],
+            ))
+        })
+        .reduce(|prev, next| {
+            Ok(ScalarExpr::FunctionCall(
+                logical_op.to_string(),
+                vec![prev?, next?],
+            ))
+        })
+        .unwrap_or_else(|| Err(SQLError::new(ErrorKind::PlannerError, "empty IN list")))
+}
+
+/// `LIKE` and `ILIKE` are bound to the functions named after the
+/// operators of Postgres, e.g. `~~` for `LIKE`.
+fn bind_like(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    expr: &Expr,
+    pattern: &Expr,
+    escape_char: Option<char>,
+    case_insensitive: bool,
+    negated: bool,
+) -> Result<ScalarExpr, SQLError> {
+    let func_name = match (case_insensitive, negated) {
+        (false, false) => "~~",
+        (true, false) => "~~*",
+        (false, true) => "!~~",
+        (true, true) => "!~~*",
+    };
+
+    let mut args = vec![
+        bind_scalar(ctx, scope, expr)?,
+        bind_scalar(ctx, scope, pattern)?,
+    ];
+    if let Some(escape_char) = escape_char {
+        args.push(ScalarExpr::Literal(Datum::String(
+            escape_char.to_string().into(),
+        )));
+    }
+
+    Ok(ScalarExpr::FunctionCall(func_name.to_string(), args))
 }
 
 fn bind_binary_op(
@@ -135,13 +888,244 @@ fn bind_binary_op(
         ast::BinaryOperator::LtEq => "<=",
         ast::BinaryOperator::Eq => "=",
         ast::BinaryOperator::NotEq => "<>",
-        _ => unimplemented!(),
+        ast::BinaryOperator::And => "and",
+        ast::BinaryOperator::Or => "or",
+        ast::BinaryOperator::PGRegexMatch => "~",
+        ast::BinaryOperator::PGRegexIMatch => "~*",
+        ast::BinaryOperator::PGRegexNotMatch => "!~",
+        ast::BinaryOperator::PGRegexNotIMatch => "!~*",
+        // `OPERATOR(pg_catalog.op)` is the builtin operator
+        ast::BinaryOperator::PGCustomBinaryOperator(parts) => match parts.as_slice() {
+            [schema, op] if schema == "pg_catalog" => op,
+            _ => return Err(unsupported_operator(op)),
+        },
+        _ => return Err(unsupported_operator(op)),
     };
 
     let left = bind_scalar(ctx, scope, left)?;
     let right = bind_scalar(ctx, scope, right)?;
 
+    let int_width = match func_name {
+        "+" | "-" => arithmetic_int_width(&[&left, &right]),
+        _ => None,
+    };
     let func = ScalarExpr::FunctionCall(func_name.to_string(), vec![left, right]);
 
-    Ok(func)
+    Ok(narrow_int(func, int_width))
+}
+
+/// Width of the integer result of arithmetic, which is the widest of the
+/// operands like Postgres, e.g. `integer + integer` is `integer`. `None` if
+/// it's `bigint`, or all the operands are literals, whose arithmetic is kept
+/// in `bigint`.
+fn arithmetic_int_width(args: &[&ScalarExpr]) -> Option<IntWidth> {
+    let mut has_narrowed = false;
+    let mut result = IntWidth::Int2;
+    for arg in args {
+        let int_width = match arg {
+            ScalarExpr::FunctionCall(name, _) if name == "to_int2" => IntWidth::Int2,
+            ScalarExpr::FunctionCall(name, _) if name == "to_int4" => IntWidth::Int4,
+            // Integer literals are `integer` if they fit
+            ScalarExpr::Literal(Datum::Int(v)) if i32::try_from(*v).is_ok() => {
+                result = result.max(IntWidth::Int4);
+                continue;
+            }
+            _ => return None,
+        };
+        has_narrowed = true;
+        result = result.max(int_width);
+    }
+
+    Some(result).filter(|_| has_narrowed)
+}
+
+/// Check the range of the integer result by the cast to its width, which is
+/// also how the width of the operands is known to the enclosing arithmetic.
+fn narrow_int(func: ScalarExpr, int_width: Option<IntWidth>) -> ScalarExpr {
+    let cast = match int_width {
+        Some(IntWidth::Int2) => "to_int2",
+        Some(IntWidth::Int4) => "to_int4",
+        Some(IntWidth::Int8) | None => return func,
+    };
+    ScalarExpr::FunctionCall(cast.to_string(), vec![func])
+}
+
+fn unsupported_operator(op: &dyn std::fmt::Display) -> SQLError {
+    SQLError::new(
+        ErrorKind::PlannerError,
+        format!("operator is not supported: {}", op),
+    )
+    .with_code(sqlstate::FEATURE_NOT_SUPPORTED)
+}
+
+/// JSON operators are bound to the functions named after the operators, and
+/// so are the containment operators `@>` and `<@`, which are parsed the same.
+fn bind_json_access(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    left: &Expr,
+    operator: &ast::JsonOperator,
+    right: &Expr,
+) -> Result<ScalarExpr, SQLError> {
+    if let Some(expr) = reassociate_json_access(left, operator, right) {
+        return bind_scalar(ctx, scope, &expr);
+    }
+
+    let func_name = match operator {
+        ast::JsonOperator::Arrow => "->",
+        ast::JsonOperator::LongArrow => "->>",
+        ast::JsonOperator::HashArrow => "#>",
+        ast::JsonOperator::HashLongArrow => "#>>",
+        // Containment of arrays
+        ast::JsonOperator::AtArrow => "@>",
+        ast::JsonOperator::ArrowAt => "<@",
+        _ => return Err(unsupported_operator(operator)),
+    };
+
+    let left = bind_scalar(ctx, scope, left)?;
+    let right = bind_scalar(ctx, scope, right)?;
+
+    Ok(ScalarExpr::FunctionCall(
+        func_name.to_string(),
+        vec![left, right],
+    ))
+}
+
+/// The parser takes everything after a JSON operator as its right operand,
+/// e.g. `a ->> 'k' = 'v'` is parsed as `a ->> ('k' = 'v')`. Rebuild the
+/// expression following Postgres, where JSON operators are left-associative,
+/// bind looser than arithmetic operators and tighter than the others.
+fn reassociate_json_access(
+    left: &Expr,
+    operator: &ast::JsonOperator,
+    right: &Expr,
+) -> Option<Expr> {
+    let access = |right: &Expr| {
+        Box::new(
+            reassociate_json_access(left, operator, right).unwrap_or_else(|| Expr::JsonAccess {
+                left: Box::new(left.clone()),
+                operator: *operator,
+                right: Box::new(right.clone()),
+            }),
+        )
+    };
+
+    let expr = match right {
+        Expr::JsonAccess {
+            left: inner,
+            operator: inner_op,
+            right: rest,
+        } => Expr::JsonAccess {
+            left: access(inner),
+            operator: *inner_op,
+            right: rest.clone(),
+        },
+        Expr::BinaryOp {
+            left: inner,
+            op,
+            right: rest,
+        } if !matches!(
+            op,
+            ast::BinaryOperator::Plus
+                | ast::BinaryOperator::Minus
+                | ast::BinaryOperator::Multiply
+                | ast::BinaryOperator::Divide
+                | ast::BinaryOperator::Modulo
+                | ast::BinaryOperator::PGExp
+        ) =>
+        {
+            Expr::BinaryOp {
+                left: access(inner),
+                op: op.clone(),
+                right: rest.clone(),
+            }
+        }
+        Expr::IsNull(inner) => Expr::IsNull(access(inner)),
+        Expr::IsNotNull(inner) => Expr::IsNotNull(access(inner)),
+        Expr::IsTrue(inner) => Expr::IsTrue(access(inner)),
+        Expr::IsNotTrue(inner) => Expr::IsNotTrue(access(inner)),
+        Expr::IsFalse(inner) => Expr::IsFalse(access(inner)),
+        Expr::IsNotFalse(inner) => Expr::IsNotFalse(access(inner)),
+        Expr::IsUnknown(inner) => Expr::IsUnknown(access(inner)),
+        Expr::IsNotUnknown(inner) => Expr::IsNotUnknown(access(inner)),
+        Expr::IsDistinctFrom(inner, rest) => Expr::IsDistinctFrom(access(inner), rest.clone()),
+        Expr::IsNotDistinctFrom(inner, rest) => {
+            Expr::IsNotDistinctFrom(access(inner), rest.clone())
+        }
+        Expr::Between {
+            expr: inner,
+            negated,
+            low,
+            high,
+        } => Expr::Between {
+            expr: access(inner),
+            negated: *negated,
+            low: low.clone(),
+            high: high.clone(),
+        },
+        Expr::InList {
+            expr: inner,
+            list,
+            negated,
+        } => Expr::InList {
+            expr: access(inner),
+            list: list.clone(),
+            negated: *negated,
+        },
+        Expr::Like {
+            negated,
+            expr: inner,
+            pattern,
+            escape_char,
+        } => Expr::Like {
+            negated: *negated,
+            expr: access(inner),
+            pattern: pattern.clone(),
+            escape_char: *escape_char,
+        },
+        Expr::ILike {
+            negated,
+            expr: inner,
+            pattern,
+            escape_char,
+        } => Expr::ILike {
+            negated: *negated,
+            expr: access(inner),
+            pattern: pattern.clone(),
+            escape_char: *escape_char,
+        },
+        _ => return None,
+    };
+
+    Some(expr)
+}
+
+fn bind_unary_op(
+    ctx: &mut BindContext,
+    scope: &Scope,
+    op: &ast::UnaryOperator,
+    expr: &Expr,
+) -> Result<ScalarExpr, SQLError> {
+    // Fold the negative numeric literals, so that the minimum
+    // value of integer can be represented.
+    if let (ast::UnaryOperator::Minus, Expr::Value(ast::Value::Number(v, long))) = (op, expr) {
+        return bind_literal(&ast::Value::Number(format!("-{}", v), *long));
+    }
+
+    let func_name = match op {
+        ast::UnaryOperator::Not => "not",
+        ast::UnaryOperator::Minus => "neg",
+        ast::UnaryOperator::Plus => "pos",
+        _ => return Err(unsupported_operator(op)),
+    };
+
+    let arg = bind_scalar(ctx, scope, expr)?;
+
+    let int_width = match func_name {
+        "neg" => arithmetic_int_width(&[&arg]),
+        _ => None,
+    };
+    let func = ScalarExpr::FunctionCall(func_name.to_string(), vec![arg]);
+
+    Ok(narrow_int(func, int_width))
 }
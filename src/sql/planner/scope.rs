@@ -1,20 +1,26 @@
 use sqlparser::ast::{Expr, Ident};
 
 use super::{Column, ScalarExpr};
-use crate::core::{ErrorKind, SQLError};
+use crate::core::{sqlstate, ErrorKind, SQLError};
 
 /// Scope is a stack structure that keeps track of visible
 /// variables in the current scope.
 #[derive(Debug, Clone, Default)]
 pub struct Scope {
     pub variables: Vec<Variable>,
+    /// Scope of `FROM` hidden by grouping, its columns can only be
+    /// referenced by the aggregate functions.
+    pub ungrouped: Option<Box<Scope>>,
 }
 
 impl Scope {
     pub fn extend(&self, other: &Scope) -> Scope {
         let mut variables = self.variables.clone();
         variables.extend(other.variables.clone());
-        Scope { variables }
+        Scope {
+            variables,
+            ungrouped: self.ungrouped.clone(),
+        }
     }
 
     pub fn resolve_column(&self, ident: &[Ident]) -> Result<Option<Column>, SQLError> {
@@ -65,10 +71,10 @@ impl Scope {
                 index: candidates[0].0,
             }))
         } else {
-            Err(SQLError::new(
-                ErrorKind::PlannerError,
-                "ambiguous column name",
-            ))
+            Err(
+                SQLError::new(ErrorKind::PlannerError, "ambiguous column name")
+                    .with_code(sqlstate::AMBIGUOUS_COLUMN),
+            )
         }
     }
 
@@ -0,0 +1,51 @@
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{Expr, Visitor};
+
+/// Check if the expression is a subquery, whose expressions belong to the
+/// subquery instead of the query containing it.
+pub fn is_subquery(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Subquery(_) | Expr::InSubquery { .. } | Expr::Exists { .. } | Expr::ArraySubquery(_)
+    )
+}
+
+/// Collect the scalar subqueries, i.e. `(SELECT ...)` and `ARRAY(SELECT ...)`
+/// used as expressions, excluding the ones nested in other subqueries.
+#[derive(Default)]
+pub struct ScalarSubqueryVisitor {
+    pub subqueries: Vec<Expr>,
+    /// Number of the subqueries being visited.
+    depth: usize,
+}
+
+impl ScalarSubqueryVisitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Visitor for ScalarSubqueryVisitor {
+    type Break = ();
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if matches!(expr, Expr::Subquery(_) | Expr::ArraySubquery(_))
+            && self.depth == 0
+            && !self.subqueries.contains(expr)
+        {
+            self.subqueries.push(expr.clone());
+        }
+        if is_subquery(expr) {
+            self.depth += 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if is_subquery(expr) {
+            self.depth -= 1;
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{Expr, Function, Visitor};
+
+use super::subquery::is_subquery;
+
+/// Collect the window functions, i.e. functions with `OVER` clause.
+#[derive(Default)]
+pub struct WindowFunctionVisitor {
+    pub window_functions: Vec<Function>,
+    /// Number of the subqueries being visited, whose window functions belong
+    /// to themselves.
+    depth: usize,
+}
+
+impl WindowFunctionVisitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Visitor for WindowFunctionVisitor {
+    type Break = ();
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if is_subquery(expr) {
+            self.depth += 1;
+        }
+        if let Expr::Function(func) = expr {
+            if self.depth == 0 && func.over.is_some() && !self.window_functions.contains(func) {
+                self.window_functions.push(func.clone());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if is_subquery(expr) {
+            self.depth -= 1;
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -1,20 +1,48 @@
-use super::executor::{
-    DDLExecutor, DMLExecutor, Executor, FilterExecutor, HashAggregateExecutor, MapExecutor,
-    NestedLoopJoinExecutor, ProjectExecutor, ScanExecutor, ValuesExecutor,
+use std::{cell::RefCell, sync::Arc};
+
+use super::{
+    executor::{
+        AggregateCall, ApplyExecutor, CreateTableAsExecutor, DDLExecutor, DMLExecutor,
+        DistinctExecutor, Executor, ExplainExecutor, ExplainedPlan, HashAggregateExecutor,
+        HashJoinExecutor, HashSemiJoinExecutor, IndexScanExecutor, LimitExecutor,
+        NestedLoopJoinExecutor, NestedLoopLeftOuterJoinExecutor, NestedLoopSemiJoinExecutor,
+        RowCountExecutor, ScanExecutor, SortExecutor, StreamAggregateExecutor, UnionAllExecutor,
+        UnnestExecutor, ValuesExecutor, WindowExecutor,
+    },
+    pipeline::{Operator, Pipeline},
+    DMLJob,
 };
 use crate::{
-    catalog::defs::TableDefinition,
-    core::{Datum, ErrorKind, SQLError, Tuple, Type},
+    catalog::{
+        defs::{ColumnDefinition, TableDefinition},
+        pg_catalog::{find_system_table, SystemCatalog},
+    },
+    core::{sqlstate, Datum, ErrorKind, Json, SQLError, SortOrder, Tuple, Type},
     sql::{
         expression::{
-            aggregate::AggregateFunctionRegistry,
-            type_check::{type_check, type_check_aggregate_function, ColumnTypeResolver},
+            function::ScalarFunctionRegistry,
+            type_check::{
+                coerce_to_boolean, common_super_type, infer_parameter_types, type_check,
+                type_check_aggregate_function, type_check_window_function, wrap_cast,
+                ColumnTypeResolver,
+            },
+            window::{WindowFrame, WindowFrameBound, WindowFrameUnits, WindowFunction},
+            Expression,
+        },
+        planner::{
+            cardinality::estimate_rows, physical::PhysicalPlan, AggregateExpr, Column,
+            ExplainFormat, ExplainOptions, Plan, ScalarExpr,
+        },
+        session::{
+            context::{QueryContext, SessionSequence},
+            to_pg_type,
         },
-        planner::{Column, Plan},
-        session::context::QueryContext,
     },
 };
 
+/// Indentation of the children in `EXPLAIN` text format.
+const EXPLAIN_INDENT_SIZE: usize = 4;
+
 /// Schema of the tuple in current context
 #[derive(Debug, Default, Clone)]
 pub struct Schema {
@@ -55,120 +83,440 @@ impl ColumnTypeResolver for Schema {
     }
 }
 
+/// Schema with the session, so the expressions can use sequences.
+pub struct SessionSchema<'a> {
+    pub schema: &'a Schema,
+    pub ctx: &'a QueryContext,
+    /// Values of the outer columns, only bound while building the subquery
+    /// of `Apply`.
+    pub outer: &'a [Expression],
+}
+
+impl ColumnTypeResolver for SessionSchema<'_> {
+    fn resolve_column_type(&self, column: &Column) -> Result<Type, SQLError> {
+        self.schema.resolve_column_type(column)
+    }
+
+    fn resolve_outer_column(&self, column: &Column) -> Result<Expression, SQLError> {
+        self.outer.get(column.index).cloned().ok_or_else(|| {
+            SQLError::new(
+                ErrorKind::UnknownError,
+                format!("cannot find outer column at index: {}", column.index),
+            )
+        })
+    }
+
+    fn resolve_sequence(&self, name: &str) -> Result<SessionSequence, SQLError> {
+        self.ctx.sequence(name)
+    }
+
+    fn catalog_functions(&self) -> Option<ScalarFunctionRegistry> {
+        Some(ScalarFunctionRegistry::catalog(
+            self.ctx.catalog.clone(),
+            self.ctx.current_database.clone(),
+            self.ctx.current_schema.clone(),
+            self.ctx.activity.clone(),
+        ))
+    }
+
+    fn scalar_functions(&self) -> Arc<ScalarFunctionRegistry> {
+        self.ctx.catalog().scalar_functions.clone()
+    }
+}
+
 pub struct ExecutorBuilder<'a> {
     ctx: &'a QueryContext,
+    /// Types of parameters inferred while building the executor,
+    /// `None` if the type cannot be decided from the context.
+    parameter_types: RefCell<Vec<Option<Type>>>,
+    /// Values of the outer columns of the subquery being built.
+    outer: RefCell<Vec<Expression>>,
 }
 
 impl<'a> ExecutorBuilder<'a> {
     pub fn new(ctx: &'a QueryContext) -> Self {
-        Self { ctx }
+        Self {
+            ctx,
+            parameter_types: RefCell::new(vec![]),
+            outer: RefCell::new(vec![]),
+        }
+    }
+
+    /// Types of parameters in the built plan, should be called after `build`.
+    pub fn parameter_types(&self) -> Vec<Option<Type>> {
+        self.parameter_types.borrow().clone()
+    }
+
+    fn type_check(&self, schema: &Schema, scalar: &ScalarExpr) -> Result<Expression, SQLError> {
+        let outer = self.outer.borrow();
+        let schema = SessionSchema {
+            schema,
+            ctx: self.ctx,
+            outer: &outer,
+        };
+        infer_parameter_types(&schema, scalar, &mut self.parameter_types.borrow_mut())?;
+        type_check(&schema, scalar)
+    }
+
+    fn type_check_predicate(
+        &self,
+        schema: &Schema,
+        scalar: &ScalarExpr,
+        construct: &str,
+    ) -> Result<Expression, SQLError> {
+        coerce_to_boolean(self.type_check(schema, scalar)?, construct)
+    }
+
+    /// DML jobs are type checked on execution, so the parameter types are
+    /// inferred here with the schema of target table, which is returned.
+    fn infer_dml_parameter_types(&self, job: &DMLJob) -> Result<Schema, SQLError> {
+        let (schema_name, table_name) = job.table();
+        let table_def = self
+            .ctx
+            .catalog()
+            .find_table_by_name(&self.ctx.current_database, schema_name, table_name)?
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "table not found")
+                    .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        let schema = Schema::from(&table_def);
+        let session_schema = SessionSchema {
+            schema: &schema,
+            ctx: self.ctx,
+            outer: &[],
+        };
+
+        // Parameters assigned to a column have the type of the column
+        let assignments = match job {
+            DMLJob::Insert(_, rows) => rows
+                .iter()
+                .flat_map(|row| row.iter().enumerate())
+                .collect::<Vec<_>>(),
+            DMLJob::Update(_, assignments, _) => assignments
+                .iter()
+                .map(|(index, scalar)| (*index, scalar))
+                .collect::<Vec<_>>(),
+            DMLJob::Delete(_, _) | DMLJob::CopyFrom(_, _) | DMLJob::CopyTo(_) => vec![],
+        };
+        for (column_index, scalar) in assignments {
+            if let ScalarExpr::Parameter(index) = scalar {
+                let mut parameter_types = self.parameter_types.borrow_mut();
+                if parameter_types.len() <= *index {
+                    parameter_types.resize(*index + 1, None);
+                }
+                parameter_types[*index].get_or_insert(schema.column_types[column_index].clone());
+            }
+        }
+
+        let scalars = match job {
+            DMLJob::Insert(_, rows) => rows.iter().flatten().collect::<Vec<_>>(),
+            DMLJob::Update(_, assignments, predicate) => assignments
+                .iter()
+                .map(|(_, scalar)| scalar)
+                .chain(predicate.iter())
+                .collect(),
+            DMLJob::Delete(_, predicate) => predicate.iter().collect(),
+            DMLJob::CopyFrom(_, defaults) => defaults.iter().collect(),
+            DMLJob::CopyTo(_) => vec![],
+        };
+        for scalar in scalars {
+            infer_parameter_types(
+                &session_schema,
+                scalar,
+                &mut self.parameter_types.borrow_mut(),
+            )?;
+        }
+
+        Ok(schema)
     }
 
-    pub fn build(&self, plan: &Plan) -> Result<Executor, SQLError> {
-        let (exec, _) = self.build_inner(plan)?;
-        Ok(exec)
+    /// Build executor of the plan, returns the executor and its output schema.
+    pub fn build(&self, plan: &PhysicalPlan) -> Result<(Executor, Schema), SQLError> {
+        self.build_inner(plan)
     }
 
-    fn build_inner(&self, plan: &Plan) -> Result<(Executor, Schema), SQLError> {
+    /// Build executor of the subquery of `Apply`, with its outer columns bound
+    /// to the values.
+    pub fn build_subquery(
+        &self,
+        plan: &PhysicalPlan,
+        outer: Vec<Expression>,
+    ) -> Result<(Executor, Schema), SQLError> {
+        self.with_outer(outer, || self.build_inner(plan))
+    }
+
+    /// Run the function with the outer columns bound to the values, the
+    /// previous ones are restored after it.
+    fn with_outer<T>(&self, outer: Vec<Expression>, f: impl FnOnce() -> T) -> T {
+        let outer = self.outer.replace(outer);
+        let result = f();
+        self.outer.replace(outer);
+        result
+    }
+
+    /// Type check the parameters of `Apply` with the schema of its input, and
+    /// bind the outer columns of subquery to NULLs of their types, which is
+    /// enough to decide the types of the subquery.
+    fn apply_parameters(
+        &self,
+        schema: &Schema,
+        parameters: &[ScalarExpr],
+    ) -> Result<(Vec<Expression>, Vec<Expression>), SQLError> {
+        let parameters = parameters
+            .iter()
+            .map(|scalar| self.type_check(schema, scalar))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outer = parameters
+            .iter()
+            .map(|parameter| Expression::Literal(Datum::Null, parameter.typ().clone()))
+            .collect();
+
+        Ok((parameters, outer))
+    }
+
+    fn build_inner(&self, plan: &PhysicalPlan) -> Result<(Executor, Schema), SQLError> {
         match plan {
-            Plan::DDL(ddl_job) => Ok((
+            PhysicalPlan::DDL(ddl_job) => Ok((
                 Executor::DDL(DDLExecutor::new(ddl_job.clone())),
                 Schema::default(),
             )),
 
-            Plan::DML(dml_job) => Ok((
-                Executor::DML(DMLExecutor::new(dml_job.clone())),
-                Schema::default(),
-            )),
+            PhysicalPlan::DML(dml_job, returning) => {
+                let table_schema = self.infer_dml_parameter_types(dml_job)?;
+                let returning = returning
+                    .as_ref()
+                    .map(|returning| {
+                        returning
+                            .iter()
+                            .map(|scalar| self.type_check(&table_schema, scalar))
+                            .collect::<Result<Vec<_>, SQLError>>()
+                    })
+                    .transpose()?;
+                let schema = Schema {
+                    column_types: returning
+                        .iter()
+                        .flatten()
+                        .map(|expr| expr.typ().clone())
+                        .collect(),
+                };
+
+                Ok((
+                    Executor::DML(DMLExecutor::new(dml_job.clone(), returning)),
+                    schema,
+                ))
+            }
 
             // Query plans
-            Plan::Project { projections, input } => {
+            PhysicalPlan::Project { projections, input } => {
                 let (input_executor, schema) = self.build_inner(input)?;
                 Ok((
-                    Executor::Project(ProjectExecutor::new(
-                        Box::new(input_executor),
-                        projections.clone(),
-                    )),
+                    Pipeline::pipelined(input_executor, Operator::Project(projections.clone())),
                     schema.project(projections),
                 ))
             }
 
-            Plan::Get {
+            PhysicalPlan::Scan {
                 schema_name,
                 table_name,
+                projections,
+                runtime_filters,
             } => {
+                if let Some(table_def) = find_system_table(schema_name, table_name) {
+                    let schema = Schema::from(table_def);
+                    let mut system_catalog = SystemCatalog::new(
+                        &self.ctx.catalog(),
+                        &self.ctx.current_database,
+                        &self.ctx.current_schema,
+                    );
+                    system_catalog.add_activities(self.ctx.activity.list());
+                    let rows = system_catalog
+                        .rows(table_name)
+                        .into_iter()
+                        .map(|row| {
+                            let row = match projections {
+                                Some(projections) => Tuple::new(row).project(projections).values,
+                                None => row,
+                            };
+                            row.into_iter()
+                                .zip(schema.column_types.iter())
+                                .map(|(value, typ)| Expression::Literal(value, typ.clone()))
+                                .collect()
+                        })
+                        .collect();
+                    let schema = match projections {
+                        Some(projections) => schema.project(projections),
+                        None => schema,
+                    };
+                    return Ok((Executor::Values(ValuesExecutor::new(rows)), schema));
+                }
+
                 let table_def = self
                     .ctx
-                    .catalog
-                    .find_table_by_name(schema_name, table_name)?
+                    .catalog()
+                    .find_table_by_name(&self.ctx.current_database, schema_name, table_name)?
                     .ok_or_else(|| {
                         SQLError::new(
                             ErrorKind::UnknownError,
                             format!("cannot find table: {}.{}", schema_name, table_name),
                         )
+                        .with_code(sqlstate::UNDEFINED_TABLE)
                     })?;
                 let schema = Schema::from(&table_def);
+                let executor = ScanExecutor::new(schema_name, table_name, self.ctx.catalog_version)
+                    .runtime_filters(runtime_filters.clone());
+
+                Ok(match projections {
+                    Some(projections) => (
+                        Executor::Scan(executor.projections(projections.clone())),
+                        schema.project(projections),
+                    ),
+                    None => (Executor::Scan(executor), schema),
+                })
+            }
+
+            PhysicalPlan::RowCount {
+                schema_name,
+                table_name,
+            } => {
+                // Report the missing table as the scan does
+                self.ctx
+                    .catalog()
+                    .find_table_by_name(&self.ctx.current_database, schema_name, table_name)?
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::UnknownError,
+                            format!("cannot find table: {}.{}", schema_name, table_name),
+                        )
+                        .with_code(sqlstate::UNDEFINED_TABLE)
+                    })?;
 
                 Ok((
-                    Executor::Scan(ScanExecutor::new(schema_name, table_name)),
-                    schema,
+                    Executor::RowCount(RowCountExecutor::new(
+                        schema_name,
+                        table_name,
+                        self.ctx.catalog_version,
+                    )),
+                    Schema {
+                        column_types: vec![Type::Int],
+                    },
                 ))
             }
 
-            Plan::Filter { predicate, input } => {
-                let (input_executor, schema) = self.build_inner(input)?;
-                let predicate = type_check(&schema, predicate)?;
+            PhysicalPlan::IndexScan {
+                schema_name,
+                table_name,
+                index_name,
+                columns,
+                range,
+                index_only,
+                projections,
+            } => {
+                let table_def = self
+                    .ctx
+                    .catalog()
+                    .find_table_by_name(&self.ctx.current_database, schema_name, table_name)?
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::UnknownError,
+                            format!("cannot find table: {}.{}", schema_name, table_name),
+                        )
+                        .with_code(sqlstate::UNDEFINED_TABLE)
+                    })?;
+                let mut schema = Schema::from(&table_def);
 
-                let predicate_fn = Box::new(move |input: Tuple| {
-                    let result = predicate.eval(&input).unwrap_or(Datum::Boolean(false));
+                // The key values are constants, which are type checked without
+                // columns, after inferring the parameter types from key columns.
+                let key_value = |column: usize, scalar: &ScalarExpr| {
+                    let comparison = ScalarExpr::FunctionCall(
+                        "=".to_string(),
+                        vec![ScalarExpr::Column(Column { index: column }), scalar.clone()],
+                    );
+                    infer_parameter_types(
+                        &schema,
+                        &comparison,
+                        &mut self.parameter_types.borrow_mut(),
+                    )?;
+                    type_check(&Schema::default(), scalar)
+                };
+                let prefix = columns
+                    .iter()
+                    .zip(range.prefix.iter())
+                    .map(|(column, scalar)| key_value(*column, scalar))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let bound_column = columns[range.prefix.len().min(columns.len() - 1)];
+                let lower = range
+                    .lower
+                    .as_ref()
+                    .map(|(scalar, inclusive)| Ok((key_value(bound_column, scalar)?, *inclusive)))
+                    .transpose()?;
+                let upper = range
+                    .upper
+                    .as_ref()
+                    .map(|(scalar, inclusive)| Ok((key_value(bound_column, scalar)?, *inclusive)))
+                    .transpose()?;
 
-                    if let Datum::Boolean(b) = result {
-                        return b;
-                    }
+                let mut executor = IndexScanExecutor::new(
+                    schema_name,
+                    table_name,
+                    self.ctx.catalog_version,
+                    index_name,
+                    schema.project(columns).column_types,
+                    prefix,
+                    lower,
+                    upper,
+                );
+                if *index_only {
+                    executor = executor.index_only(columns.clone(), schema.column_types.len());
+                }
+                if let Some(projections) = projections {
+                    executor = executor.projections(projections.clone());
+                    schema = schema.project(projections);
+                }
 
-                    match result.cast(&Type::Boolean) {
-                        Datum::Boolean(b) => b,
+                Ok((Executor::IndexScan(executor), schema))
+            }
 
-                        // For null values and other failed casts, we return false
-                        _ => false,
-                    }
-                });
+            PhysicalPlan::Filter { predicate, input } => {
+                let (input_executor, schema) = self.build_inner(input)?;
+                let predicate = self.type_check_predicate(&schema, predicate, "WHERE")?;
+
+                let predicate_fn = Box::new(move |input: &Tuple| predicate.eval_predicate(input));
 
                 Ok((
-                    Executor::Filter(FilterExecutor::new(Box::new(input_executor), predicate_fn)),
+                    Pipeline::pipelined(input_executor, Operator::Filter(predicate_fn)),
                     schema,
                 ))
             }
 
-            Plan::Map { scalars, input } => {
+            PhysicalPlan::Map { scalars, input } => {
                 let (input_executor, mut schema) = self.build_inner(input)?;
 
                 let expressions = scalars
                     .iter()
-                    .map(|scalar| type_check(&schema, scalar))
+                    .map(|scalar| self.type_check(&schema, scalar))
                     .collect::<Result<Vec<_>, _>>()?;
 
                 schema
                     .column_types
                     .extend(expressions.iter().map(|expr| expr.typ()).cloned());
 
-                let map_fn = Box::new(move |mut input| {
+                let map_fn = Box::new(move |mut input: Tuple| {
                     let new_fields = expressions
                         .iter()
-                        .map(|expr| expr.eval(&input).unwrap_or(Datum::Null))
-                        .collect::<Vec<_>>();
+                        .map(|expr| expr.eval(&input))
+                        .collect::<Result<Vec<_>, _>>()?;
 
                     input.values.extend(new_fields);
-                    input
+                    Ok(input)
                 });
 
                 Ok((
-                    Executor::Map(MapExecutor::new(Box::new(input_executor), map_fn)),
+                    Pipeline::pipelined(input_executor, Operator::Map(map_fn)),
                     schema,
                 ))
             }
 
-            Plan::Join { left, right } => {
+            PhysicalPlan::NestedLoopJoin { left, right } => {
                 let (left_executor, left_schema) = self.build_inner(left)?;
                 let (right_executor, right_schema) = self.build_inner(right)?;
 
@@ -184,68 +532,849 @@ impl<'a> ExecutorBuilder<'a> {
                 ))
             }
 
-            Plan::Aggregate {
+            PhysicalPlan::HashJoin {
+                keys,
+                left,
+                right,
+                runtime_filter,
+            } => {
+                let (left_executor, left_schema) = self.build_inner(left)?;
+                let (right_executor, right_schema) = self.build_inner(right)?;
+
+                let mut schema = left_schema.clone();
+                schema
+                    .column_types
+                    .extend(right_schema.column_types.iter().cloned());
+
+                let width = left_schema.column_types.len();
+                let mut left_keys = vec![];
+                let mut right_keys = vec![];
+                for (left_key, right_key) in keys.iter() {
+                    // Both sides are cast to the argument types of `=`
+                    let condition = self.type_check(
+                        &schema,
+                        &ScalarExpr::FunctionCall(
+                            "=".to_string(),
+                            vec![left_key.clone(), right_key.clone()],
+                        ),
+                    )?;
+                    let Expression::Function(func, _) = condition else {
+                        unreachable!()
+                    };
+
+                    // The right key is evaluated with the right tuple
+                    let right_key = right_key.rewrite_columns(
+                        &mut |column| {
+                            ScalarExpr::Column(Column {
+                                index: column.index - width,
+                            })
+                        },
+                        &mut |column| ScalarExpr::OuterColumn(column.clone()),
+                    );
+                    left_keys.push(wrap_cast(
+                        self.type_check(&left_schema, left_key)?,
+                        func.arg_types[0].clone(),
+                    ));
+                    right_keys.push(wrap_cast(
+                        self.type_check(&right_schema, &right_key)?,
+                        func.arg_types[1].clone(),
+                    ));
+                }
+
+                // The scan checks the values of columns against the filter, so
+                // it's never built if they are cast to compare with the right keys
+                let runtime_filter = runtime_filter.clone().filter(|runtime_filter| {
+                    runtime_filter
+                        .keys
+                        .iter()
+                        .all(|key| matches!(left_keys[*key], Expression::Column(..)))
+                });
+
+                Ok((
+                    Executor::HashJoin(HashJoinExecutor::new(
+                        Box::new(left_executor),
+                        Pipeline::from_executor(right_executor),
+                        left_keys,
+                        right_keys,
+                        runtime_filter,
+                    )),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::NestedLoopLeftOuterJoin {
+                condition,
+                left,
+                right,
+            } => {
+                let (left_executor, left_schema) = self.build_inner(left)?;
+                let (right_executor, right_schema) = self.build_inner(right)?;
+
+                let right_width = right_schema.column_types.len();
+                let mut schema = left_schema;
+                schema.column_types.extend(right_schema.column_types);
+                let condition = self.type_check_predicate(&schema, condition, "JOIN/ON")?;
+
+                Ok((
+                    Executor::NestedLoopLeftOuterJoin(NestedLoopLeftOuterJoinExecutor::new(
+                        Box::new(left_executor),
+                        Box::new(right_executor),
+                        condition,
+                        right_width,
+                    )),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::HashAggregate {
+                group_by,
+                grouping_sets,
+                aggregates,
+                input,
+            } => {
+                let (input, group_by, aggregates, schema) =
+                    self.build_aggregate(group_by, !grouping_sets.is_empty(), aggregates, input)?;
+
+                Ok((
+                    Executor::HashAggregate(HashAggregateExecutor::new(
+                        input,
+                        group_by,
+                        grouping_sets.clone(),
+                        aggregates,
+                    )),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::StreamAggregate {
                 group_by,
                 aggregates,
                 input,
             } => {
-                let (input_executor, input_schema) = self.build_inner(input)?;
+                let (input, group_by, aggregates, schema) =
+                    self.build_aggregate(group_by, false, aggregates, input)?;
+
+                Ok((
+                    Executor::StreamAggregate(StreamAggregateExecutor::new(
+                        input, group_by, aggregates,
+                    )),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::HashSemiJoin {
+                left_key,
+                anti,
+                left,
+                right,
+            } => {
+                let (left_executor, left_schema) = self.build_inner(left)?;
+                let (right_executor, right_schema) = self.build_inner(right)?;
+                let (left_key, key_type) =
+                    self.semi_join_key(left_key.as_ref(), &left_schema, &right_schema)?;
+
+                Ok((
+                    Executor::HashSemiJoin(HashSemiJoinExecutor::new(
+                        Box::new(left_executor),
+                        Box::new(right_executor),
+                        left_key,
+                        key_type,
+                        *anti,
+                    )),
+                    left_schema,
+                ))
+            }
+
+            PhysicalPlan::NestedLoopSemiJoin {
+                left_key,
+                conditions,
+                anti,
+                left,
+                right,
+            } => {
+                let (left_executor, left_schema) = self.build_inner(left)?;
+                let (right_executor, right_schema) = self.build_inner(right)?;
+                let (left_key, key_type) =
+                    self.semi_join_key(left_key.as_ref(), &left_schema, &right_schema)?;
 
-                let group_by = group_by
+                let mut combined_schema = left_schema.clone();
+                combined_schema
+                    .column_types
+                    .extend(right_schema.column_types.iter().cloned());
+                let conditions = conditions
                     .iter()
-                    .map(|expr| type_check(&input_schema, expr))
+                    .map(|condition| {
+                        self.type_check_predicate(&combined_schema, condition, "WHERE")
+                    })
                     .collect::<Result<Vec<_>, _>>()?;
 
-                let aggregates = aggregates
+                Ok((
+                    Executor::NestedLoopSemiJoin(NestedLoopSemiJoinExecutor::new(
+                        Box::new(left_executor),
+                        Box::new(right_executor),
+                        left_key,
+                        key_type,
+                        conditions,
+                        *anti,
+                    )),
+                    left_schema,
+                ))
+            }
+
+            PhysicalPlan::Apply {
+                parameters,
+                array,
+                subquery,
+                input,
+            } => {
+                let (input_executor, mut schema) = self.build_inner(input)?;
+                let (parameters, outer) = self.apply_parameters(&schema, parameters)?;
+                let (_, subquery_schema) = self.build_subquery(subquery, outer)?;
+                let typ = subquery_schema.column_types[0].clone();
+                schema.column_types.push(if *array {
+                    Type::Array(Box::new(typ))
+                } else {
+                    typ
+                });
+
+                Ok((
+                    Executor::Apply(ApplyExecutor::new(
+                        Box::new(input_executor),
+                        parameters,
+                        *array,
+                        subquery.as_ref().clone(),
+                    )),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::Window {
+                window_exprs,
+                input,
+            } => {
+                let (input_executor, mut schema) = self.build_inner(input)?;
+
+                let window_functions = window_exprs
                     .iter()
-                    .map(|(func_name, args)| {
-                        let args = args
+                    .map(|window_expr| {
+                        let args = window_expr
+                            .args
                             .iter()
-                            .map(|expr| type_check(&input_schema, expr))
+                            .map(|expr| self.type_check(&schema, expr))
                             .collect::<Result<Vec<_>, _>>()?;
-                        type_check_aggregate_function(
-                            func_name,
+                        let (kind, args) = type_check_window_function(
+                            &window_expr.func_name,
                             &args,
-                            AggregateFunctionRegistry::builtin(),
+                            &self.ctx.catalog().aggregate_functions,
+                        )?;
+                        let partition_by = window_expr
+                            .partition_by
+                            .iter()
+                            .map(|expr| self.type_check(&schema, expr))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let order_by = window_expr
+                            .order_by
+                            .iter()
+                            .map(|order_by| {
+                                Ok((
+                                    self.type_check(&schema, &order_by.expr)?,
+                                    order_by.sort_order(),
+                                ))
+                            })
+                            .collect::<Result<Vec<_>, SQLError>>()?;
+
+                        let frame = Self::window_frame(&window_expr.frame, &order_by)?;
+
+                        Ok(WindowFunction {
+                            kind,
+                            args,
+                            partition_by,
+                            order_by,
+                            frame,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                schema
+                    .column_types
+                    .extend(window_functions.iter().map(WindowFunction::ret_type));
+
+                Ok((
+                    Executor::Window(WindowExecutor::new(
+                        Box::new(input_executor),
+                        window_functions,
+                    )),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::Sort { order_by, input } => {
+                let (input_executor, schema) = self.build_inner(input)?;
+                let order_by = order_by
+                    .iter()
+                    .map(|order| Ok((self.type_check(&schema, &order.expr)?, order.sort_order())))
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                Ok((
+                    Executor::Sort(SortExecutor::new(
+                        Pipeline::from_executor(input_executor),
+                        order_by,
+                    )),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::Limit {
+                limit,
+                offset,
+                input,
+            } => {
+                let (input_executor, schema) = self.build_inner(input)?;
+                let limit = limit
+                    .as_ref()
+                    .map(|limit| self.row_count(limit, "LIMIT"))
+                    .transpose()?;
+                let offset = offset
+                    .as_ref()
+                    .map(|offset| self.row_count(offset, "OFFSET"))
+                    .transpose()?;
+
+                Ok((
+                    Executor::Limit(LimitExecutor::new(Box::new(input_executor), limit, offset)),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::HashDistinct { input } => {
+                let (input_executor, schema) = self.build_inner(input)?;
+
+                Ok((
+                    Executor::Distinct(DistinctExecutor::new(Box::new(input_executor))),
+                    schema,
+                ))
+            }
+
+            PhysicalPlan::UnionAll { left, right } => {
+                let (left_executor, left_schema) = self.build_inner(left)?;
+                let (right_executor, right_schema) = self.build_inner(right)?;
+
+                // Each column has the common type of both sides
+                let column_types = left_schema
+                    .column_types
+                    .iter()
+                    .zip(right_schema.column_types.iter())
+                    .map(|(left_type, right_type)| {
+                        common_super_type(&[left_type.clone(), right_type.clone()]).ok_or_else(
+                            || {
+                                SQLError::new(
+                                    ErrorKind::TypeError,
+                                    format!(
+                                        "UNION types {} and {} cannot be matched",
+                                        to_pg_type(left_type).name(),
+                                        to_pg_type(right_type).name()
+                                    ),
+                                )
+                                .with_code(sqlstate::DATATYPE_MISMATCH)
+                            },
                         )
                     })
-                    .collect::<Result<Vec<_>, _>>()?;
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+                let left_executor = cast_columns(left_executor, &left_schema, &column_types);
+                let right_executor = cast_columns(right_executor, &right_schema, &column_types);
 
-                let schema = Schema {
-                    column_types: if group_by.is_empty() {
-                        input_schema
-                            .column_types
-                            .into_iter()
-                            .chain(aggregates.iter().map(|(agg, _)| agg.ret_type.clone()))
-                            .collect()
-                    } else {
-                        group_by
-                            .iter()
-                            .map(|expr| expr.typ().clone())
-                            .chain(aggregates.iter().map(|(agg, _)| agg.ret_type.clone()))
-                            .collect()
-                    },
+                Ok((
+                    Executor::UnionAll(UnionAllExecutor::new(
+                        Box::new(left_executor),
+                        Box::new(right_executor),
+                    )),
+                    Schema { column_types },
+                ))
+            }
+
+            PhysicalPlan::CreateTableAs {
+                schema_name,
+                table_name,
+                column_names,
+                if_not_exists,
+                input,
+            } => {
+                let (input_executor, schema) = self.build_inner(input)?;
+
+                // Columns of unknown types, e.g. `NULL`, are created as strings
+                let columns = column_names
+                    .iter()
+                    .zip(schema.column_types.iter())
+                    .map(|(name, typ)| ColumnDefinition {
+                        name: name.clone(),
+                        data_type: match typ {
+                            Type::Null | Type::Any | Type::Never => Type::String,
+                            typ => typ.clone(),
+                        },
+                        null: true,
+                        default: None,
+                        int_width: None,
+                    })
+                    .collect();
+                let table_def = TableDefinition {
+                    name: table_name.clone(),
+                    columns,
+                    indexes: vec![],
+                    primary_key: None,
+                    foreign_keys: vec![],
+                    statistics: None,
+                    version: 0,
                 };
 
                 Ok((
-                    Executor::HashAggregate(HashAggregateExecutor::new(
+                    Executor::CreateTableAs(CreateTableAsExecutor::new(
+                        schema_name.clone(),
+                        table_def,
+                        *if_not_exists,
                         Box::new(input_executor),
-                        group_by,
-                        aggregates,
                     )),
-                    schema,
+                    Schema::default(),
                 ))
             }
 
-            Plan::Explain(display_str) => {
-                let values_exec =
-                    Executor::Values(ValuesExecutor::new(vec![Tuple::new(vec![Datum::String(
-                        display_str.clone(),
-                    )])]));
+            PhysicalPlan::Values { rows } => {
+                let rows = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|scalar| self.type_check(&Schema::default(), scalar))
+                            .collect::<Result<Vec<_>, SQLError>>()
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                // Each column has the common type of its values
+                let width = rows.first().map_or(0, Vec::len);
+                let column_types = (0..width)
+                    .map(|i| {
+                        let types = rows
+                            .iter()
+                            .map(|row| row[i].typ().clone())
+                            .collect::<Vec<_>>();
+                        common_super_type(&types).ok_or_else(|| {
+                            SQLError::new(
+                                ErrorKind::TypeError,
+                                format!("VALUES types cannot be matched in column{}", i + 1),
+                            )
+                            .with_code(sqlstate::DATATYPE_MISMATCH)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+                let rows = rows
+                    .into_iter()
+                    .map(|row| {
+                        row.into_iter()
+                            .zip(column_types.iter())
+                            .map(|(expr, typ)| match typ {
+                                Type::Null | Type::Any | Type::Never => expr,
+                                typ => wrap_cast(expr, typ.clone()),
+                            })
+                            .collect()
+                    })
+                    .collect();
 
-                Ok((values_exec, Schema::default()))
+                Ok((
+                    Executor::Values(ValuesExecutor::new(rows)),
+                    Schema { column_types },
+                ))
             }
-            Plan::Use(schema_name) => Ok((Executor::Use(schema_name.clone()), Schema::default())),
+
+            PhysicalPlan::Unnest { arrays } => {
+                let arrays = arrays
+                    .iter()
+                    .map(|scalar| self.type_check(&Schema::default(), scalar))
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                // Each column has the element type of its array
+                let column_types = arrays
+                    .iter()
+                    .map(|array| match array.typ() {
+                        Type::Array(elem_type) => Ok(elem_type.as_ref().clone()),
+                        Type::Null => Ok(Type::Null),
+                        _ => Err(SQLError::new(
+                            ErrorKind::CatalogError,
+                            "cannot find overload of function with given types: unnest",
+                        )
+                        .with_code(sqlstate::UNDEFINED_FUNCTION)),
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                Ok((
+                    Executor::Unnest(UnnestExecutor::new(arrays)),
+                    Schema { column_types },
+                ))
+            }
+
+            PhysicalPlan::Explain {
+                logical,
+                plan,
+                options,
+            } => {
+                let explained = match options.format {
+                    ExplainFormat::Text => {
+                        let mut lines = vec![];
+                        self.explain_text(logical, plan, options, 0, &mut lines)?;
+                        ExplainedPlan::Text(lines)
+                    }
+                    ExplainFormat::Json => {
+                        ExplainedPlan::Json(self.explain_json(logical, plan, options)?)
+                    }
+                };
+                let child = match options.analyze {
+                    true => Some(Box::new(self.build_inner(plan)?.0)),
+                    false => None,
+                };
+
+                Ok((
+                    Executor::Explain(ExplainExecutor::new(explained, child)),
+                    Schema {
+                        column_types: vec![Type::String],
+                    },
+                ))
+            }
+            PhysicalPlan::Use(schema_name) => {
+                Ok((Executor::Use(schema_name.clone()), Schema::default()))
+            }
+            PhysicalPlan::SetVariable(name, value) => Ok((
+                Executor::SetVariable(name.clone(), value.clone()),
+                Schema::default(),
+            )),
         }
     }
+
+    /// Count of `LIMIT` or `OFFSET`, cast to integer. The parameters of the
+    /// counts are integers.
+    fn row_count(&self, scalar: &ScalarExpr, clause: &str) -> Result<Expression, SQLError> {
+        if let ScalarExpr::Parameter(index) = scalar {
+            let mut parameter_types = self.parameter_types.borrow_mut();
+            if parameter_types.len() <= *index {
+                parameter_types.resize(*index + 1, None);
+            }
+            parameter_types[*index].get_or_insert(Type::Int);
+        }
+
+        let count = self.type_check(&Schema::default(), scalar)?;
+        match count.typ() {
+            Type::Int | Type::Null => Ok(count),
+            Type::Float | Type::String => Ok(wrap_cast(count, Type::Int)),
+            typ => Err(SQLError::new(
+                ErrorKind::TypeError,
+                format!(
+                    "argument of {} must be type bigint, not type {}",
+                    clause,
+                    to_pg_type(typ).name()
+                ),
+            )
+            .with_code(sqlstate::DATATYPE_MISMATCH)),
+        }
+    }
+
+    /// Input pipeline, type checked group keys and aggregates, and the output
+    /// schema of an aggregate.
+    /// Check the offsets of `RANGE` against the type of the order key, which
+    /// must be numeric. The integer offsets are converted to float for the
+    /// float keys.
+    fn window_frame(
+        frame: &WindowFrame,
+        order_by: &[(Expression, SortOrder)],
+    ) -> Result<WindowFrame, SQLError> {
+        let (WindowFrameUnits::Range, [(key, _)]) = (frame.units, order_by) else {
+            return Ok(frame.clone());
+        };
+        let bound = |bound: &WindowFrameBound| {
+            let offset = match (bound.offset(), key.typ()) {
+                (None, _) => return Ok(bound.clone()),
+                (Some(Datum::Int(offset)), Type::Float) => Datum::Float(*offset as f64),
+                (Some(offset @ Datum::Int(_)), Type::Int)
+                | (Some(offset @ Datum::Float(_)), Type::Float) => offset.clone(),
+                (Some(offset), typ) => {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!(
+                            "RANGE with offset PRECEDING/FOLLOWING is not supported for column type {} and offset type {}",
+                            to_pg_type(typ).name(),
+                            to_pg_type(&offset.typ()).name()
+                        ),
+                    )
+                    .with_code(sqlstate::FEATURE_NOT_SUPPORTED))
+                }
+            };
+            Ok(match bound {
+                WindowFrameBound::Preceding(_) => WindowFrameBound::Preceding(offset),
+                _ => WindowFrameBound::Following(offset),
+            })
+        };
+
+        Ok(WindowFrame {
+            units: frame.units,
+            start: bound(&frame.start)?,
+            end: bound(&frame.end)?,
+        })
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn build_aggregate(
+        &self,
+        group_by: &[ScalarExpr],
+        grouping_set_column: bool,
+        aggregates: &[AggregateExpr],
+        input: &PhysicalPlan,
+    ) -> Result<(Pipeline, Vec<Expression>, Vec<AggregateCall>, Schema), SQLError> {
+        let (input_executor, input_schema) = self.build_inner(input)?;
+
+        let group_by = group_by
+            .iter()
+            .map(|expr| self.type_check(&input_schema, expr))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let aggregates = aggregates
+            .iter()
+            .map(|aggregate| {
+                let args = aggregate
+                    .args
+                    .iter()
+                    .map(|expr| self.type_check(&input_schema, expr))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (func, args) = type_check_aggregate_function(
+                    &aggregate.func_name,
+                    &args,
+                    &self.ctx.catalog().aggregate_functions,
+                )?;
+                let filter = aggregate
+                    .filter
+                    .as_ref()
+                    .map(|filter| self.type_check_predicate(&input_schema, filter, "FILTER"))
+                    .transpose()?;
+                let order_by = aggregate
+                    .order_by
+                    .iter()
+                    .map(|order_by| {
+                        Ok((
+                            self.type_check(&input_schema, &order_by.expr)?,
+                            order_by.sort_order(),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+                Ok(AggregateCall {
+                    func,
+                    args,
+                    filter,
+                    order_by,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Scalar aggregate only produces the aggregate results, and the index
+        // of grouping set follows the group keys
+        let schema = Schema {
+            column_types: group_by
+                .iter()
+                .map(|expr| expr.typ().clone())
+                .chain(grouping_set_column.then_some(Type::Int))
+                .chain(aggregates.iter().map(|agg| agg.func.ret_type.clone()))
+                .collect(),
+        };
+
+        Ok((
+            Pipeline::from_executor(input_executor),
+            group_by,
+            aggregates,
+            schema,
+        ))
+    }
+
+    /// Type checked key of semi join, and the type to cast the values of
+    /// `right` to if they are not the same as the key.
+    fn semi_join_key(
+        &self,
+        left_key: Option<&ScalarExpr>,
+        left_schema: &Schema,
+        right_schema: &Schema,
+    ) -> Result<(Option<Expression>, Option<Type>), SQLError> {
+        let left_key = left_key
+            .map(|key| self.type_check(left_schema, key))
+            .transpose()?;
+        let key_type = left_key
+            .as_ref()
+            .map(|key| key.typ().clone())
+            .filter(|typ| {
+                !matches!(typ, Type::Null | Type::Any | Type::Never)
+                    && Some(typ) != right_schema.column_types.first()
+            });
+
+        Ok((left_key, key_type))
+    }
+
+    /// Values of the outer columns to explain the subquery of `Apply` with,
+    /// which is its second child. `None` for the other nodes.
+    fn subquery_outer(&self, plan: &PhysicalPlan) -> Result<Option<Vec<Expression>>, SQLError> {
+        let PhysicalPlan::Apply {
+            parameters, input, ..
+        } = plan
+        else {
+            return Ok(None);
+        };
+        let (_, schema) = self.build_inner(input)?;
+        let (_, outer) = self.apply_parameters(&schema, parameters)?;
+
+        Ok(Some(outer))
+    }
+
+    /// Names of the output types of the plan node, shown by `EXPLAIN VERBOSE`.
+    fn explain_output(&self, plan: &PhysicalPlan) -> Result<Vec<String>, SQLError> {
+        let (_, schema) = self.build_inner(plan)?;
+
+        Ok(schema
+            .column_types
+            .iter()
+            .map(|typ| to_pg_type(typ).name().to_string())
+            .collect())
+    }
+
+    /// Lines of the indented plan tree, with the operators of physical plan
+    /// and the details of logical plan, whose nodes correspond to each other.
+    fn explain_text(
+        &self,
+        logical: &Plan,
+        plan: &PhysicalPlan,
+        options: &ExplainOptions,
+        indent: usize,
+        lines: &mut Vec<String>,
+    ) -> Result<(), SQLError> {
+        let name = plan.name();
+        let (_, detail) = logical.describe();
+        let indent_str = " ".repeat(indent);
+        let mut line = match detail {
+            Some(detail) => format!("{}{}: {}", indent_str, name, detail),
+            None => format!("{}{}", indent_str, name),
+        };
+        if let Some(rows) = estimate_rows(
+            logical,
+            &self.ctx.catalog().database(&self.ctx.current_database),
+        )
+        .filter(|_| options.costs)
+        {
+            line.push_str(&format!(" (rows={})", rows.round()));
+        }
+        lines.push(line);
+        if options.verbose {
+            lines.push(format!(
+                "{}  Output: {}",
+                indent_str,
+                self.explain_output(plan)?.join(", ")
+            ));
+        }
+
+        let outer = self.subquery_outer(plan)?;
+        for (i, (logical_child, child)) in logical
+            .children()
+            .into_iter()
+            .zip(plan.children())
+            .enumerate()
+        {
+            let mut explain = || {
+                self.explain_text(
+                    logical_child,
+                    child,
+                    options,
+                    indent + EXPLAIN_INDENT_SIZE,
+                    lines,
+                )
+            };
+            match &outer {
+                Some(outer) if i == 1 => self.with_outer(outer.clone(), explain)?,
+                _ => explain()?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Plan tree as JSON, where each node has `Node Type`, the optional
+    /// `Detail` and `Output`, and the inputs in `Plans`.
+    fn explain_json(
+        &self,
+        logical: &Plan,
+        plan: &PhysicalPlan,
+        options: &ExplainOptions,
+    ) -> Result<Json, SQLError> {
+        let name = plan.name();
+        let (_, detail) = logical.describe();
+        let mut fields = vec![("Node Type".to_string(), Json::String(name.to_string()))];
+        if let Some(detail) = detail {
+            fields.push(("Detail".to_string(), Json::String(detail)));
+        }
+        if let Some(rows) = estimate_rows(
+            logical,
+            &self.ctx.catalog().database(&self.ctx.current_database),
+        )
+        .filter(|_| options.costs)
+        {
+            fields.push((
+                "Plan Rows".to_string(),
+                Json::Number(rows.round().to_string()),
+            ));
+        }
+        if options.verbose {
+            let output = self.explain_output(plan)?;
+            fields.push((
+                "Output".to_string(),
+                Json::Array(output.into_iter().map(Json::String).collect()),
+            ));
+        }
+
+        let children = logical.children();
+        if !children.is_empty() {
+            let outer = self.subquery_outer(plan)?;
+            let plans = children
+                .into_iter()
+                .zip(plan.children())
+                .enumerate()
+                .map(|(i, (logical_child, child))| {
+                    let explain = || self.explain_json(logical_child, child, options);
+                    match &outer {
+                        Some(outer) if i == 1 => self.with_outer(outer.clone(), explain),
+                        _ => explain(),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            fields.push(("Plans".to_string(), Json::Array(plans)));
+        }
+
+        Ok(Json::Object(fields))
+    }
+}
+
+/// Cast the columns of the executor to the types, the columns of unknown
+/// types, e.g. `NULL`, are kept as they are.
+fn cast_columns(executor: Executor, schema: &Schema, column_types: &[Type]) -> Executor {
+    if schema.column_types == column_types {
+        return executor;
+    }
+    let width = column_types.len();
+    let casts = column_types
+        .iter()
+        .enumerate()
+        .map(|(i, typ)| {
+            let column = Expression::Column(i, schema.column_types[i].clone());
+            match typ {
+                Type::Null | Type::Any | Type::Never => column,
+                typ => wrap_cast(column, typ.clone()),
+            }
+        })
+        .collect::<Vec<_>>();
+    let map_fn = Box::new(move |mut input: Tuple| {
+        let new_fields = casts
+            .iter()
+            .map(|expr| expr.eval(&input))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        input.values.extend(new_fields);
+        Ok(input)
+    });
+    let executor = Pipeline::pipelined(executor, Operator::Map(map_fn));
+    Pipeline::pipelined(executor, Operator::Project((width..2 * width).collect()))
 }
@@ -0,0 +1,385 @@
+use std::fs::File;
+
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter};
+
+use crate::core::{
+    datum_from_array, sqlstate, to_record_batch, Datum, ErrorKind, IntWidth, SQLError, Tuple, Type,
+};
+
+/// `COPY` between a table and a CSV or Parquet file on the server.
+#[derive(Debug, Clone)]
+pub struct CopyJob {
+    pub table: (String, String),
+    /// Indices of the columns in the file, in order.
+    pub columns: Vec<usize>,
+    pub file: String,
+    pub format: CopyFormat,
+}
+
+/// Format of the file of `COPY`.
+#[derive(Debug, Clone)]
+pub enum CopyFormat {
+    Csv(CsvOptions),
+    /// Columns of the Parquet file are matched by name, and the extra ones
+    /// are ignored.
+    Parquet,
+}
+
+/// Options of the CSV format.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub quote: char,
+    /// Character escaping the quote inside quoted values, same as `quote` by default.
+    pub escape: char,
+    /// Unquoted values equal to this string are NULL, empty by default.
+    pub null: String,
+    /// The first line is the column names, which is skipped on reading.
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            escape: '"',
+            null: String::new(),
+            header: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Check that the special characters don't conflict with each other.
+    pub fn validate(&self) -> Result<(), SQLError> {
+        if self.delimiter == self.quote {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "COPY delimiter and quote must be different",
+            )
+            .with_code(sqlstate::INVALID_PARAMETER_VALUE));
+        }
+        if [self.delimiter, self.quote, self.escape]
+            .iter()
+            .any(|c| matches!(c, '\r' | '\n'))
+        {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "COPY delimiter, quote and escape cannot be newline or carriage return",
+            )
+            .with_code(sqlstate::INVALID_PARAMETER_VALUE));
+        }
+
+        Ok(())
+    }
+
+    /// Parse the records of CSV text, the unquoted values equal to the NULL
+    /// string are `None`. Records are separated by LF or CRLF, which can be
+    /// quoted in values.
+    pub fn read(&self, text: &str) -> Result<Vec<Vec<Option<String>>>, SQLError> {
+        let mut records = vec![];
+        let mut record = vec![];
+        let mut value = String::new();
+        // If the current value is quoted, and if it's inside the quotes now
+        let mut quoted = false;
+        let mut in_quotes = false;
+
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                if c == self.escape
+                    && chars
+                        .peek()
+                        .map_or(false, |next| *next == self.quote || *next == self.escape)
+                {
+                    value.push(chars.next().unwrap());
+                } else if c == self.quote {
+                    in_quotes = false;
+                } else {
+                    value.push(c);
+                }
+                continue;
+            }
+
+            match c {
+                _ if c == self.quote => {
+                    quoted = true;
+                    in_quotes = true;
+                }
+                _ if c == self.delimiter => {
+                    record.push(self.take_value(&mut value, &mut quoted));
+                }
+                '\r' | '\n' => {
+                    if c == '\r' && chars.peek() == Some(&'\n') {
+                        chars.next();
+                    }
+                    record.push(self.take_value(&mut value, &mut quoted));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => value.push(c),
+            }
+        }
+
+        if in_quotes {
+            return Err(
+                SQLError::new(ErrorKind::RuntimeError, "unterminated CSV quoted field")
+                    .with_code(sqlstate::BAD_COPY_FILE_FORMAT),
+            );
+        }
+        // The last record without line break
+        if quoted || !value.is_empty() || !record.is_empty() {
+            record.push(self.take_value(&mut value, &mut quoted));
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    fn take_value(&self, value: &mut String, quoted: &mut bool) -> Option<String> {
+        let value = std::mem::take(value);
+        if !std::mem::take(quoted) && value == self.null {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Append a record to the CSV text, the values are quoted if they contain
+    /// special characters or equal to the NULL string.
+    pub fn write(&self, out: &mut String, record: &[Option<String>]) {
+        for (i, value) in record.iter().enumerate() {
+            if i > 0 {
+                out.push(self.delimiter);
+            }
+
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    out.push_str(&self.null);
+                    continue;
+                }
+            };
+            let need_quote = *value == self.null
+                || value
+                    .chars()
+                    .any(|c| c == self.delimiter || c == self.quote || c == '\r' || c == '\n');
+            if need_quote {
+                out.push(self.quote);
+                for c in value.chars() {
+                    if c == self.quote || c == self.escape {
+                        out.push(self.escape);
+                    }
+                    out.push(c);
+                }
+                out.push(self.quote);
+            } else {
+                out.push_str(value);
+            }
+        }
+        out.push('\n');
+    }
+}
+
+/// Read the columns of the Parquet file by name, the values of each row are
+/// in the order of `names`.
+pub fn read_parquet(file: &str, names: &[&str]) -> Result<Vec<Vec<Datum>>, SQLError> {
+    let file = File::open(file).map_err(|e| copy_file_error(file, "reading", e))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(parquet_error)?;
+
+    let mut rows = vec![];
+    for batch in reader {
+        let batch = batch.map_err(parquet_error)?;
+        let arrays = names
+            .iter()
+            .map(|name| {
+                batch.column_by_name(name).ok_or_else(|| {
+                    SQLError::new(
+                        ErrorKind::RuntimeError,
+                        format!("missing data for column \"{}\"", name),
+                    )
+                    .with_code(sqlstate::BAD_COPY_FILE_FORMAT)
+                })
+            })
+            .collect::<Result<Vec<_>, SQLError>>()?;
+        for index in 0..batch.num_rows() {
+            rows.push(
+                arrays
+                    .iter()
+                    .map(|array| datum_from_array(array.as_ref(), index))
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Write the rows to the Parquet file, the columns are named and typed by
+/// `columns`.
+pub fn write_parquet(
+    file: &str,
+    columns: &[(String, Type, Option<IntWidth>)],
+    rows: &[Tuple],
+) -> Result<(), SQLError> {
+    let batch = to_record_batch(columns, rows)?;
+    let file = File::create(file).map_err(|e| copy_file_error(file, "writing", e))?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None).map_err(parquet_error)?;
+    writer.write(&batch).map_err(parquet_error)?;
+    writer.close().map_err(parquet_error)?;
+
+    Ok(())
+}
+
+/// Error of opening the file of `COPY`.
+pub fn copy_file_error(file: &str, action: &str, e: std::io::Error) -> SQLError {
+    let code = if e.kind() == std::io::ErrorKind::NotFound {
+        sqlstate::UNDEFINED_FILE
+    } else {
+        sqlstate::IO_ERROR
+    };
+    SQLError::new(
+        ErrorKind::StorageError,
+        format!("could not open file \"{}\" for {}: {}", file, action, e),
+    )
+    .with_code(code)
+}
+
+fn parquet_error(e: impl std::fmt::Display) -> SQLError {
+    SQLError::new(
+        ErrorKind::RuntimeError,
+        format!("invalid Parquet file: {}", e),
+    )
+    .with_code(sqlstate::BAD_COPY_FILE_FORMAT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(record: &[&str]) -> Vec<Option<String>> {
+        record
+            .iter()
+            .map(|value| (*value != "NULL").then(|| value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn read_quoted() {
+        let options = CsvOptions::default();
+        let records = options
+            .read("1,\"a,b\",\"say \"\"hi\"\"\"\r\n2,\"line\nbreak\",x\n")
+            .unwrap();
+        assert_eq!(
+            records,
+            [
+                values(&["1", "a,b", "say \"hi\""]),
+                values(&["2", "line\nbreak", "x"])
+            ]
+        );
+    }
+
+    #[test]
+    fn read_null() {
+        let options = CsvOptions::default();
+        // Only the unquoted empty value is NULL
+        let records = options.read("1,,\"\"\n,").unwrap();
+        assert_eq!(
+            records,
+            [values(&["1", "NULL", ""]), values(&["NULL", "NULL"])]
+        );
+
+        let options = CsvOptions {
+            null: "\\N".to_string(),
+            ..Default::default()
+        };
+        let records = options.read("\\N,\"\\N\",\n").unwrap();
+        assert_eq!(records, [values(&["NULL", "\\N", ""])]);
+    }
+
+    #[test]
+    fn read_escape() {
+        let options = CsvOptions {
+            delimiter: '|',
+            quote: '\'',
+            escape: '\\',
+            ..Default::default()
+        };
+        let records = options.read("'it\\'s'|'a\\\\b'|c\\d\n").unwrap();
+        assert_eq!(records, [values(&["it's", "a\\b", "c\\d"])]);
+    }
+
+    #[test]
+    fn read_unterminated() {
+        let error = CsvOptions::default().read("1,\"abc\n").unwrap_err();
+        assert_eq!(error.code, Some(sqlstate::BAD_COPY_FILE_FORMAT));
+    }
+
+    #[test]
+    fn write_and_read() {
+        for options in [
+            CsvOptions::default(),
+            CsvOptions {
+                delimiter: ';',
+                quote: '\'',
+                escape: '\\',
+                null: "NULL".to_string(),
+                header: false,
+            },
+        ] {
+            let records = [
+                vec![
+                    Some("plain".to_string()),
+                    None,
+                    Some(String::new()),
+                    Some("NULL".to_string()),
+                ],
+                vec![
+                    Some("a,b;c".to_string()),
+                    Some("quotes \" and '".to_string()),
+                    Some("back\\slash".to_string()),
+                    Some("multi\r\nline".to_string()),
+                ],
+            ];
+            let mut out = String::new();
+            for record in records.iter() {
+                options.write(&mut out, record);
+            }
+            assert_eq!(options.read(&out).unwrap(), records);
+        }
+    }
+
+    #[test]
+    fn write_quoting() {
+        let options = CsvOptions::default();
+        let mut out = String::new();
+        options.write(
+            &mut out,
+            &[
+                Some("a".to_string()),
+                Some("b,c".to_string()),
+                Some("d\"e".to_string()),
+                Some(String::new()),
+                None,
+            ],
+        );
+        assert_eq!(out, "a,\"b,c\",\"d\"\"e\",\"\",\n");
+    }
+
+    #[test]
+    fn validate() {
+        let conflicting = CsvOptions {
+            quote: ',',
+            ..Default::default()
+        };
+        assert!(conflicting.validate().is_err());
+        let newline = CsvOptions {
+            escape: '\n',
+            ..Default::default()
+        };
+        assert!(newline.validate().is_err());
+        assert!(CsvOptions::default().validate().is_ok());
+    }
+}
@@ -1,15 +1,65 @@
-use crate::catalog::defs::TableDefinition;
+use crate::{
+    catalog::defs::{
+        ColumnDefinition, FunctionDefinition, IndexDefinition, SequenceDefinition, TableDefinition,
+    },
+    core::{IntWidth, Type},
+    sql::planner::ScalarExpr,
+};
 
+/// The boolean flags of jobs are `IF NOT EXISTS` or `IF EXISTS`, the objects
+/// already existing or not existing are skipped with them.
 #[derive(Debug, Clone)]
 pub enum DDLJob {
+    /// Create database with the given name.
+    CreateDatabase(String, bool),
+    /// Drop database with the given name.
+    DropDatabase(String, bool),
     /// Create schema with the given name.
-    CreateSchema(String),
+    CreateSchema(String, bool),
     /// Drop schema with the given name.
-    DropSchemas(Vec<String>),
-    /// Create table with the given definition.
-    CreateTable(String, TableDefinition),
-    /// Drop table with the given name (schema_name, table_name).
-    DropTables(Vec<(String, String)>),
+    DropSchemas(Vec<String>, bool),
+    /// Create table with the given definition, and the sequences of its serial columns.
+    CreateTable(String, TableDefinition, Vec<SequenceDefinition>, bool),
+    /// Drop table with the given name (schema_name, table_name), the sequences
+    /// owned by the table are dropped together.
+    DropTables(Vec<(String, String)>, bool),
+    /// Alter the columns of table (schema_name, table_name).
+    AlterTable(String, String, AlterTableJob),
+    /// Create index on the table (schema_name, table_name, index_def).
+    CreateIndex(String, String, IndexDefinition, bool),
+    /// Drop indexes with the given names (schema_name, table_name, index_name),
+    /// the missing ones are skipped by binder with `IF EXISTS`.
+    DropIndexes(Vec<(String, String, String)>),
+    /// Create sequence with the given definition.
+    CreateSequence(String, SequenceDefinition, bool),
+    /// Drop sequences with the given names (schema_name, sequence_name).
+    DropSequences(Vec<(String, String)>, bool),
+    /// Create function with the given definition, the flag is `OR REPLACE`.
+    CreateFunction(String, FunctionDefinition, bool),
+    /// Drop functions with the given names (schema_name, function_name), the
+    /// missing ones are skipped by binder with `IF EXISTS`.
+    DropFunctions(Vec<(String, String)>),
+    /// Collect the statistics of tables (schema_name, table_name).
+    Analyze(Vec<(String, String)>),
+    /// Compact the heaps of tables (schema_name, table_name).
+    Vacuum(Vec<(String, String)>),
     /// Show tables (schema_name)
     ShowTables(String),
 }
+
+/// Change of the columns by `ALTER TABLE`, the columns are referred by index.
+#[derive(Debug, Clone)]
+pub enum AlterTableJob {
+    /// Add the column, the existing tuples are filled with its bound default value,
+    /// or the new sequence of serial column.
+    AddColumn(
+        ColumnDefinition,
+        Option<ScalarExpr>,
+        Option<SequenceDefinition>,
+    ),
+    DropColumn(usize),
+    RenameColumn(usize, String),
+    /// Change the type of column with the declared width of integer type, the
+    /// existing values, or the `USING` expression over the old tuple, are cast to it.
+    AlterColumnType(usize, Type, Option<IntWidth>, Option<ScalarExpr>),
+}
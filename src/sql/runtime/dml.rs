@@ -1,7 +1,57 @@
-use crate::core::Tuple;
+use super::CopyJob;
+use crate::{
+    core::{Datum, SQLError},
+    sql::planner::ScalarExpr,
+};
 
 #[derive(Debug, Clone)]
 pub enum DMLJob {
-    /// `INSERT INTO` statement, insert a series of tuples into a table.
-    Insert((String, String), Vec<Tuple>),
+    /// `INSERT INTO` statement, insert a series of rows into a table. The
+    /// values are constant expressions, which will be cast to the column types.
+    Insert((String, String), Vec<Vec<ScalarExpr>>),
+    /// `UPDATE` statement, assign new values (column index, value) to the
+    /// tuples matching the optional predicate.
+    Update(
+        (String, String),
+        Vec<(usize, ScalarExpr)>,
+        Option<ScalarExpr>,
+    ),
+    /// `DELETE` statement, remove the tuples matching the optional predicate.
+    Delete((String, String), Option<ScalarExpr>),
+    /// `COPY FROM` statement, insert the records of file. The columns not in
+    /// the file are filled with the default values of all the columns.
+    CopyFrom(CopyJob, Vec<ScalarExpr>),
+    /// `COPY TO` statement, write the tuples of table to file.
+    CopyTo(CopyJob),
+}
+
+impl DMLJob {
+    /// The table modified or read by the job.
+    pub fn table(&self) -> &(String, String) {
+        match self {
+            DMLJob::Insert(name, _) | DMLJob::Update(name, _, _) | DMLJob::Delete(name, _) => name,
+            DMLJob::CopyFrom(job, _) | DMLJob::CopyTo(job) => &job.table,
+        }
+    }
+}
+
+impl DMLJob {
+    /// Replace the parameter placeholders with the given values.
+    pub fn bind_parameters(&mut self, values: &[Datum]) -> Result<(), SQLError> {
+        match self {
+            DMLJob::Insert(_, rows) => rows
+                .iter_mut()
+                .flatten()
+                .try_for_each(|scalar| scalar.bind_parameters(values)),
+            DMLJob::Update(_, assignments, predicate) => assignments
+                .iter_mut()
+                .map(|(_, scalar)| scalar)
+                .chain(predicate.iter_mut())
+                .try_for_each(|scalar| scalar.bind_parameters(values)),
+            DMLJob::Delete(_, predicate) => predicate
+                .iter_mut()
+                .try_for_each(|scalar| scalar.bind_parameters(values)),
+            DMLJob::CopyFrom(_, _) | DMLJob::CopyTo(_) => Ok(()),
+        }
+    }
 }
@@ -1,51 +1,178 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    cell::Cell,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    ops::{Bound, Range},
     sync::Arc,
+    time::Instant,
 };
 
-use super::{DDLJob, DMLJob};
+use super::{
+    builder::{ExecutorBuilder, Schema, SessionSchema},
+    copy_file_error,
+    foreign_key::{format_key, lock_relations, ForeignKeys, TableName},
+    pipeline::{Pipeline, Sink},
+    read_parquet, write_parquet, AlterTableJob, CopyFormat, DDLJob, DMLJob, RuntimeFilter,
+};
 use crate::{
-    core::{tuple::Tuple, Datum, ErrorKind, SQLError},
+    catalog::{
+        defs::{
+            ColumnDefinition, ForeignKeyDefinition, IndexDefinition, TableDefinition,
+            TableStatistics,
+        },
+        Catalog, DEFAULT_DATABASE,
+    },
+    core::{
+        sqlstate, tuple::Tuple, Chunk, Datum, ErrorKind, IntWidth, Json, SQLError, SortOrder, Type,
+        CHUNK_SIZE,
+    },
     sql::{
         expression::{
             aggregate::{AggregateFunction, AggregateState},
+            type_check::{coerce_to_boolean, type_check},
+            window::{
+                WindowFrame, WindowFrameBound, WindowFrameUnits, WindowFunction, WindowFunctionKind,
+            },
             Expression,
         },
-        session::context::QueryContext,
+        planner::physical::PhysicalPlan,
+        session::{context::QueryContext, to_pg_column_type, to_pg_type},
     },
-    storage::relation::ScanState,
+    storage::{
+        relation::{HeapTable, ScanState},
+        sequence::Sequence,
+        wal::LogRecord,
+    },
+    util::metrics::{operator_timing_enabled, METRICS},
 };
 
+thread_local! {
+    /// Time spent in the inputs of the operator being timed, in nanoseconds.
+    static INPUT_NANOS: Cell<u64> = Cell::new(0);
+}
+
 #[allow(clippy::upper_case_acronyms)]
 pub enum Executor {
-    Project(ProjectExecutor),
-    Filter(FilterExecutor),
-    Map(MapExecutor),
+    Pipeline(PipelineExecutor),
     NestedLoopJoin(NestedLoopJoinExecutor),
+    HashJoin(HashJoinExecutor),
+    HashSemiJoin(HashSemiJoinExecutor),
+    NestedLoopSemiJoin(NestedLoopSemiJoinExecutor),
+    NestedLoopLeftOuterJoin(NestedLoopLeftOuterJoinExecutor),
     HashAggregate(HashAggregateExecutor),
+    StreamAggregate(StreamAggregateExecutor),
+    RowCount(RowCountExecutor),
+    Window(WindowExecutor),
+    Apply(ApplyExecutor),
+    Sort(SortExecutor),
+    Distinct(DistinctExecutor),
+    UnionAll(UnionAllExecutor),
+    Limit(LimitExecutor),
     Scan(ScanExecutor),
+    IndexScan(IndexScanExecutor),
 
     DDL(DDLExecutor),
     DML(DMLExecutor),
+    CreateTableAs(CreateTableAsExecutor),
     Use(String),
+    SetVariable(String, Option<String>),
 
     Values(ValuesExecutor),
+    Unnest(UnnestExecutor),
+    Explain(ExplainExecutor),
 }
 
 /// Executor is responsible for executing a query plan.
-/// It is implemented in a pull-based manner, i.e. Volcano-style.
-/// The executor will be `open()`-ed, then `next()` will be called repeatedly
-/// until it returns None, then `close()` will be called.
+/// The stateless operators are fused into pipelines, which push chunks through
+/// them, and the pipeline breakers, e.g. aggregation and the build side of hash
+/// join, drive their input pipelines into sinks. Between the pipelines the
+/// executors are pulled: the executor will be `open()`-ed, then `next()` will be
+/// called repeatedly until it returns None, then `close()` will be called.
 impl Executor {
     pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        self.timed(|executor| executor.open_inner(ctx))
+    }
+
+    /// Pull the next tuple, `None` if the executor is exhausted. Fails if
+    /// the session is interrupted, which is checked on every pull.
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        ctx.interrupts.check()?;
+        self.timed(|executor| executor.next_inner(ctx))
+    }
+
+    /// Fetch the next chunk of tuples, `None` if the executor is exhausted.
+    /// The chunks are never empty. The executors that are not vectorized are
+    /// pulled one tuple at a time to fill the chunk.
+    ///
+    /// An executor should be pulled either by `next` or by `next_chunk`.
+    pub fn next_chunk(&mut self, ctx: &mut QueryContext) -> Result<Option<Chunk>, SQLError> {
+        ctx.interrupts.check()?;
+        self.timed(|executor| executor.next_chunk_inner(ctx))
+    }
+
+    /// Run the function on the executor, the time spent in it, excluding the
+    /// time spent in the inputs, is added to the metrics if it's enabled.
+    fn timed<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        if !operator_timing_enabled() {
+            return f(self);
+        }
+        let outer = INPUT_NANOS.with(|nanos| nanos.replace(0));
+        let start = Instant::now();
+        let result = f(self);
+        let elapsed = start.elapsed().as_nanos() as u64;
+        let inputs = INPUT_NANOS.with(|nanos| nanos.replace(outer + elapsed));
+        METRICS.record_operator(self.index(), elapsed.saturating_sub(inputs));
+        result
+    }
+
+    /// Index of the operator in `metrics::OPERATORS`.
+    fn index(&self) -> usize {
+        match self {
+            Executor::Pipeline(_) => 0,
+            Executor::NestedLoopJoin(_) => 1,
+            Executor::HashJoin(_) => 2,
+            Executor::HashSemiJoin(_) => 3,
+            Executor::NestedLoopSemiJoin(_) => 4,
+            Executor::NestedLoopLeftOuterJoin(_) => 5,
+            Executor::HashAggregate(_) => 6,
+            Executor::StreamAggregate(_) => 7,
+            Executor::RowCount(_) => 8,
+            Executor::Window(_) => 9,
+            Executor::Sort(_) => 10,
+            Executor::Distinct(_) => 11,
+            Executor::Scan(_) => 12,
+            Executor::IndexScan(_) => 13,
+            Executor::DDL(_) => 14,
+            Executor::DML(_) => 15,
+            Executor::CreateTableAs(_) => 16,
+            Executor::Use(_) => 17,
+            Executor::SetVariable(..) => 18,
+            Executor::Values(_) => 19,
+            Executor::Unnest(_) => 20,
+            Executor::Explain(_) => 21,
+            Executor::Limit(_) => 22,
+            Executor::Apply(_) => 23,
+            Executor::UnionAll(_) => 24,
+        }
+    }
+
+    fn open_inner(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
         match self {
             Executor::DDL(ddl_exec) => ddl_exec.open(ctx),
             Executor::DML(dml_exec) => dml_exec.open(ctx),
+            Executor::CreateTableAs(create_exec) => create_exec.open(ctx),
+            Executor::Explain(explain_exec) => explain_exec.open(ctx),
             Executor::NestedLoopJoin(nlj_exec) => nlj_exec.open(ctx),
+            Executor::HashJoin(hash_join_exec) => hash_join_exec.open(ctx),
+            Executor::HashSemiJoin(semi_join_exec) => semi_join_exec.open(ctx),
+            Executor::NestedLoopSemiJoin(semi_join_exec) => semi_join_exec.open(ctx),
+            Executor::NestedLoopLeftOuterJoin(outer_join_exec) => outer_join_exec.open(ctx),
+            Executor::IndexScan(index_scan_exec) => index_scan_exec.open(ctx),
             Executor::Use(schema_name) => {
                 ctx.current_schema = schema_name.clone();
                 Ok(())
             }
+            Executor::SetVariable(name, value) => ctx.settings.set(name, value.clone()),
             _ => {
                 for child in self.children_mut() {
                     child.open(ctx)?;
@@ -55,20 +182,52 @@ impl Executor {
         }
     }
 
-    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+    fn next_inner(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
         match self {
-            Executor::Filter(filter_exec) => filter_exec.next(ctx),
-            Executor::Map(map_exec) => map_exec.next(ctx),
-            Executor::Project(project_exec) => project_exec.next(ctx),
+            Executor::Pipeline(pipeline_exec) => pipeline_exec.next(ctx),
             Executor::Scan(scan_exec) => scan_exec.next(ctx),
+            Executor::IndexScan(index_scan_exec) => index_scan_exec.next(ctx),
             Executor::DDL(ddl_exec) => ddl_exec.next(ctx),
+            Executor::DML(dml_exec) => dml_exec.next(ctx),
             Executor::NestedLoopJoin(nlj_exec) => nlj_exec.next(ctx),
+            Executor::HashJoin(hash_join_exec) => hash_join_exec.next(ctx),
+            Executor::HashSemiJoin(semi_join_exec) => semi_join_exec.next(ctx),
+            Executor::NestedLoopSemiJoin(semi_join_exec) => semi_join_exec.next(ctx),
+            Executor::NestedLoopLeftOuterJoin(outer_join_exec) => outer_join_exec.next(ctx),
             Executor::HashAggregate(hash_aggr_exec) => hash_aggr_exec.next(ctx),
+            Executor::StreamAggregate(stream_aggr_exec) => stream_aggr_exec.next(ctx),
+            Executor::RowCount(row_count_exec) => row_count_exec.next(ctx),
+            Executor::Window(window_exec) => window_exec.next(ctx),
+            Executor::Apply(apply_exec) => apply_exec.next(ctx),
+            Executor::Sort(sort_exec) => sort_exec.next(ctx),
+            Executor::Distinct(distinct_exec) => distinct_exec.next(ctx),
+            Executor::UnionAll(union_exec) => union_exec.next(ctx),
+            Executor::Limit(limit_exec) => limit_exec.next(ctx),
             Executor::Values(values_exec) => values_exec.next(ctx),
+            Executor::Unnest(unnest_exec) => unnest_exec.next(ctx),
+            Executor::Explain(explain_exec) => explain_exec.next(ctx),
+            Executor::CreateTableAs(create_exec) => create_exec.next(ctx),
             _ => Ok(None),
         }
     }
 
+    fn next_chunk_inner(&mut self, ctx: &mut QueryContext) -> Result<Option<Chunk>, SQLError> {
+        match self {
+            Executor::Pipeline(pipeline_exec) => pipeline_exec.next_chunk(ctx),
+            Executor::Scan(scan_exec) => scan_exec.next_chunk(ctx),
+            _ => {
+                let mut chunk = Chunk::with_capacity(CHUNK_SIZE);
+                while !chunk.is_full() {
+                    match self.next(ctx)? {
+                        Some(tuple) => chunk.push(tuple),
+                        None => break,
+                    }
+                }
+                Ok(chunk.non_empty())
+            }
+        }
+    }
+
     #[allow(clippy::only_used_in_recursion)]
     pub fn close(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
         {
@@ -81,122 +240,473 @@ impl Executor {
 
     pub fn children_mut<'a>(&'a mut self) -> Box<dyn Iterator<Item = &'a mut Executor> + '_> {
         match self {
-            Executor::Project(project_exec) => {
-                Box::new(std::iter::once(project_exec.child.as_mut()))
+            Executor::Pipeline(pipeline_exec) => {
+                Box::new(std::iter::once(pipeline_exec.pipeline.source.as_mut()))
             }
-            Executor::Filter(filter_exec) => Box::new(std::iter::once(filter_exec.child.as_mut())),
-            Executor::Map(map_exec) => Box::new(std::iter::once(map_exec.child.as_mut())),
             Executor::NestedLoopJoin(nlj_exec) => Box::new(
                 Box::new(std::iter::once(nlj_exec.outer_table.as_mut()))
                     .chain(Box::new(std::iter::once(nlj_exec.inner_table.as_mut()))),
             ),
+            Executor::HashJoin(hash_join_exec) => Box::new(
+                std::iter::once(hash_join_exec.left.as_mut())
+                    .chain(std::iter::once(hash_join_exec.right.source.as_mut())),
+            ),
+            Executor::HashSemiJoin(semi_join_exec) => Box::new(
+                std::iter::once(semi_join_exec.left.as_mut())
+                    .chain(std::iter::once(semi_join_exec.right.as_mut())),
+            ),
+            Executor::NestedLoopSemiJoin(semi_join_exec) => Box::new(
+                std::iter::once(semi_join_exec.left.as_mut())
+                    .chain(std::iter::once(semi_join_exec.right.as_mut())),
+            ),
+            Executor::NestedLoopLeftOuterJoin(outer_join_exec) => Box::new(
+                std::iter::once(outer_join_exec.left.as_mut())
+                    .chain(std::iter::once(outer_join_exec.right.as_mut())),
+            ),
             Executor::HashAggregate(hash_aggr_exec) => {
-                Box::new(std::iter::once(hash_aggr_exec.input_executor.as_mut()))
+                Box::new(std::iter::once(hash_aggr_exec.input.source.as_mut()))
+            }
+            Executor::StreamAggregate(stream_aggr_exec) => {
+                Box::new(std::iter::once(stream_aggr_exec.input.source.as_mut()))
+            }
+            Executor::Window(window_exec) => Box::new(std::iter::once(window_exec.child.as_mut())),
+            Executor::Apply(apply_exec) => Box::new(std::iter::once(apply_exec.child.as_mut())),
+            Executor::Sort(sort_exec) => Box::new(std::iter::once(sort_exec.input.source.as_mut())),
+            Executor::Distinct(distinct_exec) => {
+                Box::new(std::iter::once(distinct_exec.child.as_mut()))
+            }
+            Executor::UnionAll(union_exec) => Box::new(
+                std::iter::once(union_exec.left.as_mut())
+                    .chain(std::iter::once(union_exec.right.as_mut())),
+            ),
+            Executor::Limit(limit_exec) => Box::new(std::iter::once(limit_exec.child.as_mut())),
+            Executor::CreateTableAs(create_exec) => {
+                Box::new(std::iter::once(create_exec.child.as_mut()))
+            }
+            Executor::Explain(explain_exec) => {
+                Box::new(explain_exec.child.iter_mut().map(Box::as_mut))
             }
 
             Executor::Use(_)
+            | Executor::SetVariable(..)
             | Executor::Values(_)
+            | Executor::Unnest(_)
             | Executor::Scan(_)
+            | Executor::IndexScan(_)
+            | Executor::RowCount(_)
             | Executor::DML(_)
             | Executor::DDL(_) => Box::new(std::iter::empty()),
         }
     }
 }
 
+/// Remaining tuples of the last chunk, so the vectorized executors can also be
+/// pulled one tuple at a time.
+#[derive(Default)]
+struct ChunkBuffer {
+    tuples: std::vec::IntoIter<Tuple>,
+}
+
+impl ChunkBuffer {
+    fn pop(&mut self) -> Option<Tuple> {
+        self.tuples.next()
+    }
+
+    /// Replace the buffered tuples with the chunk, and pop the first one.
+    fn refill(&mut self, chunk: Option<Chunk>) -> Option<Tuple> {
+        self.tuples = chunk?.into_iter();
+        self.pop()
+    }
+}
+
 pub struct ValuesExecutor {
-    pub values: VecDeque<Tuple>,
+    /// Rows of constant expressions, evaluated when they are fetched.
+    pub rows: VecDeque<Vec<Expression>>,
 }
 
 impl ValuesExecutor {
-    pub fn new(values: Vec<Tuple>) -> Self {
+    pub fn new(rows: Vec<Vec<Expression>>) -> Self {
+        Self { rows: rows.into() }
+    }
+
+    pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        self.rows
+            .pop_front()
+            .map(|row| {
+                let values = row
+                    .iter()
+                    .map(|expr| expr.eval(&Tuple::default()))
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+                Ok(Tuple::new(values))
+            })
+            .transpose()
+    }
+}
+
+pub struct UnnestExecutor {
+    /// Expressions of the arrays, evaluated when the first row is fetched.
+    arrays: Vec<Expression>,
+    /// Elements of the evaluated arrays, and the index of the next row.
+    state: Option<(Vec<Vec<Datum>>, usize)>,
+}
+
+impl UnnestExecutor {
+    pub fn new(arrays: Vec<Expression>) -> Self {
         Self {
-            values: values.into(),
+            arrays,
+            state: None,
         }
     }
 
     pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
-        Ok(self.values.pop_front())
+        if self.state.is_none() {
+            let elements = self
+                .arrays
+                .iter()
+                .map(|array| match array.eval(&Tuple::default())? {
+                    Datum::Array(values) => Ok(values),
+                    _ => Ok(vec![]),
+                })
+                .collect::<Result<Vec<_>, SQLError>>()?;
+            self.state = Some((elements, 0));
+        }
+
+        let (elements, index) = self.state.as_mut().unwrap();
+        if elements.iter().all(|values| *index >= values.len()) {
+            return Ok(None);
+        }
+
+        let values = elements
+            .iter()
+            .map(|values| values.get(*index).cloned().unwrap_or(Datum::Null))
+            .collect();
+        *index += 1;
+
+        Ok(Some(Tuple::new(values)))
     }
 }
 
 pub struct ScanExecutor {
     schema_name: String,
     table_name: String,
+    /// Version of catalog the statement is bound with.
+    catalog_version: u64,
     scan_state: ScanState,
+    /// Columns of the table to emit, all of them if `None`.
+    projections: Option<Vec<usize>>,
+    /// Tuples failing any of them are skipped.
+    runtime_filters: Vec<RuntimeFilter>,
+    buffer: ChunkBuffer,
 }
 
 impl ScanExecutor {
-    pub fn new(schema_name: &str, table_name: &str) -> Self {
+    pub fn new(schema_name: &str, table_name: &str, catalog_version: u64) -> Self {
         Self {
             scan_state: ScanState::default(),
             schema_name: schema_name.to_string(),
             table_name: table_name.to_string(),
+            catalog_version,
+            projections: None,
+            runtime_filters: vec![],
+            buffer: ChunkBuffer::default(),
         }
     }
 
+    /// Only emit the given columns of the tuples.
+    pub fn projections(mut self, projections: Vec<usize>) -> Self {
+        self.projections = Some(projections);
+        self
+    }
+
+    /// Skip the tuples without any match in the hash joins probing them.
+    pub fn runtime_filters(mut self, runtime_filters: Vec<RuntimeFilter>) -> Self {
+        self.runtime_filters = runtime_filters;
+        self
+    }
+
     pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        if let Some(tuple) = self.buffer.pop() {
+            return Ok(Some(tuple));
+        }
+        let chunk = self.next_chunk(ctx)?;
+        Ok(self.buffer.refill(chunk))
+    }
+
+    /// Read the tuples of a chunk with the table locked once.
+    pub fn next_chunk(&mut self, ctx: &mut QueryContext) -> Result<Option<Chunk>, SQLError> {
+        let catalog = ctx.catalog();
+        catalog.check_table_version(
+            &ctx.current_database,
+            &self.schema_name,
+            &self.table_name,
+            self.catalog_version,
+        )?;
         let table = ctx
             .storage_mgr
-            .get_relation(&self.schema_name, &self.table_name)
+            .get_relation(&ctx.current_database, &self.schema_name, &self.table_name)
             .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))?;
-        let tuple = table.scan(&mut self.scan_state);
-        Ok(tuple)
+        let table = table.read().unwrap();
+
+        let mut chunk = Chunk::with_capacity(CHUNK_SIZE);
+        while !chunk.is_full() {
+            let Some(tuple) = table.scan(&mut self.scan_state) else {
+                break;
+            };
+            if !self
+                .runtime_filters
+                .iter()
+                .all(|filter| filter.check(&tuple))
+            {
+                continue;
+            }
+            chunk.push(match &self.projections {
+                Some(projections) => tuple.project(projections),
+                None => tuple,
+            });
+        }
+
+        Ok(chunk.non_empty())
     }
 }
 
-pub struct ProjectExecutor {
-    pub child: Box<Executor>,
-    pub projections: Vec<usize>,
+/// Emit the number of tuples in a table, maintained by the heap.
+pub struct RowCountExecutor {
+    schema_name: String,
+    table_name: String,
+    /// Version of catalog the statement is bound with.
+    catalog_version: u64,
+    done: bool,
 }
 
-impl ProjectExecutor {
-    pub fn new(child: Box<Executor>, projections: Vec<usize>) -> Self {
-        Self { child, projections }
+impl RowCountExecutor {
+    pub fn new(schema_name: &str, table_name: &str, catalog_version: u64) -> Self {
+        Self {
+            schema_name: schema_name.to_string(),
+            table_name: table_name.to_string(),
+            catalog_version,
+            done: false,
+        }
     }
 
     pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
-        let tuple = self.child.next(ctx)?;
-        Ok(tuple.map(|tuple| tuple.project(&self.projections)))
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+
+        let catalog = ctx.catalog();
+        catalog.check_table_version(
+            &ctx.current_database,
+            &self.schema_name,
+            &self.table_name,
+            self.catalog_version,
+        )?;
+        let table = ctx
+            .storage_mgr
+            .get_relation(&ctx.current_database, &self.schema_name, &self.table_name)
+            .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))?;
+        let row_count = table.read().unwrap().row_count();
+
+        Ok(Some(Tuple::new(vec![Datum::Int(row_count as i64)])))
     }
 }
 
-pub struct FilterExecutor {
-    pub child: Box<Executor>,
-    pub predicate: Box<dyn Fn(Tuple) -> bool>,
+/// Scan the tuples in a key range of an index, in the order of keys.
+pub struct IndexScanExecutor {
+    schema_name: String,
+    table_name: String,
+    /// Version of catalog the statement is bound with.
+    catalog_version: u64,
+    index_name: String,
+    key_types: Vec<Type>,
+    prefix: Vec<Expression>,
+    lower: Option<(Expression, bool)>,
+    upper: Option<(Expression, bool)>,
+    /// Key columns and width of the table, if the tuples are built from the
+    /// index entries only.
+    index_only: Option<(Vec<usize>, usize)>,
+    /// Columns of the table to emit, all of them if `None`.
+    projections: Option<Vec<usize>>,
+    tuples: VecDeque<Tuple>,
 }
 
-impl FilterExecutor {
-    pub fn new(child: Box<Executor>, predicate: Box<dyn Fn(Tuple) -> bool>) -> Self {
-        Self { child, predicate }
+impl IndexScanExecutor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        schema_name: &str,
+        table_name: &str,
+        catalog_version: u64,
+        index_name: &str,
+        key_types: Vec<Type>,
+        prefix: Vec<Expression>,
+        lower: Option<(Expression, bool)>,
+        upper: Option<(Expression, bool)>,
+    ) -> Self {
+        Self {
+            schema_name: schema_name.to_string(),
+            table_name: table_name.to_string(),
+            catalog_version,
+            index_name: index_name.to_string(),
+            key_types,
+            prefix,
+            lower,
+            upper,
+            index_only: None,
+            projections: None,
+            tuples: VecDeque::new(),
+        }
+    }
+
+    /// Skip the heap, and build the tuples with the key columns of index entries.
+    pub fn index_only(mut self, columns: Vec<usize>, width: usize) -> Self {
+        self.index_only = Some((columns, width));
+        self
     }
 
-    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
-        loop {
-            let tuple = self.child.next(ctx)?;
-            if let Some(tuple) = tuple {
-                if (self.predicate)(tuple.clone()) {
-                    return Ok(Some(tuple));
-                }
+    /// Only emit the given columns of the tuples.
+    pub fn projections(mut self, projections: Vec<usize>) -> Self {
+        self.projections = Some(projections);
+        self
+    }
+
+    pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        let eval_key = |expr: &Expression, typ: &Type| -> Result<Datum, SQLError> {
+            let value = expr.eval(&Tuple::default())?;
+            Ok(if value.typ() == *typ || value == Datum::Null {
+                value
             } else {
-                return Ok(None);
-            }
+                value.cast(typ)
+            })
+        };
+
+        let prefix = self
+            .prefix
+            .iter()
+            .zip(self.key_types.iter())
+            .map(|(expr, typ)| eval_key(expr, typ))
+            .collect::<Result<Vec<_>, _>>()?;
+        let bound_type = &self.key_types[prefix.len().min(self.key_types.len() - 1)];
+        let lower = self
+            .lower
+            .as_ref()
+            .map(|(expr, inclusive)| Ok::<_, SQLError>((eval_key(expr, bound_type)?, *inclusive)))
+            .transpose()?;
+        let upper = self
+            .upper
+            .as_ref()
+            .map(|(expr, inclusive)| Ok::<_, SQLError>((eval_key(expr, bound_type)?, *inclusive)))
+            .transpose()?;
+
+        // Comparing with NULL never holds
+        if prefix
+            .iter()
+            .chain(lower.iter().map(|(value, _)| value))
+            .chain(upper.iter().map(|(value, _)| value))
+            .any(|value| *value == Datum::Null)
+        {
+            return Ok(());
         }
+
+        let to_bound = |bound: Option<(Datum, bool)>| -> Bound<Vec<Datum>> {
+            match bound {
+                Some((value, inclusive)) => {
+                    let mut key = prefix.clone();
+                    key.push(value);
+                    if inclusive {
+                        Bound::Included(key)
+                    } else {
+                        Bound::Excluded(key)
+                    }
+                }
+                None if !prefix.is_empty() => Bound::Included(prefix.clone()),
+                None => Bound::Unbounded,
+            }
+        };
+        let restricted = prefix.len() + (lower.is_some() || upper.is_some()) as usize;
+        let lower = to_bound(lower);
+        let upper = to_bound(upper);
+
+        let catalog = ctx.catalog();
+        catalog.check_table_version(
+            &ctx.current_database,
+            &self.schema_name,
+            &self.table_name,
+            self.catalog_version,
+        )?;
+        let table = ctx
+            .storage_mgr
+            .get_relation(&ctx.current_database, &self.schema_name, &self.table_name)
+            .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))?;
+        let table = table.read().unwrap();
+        let index = table.index(&self.index_name).ok_or_else(|| {
+            SQLError::new(
+                ErrorKind::UnknownError,
+                format!("cannot find index: {}", self.index_name),
+            )
+        })?;
+
+        self.tuples = index
+            .tree
+            .range(as_slice_bound(&lower), as_slice_bound(&upper))
+            // NULL keys are sorted last, but never match a comparison
+            .filter(|(key, _)| !key[..restricted].contains(&Datum::Null))
+            .filter_map(|(key, tid)| match &self.index_only {
+                Some((columns, width)) => {
+                    let mut values = vec![Datum::Null; *width];
+                    for (column, value) in columns.iter().zip(key.iter()) {
+                        values[*column] = value.clone();
+                    }
+                    Some(Tuple::new(values))
+                }
+                None => table.get(tid),
+            })
+            .map(|tuple| match &self.projections {
+                Some(projections) => tuple.project(projections),
+                None => tuple,
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        Ok(self.tuples.pop_front())
     }
 }
 
-pub struct MapExecutor {
-    pub child: Box<Executor>,
-    pub map_fn: Box<dyn Fn(Tuple) -> Tuple>,
+fn as_slice_bound(bound: &Bound<Vec<Datum>>) -> Bound<&[Datum]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key),
+        Bound::Excluded(key) => Bound::Excluded(key),
+        Bound::Unbounded => Bound::Unbounded,
+    }
 }
 
-impl MapExecutor {
-    pub fn new(child: Box<Executor>, map_fn: Box<dyn Fn(Tuple) -> Tuple>) -> Self {
-        Self { child, map_fn }
+/// Executor pulling the chunks out of a pipeline, the filters, projections
+/// and maps of the pipeline are fused into a single executor.
+pub struct PipelineExecutor {
+    pub pipeline: Pipeline,
+    buffer: ChunkBuffer,
+}
+
+impl PipelineExecutor {
+    pub fn new(pipeline: Pipeline) -> Self {
+        Self {
+            pipeline,
+            buffer: ChunkBuffer::default(),
+        }
     }
 
     pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
-        let tuple = self.child.next(ctx)?;
-        Ok(tuple.map(|tuple| (self.map_fn)(tuple)))
+        if let Some(tuple) = self.buffer.pop() {
+            return Ok(Some(tuple));
+        }
+        let chunk = self.pipeline.next_chunk(ctx)?;
+        Ok(self.buffer.refill(chunk))
+    }
+
+    pub fn next_chunk(&mut self, ctx: &mut QueryContext) -> Result<Option<Chunk>, SQLError> {
+        self.pipeline.next_chunk(ctx)
     }
 }
 
@@ -253,229 +763,2882 @@ impl NestedLoopJoinExecutor {
     pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
         let state = self.state.as_mut().unwrap();
 
-        loop {
-            if state.inner_tuples.is_empty() {
-                return Ok(None);
-            }
-
-            if state.outer_tuple.is_none() {
-                // Try to get the next outer tuple.
-                let outer_tuple = self.outer_table.next(ctx)?;
-                if let Some(outer_tuple) = outer_tuple {
-                    state.outer_tuple = Some(outer_tuple);
-                } else {
-                    return Ok(None);
-                }
-            }
-
-            if state.inner_tuple_idx == state.inner_tuples.len() {
-                // Join of previous outer tuple is done.
-                state.inner_tuple_idx = 0;
-                state.outer_tuple = None;
-                continue;
-            }
+        if state.inner_tuples.is_empty() {
+            return Ok(None);
+        }
 
-            let inner_tuple = state.inner_tuples[state.inner_tuple_idx].clone();
-            state.inner_tuple_idx += 1;
-            return Ok(Some(Self::combine_tuple(
-                state.outer_tuple.clone().unwrap(),
-                inner_tuple,
-            )));
+        if state.outer_tuple.is_none() {
+            // Join of previous outer tuple is done, try to get the next one.
+            let Some(outer_tuple) = self.outer_table.next(ctx)? else {
+                return Ok(None);
+            };
+            state.outer_tuple = Some(outer_tuple);
+            state.inner_tuple_idx = 0;
         }
-    }
 
-    fn combine_tuple(left_tuple: Tuple, right_tuple: Tuple) -> Tuple {
-        let mut combined_tuple = left_tuple;
-        combined_tuple.values.extend(right_tuple.values);
+        let inner_tuple = &state.inner_tuples[state.inner_tuple_idx];
+        state.inner_tuple_idx += 1;
+        // The outer tuple is moved into the combination with the last inner tuple
+        let mut combined_tuple = if state.inner_tuple_idx == state.inner_tuples.len() {
+            state.outer_tuple.take().unwrap()
+        } else {
+            state.outer_tuple.clone().unwrap()
+        };
         combined_tuple
+            .values
+            .extend(inner_tuple.values.iter().cloned());
+
+        Ok(Some(combined_tuple))
     }
 }
 
-#[derive(Default)]
-struct HashAggregateState {
-    hash_table: HashMap<Vec<Datum>, Vec<AggregateState>>,
-    /// A single group is used for scalar aggregates.
-    single_group: Option<Vec<AggregateState>>,
-    result_tuples: Option<VecDeque<Tuple>>,
+/// Hash table built on the keys of the right side of a hash join.
+struct JoinHashTable {
+    /// Evaluated with the `right` tuples.
+    right_keys: Vec<Expression>,
+    tuples: HashMap<Vec<Datum>, Vec<Tuple>>,
 }
 
-pub struct HashAggregateExecutor {
-    pub group_by: Vec<Expression>,
-    pub aggregates: Vec<(Arc<AggregateFunction>, Vec<Expression>)>,
-    pub input_executor: Box<Executor>,
+impl Sink for JoinHashTable {
+    fn consume(&mut self, chunk: Chunk) -> Result<(), SQLError> {
+        for tuple in chunk {
+            if let Some(key) = HashJoinExecutor::eval_keys(&self.right_keys, &tuple)? {
+                self.tuples.entry(key).or_default().push(tuple);
+            }
+        }
+        Ok(())
+    }
+}
 
-    state: HashAggregateState,
+/// Hash-based inner join executor, used for the joins with equi-join keys.
+///
+/// The right pipeline is drained into a hash table on its keys when the
+/// executor is opened, then each tuple of the left side is combined with the
+/// right tuples of the same keys. Tuples with NULL keys never match.
+///
+/// The runtime filter is built from the keys of the hash table before the
+/// left side is opened, so the scan of the left side can skip the tuples
+/// without any match.
+pub struct HashJoinExecutor {
+    pub left: Box<Executor>,
+    pub right: Pipeline,
+    /// Evaluated with the `left` tuples.
+    pub left_keys: Vec<Expression>,
+
+    hash_table: JoinHashTable,
+    runtime_filter: Option<RuntimeFilter>,
+    /// Combined tuples of the current left tuple.
+    pending: VecDeque<Tuple>,
 }
 
-impl HashAggregateExecutor {
+impl HashJoinExecutor {
     pub fn new(
-        input: Box<Executor>,
-        group_by: Vec<Expression>,
-        aggregates: Vec<(Arc<AggregateFunction>, Vec<Expression>)>,
+        left: Box<Executor>,
+        right: Pipeline,
+        left_keys: Vec<Expression>,
+        right_keys: Vec<Expression>,
+        runtime_filter: Option<RuntimeFilter>,
     ) -> Self {
         Self {
-            group_by,
-            aggregates,
-            state: HashAggregateState::default(),
-            input_executor: input,
+            left,
+            right,
+            left_keys,
+            hash_table: JoinHashTable {
+                right_keys,
+                tuples: HashMap::new(),
+            },
+            runtime_filter,
+            pending: VecDeque::new(),
         }
     }
 
-    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
-        // At the first run we drain the input executor and build the hash table.
-        while let Some(tuple) = self.input_executor.next(ctx)? {
-            let hash_key = self
-                .group_by
-                .iter()
-                .map(|expr| expr.eval(&tuple))
-                .collect::<Result<Vec<_>, _>>()?;
+    pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        self.right.source.open(ctx)?;
+        self.right.run(ctx, &mut self.hash_table)?;
+        if let Some(runtime_filter) = &self.runtime_filter {
+            runtime_filter.build(self.hash_table.tuples.keys().map(Vec::as_slice));
+        }
+        self.left.open(ctx)
+    }
 
-            let aggregate_states = if self.group_by.is_empty() {
-                self.state.single_group.get_or_insert_with(|| {
-                    self.aggregates
-                        .iter()
-                        .map(|(agg, _)| agg.default_state.clone())
-                        .collect()
-                })
-            } else {
-                self.state.hash_table.entry(hash_key).or_insert_with(|| {
-                    self.aggregates
-                        .iter()
-                        .map(|(agg, _)| agg.default_state.clone())
-                        .collect()
-                })
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        while self.pending.is_empty() {
+            if self.hash_table.tuples.is_empty() {
+                return Ok(None);
+            }
+            let Some(tuple) = self.left.next(ctx)? else {
+                return Ok(None);
             };
 
-            for (i, (agg, args)) in self.aggregates.iter().enumerate() {
-                let arg_values = args
-                    .iter()
-                    .map(|expr| expr.eval(&tuple))
-                    .collect::<Result<Vec<_>, _>>()?;
-
-                // Accumulate current tuple into the aggregate state.
-                aggregate_states[i] = (agg.accumulate)(&arg_values, &aggregate_states[i]);
+            let Some(key) = Self::eval_keys(&self.left_keys, &tuple)? else {
+                continue;
+            };
+            let Some((last, rest)) = self
+                .hash_table
+                .tuples
+                .get(&key)
+                .and_then(|tuples| tuples.split_last())
+            else {
+                continue;
+            };
+            // The left tuple is moved into the combination with the last match
+            for right_tuple in rest {
+                let mut combined_tuple = tuple.clone();
+                combined_tuple
+                    .values
+                    .extend(right_tuple.values.iter().cloned());
+                self.pending.push_back(combined_tuple);
             }
+            let mut combined_tuple = tuple;
+            combined_tuple.values.extend(last.values.iter().cloned());
+            self.pending.push_back(combined_tuple);
         }
 
-        // Hash table is finished, we can start to produce the result tuples.
-        if self.state.result_tuples.is_none() {
-            let mut result_tuples = VecDeque::new();
+        Ok(self.pending.pop_front())
+    }
 
-            if self.group_by.is_empty() {
-                let mut result_tuple = Tuple::default();
-                let aggregate_states = self.state.single_group.get_or_insert_with(|| {
-                    self.aggregates
-                        .iter()
-                        .map(|(agg, _)| agg.default_state.clone())
-                        .collect()
-                });
+    /// Evaluate the keys of the tuple, `None` if any of them is NULL.
+    fn eval_keys(keys: &[Expression], tuple: &Tuple) -> Result<Option<Vec<Datum>>, SQLError> {
+        let key = keys
+            .iter()
+            .map(|key| key.eval(tuple))
+            .collect::<Result<Vec<_>, _>>()?;
 
-                // Add aggregate function result to result tuple
-                result_tuple
-                    .values
-                    .extend(aggregate_states.iter().map(|s| s.finalize()));
+        Ok((!key.iter().any(Datum::is_null)).then_some(key))
+    }
+}
 
-                result_tuples.push_back(result_tuple);
-            } else {
-                for (hash_key, aggregate_states) in self.state.hash_table.iter() {
-                    let mut result_tuple = Tuple::default();
+/// Values produced by the right side of a semi join.
+#[derive(Default)]
+struct SemiJoinHashTable {
+    /// Values of the single column of the right side, only used if
+    /// the join has a key.
+    hash_set: HashSet<Datum>,
+    /// Whether the right side produces any tuple.
+    has_tuple: bool,
+    /// Whether the right side contains NULL.
+    has_null: bool,
+}
 
-                    // Add group keys to result tuple
-                    result_tuple.values.extend(hash_key.iter().cloned());
-                    // Add aggregate function result to result tuple
-                    result_tuple
-                        .values
-                        .extend(aggregate_states.iter().map(|s| s.finalize()));
+impl SemiJoinHashTable {
+    fn insert(&mut self, tuple: &Tuple, key_type: Option<&Type>) {
+        self.has_tuple = true;
 
-                    result_tuples.push_back(result_tuple);
-                }
-            }
+        let value = tuple.get(0).unwrap_or(Datum::Null);
+        let value = match key_type {
+            Some(typ) => value.cast(typ),
+            None => value,
+        };
 
-            self.state.result_tuples = Some(result_tuples);
+        if value.is_null() {
+            self.has_null = true;
+        } else {
+            self.hash_set.insert(value);
         }
+    }
 
-        Ok(self.state.result_tuples.as_mut().unwrap().pop_front())
+    /// Check if the key is matched, `None` for `EXISTS`.
+    ///
+    /// NULL values follow the SQL semantics of `IN`, i.e. `x NOT IN (...)` is
+    /// never satisfied if `x` is NULL or the right side contains NULL, unless
+    /// the right side is empty.
+    fn matches(&self, key: Option<&Datum>, anti: bool) -> bool {
+        let matched = match key {
+            // `EXISTS (subquery)`
+            None => self.has_tuple,
+
+            // `x IN (subquery)`
+            Some(key) if !anti => !key.is_null() && self.hash_set.contains(key),
+
+            // `x NOT IN (subquery)`, the result is inverted below.
+            Some(key) => {
+                self.has_tuple && (key.is_null() || self.has_null || self.hash_set.contains(key))
+            }
+        };
+
+        matched != anti
     }
 }
 
-pub struct DDLExecutor {
-    pub job: DDLJob,
-    pub result_buffer: VecDeque<Tuple>,
+/// Hash-based semi join (or anti join) executor.
+///
+/// The right side is drained into a hash set when the executor is opened,
+/// then each tuple of the left side is probed against it.
+pub struct HashSemiJoinExecutor {
+    pub left: Box<Executor>,
+    pub right: Box<Executor>,
+    pub left_key: Option<Expression>,
+    /// Type to cast the right values to before building the hash set.
+    pub key_type: Option<Type>,
+    pub anti: bool,
+
+    /// Will be initialized when the executor is opened.
+    hash_table: Option<SemiJoinHashTable>,
 }
 
-impl DDLExecutor {
-    pub fn new(job: DDLJob) -> Self {
+impl HashSemiJoinExecutor {
+    pub fn new(
+        left: Box<Executor>,
+        right: Box<Executor>,
+        left_key: Option<Expression>,
+        key_type: Option<Type>,
+        anti: bool,
+    ) -> Self {
         Self {
-            job,
-            result_buffer: VecDeque::new(),
+            left,
+            right,
+            left_key,
+            key_type,
+            anti,
+            hash_table: None,
         }
     }
 
     pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
-        match &self.job {
-            DDLJob::CreateSchema(schema_name) => {
-                ctx.catalog.create_schema(schema_name)?;
-            }
-            DDLJob::DropSchemas(names) => {
-                for name in names.iter() {
-                    ctx.catalog.drop_schema(name)?;
-                }
-            }
-            DDLJob::CreateTable(schema_name, table_def) => {
-                ctx.catalog.create_table(schema_name.as_str(), table_def)?;
-                ctx.storage_mgr
-                    .create_relation(schema_name, &table_def.name);
-            }
-            DDLJob::DropTables(names) => {
-                for (schema_name, table_name) in names.iter() {
-                    ctx.catalog.drop_table(schema_name, table_name)?;
-                    ctx.storage_mgr.drop_relation(schema_name, table_name);
-                }
-            }
-            DDLJob::ShowTables(schema_name) => {
-                let tables = ctx.catalog.list_tables(schema_name)?;
-                self.result_buffer.extend(tables.iter().map(|table| {
-                    let mut tuple = Tuple::default();
-                    tuple.append(Datum::String(table.clone()));
-                    tuple
-                }));
+        self.left.open(ctx)?;
+        self.right.open(ctx)?;
+
+        let mut hash_table = SemiJoinHashTable::default();
+        while let Some(tuple) = self.right.next(ctx)? {
+            hash_table.insert(&tuple, self.key_type.as_ref());
+            if self.left_key.is_none() {
+                // We only care about the existence of tuples.
+                break;
             }
         }
 
+        self.hash_table = Some(hash_table);
         Ok(())
     }
 
-    pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
-        Ok(self.result_buffer.pop_front())
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        let hash_table = self.hash_table.as_ref().unwrap();
+
+        while let Some(tuple) = self.left.next(ctx)? {
+            let key = self
+                .left_key
+                .as_ref()
+                .map(|key| key.eval(&tuple))
+                .transpose()?;
+
+            if hash_table.matches(key.as_ref(), self.anti) {
+                return Ok(Some(tuple));
+            }
+        }
+
+        Ok(None)
     }
 }
 
-pub struct DMLExecutor {
+/// Nested-loop semi join (or anti join) executor, used for the semi joins
+/// with join conditions, e.g. decorrelated subqueries.
+///
+/// The right side is drained when the executor is opened. For each tuple of
+/// the left side, only the right tuples satisfying all the conditions (which
+/// are evaluated on the combined tuple) take part in the matching.
+pub struct NestedLoopSemiJoinExecutor {
+    pub left: Box<Executor>,
+    pub right: Box<Executor>,
+    pub left_key: Option<Expression>,
+    /// Type to cast the right values to before matching.
+    pub key_type: Option<Type>,
+    pub conditions: Vec<Expression>,
+    pub anti: bool,
+
+    right_tuples: Vec<Tuple>,
+}
+
+impl NestedLoopSemiJoinExecutor {
+    pub fn new(
+        left: Box<Executor>,
+        right: Box<Executor>,
+        left_key: Option<Expression>,
+        key_type: Option<Type>,
+        conditions: Vec<Expression>,
+        anti: bool,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            left_key,
+            key_type,
+            conditions,
+            anti,
+            right_tuples: vec![],
+        }
+    }
+
+    pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        self.left.open(ctx)?;
+        self.right.open(ctx)?;
+
+        while let Some(tuple) = self.right.next(ctx)? {
+            self.right_tuples.push(tuple);
+        }
+
+        Ok(())
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        while let Some(tuple) = self.left.next(ctx)? {
+            let key = self
+                .left_key
+                .as_ref()
+                .map(|key| key.eval(&tuple))
+                .transpose()?;
+
+            let mut hash_table = SemiJoinHashTable::default();
+            // The left values are reused for each right tuple
+            let left_width = tuple.values.len();
+            let mut combined_tuple = tuple.clone();
+            for right_tuple in self.right_tuples.iter() {
+                combined_tuple.values.truncate(left_width);
+                combined_tuple
+                    .values
+                    .extend(right_tuple.values.iter().cloned());
+
+                let mut matched = true;
+                for condition in self.conditions.iter() {
+                    if !condition.eval_predicate(&combined_tuple)? {
+                        matched = false;
+                        break;
+                    }
+                }
+                if matched {
+                    hash_table.insert(right_tuple, self.key_type.as_ref());
+                }
+            }
+
+            if hash_table.matches(key.as_ref(), self.anti) {
+                return Ok(Some(tuple));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Nested-loop left outer join executor.
+///
+/// The right side is drained when the executor is opened. Each tuple of the
+/// left side is combined with the right tuples satisfying the condition, or
+/// padded with NULLs if there is none.
+pub struct NestedLoopLeftOuterJoinExecutor {
+    pub left: Box<Executor>,
+    pub right: Box<Executor>,
+    pub condition: Expression,
+    /// Number of columns of the right side, for padding NULLs.
+    pub right_width: usize,
+
+    right_tuples: Vec<Tuple>,
+    /// Combined tuples of the current left tuple.
+    pending: VecDeque<Tuple>,
+}
+
+impl NestedLoopLeftOuterJoinExecutor {
+    pub fn new(
+        left: Box<Executor>,
+        right: Box<Executor>,
+        condition: Expression,
+        right_width: usize,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            condition,
+            right_width,
+            right_tuples: vec![],
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        self.left.open(ctx)?;
+        self.right.open(ctx)?;
+
+        while let Some(tuple) = self.right.next(ctx)? {
+            self.right_tuples.push(tuple);
+        }
+
+        Ok(())
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        while self.pending.is_empty() {
+            let Some(tuple) = self.left.next(ctx)? else {
+                return Ok(None);
+            };
+
+            // The left values are reused for each right tuple
+            let left_width = tuple.values.len();
+            let mut combined_tuple = tuple.clone();
+            for right_tuple in self.right_tuples.iter() {
+                combined_tuple.values.truncate(left_width);
+                combined_tuple
+                    .values
+                    .extend(right_tuple.values.iter().cloned());
+
+                if self.condition.eval_predicate(&combined_tuple)? {
+                    self.pending.push_back(combined_tuple.clone());
+                }
+            }
+
+            if self.pending.is_empty() {
+                let mut padded_tuple = tuple;
+                padded_tuple
+                    .values
+                    .extend(std::iter::repeat(Datum::Null).take(self.right_width));
+                self.pending.push_back(padded_tuple);
+            }
+        }
+
+        Ok(self.pending.pop_front())
+    }
+}
+
+/// Aggregate function applied to the arguments of the tuples satisfying the
+/// filter.
+pub struct AggregateCall {
+    pub func: Arc<AggregateFunction>,
+    pub args: Vec<Expression>,
+    pub filter: Option<Expression>,
+    /// The tuples are buffered and sorted by the keys before they are
+    /// accumulated.
+    pub order_by: Vec<(Expression, SortOrder)>,
+}
+
+impl AggregateCall {
+    fn default_state(&self) -> AggregateState {
+        if self.order_by.is_empty() {
+            self.func.default_state.clone()
+        } else {
+            AggregateState::Buffered(vec![])
+        }
+    }
+
+    fn accumulate(&self, tuple: &Tuple, state: &mut AggregateState) -> Result<(), SQLError> {
+        if let Some(filter) = &self.filter {
+            if !filter.eval_predicate(tuple)? {
+                return Ok(());
+            }
+        }
+
+        let arg_values = self
+            .args
+            .iter()
+            .map(|expr| expr.eval(tuple))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let AggregateState::Buffered(tuples) = state {
+            let keys = self
+                .order_by
+                .iter()
+                .map(|(expr, _)| expr.eval(tuple))
+                .collect::<Result<Vec<_>, _>>()?;
+            tuples.push((keys, arg_values));
+        } else {
+            (self.func.accumulate)(&arg_values, state)?;
+        }
+
+        Ok(())
+    }
+
+    fn finalize(&self, state: &AggregateState) -> Result<Datum, SQLError> {
+        let AggregateState::Buffered(tuples) = state else {
+            return Ok(self.func.finalize(state));
+        };
+
+        let orders = self
+            .order_by
+            .iter()
+            .map(|(_, order)| *order)
+            .collect::<Vec<_>>();
+        let mut sorted = tuples.iter().collect::<Vec<_>>();
+        sorted.sort_by(|(left, _), (right, _)| compare_sort_keys(left, right, &orders));
+        let mut state = self.func.default_state.clone();
+        for (_, args) in sorted {
+            (self.func.accumulate)(args, &mut state)?;
+        }
+        Ok(self.func.finalize(&state))
+    }
+}
+
+/// Aggregate states of the groups, built from the tuples pushed into it.
+struct AggregateTable {
+    group_by: Vec<Expression>,
+    /// With grouping sets, the group keys of a tuple in each grouping set are
+    /// followed by the index of the grouping set.
+    grouping_sets: Vec<Vec<usize>>,
+    aggregates: Vec<AggregateCall>,
+    hash_table: HashMap<Vec<Datum>, Vec<AggregateState>>,
+    /// A single group is used for scalar aggregates.
+    single_group: Option<Vec<AggregateState>>,
+}
+
+impl AggregateTable {
+    fn default_states(aggregates: &[AggregateCall]) -> Vec<AggregateState> {
+        aggregates
+            .iter()
+            .map(AggregateCall::default_state)
+            .collect()
+    }
+
+    fn is_scalar(&self) -> bool {
+        self.group_by.is_empty() && self.grouping_sets.is_empty()
+    }
+
+    /// Group keys of a grouping set, the keys out of the set are NULL.
+    fn grouping_set_keys(keys: &[Datum], set_index: usize, set: &[usize]) -> Vec<Datum> {
+        let mut hash_key = vec![Datum::Null; keys.len()];
+        for key in set {
+            hash_key[*key] = keys[*key].clone();
+        }
+        hash_key.push(Datum::Int(set_index as i64));
+        hash_key
+    }
+
+    /// Accumulate the tuple into the aggregate states of its group.
+    fn accumulate(&mut self, tuple: &Tuple) -> Result<(), SQLError> {
+        let hash_key = self
+            .group_by
+            .iter()
+            .map(|expr| expr.eval(tuple))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let aggregates = &self.aggregates;
+        // The tuple is accumulated into a group of every grouping set
+        if !self.grouping_sets.is_empty() {
+            for (i, set) in self.grouping_sets.iter().enumerate() {
+                let aggregate_states = self
+                    .hash_table
+                    .entry(Self::grouping_set_keys(&hash_key, i, set))
+                    .or_insert_with(|| Self::default_states(aggregates));
+                Self::accumulate_states(aggregates, aggregate_states, tuple)?;
+            }
+            return Ok(());
+        }
+
+        let aggregate_states = if self.group_by.is_empty() {
+            self.single_group
+                .get_or_insert_with(|| Self::default_states(aggregates))
+        } else {
+            self.hash_table
+                .entry(hash_key)
+                .or_insert_with(|| Self::default_states(aggregates))
+        };
+
+        Self::accumulate_states(aggregates, aggregate_states, tuple)
+    }
+
+    /// Accumulate the tuple into the aggregate states of a group.
+    fn accumulate_states(
+        aggregates: &[AggregateCall],
+        aggregate_states: &mut [AggregateState],
+        tuple: &Tuple,
+    ) -> Result<(), SQLError> {
+        for (agg, state) in aggregates.iter().zip(aggregate_states.iter_mut()) {
+            agg.accumulate(tuple, state)?;
+        }
+
+        Ok(())
+    }
+
+    /// Tuple of the group keys followed by the aggregate results.
+    fn result_tuple(
+        aggregates: &[AggregateCall],
+        group_keys: Vec<Datum>,
+        aggregate_states: &[AggregateState],
+    ) -> Result<Tuple, SQLError> {
+        let mut values = group_keys;
+        values.extend(Self::finalize(aggregates, aggregate_states)?);
+        Ok(Tuple::new(values))
+    }
+
+    fn finalize(
+        aggregates: &[AggregateCall],
+        aggregate_states: &[AggregateState],
+    ) -> Result<Vec<Datum>, SQLError> {
+        aggregates
+            .iter()
+            .zip(aggregate_states.iter())
+            .map(|(agg, state)| agg.finalize(state))
+            .collect()
+    }
+
+    /// Tuples of the group keys followed by the aggregate results.
+    fn results(&mut self) -> Result<VecDeque<Tuple>, SQLError> {
+        let mut result_tuples = VecDeque::new();
+
+        // Like scalar aggregates, the empty grouping sets produce a row
+        // without any input
+        let nulls = vec![Datum::Null; self.group_by.len()];
+        for (i, set) in self.grouping_sets.iter().enumerate() {
+            if set.is_empty() {
+                let aggregates = &self.aggregates;
+                self.hash_table
+                    .entry(Self::grouping_set_keys(&nulls, i, set))
+                    .or_insert_with(|| Self::default_states(aggregates));
+            }
+        }
+
+        if self.is_scalar() {
+            let aggregates = &self.aggregates;
+            let aggregate_states = self
+                .single_group
+                .get_or_insert_with(|| Self::default_states(aggregates));
+
+            // Add aggregate function result to result tuple
+            result_tuples.push_back(Tuple::new(Self::finalize(aggregates, aggregate_states)?));
+        } else {
+            for (hash_key, aggregate_states) in self.hash_table.drain() {
+                result_tuples.push_back(Self::result_tuple(
+                    &self.aggregates,
+                    hash_key,
+                    &aggregate_states,
+                )?);
+            }
+        }
+
+        Ok(result_tuples)
+    }
+}
+
+impl Sink for AggregateTable {
+    fn consume(&mut self, chunk: Chunk) -> Result<(), SQLError> {
+        chunk
+            .tuples
+            .iter()
+            .try_for_each(|tuple| self.accumulate(tuple))
+    }
+}
+
+/// Hash aggregation, which breaks the pipeline of its input.
+pub struct HashAggregateExecutor {
+    pub input: Pipeline,
+
+    table: AggregateTable,
+    result_tuples: Option<VecDeque<Tuple>>,
+}
+
+impl HashAggregateExecutor {
+    pub fn new(
+        input: Pipeline,
+        group_by: Vec<Expression>,
+        grouping_sets: Vec<Vec<usize>>,
+        aggregates: Vec<AggregateCall>,
+    ) -> Self {
+        Self {
+            input,
+            table: AggregateTable {
+                group_by,
+                grouping_sets,
+                aggregates,
+                hash_table: HashMap::new(),
+                single_group: None,
+            },
+            result_tuples: None,
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        // At the first run we drain the input pipeline into the hash table,
+        // then we can start to produce the result tuples.
+        if self.result_tuples.is_none() {
+            self.input.run(ctx, &mut self.table)?;
+            self.result_tuples = Some(self.table.results()?);
+        }
+
+        Ok(self.result_tuples.as_mut().unwrap().pop_front())
+    }
+}
+
+/// Aggregation of the input sorted on the group keys. The tuples of a group
+/// are adjacent, so a group is emitted as soon as the next one starts, and
+/// only the aggregate states of the current group are kept.
+pub struct StreamAggregateExecutor {
+    pub input: Pipeline,
+    pub group_by: Vec<Expression>,
+    pub aggregates: Vec<AggregateCall>,
+
+    buffer: ChunkBuffer,
+    /// Group keys and aggregate states of the current group.
+    group: Option<(Vec<Datum>, Vec<AggregateState>)>,
+}
+
+impl StreamAggregateExecutor {
+    pub fn new(input: Pipeline, group_by: Vec<Expression>, aggregates: Vec<AggregateCall>) -> Self {
+        Self {
+            input,
+            group_by,
+            aggregates,
+            buffer: ChunkBuffer::default(),
+            group: None,
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        loop {
+            let tuple = match self.buffer.pop() {
+                Some(tuple) => tuple,
+                None => {
+                    let chunk = self.input.next_chunk(ctx)?;
+                    match self.buffer.refill(chunk) {
+                        Some(tuple) => tuple,
+                        // The last group is finished with the input
+                        None => {
+                            return self
+                                .group
+                                .take()
+                                .map(|(group_keys, states)| {
+                                    AggregateTable::result_tuple(
+                                        &self.aggregates,
+                                        group_keys,
+                                        &states,
+                                    )
+                                })
+                                .transpose();
+                        }
+                    }
+                }
+            };
+
+            let group_keys = self
+                .group_by
+                .iter()
+                .map(|expr| expr.eval(&tuple))
+                .collect::<Result<Vec<_>, _>>()?;
+            let finished = match &self.group {
+                Some((current_keys, _)) if *current_keys == group_keys => None,
+                _ => self
+                    .group
+                    .replace((group_keys, AggregateTable::default_states(&self.aggregates))),
+            };
+
+            let (_, aggregate_states) = self.group.as_mut().unwrap();
+            AggregateTable::accumulate_states(&self.aggregates, aggregate_states, &tuple)?;
+
+            if let Some((group_keys, states)) = finished {
+                return AggregateTable::result_tuple(&self.aggregates, group_keys, &states)
+                    .map(Some);
+            }
+        }
+    }
+}
+
+/// Compare two sort keys in the orders of each key.
+pub fn compare_sort_keys(left: &[Datum], right: &[Datum], orders: &[SortOrder]) -> Ordering {
+    for ((left, right), order) in left.iter().zip(right.iter()).zip(orders.iter()) {
+        let ordering = order.compare(left, right);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+/// Apply executor, the value of the scalar subquery is appended to the input tuples.
+///
+/// The subquery is built and executed with the outer columns bound to the values
+/// of the parameters, the results are cached by the values.
+pub struct ApplyExecutor {
+    pub child: Box<Executor>,
+    pub parameters: Vec<Expression>,
+    pub array: bool,
+    pub subquery: PhysicalPlan,
+
+    cache: HashMap<Vec<Datum>, Datum>,
+}
+
+impl ApplyExecutor {
+    pub fn new(
+        child: Box<Executor>,
+        parameters: Vec<Expression>,
+        array: bool,
+        subquery: PhysicalPlan,
+    ) -> Self {
+        Self {
+            child,
+            parameters,
+            array,
+            subquery,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        let Some(mut tuple) = self.child.next(ctx)? else {
+            return Ok(None);
+        };
+
+        let values = self
+            .parameters
+            .iter()
+            .map(|parameter| parameter.eval(&tuple))
+            .collect::<Result<Vec<_>, _>>()?;
+        let value = match self.cache.get(&values) {
+            Some(value) => value.clone(),
+            None => {
+                let value = self.evaluate(ctx, &values)?;
+                self.cache.insert(values, value.clone());
+                value
+            }
+        };
+        tuple.values.push(value);
+
+        Ok(Some(tuple))
+    }
+
+    /// Execute the subquery with the values of parameters, returns the first
+    /// column of its only row, or NULL if there is no row. With `array`, the
+    /// first columns of all rows are returned as an array.
+    fn evaluate(&self, ctx: &mut QueryContext, values: &[Datum]) -> Result<Datum, SQLError> {
+        let outer = self
+            .parameters
+            .iter()
+            .zip(values)
+            .map(|(parameter, value)| Expression::Literal(value.clone(), parameter.typ().clone()))
+            .collect();
+        let (mut executor, _) = ExecutorBuilder::new(ctx).build_subquery(&self.subquery, outer)?;
+
+        executor.open(ctx)?;
+        if self.array {
+            let mut values = vec![];
+            while let Some(tuple) = executor.next(ctx)? {
+                values.extend(tuple.values.into_iter().take(1));
+            }
+            executor.close(ctx)?;
+            return Ok(Datum::Array(values));
+        }
+
+        let first = executor.next(ctx)?;
+        let second = match first {
+            Some(_) => executor.next(ctx)?,
+            None => None,
+        };
+        executor.close(ctx)?;
+
+        if second.is_some() {
+            return Err(SQLError::new(
+                ErrorKind::RuntimeError,
+                "more than one row returned by a subquery used as an expression",
+            )
+            .with_code(sqlstate::CARDINALITY_VIOLATION));
+        }
+
+        Ok(first
+            .and_then(|tuple| tuple.values.into_iter().next())
+            .unwrap_or(Datum::Null))
+    }
+}
+
+/// Window executor, the results of window functions are appended to the input tuples.
+///
+/// The input is drained at the first run. For each window function, the tuples are
+/// sorted by the partition keys and the order keys, then the function is evaluated
+/// for each partition. The tuples are emitted in the order of the last window function.
+pub struct WindowExecutor {
+    pub child: Box<Executor>,
+    pub window_functions: Vec<WindowFunction>,
+
+    result_tuples: Option<VecDeque<Tuple>>,
+}
+
+impl WindowExecutor {
+    pub fn new(child: Box<Executor>, window_functions: Vec<WindowFunction>) -> Self {
+        Self {
+            child,
+            window_functions,
+            result_tuples: None,
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        if self.result_tuples.is_none() {
+            let mut tuples = vec![];
+            while let Some(tuple) = self.child.next(ctx)? {
+                tuples.push(tuple);
+            }
+
+            let mut order = (0..tuples.len()).collect::<Vec<_>>();
+            let mut results = vec![vec![]; tuples.len()];
+            for window_function in self.window_functions.iter() {
+                let values;
+                (order, values) = Self::evaluate(window_function, &tuples)?;
+                for (result, value) in results.iter_mut().zip(values) {
+                    result.push(value);
+                }
+            }
+
+            let mut tuples = tuples.into_iter().map(Some).collect::<Vec<_>>();
+            let result_tuples = order
+                .into_iter()
+                .map(|index| {
+                    let mut tuple = tuples[index].take().unwrap();
+                    tuple.values.append(&mut results[index]);
+                    tuple
+                })
+                .collect();
+
+            self.result_tuples = Some(result_tuples);
+        }
+
+        Ok(self.result_tuples.as_mut().unwrap().pop_front())
+    }
+
+    /// Evaluate the window function, returns the sorted order of the tuples
+    /// and the result of each tuple.
+    fn evaluate(
+        window_function: &WindowFunction,
+        tuples: &[Tuple],
+    ) -> Result<(Vec<usize>, Vec<Datum>), SQLError> {
+        // (partition keys, order keys) of each tuple
+        let keys = tuples
+            .iter()
+            .map(|tuple| {
+                let partition_keys = window_function
+                    .partition_by
+                    .iter()
+                    .map(|expr| expr.eval(tuple))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let order_keys = window_function
+                    .order_by
+                    .iter()
+                    .map(|(expr, _)| expr.eval(tuple))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok((partition_keys, order_keys))
+            })
+            .collect::<Result<Vec<_>, SQLError>>()?;
+        let orders = window_function
+            .order_by
+            .iter()
+            .map(|(_, order)| *order)
+            .collect::<Vec<_>>();
+
+        let mut order = (0..tuples.len()).collect::<Vec<_>>();
+        order.sort_by(|left, right| {
+            keys[*left]
+                .0
+                .cmp(&keys[*right].0)
+                .then_with(|| compare_sort_keys(&keys[*left].1, &keys[*right].1, &orders))
+        });
+
+        let mut values = vec![Datum::Null; tuples.len()];
+        let mut start = 0;
+        while start < order.len() {
+            let mut end = start + 1;
+            while end < order.len() && keys[order[end]].0 == keys[order[start]].0 {
+                end += 1;
+            }
+
+            let partition = &order[start..end];
+            match &window_function.kind {
+                WindowFunctionKind::Aggregate(agg) => Self::evaluate_aggregate(
+                    window_function,
+                    agg,
+                    partition,
+                    &keys,
+                    &orders,
+                    tuples,
+                    &mut values,
+                )?,
+                kind => Self::evaluate_ranking(kind, partition, &keys, &mut values),
+            }
+
+            start = end;
+        }
+
+        Ok((order, values))
+    }
+
+    /// Evaluate the ranking function over the sorted partition.
+    fn evaluate_ranking(
+        kind: &WindowFunctionKind,
+        partition: &[usize],
+        keys: &[(Vec<Datum>, Vec<Datum>)],
+        values: &mut [Datum],
+    ) {
+        // Peers are the tuples with the same order keys, they have the same
+        // rank.
+        let mut peer_start = 0;
+        let mut dense_rank = 0;
+        while peer_start < partition.len() {
+            let mut peer_end = peer_start + 1;
+            while peer_end < partition.len()
+                && keys[partition[peer_end]].1 == keys[partition[peer_start]].1
+            {
+                peer_end += 1;
+            }
+            dense_rank += 1;
+
+            let peers = &partition[peer_start..peer_end];
+            match kind {
+                WindowFunctionKind::RowNumber => {
+                    for (offset, index) in peers.iter().enumerate() {
+                        values[*index] = Datum::Int((peer_start + offset + 1) as i64);
+                    }
+                }
+                WindowFunctionKind::Rank => {
+                    for index in peers.iter() {
+                        values[*index] = Datum::Int((peer_start + 1) as i64);
+                    }
+                }
+                WindowFunctionKind::DenseRank => {
+                    for index in peers.iter() {
+                        values[*index] = Datum::Int(dense_rank);
+                    }
+                }
+                WindowFunctionKind::Aggregate(_) => unreachable!(),
+            }
+
+            peer_start = peer_end;
+        }
+    }
+
+    /// Evaluate the aggregate function over the frame of each tuple of the
+    /// sorted partition. The state is reused while the frame keeps its start
+    /// and grows at its end, e.g. in the default frame.
+    #[allow(clippy::too_many_arguments)]
+    fn evaluate_aggregate(
+        window_function: &WindowFunction,
+        agg: &AggregateFunction,
+        partition: &[usize],
+        keys: &[(Vec<Datum>, Vec<Datum>)],
+        orders: &[SortOrder],
+        tuples: &[Tuple],
+        values: &mut [Datum],
+    ) -> Result<(), SQLError> {
+        let args = partition
+            .iter()
+            .map(|index| {
+                window_function
+                    .args
+                    .iter()
+                    .map(|expr| expr.eval(&tuples[*index]))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let order_keys = partition
+            .iter()
+            .map(|index| keys[*index].1.as_slice())
+            .collect::<Vec<_>>();
+
+        let mut state = agg.default_state.clone();
+        // Range of the tuples accumulated into the state
+        let mut accumulated = 0..0;
+        for (current, index) in partition.iter().enumerate() {
+            let frame = Self::frame_range(&window_function.frame, current, &order_keys, orders);
+            if frame.start != accumulated.start || frame.end < accumulated.end {
+                state = agg.default_state.clone();
+                accumulated = frame.start..frame.start;
+            }
+            for arg_values in args[accumulated.end..frame.end].iter() {
+                (agg.accumulate)(arg_values, &mut state)?;
+            }
+            accumulated.end = frame.end;
+
+            values[*index] = agg.finalize(&state);
+        }
+
+        Ok(())
+    }
+
+    /// Positions of the tuples in the frame of the current tuple, within the
+    /// sorted order keys of the partition.
+    fn frame_range(
+        frame: &WindowFrame,
+        current: usize,
+        keys: &[&[Datum]],
+        orders: &[SortOrder],
+    ) -> Range<usize> {
+        let len = keys.len();
+        let peers_start =
+            || keys.partition_point(|key| compare_sort_keys(key, keys[current], orders).is_lt());
+        let peers_end =
+            || keys.partition_point(|key| compare_sort_keys(key, keys[current], orders).is_le());
+        // The first key after the key of the current tuple shifted by the
+        // offset, or after the peers if the key is NULL
+        let shifted = |offset: &Datum, forward: bool, inclusive: bool| {
+            let key = &keys[current][0];
+            let order = orders[0];
+            let target = match (key, offset) {
+                (Datum::Int(key), Datum::Int(offset)) if forward == order.asc => {
+                    Datum::Int(key.saturating_add(*offset))
+                }
+                (Datum::Int(key), Datum::Int(offset)) => Datum::Int(key.saturating_sub(*offset)),
+                (Datum::Float(key), Datum::Float(offset)) if forward == order.asc => {
+                    Datum::Float(key + offset)
+                }
+                (Datum::Float(key), Datum::Float(offset)) => Datum::Float(key - offset),
+                _ if inclusive => return peers_end(),
+                _ => return peers_start(),
+            };
+            keys.partition_point(|key| match order.compare(&key[0], &target) {
+                Ordering::Less => true,
+                Ordering::Equal => inclusive,
+                Ordering::Greater => false,
+            })
+        };
+
+        let start = match (&frame.start, frame.units) {
+            (WindowFrameBound::UnboundedPreceding, _) => 0,
+            (WindowFrameBound::CurrentRow, WindowFrameUnits::Rows) => current,
+            (WindowFrameBound::CurrentRow, WindowFrameUnits::Range) => peers_start(),
+            (WindowFrameBound::Preceding(Datum::Int(offset)), WindowFrameUnits::Rows) => {
+                current.saturating_sub(*offset as usize)
+            }
+            (WindowFrameBound::Following(Datum::Int(offset)), WindowFrameUnits::Rows) => {
+                current.saturating_add(*offset as usize).min(len)
+            }
+            (WindowFrameBound::Preceding(offset), _) => shifted(offset, false, false),
+            (WindowFrameBound::Following(offset), _) => shifted(offset, true, false),
+            (WindowFrameBound::UnboundedFollowing, _) => len,
+        };
+        let end = match (&frame.end, frame.units) {
+            (WindowFrameBound::UnboundedFollowing, _) => len,
+            (WindowFrameBound::CurrentRow, WindowFrameUnits::Rows) => current + 1,
+            (WindowFrameBound::CurrentRow, WindowFrameUnits::Range) => peers_end(),
+            (WindowFrameBound::Preceding(Datum::Int(offset)), WindowFrameUnits::Rows) => {
+                (current + 1).saturating_sub(*offset as usize)
+            }
+            (WindowFrameBound::Following(Datum::Int(offset)), WindowFrameUnits::Rows) => {
+                current.saturating_add(*offset as usize + 1).min(len)
+            }
+            (WindowFrameBound::Preceding(offset), _) => shifted(offset, false, true),
+            (WindowFrameBound::Following(offset), _) => shifted(offset, true, true),
+            (WindowFrameBound::UnboundedPreceding, _) => 0,
+        };
+
+        // The frame is empty if its end precedes its start
+        start..end.max(start)
+    }
+}
+
+/// Tuples buffered with their sort keys, which breaks the pipeline of input.
+struct SortBuffer {
+    order_by: Vec<(Expression, SortOrder)>,
+    tuples: Vec<(Vec<Datum>, Tuple)>,
+}
+
+impl Sink for SortBuffer {
+    fn consume(&mut self, chunk: Chunk) -> Result<(), SQLError> {
+        for tuple in chunk {
+            let keys = self
+                .order_by
+                .iter()
+                .map(|(expr, _)| expr.eval(&tuple))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.tuples.push((keys, tuple));
+        }
+
+        Ok(())
+    }
+}
+
+/// Sort executor, the input is drained at the first run, then the tuples are
+/// emitted in the order of the keys. Tuples of equal keys keep their input order.
+pub struct SortExecutor {
+    pub input: Pipeline,
+
+    buffer: SortBuffer,
+    result_tuples: Option<VecDeque<Tuple>>,
+}
+
+impl SortExecutor {
+    pub fn new(input: Pipeline, order_by: Vec<(Expression, SortOrder)>) -> Self {
+        Self {
+            input,
+            buffer: SortBuffer {
+                order_by,
+                tuples: vec![],
+            },
+            result_tuples: None,
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        if self.result_tuples.is_none() {
+            self.input.run(ctx, &mut self.buffer)?;
+
+            let orders = self
+                .buffer
+                .order_by
+                .iter()
+                .map(|(_, order)| *order)
+                .collect::<Vec<_>>();
+            let mut tuples = std::mem::take(&mut self.buffer.tuples);
+            tuples.sort_by(|(left, _), (right, _)| compare_sort_keys(left, right, &orders));
+            self.result_tuples = Some(tuples.into_iter().map(|(_, tuple)| tuple).collect());
+        }
+
+        Ok(self.result_tuples.as_mut().unwrap().pop_front())
+    }
+}
+
+/// Hash-based de-duplication executor.
+/// Tuples are emitted in the order they are first seen.
+pub struct DistinctExecutor {
+    pub child: Box<Executor>,
+    seen: HashSet<Vec<Datum>>,
+}
+
+impl DistinctExecutor {
+    pub fn new(child: Box<Executor>) -> Self {
+        Self {
+            child,
+            seen: HashSet::new(),
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        while let Some(tuple) = self.child.next(ctx)? {
+            if self.seen.insert(tuple.values.clone()) {
+                return Ok(Some(tuple));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Concatenation of the inputs, all the tuples of the left input are emitted
+/// before the right one is pulled.
+pub struct UnionAllExecutor {
+    pub left: Box<Executor>,
+    pub right: Box<Executor>,
+    left_exhausted: bool,
+}
+
+impl UnionAllExecutor {
+    pub fn new(left: Box<Executor>, right: Box<Executor>) -> Self {
+        Self {
+            left,
+            right,
+            left_exhausted: false,
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        if !self.left_exhausted {
+            match self.left.next(ctx)? {
+                Some(tuple) => return Ok(Some(tuple)),
+                None => self.left_exhausted = true,
+            }
+        }
+        self.right.next(ctx)
+    }
+}
+
+/// Skips the first `offset` tuples of the child, then emits at most `limit`
+/// tuples. The counts are evaluated at the first pull, and the child is not
+/// pulled any more once the limit is reached.
+pub struct LimitExecutor {
+    pub child: Box<Executor>,
+    limit: Option<Expression>,
+    offset: Option<Expression>,
+    /// Number of the tuples left to emit, `None` before the counts are
+    /// evaluated, and `u64::MAX` if there is no limit.
+    remaining: Option<u64>,
+}
+
+impl LimitExecutor {
+    pub fn new(
+        child: Box<Executor>,
+        limit: Option<Expression>,
+        offset: Option<Expression>,
+    ) -> Self {
+        Self {
+            child,
+            limit,
+            offset,
+            remaining: None,
+        }
+    }
+
+    pub fn next(&mut self, ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining,
+            None => {
+                let limit = Self::count(self.limit.as_ref(), "LIMIT")?.unwrap_or(u64::MAX);
+                let offset = Self::count(self.offset.as_ref(), "OFFSET")?.unwrap_or(0);
+                self.remaining = Some(limit);
+                if limit > 0 {
+                    for _ in 0..offset {
+                        if self.child.next(ctx)?.is_none() {
+                            break;
+                        }
+                    }
+                }
+                limit
+            }
+        };
+        if remaining == 0 {
+            return Ok(None);
+        }
+
+        let tuple = self.child.next(ctx)?;
+        self.remaining = Some(if tuple.is_some() { remaining - 1 } else { 0 });
+        Ok(tuple)
+    }
+
+    /// Evaluate the count, `None` if it's NULL, which means no limit or offset.
+    fn count(expr: Option<&Expression>, clause: &str) -> Result<Option<u64>, SQLError> {
+        let Some(expr) = expr else {
+            return Ok(None);
+        };
+        match expr.eval(&Tuple::default())? {
+            Datum::Int(count) if count >= 0 => Ok(Some(count as u64)),
+            Datum::Int(_) => Err(SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("{} must not be negative", clause),
+            )
+            .with_code(if clause == "LIMIT" {
+                sqlstate::INVALID_ROW_COUNT_IN_LIMIT_CLAUSE
+            } else {
+                sqlstate::INVALID_ROW_COUNT_IN_RESULT_OFFSET_CLAUSE
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+pub struct DDLExecutor {
+    pub job: DDLJob,
+    pub result_buffer: VecDeque<Tuple>,
+}
+
+impl DDLExecutor {
+    pub fn new(job: DDLJob) -> Self {
+        Self {
+            job,
+            result_buffer: VecDeque::new(),
+        }
+    }
+
+    pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        match &self.job {
+            DDLJob::CreateDatabase(database_name, if_not_exists) => {
+                if *if_not_exists && ctx.catalog().exists_database(database_name) {
+                    return Ok(());
+                }
+                Self::apply_changes(ctx, vec![LogRecord::CreateDatabase(database_name.clone())])?;
+            }
+            DDLJob::DropDatabase(database_name, if_exists) => {
+                // Sessions connect under the catalog lock, so no one can connect
+                // to the database between the check and the drop
+                let mut catalog = ctx.catalog_mut();
+                if *if_exists && !catalog.exists_database(database_name) {
+                    return Ok(());
+                }
+                Self::check_drop_database(ctx, database_name)?;
+                Self::apply_changes_locked(
+                    ctx,
+                    &mut catalog,
+                    vec![LogRecord::DropDatabase(database_name.clone())],
+                )?;
+            }
+            DDLJob::CreateSchema(schema_name, if_not_exists) => {
+                if *if_not_exists
+                    && ctx
+                        .catalog()
+                        .exists_schema(&ctx.current_database, schema_name)?
+                {
+                    return Ok(());
+                }
+                Self::apply_changes(
+                    ctx,
+                    vec![LogRecord::CreateSchema(
+                        ctx.current_database.clone(),
+                        schema_name.clone(),
+                    )],
+                )?;
+            }
+            DDLJob::DropSchemas(names, if_exists) => {
+                let records = {
+                    let catalog = ctx.catalog();
+                    names
+                        .iter()
+                        .filter(|name| {
+                            !*if_exists
+                                || catalog
+                                    .exists_schema(&ctx.current_database, name)
+                                    .unwrap_or(false)
+                        })
+                        .map(|name| {
+                            LogRecord::DropSchema(ctx.current_database.clone(), name.clone())
+                        })
+                        .collect()
+                };
+                Self::apply_changes(ctx, records)?;
+            }
+            DDLJob::CreateTable(schema_name, table_def, sequence_defs, if_not_exists) => {
+                if *if_not_exists
+                    && ctx.catalog().exists_relation(
+                        &ctx.current_database,
+                        schema_name,
+                        &table_def.name,
+                    )?
+                {
+                    return Ok(());
+                }
+                let records = sequence_defs
+                    .iter()
+                    .map(|sequence_def| {
+                        LogRecord::CreateSequence(
+                            ctx.current_database.clone(),
+                            schema_name.clone(),
+                            sequence_def.clone(),
+                        )
+                    })
+                    .chain([LogRecord::CreateTable(
+                        ctx.current_database.clone(),
+                        schema_name.clone(),
+                        table_def.clone(),
+                    )])
+                    .collect();
+                Self::apply_changes(ctx, records)?;
+            }
+            DDLJob::DropTables(names, if_exists) => {
+                let records = {
+                    let catalog = ctx.catalog();
+                    names
+                        .iter()
+                        .filter(|(schema_name, table_name)| {
+                            !*if_exists
+                                || matches!(
+                                    catalog.find_table_by_name(
+                                        &ctx.current_database,
+                                        schema_name,
+                                        table_name
+                                    ),
+                                    Ok(Some(_))
+                                )
+                        })
+                        .flat_map(|(schema_name, table_name)| {
+                            let sequences = catalog
+                                .owned_sequences(&ctx.current_database, schema_name, table_name)
+                                .into_iter()
+                                .map(|sequence| {
+                                    LogRecord::DropSequence(
+                                        ctx.current_database.clone(),
+                                        schema_name.clone(),
+                                        sequence.name,
+                                    )
+                                });
+                            std::iter::once(LogRecord::DropTable(
+                                ctx.current_database.clone(),
+                                schema_name.clone(),
+                                table_name.clone(),
+                            ))
+                            .chain(sequences)
+                        })
+                        .collect()
+                };
+                Self::apply_changes(ctx, records)?;
+            }
+            DDLJob::CreateSequence(schema_name, sequence_def, if_not_exists) => {
+                if *if_not_exists
+                    && ctx.catalog().exists_relation(
+                        &ctx.current_database,
+                        schema_name,
+                        &sequence_def.name,
+                    )?
+                {
+                    return Ok(());
+                }
+                Self::apply_changes(
+                    ctx,
+                    vec![LogRecord::CreateSequence(
+                        ctx.current_database.clone(),
+                        schema_name.clone(),
+                        sequence_def.clone(),
+                    )],
+                )?;
+            }
+            DDLJob::DropSequences(names, if_exists) => {
+                let records = {
+                    let catalog = ctx.catalog();
+                    names
+                        .iter()
+                        .filter(|(schema_name, sequence_name)| {
+                            !*if_exists
+                                || matches!(
+                                    catalog.find_sequence_by_name(
+                                        &ctx.current_database,
+                                        schema_name,
+                                        sequence_name
+                                    ),
+                                    Ok(Some(_))
+                                )
+                        })
+                        .map(|(schema_name, sequence_name)| {
+                            LogRecord::DropSequence(
+                                ctx.current_database.clone(),
+                                schema_name.clone(),
+                                sequence_name.clone(),
+                            )
+                        })
+                        .collect()
+                };
+                Self::apply_changes(ctx, records)?;
+            }
+            DDLJob::CreateIndex(schema_name, table_name, index_def, if_not_exists) => {
+                let mut catalog = ctx.catalog_mut();
+                if *if_not_exists
+                    && catalog.exists_relation(
+                        &ctx.current_database,
+                        schema_name,
+                        &index_def.name,
+                    )?
+                {
+                    return Ok(());
+                }
+                let mut new_catalog = catalog.clone();
+                let record = LogRecord::CreateIndex(
+                    ctx.current_database.clone(),
+                    schema_name.clone(),
+                    table_name.clone(),
+                    index_def.clone(),
+                );
+                record.redo_catalog(&mut new_catalog)?;
+
+                let table = ctx
+                    .storage_mgr
+                    .get_relation(&ctx.current_database, schema_name, table_name)
+                    .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))?;
+                // Hold the lock until the index is built, so the checked keys won't change
+                let mut table = table.write().unwrap();
+                if index_def.unique {
+                    if let Some(key) = table.find_duplicate(index_def) {
+                        let table_def = catalog
+                            .find_table_by_name(&ctx.current_database, schema_name, table_name)?
+                            .unwrap();
+                        return Err(SQLError::new(
+                            ErrorKind::RuntimeError,
+                            format!("could not create unique index \"{}\"", index_def.name),
+                        )
+                        .with_code(sqlstate::UNIQUE_VIOLATION)
+                        .with_detail(format!(
+                            "Key {} is duplicated.",
+                            format_key(&table_def, &index_def.columns, &key)
+                        )));
+                    }
+                }
+
+                ctx.storage_mgr.log(std::slice::from_ref(&record))?;
+                table.create_index(index_def.clone());
+                *catalog = new_catalog;
+            }
+            DDLJob::AlterTable(schema_name, table_name, job) => {
+                Self::alter_table(ctx, schema_name, table_name, job)?;
+            }
+            DDLJob::DropIndexes(names) => {
+                Self::apply_changes(
+                    ctx,
+                    names
+                        .iter()
+                        .map(|(schema_name, table_name, index_name)| {
+                            LogRecord::DropIndex(
+                                ctx.current_database.clone(),
+                                schema_name.clone(),
+                                table_name.clone(),
+                                index_name.clone(),
+                            )
+                        })
+                        .collect(),
+                )?;
+            }
+            DDLJob::CreateFunction(schema_name, function_def, or_replace) => {
+                if !*or_replace
+                    && ctx
+                        .catalog()
+                        .find_function_by_name(
+                            &ctx.current_database,
+                            schema_name,
+                            &function_def.name,
+                        )
+                        .is_some()
+                {
+                    return Err(SQLError::new(
+                        ErrorKind::CatalogError,
+                        format!("function \"{}\" already exists", function_def.name),
+                    )
+                    .with_code(sqlstate::DUPLICATE_FUNCTION));
+                }
+                Self::apply_changes(
+                    ctx,
+                    vec![LogRecord::CreateFunction(
+                        ctx.current_database.clone(),
+                        schema_name.clone(),
+                        function_def.clone(),
+                    )],
+                )?;
+            }
+            DDLJob::DropFunctions(names) => {
+                Self::apply_changes(
+                    ctx,
+                    names
+                        .iter()
+                        .map(|(schema_name, function_name)| {
+                            LogRecord::DropFunction(
+                                ctx.current_database.clone(),
+                                schema_name.clone(),
+                                function_name.clone(),
+                            )
+                        })
+                        .collect(),
+                )?;
+            }
+            DDLJob::Analyze(names) => {
+                let mut records = vec![];
+                for (schema_name, table_name) in names.iter() {
+                    // The table may be dropped concurrently after binding
+                    let Some(table_def) = ctx.catalog().find_table_by_name(&ctx.current_database, schema_name, table_name)? else {
+                        continue;
+                    };
+                    let Some(table) = ctx.storage_mgr.get_relation(&ctx.current_database, schema_name, table_name) else {
+                        continue;
+                    };
+                    let statistics = TableStatistics::collect(
+                        table_def.columns.len(),
+                        table.read().unwrap().tuples().map(|(_, tuple)| tuple),
+                    );
+                    records.push(LogRecord::SetStatistics(
+                        (
+                            ctx.current_database.clone(),
+                            schema_name.clone(),
+                            table_name.clone(),
+                        ),
+                        statistics,
+                    ));
+                }
+                Self::apply_changes(ctx, records)?;
+            }
+            DDLJob::Vacuum(names) => {
+                let mut catalog = ctx.catalog_mut();
+                for (schema_name, table_name) in names.iter() {
+                    ctx.storage_mgr.vacuum(
+                        &mut catalog,
+                        &ctx.current_database,
+                        schema_name,
+                        table_name,
+                    )?;
+                }
+                ctx.storage_mgr.checkpoint(&catalog)?;
+            }
+            DDLJob::ShowTables(schema_name) => {
+                let tables = ctx
+                    .catalog()
+                    .list_tables(&ctx.current_database, schema_name)?;
+                self.result_buffer.extend(tables.iter().map(|table| {
+                    let mut tuple = Tuple::default();
+                    tuple.append(Datum::String(table.clone().into()));
+                    tuple
+                }));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        Ok(self.result_buffer.pop_front())
+    }
+
+    /// Apply the catalog changes atomically. The changes are validated with a copy of
+    /// catalog first, since they must be logged before being applied.
+    fn apply_changes(ctx: &QueryContext, records: Vec<LogRecord>) -> Result<(), SQLError> {
+        Self::apply_changes_locked(ctx, &mut ctx.catalog_mut(), records)
+    }
+
+    /// Apply the catalog changes with the catalog locked by caller.
+    fn apply_changes_locked(
+        ctx: &QueryContext,
+        catalog: &mut Catalog,
+        records: Vec<LogRecord>,
+    ) -> Result<(), SQLError> {
+        let mut new_catalog = catalog.clone();
+        for record in records.iter() {
+            record.redo_catalog(&mut new_catalog)?;
+        }
+
+        let lsn = ctx.storage_mgr.log(&records)?;
+        for record in records.iter() {
+            record.redo_storage(lsn, &ctx.storage_mgr)?;
+        }
+        *catalog = new_catalog;
+
+        Ok(())
+    }
+
+    /// The databases connected by any session can't be dropped, nor can the
+    /// default one, which the new sessions connect to.
+    fn check_drop_database(ctx: &QueryContext, database_name: &str) -> Result<(), SQLError> {
+        if database_name == ctx.current_database {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                "cannot drop the currently open database",
+            )
+            .with_code(sqlstate::OBJECT_IN_USE));
+        }
+        if database_name == DEFAULT_DATABASE {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!("cannot drop the default database \"{}\"", database_name),
+            )
+            .with_code(sqlstate::OBJECT_IN_USE));
+        }
+        let sessions = ctx
+            .activity
+            .list()
+            .into_iter()
+            .filter(|activity| activity.database == database_name)
+            .count();
+        if sessions > 0 {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!(
+                    "database \"{}\" is being accessed by other users",
+                    database_name
+                ),
+            )
+            .with_code(sqlstate::OBJECT_IN_USE)
+            .with_detail(format!(
+                "There are {} other sessions using the database.",
+                sessions
+            )));
+        }
+        Ok(())
+    }
+
+    /// Alter the columns of table. The tables whose definitions are changed are locked
+    /// while holding the catalog, so the tuples are rewritten in the new layout.
+    fn alter_table(
+        ctx: &QueryContext,
+        schema_name: &str,
+        table_name: &str,
+        job: &AlterTableJob,
+    ) -> Result<(), SQLError> {
+        // Sequences are resolved with catalog, so the default or USING expression is type
+        // checked before holding it
+        let scalar = match job {
+            AlterTableJob::AddColumn(_, Some(scalar), _) => {
+                let schema = SessionSchema {
+                    schema: &Schema::default(),
+                    ctx,
+                    outer: &[],
+                };
+                Some(type_check(&schema, scalar)?)
+            }
+            AlterTableJob::AlterColumnType(.., Some(scalar)) => {
+                let table_def = ctx
+                    .catalog()
+                    .find_table_by_name(&ctx.current_database, schema_name, table_name)?
+                    .ok_or_else(|| {
+                        SQLError::new(ErrorKind::CatalogError, "table not found")
+                            .with_code(sqlstate::UNDEFINED_TABLE)
+                    })?;
+                let schema = SessionSchema {
+                    schema: &Schema::from(&table_def),
+                    ctx,
+                    outer: &[],
+                };
+                Some(type_check(&schema, scalar)?)
+            }
+            _ => None,
+        };
+
+        let mut catalog = ctx.catalog_mut();
+        let name = (schema_name.to_string(), table_name.to_string());
+        let table_def = catalog
+            .find_table_by_name(&ctx.current_database, schema_name, table_name)?
+            .ok_or_else(|| {
+                SQLError::new(ErrorKind::CatalogError, "table not found")
+                    .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        let mut new_defs = BTreeMap::new();
+        let mut new_def = table_def.clone();
+        // Changes applied after the definitions are replaced
+        let mut records = vec![];
+
+        match job {
+            AlterTableJob::AddColumn(column, ..) => new_def.columns.push(column.clone()),
+            AlterTableJob::DropColumn(index) => {
+                let column_name = &table_def.columns[*index].name;
+                let referencing =
+                    catalog.referencing_tables(&ctx.current_database, schema_name, table_name);
+                let shift = |columns: &mut Vec<usize>| {
+                    for column in columns.iter_mut().filter(|column| **column > *index) {
+                        *column -= 1;
+                    }
+                };
+
+                for (child_schema, mut child_def) in referencing {
+                    if (&child_schema, &child_def.name) == (&name.0, &name.1) {
+                        continue;
+                    }
+                    for foreign_key in child_def.foreign_keys.iter_mut() {
+                        if !foreign_key.refers_to(schema_name, table_name) {
+                            continue;
+                        }
+                        if foreign_key.referred_columns.contains(index) {
+                            return Err(SQLError::new(
+                                ErrorKind::CatalogError,
+                                format!(
+                                    "cannot drop column {} of table {} because other objects depend on it",
+                                    column_name, table_name
+                                ),
+                            )
+                            .with_code(sqlstate::DEPENDENT_OBJECTS_STILL_EXIST)
+                            .with_detail(format!(
+                                "constraint {} on table {} depends on column {} of table {}",
+                                foreign_key.name, child_def.name, column_name, table_name
+                            )));
+                        }
+                        shift(&mut foreign_key.referred_columns);
+                    }
+                    new_defs.insert((child_schema, child_def.name.clone()), child_def);
+                }
+
+                // Indexes and constraints on the column are dropped with it
+                new_def.columns.remove(*index);
+                new_def
+                    .indexes
+                    .retain(|index_def| !index_def.columns.contains(index));
+                if !new_def
+                    .indexes
+                    .iter()
+                    .any(|index_def| Some(&index_def.name) == table_def.primary_key.as_ref())
+                {
+                    new_def.primary_key = None;
+                }
+                for index_def in new_def.indexes.iter_mut() {
+                    shift(&mut index_def.columns);
+                }
+                new_def.foreign_keys.retain(|foreign_key| {
+                    let dropped = foreign_key.columns.contains(index)
+                        || foreign_key.refers_to(schema_name, table_name)
+                            && foreign_key.referred_columns.contains(index);
+                    !dropped
+                });
+                for foreign_key in new_def.foreign_keys.iter_mut() {
+                    shift(&mut foreign_key.columns);
+                    if foreign_key.refers_to(schema_name, table_name) {
+                        shift(&mut foreign_key.referred_columns);
+                    }
+                }
+
+                records.extend(
+                    catalog
+                        .owned_sequences(&ctx.current_database, schema_name, table_name)
+                        .into_iter()
+                        .filter(|sequence| {
+                            matches!(&sequence.owned_by, Some((_, column)) if column == column_name)
+                        })
+                        .map(|sequence| {
+                            LogRecord::DropSequence(ctx.current_database.clone(), schema_name.to_string(), sequence.name)
+                        }),
+                );
+            }
+            AlterTableJob::RenameColumn(index, new_name) => {
+                let column_name = &table_def.columns[*index].name;
+                records.extend(
+                    catalog
+                        .owned_sequences(&ctx.current_database, schema_name, table_name)
+                        .into_iter()
+                        .filter(|sequence| {
+                            matches!(&sequence.owned_by, Some((_, column)) if column == column_name)
+                        })
+                        .map(|sequence| {
+                            LogRecord::SetSequenceOwner(
+                                (
+                                    ctx.current_database.clone(),
+                                    schema_name.to_string(),
+                                    sequence.name,
+                                ),
+                                Some((table_name.to_string(), new_name.clone())),
+                            )
+                        }),
+                );
+                new_def.columns[*index].name = new_name.clone();
+            }
+            AlterTableJob::AlterColumnType(index, typ, int_width, _) => {
+                new_def.columns[*index].data_type = typ.clone();
+                new_def.columns[*index].int_width = *int_width;
+
+                // Key columns of foreign keys must be still of the same types
+                for foreign_key in new_def.foreign_keys.iter() {
+                    let referred_def = if foreign_key.refers_to(schema_name, table_name) {
+                        new_def.clone()
+                    } else {
+                        catalog
+                            .find_table_by_name(
+                                &ctx.current_database,
+                                &foreign_key.referred_schema,
+                                &foreign_key.referred_table,
+                            )?
+                            .ok_or_else(|| {
+                                SQLError::new(ErrorKind::CatalogError, "table not found")
+                                    .with_code(sqlstate::UNDEFINED_TABLE)
+                            })?
+                    };
+                    check_foreign_key_types(foreign_key, &new_def, &referred_def)?;
+                }
+                for (child_schema, child_def) in
+                    catalog.referencing_tables(&ctx.current_database, schema_name, table_name)
+                {
+                    if (&child_schema, &child_def.name) == (&name.0, &name.1) {
+                        continue;
+                    }
+                    for foreign_key in child_def.foreign_keys.iter() {
+                        if foreign_key.refers_to(schema_name, table_name) {
+                            check_foreign_key_types(foreign_key, &child_def, &new_def)?;
+                        }
+                    }
+                }
+            }
+        }
+        new_defs.insert(name.clone(), new_def);
+
+        let relations = new_defs
+            .keys()
+            .map(|name| {
+                let relation = ctx
+                    .storage_mgr
+                    .get_relation(&ctx.current_database, &name.0, &name.1)
+                    .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))?;
+                Ok((name.clone(), relation))
+            })
+            .collect::<Result<BTreeMap<_, _>, SQLError>>()?;
+        let mut relations = lock_relations(&relations);
+
+        // The tuples are rewritten before the definitions are replaced
+        let mut rewrite = vec![];
+        match job {
+            AlterTableJob::AddColumn(column, _, sequence_def) => {
+                let sequence = sequence_def.clone().map(Sequence::new);
+                let mut last_value = None;
+                let mut tuples = vec![];
+                for (_, mut tuple) in relations[&name].tuples() {
+                    let value = if let Some(sequence) = &sequence {
+                        let value = sequence.next(|_| Ok(()))?;
+                        last_value = Some(value);
+                        Datum::Int(value)
+                    } else if let Some(default) = &scalar {
+                        default.eval(&Tuple::default())?
+                    } else {
+                        Datum::Null
+                    };
+                    let value = value.cast(&column.data_type);
+                    if let Some(int_width) = column.int_width {
+                        int_width.check(&value)?;
+                    }
+                    if value.is_null() && !column.null {
+                        return Err(SQLError::new(
+                            ErrorKind::RuntimeError,
+                            format!(
+                                "column \"{}\" of relation \"{}\" contains null values",
+                                column.name, table_name
+                            ),
+                        )
+                        .with_code(sqlstate::NOT_NULL_VIOLATION));
+                    }
+                    tuple.append(value);
+                    tuples.push(tuple);
+                }
+
+                if let Some(sequence_def) = sequence_def {
+                    rewrite.push(LogRecord::CreateSequence(
+                        ctx.current_database.clone(),
+                        schema_name.to_string(),
+                        sequence_def.clone(),
+                    ));
+                    if let Some(value) = last_value {
+                        rewrite.push(LogRecord::SetSequence(
+                            (
+                                ctx.current_database.clone(),
+                                schema_name.to_string(),
+                                sequence_def.name.clone(),
+                            ),
+                            value,
+                        ));
+                    }
+                }
+                rewrite.push(rewrite_table(&ctx.current_database, &name, tuples)?);
+            }
+            AlterTableJob::DropColumn(index) => {
+                let tuples = relations[&name]
+                    .tuples()
+                    .map(|(_, mut tuple)| {
+                        tuple.values.remove(*index);
+                        tuple
+                    })
+                    .collect();
+                rewrite.push(rewrite_table(&ctx.current_database, &name, tuples)?);
+            }
+            AlterTableJob::AlterColumnType(index, typ, int_width, _) => {
+                let column_name = &table_def.columns[*index].name;
+                let tuples = relations[&name]
+                    .tuples()
+                    .map(|(_, mut tuple)| {
+                        let value = &match &scalar {
+                            Some(using) => using.eval(&tuple)?,
+                            None => tuple.values[*index].clone(),
+                        };
+                        let new_value = value.cast(typ);
+                        // The value is cast to NULL if it cannot be converted
+                        if new_value.is_null() && !value.is_null() {
+                            let code = if let Datum::String(_) = value {
+                                sqlstate::INVALID_TEXT_REPRESENTATION
+                            } else {
+                                sqlstate::DATATYPE_MISMATCH
+                            };
+                            return Err(SQLError::new(
+                                ErrorKind::RuntimeError,
+                                format!(
+                                    "value \"{}\" of column \"{}\" cannot be cast to the new type",
+                                    value, column_name
+                                ),
+                            )
+                            .with_code(code));
+                        }
+                        if let Some(int_width) = int_width {
+                            int_width.check(&new_value)?;
+                        }
+                        tuple.values[*index] = new_value;
+                        Ok(tuple)
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+                rewrite.push(rewrite_table(&ctx.current_database, &name, tuples)?);
+            }
+            AlterTableJob::RenameColumn(..) => {}
+        }
+
+        let records = rewrite
+            .into_iter()
+            .chain(new_defs.into_iter().map(|((schema_name, _), table_def)| {
+                LogRecord::AlterTable(ctx.current_database.clone(), schema_name, table_def)
+            }))
+            .chain(records)
+            .collect::<Vec<_>>();
+        let mut new_catalog = catalog.clone();
+        for record in records.iter() {
+            record.redo_catalog(&mut new_catalog)?;
+        }
+
+        let lsn = ctx.storage_mgr.log(&records)?;
+        for record in records.iter() {
+            match record {
+                LogRecord::AlterTable(_, schema_name, TableDefinition { name, .. })
+                | LogRecord::RewriteTable((_, schema_name, name), _) => {
+                    let relation = relations
+                        .get_mut(&(schema_name.clone(), name.clone()))
+                        .unwrap();
+                    record.redo_alter(lsn, relation)?;
+                }
+                _ => record.redo_storage(lsn, &ctx.storage_mgr)?,
+            }
+        }
+        *catalog = new_catalog;
+
+        Ok(())
+    }
+}
+
+/// Fail if the key columns of foreign key are of different types.
+fn check_foreign_key_types(
+    foreign_key: &ForeignKeyDefinition,
+    table_def: &TableDefinition,
+    referred_def: &TableDefinition,
+) -> Result<(), SQLError> {
+    for (column, referred_column) in foreign_key
+        .columns
+        .iter()
+        .zip(foreign_key.referred_columns.iter())
+    {
+        let column = &table_def.columns[*column];
+        let referred_column = &referred_def.columns[*referred_column];
+        if column.data_type != referred_column.data_type {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!(
+                    "foreign key constraint \"{}\" cannot be implemented",
+                    foreign_key.name
+                ),
+            )
+            .with_code(sqlstate::DATATYPE_MISMATCH)
+            .with_detail(format!(
+                "Key columns \"{}\" and \"{}\" are of incompatible types.",
+                column.name, referred_column.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Record replacing the tuples of table, they are located in the same way as
+/// inserted into an empty heap.
+fn rewrite_table(
+    database_name: &str,
+    (schema_name, table_name): &(String, String),
+    tuples: Vec<Tuple>,
+) -> Result<LogRecord, SQLError> {
+    let tids = HeapTable::new().insert(&tuples)?;
+    Ok(LogRecord::RewriteTable(
+        (
+            database_name.to_string(),
+            schema_name.clone(),
+            table_name.clone(),
+        ),
+        tids.into_iter().zip(tuples).collect(),
+    ))
+}
+
+/// Account for the logged DML changes in the statistics of tables. The
+/// catalog is locked after the relations are unlocked, since DDL locks them
+/// in the opposite order.
+fn update_statistics(ctx: &QueryContext, records: &[LogRecord]) -> Result<(), SQLError> {
+    let mut catalog = ctx.catalog_mut();
+    for record in records.iter() {
+        record.redo_catalog(&mut catalog)?;
+    }
+    Ok(())
+}
+
+/// Number of tuples inserted together by `CREATE TABLE ... AS`.
+const INSERT_BATCH_SIZE: usize = 1024;
+
+/// Create the table and insert the results of query into it, the table is
+/// dropped if the query fails.
+pub struct CreateTableAsExecutor {
+    pub schema_name: String,
+    pub table_def: TableDefinition,
+    pub if_not_exists: bool,
+    pub child: Box<Executor>,
+    /// Number of the inserted rows, returned once the table is created.
+    pub inserted: Option<usize>,
+}
+
+impl CreateTableAsExecutor {
+    pub fn new(
+        schema_name: String,
+        table_def: TableDefinition,
+        if_not_exists: bool,
+        child: Box<Executor>,
+    ) -> Self {
+        Self {
+            schema_name,
+            table_def,
+            if_not_exists,
+            child,
+            inserted: None,
+        }
+    }
+
+    pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        if self.if_not_exists
+            && ctx.catalog().exists_relation(
+                &ctx.current_database,
+                &self.schema_name,
+                &self.table_def.name,
+            )?
+        {
+            return Ok(());
+        }
+
+        DDLExecutor::apply_changes(
+            ctx,
+            vec![LogRecord::CreateTable(
+                ctx.current_database.clone(),
+                self.schema_name.clone(),
+                self.table_def.clone(),
+            )],
+        )?;
+
+        match self.insert_results(ctx) {
+            Ok(inserted) => self.inserted = Some(inserted),
+            Err(e) => {
+                DDLExecutor::apply_changes(
+                    ctx,
+                    vec![LogRecord::DropTable(
+                        ctx.current_database.clone(),
+                        self.schema_name.clone(),
+                        self.table_def.name.clone(),
+                    )],
+                )?;
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn insert_results(&mut self, ctx: &mut QueryContext) -> Result<usize, SQLError> {
+        let name = (self.schema_name.clone(), self.table_def.name.clone());
+        self.child.open(ctx)?;
+
+        let mut inserted = 0;
+        let mut finished = false;
+        while !finished {
+            let mut tuples = Vec::with_capacity(INSERT_BATCH_SIZE);
+            while tuples.len() < INSERT_BATCH_SIZE {
+                match self.child.next(ctx)? {
+                    Some(tuple) => tuples.push(tuple),
+                    None => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+
+            inserted += tuples.len();
+            if !tuples.is_empty() {
+                let foreign_keys = ForeignKeys::collect(
+                    &ctx.catalog(),
+                    &ctx.current_database,
+                    &name,
+                    true,
+                    false,
+                )?;
+                DMLExecutor::insert_tuples(ctx, &foreign_keys, &name, tuples)?;
+            }
+        }
+
+        Ok(inserted)
+    }
+
+    pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        Ok(self
+            .inserted
+            .take()
+            .map(|inserted| Tuple::new(vec![Datum::Int(inserted as i64)])))
+    }
+}
+
+/// Plan shown by `EXPLAIN`, rendered by the builder.
+pub enum ExplainedPlan {
+    /// Lines of the text format.
+    Text(Vec<String>),
+    /// The plan tree of the JSON format.
+    Json(Json),
+}
+
+impl ExplainedPlan {
+    /// Rows of the result, with the rows returned by the statement and the
+    /// time of execution in milliseconds if it's executed.
+    fn into_lines(self, analyzed: Option<(usize, f64)>) -> Vec<String> {
+        match self {
+            ExplainedPlan::Text(mut lines) => {
+                if let Some((rows, millis)) = analyzed {
+                    lines.push(format!("Actual Rows: {}", rows));
+                    lines.push(format!("Execution Time: {:.3} ms", millis));
+                }
+                lines
+            }
+            ExplainedPlan::Json(plan) => {
+                let mut fields = vec![("Plan".to_string(), plan)];
+                if let Some((rows, millis)) = analyzed {
+                    fields.push(("Actual Rows".to_string(), Json::Number(rows.to_string())));
+                    fields.push((
+                        "Execution Time".to_string(),
+                        Json::Number(format!("{:.3}", millis)),
+                    ));
+                }
+                vec![Json::Array(vec![Json::Object(fields)]).to_string()]
+            }
+        }
+    }
+}
+
+/// Show the plan of `EXPLAIN`, one row per line. The statement is executed
+/// first with `ANALYZE`, and its results are discarded.
+pub struct ExplainExecutor {
+    plan: Option<ExplainedPlan>,
+    /// The statement, only with `ANALYZE`.
+    pub child: Option<Box<Executor>>,
+    lines: VecDeque<String>,
+}
+
+impl ExplainExecutor {
+    pub fn new(plan: ExplainedPlan, child: Option<Box<Executor>>) -> Self {
+        Self {
+            plan: Some(plan),
+            child,
+            lines: VecDeque::new(),
+        }
+    }
+
+    pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
+        let analyzed = match &mut self.child {
+            Some(child) => {
+                let start = Instant::now();
+                child.open(ctx)?;
+                let mut rows = 0;
+                while let Some(chunk) = child.next_chunk(ctx)? {
+                    rows += chunk.tuples.len();
+                }
+                Some((rows, start.elapsed().as_secs_f64() * 1000.0))
+            }
+            None => None,
+        };
+        if let Some(plan) = self.plan.take() {
+            self.lines = plan.into_lines(analyzed).into();
+        }
+
+        Ok(())
+    }
+
+    pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        Ok(self
+            .lines
+            .pop_front()
+            .map(|line| Tuple::new(vec![Datum::String(line.into())])))
+    }
+}
+
+pub struct DMLExecutor {
     pub job: DMLJob,
+    /// Expressions of `RETURNING`, the number of affected rows is returned
+    /// if not specified.
+    pub returning: Option<Vec<Expression>>,
     pub result_buffer: VecDeque<Tuple>,
 }
 
 impl DMLExecutor {
-    pub fn new(job: DMLJob) -> Self {
+    pub fn new(job: DMLJob, returning: Option<Vec<Expression>>) -> Self {
         Self {
             job,
+            returning,
             result_buffer: VecDeque::new(),
         }
     }
 
+    /// Buffer the results of the affected tuples.
+    fn buffer_results(&mut self, affected: Vec<Tuple>) -> Result<(), SQLError> {
+        match &self.returning {
+            Some(returning) => {
+                for tuple in affected.iter() {
+                    let values = returning
+                        .iter()
+                        .map(|expr| expr.eval(tuple))
+                        .collect::<Result<Vec<_>, SQLError>>()?;
+                    self.result_buffer.push_back(Tuple::new(values));
+                }
+            }
+            None => self
+                .result_buffer
+                .push_back(Tuple::new(vec![Datum::Int(affected.len() as i64)])),
+        }
+
+        Ok(())
+    }
+
+    /// Insert the tuples into the target table of `foreign_keys` after checking
+    /// the constraints.
+    fn insert_tuples(
+        ctx: &QueryContext,
+        foreign_keys: &ForeignKeys,
+        name: &TableName,
+        tuples: Vec<Tuple>,
+    ) -> Result<(), SQLError> {
+        let table_def = foreign_keys.table_def();
+
+        // Hold the locks until flushed, so the changes are logged in order
+        let relations = foreign_keys.relations(&ctx.storage_mgr)?;
+        let mut relations = lock_relations(&relations);
+        check_not_null(table_def, &tuples)?;
+        check_int_widths(table_def, &tuples)?;
+        if let Some((index_def, key)) = relations[name].find_conflict(&[], &tuples) {
+            return Err(unique_violation(table_def, index_def, &key));
+        }
+        foreign_keys.check_references(&relations, &HashSet::new(), &tuples)?;
+
+        let table = relations.get_mut(name).unwrap();
+        let tids = table.insert(&tuples)?;
+        let record = LogRecord::Insert(
+            (ctx.current_database.clone(), name.0.clone(), name.1.clone()),
+            tids.into_iter().zip(tuples).collect(),
+        );
+        let lsn = ctx.storage_mgr.log(std::slice::from_ref(&record))?;
+        table.flush(lsn)?;
+        drop(relations);
+
+        update_statistics(ctx, &[record])
+    }
+
     pub fn open(&mut self, ctx: &mut QueryContext) -> Result<(), SQLError> {
-        match &self.job {
+        let affected = match &self.job {
             DMLJob::Insert((schema_name, table_name), insert_data) => {
-                let table = ctx
+                let name = (schema_name.clone(), table_name.clone());
+                let foreign_keys = ForeignKeys::collect(
+                    &ctx.catalog(),
+                    &ctx.current_database,
+                    &name,
+                    true,
+                    false,
+                )?;
+                let table_def = foreign_keys.table_def();
+
+                // Insert values are constants, so they are evaluated with an empty tuple.
+                let tuples = insert_data
+                    .iter()
+                    .map(|row| {
+                        let values = row
+                            .iter()
+                            .zip(table_def.columns.iter())
+                            .map(|(scalar, column)| {
+                                let schema = SessionSchema {
+                                    schema: &Schema::default(),
+                                    ctx,
+                                    outer: &[],
+                                };
+                                let value = type_check(&schema, scalar)?.eval(&Tuple::default())?;
+                                cast_to_column(&value, column)
+                            })
+                            .collect::<Result<Vec<_>, SQLError>>()?;
+                        Ok(Tuple::new(values))
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                Self::insert_tuples(ctx, &foreign_keys, &name, tuples.clone())?;
+
+                tuples
+            }
+            DMLJob::Update((schema_name, table_name), assignments, predicate) => {
+                let name = (schema_name.clone(), table_name.clone());
+                let foreign_keys =
+                    ForeignKeys::collect(&ctx.catalog(), &ctx.current_database, &name, true, true)?;
+                let table_def = foreign_keys.table_def();
+                let schema = Schema::from(table_def);
+                let session_schema = SessionSchema {
+                    schema: &schema,
+                    ctx,
+                    outer: &[],
+                };
+
+                let assignments = assignments
+                    .iter()
+                    .map(|(index, scalar)| Ok((*index, type_check(&session_schema, scalar)?)))
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+                let predicate = predicate
+                    .as_ref()
+                    .map(|predicate| {
+                        coerce_to_boolean(type_check(&session_schema, predicate)?, "WHERE")
+                    })
+                    .transpose()?;
+
+                let relations = foreign_keys.relations(&ctx.storage_mgr)?;
+                let mut relations = lock_relations(&relations);
+
+                // (location, original tuple, new tuple)
+                let mut updated = vec![];
+                for (tid, tuple) in relations[&name].tuples() {
+                    if let Some(predicate) = &predicate {
+                        if !predicate.eval_predicate(&tuple)? {
+                            continue;
+                        }
+                    }
+
+                    // All the new values are evaluated against the original tuple.
+                    let mut new_tuple = tuple.clone();
+                    for (index, expr) in assignments.iter() {
+                        new_tuple.values[*index] =
+                            cast_to_column(&expr.eval(&tuple)?, &table_def.columns[*index])?;
+                    }
+
+                    updated.push((tid, tuple, new_tuple));
+                }
+
+                let old_tids = updated.iter().map(|(tid, ..)| *tid).collect::<Vec<_>>();
+                let tuples = updated
+                    .iter()
+                    .map(|(_, _, tuple)| tuple.clone())
+                    .collect::<Vec<_>>();
+                check_not_null(table_def, &tuples)?;
+                check_int_widths(table_def, &tuples)?;
+                if let Some((index_def, key)) = relations[&name].find_conflict(&old_tids, &tuples) {
+                    return Err(unique_violation(table_def, index_def, &key));
+                }
+                foreign_keys.check_references(
+                    &relations,
+                    &old_tids.iter().cloned().collect(),
+                    &tuples,
+                )?;
+                foreign_keys.check_update(&relations, &updated)?;
+
+                let new_tuples = old_tids.into_iter().zip(tuples.clone()).collect::<Vec<_>>();
+                let table = relations.get_mut(&name).unwrap();
+                let new_tids = table.update(&new_tuples)?;
+                let record = LogRecord::Update(
+                    (
+                        ctx.current_database.clone(),
+                        schema_name.clone(),
+                        table_name.clone(),
+                    ),
+                    new_tuples
+                        .into_iter()
+                        .zip(new_tids)
+                        .map(|((old_tid, tuple), new_tid)| (old_tid, new_tid, tuple))
+                        .collect(),
+                );
+                let lsn = ctx.storage_mgr.log(std::slice::from_ref(&record))?;
+                table.flush(lsn)?;
+                drop(relations);
+                update_statistics(ctx, &[record])?;
+
+                tuples
+            }
+            DMLJob::Delete((schema_name, table_name), predicate) => {
+                let name = (schema_name.clone(), table_name.clone());
+                let foreign_keys = ForeignKeys::collect(
+                    &ctx.catalog(),
+                    &ctx.current_database,
+                    &name,
+                    false,
+                    true,
+                )?;
+                let schema = Schema::from(foreign_keys.table_def());
+                let session_schema = SessionSchema {
+                    schema: &schema,
+                    ctx,
+                    outer: &[],
+                };
+
+                let predicate = predicate
+                    .as_ref()
+                    .map(|predicate| {
+                        coerce_to_boolean(type_check(&session_schema, predicate)?, "WHERE")
+                    })
+                    .transpose()?;
+
+                let relations = foreign_keys.relations(&ctx.storage_mgr)?;
+                let mut relations = lock_relations(&relations);
+
+                let mut tids = vec![];
+                let mut tuples = vec![];
+                for (tid, tuple) in relations[&name].tuples() {
+                    if let Some(predicate) = &predicate {
+                        if !predicate.eval_predicate(&tuple)? {
+                            continue;
+                        }
+                    }
+                    tids.push(tid);
+                    tuples.push(tuple);
+                }
+
+                // Tuples referencing the deleted ones may be deleted by cascade
+                let deleted = foreign_keys.cascade_delete(&relations, tids)?;
+                let records = deleted
+                    .into_iter()
+                    .filter(|(_, tids)| !tids.is_empty())
+                    .map(|((schema_name, table_name), tids)| {
+                        let tids = tids.into_iter().collect::<Vec<_>>();
+                        let name = (schema_name, table_name);
+                        relations.get_mut(&name).unwrap().delete(&tids);
+                        LogRecord::Delete((ctx.current_database.clone(), name.0, name.1), tids)
+                    })
+                    .collect::<Vec<_>>();
+                let lsn = ctx.storage_mgr.log(&records)?;
+                for record in records.iter() {
+                    if let LogRecord::Delete((_, schema_name, table_name), _) = record {
+                        let name = (schema_name.clone(), table_name.clone());
+                        relations.get_mut(&name).unwrap().flush(lsn)?;
+                    }
+                }
+                drop(relations);
+                update_statistics(ctx, &records)?;
+
+                tuples
+            }
+            DMLJob::CopyFrom(job, defaults) => {
+                let foreign_keys = ForeignKeys::collect(
+                    &ctx.catalog(),
+                    &ctx.current_database,
+                    &job.table,
+                    true,
+                    false,
+                )?;
+                let table_def = foreign_keys.table_def();
+                let schema = Schema::from(table_def);
+                let session_schema = SessionSchema {
+                    schema: &Schema::default(),
+                    ctx,
+                    outer: &[],
+                };
+                let defaults = defaults
+                    .iter()
+                    .map(|scalar| type_check(&session_schema, scalar))
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                // Records of the values in the order of columns, and the
+                // unit of them in the context of errors
+                let (records, unit, first) = match &job.format {
+                    CopyFormat::Csv(options) => {
+                        let text = std::fs::read_to_string(&job.file)
+                            .map_err(|e| copy_file_error(&job.file, "reading", e))?;
+                        let records = options
+                            .read(&text)?
+                            .into_iter()
+                            .skip(usize::from(options.header))
+                            .map(|record| {
+                                record
+                                    .into_iter()
+                                    .map(|value| {
+                                        value.map_or(Datum::Null, |v| Datum::String(v.into()))
+                                    })
+                                    .collect::<Vec<_>>()
+                            })
+                            .collect::<Vec<_>>();
+                        (records, "line", 1 + usize::from(options.header))
+                    }
+                    CopyFormat::Parquet => {
+                        let names = job
+                            .columns
+                            .iter()
+                            .map(|column| table_def.columns[*column].name.as_str())
+                            .collect::<Vec<_>>();
+                        (read_parquet(&job.file, &names)?, "row", 1)
+                    }
+                };
+
+                let mut tuples = vec![];
+                for (index, record) in records.into_iter().enumerate() {
+                    let context = format!("COPY {}, {} {}", table_def.name, unit, index + first);
+                    if record.len() != job.columns.len() {
+                        let message = match job.columns.get(record.len()) {
+                            Some(column) => format!(
+                                "missing data for column \"{}\"",
+                                table_def.columns[*column].name
+                            ),
+                            None => "extra data after last expected column".to_string(),
+                        };
+                        return Err(SQLError::new(ErrorKind::RuntimeError, message)
+                            .with_code(sqlstate::BAD_COPY_FILE_FORMAT)
+                            .with_detail(context));
+                    }
+
+                    let mut values = vec![Datum::Null; schema.column_types.len()];
+                    for (value, column) in record.into_iter().zip(job.columns.iter()) {
+                        let typ = &schema.column_types[*column];
+                        if value != Datum::Null {
+                            values[*column] = value.cast(typ);
+                            if values[*column] == Datum::Null {
+                                return Err(SQLError::new(
+                                    ErrorKind::RuntimeError,
+                                    format!(
+                                        "invalid input syntax for type {}: \"{}\"",
+                                        to_pg_column_type(
+                                            typ,
+                                            table_def.columns[*column].int_width
+                                        )
+                                        .name(),
+                                        value
+                                    ),
+                                )
+                                .with_code(sqlstate::INVALID_TEXT_REPRESENTATION)
+                                .with_detail(context));
+                            }
+                        }
+                    }
+                    for (column, default) in defaults.iter().enumerate() {
+                        if !job.columns.contains(&column) {
+                            values[column] = default
+                                .eval(&Tuple::default())?
+                                .cast(&schema.column_types[column]);
+                        }
+                    }
+                    tuples.push(Tuple::new(values));
+                }
+
+                Self::insert_tuples(ctx, &foreign_keys, &job.table, tuples.clone())?;
+
+                tuples
+            }
+            DMLJob::CopyTo(job) => {
+                let (schema_name, table_name) = &job.table;
+                let table_def = ctx
+                    .catalog()
+                    .find_table_by_name(&ctx.current_database, schema_name, table_name)?
+                    .ok_or_else(|| {
+                        SQLError::new(ErrorKind::CatalogError, "table not found")
+                            .with_code(sqlstate::UNDEFINED_TABLE)
+                    })?;
+                let relation = ctx
                     .storage_mgr
-                    .get_relation_mut(schema_name, table_name)
+                    .get_relation(&ctx.current_database, schema_name, table_name)
                     .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))?;
-                for tuple in insert_data {
-                    table.insert(tuple.clone());
+                let tuples = relation
+                    .read()
+                    .unwrap()
+                    .tuples()
+                    .map(|(_, tuple)| tuple)
+                    .collect::<Vec<_>>();
+
+                match &job.format {
+                    CopyFormat::Csv(options) => {
+                        let mut text = String::new();
+                        if options.header {
+                            let names = job
+                                .columns
+                                .iter()
+                                .map(|column| Some(table_def.columns[*column].name.clone()))
+                                .collect::<Vec<_>>();
+                            options.write(&mut text, &names);
+                        }
+                        for tuple in tuples.iter() {
+                            let record = job
+                                .columns
+                                .iter()
+                                .map(|column| match &tuple.values[*column] {
+                                    Datum::Null => None,
+                                    datum => Some(datum.to_string()),
+                                })
+                                .collect::<Vec<_>>();
+                            options.write(&mut text, &record);
+                        }
+                        std::fs::write(&job.file, text)
+                            .map_err(|e| copy_file_error(&job.file, "writing", e))?;
+                    }
+                    CopyFormat::Parquet => {
+                        let columns = job
+                            .columns
+                            .iter()
+                            .map(|column| {
+                                let column_def = &table_def.columns[*column];
+                                (
+                                    column_def.name.clone(),
+                                    column_def.data_type.clone(),
+                                    column_def.int_width,
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        let rows = tuples
+                            .iter()
+                            .map(|tuple| {
+                                Tuple::new(
+                                    job.columns
+                                        .iter()
+                                        .map(|column| tuple.values[*column].clone())
+                                        .collect(),
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        write_parquet(&job.file, &columns, &rows)?;
+                    }
                 }
+
+                tuples
             }
+        };
+
+        self.buffer_results(affected)
+    }
+
+    pub fn next(&mut self, _ctx: &mut QueryContext) -> Result<Option<Tuple>, SQLError> {
+        Ok(self.result_buffer.pop_front())
+    }
+}
+
+/// Check the tuples against the NOT NULL constraints of columns.
+fn check_not_null(table_def: &TableDefinition, tuples: &[Tuple]) -> Result<(), SQLError> {
+    for tuple in tuples.iter() {
+        let violated = table_def
+            .columns
+            .iter()
+            .zip(tuple.values.iter())
+            .find(|(column, value)| !column.null && **value == Datum::Null);
+        if let Some((column, _)) = violated {
+            let values = tuple
+                .values
+                .iter()
+                .map(|datum| match datum {
+                    Datum::Null => "null".to_string(),
+                    datum => datum.to_string(),
+                })
+                .collect::<Vec<_>>();
+            return Err(SQLError::new(
+                ErrorKind::RuntimeError,
+                format!(
+                    "null value in column \"{}\" of relation \"{}\" violates not-null constraint",
+                    column.name, table_def.name
+                ),
+            )
+            .with_code(sqlstate::NOT_NULL_VIOLATION)
+            .with_detail(format!("Failing row contains ({}).", values.join(", "))));
         }
+    }
+    Ok(())
+}
 
-        Ok(())
+/// Cast the value written to the column to its type, fails instead of
+/// writing NULL if the value can't be converted.
+fn cast_to_column(value: &Datum, column: &ColumnDefinition) -> Result<Datum, SQLError> {
+    let new_value = value.cast(&column.data_type);
+    if new_value.is_null() && !value.is_null() {
+        let pg_type = to_pg_column_type(&column.data_type, column.int_width);
+        let type_name = pg_type.name();
+        return Err(match value {
+            Datum::String(v) if matches!(column.data_type, Type::Array(_)) => SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("malformed array literal: \"{}\"", v),
+            )
+            .with_code(sqlstate::INVALID_TEXT_REPRESENTATION),
+            Datum::String(v) => SQLError::new(
+                ErrorKind::RuntimeError,
+                format!("invalid input syntax for type {}: \"{}\"", type_name, v),
+            )
+            .with_code(sqlstate::INVALID_TEXT_REPRESENTATION),
+            Datum::Float(_) if column.data_type == Type::Int => SQLError::new(
+                ErrorKind::RuntimeError,
+                format!(
+                    "{} out of range",
+                    column.int_width.unwrap_or(IntWidth::Int8).name()
+                ),
+            )
+            .with_code(sqlstate::NUMERIC_VALUE_OUT_OF_RANGE),
+            value => SQLError::new(
+                ErrorKind::RuntimeError,
+                format!(
+                    "column \"{}\" is of type {} but expression is of type {}",
+                    column.name,
+                    type_name,
+                    to_pg_type(&value.typ()).name()
+                ),
+            )
+            .with_code(sqlstate::DATATYPE_MISMATCH),
+        });
+    }
+    Ok(new_value)
+}
+
+/// Check the values of integer columns are in the ranges of declared widths.
+fn check_int_widths(table_def: &TableDefinition, tuples: &[Tuple]) -> Result<(), SQLError> {
+    for tuple in tuples.iter() {
+        for (column, value) in table_def.columns.iter().zip(tuple.values.iter()) {
+            if let Some(int_width) = column.int_width {
+                int_width.check(value)?;
+            }
+        }
     }
+    Ok(())
+}
+
+fn unique_violation(
+    table_def: &TableDefinition,
+    index_def: &IndexDefinition,
+    key: &[Datum],
+) -> SQLError {
+    SQLError::new(
+        ErrorKind::RuntimeError,
+        format!(
+            "duplicate key value violates unique constraint \"{}\"",
+            index_def.name
+        ),
+    )
+    .with_code(sqlstate::UNIQUE_VIOLATION)
+    .with_detail(format!(
+        "Key {} already exists.",
+        format_key(table_def, &index_def.columns, key)
+    ))
 }
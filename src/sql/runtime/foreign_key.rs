@@ -0,0 +1,359 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::RwLockWriteGuard,
+};
+
+use crate::{
+    catalog::{
+        defs::{ForeignKeyDefinition, ReferentialAction, TableDefinition},
+        Catalog,
+    },
+    core::{sqlstate, Datum, ErrorKind, SQLError, Tuple},
+    storage::{
+        relation::{Relation, TupleId},
+        SharedRelation, StorageManager,
+    },
+};
+
+/// Qualified name of a table (schema_name, table_name).
+pub type TableName = (String, String);
+
+/// Relations locked by a DML statement.
+pub type LockedRelations<'a> = BTreeMap<TableName, RwLockWriteGuard<'a, Relation>>;
+
+/// The target table of a DML statement, with the tables related to it by foreign
+/// keys, which must be locked together to enforce the constraints.
+pub struct ForeignKeys {
+    database: String,
+    target: TableName,
+    defs: BTreeMap<TableName, TableDefinition>,
+    /// Foreign keys referencing each table, with the referencing table.
+    referencing: BTreeMap<TableName, Vec<(TableName, ForeignKeyDefinition)>>,
+}
+
+impl ForeignKeys {
+    /// Collect the tables referenced by the target if `parents`, and the tables
+    /// referencing the target transitively if `children`, all in the database.
+    pub fn collect(
+        catalog: &Catalog,
+        database_name: &str,
+        target: &TableName,
+        parents: bool,
+        children: bool,
+    ) -> Result<Self, SQLError> {
+        let mut foreign_keys = Self {
+            database: database_name.to_string(),
+            target: target.clone(),
+            defs: BTreeMap::new(),
+            referencing: BTreeMap::new(),
+        };
+        let table_def = find_table(catalog, database_name, target)?;
+
+        if parents {
+            for foreign_key in table_def.foreign_keys.iter() {
+                let referred = (
+                    foreign_key.referred_schema.clone(),
+                    foreign_key.referred_table.clone(),
+                );
+                let referred_def = find_table(catalog, database_name, &referred)?;
+                foreign_keys.defs.insert(referred, referred_def);
+            }
+        }
+        foreign_keys.defs.insert(target.clone(), table_def);
+
+        if children {
+            let mut worklist = vec![target.clone()];
+            while let Some(name) = worklist.pop() {
+                if foreign_keys.referencing.contains_key(&name) {
+                    continue;
+                }
+                let mut referencing = vec![];
+                for (schema_name, table_def) in
+                    catalog.referencing_tables(database_name, &name.0, &name.1)
+                {
+                    let child = (schema_name, table_def.name.clone());
+                    for foreign_key in table_def.foreign_keys.iter() {
+                        if foreign_key.refers_to(&name.0, &name.1) {
+                            referencing.push((child.clone(), foreign_key.clone()));
+                        }
+                    }
+                    worklist.push(child.clone());
+                    foreign_keys.defs.insert(child, table_def);
+                }
+                foreign_keys.referencing.insert(name, referencing);
+            }
+        }
+
+        Ok(foreign_keys)
+    }
+
+    pub fn table_def(&self) -> &TableDefinition {
+        &self.defs[&self.target]
+    }
+
+    /// Shared relations of all the collected tables.
+    pub fn relations(
+        &self,
+        storage_mgr: &StorageManager,
+    ) -> Result<BTreeMap<TableName, SharedRelation>, SQLError> {
+        self.defs
+            .keys()
+            .map(|name| {
+                let relation = storage_mgr
+                    .get_relation(&self.database, &name.0, &name.1)
+                    .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))?;
+                Ok((name.clone(), relation))
+            })
+            .collect()
+    }
+
+    /// Check that the keys of new tuples in the target table are present in the
+    /// referenced tables. The `removed` tuples of target are not counted.
+    pub fn check_references(
+        &self,
+        relations: &LockedRelations,
+        removed: &HashSet<TupleId>,
+        tuples: &[Tuple],
+    ) -> Result<(), SQLError> {
+        let table_def = self.table_def();
+        for foreign_key in table_def.foreign_keys.iter() {
+            let referred = (
+                foreign_key.referred_schema.clone(),
+                foreign_key.referred_table.clone(),
+            );
+            let referred_def = &self.defs[&referred];
+            let index = referred_def
+                .find_unique_index(&foreign_key.referred_columns)
+                .and_then(|index_def| relations[&referred].index(&index_def.name))
+                .ok_or_else(|| {
+                    SQLError::new(
+                        ErrorKind::UnknownError,
+                        format!(
+                            "cannot find unique index of foreign key: {}",
+                            foreign_key.name
+                        ),
+                    )
+                })?;
+            let self_referencing = referred == self.target;
+
+            for tuple in tuples.iter() {
+                let key = project(tuple, &foreign_key.columns);
+                // A key with NULL doesn't reference anything
+                if key.contains(&Datum::Null) {
+                    continue;
+                }
+                let present = index
+                    .tree
+                    .lookup(&key)
+                    .any(|tid| !self_referencing || !removed.contains(&tid))
+                    || self_referencing
+                        && tuples
+                            .iter()
+                            .any(|tuple| project(tuple, &foreign_key.referred_columns) == key);
+                if !present {
+                    return Err(SQLError::new(
+                        ErrorKind::RuntimeError,
+                        format!(
+                            "insert or update on table \"{}\" violates foreign key constraint \"{}\"",
+                            table_def.name, foreign_key.name
+                        ),
+                    )
+                    .with_code(sqlstate::FOREIGN_KEY_VIOLATION)
+                    .with_detail(format!(
+                        "Key {} is not present in table \"{}\".",
+                        format_key(table_def, &foreign_key.columns, &key),
+                        referred_def.name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the keys removed by updating the target table are not referenced.
+    pub fn check_update(
+        &self,
+        relations: &LockedRelations,
+        updated: &[(TupleId, Tuple, Tuple)],
+    ) -> Result<(), SQLError> {
+        let updated_tids = updated.iter().map(|(tid, ..)| *tid).collect();
+        let mut excluded = BTreeMap::new();
+        excluded.insert(self.target.clone(), updated_tids);
+
+        for (child, foreign_key) in self.referencing[&self.target].iter() {
+            let new_keys = updated
+                .iter()
+                .map(|(_, _, tuple)| project(tuple, &foreign_key.referred_columns))
+                .collect::<HashSet<_>>();
+            let removed_keys = updated
+                .iter()
+                .map(|(_, tuple, _)| project(tuple, &foreign_key.referred_columns))
+                .filter(|key| !new_keys.contains(key))
+                .collect();
+            self.check_referenced(
+                relations,
+                &self.target,
+                child,
+                foreign_key,
+                &removed_keys,
+                &excluded,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Tuples to delete from each table, including the ones referencing the deleted
+    /// tuples with `ON DELETE CASCADE`. Fails if any tuple is still referenced.
+    pub fn cascade_delete(
+        &self,
+        relations: &LockedRelations,
+        tids: Vec<TupleId>,
+    ) -> Result<BTreeMap<TableName, BTreeSet<TupleId>>, SQLError> {
+        let mut deleted = BTreeMap::<_, BTreeSet<_>>::new();
+        deleted.insert(self.target.clone(), tids.iter().cloned().collect());
+
+        let mut worklist = vec![(self.target.clone(), tids)];
+        while let Some((name, tids)) = worklist.pop() {
+            let tuples = tids
+                .iter()
+                .filter_map(|tid| relations[&name].get(*tid))
+                .collect::<Vec<_>>();
+            for (child, foreign_key) in self.referencing[&name].iter() {
+                if foreign_key.on_delete != ReferentialAction::Cascade {
+                    continue;
+                }
+                let removed_keys = tuples
+                    .iter()
+                    .map(|tuple| project(tuple, &foreign_key.referred_columns))
+                    .collect();
+                let excluded = deleted.entry(child.clone()).or_default();
+                let cascaded =
+                    find_referencing(&relations[child], foreign_key, &removed_keys, excluded)
+                        .map(|(tid, _)| tid)
+                        .collect::<Vec<_>>();
+                excluded.extend(cascaded.iter().cloned());
+                if !cascaded.is_empty() {
+                    worklist.push((child.clone(), cascaded));
+                }
+            }
+        }
+
+        // The other references must be deleted together
+        for (name, tids) in deleted.iter() {
+            let tuples = tids
+                .iter()
+                .filter_map(|tid| relations[name].get(*tid))
+                .collect::<Vec<_>>();
+            for (child, foreign_key) in self.referencing[name].iter() {
+                let removed_keys = tuples
+                    .iter()
+                    .map(|tuple| project(tuple, &foreign_key.referred_columns))
+                    .collect();
+                self.check_referenced(
+                    relations,
+                    name,
+                    child,
+                    foreign_key,
+                    &removed_keys,
+                    &deleted,
+                )?;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Fail if any tuple of `child`, except the `excluded` ones, references the
+    /// removed keys of `parent`.
+    fn check_referenced(
+        &self,
+        relations: &LockedRelations,
+        parent: &TableName,
+        child: &TableName,
+        foreign_key: &ForeignKeyDefinition,
+        removed_keys: &HashSet<Vec<Datum>>,
+        excluded: &BTreeMap<TableName, BTreeSet<TupleId>>,
+    ) -> Result<(), SQLError> {
+        let no_excluded = BTreeSet::new();
+        let excluded = excluded.get(child).unwrap_or(&no_excluded);
+        let Some((_, key)) =
+            find_referencing(&relations[child], foreign_key, removed_keys, excluded).next()
+        else {
+            return Ok(());
+        };
+
+        let parent_def = &self.defs[parent];
+        let child_def = &self.defs[child];
+        Err(SQLError::new(
+            ErrorKind::RuntimeError,
+            format!(
+                "update or delete on table \"{}\" violates foreign key constraint \"{}\" on table \"{}\"",
+                parent_def.name, foreign_key.name, child_def.name
+            ),
+        )
+        .with_code(sqlstate::FOREIGN_KEY_VIOLATION)
+        .with_detail(format!(
+            "Key {} is still referenced from table \"{}\".",
+            format_key(parent_def, &foreign_key.referred_columns, &key),
+            child_def.name
+        )))
+    }
+}
+
+/// Lock the relations in the order of names, so concurrent statements never
+/// wait for each other in a cycle.
+pub fn lock_relations(relations: &BTreeMap<TableName, SharedRelation>) -> LockedRelations<'_> {
+    relations
+        .iter()
+        .map(|(name, relation)| (name.clone(), relation.write().unwrap()))
+        .collect()
+}
+
+/// Format the key of columns like `(a, b)=(1, 2)`, for the detail of errors.
+pub fn format_key(table_def: &TableDefinition, columns: &[usize], key: &[Datum]) -> String {
+    let names = columns
+        .iter()
+        .map(|column| table_def.columns[*column].name.clone())
+        .collect::<Vec<_>>();
+    let values = key
+        .iter()
+        .map(|datum| datum.to_string())
+        .collect::<Vec<_>>();
+    format!("({})=({})", names.join(", "), values.join(", "))
+}
+
+fn find_table(
+    catalog: &Catalog,
+    database_name: &str,
+    name: &TableName,
+) -> Result<TableDefinition, SQLError> {
+    catalog
+        .find_table_by_name(database_name, &name.0, &name.1)?
+        .ok_or_else(|| {
+            SQLError::new(ErrorKind::CatalogError, "table not found")
+                .with_code(sqlstate::UNDEFINED_TABLE)
+        })
+}
+
+fn project(tuple: &Tuple, columns: &[usize]) -> Vec<Datum> {
+    columns
+        .iter()
+        .map(|column| tuple.values[*column].clone())
+        .collect()
+}
+
+/// Tuples referencing any of the keys, with the referenced key.
+fn find_referencing<'a>(
+    relation: &'a Relation,
+    foreign_key: &'a ForeignKeyDefinition,
+    keys: &'a HashSet<Vec<Datum>>,
+    excluded: &'a BTreeSet<TupleId>,
+) -> impl Iterator<Item = (TupleId, Vec<Datum>)> + 'a {
+    relation.tuples().filter_map(move |(tid, tuple)| {
+        let key = project(&tuple, &foreign_key.columns);
+        (!excluded.contains(&tid) && !key.contains(&Datum::Null) && keys.contains(&key))
+            .then_some((tid, key))
+    })
+}
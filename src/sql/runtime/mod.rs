@@ -1,26 +1,73 @@
 pub mod builder;
+mod copy;
 mod ddl;
 pub mod dml;
 pub mod executor;
+mod foreign_key;
+pub mod pipeline;
+mod runtime_filter;
 
+pub use copy::*;
 pub use ddl::*;
 pub use dml::*;
+pub use runtime_filter::*;
 
-use self::builder::ExecutorBuilder;
-use super::{planner::Plan, session::context::QueryContext};
+use self::{
+    builder::{ExecutorBuilder, Schema},
+    executor::Executor,
+};
+use super::{
+    planner::{physical::plan_physical, Plan},
+    session::context::QueryContext,
+};
 use crate::core::{SQLError, Tuple};
 
-pub fn execute_plan(ctx: &mut QueryContext, plan: &Plan) -> Result<Vec<Tuple>, SQLError> {
-    let mut executor = ExecutorBuilder::new(ctx).build(plan)?;
+/// A live executor of plan, whose results can be fetched incrementally.
+pub struct Cursor {
+    executor: Executor,
+    pub schema: Schema,
+    /// Operators of the physical plan, see `PhysicalPlan::summary`.
+    pub plan_summary: String,
+    finished: bool,
+}
+
+impl Cursor {
+    pub fn open(ctx: &mut QueryContext, plan: &Plan) -> Result<Self, SQLError> {
+        let physical_plan = plan_physical(plan, &ctx.catalog().database(&ctx.current_database));
+        let (mut executor, schema) = ExecutorBuilder::new(ctx).build(&physical_plan)?;
 
-    executor.open(ctx)?;
+        executor.open(ctx)?;
 
-    let mut result = vec![];
-    while let Some(tuple) = executor.next(ctx)? {
-        result.push(tuple);
+        Ok(Self {
+            executor,
+            schema,
+            plan_summary: physical_plan.summary(),
+            finished: false,
+        })
     }
 
-    executor.close(ctx)?;
+    /// Fetch at most `max_rows` tuples, or all the remaining tuples if
+    /// `max_rows` is 0. The executor is closed once it's exhausted.
+    pub fn fetch(
+        &mut self,
+        ctx: &mut QueryContext,
+        max_rows: usize,
+    ) -> Result<Vec<Tuple>, SQLError> {
+        let mut result = vec![];
+        while !self.finished && (max_rows == 0 || result.len() < max_rows) {
+            match self.executor.next(ctx)? {
+                Some(tuple) => result.push(tuple),
+                None => {
+                    self.executor.close(ctx)?;
+                    self.finished = true;
+                }
+            }
+        }
 
-    Ok(result)
+        Ok(result)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
 }
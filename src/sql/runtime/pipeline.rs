@@ -0,0 +1,113 @@
+use super::executor::{Executor, PipelineExecutor};
+use crate::{
+    core::{Chunk, SQLError, Tuple},
+    sql::session::context::QueryContext,
+};
+
+/// Operator transforming the chunks pushed to it, without any state.
+#[allow(clippy::type_complexity)]
+pub enum Operator {
+    /// Keep the tuples satisfying the predicate.
+    Filter(Box<dyn Fn(&Tuple) -> Result<bool, SQLError> + Send>),
+    /// Keep the columns at the indices.
+    Project(Vec<usize>),
+    /// Append the computed columns to the tuples.
+    Map(Box<dyn Fn(Tuple) -> Result<Tuple, SQLError> + Send>),
+}
+
+impl Operator {
+    pub fn push(&self, chunk: Chunk) -> Result<Chunk, SQLError> {
+        match self {
+            Operator::Filter(predicate) => {
+                let mut tuples = Vec::with_capacity(chunk.tuples.len());
+                for tuple in chunk {
+                    if predicate(&tuple)? {
+                        tuples.push(tuple);
+                    }
+                }
+                Ok(Chunk::new(tuples))
+            }
+            Operator::Project(projections) => Ok(Chunk::new(
+                chunk
+                    .into_iter()
+                    .map(|tuple| tuple.project(projections))
+                    .collect(),
+            )),
+            Operator::Map(map_fn) => Ok(Chunk::new(
+                chunk.into_iter().map(map_fn).collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+}
+
+/// Consumer of all the chunks of a pipeline.
+pub trait Sink {
+    fn consume(&mut self, chunk: Chunk) -> Result<(), SQLError>;
+}
+
+/// A chain of push-based operators, fed by the chunks pulled from the source
+/// executor. The chunks coming out of a pipeline are either pulled by the
+/// parent executor, or pushed into a sink by `run`, e.g. the hash table of an
+/// aggregation, which breaks the pipeline.
+pub struct Pipeline {
+    pub source: Box<Executor>,
+    pub operators: Vec<Operator>,
+}
+
+impl Pipeline {
+    pub fn new(source: Executor) -> Self {
+        Self {
+            source: Box::new(source),
+            operators: vec![],
+        }
+    }
+
+    /// Continue the pipeline of the executor if it is pipelined, otherwise
+    /// start a new pipeline with the executor as source.
+    pub fn from_executor(executor: Executor) -> Self {
+        match executor {
+            Executor::Pipeline(PipelineExecutor { pipeline, .. }) => pipeline,
+            executor => Self::new(executor),
+        }
+    }
+
+    /// Append the operator to the end of pipeline.
+    pub fn push_operator(&mut self, operator: Operator) {
+        self.operators.push(operator);
+    }
+
+    /// Append the operator to the pipeline of the executor.
+    pub fn pipelined(executor: Executor, operator: Operator) -> Executor {
+        let mut pipeline = Self::from_executor(executor);
+        pipeline.push_operator(operator);
+        Executor::Pipeline(PipelineExecutor::new(pipeline))
+    }
+
+    /// Push the next chunk of source through the operators, the chunks
+    /// becoming empty are skipped.
+    pub fn next_chunk(&mut self, ctx: &mut QueryContext) -> Result<Option<Chunk>, SQLError> {
+        'chunks: while let Some(mut chunk) = self.source.next_chunk(ctx)? {
+            for operator in self.operators.iter() {
+                chunk = match chunk.non_empty() {
+                    Some(chunk) => operator.push(chunk)?,
+                    None => continue 'chunks,
+                };
+            }
+            if let Some(chunk) = chunk.non_empty() {
+                return Ok(Some(chunk));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Drive the pipeline until the source is exhausted, all the chunks are
+    /// pushed into the sink.
+    pub fn run(&mut self, ctx: &mut QueryContext, sink: &mut dyn Sink) -> Result<(), SQLError> {
+        while let Some(chunk) = self.next_chunk(ctx)? {
+            sink.consume(chunk)?;
+        }
+
+        Ok(())
+    }
+}
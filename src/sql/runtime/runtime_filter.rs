@@ -0,0 +1,152 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, RwLock},
+};
+
+use crate::core::{Datum, Tuple};
+
+/// Bits of the bloom filter per key, which gives about 1% false positives
+/// with the number of hash functions.
+const BITS_PER_KEY: usize = 10;
+const NUM_HASHES: u64 = 7;
+
+/// Bloom filter of the join keys, false positives are possible while false
+/// negatives are not.
+#[derive(Debug)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    pub fn with_capacity(num_keys: usize) -> Self {
+        let num_bits = (num_keys * BITS_PER_KEY).max(64);
+        Self {
+            bits: vec![0; (num_bits + 63) / 64],
+        }
+    }
+
+    pub fn insert(&mut self, hash: u64) {
+        for bit in self.bit_positions(hash) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    pub fn may_contain(&self, hash: u64) -> bool {
+        self.bit_positions(hash)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Positions of the bits of the hash, derived from its two halves by
+    /// double hashing.
+    fn bit_positions(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let num_bits = self.bits.len() as u64 * 64;
+        let (h1, h2) = (hash & 0xffff_ffff, hash >> 32);
+        (0..NUM_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+}
+
+/// Filter on the join keys built from the build side of a hash join, pushed
+/// into the scan of the probe side to skip the tuples without any match
+/// before they flow through the probe pipeline.
+///
+/// The hash join and the scan share the filter, which passes every tuple
+/// until the hash table is built.
+#[derive(Debug, Clone)]
+pub struct RuntimeFilter {
+    /// Positions of the join keys covered by the filter.
+    pub keys: Vec<usize>,
+    /// Columns of the scanned table holding the keys, before the projection
+    /// of scan.
+    pub columns: Vec<usize>,
+    bloom: Arc<RwLock<Option<BloomFilter>>>,
+}
+
+impl RuntimeFilter {
+    pub fn new(keys: Vec<usize>, columns: Vec<usize>) -> Self {
+        Self {
+            keys,
+            columns,
+            bloom: Arc::default(),
+        }
+    }
+
+    /// Build the filter on the keys of the build side, replacing the previous
+    /// one. The keys of the probe side must be of the same types.
+    pub fn build<'a>(&self, keys: impl ExactSizeIterator<Item = &'a [Datum]>) {
+        let mut bloom = BloomFilter::with_capacity(keys.len());
+        for key in keys {
+            bloom.insert(hash_key(self.keys.iter().map(|i| &key[*i])));
+        }
+        *self.bloom.write().unwrap() = Some(bloom);
+    }
+
+    /// Check the tuple of the scanned table against the filter, fails only if
+    /// the tuple can't match any tuple of the build side.
+    pub fn check(&self, tuple: &Tuple) -> bool {
+        let bloom = self.bloom.read().unwrap();
+        let Some(bloom) = bloom.as_ref() else {
+            return true;
+        };
+        let key = self
+            .columns
+            .iter()
+            .map(|column| tuple.values.get(*column).unwrap_or(&Datum::Null));
+        // NULL keys never match
+        !key.clone().any(Datum::is_null) && bloom.may_contain(hash_key(key))
+    }
+}
+
+fn hash_key<'a>(key: impl Iterator<Item = &'a Datum>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for value in key {
+        value.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_filter_has_no_false_negatives() {
+        let mut bloom = BloomFilter::with_capacity(1000);
+        let hashes = (0..1000)
+            .map(|i| hash_key([Datum::Int(i)].iter()))
+            .collect::<Vec<_>>();
+        for hash in hashes.iter() {
+            bloom.insert(*hash);
+        }
+        assert!(hashes.iter().all(|hash| bloom.may_contain(*hash)));
+
+        let false_positives = (1000..11000)
+            .filter(|i| bloom.may_contain(hash_key([Datum::Int(*i)].iter())))
+            .count();
+        assert!(false_positives < 300, "{}", false_positives);
+    }
+
+    #[test]
+    fn runtime_filter_checks_mapped_columns() {
+        // The keys are the second and first of the build side, and the
+        // third and first columns of the scanned table
+        let filter = RuntimeFilter::new(vec![1, 0], vec![2, 0]);
+        let tuple = |values: [Datum; 3]| Tuple::new(values.to_vec());
+        let matched = tuple([Datum::Int(2), Datum::Null, Datum::String("a".into())]);
+        let unmatched = tuple([Datum::Int(3), Datum::Null, Datum::String("a".into())]);
+        let null_key = tuple([Datum::Null, Datum::Null, Datum::String("a".into())]);
+
+        // Every tuple passes until the filter is built
+        assert!(filter.check(&unmatched));
+        assert!(filter.check(&null_key));
+
+        let build_side = [
+            vec![Datum::Int(2), Datum::String("a".into())],
+            vec![Datum::Int(5), Datum::String("b".into())],
+        ];
+        filter.build(build_side.iter().map(|key| key.as_slice()));
+        assert!(filter.check(&matched));
+        assert!(!filter.check(&unmatched));
+        assert!(!filter.check(&null_key));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::SystemTime,
+};
+
+use tokio::sync::Notify;
+
+use crate::{
+    catalog::DEFAULT_DATABASE,
+    core::{sqlstate, ErrorKind, SQLError},
+};
+
+/// State of a session, as `state` of `pg_stat_activity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Waiting for a new statement.
+    Idle,
+    /// Executing a statement.
+    Active,
+}
+
+impl Display for SessionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionState::Idle => write!(f, "idle"),
+            SessionState::Active => write!(f, "active"),
+        }
+    }
+}
+
+/// What a session is doing, a row of `pg_stat_activity`.
+#[derive(Debug, Clone)]
+pub struct SessionActivity {
+    pub pid: i64,
+    pub user: Option<String>,
+    /// The database connected to.
+    pub database: String,
+    pub application_name: String,
+    pub backend_start: SystemTime,
+    pub state: SessionState,
+    pub state_change: SystemTime,
+    /// The statement being executed, or the last one if the session is idle.
+    pub query: String,
+    pub query_start: Option<SystemTime>,
+}
+
+/// Interrupts requested to a session by the others, with
+/// `pg_cancel_backend` and `pg_terminate_backend`. They are checked by the
+/// executors between rows.
+#[derive(Debug, Default)]
+pub struct Interrupts {
+    cancel: AtomicBool,
+    terminate: AtomicBool,
+    terminated: Notify,
+}
+
+impl Interrupts {
+    /// Cancel the statement being executed.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Abort the statement being executed and close the connection.
+    pub fn terminate(&self) {
+        self.terminate.store(true, Ordering::Relaxed);
+        self.terminated.notify_one();
+    }
+
+    /// Fail if the statement is interrupted, a cancel only aborts the
+    /// current statement while a termination fails all the following ones.
+    pub fn check(&self) -> Result<(), SQLError> {
+        if self.terminate.load(Ordering::Relaxed) {
+            return Err(SQLError::new(
+                ErrorKind::RuntimeError,
+                "terminating connection due to administrator command",
+            )
+            .with_code(sqlstate::ADMIN_SHUTDOWN));
+        }
+        if self.cancel.swap(false, Ordering::Relaxed) {
+            return Err(SQLError::new(
+                ErrorKind::RuntimeError,
+                "canceling statement due to user request",
+            )
+            .with_code(sqlstate::QUERY_CANCELED));
+        }
+        Ok(())
+    }
+
+    /// Drop the cancel requested while the session was idle, which doesn't
+    /// affect the next statement.
+    pub fn clear_cancel(&self) {
+        self.cancel.store(false, Ordering::Relaxed);
+    }
+
+    /// Wait until the session is terminated.
+    pub async fn terminated(&self) {
+        self.terminated.notified().await
+    }
+}
+
+/// A live session with the interrupts to it.
+type SessionEntry = (SessionActivity, Arc<Interrupts>);
+
+/// Registry of the live sessions of a database, the sessions register
+/// themselves when they are created and are removed when dropped.
+#[derive(Debug, Default)]
+pub struct ActivityRegistry {
+    /// Sessions by pid, which is assigned incrementally.
+    sessions: Mutex<(i64, BTreeMap<i64, SessionEntry>)>,
+}
+
+impl ActivityRegistry {
+    /// Register a new idle session interrupted by the `interrupts`, returns
+    /// its pid.
+    pub fn register(&self, interrupts: Arc<Interrupts>) -> i64 {
+        let mut guard = self.sessions.lock().unwrap();
+        let (last_pid, sessions) = &mut *guard;
+        *last_pid += 1;
+        let now = SystemTime::now();
+        sessions.insert(
+            *last_pid,
+            (
+                SessionActivity {
+                    pid: *last_pid,
+                    user: None,
+                    database: DEFAULT_DATABASE.to_string(),
+                    application_name: String::new(),
+                    backend_start: now,
+                    state: SessionState::Idle,
+                    state_change: now,
+                    query: String::new(),
+                    query_start: None,
+                },
+                interrupts,
+            ),
+        );
+        *last_pid
+    }
+
+    pub fn unregister(&self, pid: i64) {
+        self.sessions.lock().unwrap().1.remove(&pid);
+    }
+
+    pub fn update(&self, pid: i64, f: impl FnOnce(&mut SessionActivity)) {
+        if let Some((activity, _)) = self.sessions.lock().unwrap().1.get_mut(&pid) {
+            f(activity);
+        }
+    }
+
+    /// Interrupt the session, returns false if there is no such session.
+    pub fn interrupt(&self, pid: i64, f: impl FnOnce(&Interrupts)) -> bool {
+        match self.sessions.lock().unwrap().1.get(&pid) {
+            Some((_, interrupts)) => {
+                f(interrupts);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The sessions ordered by pid.
+    pub fn list(&self) -> Vec<SessionActivity> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .1
+            .values()
+            .map(|(activity, _)| activity.clone())
+            .collect()
+    }
+}
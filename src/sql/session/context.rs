@@ -1,8 +1,138 @@
-use crate::{catalog::Catalog, storage::StorageManager};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use super::{
+    activity::{ActivityRegistry, Interrupts},
+    settings::Settings,
+};
+use crate::{
+    catalog::{Catalog, DEFAULT_DATABASE},
+    core::{sqlstate, ErrorKind, SQLError},
+    storage::{QualifiedName, StorageManager},
+};
 
 /// The context stores all the information needed to execute a query.
+/// Catalog and storage are shared by all the sessions, while the others are session-local.
 pub struct QueryContext {
-    pub catalog: Catalog,
-    pub storage_mgr: StorageManager,
+    pub catalog: Arc<RwLock<Catalog>>,
+    pub storage_mgr: Arc<StorageManager>,
+    /// The database the session is connected to, the only one it can access.
+    pub current_database: String,
     pub current_schema: String,
+    /// Run-time parameters of the session.
+    pub settings: Settings,
+    /// Activities of all the sessions, shared like the catalog.
+    pub activity: Arc<ActivityRegistry>,
+    /// Interrupts of the session, checked while executing.
+    pub interrupts: Arc<Interrupts>,
+    /// Version of catalog the last statement is bound with.
+    pub catalog_version: u64,
+    /// Directory of the files `COPY` can read and write on the server, which
+    /// is disallowed if it's not configured.
+    pub copy_directory: Option<PathBuf>,
+    /// Directory of the WebAssembly modules functions can be loaded from,
+    /// which is disallowed if it's not configured.
+    pub function_directory: Option<PathBuf>,
+    /// The last values returned by `nextval` in the session, by qualified name.
+    sequence_values: Arc<Mutex<HashMap<QualifiedName, i64>>>,
+}
+
+impl QueryContext {
+    pub fn new(catalog: Arc<RwLock<Catalog>>, storage_mgr: Arc<StorageManager>) -> Self {
+        Self {
+            catalog,
+            storage_mgr,
+            current_database: DEFAULT_DATABASE.to_string(),
+            current_schema: "default".to_string(),
+            settings: Settings::default(),
+            activity: Arc::default(),
+            interrupts: Arc::default(),
+            catalog_version: 0,
+            copy_directory: None,
+            function_directory: None,
+            sequence_values: Arc::default(),
+        }
+    }
+
+    /// Shared access to the catalog, queries can read it concurrently.
+    pub fn catalog(&self) -> RwLockReadGuard<'_, Catalog> {
+        self.catalog.read().unwrap()
+    }
+
+    /// Exclusive access to the catalog, used by DDL.
+    pub fn catalog_mut(&self) -> RwLockWriteGuard<'_, Catalog> {
+        self.catalog.write().unwrap()
+    }
+
+    /// Resolve the sequence by name, which may be qualified like `schema.sequence`.
+    pub fn sequence(&self, name: &str) -> Result<SessionSequence, SQLError> {
+        let (schema_name, sequence_name) = match name.split_once('.') {
+            Some((schema_name, sequence_name)) => (schema_name, sequence_name),
+            None => (self.current_schema.as_str(), name),
+        };
+        if self
+            .catalog()
+            .find_sequence_by_name(&self.current_database, schema_name, sequence_name)?
+            .is_none()
+        {
+            return Err(SQLError::new(
+                ErrorKind::CatalogError,
+                format!("relation \"{}\" does not exist", name),
+            )
+            .with_code(sqlstate::UNDEFINED_TABLE));
+        }
+
+        Ok(SessionSequence {
+            name: (
+                self.current_database.clone(),
+                schema_name.to_string(),
+                sequence_name.to_string(),
+            ),
+            storage_mgr: self.storage_mgr.clone(),
+            sequence_values: self.sequence_values.clone(),
+        })
+    }
+}
+
+/// A sequence used in the session, by `nextval` and `currval`.
+#[derive(Clone)]
+pub struct SessionSequence {
+    name: QualifiedName,
+    storage_mgr: Arc<StorageManager>,
+    sequence_values: Arc<Mutex<HashMap<QualifiedName, i64>>>,
+}
+
+impl SessionSequence {
+    pub fn next_value(&self) -> Result<i64, SQLError> {
+        let value =
+            self.storage_mgr
+                .next_sequence_value(&self.name.0, &self.name.1, &self.name.2)?;
+        self.sequence_values
+            .lock()
+            .unwrap()
+            .insert(self.name.clone(), value);
+        Ok(value)
+    }
+
+    /// The value returned by the last `nextval` of the sequence in the session.
+    pub fn current_value(&self) -> Result<i64, SQLError> {
+        self.sequence_values
+            .lock()
+            .unwrap()
+            .get(&self.name)
+            .cloned()
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::RuntimeError,
+                    format!(
+                        "currval of sequence \"{}\" is not yet defined in this session",
+                        self.name.2
+                    ),
+                )
+                .with_code(sqlstate::OBJECT_NOT_IN_PREREQUISITE_STATE)
+            })
+    }
 }
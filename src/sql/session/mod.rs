@@ -1,69 +1,703 @@
+pub mod activity;
 pub mod context;
+pub mod result;
+pub mod settings;
+pub mod stats;
 
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime},
+};
+
+use arrow_array::RecordBatch;
 use log::info;
-use pgwire::api::results::FieldInfo;
-use sqlparser::ast::Statement;
+use postgres_types::Kind;
+use sqlparser::ast::{ObjectType, Statement};
 
-use self::context::QueryContext;
-use super::{parser::parse_sql, planner::binder::Binder, runtime::execute_plan};
-use crate::core::{SQLError, Tuple};
+use self::{
+    activity::SessionState,
+    context::QueryContext,
+    result::{QueryResult, ResultColumn},
+    stats::QueryStats,
+};
+use super::{
+    expression::type_check::type_check,
+    parser::{parse_sql, SQLStatement},
+    planner::{
+        bind_context::BindContext, binder::Binder, index::source_column, physical::plan_physical,
+        scalar::bind_scalar, scope::Scope, Plan,
+    },
+    runtime::{
+        builder::{ExecutorBuilder, Schema},
+        Cursor,
+    },
+};
+use crate::{
+    catalog::DatabaseCatalog,
+    core::{sqlstate, Datum, ErrorKind, IntWidth, SQLError, Tuple, Type},
+    util::metrics::METRICS,
+};
 
 /// Kind of SQL statement, used for Postgres protocol
+#[derive(Clone, Copy)]
 pub enum SQLKind {
     Query,
     Execute,
+    /// `UPDATE` statement, the result contains the number of affected rows.
+    Update,
+    /// `DELETE` statement, the result contains the number of affected rows.
+    Delete,
+    /// `COPY` statement, the result contains the number of copied rows.
+    Copy,
 }
 
-pub struct QueryResult {
-    pub fields: Vec<FieldInfo>,
-    pub data: Vec<Tuple>,
-    pub kind: SQLKind,
+/// A prepared statement, parameters are bound on execution.
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    pub statement: SQLStatement,
+    /// Types of parameters, `$1` is the first one.
+    pub parameter_types: Vec<Type>,
+    /// Columns of the result.
+    pub columns: Vec<ResultColumn>,
+    /// Version of catalog the statement is prepared with.
+    pub catalog_version: u64,
 }
 
 pub struct Session {
     ctx: QueryContext,
+    /// Prepared statements created with `PREPARE`
+    prepared_statements: HashMap<String, PreparedStatement>,
+    /// Time of parsing the last statements by `parse`, which is counted in
+    /// the stats of the next statement executed.
+    parse_time: Duration,
+    /// Identifier of the session in `pg_stat_activity`.
+    pid: i64,
 }
 
 impl Session {
     pub fn new(ctx: QueryContext) -> Self {
-        Self { ctx }
+        let pid = ctx.activity.register(ctx.interrupts.clone());
+        Self {
+            ctx,
+            prepared_statements: HashMap::new(),
+            parse_time: Duration::ZERO,
+            pid,
+        }
+    }
+
+    pub fn pid(&self) -> i64 {
+        self.pid
+    }
+
+    /// The context of session, e.g. to inspect the catalog.
+    pub fn context(&self) -> &QueryContext {
+        &self.ctx
+    }
+
+    /// Initialize the session with startup parameters of the connection.
+    /// The database is chosen by `database`, and the current schema from
+    /// `search_path` in `options`, the startup fails if either doesn't exist.
+    pub fn apply_startup_parameters(
+        &mut self,
+        parameters: &HashMap<String, String>,
+    ) -> Result<(), SQLError> {
+        // The catalog is locked until the session is registered with its
+        // database, so the database can't be dropped meanwhile
+        let catalog_lock = self.ctx.catalog.clone();
+        let catalog = catalog_lock.read().unwrap();
+        if let Some(database) = parameters.get("database") {
+            if !catalog.exists_database(database) {
+                return Err(SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("database \"{}\" does not exist", database),
+                )
+                .with_code(sqlstate::INVALID_CATALOG_NAME));
+            }
+            self.ctx.current_database = database.clone();
+        }
+        let search_path = parameters
+            .get("options")
+            .and_then(|options| parse_options(options).remove("search_path"))
+            .and_then(|search_path| {
+                search_path
+                    .split(',')
+                    .next()
+                    .map(|schema| schema.trim().to_string())
+            });
+        if let Some(schema) = search_path {
+            if !catalog.exists_schema(&self.ctx.current_database, &schema)? {
+                return Err(SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("schema \"{}\" does not exist", schema),
+                )
+                .with_code(sqlstate::INVALID_SCHEMA_NAME));
+            }
+            self.ctx.current_schema = schema;
+        }
+
+        let application_name = parameters.get("application_name").cloned();
+        info!(
+            "Session started, application: {}, database: {}, schema: {}",
+            application_name.as_deref().unwrap_or("unknown"),
+            self.ctx.current_database,
+            self.ctx.current_schema
+        );
+        if let Some(application_name) = application_name {
+            // The parameter is always settable
+            let _ = self
+                .ctx
+                .settings
+                .set("application_name", Some(application_name));
+        }
+        let user = parameters.get("user").cloned();
+        let database = self.ctx.current_database.clone();
+        self.ctx.activity.update(self.pid, |activity| {
+            activity.user = user;
+            activity.database = database;
+        });
+        drop(catalog);
+        self.set_state(SessionState::Idle, None);
+        Ok(())
+    }
+
+    /// Report the state of the session to `pg_stat_activity`, with the
+    /// statement starting to execute if it's active.
+    fn set_state(&self, state: SessionState, statement: Option<&SQLStatement>) {
+        let application_name = self
+            .ctx
+            .settings
+            .get("application_name")
+            .unwrap_or_default();
+        self.ctx.activity.update(self.pid, |activity| {
+            let now = SystemTime::now();
+            activity.application_name = application_name;
+            activity.state = state;
+            activity.state_change = now;
+            if let Some(statement) = statement {
+                activity.query = statement.to_string();
+                activity.query_start = Some(now);
+            }
+        });
+    }
+
+    /// Command of the statement reported in the command tag, `EXECUTE`
+    /// reports the command of the prepared statement.
+    pub fn command(&self, statement: &SQLStatement) -> &'static str {
+        match statement {
+            SQLStatement::Sql(Statement::Execute { name, .. }) => self
+                .prepared_statements
+                .get(&name.to_string())
+                .map_or("EXECUTE", |prepared| statement_command(&prepared.statement)),
+            statement => statement_command(statement),
+        }
+    }
+
+    /// Parse the SQL text, the time taken is reported in the stats of the
+    /// next statement executed.
+    pub fn parse(&mut self, sql: &str) -> Result<Vec<SQLStatement>, SQLError> {
+        let start = Instant::now();
+        let statements = parse_sql(sql)?;
+        self.parse_time = start.elapsed();
+
+        Ok(statements)
+    }
+
+    pub fn execute(&mut self, statement: &SQLStatement) -> Result<QueryResult, SQLError> {
+        info!("Executing SQL: {}", statement);
+
+        match statement {
+            SQLStatement::Sql(Statement::Prepare {
+                name,
+                data_types,
+                statement,
+            }) => {
+                let declared_types = data_types
+                    .iter()
+                    .map(Type::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let statement = SQLStatement::from(statement.as_ref().clone());
+                let prepared = self.prepare(&statement, &declared_types)?;
+                self.prepared_statements.insert(name.to_string(), prepared);
+
+                Ok(QueryResult::empty())
+            }
+            SQLStatement::Sql(Statement::Execute { name, parameters }) => {
+                let prepared = self
+                    .prepared_statements
+                    .get(&name.to_string())
+                    .cloned()
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::CatalogError,
+                            format!("prepared statement \"{}\" does not exist", name),
+                        )
+                        .with_code(sqlstate::INVALID_SQL_STATEMENT_NAME)
+                    })?;
+                let prepared = self.revalidate(&name.to_string(), prepared)?;
+                if parameters.len() != prepared.parameter_types.len() {
+                    return Err(SQLError::new(
+                        ErrorKind::PlannerError,
+                        format!(
+                            "wrong number of parameters for prepared statement \"{}\"",
+                            name
+                        ),
+                    )
+                    .with_code(sqlstate::SYNTAX_ERROR)
+                    .with_detail(format!(
+                        "Expected {} parameters but got {}.",
+                        prepared.parameter_types.len(),
+                        parameters.len()
+                    )));
+                }
+
+                // Parameters should be constant expressions
+                let values = parameters
+                    .iter()
+                    .zip(prepared.parameter_types.iter())
+                    .map(|(expr, typ)| {
+                        let scalar = bind_scalar(
+                            &mut BindContext { scopes: vec![] },
+                            &Scope::default(),
+                            expr,
+                        )?;
+                        let value =
+                            type_check(&Schema::default(), &scalar)?.eval(&Tuple::default())?;
+                        cast_parameter(&value, typ)
+                    })
+                    .collect::<Result<Vec<_>, SQLError>>()?;
+
+                self.execute_statement(&prepared.statement, &values)
+            }
+            SQLStatement::Sql(Statement::Deallocate { name, .. }) => {
+                self.prepared_statements
+                    .remove(&name.to_string())
+                    .ok_or_else(|| {
+                        SQLError::new(
+                            ErrorKind::CatalogError,
+                            format!("prepared statement \"{}\" does not exist", name),
+                        )
+                        .with_code(sqlstate::INVALID_SQL_STATEMENT_NAME)
+                    })?;
+
+                Ok(QueryResult::empty())
+            }
+            _ => self.execute_statement(statement, &[]),
+        }
+    }
+
+    /// Execute the statement like `execute`, and return the result as Arrow
+    /// record batches, whose schema is mapped from the column types.
+    pub fn execute_arrow(
+        &mut self,
+        statement: &SQLStatement,
+    ) -> Result<Vec<RecordBatch>, SQLError> {
+        self.execute(statement)?.to_record_batches()
+    }
+
+    /// Bind and optimize the statement without executing it.
+    pub fn plan(&mut self, statement: &SQLStatement) -> Result<Plan, SQLError> {
+        let mut binder = Binder::new(&mut self.ctx);
+        let (plan, _) = binder.bind_statement(statement)?;
+
+        Ok(plan)
+    }
+
+    /// Prepare the statement, the types of parameters are inferred from
+    /// the context if they are not declared.
+    pub fn prepare(
+        &mut self,
+        statement: &SQLStatement,
+        declared_types: &[Type],
+    ) -> Result<PreparedStatement, SQLError> {
+        let mut binder = Binder::new(&mut self.ctx);
+        let (plan, scope) = binder.bind_statement(statement)?;
+
+        let physical_plan = plan_physical(
+            &plan,
+            &self.ctx.catalog().database(&self.ctx.current_database),
+        );
+        let builder = ExecutorBuilder::new(&self.ctx);
+        let (_, schema) = builder.build(&physical_plan)?;
+        let inferred_types = builder.parameter_types();
+
+        // Parameters of unknown types are treated as strings
+        let parameter_types = (0..inferred_types.len().max(declared_types.len()))
+            .map(|i| {
+                declared_types
+                    .get(i)
+                    .cloned()
+                    .or_else(|| inferred_types[i].clone())
+                    .unwrap_or(Type::String)
+            })
+            .collect();
+
+        Ok(PreparedStatement {
+            statement: statement.clone(),
+            parameter_types,
+            columns: result_columns(
+                &scope,
+                &schema,
+                &plan,
+                &self.ctx.catalog().database(&self.ctx.current_database),
+            ),
+            catalog_version: self.ctx.catalog_version,
+        })
     }
 
-    pub fn execute(&mut self, sql_text: &str) -> Result<QueryResult, SQLError> {
-        info!("Executing SQL: {}", sql_text);
+    /// Prepare the statement again if the catalog has changed since it was
+    /// prepared, the parameters keep their types, while the columns of result
+    /// can't be changed.
+    fn revalidate(
+        &mut self,
+        name: &str,
+        prepared: PreparedStatement,
+    ) -> Result<PreparedStatement, SQLError> {
+        if prepared.catalog_version == self.ctx.catalog().version {
+            return Ok(prepared);
+        }
+        let revalidated = self.prepare(&prepared.statement, &prepared.parameter_types)?;
+        if revalidated.columns != prepared.columns {
+            return Err(SQLError::new(
+                ErrorKind::PlannerError,
+                "cached plan must not change result type",
+            )
+            .with_code(sqlstate::FEATURE_NOT_SUPPORTED));
+        }
+        self.prepared_statements
+            .insert(name.to_string(), revalidated.clone());
+
+        Ok(revalidated)
+    }
 
-        let statement = parse_sql(sql_text)?;
+    /// Execute the statement with values of parameters.
+    pub fn execute_statement(
+        &mut self,
+        statement: &SQLStatement,
+        parameters: &[Datum],
+    ) -> Result<QueryResult, SQLError> {
+        let mut cursor = self.open_cursor(statement, parameters)?;
+
+        self.fetch(&mut cursor, 0)
+    }
+
+    /// Start executing the statement with values of parameters, the results
+    /// can be fetched from the returned cursor.
+    pub fn open_cursor(
+        &mut self,
+        statement: &SQLStatement,
+        parameters: &[Datum],
+    ) -> Result<QueryCursor, SQLError> {
+        self.ctx.interrupts.clear_cancel();
+        self.ctx.interrupts.check()?;
+        self.set_state(SessionState::Active, Some(statement));
+        let cursor = self.open_cursor_inner(statement, parameters);
+        if cursor.is_err() {
+            self.set_state(SessionState::Idle, None);
+        }
+
+        cursor
+    }
 
+    fn open_cursor_inner(
+        &mut self,
+        statement: &SQLStatement,
+        parameters: &[Datum],
+    ) -> Result<QueryCursor, SQLError> {
         let kind = match statement {
-            Statement::Query(_) => SQLKind::Query,
-            _ => SQLKind::Execute,
+            SQLStatement::Sql(statement) => sql_kind(statement),
+            SQLStatement::Explain { .. } => SQLKind::Query,
+            SQLStatement::Vacuum { .. } | SQLStatement::DropDatabase { .. } => SQLKind::Execute,
         };
 
+        let parse = std::mem::take(&mut self.parse_time);
+        let start = Instant::now();
         let mut binder = Binder::new(&mut self.ctx);
-        let (plan, scope) = binder.bind_statement(&statement)?;
-
-        let result = execute_plan(&mut self.ctx, &plan)?;
-
-        let field_infos = scope
-            .variables
-            .iter()
-            .map(|variable| {
-                FieldInfo::new(
-                    variable.name.to_string(),
-                    None,
-                    None,
-                    pgwire::api::Type::VARCHAR,
-                    pgwire::api::results::FieldFormat::Text,
-                )
-            })
-            .collect::<Vec<_>>();
+        let (mut plan, scope) = binder.bind_statement(statement)?;
+        plan.bind_parameters(parameters)?;
+        let bind = start.elapsed();
+
+        let start = Instant::now();
+        let cursor = Cursor::open(&mut self.ctx, &plan)?;
+        let stats = QueryStats {
+            sql: statement.to_string(),
+            parameters: parameters.to_vec(),
+            plan: cursor.plan_summary.clone(),
+            parse,
+            bind,
+            execute: start.elapsed(),
+        };
 
-        let result = QueryResult {
-            fields: field_infos,
-            data: result,
+        Ok(QueryCursor {
+            columns: result_columns(
+                &scope,
+                &cursor.schema,
+                &plan,
+                &self.ctx.catalog().database(&self.ctx.current_database),
+            ),
+            cursor,
             kind,
+            stats,
+        })
+    }
+
+    /// Fetch at most `max_rows` rows from the cursor, or all the remaining
+    /// rows if `max_rows` is 0. The statement is logged once it's finished
+    /// if it's slow, a finished cursor returns no more rows.
+    pub fn fetch(
+        &mut self,
+        cursor: &mut QueryCursor,
+        max_rows: usize,
+    ) -> Result<QueryResult, SQLError> {
+        if cursor.is_finished() {
+            return Ok(QueryResult {
+                columns: cursor.columns.clone(),
+                data: vec![],
+                kind: cursor.kind,
+            });
+        }
+
+        let start = Instant::now();
+        let data = match cursor.cursor.fetch(&mut self.ctx, max_rows) {
+            Ok(data) => data,
+            Err(e) => {
+                self.set_state(SessionState::Idle, None);
+                return Err(e);
+            }
         };
+        cursor.stats.execute += start.elapsed();
+        if let SQLKind::Query = cursor.kind {
+            METRICS.record_rows(data.len());
+        }
+        if cursor.is_finished() {
+            self.set_state(SessionState::Idle, None);
+            METRICS.record_query(cursor.kind, cursor.stats.total());
+            cursor
+                .stats
+                .log_if_slow(self.ctx.settings.log_min_duration_statement());
+        }
+
+        Ok(QueryResult {
+            columns: cursor.columns.clone(),
+            data,
+            kind: cursor.kind,
+        })
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.ctx.activity.unregister(self.pid);
+    }
+}
+
+/// A statement being executed, see `Session::open_cursor`.
+pub struct QueryCursor {
+    cursor: Cursor,
+    columns: Vec<ResultColumn>,
+    kind: SQLKind,
+    stats: QueryStats,
+}
+
+impl QueryCursor {
+    /// Check if all the rows have been fetched.
+    pub fn is_finished(&self) -> bool {
+        self.cursor.is_finished()
+    }
+}
+
+/// Command of the statement reported in the command tag, e.g. `CREATE TABLE`,
+/// see `Session::command` for `EXECUTE`.
+pub fn statement_command(statement: &SQLStatement) -> &'static str {
+    let statement = match statement {
+        SQLStatement::Sql(statement) => statement,
+        SQLStatement::Explain { .. } => return "EXPLAIN",
+        SQLStatement::Vacuum { .. } => return "VACUUM",
+        SQLStatement::DropDatabase { .. } => return "DROP DATABASE",
+    };
+
+    match statement {
+        Statement::Query(_) => "SELECT",
+        Statement::Insert { .. } => "INSERT",
+        Statement::Update { .. } => "UPDATE",
+        Statement::Delete { .. } => "DELETE",
+        Statement::Copy { .. } => "COPY",
+        // `CREATE TABLE ... AS` reports the number of rows inserted
+        Statement::CreateTable { query: Some(_), .. } => "SELECT",
+        Statement::CreateTable { .. } => "CREATE TABLE",
+        Statement::CreateIndex { .. } => "CREATE INDEX",
+        Statement::CreateSchema { .. } => "CREATE SCHEMA",
+        Statement::CreateDatabase { .. } => "CREATE DATABASE",
+        Statement::CreateSequence { .. } => "CREATE SEQUENCE",
+        Statement::CreateFunction { .. } => "CREATE FUNCTION",
+        Statement::CreateView { .. } => "CREATE VIEW",
+        Statement::AlterTable { .. } => "ALTER TABLE",
+        Statement::AlterIndex { .. } => "ALTER INDEX",
+        Statement::Drop { object_type, .. } => match object_type {
+            ObjectType::Table => "DROP TABLE",
+            ObjectType::View => "DROP VIEW",
+            ObjectType::Index => "DROP INDEX",
+            ObjectType::Schema => "DROP SCHEMA",
+            ObjectType::Sequence => "DROP SEQUENCE",
+            ObjectType::Role => "DROP ROLE",
+            ObjectType::Stage => "DROP STAGE",
+        },
+        Statement::DropFunction { .. } => "DROP FUNCTION",
+        Statement::Analyze { .. } => "ANALYZE",
+        Statement::Truncate { .. } => "TRUNCATE TABLE",
+        Statement::SetVariable { .. } | Statement::SetTimeZone { .. } | Statement::Use { .. } => {
+            "SET"
+        }
+        Statement::ShowVariable { .. } | Statement::ShowTables { .. } => "SHOW",
+        Statement::Prepare { .. } => "PREPARE",
+        Statement::Execute { .. } => "EXECUTE",
+        Statement::Deallocate { .. } => "DEALLOCATE",
+        Statement::StartTransaction { .. } => "BEGIN",
+        Statement::Commit { .. } => "COMMIT",
+        Statement::Rollback { .. } => "ROLLBACK",
+        _ => "UNKNOWN",
+    }
+}
 
-        Ok(result)
+/// Kind of the statement parsed by sqlparser.
+fn sql_kind(statement: &Statement) -> SQLKind {
+    match statement {
+        Statement::Query(_)
+        | Statement::ShowVariable { .. }
+        | Statement::Insert {
+            returning: Some(_), ..
+        }
+        | Statement::Update {
+            returning: Some(_), ..
+        }
+        | Statement::Delete {
+            returning: Some(_), ..
+        } => SQLKind::Query,
+        Statement::Update { .. } => SQLKind::Update,
+        Statement::Delete { .. } => SQLKind::Delete,
+        Statement::Copy { .. } => SQLKind::Copy,
+        _ => SQLKind::Execute,
     }
 }
+
+/// Columns of the result, named by the variables in scope. The integer
+/// columns passed through from tables have the declared widths.
+fn result_columns(
+    scope: &Scope,
+    schema: &Schema,
+    plan: &Plan,
+    catalog: &DatabaseCatalog,
+) -> Vec<ResultColumn> {
+    scope
+        .variables
+        .iter()
+        .enumerate()
+        .map(|(i, variable)| ResultColumn {
+            name: variable.name.to_string(),
+            typ: schema.column_types.get(i).cloned().unwrap_or(Type::String),
+            int_width: source_column(i, plan, catalog)
+                .and_then(|(table_def, column)| table_def.columns[column].int_width),
+        })
+        .collect()
+}
+
+/// Map the data type to the type of Postgres wire protocol.
+pub fn to_pg_type(typ: &Type) -> pgwire::api::Type {
+    match typ {
+        Type::Int => pgwire::api::Type::INT8,
+        Type::Float => pgwire::api::Type::FLOAT8,
+        Type::String => pgwire::api::Type::VARCHAR,
+        Type::Boolean => pgwire::api::Type::BOOL,
+        Type::Uuid => pgwire::api::Type::UUID,
+        Type::Json => pgwire::api::Type::JSON,
+        Type::Bytea => pgwire::api::Type::BYTEA,
+        Type::Array(elem_type) => match to_pg_type(elem_type) {
+            pgwire::api::Type::INT8 => pgwire::api::Type::INT8_ARRAY,
+            pgwire::api::Type::FLOAT8 => pgwire::api::Type::FLOAT8_ARRAY,
+            pgwire::api::Type::VARCHAR => pgwire::api::Type::VARCHAR_ARRAY,
+            pgwire::api::Type::BOOL => pgwire::api::Type::BOOL_ARRAY,
+            pgwire::api::Type::UUID => pgwire::api::Type::UUID_ARRAY,
+            pgwire::api::Type::JSON => pgwire::api::Type::JSON_ARRAY,
+            pgwire::api::Type::BYTEA => pgwire::api::Type::BYTEA_ARRAY,
+            _ => pgwire::api::Type::UNKNOWN,
+        },
+        Type::Null | Type::Any | Type::Never => pgwire::api::Type::UNKNOWN,
+    }
+}
+
+/// Map the type of column to the type of Postgres wire protocol, with the
+/// declared width of integers.
+pub fn to_pg_column_type(typ: &Type, int_width: Option<IntWidth>) -> pgwire::api::Type {
+    match (typ, int_width) {
+        (Type::Int, Some(IntWidth::Int2)) => pgwire::api::Type::INT2,
+        (Type::Int, Some(IntWidth::Int4)) => pgwire::api::Type::INT4,
+        (Type::Array(elem_type), _) => match to_pg_column_type(elem_type, int_width) {
+            pgwire::api::Type::INT2 => pgwire::api::Type::INT2_ARRAY,
+            pgwire::api::Type::INT4 => pgwire::api::Type::INT4_ARRAY,
+            _ => to_pg_type(typ),
+        },
+        _ => to_pg_type(typ),
+    }
+}
+
+/// Map the type of Postgres wire protocol to the data type.
+pub fn from_pg_type(typ: &pgwire::api::Type) -> Result<Type, SQLError> {
+    if let Kind::Array(elem_type) = typ.kind() {
+        return Ok(Type::Array(Box::new(from_pg_type(elem_type)?)));
+    }
+
+    match *typ {
+        pgwire::api::Type::INT2 | pgwire::api::Type::INT4 | pgwire::api::Type::INT8 => {
+            Ok(Type::Int)
+        }
+        pgwire::api::Type::FLOAT4 | pgwire::api::Type::FLOAT8 => Ok(Type::Float),
+        pgwire::api::Type::TEXT | pgwire::api::Type::VARCHAR | pgwire::api::Type::BPCHAR => {
+            Ok(Type::String)
+        }
+        pgwire::api::Type::BOOL => Ok(Type::Boolean),
+        pgwire::api::Type::UUID => Ok(Type::Uuid),
+        pgwire::api::Type::BYTEA => Ok(Type::Bytea),
+        pgwire::api::Type::JSON | pgwire::api::Type::JSONB => Ok(Type::Json),
+        _ => Err(
+            SQLError::new(ErrorKind::TypeError, format!("Unknown data type: {}", typ))
+                .with_code(sqlstate::UNDEFINED_OBJECT),
+        ),
+    }
+}
+
+/// Cast the value bound to a parameter to its type, fails instead of binding
+/// NULL if the value can't be converted.
+pub fn cast_parameter(value: &Datum, typ: &Type) -> Result<Datum, SQLError> {
+    let datum = value.cast(typ);
+    if datum.is_null() && !value.is_null() {
+        return Err(SQLError::new(
+            ErrorKind::RuntimeError,
+            format!(
+                "invalid input syntax for type {}: \"{}\"",
+                to_pg_type(typ).name(),
+                value
+            ),
+        )
+        .with_code(sqlstate::INVALID_TEXT_REPRESENTATION));
+    }
+
+    Ok(datum)
+}
+
+/// Parse the `options` startup parameter, which is in form of command-line
+/// arguments, e.g. `-c search_path=foo --application_name=bar`.
+fn parse_options(options: &str) -> HashMap<String, String> {
+    let mut result = HashMap::new();
+    let mut args = options.split_whitespace();
+    while let Some(arg) = args.next() {
+        let setting = if arg == "-c" {
+            args.next()
+        } else {
+            arg.strip_prefix("-c").or_else(|| arg.strip_prefix("--"))
+        };
+        if let Some((name, value)) = setting.and_then(|setting| setting.split_once('=')) {
+            result.insert(name.replace('-', "_"), value.to_string());
+        }
+    }
+
+    result
+}
@@ -0,0 +1,219 @@
+use arrow_array::RecordBatch;
+use uuid::Uuid;
+
+use super::{to_pg_type, SQLKind};
+use crate::core::{
+    sqlstate, to_record_batch, Datum, ErrorKind, IntWidth, SQLError, Tuple, Type, CHUNK_SIZE,
+};
+
+/// A column of the result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultColumn {
+    pub name: String,
+    pub typ: Type,
+    /// Declared width of integer column, `None` for 64-bit integers.
+    pub int_width: Option<IntWidth>,
+}
+
+pub struct QueryResult {
+    pub columns: Vec<ResultColumn>,
+    pub data: Vec<Tuple>,
+    pub kind: SQLKind,
+}
+
+impl QueryResult {
+    /// Result of statements that return nothing.
+    pub fn empty() -> Self {
+        Self {
+            columns: vec![],
+            data: vec![],
+            kind: SQLKind::Execute,
+        }
+    }
+
+    /// Number of affected rows reported by DML statements.
+    pub fn affected_rows(&self) -> usize {
+        self.data
+            .first()
+            .and_then(|tuple| tuple.get(0))
+            .and_then(|datum| datum.into_int().ok())
+            .unwrap_or_default() as usize
+    }
+
+    /// Convert the rows to Arrow record batches of up to `CHUNK_SIZE` rows,
+    /// there is always at least one batch, so that the schema is known
+    /// without any row.
+    pub fn to_record_batches(&self) -> Result<Vec<RecordBatch>, SQLError> {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| (column.name.clone(), column.typ.clone(), column.int_width))
+            .collect::<Vec<_>>();
+        if self.data.is_empty() {
+            return Ok(vec![to_record_batch(&columns, &[])?]);
+        }
+
+        self.data
+            .chunks(CHUNK_SIZE)
+            .map(|rows| to_record_batch(&columns, rows))
+            .collect()
+    }
+
+    /// Rows of the result, whose values can be read with typed accessors.
+    pub fn rows(&self) -> impl Iterator<Item = Row<'_>> {
+        self.data.iter().map(|tuple| Row {
+            columns: &self.columns,
+            tuple,
+        })
+    }
+}
+
+/// A row of the result, see `QueryResult::rows`.
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    columns: &'a [ResultColumn],
+    tuple: &'a Tuple,
+}
+
+impl Row<'_> {
+    /// Get the value of the column converted to `T`, the column is either an
+    /// index or a name.
+    pub fn get<T: FromDatum>(&self, column: impl ColumnIndex) -> Result<T, SQLError> {
+        let index = column.index(self.columns)?;
+        T::from_datum(&self.tuple.values[index])
+    }
+
+    /// The raw values of the row.
+    pub fn values(&self) -> &[Datum] {
+        &self.tuple.values
+    }
+}
+
+/// Index of a column in a row, by position or by name.
+pub trait ColumnIndex {
+    fn index(&self, columns: &[ResultColumn]) -> Result<usize, SQLError>;
+}
+
+impl ColumnIndex for usize {
+    fn index(&self, columns: &[ResultColumn]) -> Result<usize, SQLError> {
+        if *self < columns.len() {
+            return Ok(*self);
+        }
+        Err(SQLError::new(
+            ErrorKind::RuntimeError,
+            format!("column index {} is out of range", self),
+        )
+        .with_code(sqlstate::INVALID_PARAMETER_VALUE))
+    }
+}
+
+impl ColumnIndex for &str {
+    fn index(&self, columns: &[ResultColumn]) -> Result<usize, SQLError> {
+        columns
+            .iter()
+            .position(|column| column.name == *self)
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::RuntimeError,
+                    format!("column \"{}\" does not exist", self),
+                )
+                .with_code(sqlstate::UNDEFINED_COLUMN)
+            })
+    }
+}
+
+/// Conversion from a datum to a Rust type, NULL can only be converted to
+/// `Option`.
+pub trait FromDatum: Sized {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError>;
+}
+
+fn mismatch(datum: &Datum, type_name: &str) -> SQLError {
+    match datum {
+        Datum::Null => SQLError::new(
+            ErrorKind::TypeError,
+            format!("cannot convert NULL to {}", type_name),
+        )
+        .with_code(sqlstate::NULL_VALUE_NOT_ALLOWED)
+        .with_hint("Use Option to read nullable columns."),
+        _ => SQLError::new(
+            ErrorKind::TypeError,
+            format!(
+                "cannot convert {} to {}",
+                to_pg_type(&datum.typ()).name(),
+                type_name
+            ),
+        )
+        .with_code(sqlstate::DATATYPE_MISMATCH),
+    }
+}
+
+impl FromDatum for Datum {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        Ok(datum.clone())
+    }
+}
+
+impl<T: FromDatum> FromDatum for Option<T> {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        match datum {
+            Datum::Null => Ok(None),
+            datum => T::from_datum(datum).map(Some),
+        }
+    }
+}
+
+impl FromDatum for i64 {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        match datum {
+            Datum::Int(v) => Ok(*v),
+            datum => Err(mismatch(datum, "i64")),
+        }
+    }
+}
+
+impl FromDatum for f64 {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        match datum {
+            Datum::Float(v) => Ok(*v),
+            Datum::Int(v) => Ok(*v as f64),
+            datum => Err(mismatch(datum, "f64")),
+        }
+    }
+}
+
+impl FromDatum for bool {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        match datum {
+            Datum::Boolean(v) => Ok(*v),
+            datum => Err(mismatch(datum, "bool")),
+        }
+    }
+}
+
+impl FromDatum for String {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        match datum {
+            Datum::String(v) => Ok(v.to_string()),
+            datum => Err(mismatch(datum, "String")),
+        }
+    }
+}
+
+impl FromDatum for Uuid {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        match datum {
+            Datum::Uuid(v) => Ok(*v),
+            datum => Err(mismatch(datum, "Uuid")),
+        }
+    }
+}
+
+impl<T: FromDatum> FromDatum for Vec<T> {
+    fn from_datum(datum: &Datum) -> Result<Self, SQLError> {
+        match datum {
+            Datum::Array(elements) => elements.iter().map(T::from_datum).collect(),
+            datum => Err(mismatch(datum, "Vec")),
+        }
+    }
+}
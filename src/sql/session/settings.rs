@@ -0,0 +1,155 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::core::{sqlstate, ErrorKind, SQLError};
+
+/// Run-time parameters with their default values, most of the values are
+/// only stored and reported.
+const PARAMETERS: &[(&str, &str)] = &[
+    ("application_name", ""),
+    ("bytea_output", "hex"),
+    ("client_encoding", "UTF8"),
+    ("client_min_messages", "notice"),
+    ("DateStyle", "ISO, MDY"),
+    ("default_transaction_isolation", "read committed"),
+    ("default_transaction_read_only", "off"),
+    ("extra_float_digits", "1"),
+    ("idle_in_transaction_session_timeout", "0"),
+    ("IntervalStyle", "postgres"),
+    ("lock_timeout", "0"),
+    // Statements running longer are logged with their timing, in
+    // milliseconds unless a unit is given, `-1` disables the logging
+    ("log_min_duration_statement", "-1"),
+    ("statement_timeout", "0"),
+    ("TimeZone", "UTC"),
+    ("transaction_isolation", "read committed"),
+    ("transaction_read_only", "off"),
+];
+
+/// Parameters that can only be shown.
+const READ_ONLY_PARAMETERS: &[(&str, &str)] = &[
+    ("integer_datetimes", "on"),
+    ("is_superuser", "on"),
+    ("max_identifier_length", "63"),
+    ("server_encoding", "UTF8"),
+    ("server_version", "14.0"),
+    ("server_version_num", "140000"),
+    ("standard_conforming_strings", "on"),
+];
+
+/// Run-time parameters of a session, changed by `SET` and read by `SHOW`.
+/// Names are case-insensitive, and custom parameters must be qualified
+/// like `myapp.option`.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Lowercase name -> value, only the changed parameters are stored.
+    values: HashMap<String, String>,
+}
+
+impl Settings {
+    /// Value of the parameter, `None` if it's unknown.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        self.values
+            .get(&name)
+            .cloned()
+            .or_else(|| find_parameter(&name).map(|(_, value)| value.to_string()))
+    }
+
+    /// Set the parameter, or reset it to the default value if `value` is `None`.
+    pub fn set(&mut self, name: &str, value: Option<String>) -> Result<(), SQLError> {
+        let name = name.to_lowercase();
+        check_settable(&name)?;
+        if let (Some(value), "log_min_duration_statement") = (&value, name.as_str()) {
+            parse_duration(value).ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::PlannerError,
+                    format!("invalid value for parameter \"{}\": \"{}\"", name, value),
+                )
+                .with_code(sqlstate::INVALID_PARAMETER_VALUE)
+                .with_hint("Valid units for this parameter are \"ms\", \"s\", \"min\".")
+            })?;
+        }
+        match value {
+            Some(value) => self.values.insert(name, value),
+            None => self.values.remove(&name),
+        };
+
+        Ok(())
+    }
+
+    /// Threshold of the slow query log, `None` if it's disabled.
+    pub fn log_min_duration_statement(&self) -> Option<Duration> {
+        parse_duration(&self.get("log_min_duration_statement")?).flatten()
+    }
+
+    /// All the known parameters and their values, sorted by name.
+    pub fn all(&self) -> Vec<(String, String)> {
+        let mut all = PARAMETERS
+            .iter()
+            .chain(READ_ONLY_PARAMETERS.iter())
+            .map(|(name, _)| (name.to_string(), self.get(name).unwrap()))
+            .chain(
+                self.values
+                    .iter()
+                    .filter(|(name, _)| name.contains('.'))
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            )
+            .collect::<Vec<_>>();
+        all.sort_by_key(|(name, _)| name.to_lowercase());
+
+        all
+    }
+}
+
+/// Parse the duration like `100`, `100ms`, `2s` or `1min`, a negative
+/// duration is parsed as `Some(None)`.
+fn parse_duration(value: &str) -> Option<Option<Duration>> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(
+        value
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(value.len()),
+    );
+    let number = number.trim().parse::<i64>().ok()?;
+    let millis = match unit {
+        "" | "ms" => number,
+        "s" => number.checked_mul(1000)?,
+        "min" => number.checked_mul(60_000)?,
+        _ => return None,
+    };
+    Some((millis >= 0).then(|| Duration::from_millis(millis as u64)))
+}
+
+fn find_parameter(name: &str) -> Option<&'static (&'static str, &'static str)> {
+    PARAMETERS
+        .iter()
+        .chain(READ_ONLY_PARAMETERS.iter())
+        .find(|(parameter, _)| parameter.eq_ignore_ascii_case(name))
+}
+
+/// Check if the parameter can be set, the name should be lowercase.
+pub fn check_settable(name: &str) -> Result<(), SQLError> {
+    if READ_ONLY_PARAMETERS
+        .iter()
+        .any(|(parameter, _)| parameter.eq_ignore_ascii_case(name))
+    {
+        return Err(SQLError::new(
+            ErrorKind::PlannerError,
+            format!("parameter \"{}\" cannot be changed", name),
+        )
+        .with_code(sqlstate::CANT_CHANGE_RUNTIME_PARAM));
+    }
+    if find_parameter(name).is_none() && !name.contains('.') {
+        return Err(unrecognized_parameter(name));
+    }
+
+    Ok(())
+}
+
+pub fn unrecognized_parameter(name: &str) -> SQLError {
+    SQLError::new(
+        ErrorKind::PlannerError,
+        format!("unrecognized configuration parameter \"{}\"", name),
+    )
+    .with_code(sqlstate::UNDEFINED_OBJECT)
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use log::warn;
+
+use crate::core::Datum;
+
+/// Timing of a statement, the ones running longer than
+/// `log_min_duration_statement` are logged as slow queries.
+#[derive(Debug, Clone, Default)]
+pub struct QueryStats {
+    pub sql: String,
+    pub parameters: Vec<Datum>,
+    /// Operators of the physical plan.
+    pub plan: String,
+    /// Time of parsing the statement, zero if it's parsed elsewhere, e.g.
+    /// by the extended query protocol.
+    pub parse: Duration,
+    /// Time of binding and optimizing the statement.
+    pub bind: Duration,
+    /// Time of building and running the executors.
+    pub execute: Duration,
+}
+
+impl QueryStats {
+    pub fn total(&self) -> Duration {
+        self.parse + self.bind + self.execute
+    }
+
+    /// Log the statement if it has run longer than the threshold.
+    pub fn log_if_slow(&self, threshold: Option<Duration>) {
+        match threshold {
+            Some(threshold) if self.total() >= threshold => {}
+            _ => return,
+        }
+        let parameters = if self.parameters.is_empty() {
+            String::new()
+        } else {
+            format!(
+                ", parameters: {}",
+                self.parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| format!("${} = {}", i + 1, quote_value(value)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        warn!(
+            "Slow query: {:.3} ms (parse: {:.3} ms, bind: {:.3} ms, execute: {:.3} ms), plan: {}{}, SQL: {}",
+            millis(self.total()),
+            millis(self.parse),
+            millis(self.bind),
+            millis(self.execute),
+            self.plan,
+            parameters,
+            self.sql
+        );
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Quote the value as a literal, so strings can be told from the others.
+fn quote_value(value: &Datum) -> String {
+    match value {
+        Datum::Null | Datum::Int(_) | Datum::Float(_) | Datum::Boolean(_) => value.to_string(),
+        value => format!("'{}'", value.to_string().replace('\'', "''")),
+    }
+}
@@ -0,0 +1,356 @@
+use std::{cmp::Ordering, ops::Bound};
+
+use super::relation::TupleId;
+use crate::core::Datum;
+
+/// Max number of entries in a node, a node is split when it's exceeded.
+const MAX_ENTRIES: usize = 64;
+
+pub type Key = Vec<Datum>;
+
+/// Entries are sorted by key, and then by location of the tuple, so duplicated
+/// keys are allowed and each entry is still unique.
+type Entry = (Key, TupleId);
+
+enum Node {
+    Leaf {
+        entries: Vec<Entry>,
+        /// The next leaf in order, for range scan.
+        next: Option<usize>,
+    },
+    Internal {
+        /// `separators[i]` is the smallest entry in `children[i + 1]`.
+        separators: Vec<Entry>,
+        children: Vec<usize>,
+    },
+}
+
+/// A B+ tree mapping keys to tuple locations. The nodes are stored in an arena
+/// and referenced by their indices.
+///
+/// Nodes are not merged on deletion, since the space is reclaimed by rebuilding.
+pub struct BTree {
+    nodes: Vec<Node>,
+    root: usize,
+}
+
+impl Default for BTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BTree {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::Leaf {
+                entries: vec![],
+                next: None,
+            }],
+            root: 0,
+        }
+    }
+
+    pub fn insert(&mut self, key: Key, tid: TupleId) {
+        let entry = (key, tid);
+        if let Some((separator, right)) = self.insert_into(self.root, entry) {
+            // Root is split, grow the tree by one level
+            let left = self.root;
+            self.nodes.push(Node::Internal {
+                separators: vec![separator],
+                children: vec![left, right],
+            });
+            self.root = self.nodes.len() - 1;
+        }
+    }
+
+    /// Remove the entry, returns false if it doesn't exist.
+    pub fn delete(&mut self, key: &Key, tid: TupleId) -> bool {
+        let entry = (key.clone(), tid);
+        let mut node = self.root;
+        loop {
+            match &mut self.nodes[node] {
+                Node::Internal {
+                    separators,
+                    children,
+                } => {
+                    node = children[separators.partition_point(|separator| *separator <= entry)];
+                }
+                Node::Leaf { entries, .. } => {
+                    return match entries.binary_search(&entry) {
+                        Ok(position) => {
+                            entries.remove(position);
+                            true
+                        }
+                        Err(_) => false,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Entries with keys in the range. The bounds are compared with the prefix of keys,
+    /// so a range can be specified with the leading columns only.
+    pub fn range<'a>(&'a self, lower: Bound<&'a [Datum]>, upper: Bound<&'a [Datum]>) -> Range<'a> {
+        // Find the leftmost leaf that may contain the lower bound
+        let mut node = self.root;
+        while let Node::Internal {
+            separators,
+            children,
+        } = &self.nodes[node]
+        {
+            let index = match lower {
+                Bound::Included(bound) | Bound::Excluded(bound) => separators
+                    .partition_point(|(key, _)| compare_prefix(key, bound) == Ordering::Less),
+                Bound::Unbounded => 0,
+            };
+            node = children[index];
+        }
+
+        Range {
+            tree: self,
+            leaf: Some(node),
+            position: 0,
+            lower,
+            upper,
+        }
+    }
+
+    /// Locations of the tuples with the key.
+    pub fn lookup<'a>(&'a self, key: &'a [Datum]) -> impl Iterator<Item = TupleId> + 'a {
+        self.range(Bound::Included(key), Bound::Included(key))
+            .map(|(_, tid)| tid)
+    }
+
+    /// Insert the entry into the subtree, returns the separator and the new node if
+    /// the node is split.
+    fn insert_into(&mut self, node: usize, entry: Entry) -> Option<(Entry, usize)> {
+        match &mut self.nodes[node] {
+            Node::Leaf { entries, .. } => {
+                let position = entries.partition_point(|e| *e < entry);
+                entries.insert(position, entry);
+            }
+            Node::Internal {
+                separators,
+                children,
+            } => {
+                let index = separators.partition_point(|separator| *separator <= entry);
+                let child = children[index];
+                let (separator, right) = self.insert_into(child, entry)?;
+                let Node::Internal {
+                    separators,
+                    children,
+                } = &mut self.nodes[node] else {
+                    unreachable!()
+                };
+                separators.insert(index, separator);
+                children.insert(index + 1, right);
+            }
+        }
+
+        self.split_if_full(node)
+    }
+
+    fn split_if_full(&mut self, node: usize) -> Option<(Entry, usize)> {
+        let new_node = self.nodes.len();
+        let (separator, right) = match &mut self.nodes[node] {
+            Node::Leaf { entries, next } if entries.len() > MAX_ENTRIES => {
+                let right_entries = entries.split_off(entries.len() / 2);
+                let separator = right_entries[0].clone();
+                let right = Node::Leaf {
+                    entries: right_entries,
+                    next: next.replace(new_node),
+                };
+                (separator, right)
+            }
+            Node::Internal {
+                separators,
+                children,
+            } if separators.len() > MAX_ENTRIES => {
+                let mut right_separators = separators.split_off(separators.len() / 2);
+                let right_children = children.split_off(separators.len() + 1);
+                // The middle separator is moved up to the parent
+                let separator = right_separators.remove(0);
+                let right = Node::Internal {
+                    separators: right_separators,
+                    children: right_children,
+                };
+                (separator, right)
+            }
+            _ => return None,
+        };
+
+        self.nodes.push(right);
+        Some((separator, new_node))
+    }
+}
+
+/// Compare the prefix of key with the bound.
+fn compare_prefix(key: &[Datum], bound: &[Datum]) -> Ordering {
+    key[..bound.len().min(key.len())].cmp(bound)
+}
+
+/// Iterator of entries in a key range, in ascending order.
+pub struct Range<'a> {
+    tree: &'a BTree,
+    leaf: Option<usize>,
+    position: usize,
+    lower: Bound<&'a [Datum]>,
+    upper: Bound<&'a [Datum]>,
+}
+
+impl<'a> Iterator for Range<'a> {
+    type Item = (&'a Key, TupleId);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Node::Leaf { entries, next } = &self.tree.nodes[self.leaf?] else {
+                unreachable!()
+            };
+            let Some((key, tid)) = entries.get(self.position) else {
+                self.leaf = *next;
+                self.position = 0;
+                continue;
+            };
+            self.position += 1;
+
+            let above_lower = match self.lower {
+                Bound::Included(bound) => compare_prefix(key, bound) != Ordering::Less,
+                Bound::Excluded(bound) => compare_prefix(key, bound) == Ordering::Greater,
+                Bound::Unbounded => true,
+            };
+            if !above_lower {
+                continue;
+            }
+
+            let below_upper = match self.upper {
+                Bound::Included(bound) => compare_prefix(key, bound) != Ordering::Greater,
+                Bound::Excluded(bound) => compare_prefix(key, bound) == Ordering::Less,
+                Bound::Unbounded => true,
+            };
+            if !below_upper {
+                self.leaf = None;
+                return None;
+            }
+
+            return Some((key, *tid));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tid(n: i64) -> TupleId {
+        TupleId {
+            page: (n / 100) as u32,
+            slot: (n % 100) as u16,
+        }
+    }
+
+    fn keys<'a>(range: impl Iterator<Item = (&'a Key, TupleId)>) -> Vec<i64> {
+        range.map(|(key, _)| *key[0].as_int().unwrap()).collect()
+    }
+
+    /// A tree of `n` keys inserted in a scrambled order, enough of them to split
+    /// the internal nodes too.
+    fn build(n: i64) -> BTree {
+        let mut tree = BTree::new();
+        for i in 0..n {
+            let key = i * 7919 % n;
+            tree.insert(vec![Datum::Int(key)], tid(key));
+        }
+        tree
+    }
+
+    #[test]
+    fn split_keeps_order() {
+        let n = 10_000;
+        let tree = build(n);
+        assert!(matches!(
+            &tree.nodes[tree.root],
+            Node::Internal { children, .. }
+                if children.iter().all(|child| matches!(tree.nodes[*child], Node::Internal { .. }))
+        ));
+        assert_eq!(
+            keys(tree.range(Bound::Unbounded, Bound::Unbounded)),
+            (0..n).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn range_bounds() {
+        let tree = build(1000);
+        let (low, high) = ([Datum::Int(100)], [Datum::Int(200)]);
+
+        assert_eq!(
+            keys(tree.range(Bound::Included(&low), Bound::Excluded(&high))),
+            (100..200).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            keys(tree.range(Bound::Excluded(&low), Bound::Included(&high))),
+            (101..=200).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            keys(tree.range(Bound::Unbounded, Bound::Excluded(&low))),
+            (0..100).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            keys(tree.range(Bound::Included(&high), Bound::Unbounded)),
+            (200..1000).collect::<Vec<_>>()
+        );
+        assert!(keys(tree.range(Bound::Included(&high), Bound::Excluded(&low))).is_empty());
+    }
+
+    #[test]
+    fn prefix_range() {
+        let mut tree = BTree::new();
+        for a in 0..20 {
+            for b in 0..20 {
+                tree.insert(vec![Datum::Int(a), Datum::Int(b)], tid(a * 20 + b));
+            }
+        }
+
+        let prefix = [Datum::Int(7)];
+        let tids = tree
+            .range(Bound::Included(&prefix), Bound::Included(&prefix))
+            .map(|(_, tid)| tid)
+            .collect::<Vec<_>>();
+        assert_eq!(tids, (140..160).map(tid).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn duplicated_keys() {
+        let mut tree = BTree::new();
+        for i in (0..200).rev() {
+            tree.insert(vec![Datum::Int(i % 2)], tid(i));
+        }
+
+        let key = [Datum::Int(1)];
+        let tids = tree.lookup(&key).collect::<Vec<_>>();
+        assert_eq!(
+            tids,
+            (0..200).filter(|i| i % 2 == 1).map(tid).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn delete() {
+        let n = 1000;
+        let mut tree = build(n);
+        for key in (0..n).filter(|key| key % 3 == 0) {
+            assert!(tree.delete(&vec![Datum::Int(key)], tid(key)));
+        }
+        // Deleted twice, or with another location
+        assert!(!tree.delete(&vec![Datum::Int(0)], tid(0)));
+        assert!(!tree.delete(&vec![Datum::Int(1)], tid(2)));
+
+        assert_eq!(
+            keys(tree.range(Bound::Unbounded, Bound::Unbounded)),
+            (0..n).filter(|key| key % 3 != 0).collect::<Vec<_>>()
+        );
+        assert_eq!(tree.lookup(&[Datum::Int(3)]).count(), 0);
+        assert_eq!(tree.lookup(&[Datum::Int(4)]).collect::<Vec<_>>(), [tid(4)]);
+    }
+}
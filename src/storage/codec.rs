@@ -0,0 +1,697 @@
+use bytes::{Buf, BufMut};
+use uuid::Uuid;
+
+use crate::{
+    catalog::defs::{
+        ColumnDefinition, ColumnStatistics, ForeignKeyDefinition, FunctionBody, FunctionDefinition,
+        IndexDefinition, ReferentialAction, SequenceDefinition, TableDefinition, TableStatistics,
+    },
+    core::{sqlstate, Datum, ErrorKind, IntWidth, Json, SQLError, Tuple, Type},
+};
+
+/// Binary serialization of the data persisted by storage.
+pub trait Encode {
+    fn encode(&self, buf: &mut impl BufMut);
+}
+
+pub trait Decode: Sized {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError>;
+}
+
+pub fn corrupted(message: impl AsRef<str>) -> SQLError {
+    SQLError::new(ErrorKind::StorageError, message).with_code(sqlstate::DATA_CORRUPTED)
+}
+
+/// Make sure there are enough bytes to read, since `Buf` panics on underflow.
+fn ensure_remaining(buf: &impl Buf, len: usize) -> Result<(), SQLError> {
+    if buf.remaining() < len {
+        return Err(corrupted("unexpected end of data"));
+    }
+    Ok(())
+}
+
+impl Encode for u64 {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_u64(*self);
+    }
+}
+
+impl Decode for u64 {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 8)?;
+        Ok(buf.get_u64())
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_i64(*self);
+    }
+}
+
+impl Decode for i64 {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 8)?;
+        Ok(buf.get_i64())
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_u8(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 1)?;
+        Ok(buf.get_u8() != 0)
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_u32(self.len() as u32);
+        buf.put_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.as_str().encode(buf);
+    }
+}
+
+impl Decode for String {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 4)?;
+        let len = buf.get_u32() as usize;
+        ensure_remaining(buf, len)?;
+        let bytes = buf.copy_to_bytes(len);
+        String::from_utf8(bytes.to_vec()).map_err(|e| corrupted(e.to_string()))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_u32(self.len() as u32);
+        for item in self.iter() {
+            item.encode(buf);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 4)?;
+        let len = buf.get_u32() as usize;
+        (0..len).map(|_| T::decode(buf)).collect()
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, buf: &mut impl BufMut) {
+        match self {
+            Some(value) => {
+                buf.put_u8(1);
+                value.encode(buf);
+            }
+            None => buf.put_u8(0),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        match bool::decode(buf)? {
+            true => Ok(Some(T::decode(buf)?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl Encode for Datum {
+    fn encode(&self, buf: &mut impl BufMut) {
+        match self {
+            Datum::Int(v) => {
+                buf.put_u8(0);
+                buf.put_i64(*v);
+            }
+            Datum::Float(v) => {
+                buf.put_u8(1);
+                buf.put_f64(*v);
+            }
+            Datum::String(v) => {
+                buf.put_u8(2);
+                v.encode(buf);
+            }
+            Datum::Boolean(v) => {
+                buf.put_u8(3);
+                v.encode(buf);
+            }
+            Datum::Uuid(v) => {
+                buf.put_u8(4);
+                buf.put_slice(v.as_bytes());
+            }
+            Datum::Null => buf.put_u8(5),
+            Datum::Json(v) => {
+                buf.put_u8(6);
+                v.to_string().encode(buf);
+            }
+            Datum::Array(v) => {
+                buf.put_u8(7);
+                v.encode(buf);
+            }
+            Datum::Bytea(v) => {
+                buf.put_u8(8);
+                buf.put_u32(v.len() as u32);
+                buf.put_slice(v);
+            }
+        }
+    }
+}
+
+impl Decode for Datum {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 1)?;
+        match buf.get_u8() {
+            0 => {
+                ensure_remaining(buf, 8)?;
+                Ok(Datum::Int(buf.get_i64()))
+            }
+            1 => {
+                ensure_remaining(buf, 8)?;
+                Ok(Datum::Float(buf.get_f64()))
+            }
+            2 => Ok(Datum::String(String::decode(buf)?.into())),
+            3 => Ok(Datum::Boolean(bool::decode(buf)?)),
+            4 => {
+                ensure_remaining(buf, 16)?;
+                let mut bytes = [0u8; 16];
+                buf.copy_to_slice(&mut bytes);
+                Ok(Datum::Uuid(Uuid::from_bytes(bytes)))
+            }
+            5 => Ok(Datum::Null),
+            6 => {
+                let text = String::decode(buf)?;
+                Json::parse(&text)
+                    .map(Datum::Json)
+                    .ok_or_else(|| corrupted(format!("invalid json: {}", text)))
+            }
+            7 => Ok(Datum::Array(Vec::decode(buf)?)),
+            8 => {
+                ensure_remaining(buf, 4)?;
+                let len = buf.get_u32() as usize;
+                ensure_remaining(buf, len)?;
+                Ok(Datum::Bytea(buf.copy_to_bytes(len).to_vec().into()))
+            }
+            tag => Err(corrupted(format!("invalid datum tag: {}", tag))),
+        }
+    }
+}
+
+impl Encode for Tuple {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.values.encode(buf);
+    }
+}
+
+impl Decode for Tuple {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok(Tuple::new(Vec::decode(buf)?))
+    }
+}
+
+impl Encode for Type {
+    fn encode(&self, buf: &mut impl BufMut) {
+        let tag = match self {
+            Type::Int => 0,
+            Type::Float => 1,
+            Type::String => 2,
+            Type::Boolean => 3,
+            Type::Uuid => 4,
+            Type::Null => 5,
+            Type::Any => 6,
+            Type::Never => 7,
+            Type::Json => 8,
+            Type::Bytea => 10,
+            Type::Array(elem_type) => {
+                buf.put_u8(9);
+                elem_type.encode(buf);
+                return;
+            }
+        };
+        buf.put_u8(tag);
+    }
+}
+
+impl Decode for Type {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 1)?;
+        match buf.get_u8() {
+            0 => Ok(Type::Int),
+            1 => Ok(Type::Float),
+            2 => Ok(Type::String),
+            3 => Ok(Type::Boolean),
+            4 => Ok(Type::Uuid),
+            5 => Ok(Type::Null),
+            6 => Ok(Type::Any),
+            7 => Ok(Type::Never),
+            8 => Ok(Type::Json),
+            9 => Ok(Type::Array(Box::new(Type::decode(buf)?))),
+            10 => Ok(Type::Bytea),
+            tag => Err(corrupted(format!("invalid type tag: {}", tag))),
+        }
+    }
+}
+
+impl Encode for IntWidth {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_u8(match self {
+            IntWidth::Int2 => 0,
+            IntWidth::Int4 => 1,
+            IntWidth::Int8 => 2,
+        });
+    }
+}
+
+impl Decode for IntWidth {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 1)?;
+        match buf.get_u8() {
+            0 => Ok(IntWidth::Int2),
+            1 => Ok(IntWidth::Int4),
+            2 => Ok(IntWidth::Int8),
+            tag => Err(corrupted(format!("invalid integer width tag: {}", tag))),
+        }
+    }
+}
+
+impl Encode for ColumnDefinition {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.name.encode(buf);
+        self.data_type.encode(buf);
+        self.null.encode(buf);
+        self.default.encode(buf);
+        self.int_width.encode(buf);
+    }
+}
+
+impl Decode for ColumnDefinition {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok(ColumnDefinition {
+            name: String::decode(buf)?,
+            data_type: Type::decode(buf)?,
+            null: bool::decode(buf)?,
+            default: Option::decode(buf)?,
+            int_width: Option::decode(buf)?,
+        })
+    }
+}
+
+impl Encode for TableDefinition {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.name.encode(buf);
+        self.columns.encode(buf);
+        self.indexes.encode(buf);
+        self.primary_key.encode(buf);
+        self.foreign_keys.encode(buf);
+    }
+}
+
+impl Decode for TableDefinition {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok(TableDefinition {
+            name: String::decode(buf)?,
+            columns: Vec::decode(buf)?,
+            indexes: Vec::decode(buf)?,
+            primary_key: Option::decode(buf)?,
+            foreign_keys: Vec::decode(buf)?,
+            // Statistics are logged separately by `ANALYZE`
+            statistics: None,
+            // The version is bumped while redoing the record
+            version: 0,
+        })
+    }
+}
+
+impl Encode for TableStatistics {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_u64(self.row_count);
+        self.columns.encode(buf);
+    }
+}
+
+impl Decode for TableStatistics {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 8)?;
+        Ok(TableStatistics {
+            row_count: buf.get_u64(),
+            columns: Vec::decode(buf)?,
+        })
+    }
+}
+
+impl Encode for ColumnStatistics {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_f64(self.null_fraction);
+        buf.put_u64(self.distinct_count);
+        self.min.encode(buf);
+        self.max.encode(buf);
+        self.histogram.encode(buf);
+    }
+}
+
+impl Decode for ColumnStatistics {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 16)?;
+        Ok(ColumnStatistics {
+            null_fraction: buf.get_f64(),
+            distinct_count: buf.get_u64(),
+            min: Datum::decode(buf)?,
+            max: Datum::decode(buf)?,
+            histogram: Vec::decode(buf)?,
+        })
+    }
+}
+
+impl Encode for IndexDefinition {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.name.encode(buf);
+        encode_columns(&self.columns, buf);
+        self.unique.encode(buf);
+    }
+}
+
+impl Decode for IndexDefinition {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok(IndexDefinition {
+            name: String::decode(buf)?,
+            columns: decode_columns(buf)?,
+            unique: bool::decode(buf)?,
+        })
+    }
+}
+
+fn encode_columns(columns: &[usize], buf: &mut impl BufMut) {
+    columns
+        .iter()
+        .map(|column| *column as u64)
+        .collect::<Vec<_>>()
+        .encode(buf);
+}
+
+fn decode_columns(buf: &mut impl Buf) -> Result<Vec<usize>, SQLError> {
+    Ok(Vec::<u64>::decode(buf)?
+        .into_iter()
+        .map(|column| column as usize)
+        .collect())
+}
+
+impl Encode for ForeignKeyDefinition {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.name.encode(buf);
+        encode_columns(&self.columns, buf);
+        self.referred_schema.encode(buf);
+        self.referred_table.encode(buf);
+        encode_columns(&self.referred_columns, buf);
+        self.on_delete.encode(buf);
+    }
+}
+
+impl Decode for ForeignKeyDefinition {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok(ForeignKeyDefinition {
+            name: String::decode(buf)?,
+            columns: decode_columns(buf)?,
+            referred_schema: String::decode(buf)?,
+            referred_table: String::decode(buf)?,
+            referred_columns: decode_columns(buf)?,
+            on_delete: ReferentialAction::decode(buf)?,
+        })
+    }
+}
+
+impl Encode for ReferentialAction {
+    fn encode(&self, buf: &mut impl BufMut) {
+        match self {
+            ReferentialAction::NoAction => buf.put_u8(0),
+            ReferentialAction::Cascade => buf.put_u8(1),
+        }
+    }
+}
+
+impl Decode for ReferentialAction {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 1)?;
+        match buf.get_u8() {
+            0 => Ok(ReferentialAction::NoAction),
+            1 => Ok(ReferentialAction::Cascade),
+            tag => Err(corrupted(format!(
+                "invalid referential action tag: {}",
+                tag
+            ))),
+        }
+    }
+}
+
+impl Encode for SequenceDefinition {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.name.encode(buf);
+        self.start.encode(buf);
+        self.increment.encode(buf);
+        self.min_value.encode(buf);
+        self.max_value.encode(buf);
+        self.cycle.encode(buf);
+        self.owned_by.encode(buf);
+    }
+}
+
+impl Decode for SequenceDefinition {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok(SequenceDefinition {
+            name: String::decode(buf)?,
+            start: i64::decode(buf)?,
+            increment: i64::decode(buf)?,
+            min_value: i64::decode(buf)?,
+            max_value: i64::decode(buf)?,
+            cycle: bool::decode(buf)?,
+            owned_by: Option::decode(buf)?,
+        })
+    }
+}
+
+impl Encode for FunctionDefinition {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.name.encode(buf);
+        self.arg_types.encode(buf);
+        self.return_type.encode(buf);
+        self.body.encode(buf);
+    }
+}
+
+impl Decode for FunctionDefinition {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok(FunctionDefinition {
+            name: String::decode(buf)?,
+            arg_types: Vec::decode(buf)?,
+            return_type: Type::decode(buf)?,
+            body: FunctionBody::decode(buf)?,
+        })
+    }
+}
+
+impl Encode for FunctionBody {
+    fn encode(&self, buf: &mut impl BufMut) {
+        match self {
+            FunctionBody::Sql(text) => {
+                buf.put_u8(0);
+                text.encode(buf);
+            }
+            FunctionBody::Wasm(module) => {
+                buf.put_u8(1);
+                buf.put_u32(module.len() as u32);
+                buf.put_slice(module);
+            }
+        }
+    }
+}
+
+impl Decode for FunctionBody {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        ensure_remaining(buf, 1)?;
+        match buf.get_u8() {
+            0 => Ok(FunctionBody::Sql(String::decode(buf)?)),
+            1 => {
+                ensure_remaining(buf, 4)?;
+                let len = buf.get_u32() as usize;
+                ensure_remaining(buf, len)?;
+                Ok(FunctionBody::Wasm(buf.copy_to_bytes(len).to_vec().into()))
+            }
+            tag => Err(corrupted(format!("invalid function body tag: {}", tag))),
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.0.encode(buf);
+        self.1.encode(buf);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok((A::decode(buf)?, B::decode(buf)?))
+    }
+}
+
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+    fn encode(&self, buf: &mut impl BufMut) {
+        self.0.encode(buf);
+        self.1.encode(buf);
+        self.2.encode(buf);
+    }
+}
+
+impl<A: Decode, B: Decode, C: Decode> Decode for (A, B, C) {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        Ok((A::decode(buf)?, B::decode(buf)?, C::decode(buf)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode the encoded datum, and check that it's encoded to the same bytes
+    /// again, since `Datum` can't be compared, and NaN isn't equal to itself.
+    fn round_trip(datum: Datum) {
+        let mut encoded = Vec::new();
+        datum.encode(&mut encoded);
+
+        let mut buf = encoded.as_slice();
+        let decoded = Datum::decode(&mut buf).unwrap();
+        assert!(buf.is_empty(), "{:?} is not fully decoded", datum);
+
+        let mut reencoded = Vec::new();
+        decoded.encode(&mut reencoded);
+        assert_eq!(
+            encoded, reencoded,
+            "{:?} is decoded as {:?}",
+            datum, decoded
+        );
+
+        // A truncated datum is an error instead of a panic
+        for len in 0..encoded.len() {
+            assert!(Datum::decode(&mut &encoded[..len]).is_err());
+        }
+    }
+
+    fn datums() -> Vec<Datum> {
+        vec![
+            Datum::Null,
+            Datum::Int(0),
+            Datum::Int(-1),
+            Datum::Int(i64::MIN),
+            Datum::Int(i64::MAX),
+            Datum::Float(0.0),
+            Datum::Float(-0.0),
+            Datum::Float(f64::MIN),
+            Datum::Float(f64::MAX),
+            Datum::Float(f64::MIN_POSITIVE),
+            Datum::Float(f64::EPSILON),
+            Datum::Float(f64::INFINITY),
+            Datum::Float(f64::NEG_INFINITY),
+            Datum::Float(f64::NAN),
+            Datum::String("".into()),
+            Datum::String("héllo, 世界\0".into()),
+            Datum::Boolean(true),
+            Datum::Boolean(false),
+            Datum::Uuid(Uuid::nil()),
+            Datum::Uuid(Uuid::from_bytes([0xff; 16])),
+            Datum::Json(Json::parse("null").unwrap()),
+            Datum::Json(Json::parse(r#"{"a": [1, 1e400, "b", true], "c": {}}"#).unwrap()),
+            Datum::Bytea(Vec::new().into()),
+            Datum::Bytea((0..=255).collect::<Vec<u8>>().into()),
+            Datum::Array(vec![]),
+            Datum::Array(vec![Datum::Int(1), Datum::Null, Datum::Int(i64::MIN)]),
+            Datum::Array(vec![Datum::String("".into()), Datum::Null]),
+        ]
+    }
+
+    #[test]
+    fn datum_round_trip() {
+        for datum in datums() {
+            round_trip(datum);
+        }
+    }
+
+    #[test]
+    fn tuple_round_trip() {
+        for tuple in [Tuple::new(vec![]), Tuple::new(datums())] {
+            let mut encoded = Vec::new();
+            tuple.encode(&mut encoded);
+
+            let mut buf = encoded.as_slice();
+            let decoded = Tuple::decode(&mut buf).unwrap();
+            assert!(buf.is_empty());
+
+            let mut reencoded = Vec::new();
+            decoded.encode(&mut reencoded);
+            assert_eq!(encoded, reencoded);
+        }
+    }
+
+    #[test]
+    fn type_round_trip() {
+        let types = [
+            Type::Int,
+            Type::Float,
+            Type::String,
+            Type::Boolean,
+            Type::Uuid,
+            Type::Json,
+            Type::Bytea,
+            Type::Array(Box::new(Type::Int)),
+            Type::Array(Box::new(Type::Array(Box::new(Type::String)))),
+            Type::Null,
+            Type::Any,
+            Type::Never,
+        ];
+        for typ in types {
+            let mut encoded = Vec::new();
+            typ.encode(&mut encoded);
+            assert_eq!(Type::decode(&mut encoded.as_slice()).unwrap(), typ);
+        }
+    }
+
+    #[test]
+    fn function_body_round_trip() {
+        let bodies = [
+            FunctionBody::Sql("CAST(($1 + 1) AS int)".to_string()),
+            FunctionBody::Wasm(b"\0asm\x01\0\0\0".to_vec().into()),
+            FunctionBody::Wasm(Vec::new().into()),
+        ];
+        for body in bodies {
+            let mut encoded = Vec::new();
+            body.encode(&mut encoded);
+
+            let mut buf = encoded.as_slice();
+            let decoded = FunctionBody::decode(&mut buf).unwrap();
+            assert!(buf.is_empty());
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", body));
+
+            for len in 0..encoded.len() {
+                assert!(FunctionBody::decode(&mut &encoded[..len]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_tag() {
+        assert!(Datum::decode(&mut [9u8].as_slice()).is_err());
+        assert!(Type::decode(&mut [11u8].as_slice()).is_err());
+        assert!(FunctionBody::decode(&mut [2u8].as_slice()).is_err());
+    }
+}
@@ -1,38 +1,534 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    io::ErrorKind as IOErrorKind,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
 
-use self::relation::HeapTable;
+use fs2::{lock_contended_error, FileExt};
+use log::info;
 
+use self::{
+    relation::{HeapTable, Relation},
+    sequence::Sequence,
+    wal::{LogRecord, Lsn, SyncPolicy, Wal},
+};
+use crate::{
+    catalog::{
+        defs::{SequenceDefinition, TableDefinition},
+        Catalog, DEFAULT_DATABASE,
+    },
+    core::{sqlstate, ErrorKind, SQLError},
+};
+
+pub mod btree;
+pub mod codec;
+pub mod page;
 pub mod relation;
+pub mod sequence;
+pub mod wal;
+
+pub fn io_error(e: std::io::Error) -> SQLError {
+    SQLError::new(ErrorKind::StorageError, e.to_string())
+}
+
+/// Lock the data directory with its `LOCK` file, so it can't be opened by
+/// two processes at once. The lock is released once the file is closed,
+/// even if the process crashes.
+fn lock_data_dir(data_dir: &Path) -> Result<File, SQLError> {
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(data_dir.join("LOCK"))
+        .map_err(io_error)?;
+    match lock_file.try_lock_exclusive() {
+        Ok(()) => Ok(lock_file),
+        Err(e) if e.kind() == lock_contended_error().kind() => Err(SQLError::new(
+            ErrorKind::StorageError,
+            format!("data directory \"{}\" is in use", data_dir.display()),
+        )
+        .with_code(sqlstate::LOCK_FILE_EXISTS)
+        .with_hint("Is another server or CLI running with the data directory?")),
+        Err(e) => Err(io_error(e)),
+    }
+}
+
+/// Relations are vacuumed automatically once their dead tuples exceed the
+/// threshold plus the fraction of live tuples, the same as Postgres.
+const AUTOVACUUM_THRESHOLD: usize = 50;
+const AUTOVACUUM_SCALE_FACTOR: f64 = 0.2;
+
+/// A relation shared between sessions.
+pub type SharedRelation = Arc<RwLock<Relation>>;
+
+/// Qualified name of a relation or sequence (database_name, schema_name, name).
+pub type QualifiedName = (String, String, String);
 
+/// Shared storage of all the relations, each relation is protected by its own lock,
+/// so queries on different tables never block each other.
 #[derive(Default)]
 pub struct StorageManager {
-    pub relations: HashMap<(String, String), HeapTable>,
+    relations: RwLock<HashMap<QualifiedName, SharedRelation>>,
+    sequences: RwLock<HashMap<QualifiedName, Arc<Sequence>>>,
+    /// Changes are only kept in memory if WAL is disabled.
+    wal: Option<Mutex<Wal>>,
+    /// Heap files are stored in `base/<database>/<schema>/<table>` under the
+    /// data directory.
+    data_dir: Option<PathBuf>,
+    /// `LOCK` file of the data directory, which is locked exclusively as long
+    /// as the storage is open.
+    _lock_file: Option<File>,
 }
 
 impl StorageManager {
-    pub fn get_relation(&self, schema_name: &str, table_name: &str) -> Option<&HeapTable> {
-        self.relations
-            .get(&(schema_name.to_string(), table_name.to_string()))
+    /// Open the WAL in data directory, and recover the database by replaying it.
+    /// The heap files are opened as the tables are created by the records, and the
+    /// indexes are built after all the changes are redone.
+    pub fn recover(
+        data_dir: &Path,
+        policy: SyncPolicy,
+        catalog: &mut Catalog,
+    ) -> Result<Self, SQLError> {
+        std::fs::create_dir_all(data_dir).map_err(io_error)?;
+        let lock_file = lock_data_dir(data_dir)?;
+        let (wal, records) = Wal::open(&data_dir.join("wal"), policy)?;
+
+        let storage_mgr = Self {
+            data_dir: Some(data_dir.to_path_buf()),
+            _lock_file: Some(lock_file),
+            ..Self::default()
+        };
+        for (lsn, record) in records.iter() {
+            record.redo_catalog(catalog)?;
+            record.redo_storage(*lsn, &storage_mgr)?;
+        }
+        for relation in storage_mgr.relations.read().unwrap().values() {
+            relation.write().unwrap().rebuild_indexes();
+        }
+        info!(
+            "Recovered {} records from WAL, next LSN: {}",
+            records.len(),
+            wal.next_lsn()
+        );
+
+        Ok(Self {
+            wal: Some(Mutex::new(wal)),
+            ..storage_mgr
+        })
+    }
+
+    /// Write the records to WAL, should be called before the changes are written to
+    /// disk. Returns the end LSN of the records.
+    pub fn log(&self, records: &[LogRecord]) -> Result<Lsn, SQLError> {
+        let Some(wal) = &self.wal else {
+            return Ok(Lsn::default());
+        };
+
+        let mut wal = wal.lock().unwrap();
+        for record in records.iter() {
+            wal.append(record)?;
+        }
+        wal.commit()?;
+
+        Ok(wal.next_lsn())
     }
 
-    pub fn get_relation_mut(
-        &mut self,
+    pub fn get_relation(
+        &self,
+        database_name: &str,
         schema_name: &str,
         table_name: &str,
-    ) -> Option<&mut HeapTable> {
+    ) -> Option<SharedRelation> {
         self.relations
-            .get_mut(&(schema_name.to_string(), table_name.to_string()))
+            .read()
+            .unwrap()
+            .get(&(
+                database_name.to_string(),
+                schema_name.to_string(),
+                table_name.to_string(),
+            ))
+            .cloned()
+    }
+
+    /// Create the relation, or open the existing heap file of it while recovering.
+    pub fn create_relation(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<(), SQLError> {
+        let table = match &self.data_dir {
+            Some(data_dir) => {
+                let schema_dir = data_dir.join("base").join(database_name).join(schema_name);
+                std::fs::create_dir_all(&schema_dir).map_err(io_error)?;
+                HeapTable::open(&schema_dir.join(table_name))?
+            }
+            None => HeapTable::new(),
+        };
+
+        self.relations.write().unwrap().insert(
+            (
+                database_name.to_string(),
+                schema_name.to_string(),
+                table_name.to_string(),
+            ),
+            Arc::new(RwLock::new(Relation::new(table))),
+        );
+
+        Ok(())
+    }
+
+    pub fn drop_relation(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<(), SQLError> {
+        self.relations.write().unwrap().remove(&(
+            database_name.to_string(),
+            schema_name.to_string(),
+            table_name.to_string(),
+        ));
+
+        if let Some(data_dir) = &self.data_dir {
+            let path = data_dir
+                .join("base")
+                .join(database_name)
+                .join(schema_name)
+                .join(table_name);
+            ignore_not_found(std::fs::remove_file(path))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn create_sequence(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        sequence_def: &SequenceDefinition,
+    ) {
+        self.sequences.write().unwrap().insert(
+            (
+                database_name.to_string(),
+                schema_name.to_string(),
+                sequence_def.name.clone(),
+            ),
+            Arc::new(Sequence::new(sequence_def.clone())),
+        );
     }
 
-    pub fn create_relation(&mut self, schema_name: &str, table_name: &str) {
-        self.relations.insert(
-            (schema_name.to_string(), table_name.to_string()),
-            HeapTable::new(),
+    pub fn get_sequence(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        sequence_name: &str,
+    ) -> Option<Arc<Sequence>> {
+        self.sequences
+            .read()
+            .unwrap()
+            .get(&(
+                database_name.to_string(),
+                schema_name.to_string(),
+                sequence_name.to_string(),
+            ))
+            .cloned()
+    }
+
+    pub fn drop_sequence(&self, database_name: &str, schema_name: &str, sequence_name: &str) {
+        self.sequences.write().unwrap().remove(&(
+            database_name.to_string(),
+            schema_name.to_string(),
+            sequence_name.to_string(),
+        ));
+    }
+
+    /// Advance the sequence, the values are logged ahead before being handed out.
+    pub fn next_sequence_value(
+        &self,
+        database_name: &str,
+        schema_name: &str,
+        sequence_name: &str,
+    ) -> Result<i64, SQLError> {
+        let sequence = self
+            .get_sequence(database_name, schema_name, sequence_name)
+            .ok_or_else(|| {
+                SQLError::new(
+                    ErrorKind::CatalogError,
+                    format!("relation \"{}\" does not exist", sequence_name),
+                )
+                .with_code(sqlstate::UNDEFINED_TABLE)
+            })?;
+        sequence.next(|value| {
+            let name = (
+                database_name.to_string(),
+                schema_name.to_string(),
+                sequence_name.to_string(),
+            );
+            self.log(&[LogRecord::SetSequence(name, value)])?;
+            Ok(())
+        })
+    }
+
+    /// Compact the heap of relation by moving its tuples to the front pages,
+    /// the indexes are rebuilt with the new locations. Returns false if there
+    /// is no dead tuple to reclaim. The version of table is bumped, since the
+    /// suspended scans can't be resumed with the moved tuples.
+    pub fn vacuum(
+        &self,
+        catalog: &mut Catalog,
+        database_name: &str,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<bool, SQLError> {
+        let Some(relation) = self.get_relation(database_name, schema_name, table_name) else {
+            return Ok(false);
+        };
+        let mut relation = relation.write().unwrap();
+        let dead_tuples = relation.heap().dead_tuple_count();
+        if dead_tuples == 0 {
+            return Ok(false);
+        }
+
+        let pages = relation.heap().page_count();
+        let tuples = relation
+            .tuples()
+            .map(|(_, tuple)| tuple)
+            .collect::<Vec<_>>();
+        let tids = HeapTable::new().insert(&tuples)?;
+        let record = LogRecord::RewriteTable(
+            (
+                database_name.to_string(),
+                schema_name.to_string(),
+                table_name.to_string(),
+            ),
+            tids.into_iter().zip(tuples).collect(),
+        );
+        let lsn = self.log(std::slice::from_ref(&record))?;
+        record.redo_catalog(catalog)?;
+        record.redo_alter(lsn, &mut relation)?;
+        relation.rebuild_indexes();
+        info!(
+            "Vacuumed {}.{}.{}: removed {} dead tuples, {} pages to {} pages",
+            database_name,
+            schema_name,
+            table_name,
+            dead_tuples,
+            pages,
+            relation.heap().page_count()
+        );
+
+        Ok(true)
+    }
+
+    /// Vacuum the relations with too many dead tuples, the log is truncated
+    /// if any of them is vacuumed.
+    pub fn autovacuum(&self, catalog: &mut Catalog) -> Result<(), SQLError> {
+        let relations = self
+            .relations
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, relation)| (name.clone(), relation.clone()))
+            .collect::<Vec<_>>();
+        let mut vacuumed = false;
+        for ((database_name, schema_name, table_name), relation) in relations {
+            let needs_vacuum = {
+                let relation = relation.read().unwrap();
+                let heap = relation.heap();
+                heap.dead_tuple_count() as f64
+                    > AUTOVACUUM_THRESHOLD as f64
+                        + AUTOVACUUM_SCALE_FACTOR * heap.row_count() as f64
+            };
+            if needs_vacuum {
+                vacuumed |= self.vacuum(catalog, &database_name, &schema_name, &table_name)?;
+            }
+        }
+        if vacuumed {
+            self.checkpoint(catalog)?;
+        }
+
+        Ok(())
+    }
+
+    /// Truncate the log, the heap files are synced and the catalog is logged
+    /// again to replace the records. The catalog should be locked meanwhile,
+    /// and the relations and sequences are locked in the same order as DML, so
+    /// nothing can be logged before the log is truncated.
+    pub fn checkpoint(&self, catalog: &Catalog) -> Result<(), SQLError> {
+        let Some(wal) = &self.wal else {
+            return Ok(());
+        };
+
+        let relations = self
+            .relations
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, relation)| (name.clone(), relation.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let mut relations = relations
+            .values()
+            .map(|relation| relation.write().unwrap())
+            .collect::<Vec<_>>();
+        let sequences = self
+            .sequences
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, sequence)| (name.clone(), sequence.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let sequence_values = sequences
+            .iter()
+            .map(|(name, sequence)| (name, sequence.lock()))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut wal = wal.lock().unwrap();
+        let lsn = wal.next_lsn();
+        for relation in relations.iter_mut() {
+            relation.heap_mut().sync(lsn)?;
+        }
+
+        let mut records = vec![];
+        // Databases and schemas are created first, since the tables may refer
+        // to any schema of their database
+        for database in catalog.databases.iter() {
+            if database.name != DEFAULT_DATABASE {
+                records.push(LogRecord::CreateDatabase(database.name.clone()));
+            }
+            if !catalog.exists_schema(&database.name, "default")? {
+                records.push(LogRecord::DropSchema(
+                    database.name.clone(),
+                    "default".to_string(),
+                ));
+            }
+            for schema in database.schemas.iter() {
+                if schema.name != "default" {
+                    records.push(LogRecord::CreateSchema(
+                        database.name.clone(),
+                        schema.name.clone(),
+                    ));
+                }
+            }
+        }
+        // Foreign keys are added after all the tables are created
+        let mut altered = vec![];
+        for database in catalog.databases.iter() {
+            for schema in database.schemas.iter() {
+                for table_def in schema.tables.iter() {
+                    records.push(LogRecord::CreateTable(
+                        database.name.clone(),
+                        schema.name.clone(),
+                        TableDefinition {
+                            foreign_keys: vec![],
+                            ..table_def.clone()
+                        },
+                    ));
+                    if !table_def.foreign_keys.is_empty() {
+                        altered.push(LogRecord::AlterTable(
+                            database.name.clone(),
+                            schema.name.clone(),
+                            table_def.clone(),
+                        ));
+                    }
+                    if let Some(statistics) = &table_def.statistics {
+                        altered.push(LogRecord::SetStatistics(
+                            (
+                                database.name.clone(),
+                                schema.name.clone(),
+                                table_def.name.clone(),
+                            ),
+                            statistics.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+        records.append(&mut altered);
+        for database in catalog.databases.iter() {
+            for schema in database.schemas.iter() {
+                for sequence_def in schema.sequences.iter() {
+                    let name = (
+                        database.name.clone(),
+                        schema.name.clone(),
+                        sequence_def.name.clone(),
+                    );
+                    records.push(LogRecord::CreateSequence(
+                        database.name.clone(),
+                        schema.name.clone(),
+                        sequence_def.clone(),
+                    ));
+                    if let Some(value) = sequence_values
+                        .get(&name)
+                        .and_then(|sequence| sequence.logged_value())
+                    {
+                        records.push(LogRecord::SetSequence(name, value));
+                    }
+                }
+                for function_def in schema.functions.iter() {
+                    records.push(LogRecord::CreateFunction(
+                        database.name.clone(),
+                        schema.name.clone(),
+                        function_def.clone(),
+                    ));
+                }
+            }
+        }
+
+        wal.truncate(&records)?;
+        info!(
+            "Checkpoint at {}: log truncated to {} records",
+            lsn,
+            records.len() + 1
         );
+
+        Ok(())
     }
 
-    pub fn drop_relation(&mut self, schema_name: &str, table_name: &str) {
+    /// Drop all the relations and sequences in the database.
+    pub fn drop_database(&self, database_name: &str) -> Result<(), SQLError> {
         self.relations
-            .remove(&(schema_name.to_string(), table_name.to_string()));
+            .write()
+            .unwrap()
+            .retain(|(database, _, _), _| database != database_name);
+        self.sequences
+            .write()
+            .unwrap()
+            .retain(|(database, _, _), _| database != database_name);
+
+        if let Some(data_dir) = &self.data_dir {
+            let path = data_dir.join("base").join(database_name);
+            ignore_not_found(std::fs::remove_dir_all(path))?;
+        }
+
+        Ok(())
+    }
+
+    /// Drop all the relations and sequences in the schema.
+    pub fn drop_schema(&self, database_name: &str, schema_name: &str) -> Result<(), SQLError> {
+        self.relations
+            .write()
+            .unwrap()
+            .retain(|(database, schema, _), _| database != database_name || schema != schema_name);
+        self.sequences
+            .write()
+            .unwrap()
+            .retain(|(database, schema, _), _| database != database_name || schema != schema_name);
+
+        if let Some(data_dir) = &self.data_dir {
+            let path = data_dir.join("base").join(database_name).join(schema_name);
+            ignore_not_found(std::fs::remove_dir_all(path))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Removing a file is idempotent, since it may have been removed before crash.
+fn ignore_not_found(result: std::io::Result<()>) -> Result<(), SQLError> {
+    match result {
+        Err(e) if e.kind() != IOErrorKind::NotFound => Err(io_error(e)),
+        _ => Ok(()),
     }
 }
@@ -0,0 +1,276 @@
+use bytes::{Buf, BufMut};
+
+use super::wal::Lsn;
+
+pub const PAGE_SIZE: usize = 8192;
+
+/// Layout of the page header: `| lsn: u64 | checksum: u32 | slot_count: u16 | upper: u16 |`
+const HEADER_SIZE: usize = 16;
+const CHECKSUM_OFFSET: usize = 8;
+const SLOT_COUNT_OFFSET: usize = 12;
+const UPPER_OFFSET: usize = 14;
+/// Each slot is `| offset: u16 | length: u16 |`, a slot of zero length is dead.
+const SLOT_SIZE: usize = 4;
+
+/// The largest tuple that can be stored in an empty page.
+pub const MAX_TUPLE_SIZE: usize = PAGE_SIZE - HEADER_SIZE - SLOT_SIZE;
+
+/// A slotted page, the slot array grows from the start of page, while the tuple
+/// data grows from the end, the free space is between them.
+/// Slots are never reused or moved, so a tuple can be identified by its slot.
+pub struct Page {
+    data: Box<[u8]>,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Page {
+    pub fn new() -> Self {
+        let mut page = Self {
+            data: vec![0u8; PAGE_SIZE].into_boxed_slice(),
+        };
+        page.set_upper(PAGE_SIZE);
+        page
+    }
+
+    /// Load a page from disk, returns None if the checksum doesn't match.
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        let page = Self {
+            data: data.into_boxed_slice(),
+        };
+        if page.data.len() != PAGE_SIZE || page.checksum() != page.compute_checksum() {
+            return None;
+        }
+        Some(page)
+    }
+
+    /// Bytes of the page to be written to disk, with the checksum updated.
+    pub fn seal(&mut self) -> &[u8] {
+        let checksum = self.compute_checksum();
+        (&mut self.data[CHECKSUM_OFFSET..]).put_u32(checksum);
+        &self.data
+    }
+
+    /// The LSN of the last change applied to this page.
+    pub fn lsn(&self) -> Lsn {
+        Lsn((&self.data[0..]).get_u64())
+    }
+
+    pub fn set_lsn(&mut self, lsn: Lsn) {
+        (&mut self.data[0..]).put_u64(lsn.0);
+    }
+
+    pub fn slot_count(&self) -> usize {
+        (&self.data[SLOT_COUNT_OFFSET..]).get_u16() as usize
+    }
+
+    /// Get the tuple data in the slot, returns None if the slot is dead or doesn't exist.
+    pub fn get(&self, slot: usize) -> Option<&[u8]> {
+        if slot >= self.slot_count() {
+            return None;
+        }
+        let (offset, len) = self.slot(slot);
+        if len == 0 {
+            return None;
+        }
+        Some(&self.data[offset..offset + len])
+    }
+
+    /// Number of the live tuples.
+    pub fn tuple_count(&self) -> usize {
+        (0..self.slot_count())
+            .filter(|slot| self.get(*slot).is_some())
+            .count()
+    }
+
+    /// Insert the tuple data into a new slot, returns None if there is no enough space.
+    pub fn insert(&mut self, tuple: &[u8]) -> Option<usize> {
+        let slot = self.slot_count();
+        self.put(slot, tuple).then_some(slot)
+    }
+
+    /// Put the tuple data into the slot, the slots before it are created as dead ones
+    /// if they don't exist. Returns false if there is no enough space.
+    pub fn put(&mut self, slot: usize, tuple: &[u8]) -> bool {
+        let new_slots = (slot + 1).saturating_sub(self.slot_count());
+        let required = tuple.len() + new_slots * SLOT_SIZE;
+        if self.free_space() < required {
+            // The space of dead tuples may be enough
+            self.compact();
+            if self.free_space() < required {
+                return false;
+            }
+        }
+
+        if new_slots > 0 {
+            let slot_count = self.slot_count();
+            for dead in slot_count..slot {
+                self.set_slot(dead, 0, 0);
+            }
+            self.set_slot_count(slot + 1);
+        }
+        let upper = self.upper() - tuple.len();
+        self.data[upper..upper + tuple.len()].copy_from_slice(tuple);
+        self.set_upper(upper);
+        self.set_slot(slot, upper, tuple.len());
+
+        true
+    }
+
+    /// Mark the slot as dead, its space is reclaimed on compaction.
+    pub fn delete(&mut self, slot: usize) {
+        if slot < self.slot_count() {
+            self.set_slot(slot, 0, 0);
+        }
+    }
+
+    pub fn free_space(&self) -> usize {
+        self.upper() - HEADER_SIZE - self.slot_count() * SLOT_SIZE
+    }
+
+    /// Move the live tuples together to reclaim the space of dead ones.
+    fn compact(&mut self) {
+        let tuples = (0..self.slot_count())
+            .filter_map(|slot| self.get(slot).map(|tuple| (slot, tuple.to_vec())))
+            .collect::<Vec<_>>();
+
+        let mut upper = PAGE_SIZE;
+        for (slot, tuple) in tuples {
+            upper -= tuple.len();
+            self.data[upper..upper + tuple.len()].copy_from_slice(&tuple);
+            self.set_slot(slot, upper, tuple.len());
+        }
+        self.set_upper(upper);
+    }
+
+    fn slot(&self, slot: usize) -> (usize, usize) {
+        let mut buf = &self.data[HEADER_SIZE + slot * SLOT_SIZE..];
+        (buf.get_u16() as usize, buf.get_u16() as usize)
+    }
+
+    fn set_slot(&mut self, slot: usize, offset: usize, len: usize) {
+        let mut buf = &mut self.data[HEADER_SIZE + slot * SLOT_SIZE..];
+        buf.put_u16(offset as u16);
+        buf.put_u16(len as u16);
+    }
+
+    fn set_slot_count(&mut self, slot_count: usize) {
+        (&mut self.data[SLOT_COUNT_OFFSET..]).put_u16(slot_count as u16);
+    }
+
+    /// Upper bound of the free space, `PAGE_SIZE` is stored as 0 since it doesn't fit in u16.
+    fn upper(&self) -> usize {
+        match (&self.data[UPPER_OFFSET..]).get_u16() as usize {
+            0 => PAGE_SIZE,
+            upper => upper,
+        }
+    }
+
+    fn set_upper(&mut self, upper: usize) {
+        (&mut self.data[UPPER_OFFSET..]).put_u16((upper % PAGE_SIZE) as u16);
+    }
+
+    fn checksum(&self) -> u32 {
+        (&self.data[CHECKSUM_OFFSET..]).get_u32()
+    }
+
+    /// Checksum of the page, with the checksum field excluded.
+    fn compute_checksum(&self) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&self.data[..CHECKSUM_OFFSET]);
+        hasher.update(&self.data[SLOT_COUNT_OFFSET..]);
+        hasher.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_get() {
+        let mut page = Page::new();
+        assert_eq!(page.insert(b"foo"), Some(0));
+        assert_eq!(page.insert(b""), Some(1));
+        assert_eq!(page.insert(b"bar"), Some(2));
+
+        assert_eq!(page.get(0), Some(&b"foo"[..]));
+        assert_eq!(page.get(2), Some(&b"bar"[..]));
+        assert_eq!(page.get(3), None);
+        assert_eq!(page.slot_count(), 3);
+        assert_eq!(
+            page.free_space(),
+            PAGE_SIZE - HEADER_SIZE - 3 * SLOT_SIZE - 6
+        );
+    }
+
+    #[test]
+    fn delete_keeps_slots() {
+        let mut page = Page::new();
+        for tuple in [b"a", b"b", b"c"] {
+            page.insert(tuple).unwrap();
+        }
+        page.delete(1);
+        page.delete(10);
+
+        assert_eq!(page.get(1), None);
+        assert_eq!(page.get(2), Some(&b"c"[..]));
+        assert_eq!(page.tuple_count(), 2);
+        // The slot of deleted tuple is not reused
+        assert_eq!(page.insert(b"d"), Some(3));
+    }
+
+    #[test]
+    fn put_creates_dead_slots() {
+        let mut page = Page::new();
+        assert!(page.put(3, b"x"));
+        assert_eq!(page.slot_count(), 4);
+        assert_eq!(page.tuple_count(), 1);
+        assert_eq!(page.get(1), None);
+        assert_eq!(page.get(3), Some(&b"x"[..]));
+    }
+
+    #[test]
+    fn full_page_is_compacted() {
+        let mut page = Page::new();
+        let tuple = [7u8; 1000];
+        let mut slots = vec![];
+        while let Some(slot) = page.insert(&tuple) {
+            slots.push(slot);
+        }
+        assert_eq!(slots.len(), 8);
+
+        // Space of the deleted tuples is reclaimed, while others keep their slots
+        page.delete(slots[2]);
+        page.delete(slots[5]);
+        assert_eq!(page.insert(&[1u8; 1500]), Some(8));
+        assert_eq!(page.get(slots[7]), Some(&tuple[..]));
+        assert_eq!(page.get(8), Some(&[1u8; 1500][..]));
+        assert_eq!(page.insert(&[1u8; 1500]), None);
+
+        let mut page = Page::new();
+        assert!(page.insert(&vec![0u8; MAX_TUPLE_SIZE]).is_some());
+        assert_eq!(page.free_space(), 0);
+    }
+
+    #[test]
+    fn seal_and_load() {
+        let mut page = Page::new();
+        page.insert(b"hello").unwrap();
+        page.set_lsn(Lsn(42));
+        let bytes = page.seal().to_vec();
+
+        let loaded = Page::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(loaded.lsn().0, 42);
+        assert_eq!(loaded.get(0), Some(&b"hello"[..]));
+
+        let mut corrupted = bytes;
+        corrupted[PAGE_SIZE - 1] ^= 1;
+        assert!(Page::from_bytes(corrupted).is_none());
+        assert!(Page::from_bytes(vec![0u8; 100]).is_none());
+    }
+}
@@ -1,8 +1,62 @@
-use crate::core::Tuple;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
 
-#[derive(Debug, Clone, Default)]
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::{
+    btree::{BTree, Key},
+    codec::{corrupted, Decode, Encode},
+    io_error,
+    page::{Page, MAX_TUPLE_SIZE, PAGE_SIZE},
+    wal::Lsn,
+};
+use crate::{
+    catalog::defs::IndexDefinition,
+    core::{sqlstate, ErrorKind, SQLError, Tuple},
+};
+
+/// Location of a tuple in the heap, which never changes unless the tuple is updated
+/// and moved to another page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TupleId {
+    pub page: u32,
+    pub slot: u16,
+}
+
+impl Encode for TupleId {
+    fn encode(&self, buf: &mut impl BufMut) {
+        buf.put_u32(self.page);
+        buf.put_u16(self.slot);
+    }
+}
+
+impl Decode for TupleId {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        if buf.remaining() < 6 {
+            return Err(corrupted("unexpected end of data"));
+        }
+        Ok(TupleId {
+            page: buf.get_u32(),
+            slot: buf.get_u16(),
+        })
+    }
+}
+
+/// A heap of slotted pages. The pages are cached in memory, and written back
+/// to the heap file with `flush()` after the changes are logged.
+#[derive(Default)]
 pub struct HeapTable {
-    pub tuples: Vec<Tuple>,
+    pages: Vec<Page>,
+    /// The pages are only kept in memory if there is no heap file.
+    file: Option<File>,
+    /// Pages changed since last flush.
+    dirty: BTreeSet<usize>,
+    /// Number of the live tuples, maintained along with the changes.
+    row_count: usize,
 }
 
 impl HeapTable {
@@ -10,28 +64,450 @@ impl HeapTable {
         Self::default()
     }
 
-    pub fn insert(&mut self, tuple: Tuple) {
-        self.tuples.push(tuple);
+    /// Open the heap file at the given path, it's created if not exists.
+    /// Pages with invalid checksum, e.g. partially written on crash, fail the
+    /// open, since the changes before the last checkpoint are no longer in
+    /// WAL to rebuild them.
+    pub fn open(path: &Path) -> Result<Self, SQLError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)
+            .map_err(io_error)?;
+
+        let mut content = vec![];
+        file.read_to_end(&mut content).map_err(io_error)?;
+        let pages = content
+            .chunks(PAGE_SIZE)
+            .enumerate()
+            .map(|(index, data)| {
+                Page::from_bytes(data.to_vec()).ok_or_else(|| {
+                    corrupted(format!(
+                        "invalid page {} in heap file \"{}\"",
+                        index,
+                        path.display()
+                    ))
+                    .with_hint("Restore the data directory from a backup.")
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            row_count: pages.iter().map(Page::tuple_count).sum(),
+            pages,
+            file: Some(file),
+            dirty: BTreeSet::new(),
+        })
+    }
+
+    /// Insert the tuples, returns their locations. Nothing is changed if any of the
+    /// tuples is too large to fit in a page.
+    pub fn insert(&mut self, tuples: &[Tuple]) -> Result<Vec<TupleId>, SQLError> {
+        let tuples = tuples
+            .iter()
+            .map(Self::serialize)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tuples.iter().map(|data| self.insert_data(data)).collect())
+    }
+
+    /// Replace the tuples at the given locations, returns their new locations. A tuple
+    /// is moved to another page if there is no enough space in the original one.
+    pub fn update(&mut self, tuples: &[(TupleId, Tuple)]) -> Result<Vec<TupleId>, SQLError> {
+        let tuples = tuples
+            .iter()
+            .map(|(tid, tuple)| Ok((*tid, Self::serialize(tuple)?)))
+            .collect::<Result<Vec<_>, SQLError>>()?;
+
+        Ok(tuples
+            .iter()
+            .map(|(tid, data)| {
+                let page_index = tid.page as usize;
+                self.dirty.insert(page_index);
+                if self.pages[page_index].put(tid.slot as usize, data) {
+                    *tid
+                } else {
+                    self.pages[page_index].delete(tid.slot as usize);
+                    self.row_count -= 1;
+                    self.insert_data(data)
+                }
+            })
+            .collect())
+    }
+
+    /// Remove the tuples at the given locations.
+    pub fn delete(&mut self, tids: &[TupleId]) {
+        for tid in tids.iter() {
+            if let Some(page) = self.pages.get_mut(tid.page as usize) {
+                if page.get(tid.slot as usize).is_some() {
+                    self.row_count -= 1;
+                }
+                page.delete(tid.slot as usize);
+                self.dirty.insert(tid.page as usize);
+            }
+        }
+    }
+
+    /// Put the tuple at the given location, used to redo changes. Missing pages are
+    /// created as empty ones.
+    pub fn put(&mut self, tid: TupleId, tuple: &Tuple) -> Result<(), SQLError> {
+        let data = Self::serialize(tuple)?;
+        let page_index = tid.page as usize;
+        while self.pages.len() <= page_index {
+            self.dirty.insert(self.pages.len());
+            self.pages.push(Page::new());
+        }
+
+        self.dirty.insert(page_index);
+        let replaced = self.pages[page_index].get(tid.slot as usize).is_some();
+        if !self.pages[page_index].put(tid.slot as usize, &data) {
+            return Err(corrupted(format!(
+                "no space for tuple ({}, {})",
+                tid.page, tid.slot
+            )));
+        }
+        if !replaced {
+            self.row_count += 1;
+        }
+
+        Ok(())
     }
 
-    #[allow(dead_code)]
-    pub fn truncate(&mut self) {
-        self.tuples.clear();
+    pub fn get(&self, tid: TupleId) -> Option<Tuple> {
+        self.pages
+            .get(tid.page as usize)
+            .and_then(|page| page.get(tid.slot as usize))
+            .map(Self::deserialize)
+    }
+
+    /// Number of the tuples, without scanning the pages.
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    /// Number of the dead slots, whose space is reclaimed by `VACUUM`.
+    pub fn dead_tuple_count(&self) -> usize {
+        self.pages.iter().map(Page::slot_count).sum::<usize>() - self.row_count
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// All the tuples with their locations.
+    pub fn tuples(&self) -> impl Iterator<Item = (TupleId, Tuple)> + '_ {
+        self.pages
+            .iter()
+            .enumerate()
+            .flat_map(|(page_index, page)| {
+                (0..page.slot_count()).filter_map(move |slot| {
+                    page.get(slot).map(|data| {
+                        (
+                            TupleId {
+                                page: page_index as u32,
+                                slot: slot as u16,
+                            },
+                            Self::deserialize(data),
+                        )
+                    })
+                })
+            })
+    }
+
+    /// The LSN of the last change applied to the page, it's zero for a missing page.
+    pub fn page_lsn(&self, page: u32) -> Lsn {
+        self.pages
+            .get(page as usize)
+            .map(Page::lsn)
+            .unwrap_or_default()
+    }
+
+    /// Mark the changed pages with the LSN of their log record, and write them back
+    /// to the heap file. The file is not synced, since the changes can be recovered
+    /// from WAL.
+    pub fn flush(&mut self, lsn: Lsn) -> Result<(), SQLError> {
+        for page_index in std::mem::take(&mut self.dirty) {
+            let page = &mut self.pages[page_index];
+            page.set_lsn(lsn);
+            if let Some(file) = &mut self.file {
+                file.seek(SeekFrom::Start((page_index * PAGE_SIZE) as u64))
+                    .map_err(io_error)?;
+                file.write_all(page.seal()).map_err(io_error)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flush the changed pages and sync the heap file, so the log before `lsn`
+    /// is no longer needed by the heap.
+    pub fn sync(&mut self, lsn: Lsn) -> Result<(), SQLError> {
+        self.flush(lsn)?;
+        if let Some(file) = &self.file {
+            file.sync_data().map_err(io_error)?;
+        }
+        Ok(())
+    }
+
+    pub fn truncate(&mut self) -> Result<(), SQLError> {
+        self.pages.clear();
+        self.dirty.clear();
+        self.row_count = 0;
+        if let Some(file) = &self.file {
+            file.set_len(0).map_err(io_error)?;
+        }
+        Ok(())
+    }
+
+    /// Replace all the tuples with the ones at the given locations, e.g. when the
+    /// columns are changed. It should be called after the change is logged.
+    pub fn rewrite(&mut self, tuples: &[(TupleId, Tuple)], lsn: Lsn) -> Result<(), SQLError> {
+        self.truncate()?;
+        for (tid, tuple) in tuples.iter() {
+            self.put(*tid, tuple)?;
+        }
+        self.flush(lsn)
     }
 
     pub fn scan(&self, scan_state: &mut ScanState) -> Option<Tuple> {
-        if scan_state.cursor >= self.tuples.len() {
-            return None;
+        while let Some(page) = self.pages.get(scan_state.page) {
+            let slot = scan_state.slot;
+            if slot >= page.slot_count() {
+                scan_state.page += 1;
+                scan_state.slot = 0;
+                continue;
+            }
+
+            scan_state.slot += 1;
+            if let Some(data) = page.get(slot) {
+                return Some(Self::deserialize(data));
+            }
+        }
+
+        None
+    }
+
+    /// Insert the serialized tuple into the last page, or a new page if it's full.
+    fn insert_data(&mut self, data: &[u8]) -> TupleId {
+        let slot = match self.pages.last_mut().and_then(|page| page.insert(data)) {
+            Some(slot) => slot,
+            None => {
+                let mut page = Page::new();
+                let slot = page.insert(data).unwrap();
+                self.pages.push(page);
+                slot
+            }
+        };
+
+        let page_index = self.pages.len() - 1;
+        self.dirty.insert(page_index);
+        self.row_count += 1;
+        TupleId {
+            page: page_index as u32,
+            slot: slot as u16,
         }
+    }
 
-        let tuple = self.tuples[scan_state.cursor].clone();
-        scan_state.cursor += 1;
+    fn serialize(tuple: &Tuple) -> Result<Vec<u8>, SQLError> {
+        let mut buf = BytesMut::new();
+        tuple.encode(&mut buf);
+        if buf.len() > MAX_TUPLE_SIZE {
+            return Err(SQLError::new(
+                ErrorKind::StorageError,
+                format!(
+                    "row is too big: size {}, maximum size {}",
+                    buf.len(),
+                    MAX_TUPLE_SIZE
+                ),
+            )
+            .with_code(sqlstate::PROGRAM_LIMIT_EXCEEDED));
+        }
+        Ok(buf.to_vec())
+    }
 
-        Some(tuple)
+    /// The tuples are validated on writing, and the pages are validated by checksum on
+    /// loading, so a tuple that fails to decode is a bug.
+    fn deserialize(mut data: &[u8]) -> Tuple {
+        Tuple::decode(&mut data).expect("invalid tuple in page")
     }
 }
 
+/// Cursor of a sequential scan.
 #[derive(Debug, Clone, Default)]
 pub struct ScanState {
-    cursor: usize,
+    page: usize,
+    slot: usize,
+}
+
+/// A B-tree index of relation.
+pub struct Index {
+    pub def: IndexDefinition,
+    pub tree: BTree,
+}
+
+/// A heap table with its indexes. The indexes are only kept in memory, and
+/// maintained along with the changes of heap.
+pub struct Relation {
+    heap: HeapTable,
+    indexes: Vec<Index>,
+}
+
+impl Relation {
+    pub fn new(heap: HeapTable) -> Self {
+        Self {
+            heap,
+            indexes: vec![],
+        }
+    }
+
+    /// The heap without indexes maintained, used to redo changes. The indexes
+    /// should be rebuilt after that.
+    pub fn heap_mut(&mut self) -> &mut HeapTable {
+        &mut self.heap
+    }
+
+    pub fn heap(&self) -> &HeapTable {
+        &self.heap
+    }
+
+    pub fn index(&self, index_name: &str) -> Option<&Index> {
+        self.indexes
+            .iter()
+            .find(|index| index.def.name == index_name)
+    }
+
+    pub fn insert(&mut self, tuples: &[Tuple]) -> Result<Vec<TupleId>, SQLError> {
+        let tids = self.heap.insert(tuples)?;
+        for index in self.indexes.iter_mut() {
+            for (tid, tuple) in tids.iter().zip(tuples.iter()) {
+                let key = index.def.key(tuple);
+                index.tree.insert(key, *tid);
+            }
+        }
+        Ok(tids)
+    }
+
+    pub fn update(&mut self, tuples: &[(TupleId, Tuple)]) -> Result<Vec<TupleId>, SQLError> {
+        let old_tuples = tuples
+            .iter()
+            .filter_map(|(tid, _)| self.heap.get(*tid).map(|tuple| (*tid, tuple)))
+            .collect::<Vec<_>>();
+        let new_tids = self.heap.update(tuples)?;
+
+        for index in self.indexes.iter_mut() {
+            for (tid, tuple) in old_tuples.iter() {
+                let key = index.def.key(tuple);
+                index.tree.delete(&key, *tid);
+            }
+            for (tid, (_, tuple)) in new_tids.iter().zip(tuples.iter()) {
+                let key = index.def.key(tuple);
+                index.tree.insert(key, *tid);
+            }
+        }
+        Ok(new_tids)
+    }
+
+    pub fn delete(&mut self, tids: &[TupleId]) {
+        for tid in tids.iter() {
+            let Some(tuple) = self.heap.get(*tid) else {
+                continue;
+            };
+            for index in self.indexes.iter_mut() {
+                let key = index.def.key(&tuple);
+                index.tree.delete(&key, *tid);
+            }
+        }
+        self.heap.delete(tids);
+    }
+
+    pub fn get(&self, tid: TupleId) -> Option<Tuple> {
+        self.heap.get(tid)
+    }
+
+    pub fn tuples(&self) -> impl Iterator<Item = (TupleId, Tuple)> + '_ {
+        self.heap.tuples()
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.heap.row_count()
+    }
+
+    pub fn scan(&self, scan_state: &mut ScanState) -> Option<Tuple> {
+        self.heap.scan(scan_state)
+    }
+
+    pub fn flush(&mut self, lsn: Lsn) -> Result<(), SQLError> {
+        self.heap.flush(lsn)
+    }
+
+    /// Check if adding the tuples, in place of the removed ones, violates any
+    /// unique index. Returns the index and the duplicated key if so.
+    pub fn find_conflict(
+        &self,
+        removed: &[TupleId],
+        added: &[Tuple],
+    ) -> Option<(&IndexDefinition, Key)> {
+        let removed = removed.iter().collect::<HashSet<_>>();
+        for index in self.indexes.iter().filter(|index| index.def.unique) {
+            let mut keys = HashSet::new();
+            for tuple in added.iter() {
+                // NULLs are never equal to each other
+                let key = index.def.key(tuple);
+                if key.iter().any(|datum| datum.is_null()) {
+                    continue;
+                }
+
+                if !keys.insert(key.clone())
+                    || index.tree.lookup(&key).any(|tid| !removed.contains(&tid))
+                {
+                    return Some((&index.def, key));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Find a duplicated key of the existing tuples for the unique index.
+    pub fn find_duplicate(&self, index_def: &IndexDefinition) -> Option<Key> {
+        let mut keys = HashSet::new();
+        self.heap
+            .tuples()
+            .map(|(_, tuple)| index_def.key(&tuple))
+            .filter(|key| !key.iter().any(|datum| datum.is_null()))
+            .find(|key| !keys.insert(key.clone()))
+    }
+
+    pub fn create_index(&mut self, index_def: IndexDefinition) {
+        let mut index = Index {
+            def: index_def,
+            tree: BTree::new(),
+        };
+        for (tid, tuple) in self.heap.tuples() {
+            let key = index.def.key(&tuple);
+            index.tree.insert(key, tid);
+        }
+        self.indexes.push(index);
+    }
+
+    pub fn drop_index(&mut self, index_name: &str) {
+        self.indexes.retain(|index| index.def.name != index_name);
+    }
+
+    /// Replace the indexes with the given definitions, and build them from the heap.
+    pub fn set_indexes(&mut self, index_defs: &[IndexDefinition]) {
+        self.indexes.clear();
+        for index_def in index_defs.iter() {
+            self.create_index(index_def.clone());
+        }
+    }
+
+    pub fn rebuild_indexes(&mut self) {
+        for index_def in std::mem::take(&mut self.indexes)
+            .into_iter()
+            .map(|index| index.def)
+        {
+            self.create_index(index_def);
+        }
+    }
 }
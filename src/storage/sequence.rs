@@ -0,0 +1,127 @@
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{
+    catalog::defs::SequenceDefinition,
+    core::{sqlstate, ErrorKind, SQLError},
+};
+
+/// Number of values handed out for each log record of a sequence, so the log is
+/// not written on every call of `nextval`.
+const LOG_AHEAD_VALUES: usize = 32;
+
+/// State of a sequence shared between sessions. The values are logged ahead of
+/// use, so a sequence never goes backwards after recovery, but the values which
+/// have been logged and not used yet are skipped.
+pub struct Sequence {
+    def: SequenceDefinition,
+    state: Mutex<SequenceState>,
+}
+
+struct SequenceState {
+    /// The last value handed out, or the start value if it's not called yet.
+    last_value: i64,
+    is_called: bool,
+    /// Number of values that can be handed out before logging again.
+    logged_values: usize,
+}
+
+impl Sequence {
+    pub fn new(def: SequenceDefinition) -> Self {
+        let state = SequenceState {
+            last_value: def.start,
+            is_called: false,
+            logged_values: 0,
+        };
+        Self {
+            def,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Restore the sequence while recovering, the values up to `value` may have
+    /// been handed out.
+    pub fn restore(&self, value: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.last_value = value;
+        state.is_called = true;
+        state.logged_values = 0;
+    }
+
+    /// Advance the sequence and return the new value. `log` is called with the
+    /// last value to be handed out before logging again.
+    pub fn next(&self, log: impl FnOnce(i64) -> Result<(), SQLError>) -> Result<i64, SQLError> {
+        let mut state = self.state.lock().unwrap();
+        let value = if state.is_called {
+            self.def
+                .next_value(state.last_value)
+                .ok_or_else(|| self.limit_exceeded())?
+        } else {
+            state.last_value
+        };
+
+        if state.logged_values == 0 {
+            let mut logged = value;
+            for _ in 1..LOG_AHEAD_VALUES {
+                match self.def.next_value(logged) {
+                    Some(next) => logged = next,
+                    None => break,
+                }
+            }
+            log(logged)?;
+            state.logged_values = LOG_AHEAD_VALUES;
+        }
+
+        state.logged_values -= 1;
+        state.last_value = value;
+        state.is_called = true;
+
+        Ok(value)
+    }
+
+    /// Hold the sequence, no value is handed out or logged until the guard is dropped.
+    pub fn lock(&self) -> SequenceGuard<'_> {
+        SequenceGuard {
+            def: &self.def,
+            state: self.state.lock().unwrap(),
+        }
+    }
+
+    fn limit_exceeded(&self) -> SQLError {
+        let (limit, value) = if self.def.increment > 0 {
+            ("maximum", self.def.max_value)
+        } else {
+            ("minimum", self.def.min_value)
+        };
+        SQLError::new(
+            ErrorKind::RuntimeError,
+            format!(
+                "nextval: reached {} value of sequence \"{}\" ({})",
+                limit, self.def.name, value
+            ),
+        )
+        .with_code(sqlstate::SEQUENCE_GENERATOR_LIMIT_EXCEEDED)
+    }
+}
+
+/// A held sequence, see `Sequence::lock()`.
+pub struct SequenceGuard<'a> {
+    def: &'a SequenceDefinition,
+    state: MutexGuard<'a, SequenceState>,
+}
+
+impl SequenceGuard<'_> {
+    /// The value in the last log record of sequence, `None` if it's never called.
+    pub fn logged_value(&self) -> Option<i64> {
+        if !self.state.is_called {
+            return None;
+        }
+        let mut value = self.state.last_value;
+        for _ in 0..self.state.logged_values {
+            match self.def.next_value(value) {
+                Some(next) => value = next,
+                None => break,
+            }
+        }
+        Some(value)
+    }
+}
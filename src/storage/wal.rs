@@ -0,0 +1,843 @@
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::{
+    codec::{corrupted, Decode, Encode},
+    io_error,
+    relation::{HeapTable, Relation, TupleId},
+    QualifiedName, StorageManager,
+};
+use crate::{
+    catalog::{
+        defs::{
+            FunctionDefinition, IndexDefinition, SequenceDefinition, TableDefinition,
+            TableStatistics,
+        },
+        Catalog,
+    },
+    core::{ErrorKind, SQLError, Tuple},
+};
+
+/// Size of the header of a record, which contains the length and checksum of payload.
+const RECORD_HEADER_SIZE: usize = 8;
+
+/// Log sequence number, i.e. the offset of a record in the log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Lsn(pub u64);
+
+impl Display for Lsn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 as u32)
+    }
+}
+
+/// A change of the databases, the objects are named by (database_name,
+/// schema_name, name). DML changes are recorded with the locations of tuples,
+/// and they are skipped on redo if the page has been flushed after them.
+#[derive(Debug, Clone)]
+pub enum LogRecord {
+    /// Database created with an empty `default` schema.
+    CreateDatabase(String),
+    DropDatabase(String),
+    CreateSchema(String, String),
+    DropSchema(String, String),
+    CreateTable(String, String, TableDefinition),
+    DropTable(String, String, String),
+    /// Index created on the table (database_name, schema_name, table_name, index_def).
+    CreateIndex(String, String, String, IndexDefinition),
+    /// Index dropped from the table (database_name, schema_name, table_name, index_name).
+    DropIndex(String, String, String, String),
+    /// Inserted tuples with their locations.
+    Insert(QualifiedName, Vec<(TupleId, Tuple)>),
+    /// Updated tuples with their original and new locations.
+    Update(QualifiedName, Vec<(TupleId, TupleId, Tuple)>),
+    /// Locations of the deleted tuples.
+    Delete(QualifiedName, Vec<TupleId>),
+    CreateSequence(String, String, SequenceDefinition),
+    DropSequence(String, String, String),
+    /// The values of sequence up to the given one may have been handed out.
+    SetSequence(QualifiedName, i64),
+    /// The owner column (table_name, column_name) of sequence is changed.
+    SetSequenceOwner(QualifiedName, Option<(String, String)>),
+    /// Definition of the table is replaced, e.g. when its columns are changed.
+    AlterTable(String, String, TableDefinition),
+    /// All the tuples of table are replaced with the given ones.
+    RewriteTable(QualifiedName, Vec<(TupleId, Tuple)>),
+    /// Statistics of the table are collected by `ANALYZE`.
+    SetStatistics(QualifiedName, TableStatistics),
+    /// Function created in the schema, replacing the one of the same name.
+    CreateFunction(String, String, FunctionDefinition),
+    DropFunction(String, String, String),
+    /// The first record of a truncated log, the records before the LSN are replaced
+    /// by the ones after this, which recreate the catalog.
+    Checkpoint(Lsn),
+}
+
+impl LogRecord {
+    /// Redo the change to catalog, DML records only change the statistics of
+    /// tables. The version of catalog is bumped by DDL records.
+    pub fn redo_catalog(&self, catalog: &mut Catalog) -> Result<(), SQLError> {
+        self.redo_definitions(catalog)?;
+        match self {
+            LogRecord::Insert(..)
+            | LogRecord::Update(..)
+            | LogRecord::Delete(..)
+            | LogRecord::SetStatistics(..)
+            | LogRecord::SetSequence(..)
+            | LogRecord::Checkpoint(_) => {}
+            // The tuples are moved by rewriting, so the scans of table can't be resumed
+            LogRecord::CreateTable(database_name, schema_name, TableDefinition { name, .. })
+            | LogRecord::AlterTable(database_name, schema_name, TableDefinition { name, .. })
+            | LogRecord::RewriteTable((database_name, schema_name, name), _) => {
+                catalog.bump_version(Some((database_name, schema_name, name)))
+            }
+            _ => catalog.bump_version(None),
+        }
+
+        Ok(())
+    }
+
+    fn redo_definitions(&self, catalog: &mut Catalog) -> Result<(), SQLError> {
+        match self {
+            LogRecord::CreateDatabase(database_name) => catalog.create_database(database_name),
+            LogRecord::DropDatabase(database_name) => catalog.drop_database(database_name),
+            LogRecord::CreateSchema(database_name, schema_name) => {
+                catalog.create_schema(database_name, schema_name)
+            }
+            LogRecord::DropSchema(database_name, schema_name) => {
+                catalog.drop_schema(database_name, schema_name)
+            }
+            LogRecord::CreateTable(database_name, schema_name, table_def) => {
+                catalog.create_table(database_name, schema_name, table_def)
+            }
+            LogRecord::DropTable(database_name, schema_name, table_name) => {
+                catalog.drop_table(database_name, schema_name, table_name)
+            }
+            LogRecord::CreateIndex(database_name, schema_name, table_name, index_def) => {
+                catalog.create_index(database_name, schema_name, table_name, index_def)
+            }
+            LogRecord::DropIndex(database_name, schema_name, table_name, index_name) => {
+                catalog.drop_index(database_name, schema_name, table_name, index_name)
+            }
+            LogRecord::CreateSequence(database_name, schema_name, sequence_def) => {
+                catalog.create_sequence(database_name, schema_name, sequence_def)
+            }
+            LogRecord::DropSequence(database_name, schema_name, sequence_name) => {
+                catalog.drop_sequence(database_name, schema_name, sequence_name)
+            }
+            LogRecord::SetSequenceOwner((database_name, schema_name, sequence_name), owned_by) => {
+                catalog.set_sequence_owner(database_name, schema_name, sequence_name, owned_by)
+            }
+            LogRecord::AlterTable(database_name, schema_name, table_def) => {
+                catalog.alter_table(database_name, schema_name, table_def)
+            }
+            LogRecord::SetStatistics((database_name, schema_name, table_name), statistics) => {
+                catalog.set_statistics(database_name, schema_name, table_name, statistics)
+            }
+            LogRecord::CreateFunction(database_name, schema_name, function_def) => {
+                catalog.create_function(database_name, schema_name, function_def)
+            }
+            LogRecord::DropFunction(database_name, schema_name, function_name) => {
+                catalog.drop_function(database_name, schema_name, function_name)
+            }
+            LogRecord::Insert((database_name, schema_name, table_name), tuples) => {
+                catalog.update_statistics(database_name, schema_name, table_name, |statistics| {
+                    statistics.record_insert(tuples.iter().map(|(_, tuple)| tuple))
+                });
+                Ok(())
+            }
+            LogRecord::Update((database_name, schema_name, table_name), tuples) => {
+                catalog.update_statistics(database_name, schema_name, table_name, |statistics| {
+                    statistics.record_update(tuples.iter().map(|(_, _, tuple)| tuple))
+                });
+                Ok(())
+            }
+            LogRecord::Delete((database_name, schema_name, table_name), tids) => {
+                catalog.update_statistics(database_name, schema_name, table_name, |statistics| {
+                    statistics.record_delete(tids.len())
+                });
+                Ok(())
+            }
+            LogRecord::SetSequence(..) | LogRecord::RewriteTable(..) | LogRecord::Checkpoint(_) => {
+                Ok(())
+            }
+        }
+    }
+
+    /// Redo the change to storage, `lsn` is the end of this record.
+    pub fn redo_storage(&self, lsn: Lsn, storage_mgr: &StorageManager) -> Result<(), SQLError> {
+        let get_relation = |database_name: &str, schema_name: &str, table_name: &str| {
+            storage_mgr
+                .get_relation(database_name, schema_name, table_name)
+                .ok_or_else(|| SQLError::new(ErrorKind::UnknownError, "cannot find storage"))
+        };
+        match self {
+            LogRecord::DropDatabase(database_name) => storage_mgr.drop_database(database_name)?,
+            LogRecord::DropSchema(database_name, schema_name) => {
+                storage_mgr.drop_schema(database_name, schema_name)?
+            }
+            LogRecord::CreateTable(database_name, schema_name, table_def) => {
+                storage_mgr.create_relation(database_name, schema_name, &table_def.name)?;
+                let table = get_relation(database_name, schema_name, &table_def.name)?;
+                let mut table = table.write().unwrap();
+                for index_def in table_def.indexes.iter() {
+                    table.create_index(index_def.clone());
+                }
+            }
+            LogRecord::DropTable(database_name, schema_name, table_name) => {
+                storage_mgr.drop_relation(database_name, schema_name, table_name)?;
+            }
+            LogRecord::CreateIndex(database_name, schema_name, table_name, index_def) => {
+                let table = get_relation(database_name, schema_name, table_name)?;
+                table.write().unwrap().create_index(index_def.clone());
+            }
+            LogRecord::DropIndex(database_name, schema_name, table_name, index_name) => {
+                let table = get_relation(database_name, schema_name, table_name)?;
+                table.write().unwrap().drop_index(index_name);
+            }
+            LogRecord::Insert((database_name, schema_name, table_name), _)
+            | LogRecord::Update((database_name, schema_name, table_name), _)
+            | LogRecord::Delete((database_name, schema_name, table_name), _) => {
+                let table = get_relation(database_name, schema_name, table_name)?;
+                self.redo_relation(lsn, table.write().unwrap().heap_mut())?;
+            }
+            LogRecord::CreateSequence(database_name, schema_name, sequence_def) => {
+                storage_mgr.create_sequence(database_name, schema_name, sequence_def);
+            }
+            LogRecord::DropSequence(database_name, schema_name, sequence_name) => {
+                storage_mgr.drop_sequence(database_name, schema_name, sequence_name);
+            }
+            LogRecord::SetSequence((database_name, schema_name, sequence_name), value) => {
+                let sequence = storage_mgr
+                    .get_sequence(database_name, schema_name, sequence_name)
+                    .ok_or_else(|| {
+                        SQLError::new(ErrorKind::UnknownError, "cannot find sequence")
+                    })?;
+                sequence.restore(*value);
+            }
+            LogRecord::AlterTable(database_name, schema_name, TableDefinition { name, .. })
+            | LogRecord::RewriteTable((database_name, schema_name, name), _) => {
+                let table = get_relation(database_name, schema_name, name)?;
+                self.redo_alter(lsn, &mut table.write().unwrap())?;
+            }
+            LogRecord::CreateDatabase(_)
+            | LogRecord::CreateSchema(..)
+            | LogRecord::SetSequenceOwner(..)
+            | LogRecord::SetStatistics(..)
+            | LogRecord::CreateFunction(..)
+            | LogRecord::DropFunction(..)
+            | LogRecord::Checkpoint(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Redo the change of table definition or tuples to the relation. The tuples are
+    /// rewritten regardless of the pages, since all of them are replaced.
+    pub fn redo_alter(&self, lsn: Lsn, table: &mut Relation) -> Result<(), SQLError> {
+        match self {
+            LogRecord::AlterTable(_, _, table_def) => table.set_indexes(&table_def.indexes),
+            LogRecord::RewriteTable(_, tuples) => table.heap_mut().rewrite(tuples, lsn)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Redo the DML change to the pages not flushed after the record.
+    pub fn redo_relation(&self, lsn: Lsn, table: &mut HeapTable) -> Result<(), SQLError> {
+        // The pages are only marked with the LSN on flush, so this is stable while redoing.
+        let outdated = |table: &HeapTable, tid: &TupleId| table.page_lsn(tid.page) < lsn;
+        match self {
+            LogRecord::Insert(_, tuples) => {
+                for (tid, tuple) in tuples.iter() {
+                    if outdated(table, tid) {
+                        table.put(*tid, tuple)?;
+                    }
+                }
+            }
+            LogRecord::Update(_, tuples) => {
+                for (old_tid, new_tid, tuple) in tuples.iter() {
+                    if old_tid != new_tid && outdated(table, old_tid) {
+                        table.delete(std::slice::from_ref(old_tid));
+                    }
+                    if outdated(table, new_tid) {
+                        table.put(*new_tid, tuple)?;
+                    }
+                }
+            }
+            LogRecord::Delete(_, tids) => {
+                for tid in tids.iter() {
+                    if outdated(table, tid) {
+                        table.delete(std::slice::from_ref(tid));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        table.flush(lsn)
+    }
+}
+
+impl Encode for LogRecord {
+    fn encode(&self, buf: &mut impl BufMut) {
+        match self {
+            LogRecord::CreateSchema(database_name, schema_name) => {
+                buf.put_u8(0);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+            }
+            LogRecord::DropSchema(database_name, schema_name) => {
+                buf.put_u8(1);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+            }
+            LogRecord::CreateTable(database_name, schema_name, table_def) => {
+                buf.put_u8(2);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                table_def.encode(buf);
+            }
+            LogRecord::DropTable(database_name, schema_name, table_name) => {
+                buf.put_u8(3);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                table_name.encode(buf);
+            }
+            LogRecord::Insert(name, tuples) => {
+                buf.put_u8(4);
+                name.encode(buf);
+                tuples.encode(buf);
+            }
+            LogRecord::Update(name, tuples) => {
+                buf.put_u8(5);
+                name.encode(buf);
+                tuples.encode(buf);
+            }
+            LogRecord::Delete(name, tids) => {
+                buf.put_u8(6);
+                name.encode(buf);
+                tids.encode(buf);
+            }
+            LogRecord::CreateIndex(database_name, schema_name, table_name, index_def) => {
+                buf.put_u8(7);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                table_name.encode(buf);
+                index_def.encode(buf);
+            }
+            LogRecord::DropIndex(database_name, schema_name, table_name, index_name) => {
+                buf.put_u8(8);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                table_name.encode(buf);
+                index_name.encode(buf);
+            }
+            LogRecord::CreateSequence(database_name, schema_name, sequence_def) => {
+                buf.put_u8(9);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                sequence_def.encode(buf);
+            }
+            LogRecord::DropSequence(database_name, schema_name, sequence_name) => {
+                buf.put_u8(10);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                sequence_name.encode(buf);
+            }
+            LogRecord::SetSequence(name, value) => {
+                buf.put_u8(11);
+                name.encode(buf);
+                value.encode(buf);
+            }
+            LogRecord::SetSequenceOwner(name, owned_by) => {
+                buf.put_u8(12);
+                name.encode(buf);
+                owned_by.encode(buf);
+            }
+            LogRecord::AlterTable(database_name, schema_name, table_def) => {
+                buf.put_u8(13);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                table_def.encode(buf);
+            }
+            LogRecord::RewriteTable(name, tuples) => {
+                buf.put_u8(14);
+                name.encode(buf);
+                tuples.encode(buf);
+            }
+            LogRecord::SetStatistics(name, statistics) => {
+                buf.put_u8(15);
+                name.encode(buf);
+                statistics.encode(buf);
+            }
+            LogRecord::CreateFunction(database_name, schema_name, function_def) => {
+                buf.put_u8(16);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                function_def.encode(buf);
+            }
+            LogRecord::DropFunction(database_name, schema_name, function_name) => {
+                buf.put_u8(17);
+                database_name.encode(buf);
+                schema_name.encode(buf);
+                function_name.encode(buf);
+            }
+            LogRecord::Checkpoint(lsn) => {
+                buf.put_u8(18);
+                lsn.0.encode(buf);
+            }
+            LogRecord::CreateDatabase(database_name) => {
+                buf.put_u8(19);
+                database_name.encode(buf);
+            }
+            LogRecord::DropDatabase(database_name) => {
+                buf.put_u8(20);
+                database_name.encode(buf);
+            }
+        }
+    }
+}
+
+impl Decode for LogRecord {
+    fn decode(buf: &mut impl Buf) -> Result<Self, SQLError> {
+        if !buf.has_remaining() {
+            return Err(corrupted("empty log record"));
+        }
+        match buf.get_u8() {
+            0 => Ok(LogRecord::CreateSchema(
+                String::decode(buf)?,
+                String::decode(buf)?,
+            )),
+            1 => Ok(LogRecord::DropSchema(
+                String::decode(buf)?,
+                String::decode(buf)?,
+            )),
+            2 => Ok(LogRecord::CreateTable(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                TableDefinition::decode(buf)?,
+            )),
+            3 => Ok(LogRecord::DropTable(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                String::decode(buf)?,
+            )),
+            4 => Ok(LogRecord::Insert(Decode::decode(buf)?, Vec::decode(buf)?)),
+            5 => Ok(LogRecord::Update(Decode::decode(buf)?, Vec::decode(buf)?)),
+            6 => Ok(LogRecord::Delete(Decode::decode(buf)?, Vec::decode(buf)?)),
+            7 => Ok(LogRecord::CreateIndex(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                String::decode(buf)?,
+                IndexDefinition::decode(buf)?,
+            )),
+            8 => Ok(LogRecord::DropIndex(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                String::decode(buf)?,
+                String::decode(buf)?,
+            )),
+            9 => Ok(LogRecord::CreateSequence(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                SequenceDefinition::decode(buf)?,
+            )),
+            10 => Ok(LogRecord::DropSequence(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                String::decode(buf)?,
+            )),
+            11 => Ok(LogRecord::SetSequence(
+                Decode::decode(buf)?,
+                i64::decode(buf)?,
+            )),
+            12 => Ok(LogRecord::SetSequenceOwner(
+                Decode::decode(buf)?,
+                Option::decode(buf)?,
+            )),
+            13 => Ok(LogRecord::AlterTable(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                TableDefinition::decode(buf)?,
+            )),
+            14 => Ok(LogRecord::RewriteTable(
+                Decode::decode(buf)?,
+                Vec::decode(buf)?,
+            )),
+            15 => Ok(LogRecord::SetStatistics(
+                Decode::decode(buf)?,
+                TableStatistics::decode(buf)?,
+            )),
+            16 => Ok(LogRecord::CreateFunction(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                FunctionDefinition::decode(buf)?,
+            )),
+            17 => Ok(LogRecord::DropFunction(
+                String::decode(buf)?,
+                String::decode(buf)?,
+                String::decode(buf)?,
+            )),
+            18 => Ok(LogRecord::Checkpoint(Lsn(u64::decode(buf)?))),
+            19 => Ok(LogRecord::CreateDatabase(String::decode(buf)?)),
+            20 => Ok(LogRecord::DropDatabase(String::decode(buf)?)),
+            tag => Err(corrupted(format!("invalid log record tag: {}", tag))),
+        }
+    }
+}
+
+/// When the log is flushed to disk with `fsync`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Every change is synced before it's applied.
+    Always,
+    /// Sync at most once in the interval, the latest changes may be lost on crash.
+    Interval(Duration),
+    /// Leave it to the OS.
+    Never,
+}
+
+impl FromStr for SyncPolicy {
+    type Err = String;
+
+    /// Parse from `always`, `never` or an interval in milliseconds like `100ms`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "always" => Ok(SyncPolicy::Always),
+            "never" => Ok(SyncPolicy::Never),
+            interval => interval
+                .strip_suffix("ms")
+                .and_then(|millis| millis.parse::<u64>().ok())
+                .map(|millis| SyncPolicy::Interval(Duration::from_millis(millis)))
+                .ok_or_else(|| format!("invalid sync policy: {}", s)),
+        }
+    }
+}
+
+/// Write-ahead log, an append-only file of log records.
+/// Each record is framed as `| length: u32 | crc32: u32 | payload |`.
+///
+/// The log is truncated by checkpoints, the LSNs keep growing since the
+/// truncated log starts from the LSN in its `Checkpoint` record.
+pub struct Wal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    policy: SyncPolicy,
+    next_lsn: Lsn,
+    last_sync: Instant,
+}
+
+impl Wal {
+    /// Open the log at the given path, and read all the valid records in it for recovery.
+    /// An incomplete record at the end, e.g. caused by crash during writing, is truncated.
+    pub fn open(
+        path: &Path,
+        policy: SyncPolicy,
+    ) -> Result<(Self, Vec<(Lsn, LogRecord)>), SQLError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)
+            .map_err(io_error)?;
+
+        let mut content = vec![];
+        file.read_to_end(&mut content).map_err(io_error)?;
+        let (mut records, valid_len) = Self::read_records(&content);
+        let base = match records.first() {
+            Some((_, LogRecord::Checkpoint(lsn))) => lsn.0,
+            _ => 0,
+        };
+        for (lsn, _) in records.iter_mut() {
+            lsn.0 += base;
+        }
+        if valid_len < content.len() {
+            log::warn!(
+                "Truncate invalid WAL data at {}, {} bytes discarded",
+                Lsn(base + valid_len as u64),
+                content.len() - valid_len
+            );
+            file.set_len(valid_len as u64).map_err(io_error)?;
+        }
+        file.seek(SeekFrom::Start(valid_len as u64))
+            .map_err(io_error)?;
+
+        let wal = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            policy,
+            next_lsn: Lsn(base + valid_len as u64),
+            last_sync: Instant::now(),
+        };
+
+        Ok((wal, records))
+    }
+
+    /// Read records until the first invalid one, returns the records with their end LSN and
+    /// the length of valid data.
+    fn read_records(mut content: &[u8]) -> (Vec<(Lsn, LogRecord)>, usize) {
+        let mut records = vec![];
+        let mut valid_len = 0;
+        while content.len() >= RECORD_HEADER_SIZE {
+            let len = (&content[0..4]).get_u32() as usize;
+            let checksum = (&content[4..8]).get_u32();
+            let Some(mut payload) = content[RECORD_HEADER_SIZE..].get(..len) else {
+                break;
+            };
+            if crc32fast::hash(payload) != checksum {
+                break;
+            }
+            let Ok(record) = LogRecord::decode(&mut payload) else {
+                break;
+            };
+
+            valid_len += RECORD_HEADER_SIZE + len;
+            records.push((Lsn(valid_len as u64), record));
+            content = &content[RECORD_HEADER_SIZE + len..];
+        }
+
+        (records, valid_len)
+    }
+
+    /// Append a record to the log, it's not durable until `commit()`.
+    pub fn append(&mut self, record: &LogRecord) -> Result<Lsn, SQLError> {
+        let mut payload = BytesMut::new();
+        record.encode(&mut payload);
+
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        (&mut header[0..4]).put_u32(payload.len() as u32);
+        (&mut header[4..8]).put_u32(crc32fast::hash(&payload));
+        self.writer.write_all(&header).map_err(io_error)?;
+        self.writer.write_all(&payload).map_err(io_error)?;
+
+        let lsn = self.next_lsn;
+        self.next_lsn = Lsn(lsn.0 + (RECORD_HEADER_SIZE + payload.len()) as u64);
+        Ok(lsn)
+    }
+
+    /// Write the appended records to the file, and sync it as required by the policy.
+    pub fn commit(&mut self) -> Result<(), SQLError> {
+        self.writer.flush().map_err(io_error)?;
+        let sync = match self.policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::Interval(interval) => self.last_sync.elapsed() >= interval,
+            SyncPolicy::Never => false,
+        };
+        if sync {
+            self.sync()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn sync(&mut self) -> Result<(), SQLError> {
+        self.writer.flush().map_err(io_error)?;
+        self.writer.get_ref().sync_data().map_err(io_error)?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// The LSN of next record.
+    pub fn next_lsn(&self) -> Lsn {
+        self.next_lsn
+    }
+
+    /// Replace the log with the records, which should recreate the current state
+    /// with the heap files synced. The new log is written aside and renamed over
+    /// the old one, so either of them is complete on crash.
+    pub fn truncate(&mut self, records: &[LogRecord]) -> Result<(), SQLError> {
+        self.sync()?;
+        let temp_path = self.path.with_extension("tmp");
+        let file = File::create(&temp_path).map_err(io_error)?;
+        let mut wal = Self {
+            path: self.path.clone(),
+            writer: BufWriter::new(file),
+            policy: self.policy,
+            next_lsn: self.next_lsn,
+            last_sync: Instant::now(),
+        };
+        wal.append(&LogRecord::Checkpoint(self.next_lsn))?;
+        for record in records.iter() {
+            wal.append(record)?;
+        }
+        wal.sync()?;
+
+        std::fs::rename(&temp_path, &self.path).map_err(io_error)?;
+        if let Some(dir) = self.path.parent() {
+            File::open(dir)
+                .and_then(|dir| dir.sync_all())
+                .map_err(io_error)?;
+        }
+        *self = wal;
+
+        Ok(())
+    }
+}
+
+impl Drop for Wal {
+    fn drop(&mut self) {
+        let _ = self.sync();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Path of the log in a fresh directory for the test.
+    fn wal_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("leisql-wal-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("wal")
+    }
+
+    fn write_records(path: &Path, records: &[LogRecord]) -> Vec<Lsn> {
+        let (mut wal, _) = Wal::open(path, SyncPolicy::Always).unwrap();
+        let lsns = records
+            .iter()
+            .map(|record| wal.append(record).unwrap())
+            .collect();
+        wal.commit().unwrap();
+        lsns
+    }
+
+    fn read_records(path: &Path) -> Vec<String> {
+        let (_, records) = Wal::open(path, SyncPolicy::Always).unwrap();
+        records
+            .iter()
+            .map(|(_, record)| format!("{:?}", record))
+            .collect()
+    }
+
+    #[test]
+    fn replay() {
+        let path = wal_path("replay");
+        write_records(
+            &path,
+            &[
+                LogRecord::CreateSchema("db".to_string(), "a".to_string()),
+                LogRecord::DropTable("db".to_string(), "a".to_string(), "t".to_string()),
+            ],
+        );
+        // Records are appended after the existing ones
+        write_records(
+            &path,
+            &[LogRecord::DropSchema("db".to_string(), "a".to_string())],
+        );
+
+        assert_eq!(
+            read_records(&path),
+            [
+                "CreateSchema(\"db\", \"a\")",
+                "DropTable(\"db\", \"a\", \"t\")",
+                "DropSchema(\"db\", \"a\")"
+            ]
+        );
+    }
+
+    #[test]
+    fn torn_record() {
+        let path = wal_path("torn");
+        let lsns = write_records(
+            &path,
+            &[
+                LogRecord::CreateSchema("db".to_string(), "a".to_string()),
+                LogRecord::CreateSchema("db".to_string(), "b".to_string()),
+            ],
+        );
+
+        // The last record is partially written
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+
+        let (wal, records) = Wal::open(&path, SyncPolicy::Always).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(wal.next_lsn(), lsns[1]);
+        drop(wal);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), lsns[1].0);
+
+        // The new records replace the discarded one
+        write_records(
+            &path,
+            &[LogRecord::CreateSchema("db".to_string(), "c".to_string())],
+        );
+        assert_eq!(
+            read_records(&path),
+            ["CreateSchema(\"db\", \"a\")", "CreateSchema(\"db\", \"c\")"]
+        );
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let path = wal_path("checksum");
+        let lsns = write_records(
+            &path,
+            &[
+                LogRecord::CreateSchema("db".to_string(), "a".to_string()),
+                LogRecord::CreateSchema("db".to_string(), "b".to_string()),
+                LogRecord::CreateSchema("db".to_string(), "c".to_string()),
+            ],
+        );
+
+        // Corrupt the payload of the second record, it and the ones after it
+        // are discarded
+        let mut content = std::fs::read(&path).unwrap();
+        content[lsns[1].0 as usize + RECORD_HEADER_SIZE + 1] ^= 0xff;
+        std::fs::write(&path, content).unwrap();
+
+        assert_eq!(read_records(&path), ["CreateSchema(\"db\", \"a\")"]);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), lsns[1].0);
+    }
+
+    #[test]
+    fn checkpoint_truncation() {
+        let path = wal_path("checkpoint");
+        write_records(
+            &path,
+            &[
+                LogRecord::CreateSchema("db".to_string(), "a".to_string()),
+                LogRecord::DropSchema("db".to_string(), "a".to_string()),
+            ],
+        );
+
+        let (mut wal, _) = Wal::open(&path, SyncPolicy::Always).unwrap();
+        let checkpoint_lsn = wal.next_lsn();
+        wal.truncate(&[LogRecord::CreateSchema("db".to_string(), "b".to_string())])
+            .unwrap();
+        let next_lsn = wal.next_lsn();
+        drop(wal);
+
+        // The log starts with the checkpoint, and the LSNs keep growing
+        let (wal, records) = Wal::open(&path, SyncPolicy::Always).unwrap();
+        assert_eq!(wal.next_lsn(), next_lsn);
+        assert!(next_lsn > checkpoint_lsn);
+        let records = records
+            .iter()
+            .map(|(_, record)| format!("{:?}", record))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records,
+            [
+                format!("Checkpoint({:?})", checkpoint_lsn),
+                "CreateSchema(\"db\", \"b\")".to_string()
+            ]
+        );
+        assert!(!path.with_extension("tmp").exists());
+    }
+}
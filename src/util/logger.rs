@@ -0,0 +1,344 @@
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use super::format_timestamp;
+use crate::core::Json;
+
+/// Levels of the log records, by the module emitting them, e.g.
+/// `info,leisql::storage=debug,pgwire=warn`.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// Levels of the modules and their submodules, the most specific module
+    /// wins.
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    pub fn new(default: LevelFilter) -> Self {
+        Self {
+            default,
+            modules: vec![],
+        }
+    }
+
+    /// Level of the records emitted by the module.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || target
+                        .strip_prefix(module.as_str())
+                        .map_or(false, |rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level of all the modules.
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    /// Parse from comma separated directives, either a level or
+    /// `module=level`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = LogFilter::new(LevelFilter::Info);
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parse_level = |level: &str| {
+                level
+                    .parse::<LevelFilter>()
+                    .map_err(|_| format!("invalid log level: {}", level))
+            };
+            match directive.split_once('=') {
+                Some((module, level)) => filter
+                    .modules
+                    .push((module.trim().to_string(), parse_level(level.trim())?)),
+                None => filter.default = parse_level(directive)?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Format of the log records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `2023-05-16T08:00:00.000Z INFO leisql::server - message`
+    Text,
+    /// One JSON object per line, with `timestamp`, `level`, `target` and
+    /// `message`.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("invalid log format: {}", s)),
+        }
+    }
+}
+
+/// Log file rotated once it exceeds the maximum size, the rotated files are
+/// renamed to `<path>.1`, `<path>.2`, ..., and the oldest ones are removed.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+enum LogOutput {
+    Stdout,
+    File(RotatingFile),
+}
+
+/// Logger of the server, records are filtered by their modules, and
+/// written to stdout or a log file.
+pub struct Logger {
+    filter: LogFilter,
+    format: LogFormat,
+    output: Mutex<LogOutput>,
+}
+
+impl Logger {
+    pub fn new(filter: LogFilter) -> Self {
+        Self {
+            filter,
+            format: LogFormat::Text,
+            output: Mutex::new(LogOutput::Stdout),
+        }
+    }
+
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Write to the file instead of stdout, which is rotated once it
+    /// exceeds `max_size` bytes, keeping at most `max_files` rotated files.
+    pub fn with_file(mut self, path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        self.output = Mutex::new(LogOutput::File(RotatingFile::open(
+            path, max_size, max_files,
+        )?));
+        Ok(self)
+    }
+
+    /// Install as the global logger, which can only be done once.
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        let max_level = self.filter.max_level();
+        log::set_logger(Box::leak(Box::new(self)))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    fn format_record(&self, record: &Record) -> String {
+        let timestamp = format_timestamp(SystemTime::now());
+        match self.format {
+            LogFormat::Text => format!(
+                "{} {} {} - {}\n",
+                timestamp,
+                record.level(),
+                record.target(),
+                record.args()
+            ),
+            LogFormat::Json => {
+                let string = |value: &dyn Display| Json::String(value.to_string());
+                let object = Json::Object(vec![
+                    ("timestamp".to_string(), Json::String(timestamp)),
+                    ("level".to_string(), string(&record.level())),
+                    ("target".to_string(), string(&record.target())),
+                    ("message".to_string(), string(record.args())),
+                ]);
+                format!("{}\n", object)
+            }
+        }
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = self.format_record(record);
+        // Failures of logging are ignored, there is nowhere to report them
+        let _ = match &mut *self.output.lock().unwrap() {
+            LogOutput::Stdout => io::stdout().lock().write_all(line.as_bytes()),
+            LogOutput::File(file) => file.write_line(&line),
+        };
+    }
+
+    fn flush(&self) {
+        let _ = match &mut *self.output.lock().unwrap() {
+            LogOutput::Stdout => io::stdout().flush(),
+            LogOutput::File(file) => file.file.flush(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    fn log(logger: &Logger, level: Level, target: &str, message: &str) {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn filter_by_module() {
+        let filter = "warn, leisql=info,leisql::storage=debug"
+            .parse::<LogFilter>()
+            .unwrap();
+        assert_eq!(filter.level("pgwire::api"), LevelFilter::Warn);
+        assert_eq!(filter.level("leisql"), LevelFilter::Info);
+        assert_eq!(filter.level("leisql::server"), LevelFilter::Info);
+        assert_eq!(filter.level("leisql::storage::wal"), LevelFilter::Debug);
+        // Only the submodules separated by `::` match
+        assert_eq!(filter.level("leisql_ext"), LevelFilter::Warn);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+        assert_eq!(
+            "leisql=loud".parse::<LogFilter>().err().unwrap(),
+            "invalid log level: loud"
+        );
+    }
+
+    #[test]
+    fn rotate_json_file() {
+        let dir = std::env::temp_dir().join(format!("leisql-logger-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("leisql.log");
+
+        let filter = "info".parse().unwrap();
+        let logger = Logger::new(filter)
+            .with_format(LogFormat::Json)
+            .with_file(&path, 100, 2)
+            .unwrap();
+        log(&logger, Level::Debug, "leisql::server", "skipped");
+        for i in 0..4 {
+            log(
+                &logger,
+                Level::Info,
+                "leisql::server",
+                &format!("message {}", i),
+            );
+        }
+
+        // Each file holds a record, the oldest one is removed
+        let read = |path: &Path| {
+            let line = std::fs::read_to_string(path).unwrap();
+            match Json::parse(line.trim_end()).unwrap() {
+                Json::Object(fields) => fields,
+                json => panic!("not an object: {}", json),
+            }
+        };
+        let fields = read(&path);
+        assert_eq!(
+            fields
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            ["timestamp", "level", "target", "message"]
+        );
+        assert_eq!(fields[1].1, Json::String("INFO".to_string()));
+        assert_eq!(fields[2].1, Json::String("leisql::server".to_string()));
+        assert_eq!(fields[3].1, Json::String("message 3".to_string()));
+        let message = |path: PathBuf| read(&path)[3].1.clone();
+        assert_eq!(
+            message(dir.join("leisql.log.1")),
+            Json::String("message 2".to_string())
+        );
+        assert_eq!(
+            message(dir.join("leisql.log.2")),
+            Json::String("message 1".to_string())
+        );
+        assert!(!dir.join("leisql.log.3").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{core::ErrorKind, sql::session::SQLKind};
+
+lazy_static! {
+    /// Metrics of the whole process, exposed in Prometheus text format.
+    pub static ref METRICS: Metrics = Metrics::default();
+}
+
+/// Whether the execution time of operators is measured, which is only done
+/// if the metrics are exposed, because it's taken on every call of them.
+static OPERATOR_TIMING: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_operator_timing() {
+    OPERATOR_TIMING.store(true, Ordering::Relaxed);
+}
+
+pub fn operator_timing_enabled() -> bool {
+    OPERATOR_TIMING.load(Ordering::Relaxed)
+}
+
+const SQL_KINDS: [&str; 5] = ["query", "execute", "update", "delete", "copy"];
+
+const ERROR_KINDS: [&str; 7] = [
+    "ParseError",
+    "PlannerError",
+    "CatalogError",
+    "TypeError",
+    "RuntimeError",
+    "StorageError",
+    "UnknownError",
+];
+
+/// Upper bounds of the buckets of query duration, in seconds.
+const DURATION_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// Names of the operators timed by `record_operator`, indexed by
+/// `Executor::index`.
+pub const OPERATORS: [&str; 25] = [
+    "Pipeline",
+    "NestedLoopJoin",
+    "HashJoin",
+    "HashSemiJoin",
+    "NestedLoopSemiJoin",
+    "NestedLoopLeftOuterJoin",
+    "HashAggregate",
+    "StreamAggregate",
+    "RowCount",
+    "Window",
+    "Sort",
+    "Distinct",
+    "Scan",
+    "IndexScan",
+    "DDL",
+    "DML",
+    "CreateTableAs",
+    "Use",
+    "SetVariable",
+    "Values",
+    "Unnest",
+    "Explain",
+    "Limit",
+    "Apply",
+    "UnionAll",
+];
+
+/// Histogram of durations, the counts of buckets are not cumulative.
+struct Histogram {
+    buckets: [AtomicU64; DURATION_BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: Default::default(),
+            count: AtomicU64::new(0),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = DURATION_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    connections_total: AtomicU64,
+    connections: AtomicI64,
+    queries_total: [AtomicU64; SQL_KINDS.len()],
+    query_duration: Histogram,
+    rows_returned_total: AtomicU64,
+    errors_total: [AtomicU64; ERROR_KINDS.len()],
+    operator_nanos: [AtomicU64; OPERATORS.len()],
+}
+
+impl Metrics {
+    pub fn connection_opened(&self) {
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_closed(&self) {
+        self.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Record a finished statement.
+    pub fn record_query(&self, kind: SQLKind, duration: Duration) {
+        let index = match kind {
+            SQLKind::Query => 0,
+            SQLKind::Execute => 1,
+            SQLKind::Update => 2,
+            SQLKind::Delete => 3,
+            SQLKind::Copy => 4,
+        };
+        self.queries_total[index].fetch_add(1, Ordering::Relaxed);
+        self.query_duration.observe(duration);
+    }
+
+    pub fn record_rows(&self, rows: usize) {
+        self.rows_returned_total
+            .fetch_add(rows as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self, kind: &ErrorKind) {
+        let index = match kind {
+            ErrorKind::ParseError => 0,
+            ErrorKind::PlannerError => 1,
+            ErrorKind::CatalogError => 2,
+            ErrorKind::TypeError => 3,
+            ErrorKind::RuntimeError => 4,
+            ErrorKind::StorageError => 5,
+            ErrorKind::UnknownError => 6,
+        };
+        self.errors_total[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add the time spent in the operator, excluding its inputs.
+    pub fn record_operator(&self, operator: usize, nanos: u64) {
+        self.operator_nanos[operator].fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Render in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let load = |value: &AtomicU64| value.load(Ordering::Relaxed);
+
+        header(
+            &mut out,
+            "leisql_connections_total",
+            "counter",
+            "Connections accepted.",
+        );
+        let _ = writeln!(
+            out,
+            "leisql_connections_total {}",
+            load(&self.connections_total)
+        );
+        header(&mut out, "leisql_connections", "gauge", "Open connections.");
+        let _ = writeln!(
+            out,
+            "leisql_connections {}",
+            self.connections.load(Ordering::Relaxed)
+        );
+
+        header(
+            &mut out,
+            "leisql_queries_total",
+            "counter",
+            "Statements executed, by kind.",
+        );
+        for (kind, count) in SQL_KINDS.iter().zip(self.queries_total.iter()) {
+            let _ = writeln!(
+                out,
+                "leisql_queries_total{{kind=\"{}\"}} {}",
+                kind,
+                load(count)
+            );
+        }
+
+        header(
+            &mut out,
+            "leisql_query_duration_seconds",
+            "histogram",
+            "Time of executing statements.",
+        );
+        let mut cumulative = 0;
+        for (bound, count) in DURATION_BUCKETS
+            .iter()
+            .zip(self.query_duration.buckets.iter())
+        {
+            cumulative += load(count);
+            let _ = writeln!(
+                out,
+                "leisql_query_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound, cumulative
+            );
+        }
+        let count = load(&self.query_duration.count);
+        let _ = writeln!(
+            out,
+            "leisql_query_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            count
+        );
+        let _ = writeln!(
+            out,
+            "leisql_query_duration_seconds_sum {}",
+            load(&self.query_duration.sum_nanos) as f64 / 1e9
+        );
+        let _ = writeln!(out, "leisql_query_duration_seconds_count {}", count);
+
+        header(
+            &mut out,
+            "leisql_rows_returned_total",
+            "counter",
+            "Rows returned by queries.",
+        );
+        let _ = writeln!(
+            out,
+            "leisql_rows_returned_total {}",
+            load(&self.rows_returned_total)
+        );
+
+        header(
+            &mut out,
+            "leisql_errors_total",
+            "counter",
+            "Errors returned to clients, by kind.",
+        );
+        for (kind, count) in ERROR_KINDS.iter().zip(self.errors_total.iter()) {
+            let _ = writeln!(
+                out,
+                "leisql_errors_total{{kind=\"{}\"}} {}",
+                kind,
+                load(count)
+            );
+        }
+
+        header(
+            &mut out,
+            "leisql_operator_seconds_total",
+            "counter",
+            "Time spent in operators, excluding their inputs.",
+        );
+        for (operator, nanos) in OPERATORS.iter().zip(self.operator_nanos.iter()) {
+            let _ = writeln!(
+                out,
+                "leisql_operator_seconds_total{{operator=\"{}\"}} {}",
+                operator,
+                load(nanos) as f64 / 1e9
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, typ: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, typ);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_cumulative_buckets() {
+        let metrics = Metrics::default();
+        metrics.record_query(SQLKind::Query, Duration::from_micros(500));
+        metrics.record_query(SQLKind::Query, Duration::from_millis(30));
+        metrics.record_query(SQLKind::Delete, Duration::from_secs(10));
+        metrics.record_error(&ErrorKind::CatalogError);
+
+        let out = metrics.render();
+        let samples = [
+            "leisql_queries_total{kind=\"query\"} 2",
+            "leisql_queries_total{kind=\"delete\"} 1",
+            "leisql_query_duration_seconds_bucket{le=\"0.001\"} 1",
+            "leisql_query_duration_seconds_bucket{le=\"0.025\"} 1",
+            "leisql_query_duration_seconds_bucket{le=\"0.05\"} 2",
+            "leisql_query_duration_seconds_bucket{le=\"5\"} 2",
+            "leisql_query_duration_seconds_bucket{le=\"+Inf\"} 3",
+            "leisql_query_duration_seconds_sum 10.0305",
+            "leisql_query_duration_seconds_count 3",
+            "leisql_errors_total{kind=\"CatalogError\"} 1",
+            "leisql_errors_total{kind=\"TypeError\"} 0",
+        ];
+        for sample in samples {
+            assert!(out.lines().any(|line| line == sample), "{}", sample);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::net::SocketAddr;
+
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use leisql::{
+    catalog::user::UserCatalog,
+    server::{
+        auth::{AuthMethod, MakeAuthenticator},
+        MakePostgresHandler,
+    },
+    Database,
+};
+use pgwire::{api::MakeHandler, tokio::process_socket};
+use postgres_protocol::{
+    authentication::{
+        md5_hash,
+        sasl::{ChannelBinding, ScramSha256, SCRAM_SHA_256},
+    },
+    message::{backend::Message, frontend},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Start a server with the authentication method and a single user `alice`
+/// whose password is `secret`.
+async fn start_server(method: AuthMethod) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let users = UserCatalog::parse("alice:secret").unwrap();
+    let authenticator = MakeAuthenticator::new(method, users);
+    let processor = MakePostgresHandler::new(Database::in_memory(), authenticator);
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let processor = processor.make();
+            tokio::spawn(process_socket(
+                socket,
+                None,
+                processor.clone(),
+                processor.clone(),
+                processor,
+            ));
+        }
+    });
+
+    addr
+}
+
+async fn send(stream: &mut TcpStream, buf: &mut BytesMut) {
+    stream.write_all(buf).await.unwrap();
+    buf.clear();
+}
+
+async fn receive(stream: &mut TcpStream, buf: &mut BytesMut) -> Message {
+    loop {
+        if let Some(message) = Message::parse(buf).unwrap() {
+            return message;
+        }
+        assert!(stream.read_buf(buf).await.unwrap() > 0, "connection closed");
+    }
+}
+
+/// Log in as the user with the password, returns the error reported by the
+/// server if it fails.
+async fn login(addr: SocketAddr, user: &str, password: &str) -> Result<(), String> {
+    login_with(addr, user, password, &[]).await
+}
+
+/// Log in with the startup parameters besides `user`.
+async fn login_with(
+    addr: SocketAddr,
+    user: &str,
+    password: &str,
+    parameters: &[(&str, &str)],
+) -> Result<(), String> {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut output = BytesMut::new();
+    let mut input = BytesMut::new();
+    let parameters = [("user", user)]
+        .into_iter()
+        .chain(parameters.iter().copied());
+    frontend::startup_message(parameters, &mut output).unwrap();
+    send(&mut stream, &mut output).await;
+
+    let mut scram = None;
+    loop {
+        match receive(&mut stream, &mut input).await {
+            Message::AuthenticationOk => return Ok(()),
+            Message::AuthenticationCleartextPassword => {
+                frontend::password_message(password.as_bytes(), &mut output).unwrap();
+            }
+            Message::AuthenticationMd5Password(body) => {
+                let hashed = md5_hash(user.as_bytes(), password.as_bytes(), body.salt());
+                frontend::password_message(hashed.as_bytes(), &mut output).unwrap();
+            }
+            Message::AuthenticationSasl(body) => {
+                let mechanisms = body.mechanisms().collect::<Vec<_>>().unwrap();
+                assert!(mechanisms.contains(&SCRAM_SHA_256));
+                let client = ScramSha256::new(password.as_bytes(), ChannelBinding::unsupported());
+                frontend::sasl_initial_response(SCRAM_SHA_256, client.message(), &mut output)
+                    .unwrap();
+                scram = Some(client);
+            }
+            Message::AuthenticationSaslContinue(body) => {
+                let client = scram.as_mut().unwrap();
+                client.update(body.data()).unwrap();
+                frontend::sasl_response(client.message(), &mut output).unwrap();
+            }
+            Message::AuthenticationSaslFinal(body) => {
+                scram
+                    .take()
+                    .unwrap()
+                    .finish(body.data())
+                    .map_err(|e| e.to_string())?;
+            }
+            Message::ErrorResponse(body) => {
+                let message = body
+                    .fields()
+                    .find(|field| Ok(field.type_() == b'M'))
+                    .unwrap()
+                    .map(|field| field.value().to_string());
+                return Err(message.unwrap_or_default());
+            }
+            _ => panic!("unexpected message"),
+        }
+        send(&mut stream, &mut output).await;
+    }
+}
+
+/// The user is logged in with the right password, a wrong password and an
+/// unknown user fail with the same error.
+async fn check_login(method: AuthMethod) {
+    let addr = start_server(method).await;
+
+    login(addr, "alice", "secret").await.unwrap();
+    let wrong_password = login(addr, "alice", "guess").await.unwrap_err();
+    let unknown_user = login(addr, "bob", "secret").await.unwrap_err();
+    assert_eq!(wrong_password, unknown_user);
+}
+
+#[tokio::test]
+async fn cleartext_password() {
+    check_login(AuthMethod::Password).await;
+}
+
+#[tokio::test]
+async fn md5_password() {
+    check_login(AuthMethod::Md5).await;
+}
+
+#[tokio::test]
+async fn scram_sha_256() {
+    check_login(AuthMethod::ScramSha256).await;
+}
+
+/// Connections to the database or schema that doesn't exist are rejected.
+#[tokio::test]
+async fn nonexistent_database() {
+    let addr = start_server(AuthMethod::Password).await;
+
+    login_with(addr, "alice", "secret", &[("database", "leisql")])
+        .await
+        .unwrap();
+    let error = login_with(addr, "alice", "secret", &[("database", "missing")])
+        .await
+        .unwrap_err();
+    assert_eq!(error, "database \"missing\" does not exist");
+    let error = login_with(
+        addr,
+        "alice",
+        "secret",
+        &[("options", "-c search_path=missing")],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error, "schema \"missing\" does not exist");
+}
@@ -0,0 +1,344 @@
+use std::{
+    io::Write,
+    net::SocketAddr,
+    process::{Command, Output, Stdio},
+};
+
+use leisql::{
+    catalog::user::UserCatalog,
+    cli::{Backend, Client, Repl},
+    server::{
+        auth::{AuthMethod, MakeAuthenticator},
+        MakePostgresHandler,
+    },
+    Database,
+};
+use pgwire::{api::MakeHandler, tokio::process_socket};
+use tokio::net::TcpListener;
+
+/// Shell of an in-memory database reading from a pipe, so there are no
+/// prompts.
+fn new_repl() -> Repl {
+    Repl::new(Backend::Embedded(Database::in_memory().connect())).with_prompts(false)
+}
+
+/// Feed the lines to the shell, returns its output.
+fn run(repl: &mut Repl, input: &str) -> String {
+    let mut output = vec![];
+    repl.run(input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Lines are accumulated until the statement ends with `;`, and `\q` quits
+/// the shell without reading the rest.
+#[test]
+fn multi_line_statements() {
+    let mut repl = new_repl();
+    let output = run(
+        &mut repl,
+        "CREATE TABLE t (a INT, b VARCHAR);\n\
+         INSERT INTO t\n\
+         VALUES (1, 'x'),\n\
+         \n\
+         (2, 'y');\n\
+         SELECT a, b\n\
+         FROM t ORDER BY a; SELECT 3 AS c;\n\
+         \\q\n\
+         SELECT 4;\n",
+    );
+    assert_eq!(
+        output,
+        "OK\nOK\n a | b \n---+---\n 1 | x\n 2 | y\n(2 rows)\n\n c \n---\n 3\n(1 row)\n\n"
+    );
+    assert!(!repl.failed());
+
+    // The rest statements of the input are skipped once one of them fails
+    let output = run(
+        &mut repl,
+        "SELECT 1 AS a; SELECT * FROM missing; SELECT 2;\n",
+    );
+    assert_eq!(output, " a \n---\n 1\n(1 row)\n\n");
+    assert!(repl.failed());
+
+    // An incomplete statement at EOF is not executed
+    let mut repl = new_repl();
+    assert_eq!(run(&mut repl, "SELECT 1\n"), "");
+}
+
+/// `\dt`, `\d` and `\l` describe the catalog, `\i` runs the statements in
+/// the file and `\timing` toggles timing.
+#[test]
+fn meta_commands() {
+    let mut repl = new_repl();
+    assert_eq!(run(&mut repl, "\\dt\n"), "Did not find any relations.\n");
+
+    let path = std::env::temp_dir().join(format!("leisql-script-{}.sql", std::process::id()));
+    std::fs::write(
+        &path,
+        "CREATE TABLE t (a INT PRIMARY KEY, b VARCHAR NOT NULL);\nCREATE TABLE u (c INT);\n",
+    )
+    .unwrap();
+    let output = run(&mut repl, &format!("\\i {}\n", path.display()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output, "OK\nOK\n");
+
+    let output = run(&mut repl, "\\dt\n\\d t\n\\l\n");
+    assert_eq!(
+        output,
+        concat!(
+            " Schema  | Name | Type  \n",
+            "---------+------+-------\n",
+            " default | t    | table\n",
+            " default | u    | table\n",
+            "(2 rows)\n",
+            "\n",
+            "Table \"t\"\n",
+            " Column |  Type   | Nullable | Default \n",
+            "--------+---------+----------+---------\n",
+            " a      | int4    | not null |\n",
+            " b      | varchar | not null |\n",
+            "Indexes:\n",
+            "    \"t_pkey\" PRIMARY KEY (a)\n",
+            "\n",
+            "  Name   \n",
+            "---------\n",
+            " default\n",
+            "(1 row)\n",
+            "\n",
+        )
+    );
+
+    let output = run(&mut repl, "\\timing\nSELECT 1 AS a;\n\\timing\n");
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[..6],
+        ["Timing is on.", " a ", "---", " 1", "(1 row)", ""]
+    );
+    assert!(lines[6].starts_with("Time: ") && lines[6].ends_with(" ms"));
+    assert_eq!(lines[7..], ["Timing is off."]);
+    assert!(!repl.failed());
+
+    // Errors of meta-commands are printed to stderr like the ones of
+    // statements
+    let output = run(&mut repl, "\\d missing\n\\i missing.sql\n\\foo\n");
+    assert_eq!(output, "invalid command \\foo, try \\? for help\n");
+    assert!(repl.failed());
+}
+
+/// `\pset` switches the rows to CSV, JSON or expanded output.
+#[test]
+fn output_formats() {
+    let mut repl = new_repl();
+    let setup = "CREATE TABLE t (a INT, b VARCHAR);\n\
+                 INSERT INTO t VALUES (1, 'x, \"y\"'), (NULL, 'long text');\n";
+    run(&mut repl, setup);
+    let query = "SELECT a, b FROM t ORDER BY a;\n";
+
+    let output = run(&mut repl, &format!("\\pset format csv\n{}", query));
+    assert_eq!(
+        output,
+        "Output format is csv.\na,b\n1,\"x, \"\"y\"\"\"\n,long text\n"
+    );
+
+    let output = run(&mut repl, &format!("\\pset format json\n{}", query));
+    assert_eq!(
+        output,
+        concat!(
+            "Output format is json.\n",
+            "[{\"a\": 1, \"b\": \"x, \\\"y\\\"\"}, {\"a\": null, \"b\": \"long text\"}]\n",
+        )
+    );
+
+    let output = run(
+        &mut repl,
+        &format!("\\pset format aligned\n\\x\n{}\\x off\n", query),
+    );
+    assert_eq!(
+        output,
+        concat!(
+            "Output format is aligned.\n",
+            "Expanded display is on.\n",
+            "-[ RECORD 1 ]\n",
+            "a | 1\n",
+            "b | x, \"y\"\n",
+            "-[ RECORD 2 ]\n",
+            "a |\n",
+            "b | long text\n",
+            "\n",
+            "Expanded display is off.\n",
+        )
+    );
+
+    // Invalid settings are reported, and the current ones are kept
+    let output = run(&mut repl, "\\pset format xml\n\\x maybe\n\\pset\n");
+    assert_eq!(
+        output,
+        concat!(
+            "\\pset: allowed formats are aligned, csv, json, not xml\n",
+            "unrecognized value \"maybe\" for \"expanded\": Boolean expected\n",
+            "format      aligned\n",
+            "expanded    off\n",
+        )
+    );
+    assert!(!repl.failed());
+}
+
+/// Run the shell binary with the arguments and the input.
+fn run_binary(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_leisql"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// The commands given with `-c` and `-f` are run in order, the exit status
+/// tells whether any of them failed.
+#[test]
+fn non_interactive() {
+    let path = std::env::temp_dir().join(format!("leisql-script-{}-f.sql", std::process::id()));
+    std::fs::write(
+        &path,
+        "INSERT INTO t VALUES (1), (2);\nSELECT a FROM t ORDER BY a",
+    )
+    .unwrap();
+    let output = run_binary(
+        &[
+            "--csv",
+            "-q",
+            "-c",
+            "CREATE TABLE t (a INT)",
+            "-f",
+            path.to_str().unwrap(),
+            "-c",
+            "UPDATE t SET a = a + 1",
+            "-c",
+            "SELECT sum(a) AS s FROM t",
+        ],
+        "",
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a\n1\n2\ns\n5\n");
+
+    // The rest commands are skipped once one of them fails
+    let output = run_binary(&["-c", "SELECT * FROM missing", "-c", "SELECT 1"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, b"");
+    assert_eq!(output.stderr, b"ERROR:  table missing not found\n");
+
+    // Commands are read from stdin without -c or -f, failures don't stop them
+    let output = run_binary(&[], "SELECT * FROM missing;\nSELECT 1 AS a;\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, b" a \n---\n 1\n(1 row)\n\n");
+
+    // Invalid usage and unreadable files
+    let output = run_binary(&["--bogus"], "");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("leisql: invalid option --bogus\n\nUsage: "));
+    let output = run_binary(&["-f", "missing.sql"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "leisql: missing.sql: No such file or directory (os error 2)\n"
+    );
+}
+
+/// Start a server with SCRAM authentication and a single user `alice` whose
+/// password is `secret`.
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let users = UserCatalog::parse("alice:secret").unwrap();
+    let authenticator = MakeAuthenticator::new(AuthMethod::ScramSha256, users);
+    let processor = MakePostgresHandler::new(Database::in_memory(), authenticator);
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let processor = processor.make();
+            tokio::spawn(process_socket(
+                socket,
+                None,
+                processor.clone(),
+                processor.clone(),
+                processor,
+            ));
+        }
+    });
+
+    addr
+}
+
+/// The statements and meta-commands of a shell connected to a server are
+/// run by the server.
+#[tokio::test(flavor = "multi_thread")]
+async fn remote_backend() {
+    let addr = start_server().await.to_string();
+    tokio::task::spawn_blocking(move || {
+        let err = Client::connect(&addr, "alice", None, Some("wrong"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.message,
+            "password authentication failed for user \"alice\""
+        );
+        assert_eq!(err.code, Some("28P01"));
+        let err = Client::connect(&addr, "alice", None, None).err().unwrap();
+        assert_eq!(err.code, Some("28P01"));
+
+        let client = Client::connect(&addr, "alice", None, Some("secret")).unwrap();
+        let mut repl = Repl::new(Backend::Remote(client)).with_prompts(false);
+        let output = run(
+            &mut repl,
+            "CREATE TABLE t (a INT NOT NULL, b VARCHAR);\n\
+             INSERT INTO t VALUES (1, 'x'), (2, NULL);\n\
+             UPDATE t SET b = 'y' WHERE a = 2;\n\
+             SELECT a, b FROM t ORDER BY a;\n\
+             \\dt\n\
+             \\d t\n",
+        );
+        assert_eq!(
+            output,
+            concat!(
+                "OK\n",
+                "OK\n",
+                "UPDATE 1\n",
+                " a | b \n",
+                "---+---\n",
+                " 1 | x\n",
+                " 2 | y\n",
+                "(2 rows)\n",
+                "\n",
+                " Schema  | Name | Type  \n",
+                "---------+------+-------\n",
+                " default | t    | table\n",
+                "(1 row)\n",
+                "\n",
+                "Table \"t\"\n",
+                " Column |       Type        | Nullable \n",
+                "--------+-------------------+----------\n",
+                " a      | integer           | not null\n",
+                " b      | character varying |\n",
+                "\n",
+            )
+        );
+        assert!(!repl.failed());
+
+        let output = run(&mut repl, "SELECT * FROM missing;\n\\d missing\n");
+        assert_eq!(output, "");
+        assert!(repl.failed());
+    })
+    .await
+    .unwrap();
+}
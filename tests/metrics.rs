@@ -0,0 +1,69 @@
+use leisql::{server::metrics::serve_metrics, Database};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Send the request to the metrics endpoint, returns the status line and
+/// the body of the response.
+async fn request(addr: &str, request_line: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(format!("{}\r\nHost: localhost\r\n\r\n", request_line).as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+/// Value of the sample in the metrics.
+fn sample(metrics: &str, name: &str) -> f64 {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("no sample {}", name))
+        .parse()
+        .unwrap()
+}
+
+/// The statements executed are counted in the metrics served over HTTP.
+#[tokio::test]
+async fn metrics_endpoint() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(serve_metrics(listener));
+
+    let (status, before) = request(&addr, "GET /metrics HTTP/1.1").await;
+    assert_eq!(status, "HTTP/1.1 200 OK");
+
+    let mut conn = Database::in_memory().connect();
+    conn.execute("CREATE TABLE t (a INT)", &[]).unwrap();
+    conn.execute("INSERT INTO t VALUES (1), (2), (3)", &[])
+        .unwrap();
+    conn.query("SELECT a FROM t WHERE a > 1", &[]).unwrap();
+
+    let (_, after) = request(&addr, "GET /metrics HTTP/1.1").await;
+    let increase = |name: &str| sample(&after, name) - sample(&before, name);
+    assert_eq!(increase("leisql_queries_total{kind=\"query\"}"), 1.0);
+    assert_eq!(increase("leisql_queries_total{kind=\"execute\"}"), 2.0);
+    assert_eq!(increase("leisql_query_duration_seconds_count"), 3.0);
+    assert_eq!(
+        increase("leisql_query_duration_seconds_bucket{le=\"+Inf\"}"),
+        3.0
+    );
+    assert_eq!(increase("leisql_rows_returned_total"), 2.0);
+    // Operators are timed once the metrics are served
+    assert!(increase("leisql_operator_seconds_total{operator=\"Scan\"}") > 0.0);
+    assert!(increase("leisql_operator_seconds_total{operator=\"DML\"}") > 0.0);
+
+    assert_eq!(
+        request(&addr, "GET /other HTTP/1.1").await.0,
+        "HTTP/1.1 404 Not Found"
+    );
+    assert_eq!(
+        request(&addr, "POST /metrics HTTP/1.1").await.0,
+        "HTTP/1.1 405 Method Not Allowed"
+    );
+}
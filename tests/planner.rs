@@ -0,0 +1,101 @@
+use std::{fs, path::Path};
+
+use leisql::{sql::parser::parse_sql, Database};
+
+const PLANNER_DIR: &str = "tests/planner";
+
+/// Golden-file tests of the planner. The queries in `tests/planner/*.sql` are
+/// bound against the tables created by `tests/planner/schema.sql`, and the
+/// optimized plans are compared with the ones in the `.plan` file next to
+/// them. Run with `UPDATE_PLANS=1` to accept the new plans.
+#[test]
+fn planner() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(PLANNER_DIR);
+    let update = std::env::var_os("UPDATE_PLANS").is_some();
+
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(&fs::read_to_string(dir.join("schema.sql")).unwrap(), &[])
+        .unwrap();
+
+    let mut cases = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension().map_or(false, |ext| ext == "sql")
+                && path.file_name().map_or(false, |name| name != "schema.sql")
+        })
+        .collect::<Vec<_>>();
+    cases.sort();
+
+    let mut mismatches = vec![];
+    for case in cases.iter() {
+        let statements = parse_sql(&fs::read_to_string(case).unwrap()).unwrap();
+        let mut output = String::new();
+        for statement in statements.iter() {
+            output.push_str(&format!("{};\n", statement));
+            match conn.session().plan(statement) {
+                Ok(plan) => output.push_str(&format!("{}\n", plan)),
+                Err(e) => output.push_str(&format!("ERROR: {}\n", e.message)),
+            }
+            output.push('\n');
+        }
+
+        let plan_file = case.with_extension("plan");
+        if update {
+            fs::write(&plan_file, &output).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&plan_file).unwrap_or_default();
+        if expected != output {
+            mismatches.push(format!(
+                "{}:\n{}",
+                plan_file.display(),
+                diff(&expected, &output)
+            ));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "plans changed, run with UPDATE_PLANS=1 to accept them\n\n{}",
+        mismatches.join("\n")
+    );
+}
+
+/// Lines only in the expected plans are prefixed with `-`, and the ones
+/// only in the actual plans with `+`.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    // Longest common subsequence of the lines
+    let mut lcs = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            result.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            result.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+
+    result
+}
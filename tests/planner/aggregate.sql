@@ -0,0 +1,5 @@
+select count(*) from p;
+select b, sum(a) from p group by b;
+select y, count(*) from q group by y having count(*) > 0;
+select distinct c from (select c, d from p) t;
+select a, rank() over (order by b desc) from p;
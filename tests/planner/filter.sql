@@ -0,0 +1,6 @@
+select * from u where true;
+select c from p where a = 2;
+select b from p where a > 1;
+select * from (select id, name from u) x where x.id = 3;
+select * from (select distinct uid from o) d where d.uid = 1;
+select * from u where missing = 1;
@@ -0,0 +1,5 @@
+select * from u, o where u.id = o.uid and u.id = 2 and o.amount > 10;
+select * from u left join o on u.id = o.uid where u.id = 1 and o.amount > 60;
+select c, y from p, q where p.a = q.x and p.b > 5;
+select * from u where id in (select uid from o) and id = 1;
+select * from u where exists (select 1 from o where o.uid = u.id and o.amount > 60);
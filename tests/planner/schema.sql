@@ -0,0 +1,5 @@
+create table u (id int primary key, name text);
+create table o (id int primary key, uid int, amount int);
+create index o_uid on o (uid);
+create table p (a int primary key, b int, c text, d text);
+create table q (x int, y text);
@@ -0,0 +1,4 @@
+select name, (select count(*) from o where o.uid = u.id) from u where id < 3;
+select id, array(select amount from o where o.uid = u.id order by amount) from u;
+select * from u where id > (select max(uid) from o);
+select uid, count(*) from o group by uid having count(*) > (select count(*) from u);
@@ -0,0 +1,423 @@
+use bytes::{BufMut, BytesMut};
+use fallible_iterator::FallibleIterator;
+use leisql::{
+    catalog::user::UserCatalog,
+    server::{
+        auth::{AuthMethod, MakeAuthenticator},
+        MakePostgresHandler,
+    },
+    Database,
+};
+use pgwire::{api::MakeHandler, tokio::process_socket};
+use postgres_protocol::{
+    message::{backend::Message, frontend},
+    IsNull,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// A client speaking the wire protocol, the messages received are described
+/// as strings to check the whole sequence.
+struct Client {
+    stream: TcpStream,
+    input: BytesMut,
+    output: BytesMut,
+}
+
+impl Client {
+    /// Start a server without authentication, and connect to it.
+    async fn connect() -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let users = UserCatalog::parse("alice:secret").unwrap();
+        let authenticator = MakeAuthenticator::new(AuthMethod::Trust, users);
+        let processor = MakePostgresHandler::new(Database::in_memory(), authenticator);
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let processor = processor.make();
+            process_socket(
+                socket,
+                None,
+                processor.clone(),
+                processor.clone(),
+                processor,
+            )
+            .await
+        });
+
+        let mut client = Client {
+            stream: TcpStream::connect(addr).await.unwrap(),
+            input: BytesMut::new(),
+            output: BytesMut::new(),
+        };
+        frontend::startup_message([("user", "alice")], &mut client.output).unwrap();
+        client.send().await;
+        client.receive_until_ready().await;
+        client
+    }
+
+    async fn send(&mut self) {
+        self.stream.write_all(&self.output).await.unwrap();
+        self.output.clear();
+    }
+
+    async fn receive(&mut self) -> Message {
+        loop {
+            if let Some(message) = Message::parse(&mut self.input).unwrap() {
+                return message;
+            }
+            let read = self.stream.read_buf(&mut self.input).await.unwrap();
+            assert!(read > 0, "connection closed");
+        }
+    }
+
+    /// Receive the messages up to `ReadyForQuery`.
+    async fn receive_until_ready(&mut self) -> Vec<String> {
+        let mut messages = vec![];
+        loop {
+            let message = self.receive().await;
+            let ready = matches!(message, Message::ReadyForQuery(_));
+            messages.push(describe(message));
+            if ready {
+                return messages;
+            }
+        }
+    }
+
+    async fn simple_query(&mut self, sql: &str) -> Vec<String> {
+        frontend::query(sql, &mut self.output).unwrap();
+        self.send().await;
+        self.receive_until_ready().await
+    }
+
+    fn parse(&mut self, name: &str, sql: &str) {
+        frontend::parse(name, sql, [], &mut self.output).unwrap();
+    }
+
+    /// Bind the parameters in text format.
+    fn bind(&mut self, portal: &str, statement: &str, parameters: &[&str]) {
+        self.bind_with_result_formats(portal, statement, parameters, &[]);
+    }
+
+    /// Bind the parameters in text format, and request the result columns in
+    /// the formats, 0 for text and 1 for binary.
+    fn bind_with_result_formats(
+        &mut self,
+        portal: &str,
+        statement: &str,
+        parameters: &[&str],
+        result_formats: &[i16],
+    ) {
+        frontend::bind(
+            portal,
+            statement,
+            [],
+            parameters.iter(),
+            |value, buf| {
+                buf.put_slice(value.as_bytes());
+                Ok(IsNull::No)
+            },
+            result_formats.iter().copied(),
+            &mut self.output,
+        )
+        .map_err(|_| "bind")
+        .unwrap();
+    }
+
+    fn describe_statement(&mut self, name: &str) {
+        frontend::describe(b'S', name, &mut self.output).unwrap();
+    }
+
+    fn execute(&mut self, portal: &str, max_rows: i32) {
+        frontend::execute(portal, max_rows, &mut self.output).unwrap();
+    }
+
+    async fn sync(&mut self) -> Vec<String> {
+        frontend::sync(&mut self.output);
+        self.send().await;
+        self.receive_until_ready().await
+    }
+}
+
+/// Describe the message by its type, with the first column of data row, the
+/// tag of command or SQLSTATE code of error.
+fn describe(message: Message) -> String {
+    match message {
+        Message::BindComplete => "BindComplete".to_string(),
+        Message::CloseComplete => "CloseComplete".to_string(),
+        Message::CommandComplete(body) => format!("CommandComplete {}", body.tag().unwrap()),
+        Message::DataRow(body) => {
+            let ranges = body.ranges().collect::<Vec<_>>().unwrap();
+            let value = ranges[0]
+                .clone()
+                .map(|range| String::from_utf8_lossy(&body.buffer()[range]).to_string());
+            format!("DataRow {}", value.as_deref().unwrap_or("NULL"))
+        }
+        Message::EmptyQueryResponse => "EmptyQueryResponse".to_string(),
+        Message::ErrorResponse(body) => {
+            let code = body
+                .fields()
+                .find(|field| Ok(field.type_() == b'C'))
+                .unwrap()
+                .map(|field| field.value().to_string());
+            format!("ErrorResponse {}", code.unwrap_or_default())
+        }
+        Message::NoData => "NoData".to_string(),
+        Message::ParameterDescription(_) => "ParameterDescription".to_string(),
+        Message::ParseComplete => "ParseComplete".to_string(),
+        Message::PortalSuspended => "PortalSuspended".to_string(),
+        Message::ReadyForQuery(_) => "ReadyForQuery".to_string(),
+        Message::RowDescription(_) => "RowDescription".to_string(),
+        _ => "Other".to_string(),
+    }
+}
+
+/// Once a message of extended query fails, the rest are discarded until
+/// `Sync`, which is answered with a single `ReadyForQuery`.
+#[tokio::test(flavor = "multi_thread")]
+async fn error_until_sync() {
+    let mut client = Client::connect().await;
+
+    client.parse("", "SELEC 1");
+    client.bind("", "", &[]);
+    client.describe_statement("");
+    client.execute("", 0);
+    assert_eq!(
+        client.sync().await,
+        ["ErrorResponse 42601", "ReadyForQuery"]
+    );
+
+    client
+        .simple_query("CREATE TABLE t (a int PRIMARY KEY); INSERT INTO t VALUES (1)")
+        .await;
+    client.parse("", "INSERT INTO t VALUES ($1)");
+    client.bind("", "", &["1"]);
+    client.execute("", 0);
+    client.execute("", 0);
+    assert_eq!(
+        client.sync().await,
+        [
+            "ParseComplete",
+            "BindComplete",
+            "ErrorResponse 23505",
+            "ReadyForQuery"
+        ]
+    );
+
+    client.parse("", "SELECT 1 AS a");
+    client.execute("missing", 0);
+    client.bind("", "", &[]);
+    assert_eq!(
+        client.sync().await,
+        ["ParseComplete", "ErrorResponse 34000", "ReadyForQuery"]
+    );
+
+    // The statement parsed before the error is kept
+    client.bind("", "", &[]);
+    client.execute("", 0);
+    assert_eq!(
+        client.sync().await,
+        [
+            "BindComplete",
+            "DataRow 1",
+            "CommandComplete SELECT 1",
+            "ReadyForQuery"
+        ]
+    );
+}
+
+/// The suspended portal is resumed by the next `Execute`, and an exhausted
+/// one completes without rows instead of running again.
+#[tokio::test(flavor = "multi_thread")]
+async fn suspended_portal() {
+    let mut client = Client::connect().await;
+    client
+        .simple_query("CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2), (3)")
+        .await;
+
+    client.parse("", "SELECT a FROM t ORDER BY a");
+    client.bind("", "", &[]);
+    client.execute("", 2);
+    client.execute("", 2);
+    client.execute("", 2);
+    assert_eq!(
+        client.sync().await,
+        [
+            "ParseComplete",
+            "BindComplete",
+            "DataRow 1",
+            "DataRow 2",
+            "PortalSuspended",
+            "DataRow 3",
+            "CommandComplete SELECT 1",
+            "CommandComplete SELECT 0",
+            "ReadyForQuery"
+        ]
+    );
+}
+
+/// Statements without result columns are described with `NoData`.
+#[tokio::test(flavor = "multi_thread")]
+async fn describe_statement() {
+    let mut client = Client::connect().await;
+    client.simple_query("CREATE TABLE t (a int)").await;
+
+    client.parse("select", "SELECT a FROM t WHERE a > $1");
+    client.parse("insert", "INSERT INTO t VALUES ($1)");
+    client.describe_statement("select");
+    client.describe_statement("insert");
+    assert_eq!(
+        client.sync().await,
+        [
+            "ParseComplete",
+            "ParseComplete",
+            "ParameterDescription",
+            "RowDescription",
+            "ParameterDescription",
+            "NoData",
+            "ReadyForQuery"
+        ]
+    );
+}
+
+/// Statements are completed with the command tags of Postgres, with the
+/// number of rows for DML, even if they return rows.
+#[tokio::test(flavor = "multi_thread")]
+async fn command_tags() {
+    let mut client = Client::connect().await;
+
+    let cases = [
+        (
+            "CREATE TABLE t (a int)",
+            vec!["CommandComplete CREATE TABLE"],
+        ),
+        (
+            "INSERT INTO t VALUES (1), (2)",
+            vec!["CommandComplete INSERT 0 2"],
+        ),
+        (
+            "INSERT INTO t VALUES (3) RETURNING a",
+            vec!["RowDescription", "DataRow 3", "CommandComplete INSERT 0 1"],
+        ),
+        (
+            "UPDATE t SET a = a + 10 WHERE a > 1",
+            vec!["CommandComplete UPDATE 2"],
+        ),
+        (
+            "DELETE FROM t WHERE a > 12 RETURNING a",
+            vec!["RowDescription", "DataRow 13", "CommandComplete DELETE 1"],
+        ),
+        (
+            "SELECT a FROM t ORDER BY a",
+            vec![
+                "RowDescription",
+                "DataRow 1",
+                "DataRow 12",
+                "CommandComplete SELECT 2",
+            ],
+        ),
+        (
+            "CREATE TABLE s AS SELECT a FROM t",
+            vec!["CommandComplete SELECT 2"],
+        ),
+        (
+            "PREPARE p AS INSERT INTO t VALUES ($1)",
+            vec!["CommandComplete PREPARE"],
+        ),
+        ("EXECUTE p (4)", vec!["CommandComplete INSERT 0 1"]),
+        ("DEALLOCATE p", vec!["CommandComplete DEALLOCATE"]),
+        (
+            "CREATE INDEX i ON t (a)",
+            vec!["CommandComplete CREATE INDEX"],
+        ),
+        ("DROP TABLE s", vec!["CommandComplete DROP TABLE"]),
+        ("SET application_name = psql", vec!["CommandComplete SET"]),
+        (
+            "SHOW application_name",
+            vec!["RowDescription", "DataRow psql", "CommandComplete SHOW"],
+        ),
+    ];
+    for (sql, expected) in cases {
+        let mut messages = client.simple_query(sql).await;
+        assert_eq!(messages.pop().as_deref(), Some("ReadyForQuery"));
+        assert_eq!(messages, expected, "{}", sql);
+    }
+
+    // An exhausted portal affects no more rows
+    client.parse("", "INSERT INTO t VALUES (5)");
+    client.bind("", "", &[]);
+    client.execute("", 0);
+    client.execute("", 0);
+    assert_eq!(
+        client.sync().await,
+        [
+            "ParseComplete",
+            "BindComplete",
+            "CommandComplete INSERT 0 1",
+            "CommandComplete INSERT 0 0",
+            "ReadyForQuery"
+        ]
+    );
+}
+
+/// The result columns requested in binary format are encoded in the binary
+/// forms of their types, including the declared widths of integers.
+#[tokio::test(flavor = "multi_thread")]
+async fn binary_result_format() {
+    let mut client = Client::connect().await;
+    client
+        .simple_query(
+            "CREATE TABLE t (a smallint, b int, c bigint, e text, \
+             f boolean, g uuid, h bigint[]); \
+             INSERT INTO t VALUES (1, 2, 3, 'x', true, \
+             'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11', ARRAY[7, NULL])",
+        )
+        .await;
+
+    client.parse("", "SELECT a, b, c, 1.5 AS d, e, f, g, h, a AS text FROM t");
+    client.bind_with_result_formats("", "", &[], &[1, 1, 1, 1, 1, 1, 1, 1, 0]);
+    client.execute("", 0);
+    frontend::sync(&mut client.output);
+    client.send().await;
+
+    assert!(matches!(client.receive().await, Message::ParseComplete));
+    assert!(matches!(client.receive().await, Message::BindComplete));
+    let Message::DataRow(body) = client.receive().await else {
+        panic!("expect DataRow");
+    };
+    let fields = body
+        .ranges()
+        .map(|range| Ok(range.map(|range| body.buffer()[range].to_vec())))
+        .collect::<Vec<_>>()
+        .unwrap();
+    // Dimensions, has NULL, element type, length and lower bound, then the
+    // elements prefixed with their lengths
+    let mut array = vec![];
+    for value in [1, 1, 20, 2, 1, 8] {
+        array.extend_from_slice(&i32::to_be_bytes(value));
+    }
+    array.extend_from_slice(&7i64.to_be_bytes());
+    array.extend_from_slice(&(-1i32).to_be_bytes());
+    let expected = [
+        1i16.to_be_bytes().to_vec(),
+        2i32.to_be_bytes().to_vec(),
+        3i64.to_be_bytes().to_vec(),
+        1.5f64.to_be_bytes().to_vec(),
+        b"x".to_vec(),
+        vec![1],
+        vec![
+            0xa0, 0xee, 0xbc, 0x99, 0x9c, 0x0b, 0x4e, 0xf8, 0xbb, 0x6d, 0x6b, 0xb9, 0xbd, 0x38,
+            0x0a, 0x11,
+        ],
+        array,
+        b"1".to_vec(),
+    ];
+    assert_eq!(fields, expected.map(Some));
+    assert_eq!(
+        client.receive_until_ready().await,
+        ["CommandComplete SELECT 1", "ReadyForQuery"]
+    );
+}
@@ -0,0 +1,283 @@
+use leisql::Database;
+
+fn data_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("leisql-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn ids(database: &Database) -> Vec<i64> {
+    database
+        .connect()
+        .query("SELECT id FROM t ORDER BY id", &[])
+        .unwrap()
+        .rows()
+        .map(|row| row.get::<i64>("id").unwrap())
+        .collect()
+}
+
+/// The changes are replayed from the WAL after the database is reopened,
+/// including the ones made after the log is truncated by `VACUUM`.
+#[test]
+fn replay_after_checkpoint() {
+    let dir = data_dir("recovery");
+    {
+        let database = Database::open(&dir).unwrap();
+        let mut conn = database.connect();
+        conn.execute(
+            "CREATE TABLE t (id int PRIMARY KEY); \
+             INSERT INTO t VALUES (1), (2), (3); \
+             DELETE FROM t WHERE id = 2",
+            &[],
+        )
+        .unwrap();
+    }
+    let database = Database::open(&dir).unwrap();
+    assert_eq!(ids(&database), [1, 3]);
+
+    let mut conn = database.connect();
+    conn.execute("VACUUM; INSERT INTO t VALUES (4)", &[])
+        .unwrap();
+    drop(conn);
+    drop(database);
+
+    let database = Database::open(&dir).unwrap();
+    assert_eq!(ids(&database), [1, 3, 4]);
+}
+
+/// The modules of WebAssembly functions are kept in the catalog, so the
+/// functions are recovered without the files they are loaded from.
+#[test]
+fn recover_wasm_function() {
+    let dir = data_dir("recovery-wasm");
+    let module_dir = data_dir("recovery-wasm-modules");
+    std::fs::create_dir_all(&module_dir).unwrap();
+    let module = wat::parse_str(
+        r#"(module
+            (func (export "twice") (param i64) (result i64)
+                (i64.mul (local.get 0) (i64.const 2))))"#,
+    )
+    .unwrap();
+    std::fs::write(module_dir.join("twice.wasm"), module).unwrap();
+    {
+        let mut database = Database::open(&dir).unwrap();
+        database.set_function_directory(&module_dir);
+        database
+            .connect()
+            .execute(
+                "CREATE FUNCTION twice(bigint) RETURNS bigint LANGUAGE wasm AS 'twice.wasm'",
+                &[],
+            )
+            .unwrap();
+    }
+    std::fs::remove_dir_all(&module_dir).unwrap();
+
+    let twice = |database: &Database| {
+        let result = database
+            .connect()
+            .query("SELECT twice(21) AS n", &[])
+            .unwrap();
+        let row = result.rows().next().unwrap();
+        row.get::<i64>("n").unwrap()
+    };
+    let database = Database::open(&dir).unwrap();
+    assert_eq!(twice(&database), 42);
+
+    // The checkpoint of `VACUUM` rewrites the catalog
+    database.connect().execute("VACUUM", &[]).unwrap();
+    drop(database);
+    let database = Database::open(&dir).unwrap();
+    assert_eq!(twice(&database), 42);
+}
+
+/// The tables of the same name in different databases are recovered
+/// separately, and the dropped databases stay dropped.
+#[test]
+fn recover_databases() {
+    let dir = data_dir("recovery-databases");
+    {
+        let database = Database::open(&dir).unwrap();
+        database
+            .connect()
+            .execute(
+                "CREATE DATABASE a; CREATE DATABASE b; \
+                 CREATE TABLE t (id int PRIMARY KEY); INSERT INTO t VALUES (1)",
+                &[],
+            )
+            .unwrap();
+        for (name, id) in [("a", 2), ("b", 3)] {
+            database
+                .connect_to(name)
+                .unwrap()
+                .execute(
+                    &format!(
+                        "CREATE SCHEMA s; CREATE TABLE s.t (id int PRIMARY KEY); \
+                         INSERT INTO s.t VALUES ({})",
+                        id
+                    ),
+                    &[],
+                )
+                .unwrap();
+        }
+        database.connect().execute("DROP DATABASE b", &[]).unwrap();
+    }
+
+    let check = |database: &Database| {
+        assert_eq!(ids(database), [1]);
+        let result = database
+            .connect_to("a")
+            .unwrap()
+            .query("SELECT id FROM s.t", &[])
+            .unwrap();
+        let row = result.rows().next().unwrap();
+        assert_eq!(row.get::<i64>("id").unwrap(), 2);
+        let err = database.connect_to("b").err().unwrap();
+        assert_eq!(err.code, Some("3D000"));
+    };
+    let database = Database::open(&dir).unwrap();
+    check(&database);
+
+    // The checkpoint of `VACUUM` rewrites the catalog
+    database.connect().execute("VACUUM", &[]).unwrap();
+    drop(database);
+    let database = Database::open(&dir).unwrap();
+    check(&database);
+}
+
+/// A heap page torn by crash fails the open, instead of losing the rows
+/// written before the last checkpoint, which are no longer in the WAL.
+#[test]
+fn torn_page_after_checkpoint() {
+    let dir = data_dir("recovery-torn-page");
+    {
+        let database = Database::open(&dir).unwrap();
+        let mut conn = database.connect();
+        conn.execute(
+            "CREATE TABLE t (id int PRIMARY KEY); \
+             INSERT INTO t VALUES (1), (2), (3); \
+             DELETE FROM t WHERE id = 2; VACUUM",
+            &[],
+        )
+        .unwrap();
+    }
+
+    // Tuples are stored from the end of page, which is half written
+    let path = dir.join("base").join("leisql").join("default").join("t");
+    let mut content = std::fs::read(&path).unwrap();
+    let len = content.len();
+    content[len / 2..].fill(0);
+    std::fs::write(&path, content).unwrap();
+
+    let err = Database::open(&dir).err().unwrap();
+    assert_eq!(err.code, Some("XX001"), "{}", err.message);
+}
+
+/// The data directory is locked while the database is open, so it can't be
+/// opened again until the database is dropped.
+#[test]
+fn lock_data_dir() {
+    let dir = data_dir("recovery-lock");
+    let database = Database::open(&dir).unwrap();
+    let err = Database::open(&dir).err().unwrap();
+    assert_eq!(err.code, Some("F0001"), "{}", err.message);
+
+    drop(database);
+    Database::open(&dir).unwrap();
+}
+
+/// `VACUUM` compacts the heap file of the table, and the indexes still find
+/// the moved tuples, before and after the database is reopened.
+#[test]
+fn vacuum_compacts_heap() {
+    let dir = data_dir("recovery-vacuum");
+    let path = dir.join("base").join("leisql").join("default").join("t");
+    let file_size = || std::fs::metadata(&path).unwrap().len();
+    let check = |database: &Database| {
+        let mut conn = database.connect();
+        let result = conn
+            .query("SELECT id FROM t WHERE name = 'name 1990'", &[])
+            .unwrap();
+        assert_eq!(
+            result.rows().next().unwrap().get::<i64>("id").unwrap(),
+            1990
+        );
+        let err = conn
+            .execute("INSERT INTO t VALUES (1995, 'duplicate')", &[])
+            .err()
+            .unwrap();
+        assert_eq!(err.code, Some("23505"), "{}", err.message);
+    };
+    {
+        let database = Database::open(&dir).unwrap();
+        let mut conn = database.connect();
+        let values = (0..2000)
+            .map(|id| format!("({}, 'name {}')", id, id))
+            .collect::<Vec<_>>();
+        conn.execute(
+            &format!(
+                "CREATE TABLE t (id int PRIMARY KEY, name varchar); \
+                 CREATE INDEX t_name ON t (name); \
+                 INSERT INTO t VALUES {}; \
+                 DELETE FROM t WHERE id < 1980",
+                values.join(", ")
+            ),
+            &[],
+        )
+        .unwrap();
+        let size = file_size();
+        conn.execute("VACUUM", &[]).unwrap();
+        // The rest tuples fit in a page
+        assert!(file_size() < size);
+        assert_eq!(file_size(), 8192);
+        let size = file_size();
+
+        // Nothing is reclaimed without dead tuples
+        conn.execute("VACUUM t", &[]).unwrap();
+        assert_eq!(file_size(), size);
+        check(&database);
+    }
+    let database = Database::open(&dir).unwrap();
+    assert_eq!(ids(&database), (1980..2000).collect::<Vec<_>>());
+    check(&database);
+
+    let err = database
+        .connect()
+        .execute("VACUUM missing", &[])
+        .err()
+        .unwrap();
+    assert_eq!(err.code, Some("42P01"), "{}", err.message);
+}
+
+/// Autovacuum only compacts the tables whose dead tuples exceed the
+/// threshold, 50 plus 20% of the live tuples.
+#[test]
+fn autovacuum_threshold() {
+    let dir = data_dir("recovery-autovacuum");
+    let path = dir.join("base").join("leisql").join("default").join("t");
+    let file_size = || std::fs::metadata(&path).unwrap().len();
+
+    let database = Database::open(&dir).unwrap();
+    let mut conn = database.connect();
+    let values = (0..1000)
+        .map(|id| format!("({}, 'name {}')", id, id))
+        .collect::<Vec<_>>();
+    conn.execute(
+        &format!(
+            "CREATE TABLE t (id int PRIMARY KEY, name varchar); \
+             INSERT INTO t VALUES {}; \
+             DELETE FROM t WHERE id < 200",
+            values.join(", ")
+        ),
+        &[],
+    )
+    .unwrap();
+    // 200 dead tuples don't exceed 50 + 20% of 800 live tuples
+    let size = file_size();
+    database.autovacuum().unwrap();
+    assert_eq!(file_size(), size);
+
+    conn.execute("DELETE FROM t WHERE id < 990", &[]).unwrap();
+    database.autovacuum().unwrap();
+    assert_eq!(file_size(), 8192);
+    assert_eq!(ids(&database), (990..1000).collect::<Vec<_>>());
+}
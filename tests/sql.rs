@@ -0,0 +1,2232 @@
+use leisql::{
+    core::{Datum, Json, Type},
+    Connection, Database, QueryResult,
+};
+use uuid::Uuid;
+
+/// The select list of `SELECT` without `FROM` is evaluated over a single row,
+/// the columns are named by the aliases and typed by the expressions.
+#[test]
+fn select_without_from() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+
+    let result = conn.query("SELECT 1 + 2 AS x, 'hi' AS y", &[]).unwrap();
+    let columns = result
+        .columns
+        .iter()
+        .map(|column| (column.name.as_str(), column.typ.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(columns, vec![("x", Type::Int), ("y", Type::String)]);
+
+    let rows = result.rows().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<i64>("x").unwrap(), 3);
+    assert_eq!(rows[0].get::<String>("y").unwrap(), "hi");
+}
+
+/// Correlated scalar subqueries are evaluated for each outer row, they are
+/// NULL without any row, and an error with more than one row.
+#[test]
+fn scalar_subquery() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE u (id int PRIMARY KEY); \
+         CREATE TABLE o (uid int, amount int); \
+         INSERT INTO u VALUES (1), (2), (3); \
+         INSERT INTO o VALUES (1, 10), (1, 20), (2, 30)",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT id, (SELECT count(*) FROM o WHERE o.uid = u.id) AS n, \
+             (SELECT max(amount) FROM o WHERE o.uid = u.id AND amount > 15) AS m \
+             FROM u ORDER BY id",
+            &[],
+        )
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<i64>("id").unwrap(),
+                row.get::<i64>("n").unwrap(),
+                row.get::<Option<i64>>("m").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![(1, 2, Some(20)), (2, 1, Some(30)), (3, 0, None)]);
+
+    let error = conn
+        .query("SELECT (SELECT amount FROM o) FROM u", &[])
+        .err()
+        .unwrap();
+    assert_eq!(error.code, Some("21000"));
+}
+
+/// The queries of psql 15's `\d table`, in the order they are issued, with
+/// `{oid}` in place of the OID of the table.
+const PSQL_DESCRIBE_TABLE: [&str; 10] = [
+    r"SELECT c.oid,
+  n.nspname,
+  c.relname
+FROM pg_catalog.pg_class c
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relname OPERATOR(pg_catalog.~) '^(t)$' COLLATE pg_catalog.default
+  AND pg_catalog.pg_table_is_visible(c.oid)
+ORDER BY 2, 3;",
+    r"SELECT c.relchecks, c.relkind, c.relhasindex, c.relhasrules, c.relhastriggers, c.relrowsecurity, c.relforcerowsecurity, false AS relhasoids, c.relispartition, '', c.reltablespace, CASE WHEN c.reloftype = 0 THEN '' ELSE c.reloftype::pg_catalog.regtype::pg_catalog.text END, c.relpersistence, c.relreplident, am.amname
+FROM pg_catalog.pg_class c
+ LEFT JOIN pg_catalog.pg_class tc ON (c.reltoastrelid = tc.oid)
+LEFT JOIN pg_catalog.pg_am am ON (c.relam = am.oid)
+WHERE c.oid = '{oid}';",
+    r"SELECT a.attname,
+  pg_catalog.format_type(a.atttypid, a.atttypmod),
+  (SELECT pg_catalog.pg_get_expr(d.adbin, d.adrelid, true)
+   FROM pg_catalog.pg_attrdef d
+   WHERE d.adrelid = a.attrelid AND d.adnum = a.attnum AND a.atthasdef),
+  a.attnotnull,
+  (SELECT c.collname FROM pg_catalog.pg_collation c, pg_catalog.pg_type t
+   WHERE c.oid = a.attcollation AND t.oid = a.atttypid AND a.attcollation <> t.typcollation) AS attcollation,
+  a.attidentity,
+  a.attgenerated
+FROM pg_catalog.pg_attribute a
+WHERE a.attrelid = '{oid}' AND a.attnum > 0 AND NOT a.attisdropped
+ORDER BY a.attnum;",
+    r"SELECT c2.relname, i.indisprimary, i.indisunique, i.indisclustered, i.indisvalid, pg_catalog.pg_get_indexdef(i.indexrelid, 0, true),
+  pg_catalog.pg_get_constraintdef(con.oid, true), contype, condeferrable, condeferred, i.indisreplident, c2.reltablespace
+FROM pg_catalog.pg_class c, pg_catalog.pg_class c2, pg_catalog.pg_index i
+  LEFT JOIN pg_catalog.pg_constraint con ON (conrelid = i.indrelid AND conindid = i.indexrelid AND contype IN ('p','u','x'))
+WHERE c.oid = '{oid}' AND c.oid = i.indrelid AND i.indexrelid = c2.oid
+ORDER BY i.indisprimary DESC, c2.relname;",
+    r"SELECT pol.polname, pol.polpermissive,
+  CASE WHEN pol.polroles = '{0}' THEN NULL ELSE pg_catalog.array_to_string(array(select rolname from pg_catalog.pg_roles where oid = any (pol.polroles) order by 1),',') END,
+  pg_catalog.pg_get_expr(pol.polqual, pol.polrelid),
+  pg_catalog.pg_get_expr(pol.polwithcheck, pol.polrelid),
+  CASE pol.polcmd
+    WHEN 'r' THEN 'SELECT'
+    WHEN 'a' THEN 'INSERT'
+    WHEN 'w' THEN 'UPDATE'
+    WHEN 'd' THEN 'DELETE'
+    END AS cmd
+FROM pg_catalog.pg_policy pol
+WHERE pol.polrelid = '{oid}' ORDER BY 1;",
+    r"SELECT oid, stxrelid::pg_catalog.regclass, stxnamespace::pg_catalog.regnamespace::pg_catalog.text AS nsp, stxname,
+pg_catalog.pg_get_statisticsobjdef_columns(oid) AS columns,
+  'd' = any(stxkind) AS ndist_enabled,
+  'f' = any(stxkind) AS deps_enabled,
+  'm' = any(stxkind) AS mcv_enabled,
+stxstattarget
+FROM pg_catalog.pg_statistic_ext
+WHERE stxrelid = '{oid}'
+ORDER BY nsp, stxname;",
+    r"SELECT pubname
+     , NULL
+     , NULL
+FROM pg_catalog.pg_publication p
+JOIN pg_catalog.pg_publication_rel pr ON p.oid = pr.prpubid
+WHERE pr.prrelid = '{oid}'
+UNION ALL
+SELECT pubname
+     , NULL
+     , NULL
+FROM pg_catalog.pg_publication p
+WHERE p.puballtables AND pg_catalog.pg_relation_is_publishable('{oid}')
+ORDER BY 1;",
+    r"SELECT c.oid::pg_catalog.regclass
+FROM pg_catalog.pg_class c, pg_catalog.pg_inherits i
+WHERE c.oid = i.inhparent AND i.inhrelid = '{oid}'
+  AND c.relkind != 'p' AND c.relkind != 'I'
+ORDER BY inhseqno;",
+    r"SELECT c.oid::pg_catalog.regclass, c.relkind, inhdetachpending, pg_catalog.pg_get_expr(c.relpartbound, c.oid)
+FROM pg_catalog.pg_class c, pg_catalog.pg_inherits i
+WHERE c.oid = i.inhrelid AND i.inhparent = '{oid}'
+ORDER BY pg_catalog.pg_get_expr(c.relpartbound, c.oid) = 'DEFAULT', c.oid::pg_catalog.regclass::pg_catalog.text;",
+    // `\df`
+    r#"SELECT n.nspname as "Schema",
+  p.proname as "Name",
+  pg_catalog.pg_get_function_result(p.oid) as "Result data type",
+  pg_catalog.pg_get_function_arguments(p.oid) as "Argument data types",
+ CASE p.prokind
+  WHEN 'a' THEN 'agg'
+  WHEN 'w' THEN 'window'
+  WHEN 'p' THEN 'proc'
+  ELSE 'func'
+ END as "Type"
+FROM pg_catalog.pg_proc p
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+WHERE pg_catalog.pg_function_is_visible(p.oid)
+      AND n.nspname <> 'pg_catalog'
+      AND n.nspname <> 'information_schema'
+ORDER BY 1, 2, 4;"#,
+];
+
+/// psql's `\d table` and `\df` run against the catalog, policies, extended
+/// statistics, publications and inheritance are always empty.
+#[test]
+fn psql_describe_table() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int PRIMARY KEY, b text DEFAULT 'x'); \
+         CREATE FUNCTION add(x int, y text) RETURNS int AS 'SELECT $1'",
+        &[],
+    )
+    .unwrap();
+
+    let mut results = vec![];
+    let mut oid: Option<i64> = None;
+    for sql in PSQL_DESCRIBE_TABLE {
+        let sql = match oid {
+            Some(oid) => sql.replace("{oid}", &oid.to_string()),
+            None => sql.to_string(),
+        };
+        let result = conn.query(&sql, &[]).unwrap();
+        oid = oid.or_else(|| result.rows().next().unwrap().get::<i64>(0).ok());
+        results.push(result);
+    }
+
+    let rows = results[2]
+        .rows()
+        .map(|row| {
+            (
+                row.get::<String>(0).unwrap(),
+                row.get::<Option<String>>(2).unwrap(),
+                row.get::<bool>(3).unwrap(),
+                row.get::<Option<String>>(4).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            ("a".to_string(), None, true, None),
+            ("b".to_string(), Some("'x'".to_string()), false, None),
+        ]
+    );
+
+    let rows = results[3]
+        .rows()
+        .map(|row| (row.get::<String>(0).unwrap(), row.get::<String>(6).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![("t_pkey".to_string(), "PRIMARY KEY (a)".to_string())]
+    );
+
+    for result in &results[4..9] {
+        assert_eq!(result.data.len(), 0);
+    }
+
+    let rows = results[9]
+        .rows()
+        .map(|row| {
+            (
+                row.get::<String>("Name").unwrap(),
+                row.get::<String>("Result data type").unwrap(),
+                row.get::<String>("Argument data types").unwrap(),
+                row.get::<String>("Type").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![(
+            "add".to_string(),
+            "bigint".to_string(),
+            "bigint, character varying".to_string(),
+            "func".to_string(),
+        )]
+    );
+
+    let result = conn
+        .query(
+            "SELECT 1 = any('{1,NULL}'::int[]), 2 = any('{1,NULL}'::int[]), \
+             'pg_catalog'::regnamespace::int::regnamespace",
+            &[],
+        )
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<Option<bool>>(0).unwrap(), Some(true));
+    assert_eq!(row.get::<Option<bool>>(1).unwrap(), None);
+    assert_eq!(row.get::<String>(2).unwrap(), "pg_catalog");
+}
+
+/// `COPY` only reads and writes the files in the configured directory.
+#[test]
+fn copy_directory() {
+    let dir = std::env::temp_dir().join(format!("leisql-copy-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute("CREATE TABLE t (a int, b text)", &[]).unwrap();
+    let error = conn
+        .execute("COPY t TO 't.csv' (FORMAT csv)", &[])
+        .unwrap_err();
+    assert_eq!(error.code, Some("42501"));
+
+    database.set_copy_directory(&dir);
+    let mut conn = database.connect();
+    conn.execute(
+        "INSERT INTO t VALUES (1, 'x'), (2, NULL); \
+         COPY t TO 't.csv' (FORMAT csv, HEADER); \
+         DELETE FROM t; \
+         COPY t FROM 't.csv' (FORMAT csv, HEADER)",
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("t.csv")).unwrap(),
+        "a,b\n1,x\n2,\n"
+    );
+    assert_eq!(conn.execute("SELECT * FROM t", &[]).unwrap(), 2);
+
+    for file in ["/etc/passwd", "../t.csv", "sub/../../t.csv"] {
+        let error = conn
+            .execute(&format!("COPY t FROM '{}' (FORMAT csv)", file), &[])
+            .unwrap_err();
+        assert_eq!(error.code, Some("42501"), "{}", file);
+    }
+}
+
+/// Tables are exported to Parquet and imported back with the same values,
+/// the columns are matched by name.
+#[test]
+fn copy_parquet() {
+    let dir = std::env::temp_dir().join(format!("leisql-parquet-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut database = Database::in_memory();
+    database.set_copy_directory(&dir);
+    let mut conn = database.connect();
+    let columns = "a smallint, b bigint, d text, e boolean, f uuid, g json, h bytea, i int[]";
+    conn.execute(
+        &format!(
+            "CREATE TABLE t ({columns}); \
+             CREATE TABLE u ({columns}); \
+             INSERT INTO t VALUES \
+             (-32768, 9223372036854775807, 'x', true, \
+              'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11', '{{\"k\": [1, null]}}', '\\x00ff', ARRAY[1, NULL]), \
+             (NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL); \
+             COPY t TO 't.parquet' (FORMAT parquet); \
+             COPY u FROM 't.parquet' (FORMAT parquet)"
+        ),
+        &[],
+    )
+    .unwrap();
+
+    let rows = |conn: &mut leisql::Connection, table: &str| {
+        conn.query(&format!("SELECT * FROM {} ORDER BY a", table), &[])
+            .unwrap()
+            .rows()
+            .map(|row| format!("{:?}", row.values()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(rows(&mut conn, "t"), rows(&mut conn, "u"));
+
+    // Only the columns in the list are read, the others are filled with defaults
+    conn.execute(
+        "CREATE TABLE v (d text, z int DEFAULT 7, b bigint); \
+         COPY v (b, d) FROM 't.parquet' (FORMAT parquet)",
+        &[],
+    )
+    .unwrap();
+    let result = conn.query("SELECT * FROM v ORDER BY b", &[]).unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<Option<String>>("d").unwrap(),
+                row.get::<i64>("z").unwrap(),
+                row.get::<Option<i64>>("b").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![(Some("x".to_string()), 7, Some(i64::MAX)), (None, 7, None)]
+    );
+
+    let error = conn
+        .execute("COPY v (z) FROM 't.parquet' (FORMAT parquet)", &[])
+        .unwrap_err();
+    assert_eq!(error.message, "missing data for column \"z\"");
+    let error = conn
+        .execute("COPY v TO 'v.parquet' (FORMAT parquet, HEADER)", &[])
+        .unwrap_err();
+    assert_eq!(error.code, Some("0A000"));
+
+    // Query results are exported by the connection
+    let path = dir.join("query.parquet");
+    let count = conn
+        .export_parquet(
+            "SELECT b - 1 AS b, d FROM t WHERE b > $1",
+            &[Datum::Int(0)],
+            &path,
+        )
+        .unwrap();
+    assert_eq!(count, 1);
+    conn.execute(
+        "DELETE FROM v; COPY v (b, d) FROM 'query.parquet' (FORMAT parquet)",
+        &[],
+    )
+    .unwrap();
+    let row = conn.query("SELECT d, b FROM v", &[]).unwrap();
+    let row = row.rows().next().unwrap();
+    assert_eq!(row.get::<String>("d").unwrap(), "x");
+    assert_eq!(row.get::<i64>("b").unwrap(), i64::MAX - 1);
+}
+
+/// Results are returned as Arrow record batches, whose schema is mapped from
+/// the column types.
+#[test]
+fn execute_arrow() {
+    use arrow_array::{
+        cast::AsArray,
+        types::{Int16Type, Int64Type},
+        Array,
+    };
+    use arrow_schema::DataType;
+
+    let database = Database::in_memory();
+    let mut session = database.session();
+    let mut execute = |sql: &str| {
+        let statement = session.parse(sql).unwrap().remove(0);
+        session.execute_arrow(&statement).unwrap()
+    };
+    execute("CREATE TABLE t (a smallint, b text, c int[])");
+    execute("INSERT INTO t VALUES (1, 'x', ARRAY[1, 2]), (2, NULL, NULL)");
+
+    let batches = execute("SELECT a, b, c, 1.5 AS d, NULL AS e FROM t ORDER BY a");
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    let types = batch
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| (field.name().clone(), field.data_type().clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types[..2],
+        [
+            ("a".to_string(), DataType::Int16),
+            ("b".to_string(), DataType::Utf8)
+        ]
+    );
+    assert!(matches!(types[2].1, DataType::List(_)));
+    assert_eq!(types[3].1, DataType::Float64);
+    assert_eq!(types[4].1, DataType::Null);
+
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(
+        batch.column(0).as_primitive::<Int16Type>().values(),
+        &[1, 2]
+    );
+    let b = batch.column(1).as_string::<i32>();
+    assert_eq!((b.value(0), b.is_null(1)), ("x", true));
+    let c = batch.column(2).as_list::<i32>();
+    assert_eq!(c.value(0).as_primitive::<Int64Type>().values(), &[1, 2]);
+    assert!(c.is_null(1));
+
+    // The schema is known without any row
+    let batches = execute("SELECT a FROM t WHERE a > 2");
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].num_rows(), 0);
+    assert_eq!(batches[0].schema().field(0).data_type(), &DataType::Int16);
+}
+
+/// Arrays are cast to the arrays of other element types, including the empty
+/// array literal, which has no element to infer the type from.
+#[test]
+fn array_cast() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+
+    let result = conn
+        .query(
+            "SELECT ARRAY[]::int[] AS a, ARRAY[1, NULL]::text[] AS b, \
+             array_length(ARRAY[]::int[], 1) AS c",
+            &[],
+        )
+        .unwrap();
+    let columns = result
+        .columns
+        .iter()
+        .map(|column| column.typ.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        columns,
+        vec![
+            Type::Array(Box::new(Type::Int)),
+            Type::Array(Box::new(Type::String)),
+            Type::Int
+        ]
+    );
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<Vec<i64>>("a").unwrap(), Vec::<i64>::new());
+    assert_eq!(
+        row.get::<Vec<Option<String>>>("b").unwrap(),
+        vec![Some("1".to_string()), None]
+    );
+    assert_eq!(row.get::<Option<i64>>("c").unwrap(), None);
+
+    conn.execute(
+        "CREATE TABLE t (a int[]); INSERT INTO t VALUES (ARRAY[]::int[])",
+        &[],
+    )
+    .unwrap();
+}
+
+/// `count(expr)` counts the non-null values and `avg` is their mean, both
+/// ignore NULL rather than the whole row.
+#[test]
+fn count_and_avg() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (g int, a int); \
+         INSERT INTO t VALUES (1, 1), (1, 2), (1, NULL), (2, NULL), (3, 4)",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT g, count(*) AS n, count(a) AS c, avg(a) AS m, count(a + 1) AS d \
+             FROM t GROUP BY g ORDER BY g",
+            &[],
+        )
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<i64>("g").unwrap(),
+                row.get::<i64>("n").unwrap(),
+                row.get::<i64>("c").unwrap(),
+                row.get::<Option<f64>>("m").unwrap(),
+                row.get::<i64>("d").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (1, 3, 2, Some(1.5), 2),
+            (2, 1, 0, None, 0),
+            (3, 1, 1, Some(4.0), 1)
+        ]
+    );
+
+    let result = conn
+        .query("SELECT count(a) AS c, avg(a) AS m FROM t WHERE g > 3", &[])
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<i64>("c").unwrap(), 0);
+    assert_eq!(row.get::<Option<f64>>("m").unwrap(), None);
+}
+
+/// `FILTER (WHERE ...)` of aggregates is allowed anywhere an aggregate is,
+/// and isn't accepted as an argument of the function.
+#[test]
+fn aggregate_filter() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (g int, a int); INSERT INTO t VALUES (1, 1), (1, 5), (2, 7)",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT g, count(*) FILTER (WHERE a > 1) + 1 AS n, \
+             sum(a) FILTER (WHERE a < 7) AS s FROM t GROUP BY g \
+             HAVING count(*) FILTER (WHERE a > 4) > 0 ORDER BY g",
+            &[],
+        )
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<i64>("g").unwrap(),
+                row.get::<i64>("n").unwrap(),
+                row.get::<Option<i64>>("s").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![(1, 2, Some(6)), (2, 2, None)]);
+
+    let err = conn
+        .execute("SELECT count(a, filter => a > 10) FROM t", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("42883"));
+    let err = conn
+        .execute("SELECT sum(a, filter => a > 10) FROM t", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("0A000"));
+    let err = conn
+        .execute("SELECT abs(a) FILTER (WHERE a > 1) FROM t", &[])
+        .unwrap_err();
+    assert_eq!(
+        err.message,
+        "FILTER specified, but abs is not an aggregate function"
+    );
+}
+
+/// The values of aggregates are ordered by all the keys of `ORDER BY`, which
+/// is allowed along with `FILTER`.
+#[test]
+fn aggregate_order_by() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (x int, o int); \
+         INSERT INTO t VALUES (3, 1), (1, 2), (2, 1), (4, NULL)",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT array_agg(x ORDER BY o, x) AS a, \
+             array_agg(x ORDER BY o DESC NULLS LAST, x DESC) AS b, \
+             string_agg(x::text, ',' ORDER BY o NULLS FIRST, x) AS c, \
+             array_agg(x ORDER BY x) FILTER (WHERE o = 1) AS d FROM t",
+            &[],
+        )
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<Vec<i64>>("a").unwrap(), vec![2, 3, 1, 4]);
+    assert_eq!(row.get::<Vec<i64>>("b").unwrap(), vec![1, 3, 2, 4]);
+    assert_eq!(row.get::<String>("c").unwrap(), "4,2,3,1");
+    assert_eq!(row.get::<Vec<i64>>("d").unwrap(), vec![2, 3]);
+
+    let err = conn
+        .execute("SELECT abs(x ORDER BY o) FROM t", &[])
+        .unwrap_err();
+    assert_eq!(
+        err.message,
+        "ORDER BY specified, but abs is not an aggregate function"
+    );
+}
+
+/// The conditions of `WHERE` and `HAVING` are boolean, other types aren't
+/// cast implicitly.
+#[test]
+fn boolean_condition() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute("CREATE TABLE t (a int); INSERT INTO t VALUES (0), (1)", &[])
+        .unwrap();
+
+    for sql in [
+        "SELECT a FROM t WHERE a",
+        "SELECT a FROM t WHERE 1.5",
+        "SELECT count(*) FROM t HAVING count(*)",
+    ] {
+        let err = conn.query(sql, &[]).err().unwrap();
+        assert!(
+            err.message.contains("must be type boolean"),
+            "{}: {}",
+            sql,
+            err.message
+        );
+    }
+
+    let result = conn.query("SELECT a FROM t WHERE NULL", &[]).unwrap();
+    assert_eq!(result.rows().count(), 0);
+    let result = conn.query("SELECT a FROM t WHERE a = 1", &[]).unwrap();
+    assert_eq!(result.rows().count(), 1);
+}
+
+/// Integers are compared with and added to floats as floats, and floats are
+/// rounded half to even when they are cast or written to integer columns.
+#[test]
+fn int_float_promotion() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+
+    let result = conn
+        .query(
+            "SELECT 1 = 1.5 AS a, 1 < 1.5 AS b, 1 + 1.5 AS c, 1.9::int AS d, \
+             2.5::int AS e, -3.5::int AS f",
+            &[],
+        )
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert!(!row.get::<bool>("a").unwrap());
+    assert!(row.get::<bool>("b").unwrap());
+    assert_eq!(row.get::<f64>("c").unwrap(), 2.5);
+    assert_eq!(row.get::<i64>("d").unwrap(), 2);
+    assert_eq!(row.get::<i64>("e").unwrap(), 2);
+    assert_eq!(row.get::<i64>("f").unwrap(), -4);
+
+    conn.execute(
+        "CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2.7)",
+        &[],
+    )
+    .unwrap();
+    let result = conn.query("SELECT a FROM t WHERE a = 1.5", &[]).unwrap();
+    assert_eq!(result.rows().count(), 0);
+    let result = conn.query("SELECT a FROM t WHERE a > 1.5", &[]).unwrap();
+    let rows = result
+        .rows()
+        .map(|row| row.get::<i64>("a").unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![3]);
+
+    let err = conn
+        .execute("INSERT INTO t VALUES (1e30)", &[])
+        .unwrap_err();
+    assert_eq!(err.message, "integer out of range");
+}
+
+/// Arithmetic of the narrower integer types is range checked in the widest
+/// type of the operands, and so are the casts of floats.
+#[test]
+fn integer_widths() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+
+    let cases = [
+        ("SELECT 1::int + 2147483647::int", "integer out of range"),
+        ("SELECT 2147483647::int + 1", "integer out of range"),
+        (
+            "SELECT -(-2147483648)::int - 1::int",
+            "integer out of range",
+        ),
+        (
+            "SELECT 32767::smallint + 1::smallint",
+            "smallint out of range",
+        ),
+        ("SELECT 1e300::int", "integer out of range"),
+        ("SELECT 1e300::smallint", "smallint out of range"),
+        ("SELECT 1e300::bigint", "bigint out of range"),
+    ];
+    for (sql, message) in cases {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some("22003"), "{}", sql);
+        assert_eq!(err.message, message, "{}", sql);
+    }
+
+    let result = conn
+        .query(
+            "SELECT 1::int + 2147483647::bigint AS a, 32767::smallint + 1 AS b, \
+             1 + 2147483647 AS c",
+            &[],
+        )
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<i64>("a").unwrap(), 2147483648);
+    assert_eq!(row.get::<i64>("b").unwrap(), 32768);
+    assert_eq!(row.get::<i64>("c").unwrap(), 2147483648);
+}
+
+/// `UNION ALL` keeps the duplicates of both sides and `UNION` removes them,
+/// the columns have the common types of both sides.
+#[test]
+fn union() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2), (2)",
+        &[],
+    )
+    .unwrap();
+
+    let query = |conn: &mut Connection, sql: &str| {
+        conn.query(sql, &[])
+            .unwrap()
+            .rows()
+            .map(|row| row.get::<Option<i64>>(0).unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        query(
+            &mut conn,
+            "SELECT a FROM t UNION ALL SELECT a + 1 FROM t ORDER BY 1"
+        ),
+        vec![Some(1), Some(2), Some(2), Some(2), Some(3), Some(3)]
+    );
+    assert_eq!(
+        query(
+            &mut conn,
+            "SELECT a FROM t UNION SELECT NULL UNION SELECT 3 ORDER BY a"
+        ),
+        vec![Some(1), Some(2), Some(3), None]
+    );
+
+    let result = conn.query("SELECT 1 UNION ALL SELECT 2.5", &[]).unwrap();
+    assert_eq!(result.columns[0].typ, Type::Float);
+
+    let err = conn.execute("SELECT 1 UNION SELECT 1, 2", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42601"));
+}
+
+/// Valid SQL that isn't supported yet fails with an error instead of
+/// panicking, and the connection is still usable afterwards.
+#[test]
+fn unsupported_features() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute("CREATE TABLE t (a int); INSERT INTO t VALUES (1)", &[])
+        .unwrap();
+
+    for sql in [
+        "CREATE VIEW v AS SELECT 1",
+        "DROP VIEW v",
+        "INSERT INTO t SELECT 1",
+        "SELECT 1 INTERSECT SELECT 1",
+        "SELECT * FROM t RIGHT JOIN t AS u ON true",
+        "SELECT * FROM t FULL JOIN t AS u ON true",
+        "SELECT * FROM t JOIN t AS u USING (a)",
+        "SELECT * FROM t NATURAL JOIN t AS u",
+        "SELECT t.* FROM t",
+        "SELECT abs(a => 1)",
+        "SELECT extract(year FROM a) FROM t",
+        "SELECT N'abc'",
+        "MSCK REPAIR TABLE t",
+    ] {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some("0A000"), "{}: {}", sql, err.message);
+    }
+
+    let err = conn.execute("SELECT abs(*) FROM t", &[]).unwrap_err();
+    assert_eq!(
+        err.message,
+        "abs(*) specified, but abs is not an aggregate function"
+    );
+
+    let result = conn
+        .query("SELECT COUNT(*) AS n, SUM(a) AS s FROM t", &[])
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<i64>("n").unwrap(), 1);
+    assert_eq!(row.get::<i64>("s").unwrap(), 1);
+}
+
+/// Functions registered on the database are available to its sessions,
+/// including the ones connected before the registration.
+#[test]
+fn register_functions() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2), (NULL), (4)",
+        &[],
+    )
+    .unwrap();
+    let err = conn.execute("SELECT double(a) FROM t", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42883"));
+
+    database.register_scalar_function("Double", &[Type::Int], Type::Int, |args| match &args[0] {
+        Datum::Int(v) => Datum::Int(v * 2),
+        _ => Datum::Null,
+    });
+    // Product of the non-null values, NULL without any
+    database.register_aggregate_function(
+        "product",
+        &[Type::Int],
+        Type::Int,
+        Datum::Null,
+        |state, args| match (state, &args[0]) {
+            (_, Datum::Null) => {}
+            (Datum::Int(product), Datum::Int(v)) => *product *= v,
+            (state, value) => *state = value.clone(),
+        },
+        |state| state.clone(),
+    );
+
+    let result = conn
+        .query("SELECT double(a) AS d FROM t ORDER BY a", &[])
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| row.get::<Option<i64>>("d").unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![Some(2), Some(4), Some(8), None]);
+
+    let mut other = database.connect();
+    let result = other
+        .query(
+            "SELECT product(a) AS p, product(a) FILTER (WHERE a > 4) AS q FROM t",
+            &[],
+        )
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<i64>("p").unwrap(), 8);
+    assert_eq!(row.get::<Option<i64>>("q").unwrap(), None);
+}
+
+/// Functions are loaded from the WebAssembly modules in the directory of
+/// functions, and every call is sandboxed.
+#[test]
+fn wasm_functions() {
+    let dir = std::env::temp_dir().join(format!("leisql-wasm-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let modules = [
+        (
+            "math.wasm",
+            r#"(module
+                (func (export "add") (param i64 i64) (result i64)
+                    (i64.add (local.get 0) (local.get 1)))
+                (func (export "negate") (param i32) (result i32)
+                    (i32.eqz (local.get 0)))
+                (func (export "div") (param i64 i64) (result i64)
+                    (i64.div_s (local.get 0) (local.get 1)))
+                (func (export "spin") (param i64) (result i64)
+                    (loop $forever (br $forever))
+                    (i64.const 0)))"#,
+        ),
+        (
+            "shout.wasm",
+            r#"(module
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 1024))
+                (func (export "alloc") (param $len i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr (global.get $next))
+                    (global.set $next (i32.add (global.get $next) (local.get $len)))
+                    (local.get $ptr))
+                (func (export "shout") (param $ptr i32) (param $len i32) (result i64)
+                    (local $i i32)
+                    (local $c i32)
+                    (block $done
+                        (loop $char
+                            (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+                            (local.set $c (i32.load8_u (i32.add (local.get $ptr) (local.get $i))))
+                            (if (i32.and (i32.ge_u (local.get $c) (i32.const 97))
+                                         (i32.le_u (local.get $c) (i32.const 122)))
+                                (then (i32.store8 (i32.add (local.get $ptr) (local.get $i))
+                                                  (i32.sub (local.get $c) (i32.const 32)))))
+                            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                            (br $char)))
+                    (i64.or (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                            (i64.extend_i32_u (local.get $len)))))"#,
+        ),
+        (
+            "host.wasm",
+            r#"(module
+                (import "env" "log" (func $log (param i64)))
+                (func (export "host") (param i64) (result i64)
+                    (call $log (local.get 0))
+                    (local.get 0)))"#,
+        ),
+    ];
+    for (file, text) in modules {
+        std::fs::write(dir.join(file), wat::parse_str(text).unwrap()).unwrap();
+    }
+
+    let mut database = Database::in_memory();
+    let mut conn = database.connect();
+    let err = conn
+        .execute(
+            "CREATE FUNCTION add(bigint, bigint) RETURNS bigint LANGUAGE wasm AS 'math.wasm'",
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(err.code, Some("42501"));
+
+    database.set_function_directory(&dir);
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE FUNCTION add(bigint, bigint) RETURNS bigint LANGUAGE wasm AS 'math.wasm'; \
+         CREATE FUNCTION negate(boolean) RETURNS boolean LANGUAGE wasm AS 'math.wasm'; \
+         CREATE FUNCTION div(bigint, bigint) RETURNS bigint LANGUAGE wasm AS 'math.wasm'; \
+         CREATE FUNCTION spin(bigint) RETURNS bigint LANGUAGE wasm AS 'math.wasm'; \
+         CREATE FUNCTION shout(text) RETURNS text LANGUAGE wasm AS 'shout.wasm'; \
+         CREATE TABLE t (a bigint, s text); \
+         INSERT INTO t VALUES (1, 'abc'), (NULL, 'MiXed 1')",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT add(a, 41) AS n, negate(a IS NULL) AS b, shout(s) AS s FROM t ORDER BY s",
+            &[],
+        )
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<Option<i64>>("n").unwrap(),
+                row.get::<bool>("b").unwrap(),
+                row.get::<String>("s").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (Some(42), true, "ABC".to_string()),
+            (None, false, "MIXED 1".to_string())
+        ]
+    );
+
+    let err = conn.execute("SELECT div(1, 0)", &[]).unwrap_err();
+    assert_eq!(err.code, Some("38000"), "{}", err.message);
+    let err = conn.execute("SELECT spin(1)", &[]).unwrap_err();
+    assert_eq!(err.code, Some("54000"), "{}", err.message);
+
+    for (sql, code) in [
+        // Modules can't call the host
+        (
+            "CREATE FUNCTION host(bigint) RETURNS bigint LANGUAGE wasm AS 'host.wasm'",
+            "42P13",
+        ),
+        // The export doesn't match the signature
+        (
+            "CREATE FUNCTION div(bigint) RETURNS bigint LANGUAGE wasm AS 'math.wasm'",
+            "42P13",
+        ),
+        (
+            "CREATE FUNCTION sub(bigint, bigint) RETURNS bigint LANGUAGE wasm AS 'math.wasm'",
+            "42P13",
+        ),
+        (
+            "CREATE FUNCTION add(bigint, bigint) RETURNS bigint LANGUAGE wasm AS '../math.wasm'",
+            "42501",
+        ),
+        (
+            "CREATE FUNCTION add(bigint, bigint) RETURNS bigint LANGUAGE wasm AS 'missing.wasm'",
+            "58P01",
+        ),
+        (
+            "CREATE FUNCTION add(bigint, bigint) RETURNS bigint LANGUAGE wasm RETURN $1 + $2",
+            "42P13",
+        ),
+    ] {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some(code), "{}: {}", sql, err.message);
+    }
+
+    conn.execute("DROP FUNCTION add", &[]).unwrap();
+    let err = conn.execute("SELECT add(1, 2)", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42883"));
+}
+
+/// Objects are named within their database, so the databases can have the
+/// same schemas and tables, and a database is dropped with all of them.
+#[test]
+fn multiple_databases() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE DATABASE other; CREATE DATABASE IF NOT EXISTS other",
+        &[],
+    )
+    .unwrap();
+    let err = conn.execute("CREATE DATABASE other", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42P04"));
+    let err = database.connect_to("missing").err().unwrap();
+    assert_eq!(err.code, Some("3D000"));
+
+    let mut other = database.connect_to("other").unwrap();
+    conn.execute("CREATE TABLE t (a int); INSERT INTO t VALUES (1)", &[])
+        .unwrap();
+    other
+        .execute(
+            "CREATE TABLE t (a int, b text); INSERT INTO t VALUES (2, 'x'), (3, 'y')",
+            &[],
+        )
+        .unwrap();
+    let values = |conn: &mut Connection| {
+        conn.query("SELECT a FROM t ORDER BY a", &[])
+            .unwrap()
+            .rows()
+            .map(|row| row.get::<i64>("a").unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(values(&mut conn), [1]);
+    assert_eq!(values(&mut other), [2, 3]);
+
+    let result = conn
+        .query("SELECT datname FROM pg_database ORDER BY datname", &[])
+        .unwrap();
+    let names = result
+        .rows()
+        .map(|row| row.get::<String>("datname").unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["leisql", "other"]);
+
+    // The databases connected to can't be dropped, nor can the default one
+    for conn in [&mut conn, &mut other] {
+        for sql in ["DROP DATABASE leisql", "DROP DATABASE other"] {
+            let err = conn.execute(sql, &[]).unwrap_err();
+            assert_eq!(err.code, Some("55006"), "{}: {}", sql, err.message);
+        }
+    }
+
+    drop(other);
+    conn.execute("DROP DATABASE other", &[]).unwrap();
+    let err = conn.execute("DROP DATABASE other", &[]).unwrap_err();
+    assert_eq!(err.code, Some("3D000"));
+    conn.execute("DROP DATABASE IF EXISTS other", &[]).unwrap();
+    assert_eq!(values(&mut conn), [1]);
+
+    conn.execute("CREATE DATABASE other", &[]).unwrap();
+    let mut other = database.connect_to("other").unwrap();
+    let err = other.execute("SELECT a FROM t", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42P01"));
+
+    // `UNCACHE TABLE` of Spark isn't supported, and drops nothing
+    drop(other);
+    let err = conn.execute("UNCACHE TABLE other", &[]).unwrap_err();
+    assert_eq!(err.code, Some("0A000"), "{}", err.message);
+    database.connect_to("other").unwrap();
+}
+
+/// `EXPLAIN` shows the estimated rows unless `COSTS OFF` is given, and
+/// `DESCRIBE` is the same as `EXPLAIN`.
+#[test]
+fn explain_costs() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2); ANALYZE t",
+        &[],
+    )
+    .unwrap();
+
+    let mut explain = |sql: &str| {
+        conn.query(sql, &[])
+            .unwrap()
+            .rows()
+            .map(|row| row.get::<String>(0).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    for sql in [
+        "EXPLAIN SELECT a FROM t",
+        "EXPLAIN (COSTS) SELECT a FROM t",
+        "DESCRIBE SELECT a FROM t",
+    ] {
+        assert!(explain(sql).contains("(rows=2)"), "{}", sql);
+    }
+    for sql in [
+        "EXPLAIN (COSTS OFF) SELECT a FROM t",
+        "EXPLAIN (VERBOSE, COSTS false) SELECT a FROM t",
+    ] {
+        assert!(!explain(sql).contains("rows="), "{}", sql);
+    }
+}
+
+/// `EXPLAIN (FORMAT JSON)` renders the same plan tree as the text format in
+/// JSON, and `VERBOSE` adds the output types of each operator.
+#[test]
+fn explain_formats() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute("CREATE TABLE t (a int, b text)", &[]).unwrap();
+
+    let mut explain = |sql: &str| {
+        conn.query(sql, &[])
+            .unwrap()
+            .rows()
+            .map(|row| row.get::<String>(0).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let sql = "SELECT b FROM t WHERE a > 1";
+    assert_eq!(
+        explain(&format!("EXPLAIN (FORMAT TEXT) {}", sql)),
+        explain(&format!("EXPLAIN {}", sql))
+    );
+    let verbose = explain(&format!("EXPLAIN VERBOSE {}", sql));
+    assert!(verbose.iter().any(|line| line.trim() == "Output: varchar"));
+    assert!(verbose
+        .iter()
+        .any(|line| line.trim() == "Output: int8, varchar"));
+
+    let json = explain(&format!("EXPLAIN (FORMAT JSON, VERBOSE) {}", sql));
+    assert_eq!(json.len(), 1);
+    let json = Json::parse(&json[0]).unwrap();
+    let project = json.element(0).unwrap().field("Plan").unwrap();
+    assert_eq!(
+        project.field("Node Type"),
+        Some(&Json::String("Project".into()))
+    );
+    assert_eq!(
+        project.field("Output"),
+        Some(&Json::Array(vec![Json::String("varchar".into())]))
+    );
+    let filter = project.field("Plans").unwrap().element(0).unwrap();
+    assert_eq!(
+        filter.field("Node Type"),
+        Some(&Json::String("Filter".into()))
+    );
+    let scan = filter.field("Plans").unwrap().element(0).unwrap();
+    assert_eq!(scan.field("Node Type"), Some(&Json::String("Scan".into())));
+    assert_eq!(scan.field("Plans"), None);
+
+    let err = conn
+        .execute(&format!("EXPLAIN (FORMAT YAML) {}", sql), &[])
+        .unwrap_err();
+    assert!(
+        err.message.contains("unrecognized value"),
+        "{}",
+        err.message
+    );
+}
+
+/// Parameters that can't be converted to their types fail the statement,
+/// instead of being bound as NULL.
+#[test]
+fn invalid_parameter() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (id int); INSERT INTO t VALUES (1); \
+         PREPARE p(int) AS SELECT id FROM t WHERE id = $1; \
+         PREPARE q(int) AS INSERT INTO t VALUES ($1)",
+        &[],
+    )
+    .unwrap();
+
+    for sql in ["EXECUTE p('abc')", "EXECUTE q('abc')"] {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some("22P02"), "{}: {}", sql, err.message);
+    }
+
+    let result = conn.query("EXECUTE p('1')", &[]).unwrap();
+    assert_eq!(result.rows().count(), 1);
+    let result = conn.query("SELECT count(*) FROM t", &[]).unwrap();
+    assert_eq!(result.rows().next().unwrap().get::<i64>(0).unwrap(), 1);
+}
+
+/// Foreign keys reject the keys missing from the referenced table, and the
+/// referenced keys can't be deleted or updated unless the references are
+/// deleted by `ON DELETE CASCADE`. Keys with NULL reference nothing.
+#[test]
+fn foreign_keys() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE p (id int PRIMARY KEY); \
+         CREATE TABLE c (id int, pid int REFERENCES p (id)); \
+         CREATE TABLE d (id int, pid int REFERENCES p ON DELETE CASCADE); \
+         INSERT INTO p VALUES (1), (2), (3); \
+         INSERT INTO c VALUES (1, 1), (2, NULL); \
+         INSERT INTO d VALUES (1, 2), (2, 2)",
+        &[],
+    )
+    .unwrap();
+    let count = |conn: &mut Connection, table: &str| {
+        conn.query(&format!("SELECT count(*) AS n FROM {}", table), &[])
+            .unwrap()
+            .rows()
+            .next()
+            .unwrap()
+            .get::<i64>("n")
+            .unwrap()
+    };
+
+    let err = conn
+        .execute("INSERT INTO c VALUES (3, 4)", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("23503"));
+    assert_eq!(
+        err.detail.as_deref(),
+        Some("Key (pid)=(4) is not present in table \"p\".")
+    );
+    let err = conn
+        .execute("UPDATE c SET pid = 4 WHERE id = 1", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("23503"));
+
+    for sql in [
+        "DELETE FROM p WHERE id = 1",
+        "UPDATE p SET id = 10 WHERE id = 1",
+    ] {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some("23503"), "{}: {}", sql, err.message);
+        assert_eq!(
+            err.detail.as_deref(),
+            Some("Key (id)=(1) is still referenced from table \"c\".")
+        );
+    }
+    assert_eq!(count(&mut conn, "p"), 3);
+
+    // The unreferenced keys can be changed, and the cascaded references are
+    // deleted together
+    conn.execute("UPDATE p SET id = 4 WHERE id = 3", &[])
+        .unwrap();
+    assert_eq!(conn.execute("DELETE FROM p WHERE id = 2", &[]).unwrap(), 1);
+    assert_eq!(count(&mut conn, "d"), 0);
+
+    // Keys with NULL are never checked, nor do they keep the keys referenced
+    conn.execute(
+        "INSERT INTO c VALUES (3, NULL); UPDATE c SET pid = NULL WHERE id = 1",
+        &[],
+    )
+    .unwrap();
+    conn.execute("DELETE FROM p WHERE id = 1", &[]).unwrap();
+    assert_eq!(count(&mut conn, "c"), 3);
+    assert_eq!(count(&mut conn, "p"), 1);
+}
+
+/// `nextval` advances the sequence shared by sessions, while `currval` is the
+/// last value returned to the session. The values never go backwards after
+/// the database is reopened, but the ones logged ahead and unused are skipped.
+#[test]
+fn sequences() {
+    let dir = std::env::temp_dir().join(format!("leisql-sequences-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let value = |conn: &mut Connection, sql: &str| {
+        conn.query(sql, &[])
+            .unwrap()
+            .rows()
+            .next()
+            .unwrap()
+            .get::<i64>("v")
+            .unwrap()
+    };
+    {
+        let database = Database::open(&dir).unwrap();
+        let mut conn = database.connect();
+        conn.execute(
+            "CREATE SEQUENCE s; \
+             CREATE TABLE t (id serial, a int); \
+             INSERT INTO t (a) VALUES (10), (20)",
+            &[],
+        )
+        .unwrap();
+
+        let err = conn.query("SELECT currval('s') AS v", &[]).err().unwrap();
+        assert_eq!(err.code, Some("55000"));
+        assert_eq!(value(&mut conn, "SELECT nextval('s') AS v"), 1);
+        assert_eq!(value(&mut conn, "SELECT nextval('s') AS v"), 2);
+        assert_eq!(value(&mut conn, "SELECT currval('s') AS v"), 2);
+
+        // `currval` isn't changed by `nextval` of other sessions
+        let mut other = database.connect();
+        assert_eq!(value(&mut other, "SELECT nextval('s') AS v"), 3);
+        assert_eq!(value(&mut conn, "SELECT currval('s') AS v"), 2);
+    }
+
+    let database = Database::open(&dir).unwrap();
+    let mut conn = database.connect();
+    assert_eq!(value(&mut conn, "SELECT nextval('s') AS v"), 33);
+    conn.execute("INSERT INTO t (a) VALUES (30)", &[]).unwrap();
+    let result = conn.query("SELECT id, a FROM t ORDER BY a", &[]).unwrap();
+    let rows = result
+        .rows()
+        .map(|row| (row.get::<i64>("id").unwrap(), row.get::<i64>("a").unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![(1, 10), (2, 20), (33, 30)]);
+}
+
+/// Columns added to a table with rows are filled with their defaults, the
+/// dropped columns are removed from the rows, and the renamed columns are
+/// queried by the new names.
+#[test]
+fn alter_table_columns() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int, b text); INSERT INTO t VALUES (1, 'x'), (2, 'y')",
+        &[],
+    )
+    .unwrap();
+
+    conn.execute(
+        "ALTER TABLE t ADD COLUMN c int; \
+         ALTER TABLE t ADD COLUMN d int DEFAULT 7; \
+         INSERT INTO t (a, b, c) VALUES (3, 'z', 30)",
+        &[],
+    )
+    .unwrap();
+    let result = conn.query("SELECT a, c, d FROM t ORDER BY a", &[]).unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<i64>("a").unwrap(),
+                row.get::<Option<i64>>("c").unwrap(),
+                row.get::<i64>("d").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![(1, None, 7), (2, None, 7), (3, Some(30), 7)]);
+
+    conn.execute("ALTER TABLE t DROP COLUMN b", &[]).unwrap();
+    let err = conn.query("SELECT b FROM t", &[]).err().unwrap();
+    assert_eq!(err.code, Some("42703"));
+    let result = conn.query("SELECT * FROM t ORDER BY a", &[]).unwrap();
+    let columns = result
+        .columns
+        .iter()
+        .map(|column| column.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(columns, ["a", "c", "d"]);
+    assert_eq!(result.rows().count(), 3);
+
+    conn.execute("ALTER TABLE t RENAME COLUMN a TO id", &[])
+        .unwrap();
+    let err = conn.query("SELECT a FROM t", &[]).err().unwrap();
+    assert_eq!(err.code, Some("42703"));
+    let result = conn
+        .query("SELECT id, c FROM t WHERE id > 1 ORDER BY id", &[])
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<i64>("id").unwrap(),
+                row.get::<Option<i64>>("c").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![(2, None), (3, Some(30))]);
+}
+
+/// `ROWS` frames are bounded by the positions of rows, while `RANGE` frames
+/// are bounded by the values of the sort key, so the peers of the current
+/// row are always in the frame together.
+#[test]
+fn window_frames() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (id int, a int); \
+         INSERT INTO t VALUES (1, 1), (2, 2), (3, 2), (4, 4), (5, 5)",
+        &[],
+    )
+    .unwrap();
+    let column = |conn: &mut Connection, sql: &str, name: &str| {
+        conn.query(sql, &[])
+            .unwrap()
+            .rows()
+            .map(|row| row.get::<Option<i64>>(name).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let sql = "SELECT id, \
+               sum(a) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) AS prev, \
+               sum(a) OVER (ORDER BY id ROWS BETWEEN 1 FOLLOWING AND 2 FOLLOWING) AS next, \
+               sum(a) OVER (ORDER BY id ROWS UNBOUNDED PRECEDING) AS total \
+               FROM t ORDER BY id";
+    assert_eq!(
+        column(&mut conn, sql, "prev"),
+        [Some(1), Some(3), Some(4), Some(6), Some(9)]
+    );
+    // The frame past the last row is empty
+    assert_eq!(
+        column(&mut conn, sql, "next"),
+        [Some(4), Some(6), Some(9), Some(5), None]
+    );
+    assert_eq!(
+        column(&mut conn, sql, "total"),
+        [Some(1), Some(3), Some(5), Some(9), Some(14)]
+    );
+
+    let sql = "SELECT id, \
+               sum(a) OVER (ORDER BY a) AS peers, \
+               sum(a) OVER (ORDER BY a RANGE BETWEEN 1 PRECEDING AND 1 FOLLOWING) AS near, \
+               count(*) OVER (ORDER BY a \
+               RANGE BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING) AS rest \
+               FROM t ORDER BY id";
+    // The default frame ends with the last peer of the current row
+    assert_eq!(
+        column(&mut conn, sql, "peers"),
+        [Some(1), Some(5), Some(5), Some(9), Some(14)]
+    );
+    assert_eq!(
+        column(&mut conn, sql, "near"),
+        [Some(5), Some(5), Some(5), Some(9), Some(9)]
+    );
+    assert_eq!(
+        column(&mut conn, sql, "rest"),
+        [Some(5), Some(4), Some(4), Some(2), Some(1)]
+    );
+
+    let err = conn
+        .query(
+            "SELECT sum(a) OVER (ORDER BY a, id RANGE 1 PRECEDING) FROM t",
+            &[],
+        )
+        .err()
+        .unwrap();
+    assert_eq!(err.code, Some("42P20"));
+}
+
+/// Prepared statements are planned again once the catalog is changed by DDL,
+/// so they never run a plan of the old table definition.
+#[test]
+fn replan_prepared_statement() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (b int, a int); \
+         INSERT INTO t VALUES (10, 1), (20, 2); \
+         PREPARE p AS SELECT a FROM t ORDER BY a; \
+         PREPARE q AS SELECT * FROM t",
+        &[],
+    )
+    .unwrap();
+    let values = |conn: &mut Connection| {
+        conn.query("EXECUTE p", &[])
+            .unwrap()
+            .rows()
+            .map(|row| row.get::<i64>("a").unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(values(&mut conn), [1, 2]);
+
+    // The column is moved by dropping the one before it, which is changed
+    // by another session
+    let mut other = database.connect();
+    other
+        .execute("ALTER TABLE t DROP COLUMN b; INSERT INTO t VALUES (3)", &[])
+        .unwrap();
+    assert_eq!(values(&mut conn), [1, 2, 3]);
+
+    // The result of `*` can't be changed
+    let err = conn.query("EXECUTE q", &[]).err().unwrap();
+    assert_eq!(err.code, Some("0A000"), "{}", err.message);
+
+    other
+        .execute("ALTER TABLE t RENAME COLUMN a TO c", &[])
+        .unwrap();
+    let err = conn.query("EXECUTE p", &[]).err().unwrap();
+    assert_eq!(err.code, Some("42703"), "{}", err.message);
+
+    other.execute("DROP TABLE t", &[]).unwrap();
+    let err = conn.query("EXECUTE p", &[]).err().unwrap();
+    assert_eq!(err.code, Some("42P01"), "{}", err.message);
+}
+
+/// A portal executed with a row limit is suspended with its cursor, the
+/// next execution resumes from the rows left, even if other statements are
+/// executed meanwhile.
+#[test]
+fn suspended_portal() {
+    let database = Database::in_memory();
+    let mut session = database.session();
+    let statements = session
+        .parse("CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2), (3), (4), (5)")
+        .unwrap();
+    for statement in statements.iter() {
+        session.execute(statement).unwrap();
+    }
+    let values = |result: QueryResult| {
+        result
+            .rows()
+            .map(|row| row.get::<i64>("a").unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let statement = session
+        .parse("SELECT a FROM t WHERE a > $1 ORDER BY a")
+        .unwrap()
+        .remove(0);
+    let mut cursor = session.open_cursor(&statement, &[Datum::Int(1)]).unwrap();
+    assert_eq!(values(session.fetch(&mut cursor, 2).unwrap()), [2, 3]);
+    assert!(!cursor.is_finished());
+
+    let insert = session.parse("INSERT INTO t VALUES (0)").unwrap().remove(0);
+    session.execute(&insert).unwrap();
+    assert_eq!(values(session.fetch(&mut cursor, 1).unwrap()), [4]);
+    assert!(!cursor.is_finished());
+
+    // No limit fetches all the rows left
+    assert_eq!(values(session.fetch(&mut cursor, 0).unwrap()), [5]);
+    assert!(cursor.is_finished());
+    assert!(values(session.fetch(&mut cursor, 2).unwrap()).is_empty());
+}
+
+/// Casts of invalid values and overflowing sums fail rather than evaluate to
+/// NULL or panic.
+#[test]
+fn runtime_errors() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (s text, n bigint); \
+         INSERT INTO t VALUES ('abc', 9223372036854775807), ('1', 1)",
+        &[],
+    )
+    .unwrap();
+
+    let cases = [
+        ("SELECT s::int FROM t", "22P02"),
+        ("SELECT s::boolean FROM t", "22P02"),
+        ("SELECT s::uuid FROM t", "22P02"),
+        ("SELECT s::json FROM t", "22P02"),
+        ("SELECT sum(n) FROM t", "22003"),
+    ];
+    for (sql, code) in cases {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some(code), "{}: {}", sql, err.message);
+    }
+
+    let result = conn
+        .query("SELECT s::int AS i FROM t WHERE n = 1", &[])
+        .unwrap();
+    assert_eq!(result.rows().next().unwrap().get::<i64>("i").unwrap(), 1);
+}
+
+/// Invalid patterns of `LIKE` and regular expressions fail rather than
+/// evaluating to NULL.
+#[test]
+fn invalid_patterns() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute("CREATE TABLE t (s text); INSERT INTO t VALUES ('a(b')", &[])
+        .unwrap();
+
+    let cases = [
+        ("SELECT s ~ 'a(' FROM t", "2201B"),
+        ("SELECT s !~* '[a' FROM t", "2201B"),
+        ("SELECT s LIKE 'a\\' FROM t", "22025"),
+        ("SELECT s ILIKE 'a!' ESCAPE '!' FROM t", "22025"),
+    ];
+    for (sql, code) in cases {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some(code), "{}: {}", sql, err.message);
+    }
+
+    let result = conn
+        .query(
+            "SELECT s ~ 'a\\(' AS a, s LIKE 'a!(%' ESCAPE '!' AS b FROM t",
+            &[],
+        )
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert!(row.get::<bool>("a").unwrap());
+    assert!(row.get::<bool>("b").unwrap());
+}
+
+/// `SELECT DISTINCT` removes the duplicate rows, NULLs are equal to each
+/// other, and the sort keys must be in the select list.
+#[test]
+fn select_distinct() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int, b text); \
+         INSERT INTO t VALUES (1, 'x'), (2, 'y'), (1, 'x'), (NULL, 'x'), (NULL, 'x'), (1, 'z')",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query("SELECT DISTINCT a, b FROM t ORDER BY a, b", &[])
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<Option<i64>>("a").unwrap(),
+                row.get::<String>("b").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (Some(1), "x".to_string()),
+            (Some(1), "z".to_string()),
+            (Some(2), "y".to_string()),
+            (None, "x".to_string()),
+        ]
+    );
+
+    let result = conn
+        .query(
+            "SELECT count(*) AS n FROM (SELECT DISTINCT a FROM t) s",
+            &[],
+        )
+        .unwrap();
+    assert_eq!(result.rows().next().unwrap().get::<i64>("n").unwrap(), 3);
+
+    let err = conn
+        .execute("SELECT DISTINCT a FROM t ORDER BY b", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("42P10"));
+}
+
+/// `UPDATE` changes the matching rows with the values computed from the old
+/// row, and reports the number of them.
+#[test]
+fn update_rows() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int, b text); INSERT INTO t VALUES (1, 'x'), (2, 'y'), (3, 'z')",
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(
+        conn.execute("UPDATE t SET a = a + 10, b = 'w' WHERE a >= 2", &[])
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        conn.execute("UPDATE t SET b = 'w' WHERE a > 100", &[])
+            .unwrap(),
+        0
+    );
+    // Each row is updated with the values of the old row
+    assert_eq!(
+        conn.execute("UPDATE t SET a = a + 1, b = a::text", &[])
+            .unwrap(),
+        3
+    );
+
+    let result = conn.query("SELECT a, b FROM t ORDER BY a", &[]).unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<i64>("a").unwrap(),
+                row.get::<String>("b").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (2, "1".to_string()),
+            (13, "12".to_string()),
+            (14, "13".to_string())
+        ]
+    );
+
+    let err = conn.execute("UPDATE t SET c = 1", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42703"));
+}
+
+/// `DELETE` removes the matching rows, all of them without `WHERE`, and
+/// reports the number of them.
+#[test]
+fn delete_rows() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2), (3), (NULL)",
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(conn.execute("DELETE FROM t WHERE a > 1", &[]).unwrap(), 2);
+    assert_eq!(conn.execute("DELETE FROM t WHERE a > 1", &[]).unwrap(), 0);
+    let result = conn.query("SELECT a FROM t ORDER BY a", &[]).unwrap();
+    let rows = result
+        .rows()
+        .map(|row| row.get::<Option<i64>>("a").unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(rows, vec![Some(1), None]);
+
+    assert_eq!(conn.execute("DELETE FROM t", &[]).unwrap(), 2);
+    let result = conn.query("SELECT a FROM t", &[]).unwrap();
+    assert_eq!(result.rows().count(), 0);
+
+    let err = conn.execute("DELETE FROM missing", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42P01"));
+}
+
+/// `IN` and `EXISTS` subqueries filter the rows, negated or not, and `NOT IN`
+/// is never true if the subquery has NULLs.
+#[test]
+fn in_and_exists_subqueries() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int); INSERT INTO t VALUES (1), (2), (3); \
+         CREATE TABLE s (b int); INSERT INTO s VALUES (2), (3), (4)",
+        &[],
+    )
+    .unwrap();
+
+    let mut query = |sql: &str| {
+        let result = conn.query(sql, &[]).unwrap();
+        result
+            .rows()
+            .map(|row| row.get::<i64>(0).unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        query("SELECT a FROM t WHERE a IN (SELECT b FROM s) ORDER BY a"),
+        vec![2, 3]
+    );
+    assert_eq!(
+        query("SELECT a FROM t WHERE a NOT IN (SELECT b FROM s) ORDER BY a"),
+        vec![1]
+    );
+    assert_eq!(
+        query("SELECT a FROM t WHERE EXISTS (SELECT 1 FROM s WHERE b = a + 2) ORDER BY a"),
+        vec![1, 2]
+    );
+    assert_eq!(
+        query("SELECT a FROM t WHERE NOT EXISTS (SELECT 1 FROM s WHERE b = a) ORDER BY a"),
+        vec![1]
+    );
+    assert_eq!(
+        query("SELECT a FROM t WHERE EXISTS (SELECT 1 FROM s WHERE b > 10)"),
+        Vec::<i64>::new()
+    );
+
+    // `NOT IN` is never true if the subquery has NULL
+    conn.execute("INSERT INTO s VALUES (NULL)", &[]).unwrap();
+    let result = conn
+        .query(
+            "SELECT a FROM t WHERE a NOT IN (SELECT b FROM s) ORDER BY a",
+            &[],
+        )
+        .unwrap();
+    assert_eq!(result.rows().count(), 0);
+
+    // Subquery predicates are only supported as conjuncts of `WHERE`
+    let err = conn
+        .execute("SELECT 1 IN (SELECT b FROM s)", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("0A000"));
+}
+
+/// `LIKE` matches the whole string with `%` and `_` as wildcards, `ILIKE`
+/// ignores case, and the regex operators match anywhere in the string.
+#[test]
+fn pattern_matching() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (s text); \
+         INSERT INTO t VALUES ('apple'), ('Apricot'), ('banana'), ('a_b'), (NULL)",
+        &[],
+    )
+    .unwrap();
+
+    let mut query = |sql: &str| {
+        let result = conn.query(sql, &[]).unwrap();
+        result
+            .rows()
+            .map(|row| row.get::<String>(0).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let cases = [
+        ("s LIKE 'ap%'", vec!["apple"]),
+        ("s ILIKE 'ap%'", vec!["Apricot", "apple"]),
+        ("s NOT LIKE '%a%'", vec!["Apricot"]),
+        ("s NOT ILIKE '%A%'", vec![]),
+        ("s LIKE '_pple'", vec!["apple"]),
+        ("s LIKE 'a\\_b'", vec!["a_b"]),
+        ("s LIKE 'a#_%' ESCAPE '#'", vec!["a_b"]),
+        ("s ~ 'an+a'", vec!["banana"]),
+        ("s ~* '^a.*t$'", vec!["Apricot"]),
+        ("s !~ '^a'", vec!["Apricot", "banana"]),
+        ("s !~* '^a'", vec!["banana"]),
+    ];
+    for (condition, expected) in cases {
+        let sql = format!("SELECT s FROM t WHERE {} ORDER BY s", condition);
+        assert_eq!(query(&sql), expected, "{}", condition);
+    }
+
+    let result = conn
+        .query("SELECT NULL LIKE 'a' AS a, 'a' LIKE NULL AS b", &[])
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<Option<bool>>("a").unwrap(), None);
+    assert_eq!(row.get::<Option<bool>>("b").unwrap(), None);
+}
+
+/// `COALESCE` takes the first non-NULL argument, `NULLIF` is NULL if the
+/// arguments are equal, and `GREATEST` and `LEAST` skip NULLs.
+#[test]
+fn null_functions() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int, b int); INSERT INTO t VALUES (1, NULL), (NULL, 2), (NULL, NULL)",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT coalesce(a, b, 0) AS c, nullif(coalesce(a, b), 2) AS n, \
+             greatest(a, b, 0) AS g, least(a, b) AS l FROM t ORDER BY a, b",
+            &[],
+        )
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<i64>("c").unwrap(),
+                row.get::<Option<i64>>("n").unwrap(),
+                row.get::<i64>("g").unwrap(),
+                row.get::<Option<i64>>("l").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (1, Some(1), 1, Some(1)),
+            (2, None, 2, Some(2)),
+            (0, None, 0, None)
+        ]
+    );
+
+    // The arguments are promoted to the common type
+    let result = conn
+        .query("SELECT coalesce(NULL, 1, 2.5) AS a", &[])
+        .unwrap();
+    assert_eq!(result.rows().next().unwrap().get::<f64>("a").unwrap(), 1.0);
+    let err = conn
+        .execute("SELECT coalesce(1, gen_random_uuid())", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("42804"));
+}
+
+/// UUIDs are parsed from their text forms, compared by value, and generated
+/// randomly by `gen_random_uuid()`.
+#[test]
+fn uuid_type() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (id uuid); \
+         INSERT INTO t VALUES ('a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a12'), \
+         ('{A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11}')",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT id, id::text AS s FROM t \
+             WHERE id <= 'a0eebc999c0b4ef8bb6d6bb9bd380a12'::uuid ORDER BY id",
+            &[],
+        )
+        .unwrap();
+    let rows = result
+        .rows()
+        .map(|row| {
+            (
+                row.get::<Uuid>("id").unwrap(),
+                row.get::<String>("s").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let first = "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11";
+    let second = "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a12";
+    assert_eq!(
+        rows,
+        vec![
+            (Uuid::parse_str(first).unwrap(), first.to_string()),
+            (Uuid::parse_str(second).unwrap(), second.to_string())
+        ]
+    );
+
+    let result = conn
+        .query(
+            "SELECT gen_random_uuid() AS a, gen_random_uuid() AS b FROM t",
+            &[],
+        )
+        .unwrap();
+    for row in result.rows() {
+        let a = row.get::<Uuid>("a").unwrap();
+        let b = row.get::<Uuid>("b").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.get_version_num(), 4);
+    }
+
+    let err = conn
+        .execute("INSERT INTO t VALUES ('not-a-uuid')", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("22P02"));
+    let err = conn
+        .execute("SELECT id FROM t WHERE id = 1", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("42883"), "{}", err.message);
+}
+
+/// JSON values are accessed by `->` and `->>` with keys or indexes, and by
+/// `#>` and `#>>` with paths, missing ones are NULL.
+#[test]
+fn json_operators() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (j json); \
+         INSERT INTO t VALUES ('{\"a\": {\"b\": [1, \"x\", null]}, \"c\": \"text\"}')",
+        &[],
+    )
+    .unwrap();
+
+    let result = conn
+        .query(
+            "SELECT (j -> 'a' -> 'b')::text AS a, j -> 'a' -> 'b' ->> 1 AS b, \
+             j ->> 'c' AS c, (j -> 'c')::text AS d, j #>> '{a,b,0}' AS e, \
+             (j #> '{a}')::text AS f, j -> 'missing' AS g, j -> 'a' -> 'b' -> 5 AS h, \
+             j -> 'a' -> 'b' ->> 2 AS i FROM t",
+            &[],
+        )
+        .unwrap();
+    let row = result.rows().next().unwrap();
+    assert_eq!(row.get::<String>("a").unwrap(), "[1, \"x\", null]");
+    assert_eq!(row.get::<String>("b").unwrap(), "x");
+    assert_eq!(row.get::<String>("c").unwrap(), "text");
+    assert_eq!(row.get::<String>("d").unwrap(), "\"text\"");
+    assert_eq!(row.get::<String>("e").unwrap(), "1");
+    assert_eq!(row.get::<String>("f").unwrap(), "{\"b\": [1, \"x\", null]}");
+    assert_eq!(row.get::<Option<String>>("g").unwrap(), None);
+    assert_eq!(row.get::<Option<String>>("h").unwrap(), None);
+    assert_eq!(row.get::<Option<String>>("i").unwrap(), None);
+
+    let err = conn
+        .execute("INSERT INTO t VALUES ('{\"a\": }')", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("22P02"));
+}
+
+/// `SET` changes the parameters of the session, which are read by `SHOW` and
+/// `SHOW ALL`, and `SET ... TO DEFAULT` restores the defaults.
+#[test]
+fn set_and_show() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    let show = |conn: &mut Connection, sql: &str| {
+        let result = conn.query(sql, &[]).unwrap();
+        let value = result.rows().next().unwrap().get::<String>(0).unwrap();
+        value
+    };
+
+    assert_eq!(show(&mut conn, "SHOW DateStyle"), "ISO, MDY");
+    conn.execute("SET application_name = 'psql'", &[]).unwrap();
+    conn.execute("SET DATESTYLE TO 'ISO, DMY'", &[]).unwrap();
+    conn.execute("SET myapp.option = 42", &[]).unwrap();
+    assert_eq!(show(&mut conn, "SHOW application_name"), "psql");
+    assert_eq!(show(&mut conn, "SHOW datestyle"), "ISO, DMY");
+    assert_eq!(show(&mut conn, "SHOW myapp.option"), "42");
+
+    let result = conn.query("SHOW ALL", &[]).unwrap();
+    let all = result
+        .rows()
+        .map(|row| (row.get::<String>(0).unwrap(), row.get::<String>(1).unwrap()))
+        .collect::<Vec<_>>();
+    assert!(all.contains(&("application_name".to_string(), "psql".to_string())));
+    assert!(all.contains(&("myapp.option".to_string(), "42".to_string())));
+    assert!(all.contains(&("server_version".to_string(), "14.0".to_string())));
+
+    conn.execute("SET application_name TO DEFAULT", &[])
+        .unwrap();
+    assert_eq!(show(&mut conn, "SHOW application_name"), "");
+    conn.execute("SET TIME ZONE 'Asia/Shanghai'", &[]).unwrap();
+    assert_eq!(show(&mut conn, "SHOW TIME ZONE"), "Asia/Shanghai");
+
+    // Each connection has its own parameters
+    let mut other = database.connect();
+    assert_eq!(show(&mut other, "SHOW datestyle"), "ISO, MDY");
+
+    let cases = [
+        ("SET no_such_parameter = 1", "42704"),
+        ("SHOW no_such_parameter", "42704"),
+        ("SET server_version = '15'", "55P02"),
+        ("SET log_min_duration_statement = '1h'", "22023"),
+    ];
+    for (sql, code) in cases {
+        let err = conn.execute(sql, &[]).unwrap_err();
+        assert_eq!(err.code, Some(code), "{}: {}", sql, err.message);
+    }
+}
+
+/// `ANALYZE` collects the statistics of columns shown in `pg_stats`, the
+/// tables never analyzed have no statistics.
+#[test]
+fn analyze_statistics() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a int, b text); \
+         INSERT INTO t VALUES (1, 'x'), (2, NULL), (2, 'y'), (3, NULL)",
+        &[],
+    )
+    .unwrap();
+
+    let stats = |conn: &mut Connection| {
+        let result = conn
+            .query(
+                "SELECT attname, null_frac, n_distinct, min_value, max_value \
+                 FROM pg_stats WHERE tablename = 't' ORDER BY attname",
+                &[],
+            )
+            .unwrap();
+        result
+            .rows()
+            .map(|row| {
+                (
+                    row.get::<String>("attname").unwrap(),
+                    row.get::<f64>("null_frac").unwrap(),
+                    row.get::<f64>("n_distinct").unwrap(),
+                    row.get::<Option<String>>("min_value").unwrap(),
+                    row.get::<Option<String>>("max_value").unwrap(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(stats(&mut conn), vec![]);
+
+    conn.execute("ANALYZE t", &[]).unwrap();
+    let expected = vec![
+        (
+            "a".to_string(),
+            0.0,
+            3.0,
+            Some("1".to_string()),
+            Some("3".to_string()),
+        ),
+        (
+            "b".to_string(),
+            0.5,
+            2.0,
+            Some("x".to_string()),
+            Some("y".to_string()),
+        ),
+    ];
+    assert_eq!(stats(&mut conn), expected);
+
+    // The statistics of analyzed tables account for the changes of DML
+    conn.execute("INSERT INTO t VALUES (10, 'z')", &[]).unwrap();
+    let updated = stats(&mut conn);
+    assert_eq!(updated[0].4.as_deref(), Some("10"));
+    assert_eq!(updated[1].4.as_deref(), Some("z"));
+
+    // All the tables are analyzed without a name
+    conn.execute("CREATE TABLE s (c int); INSERT INTO s VALUES (1)", &[])
+        .unwrap();
+    conn.execute("ANALYZE", &[]).unwrap();
+    let result = conn
+        .query("SELECT attname FROM pg_stats WHERE tablename = 's'", &[])
+        .unwrap();
+    assert_eq!(result.rows().count(), 1);
+
+    let err = conn.execute("ANALYZE missing", &[]).unwrap_err();
+    assert_eq!(err.code, Some("42P01"));
+}
+
+/// Inner joins on equality are executed as hash joins, where NULL keys never
+/// match and the duplicate keys on both sides produce all the pairs.
+#[test]
+fn hash_join() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE l (a int, b int); \
+         INSERT INTO l VALUES (1, 1), (1, 2), (2, 1), (NULL, 1), (3, 3); \
+         CREATE TABLE r (c int, d int); \
+         INSERT INTO r VALUES (1, 1), (1, 1), (2, 2), (NULL, 1), (4, 4)",
+        &[],
+    )
+    .unwrap();
+
+    let mut query = |sql: &str| {
+        let result = conn.query(sql, &[]).unwrap();
+        result
+            .rows()
+            .map(|row| {
+                row.values()
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+    };
+    let sql = "SELECT a, b, d FROM l JOIN r ON a = c ORDER BY a, b, d";
+    let plan = query(&format!("EXPLAIN {}", sql)).join("\n");
+    assert!(plan.contains("HashJoin"), "{}", plan);
+    assert_eq!(query(sql), ["1,1,1", "1,1,1", "1,2,1", "1,2,1", "2,1,2"]);
+
+    // Multiple keys, and the other conditions are checked on the joined rows
+    assert_eq!(
+        query("SELECT a, b FROM l JOIN r ON a = c AND b = d ORDER BY a, b"),
+        ["1,1", "1,1"]
+    );
+    assert_eq!(
+        query("SELECT a, b, d FROM l, r WHERE a = c AND b > d ORDER BY a, b"),
+        ["1,2,1", "1,2,1"]
+    );
+    assert_eq!(
+        query("SELECT count(*) FROM l JOIN r ON a = c WHERE c > 10"),
+        ["0"]
+    );
+}
+
+/// `GROUPING SETS`, `ROLLUP` and `CUBE` aggregate the rows by each of the
+/// grouping sets, the keys not in the set are NULL, and `grouping()` tells
+/// them apart from the NULL values of keys.
+#[test]
+fn grouping_sets() {
+    let database = Database::in_memory();
+    let mut conn = database.connect();
+    conn.execute(
+        "CREATE TABLE t (a text, b int, n int); \
+         INSERT INTO t VALUES ('x', 1, 1), ('x', 2, 2), ('y', 1, 3), (NULL, 1, 4)",
+        &[],
+    )
+    .unwrap();
+
+    let mut query = |sql: &str| {
+        let result = conn.query(sql, &[]).unwrap();
+        result
+            .rows()
+            .map(|row| {
+                row.values()
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        query(
+            "SELECT a, b, sum(n), grouping(a, b) FROM t \
+             GROUP BY GROUPING SETS ((a), (b), ()) ORDER BY grouping(a, b), a, b"
+        ),
+        [
+            "x,NULL,3,1",
+            "y,NULL,3,1",
+            "NULL,NULL,4,1",
+            "NULL,1,8,2",
+            "NULL,2,2,2",
+            "NULL,NULL,10,3"
+        ]
+    );
+    assert_eq!(
+        query(
+            "SELECT a, b, sum(n) FROM t WHERE a IS NOT NULL GROUP BY ROLLUP (a, b) ORDER BY a, b"
+        ),
+        [
+            "x,1,1",
+            "x,2,2",
+            "x,NULL,3",
+            "y,1,3",
+            "y,NULL,3",
+            "NULL,NULL,6"
+        ]
+    );
+    assert_eq!(
+        query("SELECT count(*) FROM (SELECT a, b FROM t GROUP BY CUBE (a, b)) s"),
+        ["10"]
+    );
+
+    let err = conn
+        .execute("SELECT grouping(n) FROM t GROUP BY ROLLUP (a)", &[])
+        .unwrap_err();
+    assert_eq!(err.code, Some("42803"));
+}
@@ -0,0 +1,159 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use leisql::{
+    catalog::user::UserCatalog,
+    server::{
+        auth::{AuthMethod, MakeAuthenticator},
+        tls::load_tls_acceptor,
+        MakePostgresHandler,
+    },
+    Database,
+};
+use pgwire::{api::MakeHandler, tokio::process_socket};
+use postgres_protocol::message::{backend::Message, frontend};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::{
+    rustls::{Certificate, ClientConfig, RootCertStore, ServerName},
+    TlsAcceptor, TlsConnector,
+};
+
+/// Certificate of `localhost` signed by the CA, and its private key.
+const CA_CERT: &str = "tests/tls/ca.pem";
+const SERVER_CERT: &str = "tests/tls/server.pem";
+const SERVER_KEY: &str = "tests/tls/server.key";
+
+/// Start a server without authentication, with TLS enabled if the acceptor
+/// is provided.
+async fn start_server(tls_acceptor: Option<TlsAcceptor>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let users = UserCatalog::parse("alice:secret").unwrap();
+    let authenticator = MakeAuthenticator::new(AuthMethod::Trust, users);
+    let processor = MakePostgresHandler::new(Database::in_memory(), authenticator);
+    let tls_acceptor = tls_acceptor.map(Arc::new);
+    tokio::spawn(async move {
+        loop {
+            let (socket, _) = listener.accept().await.unwrap();
+            let processor = processor.make();
+            tokio::spawn(process_socket(
+                socket,
+                tls_acceptor.clone(),
+                processor.clone(),
+                processor.clone(),
+                processor,
+            ));
+        }
+    });
+
+    addr
+}
+
+/// Send `SSLRequest`, and return the single byte answer of the server.
+async fn request_ssl(stream: &mut TcpStream) -> u8 {
+    let mut buf = BytesMut::new();
+    frontend::ssl_request(&mut buf);
+    stream.write_all(&buf).await.unwrap();
+    stream.read_u8().await.unwrap()
+}
+
+/// Log in as `alice` and run the query, returns the first column of the
+/// data rows.
+async fn query<S>(stream: &mut S, sql: &str) -> Vec<String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = BytesMut::new();
+    frontend::startup_message([("user", "alice")], &mut buf).unwrap();
+    frontend::query(sql, &mut buf).unwrap();
+    stream.write_all(&buf).await.unwrap();
+    buf.clear();
+
+    // The first `ReadyForQuery` follows login, and the second one the query
+    let mut ready = 0;
+    let mut rows = vec![];
+    while ready < 2 {
+        let message = loop {
+            if let Some(message) = Message::parse(&mut buf).unwrap() {
+                break message;
+            }
+            assert!(
+                stream.read_buf(&mut buf).await.unwrap() > 0,
+                "connection closed"
+            );
+        };
+        match message {
+            Message::ReadyForQuery(_) => ready += 1,
+            Message::DataRow(body) => {
+                let range = body.ranges().next().unwrap().unwrap().unwrap();
+                rows.push(String::from_utf8_lossy(&body.buffer()[range]).to_string());
+            }
+            Message::ErrorResponse(_) => panic!("query failed: {}", sql),
+            _ => {}
+        }
+    }
+    rows
+}
+
+/// The connection is encrypted after `SSLRequest` is accepted, and the client
+/// verifies the certificate of the server.
+#[tokio::test(flavor = "multi_thread")]
+async fn encrypted_connection() {
+    let acceptor = load_tls_acceptor(SERVER_CERT, SERVER_KEY).unwrap();
+    let addr = start_server(Some(acceptor)).await;
+
+    let mut roots = RootCertStore::empty();
+    let ca = std::fs::read(CA_CERT).unwrap();
+    for cert in rustls_pemfile::certs(&mut ca.as_slice()).unwrap() {
+        roots.add(&Certificate(cert)).unwrap();
+    }
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connector = TlsConnector::from(Arc::new(config));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(request_ssl(&mut stream).await, b'S');
+    let mut stream = connector
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+        .unwrap();
+    assert_eq!(query(&mut stream, "SELECT 1 + 1").await, ["2"]);
+
+    // The clients not requesting TLS are still served
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(query(&mut stream, "SELECT 1").await, ["1"]);
+}
+
+/// `SSLRequest` is declined if TLS is not enabled, and the client continues
+/// in plain text.
+#[tokio::test(flavor = "multi_thread")]
+async fn tls_not_enabled() {
+    let addr = start_server(None).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(request_ssl(&mut stream).await, b'N');
+    assert_eq!(query(&mut stream, "SELECT 1").await, ["1"]);
+}
+
+/// Files without certificate or private key are rejected on loading.
+#[test]
+fn invalid_tls_files() {
+    let err = load_tls_acceptor(SERVER_KEY, SERVER_KEY).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        format!("no certificate found in {}", SERVER_KEY)
+    );
+    let err = load_tls_acceptor(SERVER_CERT, SERVER_CERT).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        format!("no private key found in {}", SERVER_CERT)
+    );
+    assert!(load_tls_acceptor("tests/tls/missing.pem", SERVER_KEY).is_err());
+}